@@ -0,0 +1,157 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use ngit::{
+    client::{STATE_KIND, get_global_cache_path, get_local_cache_path},
+    git::{Repo, RepoActions},
+    git_events::status_kinds,
+    login::{SignerInfo, SignerInfoSource, existing::get_signer_info},
+};
+use nostr_sdk::Kind;
+
+const NIPS: [&str; 8] = ["01", "05", "19", "34", "42", "46", "49", "65"];
+
+/// print details useful for bug reports. must not access the network
+pub fn print_doctor() -> Result<()> {
+    const VERSION: &str = env!("CARGO_PKG_VERSION");
+    println!("git-remote-nostr: v{VERSION}");
+    println!(
+        "nips: {}",
+        NIPS.iter()
+            .map(|n| format!("NIP-{n}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!(
+        "kinds: {}",
+        [Kind::GitRepoAnnouncement, STATE_KIND, Kind::GitPatch]
+            .into_iter()
+            .chain(status_kinds())
+            .map(|k| k.as_u16().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let git_repo = match env::var("GIT_DIR") {
+        Ok(git_dir) => {
+            let git_repo = Repo::from_path(&PathBuf::from(&git_dir));
+            println!(
+                "git repository: {git_dir}{}",
+                if git_repo.is_ok() { "" } else { " (not found)" }
+            );
+            git_repo.ok()
+        }
+        // eg. run by hand rather than by git
+        Err(_) => match Repo::discover() {
+            Ok(git_repo) => {
+                println!(
+                    "git repository: {} (found from working directory)",
+                    git_repo.git_repo.path().display()
+                );
+                Some(git_repo)
+            }
+            Err(_) => {
+                println!(
+                    "git repository: none (GIT_DIR not set and none found from working directory)"
+                );
+                None
+            }
+        },
+    };
+
+    println!("login: {}", describe_login(&git_repo.as_ref()));
+
+    if let Some(git_repo_path) = git_repo.as_ref().and_then(|r| r.get_path().ok()) {
+        println!(
+            "local cache: {}",
+            describe_cache(&get_local_cache_path(git_repo_path))
+        );
+    }
+    match get_global_cache_path(git_repo.as_ref().and_then(|r| r.get_path().ok())) {
+        Ok(path) => println!("global cache: {}", describe_cache(&path)),
+        Err(_) => println!("global cache: unknown"),
+    }
+
+    let exe_name = format!("git-remote-nostr{}", env::consts::EXE_SUFFIX);
+    match find_on_path(&exe_name) {
+        Some(path) => println!("{exe_name} on PATH: {}", path.display()),
+        None => {
+            println!("{exe_name} on PATH: not found. git will not be able to use nostr:// remotes")
+        }
+    }
+    if let Ok(exe) = env::current_exe() {
+        println!("running binary: {}", exe.display());
+    }
+    Ok(())
+}
+
+fn describe_login(git_repo: &Option<&Repo>) -> String {
+    let Ok((signer_info, source)) = get_signer_info(git_repo, &None, &None, &None) else {
+        return "none".to_string();
+    };
+    let (method, npub) = match signer_info {
+        SignerInfo::Nsec { nsec, npub, .. } => (
+            if nsec.contains("ncryptsec") {
+                "ncryptsec"
+            } else {
+                "nsec"
+            },
+            npub,
+        ),
+        SignerInfo::Bunker { npub, .. } => ("nostr connect", npub),
+    };
+    format!(
+        "{method} via {}{}",
+        match source {
+            SignerInfoSource::GitLocal => "local git config",
+            SignerInfoSource::GitGlobal => "global git config",
+            SignerInfoSource::CommandLineArguments => "command line arguments",
+        },
+        npub.map(|npub| format!(" as {npub}")).unwrap_or_default(),
+    )
+}
+
+fn describe_cache(path: &Path) -> String {
+    if path.exists() {
+        format!(
+            "{} ({})",
+            path.display(),
+            dir_size(path)
+                .map(format_size)
+                .unwrap_or("unknown size".to_string())
+        )
+    } else {
+        format!("{} (not created yet)", path.display())
+    }
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path).context("failed to read cache directory")? {
+        let metadata = entry?.metadata()?;
+        size += if metadata.is_dir() { 0 } else { metadata.len() };
+    }
+    Ok(size)
+}
+
+fn format_size(bytes: u64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let mb = bytes as f64 / 1_048_576.0;
+    if mb >= 1.0 {
+        format!("{mb:.1} MB")
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+fn find_on_path(exe_name: &str) -> Option<PathBuf> {
+    let git_exec_path = env::var_os("GIT_EXEC_PATH").map(PathBuf::from);
+    git_exec_path
+        .into_iter()
+        .chain(env::var_os("PATH").iter().flat_map(env::split_paths))
+        .map(|dir| dir.join(exe_name))
+        .find(|path| path.is_file())
+}
@@ -1,16 +1,18 @@
 use core::str;
 use std::{
     collections::HashMap,
-    io::Stdin,
+    io::{Read, Stdin, Write},
+    process::{Command, Stdio},
     sync::{Arc, Mutex},
     time::Instant,
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use auth_git2::GitAuthenticator;
-use git2::{Progress, Repository};
+use git2::{Oid, Progress, Repository};
 use ngit::{
     cli_interactor::count_lines_per_msg_vec,
+    client::get_state_from_cache,
     git::{
         Repo, RepoActions,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
@@ -18,42 +20,108 @@ use ngit::{
     },
     git_events::tag_value,
     login::get_curent_user,
+    proxy::{ProxyUse, ensure_onion_url_has_proxy, get_proxy, git_proxy_options},
     repo_ref::RepoRef,
 };
 use nostr::nips::nip19;
 use nostr_sdk::{Event, ToBech32};
 
 use crate::utils::{
-    Direction, fetch_or_list_error_is_not_authentication_failure,
+    Direction, SHELL_GIT_REMOTE, fetch_or_list_error_is_not_authentication_failure,
     find_proposal_and_patches_by_branch_name, get_oids_from_fetch_batch,
-    get_open_or_draft_proposals, get_read_protocols_to_try, join_with_and, set_protocol_preference,
+    get_open_or_draft_proposals, get_read_protocols_to_try, join_with_and,
+    proposal_branch_name_from_refstr, report_shell_git_fallback, set_protocol_preference,
+    shell_git_command, should_fallback_to_shell_git, warn_about_rewound_branches,
 };
 
+/// partial clone options git sets with `option` before `fetch`, eg. for `git
+/// clone --filter=blob:none` and when it fetches missing objects later
+#[derive(Debug, Default, Clone)]
+pub struct PartialCloneOptions {
+    pub filter: Option<String>,
+    pub from_promisor: bool,
+}
+
+impl PartialCloneOptions {
+    fn is_partial(&self) -> bool {
+        self.filter.is_some() || self.from_promisor
+    }
+}
+
 pub async fn run_fetch(
     git_repo: &Repo,
     repo_ref: &RepoRef,
+    remote: Option<&str>,
     stdin: &Stdin,
     oid: &str,
     refstr: &str,
+    partial_clone: &PartialCloneOptions,
 ) -> Result<()> {
+    // git sends the whole batch before expecting a response so collect it all
+    // and fetch from each git server in a single negotiation
     let mut fetch_batch = get_oids_from_fetch_batch(stdin, oid, refstr)?;
 
+    if let Some(head_oid) = fetch_batch.remove("HEAD") {
+        let (branch, branch_oid) = resolve_head(git_repo, repo_ref, &head_oid).await;
+        if branch_oid != head_oid {
+            // fetch what git asked for as well as the tip in the state event
+            fetch_batch.insert("HEAD".to_string(), head_oid);
+        }
+        fetch_batch.entry(branch).or_insert(branch_oid);
+    }
+
     let oids_from_git_servers = fetch_batch
         .iter()
-        .filter(|(refstr, _)| !refstr.contains("refs/heads/pr/"))
+        .filter(|(refstr, _)| proposal_branch_name_from_refstr(refstr).is_none())
         .map(|(_, oid)| oid.clone())
         .collect::<Vec<String>>();
 
+    let branch_tips = fetch_batch.clone();
+    fetch_batch.retain(|refstr, _| proposal_branch_name_from_refstr(refstr).is_some());
+
+    let open_and_draft_proposals = if fetch_batch.is_empty() {
+        HashMap::new()
+    } else {
+        get_open_or_draft_proposals(git_repo, repo_ref).await?
+    };
+    let current_user = get_curent_user(git_repo)?;
+
+    let proposal_patches = fetch_batch
+        .keys()
+        .filter_map(|refstr| {
+            find_proposal_and_patches_by_branch_name(
+                &proposal_branch_name_from_refstr(refstr)?,
+                &open_and_draft_proposals,
+                current_user.as_ref(),
+            )
+            .map(|(_, (_, patches))| (refstr.clone(), patches))
+        })
+        .collect::<HashMap<String, &Vec<Event>>>();
+
+    // proposal commits are built from patches but their parent commits may
+    // need fetching from a git server
+    let mut oids_to_fetch = oids_from_git_servers.clone();
+    for patches in proposal_patches.values() {
+        if let Some(parent_commit) = patches
+            .last()
+            .and_then(|patch| tag_value(patch, "parent-commit").ok())
+        {
+            oids_to_fetch.push(parent_commit);
+        }
+    }
+    oids_to_fetch.sort();
+    oids_to_fetch.dedup();
+
     let mut errors = vec![];
     let term = console::Term::stderr();
 
     for git_server_url in &repo_ref.git_server {
-        let term = console::Term::stderr();
         if let Err(error) = fetch_from_git_server(
             git_repo,
-            &oids_from_git_servers,
+            &oids_to_fetch,
             git_server_url,
             &repo_ref.to_nostr_git_url(&None),
+            partial_clone,
             &term,
         ) {
             errors.push(error);
@@ -62,9 +130,10 @@ pub async fn run_fetch(
         }
     }
 
+    // git asks for missing blobs and trees by oid in partial clones
     if oids_from_git_servers
         .iter()
-        .any(|oid| !git_repo.does_commit_exist(oid).unwrap())
+        .any(|oid| !object_exists(git_repo, oid))
         && !errors.is_empty()
     {
         bail!(
@@ -77,14 +146,37 @@ pub async fn run_fetch(
         );
     }
 
-    fetch_batch.retain(|refstr, _| refstr.contains("refs/heads/pr/"));
+    warn_about_rewound_branches(&term, git_repo, remote, &branch_tips)?;
 
-    fetch_open_or_draft_proposals(git_repo, &term, repo_ref, &fetch_batch).await?;
+    for (refstr, patches) in proposal_patches {
+        if let Err(error) = make_commits_for_proposal(git_repo, repo_ref, patches) {
+            term.write_line(
+                format!("WARNING: failed to create branch for {refstr}, error: {error}",).as_str(),
+            )?;
+        }
+    }
     term.flush()?;
     println!();
     Ok(())
 }
 
+/// the branch HEAD points to in the nostr state event and its tip. falls back
+/// to HEAD and `head_oid` when there is no state event or it has no HEAD
+async fn resolve_head(git_repo: &Repo, repo_ref: &RepoRef, head_oid: &str) -> (String, String) {
+    if let Ok(nostr_state) = get_state_from_cache(git_repo.get_path().ok(), repo_ref).await {
+        if let Some(branch) = nostr_state
+            .state
+            .get("HEAD")
+            .and_then(|value| value.strip_prefix("ref: "))
+        {
+            if let Some(branch_oid) = nostr_state.state.get(branch) {
+                return (branch.to_string(), branch_oid.clone());
+            }
+        }
+    }
+    ("HEAD".to_string(), head_oid.to_string())
+}
+
 pub fn make_commits_for_proposal(
     git_repo: &Repo,
     repo_ref: &RepoRef,
@@ -128,34 +220,8 @@ pub fn make_commits_for_proposal(
     Ok(tip_commit_id)
 }
 
-async fn fetch_open_or_draft_proposals(
-    git_repo: &Repo,
-    term: &console::Term,
-    repo_ref: &RepoRef,
-    proposal_refs: &HashMap<String, String>,
-) -> Result<()> {
-    if !proposal_refs.is_empty() {
-        let open_and_draft_proposals = get_open_or_draft_proposals(git_repo, repo_ref).await?;
-
-        let current_user = get_curent_user(git_repo)?;
-
-        for refstr in proposal_refs.keys() {
-            if let Some((_, (_, patches))) = find_proposal_and_patches_by_branch_name(
-                refstr,
-                &open_and_draft_proposals,
-                current_user.as_ref(),
-            ) {
-                if let Err(error) = make_commits_for_proposal(git_repo, repo_ref, patches) {
-                    term.write_line(
-                        format!("WARNING: failed to create branch for {refstr}, error: {error}",)
-                            .as_str(),
-                    )?;
-                    break;
-                }
-            }
-        }
-    }
-    Ok(())
+fn object_exists(git_repo: &Repo, oid: &str) -> bool {
+    Oid::from_str(oid).is_ok_and(|oid| git_repo.git_repo.odb().is_ok_and(|odb| odb.exists(oid)))
 }
 
 pub fn fetch_from_git_server(
@@ -163,12 +229,16 @@ pub fn fetch_from_git_server(
     oids: &[String],
     git_server_url: &str,
     decoded_nostr_url: &NostrUrlDecoded,
+    partial_clone: &PartialCloneOptions,
     term: &console::Term,
 ) -> Result<()> {
-    let already_have_oids = oids
+    // only negotiate for objects we don't already have
+    let oids = oids
         .iter()
-        .all(|oid| git_repo.does_commit_exist(oid).is_ok_and(|outcome| outcome));
-    if already_have_oids {
+        .filter(|oid| !object_exists(git_repo, oid))
+        .cloned()
+        .collect::<Vec<String>>();
+    if oids.is_empty() {
         return Ok(());
     }
 
@@ -176,6 +246,8 @@ pub fn fetch_from_git_server(
 
     let protocols_to_attempt = get_read_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let proxy = get_proxy(&Some(git_repo), ProxyUse::GitServers)?.map(|proxy| proxy.value);
+
     let mut failed_protocols = vec![];
     let mut success = false;
     for protocol in &protocols_to_attempt {
@@ -184,13 +256,42 @@ pub fn fetch_from_git_server(
         )?;
 
         let formatted_url = server_url.format_as(protocol, &decoded_nostr_url.user)?;
-        let res = fetch_from_git_server_url(
-            &git_repo.git_repo,
-            oids,
-            &formatted_url,
-            [ServerProtocol::UnauthHttps, ServerProtocol::UnauthHttp].contains(protocol),
-            term,
-        );
+        let dont_authenticate =
+            [ServerProtocol::UnauthHttps, ServerProtocol::UnauthHttp].contains(protocol);
+        let res = if partial_clone.is_partial() {
+            fetch_from_git_server_url_with_git_fetch_pack(
+                git_repo,
+                &oids,
+                &formatted_url,
+                dont_authenticate,
+                &proxy,
+                partial_clone,
+                term,
+            )
+        } else {
+            fetch_from_git_server_url(
+                &git_repo.git_repo,
+                &oids,
+                &formatted_url,
+                dont_authenticate,
+                &proxy,
+                term,
+            )
+            .or_else(|error| {
+                if should_fallback_to_shell_git(git_repo, &formatted_url, &error) {
+                    report_shell_git_fallback(&Direction::Fetch, &formatted_url, &error, term);
+                    fetch_from_git_server_url_with_shell_git(
+                        git_repo,
+                        &oids,
+                        &formatted_url,
+                        dont_authenticate,
+                        &proxy,
+                    )
+                } else {
+                    Err(error)
+                }
+            })
+        };
         if let Err(error) = res {
             term.write_line(
                 format!("fetch: {formatted_url} failed over {protocol}: {error}").as_str(),
@@ -401,8 +502,10 @@ fn fetch_from_git_server_url(
     oids: &[String],
     git_server_url: &str,
     dont_authenticate: bool,
+    proxy: &Option<String>,
     term: &console::Term,
 ) -> Result<()> {
+    ensure_onion_url_has_proxy(git_server_url, proxy, ProxyUse::GitServers)?;
     if git_server_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh && !check_ssh_keys() {
         bail!("no ssh keys found");
     }
@@ -433,12 +536,113 @@ fn fetch_from_git_server_url(
         remote_callbacks.credentials(auth.credentials(&git_config));
     }
     fetch_options.remote_callbacks(remote_callbacks);
+    fetch_options.proxy_options(git_proxy_options(proxy));
     git_server_remote.download(oids, Some(&mut fetch_options))?;
 
     git_server_remote.disconnect()?;
     Ok(())
 }
 
+/// used when libgit2 couldn't reach an http(s) git server
+fn fetch_from_git_server_url_with_shell_git(
+    git_repo: &Repo,
+    oids: &[String],
+    git_server_url: &str,
+    dont_authenticate: bool,
+    proxy: &Option<String>,
+) -> Result<()> {
+    // stdout is the git remote helper protocol
+    let status = shell_git_command(git_repo, git_server_url, dont_authenticate, proxy)
+        .args([
+            "fetch",
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--no-recurse-submodules",
+            SHELL_GIT_REMOTE,
+        ])
+        .args(oids)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .context("failed to run git fetch")?;
+    if !status.success() {
+        bail!("git fetch failed");
+    }
+    Ok(())
+}
+
+/// libgit2 doesn't support partial clone so `git fetch-pack` is used. it
+/// marks the pack as from the promisor remote so git fetches objects left out
+/// by the filter when they are needed. servers that don't support filters send
+/// everything
+fn fetch_from_git_server_url_with_git_fetch_pack(
+    git_repo: &Repo,
+    oids: &[String],
+    git_server_url: &str,
+    dont_authenticate: bool,
+    proxy: &Option<String>,
+    partial_clone: &PartialCloneOptions,
+    term: &console::Term,
+) -> Result<()> {
+    ensure_onion_url_has_proxy(git_server_url, proxy, ProxyUse::GitServers)?;
+    if git_server_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh && !check_ssh_keys() {
+        bail!("no ssh keys found");
+    }
+    let mut command = Command::new("git");
+    if let Some(proxy) = proxy {
+        command.arg("-c").arg(format!("http.proxy={proxy}"));
+    }
+    if dont_authenticate {
+        command
+            .args(["-c", "credential.helper="])
+            .env("GIT_TERMINAL_PROMPT", "0");
+    }
+    command
+        .arg("--git-dir")
+        .arg(git_repo.git_repo.path())
+        .args(["fetch-pack", "--from-promisor"]);
+    if let Some(filter) = &partial_clone.filter {
+        command.arg(format!("--filter={filter}"));
+    }
+    // stdout lists the pack and oids fetched, which would corrupt the git
+    // protocol on our stdout
+    let mut child = command
+        .arg(git_server_url)
+        .args(oids)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run git fetch-pack")?;
+    let mut stderr = vec![];
+    if let Some(mut child_stderr) = child.stderr.take() {
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = child_stderr.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            // pass progress through as it arrives
+            let _ = std::io::stderr().write_all(&buf[..n]);
+            stderr.extend_from_slice(&buf[..n]);
+        }
+    }
+    if !child.wait()?.success() {
+        bail!("git fetch-pack failed");
+    }
+    if let Some(filter) = &partial_clone.filter {
+        if String::from_utf8_lossy(&stderr).contains("filtering not recognized by server") {
+            term.write_line(
+                format!(
+                    "WARNING: {git_server_url} doesn't support partial clone so '{filter}' was ignored and all objects were fetched"
+                )
+                .as_str(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
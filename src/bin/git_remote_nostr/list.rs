@@ -1,37 +1,47 @@
 use core::str;
 use std::collections::HashMap;
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use auth_git2::GitAuthenticator;
 use client::get_state_from_cache;
 use git::RepoActions;
 use ngit::{
     client,
+    config::PrsAsRefs,
     git::{
         self,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
+        proposal_notes::{ProposalNote, update_proposal_notes},
     },
-    git_events::event_to_cover_letter,
+    git_events::{event_to_cover_letter, tag_value},
     login::get_curent_user,
+    proxy::{ProxyUse, ensure_onion_url_has_proxy, get_proxy, git_proxy_options},
     repo_ref,
+    repo_state::{
+        get_state_ref_ignore_patterns, is_state_ref_ignored, point_head_at_existing_branch,
+    },
+    timeout,
 };
-use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use nostr_sdk::{Kind, ToBech32, hashes::sha1::Hash as Sha1Hash, nips::nip01::Coordinate};
 use repo_ref::RepoRef;
 
 use crate::{
-    fetch::{fetch_from_git_server, make_commits_for_proposal},
+    fetch::{PartialCloneOptions, fetch_from_git_server, make_commits_for_proposal},
     git::Repo,
     utils::{
-        Direction, fetch_or_list_error_is_not_authentication_failure, get_open_or_draft_proposals,
-        get_read_protocols_to_try, get_short_git_server_name, join_with_and,
-        set_protocol_preference,
+        Direction, fetch_or_list_error_is_not_authentication_failure,
+        get_open_or_draft_proposals_with_status, get_read_protocols_to_try,
+        get_short_git_server_name, join_with_and, proposal_refstr, set_protocol_preference,
+        warn_about_rewound_branches,
     },
 };
 
 pub async fn run_list(
     git_repo: &Repo,
     repo_ref: &RepoRef,
+    remote: Option<&str>,
     for_push: bool,
+    prs_as_refs: PrsAsRefs,
 ) -> Result<HashMap<String, HashMap<String, String>>> {
     let nostr_state =
         if let Ok(nostr_state) = get_state_from_cache(Some(git_repo.get_path()?), repo_ref).await {
@@ -50,11 +60,24 @@ pub async fn run_list(
     );
 
     let mut state = if let Some(nostr_state) = nostr_state {
+        let state_ref_ignore = get_state_ref_ignore_patterns(git_repo, repo_ref)?;
         for (name, value) in &nostr_state.state {
+            if is_state_ref_ignored(name, &state_ref_ignore) {
+                continue;
+            }
             for (url, remote_state) in &remote_states {
                 let remote_name = get_short_git_server_name(git_repo, url);
                 if let Some(remote_value) = remote_state.get(name) {
-                    if value.ne(remote_value) {
+                    if value.ne(remote_value) && name == "HEAD" {
+                        term.write_line(
+                            format!(
+                                "WARNING: {remote_name} HEAD is {} but nostr HEAD is {}",
+                                describe_head(remote_value),
+                                describe_head(value),
+                            )
+                            .as_str(),
+                        )?;
+                    } else if value.ne(remote_value) {
                         term.write_line(
                             format!(
                                 "WARNING: {remote_name} {name} is {} nostr ",
@@ -77,25 +100,75 @@ pub async fn run_list(
                 }
             }
         }
+        if !for_push {
+            // tips fetched since the last fetch are checked after `fetch`
+            warn_about_rewound_branches(&term, git_repo, remote, &nostr_state.state)?;
+        }
         nostr_state.state
+    } else if let Some(remote_state) = repo_ref
+        .git_server
+        .iter()
+        .find_map(|server| remote_states.get(server))
+    {
+        remote_state.clone()
+    } else if repo_ref.root_commit.is_empty() {
+        // announced before its first commit so there may be nothing to list
+        HashMap::new()
     } else {
-        repo_ref
-            .git_server
-            .iter()
-            .filter_map(|server| remote_states.get(server))
-            .cloned()
-            .collect::<Vec<HashMap<String, String>>>()
-            .first()
-            .context("failed to get refs from git server")?
-            .clone()
+        bail!("failed to get refs from git server");
     };
 
     state.retain(|k, _| !k.starts_with("refs/heads/pr/"));
+    point_head_at_existing_branch(&mut state);
 
-    let proposals_state =
-        get_open_and_draft_proposals_state(&term, git_repo, repo_ref, &remote_states).await?;
+    if for_push {
+        // push compares pushed refs with the git server tips so they must be
+        // available locally
+        for (git_server_url, oids_from_git_servers) in &remote_states {
+            if fetch_from_git_server(
+                git_repo,
+                &oids_from_git_servers
+                    .values()
+                    .filter(|v| !v.starts_with("ref: "))
+                    .cloned()
+                    .collect::<Vec<String>>(),
+                git_server_url,
+                &repo_ref.to_nostr_git_url(&None),
+                &PartialCloneOptions::default(),
+                &term,
+            )
+            .is_ok()
+            {
+                break;
+            }
+        }
+    }
 
-    state.extend(proposals_state);
+    // push compares pushed `pr/` branches with the listed refs so they are
+    // always listed as heads
+    let prs_as_refs = if for_push {
+        PrsAsRefs::Heads
+    } else {
+        prs_as_refs
+    };
+    if prs_as_refs != PrsAsRefs::None {
+        let proposals_state = get_open_and_draft_proposals_state(
+            &term,
+            git_repo,
+            repo_ref,
+            &remote_states,
+            prs_as_refs,
+        )
+        .await?;
+        state.extend(proposals_state);
+    }
+    if let (PrsAsRefs::Prs, Some(remote)) = (prs_as_refs, remote) {
+        if let Err(error) = ensure_prs_fetch_refspec(git_repo, remote) {
+            term.write_line(&format!(
+                "WARNING: failed to add refs/prs/* to remote.{remote}.fetch error: {error}"
+            ))?;
+        }
+    }
 
     // TODO 'for push' should we check with the git servers to see if any of them
     // allow push from the user?
@@ -113,11 +186,20 @@ pub async fn run_list(
     Ok(remote_states)
 }
 
+/// HEAD is either `ref: <branch>` or, when detached, a commit id
+fn describe_head(value: &str) -> String {
+    value.strip_prefix("ref: ").map_or_else(
+        || format!("detached at {}", &value[..value.len().min(7)]),
+        std::string::ToString::to_string,
+    )
+}
+
 async fn get_open_and_draft_proposals_state(
     term: &console::Term,
     git_repo: &Repo,
     repo_ref: &RepoRef,
     remote_states: &HashMap<String, HashMap<String, String>>,
+    prs_as_refs: PrsAsRefs,
 ) -> Result<HashMap<String, String>> {
     // we cannot use commit_id in the latest patch in a proposal because:
     // 1) the `commit` tag is optional
@@ -125,29 +207,47 @@ async fn get_open_and_draft_proposals_state(
     //    working
 
     // without trusting commit_id we must apply each patch which requires the oid of
-    // the parent so we much do a fetch
-    for (git_server_url, oids_from_git_servers) in remote_states {
-        if fetch_from_git_server(
-            git_repo,
-            &oids_from_git_servers
-                .values()
-                .filter(|v| !v.starts_with("ref: "))
-                .cloned()
-                .collect::<Vec<String>>(),
-            git_server_url,
-            &repo_ref.to_nostr_git_url(&None),
-            term,
-        )
-        .is_ok()
-        {
-            break;
+    // the parent. only fetch those parents that are missing rather than every
+    // branch tip, so `git clone --single-branch` doesn't download other branches
+    let open_and_draft_proposals =
+        get_open_or_draft_proposals_with_status(git_repo, repo_ref).await?;
+    let mut missing_parent_commits = open_and_draft_proposals
+        .values()
+        .filter_map(|(_, patches, _)| {
+            patches
+                .last()
+                .and_then(|patch| tag_value(patch, "parent-commit").ok())
+        })
+        .filter(|oid| !git_repo.does_commit_exist(oid).is_ok_and(|exists| exists))
+        .collect::<Vec<String>>();
+    missing_parent_commits.sort();
+    missing_parent_commits.dedup();
+    if !missing_parent_commits.is_empty() {
+        for git_server_url in remote_states.keys() {
+            if fetch_from_git_server(
+                git_repo,
+                &missing_parent_commits,
+                git_server_url,
+                &repo_ref.to_nostr_git_url(&None),
+                &PartialCloneOptions::default(),
+                term,
+            )
+            .is_ok()
+            {
+                break;
+            }
         }
     }
 
     let mut state = HashMap::new();
-    let open_and_draft_proposals = get_open_or_draft_proposals(git_repo, repo_ref).await?;
+    let mut notes = HashMap::new();
     let current_user = get_curent_user(git_repo)?;
-    for (_, (proposal, patches)) in open_and_draft_proposals {
+    let repo_coordinate = Coordinate {
+        relays: vec![],
+        ..repo_ref.coordinate_with_hint()
+    }
+    .to_string();
+    for (_, (proposal, patches, status)) in open_and_draft_proposals {
         if let Ok(cl) = event_to_cover_letter(&proposal) {
             if let Ok(mut branch_name) = cl.get_branch_name_with_pr_prefix_and_shorthand_id() {
                 branch_name = if let Some(public_key) = current_user {
@@ -161,7 +261,24 @@ async fn get_open_and_draft_proposals_state(
                 };
                 match make_commits_for_proposal(git_repo, repo_ref, &patches) {
                     Ok(tip) => {
-                        state.insert(format!("refs/heads/{branch_name}"), tip);
+                        if let Ok(oid) = git2::Oid::from_str(&tip) {
+                            notes.insert(oid, ProposalNote {
+                                branch_name: branch_name.clone(),
+                                title: cl.title.clone(),
+                                author: proposal.pubkey.to_bech32()?,
+                                root: proposal.id.to_hex(),
+                                status: if status == Kind::GitStatusDraft {
+                                    "draft"
+                                } else {
+                                    "open"
+                                }
+                                .to_string(),
+                                repo: repo_coordinate.clone(),
+                            });
+                        }
+                        if let Some(refstr) = proposal_refstr(&branch_name, prs_as_refs) {
+                            state.insert(refstr, tip);
+                        }
                     }
                     Err(error) => {
                         let _ = term.write_line(
@@ -173,9 +290,27 @@ async fn get_open_and_draft_proposals_state(
             }
         }
     }
+    if let Err(error) = update_proposal_notes(git_repo, &repo_coordinate, &notes) {
+        let _ = term.write_line(
+            format!("WARNING: failed to update proposal notes in refs/notes/nostr error: {error}")
+                .as_str(),
+        );
+    }
     Ok(state)
 }
 
+/// git only fetches refs matching the remote's fetch refspecs so without this
+/// `refs/prs/*` wouldn't be stored under `refs/remotes/<remote>/prs/`. git
+/// reads them before running the helper so this applies from the next fetch
+fn ensure_prs_fetch_refspec(git_repo: &Repo, remote: &str) -> Result<()> {
+    let refspec = format!("+refs/prs/*:refs/remotes/{remote}/prs/*");
+    let existing = git_repo.git_repo.find_remote(remote)?.fetch_refspecs()?;
+    if !existing.iter().flatten().any(|r| r.eq(&refspec)) {
+        git_repo.git_repo.remote_add_fetch(remote, &refspec)?;
+    }
+    Ok(())
+}
+
 pub fn list_from_remotes(
     term: &console::Term,
     git_repo: &Repo,
@@ -185,11 +320,13 @@ pub fn list_from_remotes(
     let mut remote_states = HashMap::new();
     let mut errors = HashMap::new();
     for url in git_servers {
+        timeout::git_server_asked();
         match list_from_remote(term, git_repo, url, decoded_nostr_url) {
             Err(error) => {
                 errors.insert(url, error);
             }
             Ok(state) => {
+                timeout::git_server_answered();
                 remote_states.insert(url.to_string(), state);
             }
         }
@@ -206,6 +343,8 @@ pub fn list_from_remote(
     let server_url = git_server_url.parse::<CloneUrl>()?;
     let protocols_to_attempt = get_read_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let proxy = get_proxy(&Some(git_repo), ProxyUse::GitServers)?.map(|proxy| proxy.value);
+
     let mut failed_protocols = vec![];
     let mut remote_state: Option<HashMap<String, String>> = None;
 
@@ -223,6 +362,7 @@ pub fn list_from_remote(
             git_repo,
             &formatted_url,
             [ServerProtocol::UnauthHttps, ServerProtocol::UnauthHttp].contains(protocol),
+            &proxy,
             term,
         );
 
@@ -283,8 +423,10 @@ fn list_from_remote_url(
     git_repo: &Repo,
     git_server_remote_url: &str,
     dont_authenticate: bool,
+    proxy: &Option<String>,
     term: &console::Term,
 ) -> Result<HashMap<String, String>> {
+    ensure_onion_url_has_proxy(git_server_remote_url, proxy, ProxyUse::GitServers)?;
     let git_config = git_repo.git_repo.config()?;
 
     let mut git_server_remote = git_repo.git_repo.remote_anonymous(git_server_remote_url)?;
@@ -295,7 +437,11 @@ fn list_from_remote_url(
         remote_callbacks.credentials(auth.credentials(&git_config));
     }
     term.write_line("list: connecting...")?;
-    git_server_remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks), None)?;
+    git_server_remote.connect_auth(
+        git2::Direction::Fetch,
+        Some(remote_callbacks),
+        Some(git_proxy_options(proxy)),
+    )?;
     term.clear_last_lines(1)?;
     let mut state = HashMap::new();
     for head in git_server_remote.list()? {
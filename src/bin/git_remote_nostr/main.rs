@@ -9,59 +9,142 @@ use std::{
     collections::HashSet,
     env, io,
     path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
 };
 
 use anyhow::{Context, Result, bail};
-use client::{Connect, consolidate_fetch_reports, get_repo_ref_from_cache};
-use git::{RepoActions, nostr_url::NostrUrlDecoded};
-use ngit::{client, git, login::existing::load_existing_login};
-use nostr::nips::nip01::Coordinate;
+use client::{
+    Connect, FetchReport, Params, consolidate_fetch_reports, get_repo_ref_from_cache,
+    get_repo_ref_from_cache_after_fetch, get_state_from_cache, warn_on_clock_skew,
+};
+use git::{
+    RepoActions, get_git_config_item,
+    nostr_url::{NostrUrlDecoded, migrate_legacy_remote_url},
+    parse_git_config_bool,
+};
+use ngit::{
+    background_fetch::{background_fetch_running, get_background_fetch_log_path},
+    cli_interactor::{self, Interactor, InteractorPrompt, PromptConfirmParms},
+    client,
+    config::Config,
+    error::{EXIT_CODES_HELP, ErrorCategory, NgitError, report_and_exit},
+    git,
+    login::{
+        existing::{LoadLoginOptions, NeedsInteraction, load_existing_login},
+        user::get_names_for_display,
+    },
+    output::{ColorChoice, dim, init_color},
+    repo_fetched_at::seconds_since_repo_fetched,
+    repo_ref::RepoRef,
+    timeout::{get_timeout_from_git_config, with_cancellation, with_timeout},
+};
+use nostr::{Timestamp, nips::nip01::Coordinate};
 use utils::read_line;
 
 use crate::{client::Client, git::Repo};
 
+mod doctor;
 mod fetch;
 mod list;
 mod push;
 mod utils;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    report_and_exit(run().await)
+}
+
+async fn run() -> Result<()> {
+    // stdout is the git protocol so only stderr is ever styled
+    init_color(ColorChoice::Auto);
+    // stdin is git's pipe so prompts go to the terminal behind stderr
+    cli_interactor::prompt_when_stderr_is_terminal();
     let Some((decoded_nostr_url, git_repo)) = process_args().await? else {
         return Ok(());
     };
+    let timeout = get_timeout_from_git_config(&git_repo)?;
+    with_cancellation(with_timeout(timeout, serve(decoded_nostr_url, git_repo))).await
+}
 
+async fn serve(decoded_nostr_url: NostrUrlDecoded, git_repo: Repo) -> Result<()> {
     let git_repo_path = git_repo.get_path()?;
 
-    let mut client = Client::default();
+    let config = Config::load(&Some(&git_repo)).category(NgitError::Config)?;
+    init_color(config.color.value);
+    // stdout is still the git protocol, whatever the config says
+    console::set_colors_enabled(false);
 
-    if let Ok((signer, _, _)) = load_existing_login(
+    let mut client = Client::new(Params::with_config(&config));
+
+    match load_existing_login(
         &Some(&git_repo),
         &None,
         &None,
         &None,
         None,
-        true,
-        false,
-        false,
+        &LoadLoginOptions::default().silent().without_prompts(),
     )
     .await
     {
         // signer for to respond to relay auth request
-        client.set_signer(signer).await;
+        Ok((signer, _, _)) => client.set_signer(signer).await,
+        // stdout is the remote helper protocol so never prompt
+        Err(error) if error.downcast_ref::<NeedsInteraction>().is_some() => eprintln!(
+            "nostr: stored nsec is encrypted with a password so continuing without logging in"
+        ),
+        Err(_) => {}
     }
 
-    fetching_with_report_for_helper(git_repo_path, &client, &decoded_nostr_url.coordinate).await?;
+    let fix_timestamp = get_git_config_item(&Some(&git_repo), "nostr.fix-timestamp")?
+        .map(|v| parse_git_config_bool(&v))
+        .transpose()
+        .context("invalid git config item nostr.fix-timestamp")?
+        .unwrap_or(false);
 
-    let mut repo_ref =
-        get_repo_ref_from_cache(Some(git_repo_path), &decoded_nostr_url.coordinate).await?;
+    // cached_state_age is the seconds since the cached state was fetched,
+    // until it is refreshed
+    // with nostr.background-fetch a stale cache is used and refreshed for next
+    // time
+    let (mut repo_ref, mut cached_state_age) = match get_fresh_repo_ref_from_cache(
+        git_repo_path,
+        &decoded_nostr_url,
+        if config.background_fetch.value {
+            u64::MAX
+        } else {
+            config.cache_max_age_secs.value
+        },
+    )
+    .await
+    {
+        Some((repo_ref, age)) => (repo_ref, Some(age)),
+        None => (
+            fetch_repo_ref(git_repo_path, &client, &decoded_nostr_url, fix_timestamp).await?,
+            None,
+        ),
+    };
 
-    repo_ref.set_nostr_git_url(decoded_nostr_url.clone());
+    // git passes the url in place of the remote name when there is no remote
+    let remote = env::args()
+        .nth(1)
+        .filter(|name| git_repo.git_repo.find_remote(name).is_ok());
+
+    let mut refresh_in_background =
+        cached_state_age.is_some_and(|age| age >= config.cache_max_age_secs.value);
+
+    // a clone is the only time the helper runs with no local refs
+    let initial_clone = !config.quiet_clone.value
+        && git_repo
+            .git_repo
+            .references()
+            .is_ok_and(|mut refs| refs.next().is_none());
+    let mut fetched = false;
 
     let stdin = io::stdin();
     let mut line = String::new();
 
     let mut list_outputs = None;
+    let mut push_options = vec![];
+    let mut partial_clone = fetch::PartialCloneOptions::default();
     loop {
         let tokens = read_line(&stdin, &mut line)?;
 
@@ -70,18 +153,51 @@ async fn main() -> Result<()> {
                 println!("option");
                 println!("push");
                 println!("fetch");
+                // HEAD is listed as `@<ref> HEAD`. git ignores capabilities it
+                // doesn't know so this is only a hint for tooling
+                println!("symref");
+                // partial clone is supported through `option filter`
+                println!("filter");
                 println!();
             }
-            ["option", "verbosity"] => {
+            ["option", "verbosity", level] => {
+                let verbosity = level.parse::<u8>().unwrap_or(1);
+                utils::set_verbosity(verbosity);
+                client::set_verbose(verbosity > 1);
+                println!("ok");
+            }
+            ["option", "push-option", push_option] => {
+                push_options.push((*push_option).to_string());
+                println!("ok");
+            }
+            ["option", "filter", filter] => {
+                partial_clone.filter = Some((*filter).to_string());
+                println!("ok");
+            }
+            ["option", "from-promisor", value] => {
+                partial_clone.from_promisor = value.eq(&"true");
                 println!("ok");
             }
             ["option", ..] => {
                 println!("unsupported");
             }
             ["fetch", oid, refstr] => {
-                fetch::run_fetch(&git_repo, &repo_ref, &stdin, oid, refstr).await?;
+                fetch::run_fetch(
+                    &git_repo,
+                    &repo_ref,
+                    remote.as_deref(),
+                    &stdin,
+                    oid,
+                    refstr,
+                    &partial_clone,
+                )
+                .await?;
+                fetched = true;
             }
             ["push", refspec] => {
+                if push_options.iter().any(|o| o == "no-blaster") {
+                    client.disable_blaster_relays();
+                }
                 push::run_push(
                     &git_repo,
                     &repo_ref,
@@ -89,16 +205,67 @@ async fn main() -> Result<()> {
                     refspec,
                     &client,
                     list_outputs.clone(),
+                    &push_options,
+                    config.proposal_expiry_days.value,
                 )
                 .await?;
             }
             ["list"] => {
-                list_outputs = Some(list::run_list(&git_repo, &repo_ref, false).await?);
+                if let (Some(age), true) = (cached_state_age, utils::get_verbosity() > 0) {
+                    console::Term::stderr().write_line(
+                        &dim(&format!(
+                            "nostr: using cached state ({age}s old){}",
+                            if refresh_in_background {
+                                ", refreshing in the background"
+                            } else {
+                                ""
+                            }
+                        ))
+                        .for_stderr()
+                        .to_string(),
+                    )?;
+                }
+                if refresh_in_background {
+                    refresh_in_background = false;
+                    if let Err(error) = spawn_background_fetch(&git_repo, remote.as_deref()) {
+                        console::Term::stderr().write_line(&format!(
+                            "nostr: failed to start background fetch: {error}"
+                        ))?;
+                    }
+                }
+                list_outputs = Some(
+                    list::run_list(
+                        &git_repo,
+                        &repo_ref,
+                        remote.as_deref(),
+                        false,
+                        config.prs_as_refs.value,
+                    )
+                    .await?,
+                );
             }
             ["list", "for-push"] => {
-                list_outputs = Some(list::run_list(&git_repo, &repo_ref, true).await?);
+                // pushing against stale state could overwrite newer commits
+                if cached_state_age.take().is_some() {
+                    repo_ref =
+                        fetch_repo_ref(git_repo_path, &client, &decoded_nostr_url, fix_timestamp)
+                            .await?;
+                }
+                list_outputs = Some(
+                    list::run_list(
+                        &git_repo,
+                        &repo_ref,
+                        remote.as_deref(),
+                        true,
+                        config.prs_as_refs.value,
+                    )
+                    .await?,
+                );
             }
             [] => {
+                if initial_clone && fetched {
+                    print_clone_banner(git_repo_path, &repo_ref).await?;
+                }
                 return Ok(());
             }
             _ => {
@@ -118,6 +285,14 @@ async fn process_args() -> Result<Option<(NostrUrlDecoded, Repo)>> {
         return Ok(None);
     }
 
+    if matches!(
+        env::args().nth(1).as_deref(),
+        Some("--doctor" | "--capabilities")
+    ) {
+        doctor::print_doctor()?;
+        return Ok(None);
+    }
+
     let ([_, nostr_remote_url] | [nostr_remote_url]) = args.as_slice() else {
         println!("nostr plugin for git");
         println!("Usage:");
@@ -131,6 +306,8 @@ async fn process_args() -> Result<Option<(NostrUrlDecoded, Repo)>> {
             " - to open a PR, push a branch with the prefix `pr/` or use `ngit send` for advanced options"
         );
         println!("- publish a repository to nostr with `ngit init`");
+        println!();
+        println!("{EXIT_CODES_HELP}");
         return Ok(None);
     };
 
@@ -140,16 +317,190 @@ async fn process_args() -> Result<Option<(NostrUrlDecoded, Repo)>> {
 
     let decoded_nostr_url = NostrUrlDecoded::parse_and_resolve(nostr_remote_url, &Some(&git_repo))
         .await
-        .context("invalid nostr url")?;
+        .context("invalid nostr url")
+        .category(NgitError::Config)?;
+
+    if decoded_nostr_url.is_legacy() {
+        if let [remote, _] = args.as_slice() {
+            offer_to_migrate_legacy_remote(&git_repo, remote, &decoded_nostr_url)?;
+        }
+    }
 
     Ok(Some((decoded_nostr_url, git_repo)))
 }
 
+/// remotes written by older ngit versions keep working but are rewritten in
+/// naddr form when the user agrees
+fn offer_to_migrate_legacy_remote(
+    git_repo: &Repo,
+    remote: &str,
+    decoded_nostr_url: &NostrUrlDecoded,
+) -> Result<()> {
+    // urls used without a remote, eg. `git fetch nostr://...`, are also passed
+    // in place of the remote name
+    if !git_repo
+        .git_repo
+        .find_remote(remote)
+        .is_ok_and(|r| r.url() == Some(decoded_nostr_url.original_string.as_str()))
+    {
+        return Ok(());
+    }
+    let term = console::Term::stderr();
+    let naddr_url = decoded_nostr_url.to_naddr_url()?;
+    if cli_interactor::prompts_allowed()
+        && Interactor::default().confirm(
+            PromptConfirmParms::default()
+                .with_prompt(format!(
+                    "remote '{remote}' uses a legacy nostr url. rewrite it in naddr format?"
+                ))
+                .with_default(true),
+        )?
+    {
+        migrate_legacy_remote_url(git_repo, remote, decoded_nostr_url)?;
+        term.write_line(&format!("migrated remote '{remote}' to {naddr_url}"))?;
+    } else {
+        term.write_line(&format!(
+            "nostr: remote '{remote}' uses a legacy nostr url. run `ngit migrate` to rewrite it as {naddr_url}"
+        ))?;
+    }
+    Ok(())
+}
+
+/// where discussion happens and who maintains the repository, for after a
+/// clone
+async fn print_clone_banner(git_repo_path: &Path, repo_ref: &RepoRef) -> Result<()> {
+    let term = console::Term::stderr();
+    term.write_line(&format!(
+        "nostr: cloned {}{}",
+        if repo_ref.name.is_empty() {
+            &repo_ref.identifier
+        } else {
+            &repo_ref.name
+        },
+        if repo_ref.description.trim().is_empty() {
+            String::new()
+        } else {
+            format!(" - {}", repo_ref.description.trim())
+        }
+    ))?;
+    if !repo_ref.web.is_empty() {
+        term.write_line(&format!("  web: {}", repo_ref.web.join(" ")))?;
+    }
+    // profiles fetched with the repository are cached so no relays are asked
+    let names = get_names_for_display(
+        &repo_ref.maintainers.iter().copied().collect(),
+        None,
+        Some(git_repo_path),
+        &[],
+    )
+    .await?;
+    term.write_line(&format!(
+        "  maintainers: {}",
+        repo_ref
+            .maintainers
+            .iter()
+            .filter_map(|public_key| names.get(public_key).cloned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))?;
+    term.write_line("  run `ngit list` to see open proposals")?;
+    Ok(())
+}
+
+/// the cached repository when its state was fetched within `max_age_secs`,
+/// with the seconds since
+async fn get_fresh_repo_ref_from_cache(
+    git_repo_path: &Path,
+    decoded_nostr_url: &NostrUrlDecoded,
+    max_age_secs: u64,
+) -> Option<(RepoRef, u64)> {
+    let age = seconds_since_repo_fetched(
+        git_repo_path,
+        &decoded_nostr_url.coordinate,
+        Timestamp::now(),
+    )
+    .filter(|age| *age < max_age_secs)?;
+    let mut repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &decoded_nostr_url.coordinate)
+        .await
+        .ok()?;
+    get_state_from_cache(Some(git_repo_path), &repo_ref)
+        .await
+        .ok()?;
+    repo_ref.set_nostr_git_url(decoded_nostr_url.clone());
+    Some((repo_ref, age))
+}
+
+/// refresh the cache for next time with a detached `ngit fetch --quiet`. its
+/// output goes to a log file so it can't interleave with the protocol on
+/// stdout
+fn spawn_background_fetch(git_repo: &Repo, remote: Option<&str>) -> Result<()> {
+    let git_repo_path = git_repo.get_path()?;
+    if background_fetch_running(git_repo_path, Timestamp::now()) {
+        return Ok(());
+    }
+    let log_path = get_background_fetch_log_path(git_repo_path);
+    if let Some(dir) = log_path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create .git/nostr directory")?;
+    }
+    let log = std::fs::File::create(&log_path).context(format!(
+        "failed to create background fetch log {}",
+        log_path.display()
+    ))?;
+    // installed alongside git-remote-nostr
+    let ngit = env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("ngit{}", env::consts::EXE_SUFFIX)))
+        .filter(|ngit| ngit.exists())
+        .unwrap_or_else(|| PathBuf::from("ngit"));
+    let mut command = Command::new(ngit);
+    command.args(["fetch", "--quiet"]);
+    if let Some(remote) = remote {
+        command.args(["--remote", remote]);
+    }
+    command
+        .current_dir(git_repo.get_workdir()?)
+        .env_remove("GIT_DIR")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // keeps running after git exits, even when git is interrupted
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn().context("failed to run ngit fetch")?;
+    Ok(())
+}
+
+async fn fetch_repo_ref(
+    git_repo_path: &Path,
+    client: &Client,
+    decoded_nostr_url: &NostrUrlDecoded,
+    fix_timestamp: bool,
+) -> Result<RepoRef> {
+    let report = fetching_with_report_for_helper(
+        git_repo_path,
+        client,
+        &decoded_nostr_url.coordinate,
+        fix_timestamp,
+    )
+    .await?;
+
+    let mut repo_ref = get_repo_ref_from_cache_after_fetch(
+        Some(git_repo_path),
+        &decoded_nostr_url.coordinate,
+        &report,
+    )
+    .await?;
+
+    repo_ref.set_nostr_git_url(decoded_nostr_url.clone());
+    Ok(repo_ref)
+}
+
 async fn fetching_with_report_for_helper(
     git_repo_path: &Path,
     client: &Client,
     trusted_maintainer_coordinate: &Coordinate,
-) -> Result<()> {
+    fix_timestamp: bool,
+) -> Result<FetchReport> {
     let term = console::Term::stderr();
     term.write_line("nostr: fetching...")?;
     let (relay_reports, progress_reporter) = client
@@ -159,15 +510,27 @@ async fn fetching_with_report_for_helper(
             &HashSet::new(),
         )
         .await?;
-    if !relay_reports.iter().any(std::result::Result::is_err) {
+    // no reports when another process fetched recently and said so
+    if !relay_reports.is_empty()
+        && !relay_reports
+            .iter()
+            .any(|r| r.as_ref().map_or(true, FetchReport::has_partial_results))
+    {
         let _ = progress_reporter.clear();
         term.clear_last_lines(1)?;
     }
     let report = consolidate_fetch_reports(relay_reports);
     if report.to_string().is_empty() {
-        term.write_line("nostr: no updates")?;
+        term.write_line(&dim("nostr: no updates").for_stderr().to_string())?;
     } else {
-        term.write_line(&format!("nostr updates: {report}"))?;
+        term.write_line(&format!("{} {report}", dim("nostr updates:").for_stderr()))?;
     }
-    Ok(())
+    if let Some(note) = report.skipped_relays_note() {
+        term.write_line(&dim(&format!("nostr: {note}")).for_stderr().to_string())?;
+    }
+    if let Some(warning) = report.partial_results_warning() {
+        term.write_line(&format!("nostr: {warning}"))?;
+    }
+    warn_on_clock_skew(&report, fix_timestamp)?;
+    Ok(report)
 }
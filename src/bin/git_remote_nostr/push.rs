@@ -2,23 +2,30 @@ use core::str;
 use std::{
     collections::{HashMap, HashSet},
     io::Stdin,
+    process::Stdio,
     sync::{Arc, Mutex},
     time::Instant,
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use auth_git2::GitAuthenticator;
+use base64::{Engine, prelude::BASE64_STANDARD};
 use client::{
-    STATE_KIND, get_events_from_local_cache, get_state_from_cache, send_events, sign_event,
+    Connect, STATE_KIND, correct_clock_ahead_after_rejection, event_reached_any_relay,
+    get_events_from_local_cache, get_repo_relays, get_state_from_cache, print_repo_relays_notice,
+    send_events, sign_event, sign_event_at, timestamp_now,
 };
 use console::Term;
-use git::{RepoActions, sha1_to_oid};
+use git::{RepoActions, parse_git_config_bool, sha1_to_oid};
 use git_events::{
-    generate_cover_letter_and_patch_events, generate_patch_event, get_commit_id_from_patch,
+    expiration_tags, generate_cover_letter_and_patch_events, generate_patch_event,
+    get_commit_id_from_patch, proposal_expiration,
 };
 use git2::{Oid, Repository};
 use ngit::{
-    cli_interactor::count_lines_per_msg_vec,
+    cli_interactor::{
+        self, Interactor, InteractorPrompt, PromptConfirmParms, count_lines_per_msg_vec,
+    },
     client::{self, get_event_from_cache_by_id},
     git::{
         self,
@@ -26,30 +33,46 @@ use ngit::{
         oid_to_shorthand_string,
     },
     git_events::{self, event_to_cover_letter, get_event_root},
+    hooks::run_pre_send_hook,
     login::{self, user::UserRef},
-    repo_ref::{self, get_repo_config_from_yaml},
+    proxy::{ProxyUse, ensure_onion_url_has_proxy, get_proxy, git_proxy_options},
+    repo_ref::{
+        self, announcement_from_tags, get_read_only_git_servers, get_repo_config_from_yaml,
+        merge_announcement_tags,
+    },
     repo_state,
 };
-use nostr::nips::nip10::Marker;
+use nostr::{
+    JsonUtil,
+    nips::{
+        nip10::Marker,
+        nip98::{HttpData, HttpMethod},
+    },
+};
 use nostr_sdk::{
-    Event, EventBuilder, EventId, Kind, NostrSigner, PublicKey, RelayUrl, Tag,
-    hashes::sha1::Hash as Sha1Hash,
+    Alphabet, Event, EventBuilder, EventId, Kind, NostrSigner, PublicKey, RelayUrl,
+    SingleLetterTag, Tag, TagKind, Timestamp, ToBech32, hashes::sha1::Hash as Sha1Hash,
 };
 use repo_ref::RepoRef;
-use repo_state::RepoState;
+use repo_state::{
+    RepoState, current_state_event, get_state_ref_ignore_patterns, is_state_ref_ignored,
+    next_state_created_at, point_head_at_existing_branch,
+};
 
 use crate::{
     client::Client,
     git::Repo,
     list::list_from_remotes,
     utils::{
-        Direction, find_proposal_and_patches_by_branch_name, get_all_proposals,
-        get_remote_name_by_url, get_short_git_server_name, get_write_protocols_to_try,
-        join_with_and, push_error_is_not_authentication_failure, read_line,
-        set_protocol_preference,
+        Direction, SHELL_GIT_REMOTE, find_proposal_and_patches_by_branch_name,
+        find_proposals_and_patches_by_ancestry, get_all_proposals, get_remote_name_by_url,
+        get_short_git_server_name, get_write_protocols_to_try, join_with_and,
+        push_error_is_not_authentication_failure, read_line, report_shell_git_fallback,
+        set_protocol_preference, shell_git_command, should_fallback_to_shell_git,
     },
 };
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_push(
     git_repo: &Repo,
     repo_ref: &RepoRef,
@@ -57,8 +80,26 @@ pub async fn run_push(
     initial_refspec: &str,
     client: &Client,
     list_outputs: Option<HashMap<String, HashMap<String, String>>>,
+    push_options: &[String],
+    proposal_expiry_days: Option<u64>,
 ) -> Result<()> {
     let refspecs = get_refspecs_from_push_batch(stdin, initial_refspec)?;
+    let proposal_push_options = ProposalPushOptions {
+        batch_pushes_all_branches: batch_pushes_all_local_branches(git_repo, &refspecs)?,
+        yes: push_options.iter().any(|o| o == "yes"),
+        expiration: proposal_expiration(
+            match push_options
+                .iter()
+                .find_map(|o| o.strip_prefix("expiry-days="))
+            {
+                Some(days) => Some(
+                    days.parse()
+                        .context("push option expiry-days must be a number of days")?,
+                ),
+                None => proposal_expiry_days,
+            },
+        ),
+    };
 
     let proposal_refspecs = refspecs
         .iter()
@@ -83,7 +124,7 @@ pub async fn run_push(
         )
     });
 
-    let existing_state = {
+    let mut existing_state = {
         // if no state events - create from first git server listed
         if let Ok(nostr_state) = &get_state_from_cache(Some(git_repo.get_path()?), repo_ref).await {
             nostr_state.state.clone()
@@ -100,13 +141,25 @@ pub async fn run_push(
             );
         }
     };
+    // git never pushes HEAD so it is taken from where it is defined
+    if let Some(head) = head_for_state(git_repo, repo_ref, &existing_state, &list_outputs) {
+        existing_state.insert("HEAD".to_string(), head);
+    }
+
+    // read-only mirrors are still used to read state but are never pushed to
+    let read_only_git_servers = get_read_only_git_servers(git_repo, repo_ref)?;
+    let writable_list_outputs = list_outputs
+        .iter()
+        .filter(|(url, _)| !read_only_git_servers.contains(url))
+        .map(|(url, state)| (url.clone(), state.clone()))
+        .collect::<HashMap<String, HashMap<String, String>>>();
 
     let (rejected_refspecs, remote_refspecs) = create_rejected_refspecs_and_remotes_refspecs(
         &term,
         git_repo,
         &git_server_refspecs,
         &existing_state,
-        &list_outputs,
+        &writable_list_outputs,
     )?;
 
     git_server_refspecs.retain(|refspec| {
@@ -121,50 +174,164 @@ pub async fn run_push(
 
     // all refspecs aren't rejected
     if !(git_server_refspecs.is_empty() && proposal_refspecs.is_empty()) {
-        let (rejected_proposal_refspecs, rejected) = create_and_publish_events(
+        if let Some(push_events) = create_events(
             git_repo,
             repo_ref,
             &git_server_refspecs,
             &proposal_refspecs,
+            &proposal_push_options,
             client,
             existing_state,
             &term,
         )
-        .await?;
+        .await?
+        {
+            // TODO make async - check gitlib2 callbacks work async
 
-        if !rejected {
-            for refspec in git_server_refspecs.iter().chain(proposal_refspecs.iter()) {
-                if rejected_proposal_refspecs.contains(refspec) {
-                    continue;
-                }
-                let (_, to) = refspec_to_from_to(refspec)?;
-                println!("ok {to}");
-                update_remote_refs_pushed(
-                    &git_repo.git_repo,
-                    refspec,
-                    &repo_ref.to_nostr_git_url(&None).to_string(),
-                )
-                .context("could not update remote_ref locally")?;
+            if !git_server_refspecs.is_empty() && !read_only_git_servers.is_empty() {
+                term.write_line(
+                    format!(
+                        "skipped {} read-only mirror{}",
+                        read_only_git_servers.len(),
+                        if read_only_git_servers.len() == 1 {
+                            ""
+                        } else {
+                            "s"
+                        },
+                    )
+                    .as_str(),
+                )?;
             }
 
-            // TODO make async - check gitlib2 callbacks work async
-
+            // push to git servers before publishing the nostr state so the
+            // state never references commits the git servers don't have
+            let mut pushed_remote_refspecs = HashMap::new();
             for (git_server_url, remote_refspecs) in remote_refspecs {
                 let remote_refspecs = remote_refspecs
                     .iter()
                     .filter(|refspec| git_server_refspecs.contains(refspec))
                     .cloned()
                     .collect::<Vec<String>>();
-                if !refspecs.is_empty() {
-                    let _ = push_to_remote(
+                if !remote_refspecs.is_empty()
+                    && push_to_remote(
                         git_repo,
                         &git_server_url,
                         &repo_ref.to_nostr_git_url(&None),
                         &remote_refspecs,
                         &term,
-                    );
+                    )
+                    .is_ok()
+                {
+                    pushed_remote_refspecs.insert(git_server_url, remote_refspecs);
+                }
+            }
+
+            let mut state_published = true;
+            if !push_events.events.is_empty() {
+                let (repo_relays, repo_relays_source) = get_repo_relays(
+                    Some(git_repo.get_path()?),
+                    repo_ref,
+                    client.get_fallback_relays(),
+                )
+                .await;
+                print_repo_relays_notice(repo_relays_source);
+                term.write_line("broadcast to nostr relays:")?;
+                let mut accepted = send_events(
+                    client,
+                    Some(git_repo.get_path()?),
+                    push_events.events,
+                    push_events.my_write_relays.clone(),
+                    repo_relays.clone(),
+                    true,
+                    false,
+                )
+                .await?;
+                if let Some(url) = &push_events.contributor_push_url {
+                    let refspecs = push_events
+                        .contributor_pushes
+                        .iter()
+                        .filter(|push| {
+                            event_reached_any_relay(&accepted, &push.event_id, &repo_relays)
+                        })
+                        .map(|push| push.refspec.clone())
+                        .collect::<Vec<String>>();
+                    push_to_contributor_namespace(
+                        git_repo,
+                        url,
+                        &refspecs,
+                        &push_events.signer,
+                        &term,
+                    )
+                    .await;
+                }
+                if let Some(mut state) = push_events.state {
+                    // the state event is replaceable so it can be signed and
+                    // sent again if relays reveal the local clock is ahead
+                    if correct_clock_ahead_after_rejection(
+                        &accepted,
+                        std::slice::from_ref(&state.event),
+                    )? {
+                        state = RepoState::build(
+                            state.identifier.clone(),
+                            state.state.clone(),
+                            &push_events.signer,
+                            timestamp_now(),
+                        )
+                        .await?;
+                        accepted = send_events(
+                            client,
+                            Some(git_repo.get_path()?),
+                            vec![state.event.clone()],
+                            push_events.my_write_relays.clone(),
+                            repo_relays.clone(),
+                            true,
+                            false,
+                        )
+                        .await?;
+                    }
+                    state_published =
+                        event_reached_any_relay(&accepted, &state.event.id, &repo_relays)
+                            && ensure_state_event_kept(
+                                client,
+                                git_repo,
+                                state,
+                                &push_events.signer,
+                                &push_events.my_write_relays,
+                                &repo_relays,
+                                &term,
+                            )
+                            .await?;
                 }
             }
+
+            for refspec in git_server_refspecs.iter().chain(proposal_refspecs.iter()) {
+                if push_events.rejected_proposal_refspecs.contains(refspec) {
+                    continue;
+                }
+                let (_, to) = refspec_to_from_to(refspec)?;
+                if !state_published && git_server_refspecs.contains(refspec) {
+                    println!("error {to} nostr state update failed");
+                    continue;
+                }
+                println!("ok {to}");
+                update_remote_refs_pushed(
+                    &git_repo.git_repo,
+                    refspec,
+                    &repo_ref.to_nostr_git_url(&None).to_string(),
+                )
+                .context("could not update remote_ref locally")?;
+            }
+
+            if !state_published {
+                let rolled_back = rollback_git_servers(
+                    git_repo,
+                    &repo_ref.to_nostr_git_url(&None),
+                    &pushed_remote_refspecs,
+                    &writable_list_outputs,
+                    &term,
+                );
+                print_state_update_failed_instructions(repo_ref, rolled_back, &term)?;
+            }
         }
     }
 
@@ -172,15 +339,58 @@ pub async fn run_push(
     Ok(())
 }
 
-async fn create_and_publish_events(
+/// choices for the whole push batch that affect how `pr/*` refspecs are handled
+struct ProposalPushOptions {
+    batch_pushes_all_branches: bool,
+    /// `git push -o yes` skips confirmations, eg. orphaning review comments
+    yes: bool,
+    /// from `git push -o expiry-days=<days>` or `nostr.proposal-expiry-days`
+    expiration: Option<Timestamp>,
+}
+
+struct PushEvents {
+    events: Vec<Event>,
+    /// none when `nostr.nostate` is set or no git server refs were pushed
+    state: Option<RepoState>,
+    rejected_proposal_refspecs: Vec<String>,
+    /// see [`ProposalEvents`]
+    contributor_push_url: Option<String>,
+    contributor_pushes: Vec<ContributorPush>,
+    my_write_relays: Vec<String>,
+    /// to republish the state event if a conflicting one is kept instead
+    signer: Arc<dyn NostrSigner>,
+}
+
+#[derive(Default)]
+struct ProposalEvents {
+    events: Vec<Event>,
+    rejected_proposal_refspecs: Vec<String>,
+    /// contributor-push git server the patch events point to
+    contributor_push_url: Option<String>,
+    contributor_pushes: Vec<ContributorPush>,
+}
+
+/// proposal commits to push to the contributor's namespace once the patch
+/// events pointing to them are accepted
+struct ContributorPush {
+    /// `+<tip>:refs/heads/contrib/<npub>/<branch>`
+    refspec: String,
+    /// the first of the patch events
+    event_id: EventId,
+}
+
+/// returns None if the user cannot push any of the refspecs
+#[allow(clippy::too_many_arguments)]
+async fn create_events(
     git_repo: &Repo,
     repo_ref: &RepoRef,
     git_server_refspecs: &Vec<String>,
     proposal_refspecs: &Vec<String>,
+    proposal_push_options: &ProposalPushOptions,
     client: &Client,
     existing_state: HashMap<String, String>,
     term: &Term,
-) -> Result<(Vec<String>, bool)> {
+) -> Result<Option<PushEvents>> {
     let (signer, user_ref, _) =
         login::login_or_signup(&Some(git_repo), &None, &None, Some(client), true).await?;
 
@@ -193,14 +403,17 @@ async fn create_and_publish_events(
             );
         }
         if proposal_refspecs.is_empty() {
-            return Ok((vec![], true));
+            return Ok(None);
         }
     }
 
     let mut events = vec![];
+    let mut state = None;
 
     if !git_server_refspecs.is_empty() {
-        let new_state = generate_updated_state(git_repo, &existing_state, git_server_refspecs)?;
+        let mut new_state = generate_updated_state(git_repo, &existing_state, git_server_refspecs)?;
+        let state_ref_ignore = get_state_ref_ignore_patterns(git_repo, repo_ref)?;
+        new_state.retain(|name, _| !is_state_ref_ignored(name, &state_ref_ignore));
 
         let store_state =
             if let Ok(Some(nostate)) = git_repo.get_git_config_item("nostr.nostate", None) {
@@ -210,9 +423,28 @@ async fn create_and_publish_events(
             };
 
         if store_state {
-            let new_repo_state =
-                RepoState::build(repo_ref.identifier.clone(), new_state, &signer).await?;
-            events.push(new_repo_state.event);
+            let latest_state_created_at = get_events_from_local_cache(
+                git_repo.get_path()?,
+                vec![
+                    nostr::Filter::default()
+                        .kind(STATE_KIND)
+                        .author(user_ref.public_key)
+                        .identifier(repo_ref.identifier.clone()),
+                ],
+            )
+            .await?
+            .iter()
+            .map(|e| e.created_at)
+            .max();
+            let new_repo_state = RepoState::build(
+                repo_ref.identifier.clone(),
+                new_state,
+                &signer,
+                next_state_created_at(latest_state_created_at, timestamp_now()),
+            )
+            .await?;
+            events.push(new_repo_state.event.clone());
+            state = Some(new_repo_state);
         }
 
         for event in get_merged_status_events(
@@ -222,6 +454,7 @@ async fn create_and_publish_events(
             git_repo,
             &signer,
             git_server_refspecs,
+            proposal_push_options.expiration,
         )
         .await?
         {
@@ -237,66 +470,296 @@ async fn create_and_publish_events(
             git_server_refspecs,
         )
         .await
+        {
+            events.push(repo_ref_event);
+        } else if let Some(repo_ref_event) = get_root_commit_update(
+            term,
+            repo_ref,
+            git_repo,
+            &user_ref.public_key,
+            &signer,
+            git_server_refspecs,
+        )
+        .await?
         {
             events.push(repo_ref_event);
         }
     }
 
-    let (proposal_events, rejected_proposal_refspecs) = process_proposal_refspecs(
+    let proposal_events = process_proposal_refspecs(
         git_repo,
         repo_ref,
         proposal_refspecs,
+        proposal_push_options,
+        client,
         &user_ref,
         &signer,
         term,
     )
     .await?;
-    for e in proposal_events {
+    for e in proposal_events.events {
         events.push(e);
     }
 
-    // TODO check whether tip of each branch pushed is on at least one git server
-    // before broadcasting the nostr state
-    if !events.is_empty() {
-        term.write_line("broadcast to nostr relays:")?;
-        send_events(
+    Ok(Some(PushEvents {
+        events,
+        state,
+        rejected_proposal_refspecs: proposal_events.rejected_proposal_refspecs,
+        contributor_push_url: proposal_events.contributor_push_url,
+        contributor_pushes: proposal_events.contributor_pushes,
+        my_write_relays: user_ref.relays.write(),
+        signer,
+    }))
+}
+
+/// times the state event is republished with a later created_at when the repo
+/// relays keep a conflicting one of ours instead
+static STATE_EVENT_REPUBLISH_ATTEMPTS: usize = 2;
+
+/// relays keep one state event per maintainer. read it back from the repo
+/// relays and, if a conflicting one of ours was kept instead, eg. from a push
+/// moments earlier, republish with a later created_at. returns false if ours
+/// still isn't kept
+async fn ensure_state_event_kept(
+    client: &Client,
+    git_repo: &Repo,
+    mut state: RepoState,
+    signer: &Arc<dyn NostrSigner>,
+    my_write_relays: &[String],
+    repo_relays: &[RelayUrl],
+    term: &Term,
+) -> Result<bool> {
+    for attempt in 0..=STATE_EVENT_REPUBLISH_ATTEMPTS {
+        let kept_events = client
+            .get_events(
+                repo_relays.iter().map(ToString::to_string).collect(),
+                vec![
+                    nostr::Filter::default()
+                        .kind(STATE_KIND)
+                        .author(state.event.pubkey)
+                        .identifier(state.identifier.clone()),
+                ],
+            )
+            .await
+            .unwrap_or_default();
+        let Some(kept) = current_state_event(&kept_events) else {
+            // nothing read back so rely on the relays accepting it
+            return Ok(true);
+        };
+        if kept.id.eq(&state.event.id)
+            || RepoState::try_from(vec![kept.clone()]).is_ok_and(|s| s.state.eq(&state.state))
+        {
+            return Ok(true);
+        }
+        if attempt == STATE_EVENT_REPUBLISH_ATTEMPTS {
+            break;
+        }
+        term.write_line(
+            "repo relays kept a conflicting state event. republishing with a later timestamp...",
+        )?;
+        state = RepoState::build(
+            state.identifier.clone(),
+            state.state.clone(),
+            signer,
+            next_state_created_at(Some(kept.created_at), timestamp_now()),
+        )
+        .await?;
+        let accepted = send_events(
             client,
             Some(git_repo.get_path()?),
-            events,
-            user_ref.relays.write(),
-            repo_ref.relays.clone(),
+            vec![state.event.clone()],
+            my_write_relays.to_vec(),
+            repo_relays.to_vec(),
             true,
             false,
         )
         .await?;
+        if !event_reached_any_relay(&accepted, &state.event.id, repo_relays) {
+            return Ok(false);
+        }
+    }
+    Ok(false)
+}
+
+/// best effort attempt to return refs on git servers that accepted the push
+/// to their values beforehand. returns true if all were rolled back
+fn rollback_git_servers(
+    git_repo: &Repo,
+    decoded_nostr_url: &NostrUrlDecoded,
+    pushed_remote_refspecs: &HashMap<String, Vec<String>>,
+    list_outputs: &HashMap<String, HashMap<String, String>>,
+    term: &Term,
+) -> bool {
+    let mut rolled_back = true;
+    for (git_server_url, remote_refspecs) in pushed_remote_refspecs {
+        let previous_state = list_outputs.get(git_server_url);
+        let mut rollback_refspecs = vec![];
+        let mut tmp_refs = vec![];
+        for refspec in remote_refspecs {
+            let Ok((_, to)) = refspec_to_from_to(refspec) else {
+                rolled_back = false;
+                continue;
+            };
+            if let Some(previous) = previous_state.and_then(|state| state.get(to)) {
+                // libgit2 can only push from a reference so stage a temporary one
+                let tmp_ref = rollback_tmp_ref_name(to);
+                if Oid::from_str(previous)
+                    .and_then(|oid| {
+                        git_repo
+                            .git_repo
+                            .reference(&tmp_ref, oid, true, "ngit push rollback")
+                    })
+                    .is_err()
+                {
+                    rolled_back = false;
+                    continue;
+                }
+                rollback_refspecs.push(format!("+{tmp_ref}:{to}"));
+                tmp_refs.push(tmp_ref);
+            } else {
+                rollback_refspecs.push(format!(":{to}"));
+            }
+        }
+        if !rollback_refspecs.is_empty() {
+            let _ = term.write_line(
+                format!(
+                    "push: rolling back {}",
+                    get_short_git_server_name(git_repo, git_server_url)
+                )
+                .as_str(),
+            );
+            if push_to_remote(
+                git_repo,
+                git_server_url,
+                decoded_nostr_url,
+                &rollback_refspecs,
+                term,
+            )
+            .is_err()
+            {
+                rolled_back = false;
+            }
+        }
+        for tmp_ref in tmp_refs {
+            if let Ok(mut reference) = git_repo.git_repo.find_reference(&tmp_ref) {
+                let _ = reference.delete();
+            }
+        }
+    }
+    rolled_back
+}
+
+fn rollback_tmp_ref_name(name: &str) -> String {
+    format!("refs/ngit-rollback/{}", name.trim_start_matches("refs/"))
+}
+
+fn print_state_update_failed_instructions(
+    repo_ref: &RepoRef,
+    rolled_back: bool,
+    term: &Term,
+) -> Result<()> {
+    term.write_line(
+        format!(
+            "nostr state update failed: no repository relay accepted the state event ({})",
+            repo_ref
+                .relays
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+        .as_str(),
+    )?;
+    if rolled_back {
+        term.write_line(
+            "git server refs have been restored to their previous values. resolve the relay issue (eg. authentication) and push again",
+        )?;
+    } else {
+        term.write_line(
+            "WARNING: git servers may now be ahead of the nostr state so clones will get the old refs. resolve the relay issue (eg. authentication) and push again to publish the nostr state",
+        )?;
     }
-    Ok((rejected_proposal_refspecs, false))
+    Ok(())
 }
 
 #[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
 async fn process_proposal_refspecs(
     git_repo: &Repo,
     repo_ref: &RepoRef,
     proposal_refspecs: &Vec<String>,
+    proposal_push_options: &ProposalPushOptions,
+    client: &Client,
     user_ref: &UserRef,
     signer: &Arc<dyn NostrSigner>,
     term: &Term,
-) -> Result<(Vec<Event>, Vec<String>)> {
+) -> Result<ProposalEvents> {
     let mut events = vec![];
     let mut rejected_proposal_refspecs = vec![];
+    let mut contributor_pushes = vec![];
     if proposal_refspecs.is_empty() {
-        return Ok((events, rejected_proposal_refspecs));
+        return Ok(ProposalEvents::default());
     }
     let all_proposals = get_all_proposals(git_repo, repo_ref).await?;
     let current_user = &user_ref.public_key;
+    let contributor_push_url = contributor_push_server(git_repo, repo_ref, current_user);
 
+    let mut existing_proposal_ids = HashMap::new();
     for refspec in proposal_refspecs {
+        let (from, to) = refspec_to_from_to(refspec)?;
+        if from.is_empty() {
+            continue;
+        }
+        if let Some((id, _)) =
+            find_proposal_and_patches_by_branch_name(to, &all_proposals, Some(current_user))
+        {
+            existing_proposal_ids.insert(refspec.clone(), *id);
+        } else if let [(id, (proposal, _))] = find_proposals_and_patches_by_ancestry(
+            git_repo,
+            &git_repo.get_commit_or_tip_of_reference(from)?,
+            &all_proposals,
+        )
+        .as_slice()
+        {
+            if proposal_match_by_commits_confirmed(to, proposal, term)? {
+                existing_proposal_ids.insert(refspec.clone(), **id);
+            }
+        }
+    }
+
+    let new_proposal_refspecs = proposal_refspecs
+        .iter()
+        .filter(|refspec| {
+            refspec_to_from_to(refspec).is_ok_and(|(from, _)| {
+                !from.is_empty() && !existing_proposal_ids.contains_key(*refspec)
+            })
+        })
+        .cloned()
+        .collect::<Vec<String>>();
+    if (new_proposal_refspecs.len() > 1
+        || (proposal_push_options.batch_pushes_all_branches && !new_proposal_refspecs.is_empty()))
+        && !bulk_proposals_allowed(git_repo, new_proposal_refspecs.len())?
+    {
+        for refspec in &new_proposal_refspecs {
+            let (_, to) = refspec_to_from_to(refspec)?;
+            println!(
+                "error {to} refusing to create {} new proposals in one push (set nostr.allow-bulk-proposals)",
+                new_proposal_refspecs.len(),
+            );
+            rejected_proposal_refspecs.push(refspec.to_string());
+        }
+    }
+
+    for refspec in proposal_refspecs {
+        if rejected_proposal_refspecs.contains(refspec) {
+            continue;
+        }
         let (from, to) = refspec_to_from_to(refspec).unwrap();
         let tip_of_pushed_branch = git_repo.get_commit_or_tip_of_reference(from)?;
 
-        // this failed to find existing PR from user
-        if let Some((_, (proposal, patches))) =
-            find_proposal_and_patches_by_branch_name(to, &all_proposals, Some(current_user))
+        if let Some((proposal, patches)) = existing_proposal_ids
+            .get(refspec)
+            .and_then(|id| all_proposals.get(id))
         {
             if [repo_ref.maintainers.clone(), vec![proposal.pubkey]]
                 .concat()
@@ -308,6 +771,59 @@ async fn process_proposal_refspecs(
                     let (mut ahead, _) =
                         git_repo.get_commits_ahead_behind(&main_tip, &tip_of_pushed_branch)?;
                     ahead.reverse();
+                    if !pre_send_hook_passed(
+                        git_repo,
+                        repo_ref,
+                        &main_tip,
+                        &tip_of_pushed_branch,
+                        to,
+                    )? {
+                        rejected_proposal_refspecs.push(refspec.to_string());
+                        continue;
+                    }
+                    // patches without a commit id can't be matched so count as rewritten
+                    let superseded_patches = patches
+                        .iter()
+                        .filter(|patch| {
+                            !get_commit_id_from_patch(patch)
+                                .is_ok_and(|id| ahead.iter().any(|c| c.to_string() == id))
+                        })
+                        .collect::<Vec<&Event>>();
+                    let orphaned_comments = get_comments_on_patches(
+                        git_repo,
+                        repo_ref,
+                        client,
+                        &proposal.id,
+                        &superseded_patches,
+                    )
+                    .await?;
+                    if !orphaned_comments.is_empty()
+                        && !proposal_push_options.yes
+                        && !orphaning_comments_confirmed(git_repo, &orphaned_comments, term).await?
+                    {
+                        println!(
+                            "error {to} force push would orphan {} review comments on rewritten commits (push with -o yes to confirm)",
+                            orphaned_comments.len(),
+                        );
+                        rejected_proposal_refspecs.push(refspec.to_string());
+                        continue;
+                    }
+                    let replaces_tags = superseded_patches
+                        .iter()
+                        .map(|patch| {
+                            Tag::custom(
+                                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::E)),
+                                vec![patch.id.to_hex(), String::new(), "replaces".to_string()],
+                            )
+                        })
+                        .collect::<Vec<Tag>>();
+                    let contributor_ref = contributor_ref(
+                        contributor_push_url.as_deref(),
+                        current_user,
+                        to,
+                        &tip_of_pushed_branch,
+                    );
+                    let first_new_event = events.len();
                     for patch in generate_cover_letter_and_patch_events(
                         None,
                         git_repo,
@@ -315,12 +831,22 @@ async fn process_proposal_refspecs(
                         signer,
                         repo_ref,
                         &Some(proposal.id.to_string()),
-                        &[],
+                        &None,
+                        &[replaces_tags, contributor_ref_tags(&contributor_ref)].concat(),
+                        proposal_push_options.expiration,
                     )
                     .await?
                     {
                         events.push(patch);
                     }
+                    if let (Some((_, refspec)), Some(event)) =
+                        (contributor_ref, events.get(first_new_event))
+                    {
+                        contributor_pushes.push(ContributorPush {
+                            refspec,
+                            event_id: event.id,
+                        });
+                    }
                 } else {
                     // fast forward push
                     let tip_patch = patches.first().unwrap();
@@ -331,6 +857,16 @@ async fn process_proposal_refspecs(
                     let (mut ahead, behind) = git_repo
                         .get_commits_ahead_behind(&tip_of_proposal_commit, &tip_of_pushed_branch)?;
                     if behind.is_empty() {
+                        if !pre_send_hook_passed(
+                            git_repo,
+                            repo_ref,
+                            &tip_of_proposal_commit,
+                            &tip_of_pushed_branch,
+                            to,
+                        )? {
+                            rejected_proposal_refspecs.push(refspec.to_string());
+                            continue;
+                        }
                         let thread_id = if let Ok(root_event_id) = get_event_root(tip_patch) {
                             root_event_id
                         } else {
@@ -339,6 +875,14 @@ async fn process_proposal_refspecs(
                         };
                         let mut parent_patch = tip_patch.clone();
                         ahead.reverse();
+                        let contributor_ref = contributor_ref(
+                            contributor_push_url.as_deref(),
+                            current_user,
+                            to,
+                            &tip_of_pushed_branch,
+                        );
+                        let contributor_ref_tags = contributor_ref_tags(&contributor_ref);
+                        let first_new_event = events.len();
                         for (i, commit) in ahead.iter().enumerate() {
                             let new_patch = generate_patch_event(
                                 git_repo,
@@ -354,13 +898,22 @@ async fn process_proposal_refspecs(
                                 )),
                                 None,
                                 &None,
-                                &[],
+                                &contributor_ref_tags,
+                                proposal_push_options.expiration,
                             )
                             .await
                             .context("failed to make patch event from commit")?;
                             events.push(new_patch.clone());
                             parent_patch = new_patch;
                         }
+                        if let (Some((_, refspec)), Some(event)) =
+                            (contributor_ref, events.get(first_new_event))
+                        {
+                            contributor_pushes.push(ContributorPush {
+                                refspec,
+                                event_id: event.id,
+                            });
+                        }
                     } else {
                         // we shouldn't get here
                         term.write_line(
@@ -388,6 +941,17 @@ async fn process_proposal_refspecs(
             let (mut ahead, _) =
                 git_repo.get_commits_ahead_behind(&main_tip, &tip_of_pushed_branch)?;
             ahead.reverse();
+            if !pre_send_hook_passed(git_repo, repo_ref, &main_tip, &tip_of_pushed_branch, to)? {
+                rejected_proposal_refspecs.push(refspec.to_string());
+                continue;
+            }
+            let contributor_ref = contributor_ref(
+                contributor_push_url.as_deref(),
+                current_user,
+                to,
+                &tip_of_pushed_branch,
+            );
+            let first_new_event = events.len();
             for patch in generate_cover_letter_and_patch_events(
                 None,
                 git_repo,
@@ -395,16 +959,356 @@ async fn process_proposal_refspecs(
                 signer,
                 repo_ref,
                 &None,
-                &[],
+                &None,
+                &contributor_ref_tags(&contributor_ref),
+                proposal_push_options.expiration,
             )
             .await?
             {
                 events.push(patch);
             }
+            if let (Some((_, refspec)), Some(event)) =
+                (contributor_ref, events.get(first_new_event))
+            {
+                contributor_pushes.push(ContributorPush {
+                    refspec,
+                    event_id: event.id,
+                });
+            }
+        }
+    }
+
+    Ok(ProposalEvents {
+        events,
+        rejected_proposal_refspecs,
+        contributor_push_url,
+        contributor_pushes,
+    })
+}
+
+/// run the pre-send hook for `base..tip`, telling git `to` was rejected when
+/// it fails
+fn pre_send_hook_passed(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    base: &Sha1Hash,
+    tip: &Sha1Hash,
+    to: &str,
+) -> Result<bool> {
+    if base.eq(tip) {
+        return Ok(true);
+    }
+    if let Err(error) = run_pre_send_hook(
+        git_repo,
+        &format!("{base}..{tip}"),
+        &repo_ref.coordinate_with_hint(),
+    ) {
+        eprintln!("{error}");
+        println!("error {to} rejected by pre-send hook");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// where a contributor's `pr/*` branch is pushed on git servers listed in the
+/// announcement's contributor-push tag
+fn contributor_ref_name(public_key: &PublicKey, to: &str) -> Result<String> {
+    Ok(format!(
+        "refs/heads/contrib/{}/{}",
+        public_key.to_bech32()?,
+        to.trim_start_matches("refs/heads/pr/"),
+    ))
+}
+
+/// the first git server in the announcement's contributor-push tag that can
+/// be reached. the patch events point to it before the commits are pushed, so
+/// a server that is down is skipped. none for maintainers, who push to the git
+/// servers directly
+fn contributor_push_server(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    public_key: &PublicKey,
+) -> Option<String> {
+    if repo_ref.maintainers.contains(public_key) {
+        return None;
+    }
+    let proxy = get_proxy(&Some(git_repo), ProxyUse::GitServers)
+        .ok()
+        .flatten()
+        .map(|proxy| proxy.value);
+    repo_ref
+        .contributor_push
+        .iter()
+        .find(|url| git_server_reachable(git_repo, url, &proxy))
+        .cloned()
+}
+
+fn git_server_reachable(git_repo: &Repo, url: &str, proxy: &Option<String>) -> bool {
+    if ensure_onion_url_has_proxy(url, proxy, ProxyUse::GitServers).is_err() {
+        return false;
+    }
+    let Ok(mut remote) = git_repo.git_repo.remote_anonymous(url) else {
+        return false;
+    };
+    remote
+        .connect_auth(git2::Direction::Fetch, None, Some(git_proxy_options(proxy)))
+        .is_ok()
+}
+
+/// a `clone-url` tag with the server and contributor ref for the patch events,
+/// and the refspec to push there once they are accepted
+fn contributor_ref(
+    contributor_push_url: Option<&str>,
+    public_key: &PublicKey,
+    to: &str,
+    tip: &Sha1Hash,
+) -> Option<(Tag, String)> {
+    let url = contributor_push_url?;
+    let ref_name = contributor_ref_name(public_key, to).ok()?;
+    Some((
+        Tag::custom(
+            TagKind::Custom(std::borrow::Cow::Borrowed("clone-url")),
+            vec![url.to_string(), ref_name.clone()],
+        ),
+        format!("+{tip}:{ref_name}"),
+    ))
+}
+
+fn contributor_ref_tags(contributor_ref: &Option<(Tag, String)>) -> Vec<Tag> {
+    contributor_ref.iter().map(|(tag, _)| tag.clone()).collect()
+}
+
+/// push proposal commits to the contributor's namespace on `url`, in one push
+/// signed once, after relays accepted the patch events that point to them so a
+/// grasp server can find those events. a refusal leaves the proposal patch
+/// only
+async fn push_to_contributor_namespace(
+    git_repo: &Repo,
+    url: &str,
+    refspecs: &[String],
+    signer: &Arc<dyn NostrSigner>,
+    term: &Term,
+) {
+    if refspecs.is_empty() {
+        return;
+    }
+    let proxy = get_proxy(&Some(git_repo), ProxyUse::GitServers)
+        .ok()
+        .flatten()
+        .map(|proxy| proxy.value);
+    let auth_header = if url.starts_with("https://") || url.starts_with("http://") {
+        let Ok(auth_header) = nip98_authorization_header(url, signer).await else {
+            return;
+        };
+        Some(auth_header)
+    } else {
+        None
+    };
+    let server_name = get_short_git_server_name(git_repo, url);
+    match push_to_contributor_namespace_url(git_repo, url, refspecs, auth_header.as_deref(), &proxy)
+    {
+        Ok(()) => {
+            for refspec in refspecs {
+                let (_, ref_name) = refspec.split_once(':').unwrap_or_default();
+                let _ = term.write_line(&format!(
+                    "push: {server_name} accepted proposal commits as {ref_name}",
+                ));
+            }
+        }
+        Err(error) => {
+            let _ = term.write_line(&format!(
+                "push: {server_name} refused proposal commits so only the patches are available: {error}",
+            ));
         }
     }
+}
 
-    Ok((events, rejected_proposal_refspecs))
+/// NIP-98 http auth header grasp servers accept in place of git credentials.
+/// git sends the same headers with every request of a push so `u` is the
+/// repository url rather than a specific endpoint
+async fn nip98_authorization_header(url: &str, signer: &Arc<dyn NostrSigner>) -> Result<String> {
+    let event = sign_event(
+        EventBuilder::http_auth(HttpData::new(nostr::Url::parse(url)?, HttpMethod::POST)),
+        signer,
+    )
+    .await?;
+    Ok(format!(
+        "Authorization: Nostr {}",
+        BASE64_STANDARD.encode(event.as_json())
+    ))
+}
+
+/// push without progress output or prompting for credentials. unlike
+/// `push_to_remote_url`, a ref the server rejects is an error
+fn push_to_contributor_namespace_url(
+    git_repo: &Repo,
+    url: &str,
+    refspecs: &[String],
+    auth_header: Option<&str>,
+    proxy: &Option<String>,
+) -> Result<()> {
+    ensure_onion_url_has_proxy(url, proxy, ProxyUse::GitServers)?;
+    let mut remote = git_repo.git_repo.remote_anonymous(url)?;
+    let mut rejections = vec![];
+    let mut remote_callbacks = git2::RemoteCallbacks::new();
+    remote_callbacks.push_update_reference(|ref_name, status| {
+        if let Some(status) = status {
+            rejections.push(format!("{ref_name} rejected: {status}"));
+        }
+        Ok(())
+    });
+    let mut push_options = git2::PushOptions::new();
+    push_options.remote_callbacks(remote_callbacks);
+    push_options.proxy_options(git_proxy_options(proxy));
+    if let Some(auth_header) = auth_header {
+        push_options.custom_headers(&[auth_header]);
+    }
+    remote.push(refspecs, Some(&mut push_options))?;
+    drop(push_options);
+    let _ = remote.disconnect();
+    if !rejections.is_empty() {
+        bail!(rejections.join(", "));
+    }
+    Ok(())
+}
+
+/// review comments (kind 1111) and patch replies e-tagging `patches`. events
+/// in the proposal's own patch chains aren't comments so are excluded
+async fn get_comments_on_patches(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    client: &Client,
+    proposal_id: &EventId,
+    patches: &[&Event],
+) -> Result<Vec<Event>> {
+    if patches.is_empty() {
+        return Ok(vec![]);
+    }
+    let git_repo_path = git_repo.get_path()?;
+    let filter = nostr::Filter::default()
+        .kinds([Kind::Custom(1111), Kind::GitPatch])
+        .events(patches.iter().map(|patch| patch.id));
+    let (repo_relays, _) =
+        get_repo_relays(Some(git_repo_path), repo_ref, client.get_fallback_relays()).await;
+    let mut comments = client
+        .get_events(
+            repo_relays
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            vec![filter.clone()],
+        )
+        .await
+        .unwrap_or_default();
+    comments.extend(get_events_from_local_cache(git_repo_path, vec![filter]).await?);
+    let mut seen = HashSet::new();
+    comments.retain(|comment| {
+        seen.insert(comment.id)
+            && !(comment.kind == Kind::GitPatch
+                && (comment.id == *proposal_id
+                    || get_event_root(comment).is_ok_and(|root| root == *proposal_id)))
+    });
+    comments.sort_by_key(|comment| comment.created_at);
+    Ok(comments)
+}
+
+/// lists the comments and asks for confirmation on the terminal
+async fn orphaning_comments_confirmed(
+    git_repo: &Repo,
+    comments: &[Event],
+    term: &Term,
+) -> Result<bool> {
+    term.write_line(
+        "force push rewrites commits with review comments that will no longer point at any branch:",
+    )?;
+    for comment in comments {
+        let author = if let Ok(user_ref) =
+            login::user::get_user_ref_from_cache(Some(git_repo.get_path()?), &comment.pubkey).await
+        {
+            user_ref.metadata.name
+        } else {
+            comment.pubkey.to_bech32()?
+        };
+        let excerpt = comment.content.lines().next().unwrap_or_default();
+        let excerpt = if excerpt.chars().count() > 60 {
+            format!("{}...", excerpt.chars().take(60).collect::<String>())
+        } else {
+            excerpt.to_string()
+        };
+        term.write_line(format!("  {author}: \"{excerpt}\"").as_str())?;
+    }
+    if !cli_interactor::prompts_allowed() {
+        return Ok(false);
+    }
+    Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "force push anyway and orphan {} review comments?",
+                comments.len()
+            ))
+            .with_default(false),
+    )
+}
+
+/// a branch whose name doesn't identify a proposal but builds on one is
+/// pushed as an update to it once the user confirms. without a terminal it
+/// becomes a new proposal
+fn proposal_match_by_commits_confirmed(to: &str, proposal: &Event, term: &Term) -> Result<bool> {
+    let title = event_to_cover_letter(proposal).map_or(String::new(), |cl| cl.title);
+    term.write_line(
+        format!(
+            "{to} builds on proposal \"{title}\" ({}) but its name doesn't identify it",
+            &proposal.id.to_hex()[..8],
+        )
+        .as_str(),
+    )?;
+    if !cli_interactor::prompts_allowed() {
+        term.write_line("pushing as a new proposal")?;
+        return Ok(false);
+    }
+    Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!("push {to} as an update to \"{title}\"?"))
+            .with_default(true),
+    )
+}
+
+/// true when the batch updates every local branch, as `git push --all` and
+/// `git push --mirror` do
+fn batch_pushes_all_local_branches(git_repo: &Repo, refspecs: &[String]) -> Result<bool> {
+    let local_branches = git_repo.get_local_branch_names()?;
+    Ok(local_branches.len() > 1
+        && local_branches.iter().all(|name| {
+            let branch_ref = format!("refs/heads/{name}");
+            refspecs.iter().any(|refspec| {
+                refspec_to_from_to(refspec).is_ok_and(|(from, _)| from == branch_ref)
+            })
+        }))
+}
+
+/// creating several proposals in one push is often `git push --all`
+/// publishing private `pr/` branches by accident. allowed when
+/// `nostr.allow-bulk-proposals` is set or the user confirms on the terminal
+fn bulk_proposals_allowed(git_repo: &Repo, count: usize) -> Result<bool> {
+    if git_repo
+        .get_git_config_item("nostr.allow-bulk-proposals", None)?
+        .map(|v| parse_git_config_bool(&v))
+        .transpose()
+        .context("invalid git config item nostr.allow-bulk-proposals")?
+        .unwrap_or(false)
+    {
+        return Ok(true);
+    }
+    if !cli_interactor::prompts_allowed() {
+        return Ok(false);
+    }
+    Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "publish {count} pr/ branches as new public proposals?"
+            ))
+            .with_default(false),
+    )
 }
 
 fn push_to_remote(
@@ -417,6 +1321,8 @@ fn push_to_remote(
     let server_url = git_server_url.parse::<CloneUrl>()?;
     let protocols_to_attempt = get_write_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let proxy = get_proxy(&Some(git_repo), ProxyUse::GitServers)?.map(|proxy| proxy.value);
+
     let mut failed_protocols = vec![];
     let mut success = false;
 
@@ -425,7 +1331,24 @@ fn push_to_remote(
 
         let formatted_url = server_url.format_as(protocol, &decoded_nostr_url.user)?;
 
-        if let Err(error) = push_to_remote_url(git_repo, &formatted_url, remote_refspecs, term) {
+        if let Err(error) =
+            push_to_remote_url(git_repo, &formatted_url, remote_refspecs, &proxy, term).or_else(
+                |error| {
+                    if should_fallback_to_shell_git(git_repo, &formatted_url, &error) {
+                        report_shell_git_fallback(&Direction::Push, &formatted_url, &error, term);
+                        push_to_remote_url_with_shell_git(
+                            git_repo,
+                            &formatted_url,
+                            remote_refspecs,
+                            &proxy,
+                            term,
+                        )
+                    } else {
+                        Err(error)
+                    }
+                },
+            )
+        {
             term.write_line(
                 format!("push: {formatted_url} failed over {protocol}: {error}").as_str(),
             )?;
@@ -464,8 +1387,10 @@ fn push_to_remote_url(
     git_repo: &Repo,
     git_server_url: &str,
     remote_refspecs: &[String],
+    proxy: &Option<String>,
     term: &Term,
 ) -> Result<()> {
+    ensure_onion_url_has_proxy(git_server_url, proxy, ProxyUse::GitServers)?;
     let git_config = git_repo.git_repo.config()?;
     let mut git_server_remote = git_repo.git_repo.remote_anonymous(git_server_url)?;
     let auth = GitAuthenticator::default();
@@ -508,6 +1433,8 @@ fn push_to_remote_url(
                 } else if update.src().is_zero() {
                     if update.dst_refname().unwrap_or("").contains("refs/tags") {
                         format!("push: * [new tag]         {dst_refname}")
+                    } else if dst_refname.starts_with("refs/notes/") {
+                        format!("push: * [new ref]         {dst_refname}")
                     } else {
                         format!("push: * [new branch]      {dst_refname}")
                     }
@@ -560,11 +1487,85 @@ fn push_to_remote_url(
         }
     });
     push_options.remote_callbacks(remote_callbacks);
+    push_options.proxy_options(git_proxy_options(proxy));
     git_server_remote.push(remote_refspecs, Some(&mut push_options))?;
     let _ = git_server_remote.disconnect();
     Ok(())
 }
 
+/// a ref update reported by `git push --porcelain`
+#[derive(Debug, PartialEq)]
+struct PorcelainRefUpdate {
+    flag: char,
+    to: String,
+    summary: String,
+}
+
+/// ref lines are `<flag>\t<from>:<to>\t<summary>`. the `To <url>` and `Done`
+/// lines are skipped
+fn parse_push_porcelain(stdout: &str) -> Vec<PorcelainRefUpdate> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let mut flag = parts.next()?.chars();
+            let (Some(flag), None) = (flag.next(), flag.next()) else {
+                return None;
+            };
+            let (_, to) = parts.next()?.split_once(':')?;
+            Some(PorcelainRefUpdate {
+                flag,
+                to: to.to_string(),
+                summary: parts.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// used when libgit2 couldn't reach an http(s) git server. as with libgit2,
+/// refs the server rejects are reported as warnings rather than failing
+fn push_to_remote_url_with_shell_git(
+    git_repo: &Repo,
+    git_server_url: &str,
+    remote_refspecs: &[String],
+    proxy: &Option<String>,
+    term: &Term,
+) -> Result<()> {
+    // pre-push hooks already ran for the push that invoked us
+    let output = shell_git_command(git_repo, git_server_url, false, proxy)
+        .args(["push", "--porcelain", "--no-verify", SHELL_GIT_REMOTE])
+        .args(remote_refspecs)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to run git push")?;
+    let updates = parse_push_porcelain(&String::from_utf8_lossy(&output.stdout));
+    if updates.is_empty() {
+        bail!("git push failed");
+    }
+    for update in updates {
+        let to = update
+            .to
+            .replace("refs/heads/", "")
+            .replace("refs/tags/", "tags/");
+        if update.flag == '!' {
+            term.write_line(
+                format!(
+                    "WARNING: {} failed to push {to} error: {}",
+                    get_short_git_server_name(git_repo, git_server_url),
+                    update.summary,
+                )
+                .as_str(),
+            )?;
+        } else {
+            term.write_line(
+                format!("push: {} {: <17} {to}", update.flag, update.summary).as_str(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
 #[allow(clippy::cast_precision_loss)]
 #[allow(clippy::float_cmp)]
 #[allow(clippy::needless_pass_by_value)]
@@ -877,6 +1878,31 @@ fn create_rejected_refspecs_and_remotes_refspecs(
     Ok((rejected_refspecs, remotes_refspecs_without_rejected))
 }
 
+/// HEAD for the state event as the first git server to report it has it, so
+/// a HEAD detached at a commit is published as that commit id. otherwise the
+/// existing HEAD or, when the state doesn't have one yet, the local HEAD if
+/// it is a branch. a detached local HEAD is usually mid rebase so is ignored
+fn head_for_state(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    existing_state: &HashMap<String, String>,
+    list_outputs: &HashMap<String, HashMap<String, String>>,
+) -> Option<String> {
+    repo_ref
+        .git_server
+        .iter()
+        .find_map(|url| list_outputs.get(url)?.get("HEAD").cloned())
+        .or_else(|| existing_state.get("HEAD").cloned())
+        .or_else(|| {
+            git_repo
+                .git_repo
+                .find_reference("HEAD")
+                .ok()?
+                .symbolic_target()
+                .map(|branch| format!("ref: {branch}"))
+        })
+}
+
 fn generate_updated_state(
     git_repo: &Repo,
     existing_state: &HashMap<String, String>,
@@ -922,6 +1948,7 @@ fn generate_updated_state(
             );
         }
     }
+    point_head_at_existing_branch(&mut new_state);
     Ok(new_state)
 }
 
@@ -994,6 +2021,55 @@ async fn get_maintainers_yaml_update(
     Ok(None)
 }
 
+/// an update to our announcement adding the root commit of the first branch
+/// pushed, when the repository was announced before it had any commits
+async fn get_root_commit_update(
+    term: &console::Term,
+    repo_ref: &RepoRef,
+    git_repo: &Repo,
+    public_key: &PublicKey,
+    signer: &Arc<dyn NostrSigner>,
+    refspecs_to_git_server: &[String],
+) -> Result<Option<Event>> {
+    if !repo_ref.root_commit.is_empty() {
+        return Ok(None);
+    }
+    let Some(baseline) = repo_ref
+        .events
+        .iter()
+        .find(|(coordinate, _)| coordinate.public_key.eq(public_key))
+        .map(|(_, event)| event)
+    else {
+        return Ok(None);
+    };
+    let mut pushed_branches = vec![];
+    for refspec in refspecs_to_git_server {
+        let (from, to) = refspec_to_from_to(refspec)?;
+        if !from.is_empty() && to.starts_with("refs/heads/") {
+            pushed_branches.push((from, to));
+        }
+    }
+    // prefer main or master over other branches pushed with it
+    pushed_branches.sort_by_key(|(_, to)| !["refs/heads/main", "refs/heads/master"].contains(to));
+    let Some((from, _)) = pushed_branches.first() else {
+        return Ok(None);
+    };
+    let root_commit =
+        git_repo.get_root_commit_of(&git_repo.get_commit_or_tip_of_reference(from)?)?;
+    let mut updated = RepoRef::try_from((baseline.clone(), None))?;
+    updated.root_commit = root_commit.to_string();
+    term.write_line(
+        format!(
+            "repository was announced before its first commit so adding root commit {} to the announcement",
+            oid_to_shorthand_string(sha1_to_oid(&root_commit)?)?,
+        )
+        .as_str(),
+    )?;
+    Ok(Some(
+        announcement_from_tags(merge_announcement_tags(baseline, &updated), signer).await?,
+    ))
+}
+
 async fn get_merged_status_events(
     term: &console::Term,
     decoded_nostr_url: &NostrUrlDecoded,
@@ -1001,6 +2077,7 @@ async fn get_merged_status_events(
     git_repo: &Repo,
     signer: &Arc<dyn NostrSigner>,
     refspecs_to_git_server: &Vec<String>,
+    expiration: Option<Timestamp>,
 ) -> Result<Vec<Event>> {
     let mut events = vec![];
     for refspec in refspecs_to_git_server {
@@ -1236,6 +2313,7 @@ async fn create_merge_events(
                     && merged_patches
                         .values()
                         .any(|m| matches!(m, MergedPRCommitType::PatchApplied { .. })),
+                expiration,
             )
             .await?,
         );
@@ -1258,6 +2336,7 @@ async fn create_merge_status(
     merge_commits: Vec<Sha1Hash>,
     merged_patches: Vec<EventId>,
     applied: bool,
+    expiration: Option<Timestamp>,
 ) -> Result<Event> {
     let mut public_keys = repo_ref
         .maintainers
@@ -1338,6 +2417,7 @@ async fn create_merge_status(
                         )))
                     })
                     .collect::<Vec<Tag>>(),
+                expiration_tags(expiration),
             ]
             .concat(),
         ),
@@ -1517,6 +2597,7 @@ trait BuildRepoState {
         identifier: String,
         state: HashMap<String, String>,
         signer: &Arc<dyn NostrSigner>,
+        created_at: Timestamp,
     ) -> Result<RepoState>;
 }
 impl BuildRepoState for RepoState {
@@ -1524,6 +2605,7 @@ impl BuildRepoState for RepoState {
         identifier: String,
         state: HashMap<String, String>,
         signer: &Arc<dyn NostrSigner>,
+        created_at: Timestamp,
     ) -> Result<RepoState> {
         let mut tags = vec![Tag::identifier(identifier.clone())];
         for (name, value) in &state {
@@ -1531,7 +2613,12 @@ impl BuildRepoState for RepoState {
                 value.clone(),
             ]));
         }
-        let event = sign_event(EventBuilder::new(STATE_KIND, "").tags(tags), signer).await?;
+        let event = sign_event_at(
+            EventBuilder::new(STATE_KIND, "").tags(tags),
+            created_at,
+            signer,
+        )
+        .await?;
         Ok(RepoState {
             identifier,
             state,
@@ -1553,4 +2640,34 @@ mod tests {
             assert_eq!(from, "testing");
         }
     }
+
+    mod parse_push_porcelain {
+        use super::*;
+
+        #[test]
+        fn ref_lines_parsed_and_others_skipped() {
+            assert_eq!(
+                parse_push_porcelain(
+                    "To http://example.com/repo.git\n*\trefs/heads/vnext:refs/heads/vnext\t[new branch]\n!\trefs/heads/main:refs/heads/main\t[rejected] (non-fast-forward)\n-\t:refs/heads/old\t[deleted]\nDone\n"
+                ),
+                vec![
+                    PorcelainRefUpdate {
+                        flag: '*',
+                        to: "refs/heads/vnext".to_string(),
+                        summary: "[new branch]".to_string(),
+                    },
+                    PorcelainRefUpdate {
+                        flag: '!',
+                        to: "refs/heads/main".to_string(),
+                        summary: "[rejected] (non-fast-forward)".to_string(),
+                    },
+                    PorcelainRefUpdate {
+                        flag: '-',
+                        to: "refs/heads/old".to_string(),
+                        summary: "[deleted]".to_string(),
+                    },
+                ]
+            );
+        }
+    }
 }
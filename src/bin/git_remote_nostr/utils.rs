@@ -3,7 +3,9 @@ use std::{
     collections::HashMap,
     fmt,
     io::{self, Stdin},
+    process::Command,
     str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
 };
 
 use anyhow::{Context, Result, bail};
@@ -13,17 +15,43 @@ use ngit::{
         get_all_proposal_patch_events_from_cache, get_events_from_local_cache,
         get_proposals_and_revisions_from_cache,
     },
+    config::PrsAsRefs,
     git::{
         Repo, RepoActions,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
+        str_to_sha1,
     },
     git_events::{
-        event_is_revision_root, get_most_recent_patch_with_ancestors,
+        event_is_revision_root, get_commit_id_from_patch, get_most_recent_patch_with_ancestors,
         is_event_proposal_root_for_branch, status_kinds,
     },
     repo_ref::RepoRef,
+    repo_state::find_rewound_branches,
 };
-use nostr_sdk::{Event, EventId, Kind, PublicKey, Url};
+use nostr_sdk::{Event, EventId, Kind, PublicKey, Url, hashes::sha1::Hash as Sha1Hash};
+
+/// explain branches force pushed on nostr since they were last fetched from
+/// `remote`, rather than leaving users to decode git's non-fast-forward errors
+pub fn warn_about_rewound_branches(
+    term: &console::Term,
+    git_repo: &Repo,
+    remote: Option<&str>,
+    state: &HashMap<String, String>,
+) -> Result<()> {
+    let Some(remote) = remote else {
+        return Ok(());
+    };
+    for rewound in find_rewound_branches(git_repo, remote, state) {
+        let mut lines = rewound.guidance().into_iter();
+        if let Some(first) = lines.next() {
+            term.write_line(&format!("WARNING: {first}"))?;
+        }
+        for line in lines {
+            term.write_line(&line)?;
+        }
+    }
+    Ok(())
+}
 
 pub fn get_short_git_server_name(git_repo: &Repo, url: &str) -> std::string::String {
     if let Ok(name) = get_remote_name_by_url(&git_repo.git_repo, url) {
@@ -98,6 +126,18 @@ pub async fn get_open_or_draft_proposals(
     git_repo: &Repo,
     repo_ref: &RepoRef,
 ) -> Result<HashMap<EventId, (Event, Vec<Event>)>> {
+    Ok(get_open_or_draft_proposals_with_status(git_repo, repo_ref)
+        .await?
+        .into_iter()
+        .map(|(id, (proposal, patches, _))| (id, (proposal, patches)))
+        .collect())
+}
+
+/// open or draft proposals with their patches and status kind
+pub async fn get_open_or_draft_proposals_with_status(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+) -> Result<HashMap<EventId, (Event, Vec<Event>, Kind)>> {
     let git_repo_path = git_repo.get_path()?;
     let proposals: Vec<nostr::Event> =
         get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
@@ -144,8 +184,10 @@ pub async fn get_open_or_draft_proposals(
                 if let Ok(most_recent_proposal_patch_chain) =
                     get_most_recent_patch_with_ancestors(commits_events.clone())
                 {
-                    open_or_draft_proposals
-                        .insert(proposal.id, (proposal, most_recent_proposal_patch_chain));
+                    open_or_draft_proposals.insert(
+                        proposal.id,
+                        (proposal, most_recent_proposal_patch_chain, status),
+                    );
                 }
             }
         }
@@ -182,8 +224,33 @@ pub async fn get_all_proposals(
     Ok(all_proposals)
 }
 
+/// the ref a proposal branch, eg. `pr/name(1234abcd)`, is listed as. None when
+/// proposals aren't listed
+pub fn proposal_refstr(branch_name: &str, prs_as_refs: PrsAsRefs) -> Option<String> {
+    match prs_as_refs {
+        PrsAsRefs::Heads => Some(format!("refs/heads/{branch_name}")),
+        PrsAsRefs::Prs => Some(format!(
+            "refs/prs/{}",
+            branch_name.trim_start_matches("pr/")
+        )),
+        PrsAsRefs::None => None,
+    }
+}
+
+/// the proposal branch name of a ref listed by `proposal_refstr` in any mode
+pub fn proposal_branch_name_from_refstr(refstr: &str) -> Option<String> {
+    if let Some(name) = refstr.strip_prefix("refs/prs/") {
+        Some(format!("pr/{name}"))
+    } else {
+        refstr
+            .strip_prefix("refs/heads/")
+            .filter(|name| name.starts_with("pr/"))
+            .map(String::from)
+    }
+}
+
 pub fn find_proposal_and_patches_by_branch_name<'a>(
-    refstr: &'a str,
+    refstr: &str,
     proposals: &'a HashMap<EventId, (Event, Vec<Event>)>,
     current_user: Option<&PublicKey>,
 ) -> Option<(&'a EventId, &'a (Event, Vec<Event>))> {
@@ -192,6 +259,46 @@ pub fn find_proposal_and_patches_by_branch_name<'a>(
     })
 }
 
+/// proposals that `tip` builds on when its branch name doesn't identify one,
+/// eg. a branch renamed after checking out a `pr/` ref. a proposal that
+/// another matching proposal builds on, or that is already on the main
+/// branch, isn't returned
+pub fn find_proposals_and_patches_by_ancestry<'a>(
+    git_repo: &Repo,
+    tip: &Sha1Hash,
+    proposals: &'a HashMap<EventId, (Event, Vec<Event>)>,
+) -> Vec<(&'a EventId, &'a (Event, Vec<Event>))> {
+    let main_tip = git_repo
+        .get_main_or_master_branch()
+        .ok()
+        .map(|(_, tip)| tip);
+    let is_same_or_ancestor = |commit: &Sha1Hash, of: &Sha1Hash| {
+        commit == of || git_repo.ancestor_of(of, commit).unwrap_or(false)
+    };
+    let candidates = proposals
+        .iter()
+        .filter_map(|(id, proposal_and_patches)| {
+            let tip_patch = proposal_and_patches.1.first()?;
+            let proposal_tip = str_to_sha1(&get_commit_id_from_patch(tip_patch).ok()?).ok()?;
+            (is_same_or_ancestor(&proposal_tip, tip)
+                && !main_tip.is_some_and(|main_tip| is_same_or_ancestor(&proposal_tip, &main_tip)))
+            .then_some(((id, proposal_and_patches), proposal_tip))
+        })
+        .collect::<Vec<_>>();
+    candidates
+        .iter()
+        .filter(|(_, proposal_tip)| {
+            !candidates.iter().any(|(_, other_tip)| {
+                other_tip != proposal_tip
+                    && git_repo
+                        .ancestor_of(other_tip, proposal_tip)
+                        .unwrap_or(false)
+            })
+        })
+        .map(|(entry, _)| *entry)
+        .collect()
+}
+
 pub fn join_with_and<T: ToString>(items: &[T]) -> String {
     match items.len() {
         0 => String::new(),
@@ -354,6 +461,96 @@ pub fn set_protocol_preference(
     )
 }
 
+static VERBOSITY: AtomicU8 = AtomicU8::new(1);
+
+/// level set by git with `option verbosity`. 0 with --quiet and 1 by default
+pub fn set_verbosity(verbosity: u8) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+pub fn get_verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.parse::<CloneUrl>()
+        .is_ok_and(|url| [ServerProtocol::Http, ServerProtocol::Https].contains(&url.protocol()))
+}
+
+/// libgit2 failed to talk to an http(s) server rather than being refused, so
+/// system git, which honours the user's proxy and credential helper setup,
+/// is worth a try. disabled with `nostr.shell-git-fallback false`
+pub fn should_fallback_to_shell_git(
+    git_repo: &Repo,
+    git_server_url: &str,
+    error: &anyhow::Error,
+) -> bool {
+    is_http_url(git_server_url)
+        && error_is_libgit2_transport_failure(error)
+        && !git_repo
+            .get_git_config_item("nostr.shell-git-fallback", None)
+            .ok()
+            .flatten()
+            .is_some_and(|v| v.eq("false"))
+}
+
+/// temporary remote given to system git with `-c` so nothing is written to
+/// the git config
+pub static SHELL_GIT_REMOTE: &str = "ngit-shell-git-fallback";
+
+/// `git` with `SHELL_GIT_REMOTE` pointing at `git_server_url`. it inherits
+/// the environment so proxies and credential helpers apply
+pub fn shell_git_command(
+    git_repo: &Repo,
+    git_server_url: &str,
+    dont_authenticate: bool,
+    proxy: &Option<String>,
+) -> Command {
+    let mut command = Command::new("git");
+    command
+        .arg("-c")
+        .arg(format!("remote.{SHELL_GIT_REMOTE}.url={git_server_url}"));
+    if let Some(proxy) = proxy {
+        command.arg("-c").arg(format!("http.proxy={proxy}"));
+    }
+    if dont_authenticate {
+        command
+            .args(["-c", "credential.helper="])
+            .env("GIT_TERMINAL_PROMPT", "0");
+    }
+    command.arg("--git-dir").arg(git_repo.git_repo.path());
+    command
+}
+
+pub fn report_shell_git_fallback(
+    direction: &Direction,
+    git_server_url: &str,
+    error: &anyhow::Error,
+    term: &console::Term,
+) {
+    if get_verbosity() > 0 {
+        let _ = term.write_line(
+            format!(
+                "{direction}: libgit2 failed to reach {git_server_url}: {error}. retrying with system git"
+            )
+            .as_str(),
+        );
+    }
+}
+
+fn error_is_libgit2_transport_failure(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<git2::Error>().is_some_and(|e| {
+        e.code() != git2::ErrorCode::Auth
+            && [
+                git2::ErrorClass::Net,
+                git2::ErrorClass::Http,
+                git2::ErrorClass::Ssl,
+                git2::ErrorClass::Os,
+            ]
+            .contains(&e.class())
+    }) && !error_might_be_authentication_related(error)
+}
+
 /// to understand whether to try over another protocol
 pub fn fetch_or_list_error_is_not_authentication_failure(error: &anyhow::Error) -> bool {
     !error_might_be_authentication_related(error)
@@ -422,4 +619,63 @@ mod tests {
             assert_eq!(join_with_and(&items), "one, two, three, four and five");
         }
     }
+    mod proposal_branch_name_from_refstr {
+        use super::*;
+
+        #[test]
+        fn round_trips_proposal_refstr_in_each_listing_mode() {
+            for prs_as_refs in [PrsAsRefs::Heads, PrsAsRefs::Prs] {
+                let refstr = proposal_refstr("pr/feature(1234abcd)", prs_as_refs).unwrap();
+                assert_eq!(
+                    proposal_branch_name_from_refstr(&refstr),
+                    Some("pr/feature(1234abcd)".to_string())
+                );
+            }
+        }
+
+        #[test]
+        fn none_for_other_refs() {
+            assert_eq!(proposal_branch_name_from_refstr("refs/heads/main"), None);
+            assert_eq!(proposal_branch_name_from_refstr("refs/tags/pr/v1"), None);
+        }
+    }
+    mod error_is_libgit2_transport_failure {
+        use super::*;
+
+        fn git2_error(code: git2::ErrorCode, class: git2::ErrorClass, msg: &str) -> anyhow::Error {
+            git2::Error::new(code, class, msg).into()
+        }
+
+        #[test]
+        fn network_and_tls_errors_are() {
+            for class in [git2::ErrorClass::Net, git2::ErrorClass::Ssl] {
+                assert!(error_is_libgit2_transport_failure(&git2_error(
+                    git2::ErrorCode::GenericError,
+                    class,
+                    "unexpected http response",
+                )));
+            }
+        }
+
+        #[test]
+        fn authentication_failures_arent() {
+            assert!(!error_is_libgit2_transport_failure(&git2_error(
+                git2::ErrorCode::Auth,
+                git2::ErrorClass::Http,
+                "remote authentication required",
+            )));
+            assert!(!error_is_libgit2_transport_failure(&git2_error(
+                git2::ErrorCode::GenericError,
+                git2::ErrorClass::Http,
+                "unexpected http status code: 404 not found",
+            )));
+        }
+
+        #[test]
+        fn other_errors_arent() {
+            assert!(!error_is_libgit2_transport_failure(&anyhow::anyhow!(
+                "no ssh keys found"
+            )));
+        }
+    }
 }
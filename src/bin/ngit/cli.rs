@@ -1,6 +1,6 @@
 use anyhow::{Result, bail};
 use clap::{Parser, Subcommand};
-use ngit::login::SignerInfo;
+use ngit::{login::SignerInfo, output::ColorChoice};
 
 use crate::sub_commands;
 
@@ -8,12 +8,13 @@ use crate::sub_commands;
 #[command(
     author,
     version,
-    help_template = "{name} {version}\nnostr plugin for git\n - clone a nostr repository, or add as a remote, by using the url format nostr://pub123/identifier\n - remote branches beginning with `pr/` are open PRs from contributors; `ngit list` can be used to view all PRs\n - to open a PR, push a branch with the prefix `pr/` or use `ngit send` for advanced options\n- publish a repository to nostr with `ngit init`\n\n{usage}\n{all-args}"
+    help_template = "{name} {version}\nnostr plugin for git\n - clone a nostr repository, or add as a remote, by using the url format nostr://pub123/identifier\n - remote branches beginning with `pr/` are open PRs from contributors; `ngit list` can be used to view all PRs\n - to open a PR, push a branch with the prefix `pr/` or use `ngit send` for advanced options\n- publish a repository to nostr with `ngit init`\n\n{usage}\n{all-args}\n\n{after-help}",
+    after_help = ngit::error::EXIT_CODES_HELP
 )]
 #[command(propagate_version = true)]
 pub struct Cli {
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
     /// remote signer address
     #[arg(long, global = true, hide = true)]
     pub bunker_uri: Option<String>,
@@ -29,6 +30,34 @@ pub struct Cli {
     /// disable spinner animations
     #[arg(long, action, hide = true)]
     pub disable_cli_spinners: bool,
+    /// answer yes to confirmations that offer --yes, take the default of
+    /// every other prompt and fail when one has no default
+    #[arg(short, long, action, global = true, visible_alias = "non-interactive")]
+    pub yes: bool,
+    /// sign events using relay-inferred time when the local clock is behind
+    #[arg(long, action, global = true)]
+    pub fix_timestamp: bool,
+    /// fetch complete history rather than the most recent events of each
+    /// kind. see nostr.fetch-max-events-per-kind
+    #[arg(long, action, global = true)]
+    pub fetch_all: bool,
+    /// print extra detail, eg. when requests are split to fit relay limits
+    #[arg(short, long, action, global = true)]
+    pub verbose: bool,
+    /// when to use colors, overriding git config nostr.color and the config
+    /// file. defaults to auto, which honors NO_COLOR and CLICOLOR_FORCE
+    #[arg(long, global = true, value_enum, value_name = "WHEN")]
+    pub color: Option<ColorChoice>,
+    /// give up after this many seconds, reporting what completed, and exit
+    /// with the network error code
+    #[arg(
+        long,
+        global = true,
+        env = "NGIT_TIMEOUT",
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timeout: Option<u64>,
 }
 
 pub fn extract_signer_cli_arguments(args: &Cli) -> Result<Option<SignerInfo>> {
@@ -62,9 +91,52 @@ pub enum Commands {
     /// submit PR with advanced options
     Send(sub_commands::send::SubCommandArgs),
     /// list PRs; checkout, apply or download selected
-    List,
+    List(sub_commands::list::SubCommandArgs),
+    /// print the full diff of a PR, eg. to pipe into delta
+    Diff(sub_commands::diff::SubCommandArgs),
+    /// update the cache of the repository's nostr state, PRs and comments
+    Fetch(sub_commands::fetch::SubCommandArgs),
+    /// timeline of the repository's nostr activity from the cache, newest first
+    Log(sub_commands::log::SubCommandArgs),
+    /// check whether branches were force pushed on nostr since you last
+    /// fetched, and the ci result of a checked out PR
+    Status,
+    /// apply selected patches from a PR to the current branch with `git am`
+    Apply(sub_commands::apply::SubCommandArgs),
+    /// edit, label, share, open or delete a PR
+    Proposal(ProposalSubCommandArgs),
+    /// share the repository's nostr address
+    Repo(RepoSubCommandArgs),
+    /// inspect how relays responded to events you published
+    Events(EventsSubCommandArgs),
+    /// watch this repository by adding it to your nostr git repositories list
+    Watch,
+    /// stop watching this repository
+    Unwatch,
+    /// list repositories you are watching with their latest activity
+    Watched,
+    /// open PRs and issues across every repository you maintain
+    Inbox(sub_commands::inbox::SubCommandArgs),
+    /// push nostr state to a plain git remote so it can serve as a read-only mirror
+    Mirror(sub_commands::mirror::SubCommandArgs),
     /// login, logout or export keys
     Account(AccountSubCommandArgs),
+    /// manage short nostr://alias/<name> names for nostr git urls
+    Alias(AliasSubCommandArgs),
+    /// list or restore local branches ngit saved before overwriting them
+    Backups(BackupsSubCommandArgs),
+    /// delete local branches of closed or applied PRs
+    PruneBranches(sub_commands::prune_branches::SubCommandArgs),
+    /// run checks, eg. tests, before ngit publishes a PR
+    Hooks(HooksSubCommandArgs),
+    /// tidy up data ngit keeps in this repository
+    Cache(CacheSubCommandArgs),
+    /// view user configuration
+    Config(sub_commands::config::SubCommandArgs),
+    /// diagnose common setup problems
+    Doctor,
+    /// update remotes, git config and cache left by older ngit versions
+    Migrate,
 }
 
 #[derive(Subcommand)]
@@ -82,3 +154,112 @@ pub struct AccountSubCommandArgs {
     #[command(subcommand)]
     pub account_command: AccountCommands,
 }
+
+#[derive(Subcommand)]
+pub enum AliasCommands {
+    /// save a nostr git url under a name, usable as nostr://alias/<name>
+    Add(sub_commands::alias::AddArgs),
+    /// list saved aliases
+    List,
+    /// remove a saved alias
+    Remove(sub_commands::alias::RemoveArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct AliasSubCommandArgs {
+    #[command(subcommand)]
+    pub alias_command: AliasCommands,
+}
+
+#[derive(Subcommand)]
+pub enum BackupsCommands {
+    /// list backups, newest first
+    List,
+    /// point a branch back at a backup. its current tip is backed up first
+    Restore(sub_commands::backups::RestoreArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct BackupsSubCommandArgs {
+    #[command(subcommand)]
+    pub backups_command: BackupsCommands,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// remove backups of overwritten branches older than 30 days
+    Prune,
+}
+
+#[derive(clap::Parser)]
+pub struct CacheSubCommandArgs {
+    #[command(subcommand)]
+    pub cache_command: CacheCommands,
+}
+
+#[derive(Subcommand)]
+pub enum HooksCommands {
+    /// write a pre-send hook that must pass before `ngit send` or `git push`
+    /// publish a PR
+    Install(sub_commands::hooks::InstallArgs),
+    /// remove the pre-send hook
+    Uninstall,
+    /// show whether a pre-send hook is installed
+    Status,
+}
+
+#[derive(clap::Parser)]
+pub struct HooksSubCommandArgs {
+    #[command(subcommand)]
+    pub hooks_command: HooksCommands,
+}
+
+#[derive(Subcommand)]
+pub enum EventsCommands {
+    /// show which relays accepted or rejected a published event, and why
+    Status(sub_commands::event_status::SubCommandArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct EventsSubCommandArgs {
+    #[command(subcommand)]
+    pub events_command: EventsCommands,
+}
+
+#[derive(Subcommand)]
+pub enum ProposalCommands {
+    /// print the PR's status, patches, labels, ci result and comments
+    Show(sub_commands::show_proposal::SubCommandArgs),
+    /// publish a new title and description without a new revision
+    Edit(sub_commands::edit_proposal::SubCommandArgs),
+    /// show, add or remove the PR's labels, eg. bug or breaking
+    Label(sub_commands::label_proposal::SubCommandArgs),
+    /// print the PR's nevent with relay hints and its web url
+    Share(sub_commands::share::SubCommandArgs),
+    /// open the PR in a web viewer, gitworkshop.dev unless
+    /// nostr.web-viewer-url is set
+    Open(sub_commands::share::SubCommandArgs),
+    /// ask relays to delete a PR you authored, eg. after leaking a secret
+    Delete(sub_commands::delete_proposal::SubCommandArgs),
+    /// show which repo relays hold the PR's root and patches, and republish
+    /// any they are missing
+    Coverage(sub_commands::proposal_coverage::SubCommandArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct ProposalSubCommandArgs {
+    #[command(subcommand)]
+    pub proposal_command: ProposalCommands,
+}
+
+#[derive(Subcommand)]
+pub enum RepoCommands {
+    /// print the naddr with relay hints, a git clone command and web url
+    Share,
+}
+
+#[derive(clap::Parser)]
+pub struct RepoSubCommandArgs {
+    #[command(subcommand)]
+    pub repo_command: RepoCommands,
+}
@@ -2,26 +2,146 @@
 #![allow(clippy::large_futures)]
 #![cfg_attr(not(test), warn(clippy::expect_used))]
 
+use std::process::ExitCode;
+
 use anyhow::Result;
-use clap::Parser;
-use cli::{AccountCommands, Cli, Commands};
+use clap::{CommandFactory, Parser};
+use cli::{
+    AccountCommands, AliasCommands, BackupsCommands, CacheCommands, Cli, Commands, EventsCommands,
+    HooksCommands, ProposalCommands, RepoCommands,
+};
 
 mod cli;
-use ngit::{cli_interactor, client, git, git_events, login, repo_ref};
+use ngit::{
+    cli_interactor, client, config,
+    error::{ErrorCategory, NgitError, report_and_exit},
+    git, git_events, login, output, repo_ref, timeout,
+};
 
 mod sub_commands;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    report_and_exit(run().await)
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
-    match &cli.command {
+    client::set_verbose(cli.verbose);
+    if cli.yes {
+        cli_interactor::accept_defaults();
+    }
+    let mut config =
+        config::Config::load(&git::Repo::discover().ok().as_ref()).category(NgitError::Config)?;
+    if let Some(color) = cli.color {
+        config.color.set(color, config::ConfigSource::CommandLine);
+    }
+    output::init_color(config.color.value);
+    if cli.fetch_all {
+        config
+            .fetch_max_events_per_kind
+            .set(None, config::ConfigSource::CommandLine);
+    }
+    let Some(command) = &cli.command else {
+        if cli_interactor::prompts_allowed() {
+            if let Ok(git_repo) = git::Repo::discover() {
+                return sub_commands::first_run::launch(&cli, &git_repo, &config).await;
+            }
+        }
+        // exit with the usual clap missing sub command error so scripts don't hang
+        let _ = Cli::command()
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .get_matches();
+        unreachable!()
+    };
+    if !matches!(command, Commands::Migrate) {
+        if let Ok(git_repo) = git::Repo::discover() {
+            sub_commands::migrate::offer_to_move_local_cache(&git_repo)?;
+        }
+    }
+    timeout::with_cancellation(timeout::with_timeout(
+        cli.timeout,
+        launch(&cli, command, &config),
+    ))
+    .await
+}
+
+async fn launch(cli: &Cli, command: &Commands, config: &config::Config) -> Result<()> {
+    match command {
         Commands::Account(args) => match &args.account_command {
-            AccountCommands::Login(sub_args) => sub_commands::login::launch(&cli, sub_args).await,
+            AccountCommands::Login(sub_args) => {
+                sub_commands::login::launch(cli, sub_args, config).await
+            }
             AccountCommands::Logout => sub_commands::logout::launch().await,
             AccountCommands::ExportKeys => sub_commands::export_keys::launch().await,
         },
-        Commands::Init(args) => sub_commands::init::launch(&cli, args).await,
-        Commands::List => sub_commands::list::launch().await,
-        Commands::Send(args) => sub_commands::send::launch(&cli, args, false).await,
+        Commands::Alias(args) => match &args.alias_command {
+            AliasCommands::Add(sub_args) => sub_commands::alias::launch_add(sub_args).await,
+            AliasCommands::List => sub_commands::alias::launch_list(),
+            AliasCommands::Remove(sub_args) => sub_commands::alias::launch_remove(sub_args),
+        },
+        Commands::Apply(args) => sub_commands::apply::launch(args, config).await,
+        Commands::Backups(args) => match &args.backups_command {
+            BackupsCommands::List => sub_commands::backups::launch_list(),
+            BackupsCommands::Restore(sub_args) => sub_commands::backups::launch_restore(sub_args),
+        },
+        Commands::Cache(args) => match &args.cache_command {
+            CacheCommands::Prune => sub_commands::cache::launch_prune(),
+        },
+        Commands::Config(args) => sub_commands::config::launch(args, config),
+        Commands::Diff(args) => sub_commands::diff::launch(args, config).await,
+        Commands::Doctor => sub_commands::doctor::launch(cli, config).await,
+        Commands::Events(args) => match &args.events_command {
+            EventsCommands::Status(sub_args) => {
+                sub_commands::event_status::launch(sub_args, config).await
+            }
+        },
+        Commands::Fetch(args) => sub_commands::fetch::launch(args, config).await,
+        Commands::Hooks(args) => match &args.hooks_command {
+            HooksCommands::Install(sub_args) => sub_commands::hooks::launch_install(sub_args),
+            HooksCommands::Uninstall => sub_commands::hooks::launch_uninstall(),
+            HooksCommands::Status => sub_commands::hooks::launch_status(),
+        },
+        Commands::Inbox(args) => sub_commands::inbox::launch(cli, args, config).await,
+        Commands::Init(args) => sub_commands::init::launch(cli, args, config).await,
+        Commands::List(args) => sub_commands::list::launch(args, config).await,
+        Commands::Log(args) => sub_commands::log::launch(args, config).await,
+        Commands::Migrate => sub_commands::migrate::launch().await,
+        Commands::Mirror(args) => sub_commands::mirror::launch(args, config).await,
+        Commands::Proposal(args) => match &args.proposal_command {
+            ProposalCommands::Show(sub_args) => {
+                sub_commands::show_proposal::launch(sub_args, config).await
+            }
+            ProposalCommands::Edit(sub_args) => {
+                sub_commands::edit_proposal::launch(cli, sub_args, config).await
+            }
+            ProposalCommands::Label(sub_args) => {
+                sub_commands::label_proposal::launch(cli, sub_args, config).await
+            }
+            ProposalCommands::Open(sub_args) => {
+                sub_commands::open_proposal::launch(sub_args, config).await
+            }
+            ProposalCommands::Share(sub_args) => {
+                sub_commands::share::launch_proposal(sub_args, config).await
+            }
+            ProposalCommands::Delete(sub_args) => {
+                sub_commands::delete_proposal::launch(cli, sub_args, config).await
+            }
+            ProposalCommands::Coverage(sub_args) => {
+                sub_commands::proposal_coverage::launch(sub_args, config).await
+            }
+        },
+        Commands::PruneBranches(args) => {
+            sub_commands::prune_branches::launch(cli, args, config).await
+        }
+        Commands::Repo(args) => match &args.repo_command {
+            RepoCommands::Share => sub_commands::share::launch_repo(config).await,
+        },
+        Commands::Send(args) => sub_commands::send::launch(cli, args, config, false).await,
+        Commands::Status => sub_commands::status::launch(config).await,
+        Commands::Unwatch => sub_commands::watch::launch(cli, config, false).await,
+        Commands::Watch => sub_commands::watch::launch(cli, config, true).await,
+        Commands::Watched => sub_commands::watched::launch(cli, config).await,
     }
 }
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result, bail};
+use ngit::git::nostr_url::{
+    NOSTR_URL_ALIAS_PREFIX, NostrUrlDecoded, list_nostr_url_aliases, remove_nostr_url_alias,
+    save_nostr_url_alias, validate_nostr_url_alias_name,
+};
+
+use crate::git::Repo;
+
+#[derive(Debug, clap::Args)]
+pub struct AddArgs {
+    /// name used in nostr://alias/<name>
+    pub(crate) name: String,
+    /// nostr git url eg. nostr://naddr123 or nostr://npub123/my-repo
+    pub(crate) url: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RemoveArgs {
+    pub(crate) name: String,
+}
+
+/// aliases are stored in global git config. under test the repository config
+/// is used so the developer's global config is left untouched
+fn get_config_scope(git_repo: Option<&Repo>) -> Result<Option<&Repo>> {
+    if std::env::var("NGITTEST").is_ok() {
+        Ok(Some(git_repo.context("failed to find a git repository")?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn launch_add(args: &AddArgs) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    validate_nostr_url_alias_name(&args.name)?;
+    if args.url.starts_with(NOSTR_URL_ALIAS_PREFIX) {
+        bail!("an alias cannot point to another alias");
+    }
+    NostrUrlDecoded::parse_and_resolve(&args.url, &git_repo.as_ref())
+        .await
+        .context(format!("{} is not a valid nostr git url", args.url))?;
+    save_nostr_url_alias(&args.name, &args.url, &get_config_scope(git_repo.as_ref())?)?;
+    println!(
+        "added alias '{}'. use it as {NOSTR_URL_ALIAS_PREFIX}{}",
+        args.name, args.name
+    );
+    Ok(())
+}
+
+pub fn launch_list() -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    let aliases = list_nostr_url_aliases(&get_config_scope(git_repo.as_ref())?)?;
+    if aliases.is_empty() {
+        println!("no nostr url aliases. add one with `ngit alias add <name> <nostr-url>`");
+    }
+    for (name, url) in aliases {
+        println!("{name} {url}");
+    }
+    Ok(())
+}
+
+pub fn launch_remove(args: &RemoveArgs) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    if remove_nostr_url_alias(&args.name, &get_config_scope(git_repo.as_ref())?)? {
+        println!("removed alias '{}'", args.name);
+        Ok(())
+    } else {
+        bail!("no nostr url alias '{}'", args.name)
+    }
+}
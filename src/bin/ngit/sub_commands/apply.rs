@@ -0,0 +1,76 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::{get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache},
+    git_events::{event_tag_from_nip19_or_hex, get_most_recent_patch_with_ancestors},
+};
+use nostr::nips::nip10::Marker;
+use nostr_sdk::TagStandard;
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+    sub_commands::list::{launch_git_am_with_patch_selection, select_patches},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note or hex event id
+    pub(crate) id: String,
+    /// patches to apply, numbered from 1 for the oldest eg. 1,4,5. prompted
+    /// for when not given
+    #[arg(long, value_delimiter = ',')]
+    pub(crate) patches: Vec<usize>,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let invalid_reference = format!(
+        "{} is not a valid proposal reference. use nevent, note or hex event id",
+        args.id
+    );
+    let tag = event_tag_from_nip19_or_hex(&args.id, "proposal", Marker::Root, false, false)
+        .context(invalid_reference.clone())?;
+    let Some(TagStandard::Event { event_id, .. }) = tag.as_standardized() else {
+        bail!(invalid_reference);
+    };
+
+    if !get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+        .await?
+        .iter()
+        .any(|e| e.id.eq(event_id))
+    {
+        bail!("failed to find proposal {}", args.id);
+    }
+
+    let commits_events =
+        get_all_proposal_patch_events_from_cache(git_repo_path, &repo_ref, event_id).await?;
+    let mut patches = get_most_recent_patch_with_ancestors(commits_events)
+        .context("failed to find any patches on this proposal")?;
+    patches.reverse();
+
+    let selected = if args.patches.is_empty() {
+        select_patches(&patches)?
+    } else {
+        if args.patches.contains(&0) {
+            bail!("patches are numbered from 1");
+        }
+        args.patches.iter().map(|n| n - 1).collect()
+    };
+
+    launch_git_am_with_patch_selection(patches, &selected)
+}
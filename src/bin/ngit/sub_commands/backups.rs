@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+use nostr_sdk::Timestamp;
+
+use crate::git::{
+    Repo,
+    backup::{find_backup, list_backups, restore_backup},
+    oid_to_shorthand_string,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct RestoreArgs {
+    /// backup as listed by `ngit backups list`
+    pub(crate) backup: String,
+}
+
+pub fn launch_list() -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let backups = list_backups(&git_repo)?;
+    if backups.is_empty() {
+        println!("no backups. ngit saves one when it overwrites a local proposal branch");
+    }
+    let now = Timestamp::now().as_u64();
+    for backup in backups {
+        println!(
+            "{} {} ({} days ago)",
+            backup.ref_name,
+            oid_to_shorthand_string(backup.tip)?,
+            now.saturating_sub(backup.created_at) / (24 * 60 * 60),
+        );
+    }
+    Ok(())
+}
+
+pub fn launch_restore(args: &RestoreArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let backup = find_backup(&git_repo, &args.backup)?;
+    let current_tip_backup = restore_backup(&git_repo, &backup)?;
+    println!(
+        "restored '{}' to {}",
+        backup.branch_name,
+        oid_to_shorthand_string(backup.tip)?
+    );
+    if let Some(ref_name) = current_tip_backup {
+        println!("previous tip saved as {ref_name}");
+    }
+    Ok(())
+}
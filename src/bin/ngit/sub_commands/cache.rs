@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+use nostr_sdk::Timestamp;
+
+use crate::git::{
+    Repo,
+    backup::{BACKUP_MAX_AGE_SECS, prune_backups},
+};
+
+pub fn launch_prune() -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let pruned = prune_backups(&git_repo, BACKUP_MAX_AGE_SECS, Timestamp::now())?;
+    println!(
+        "removed {} backup(s) of overwritten branches older than {} days",
+        pruned.len(),
+        BACKUP_MAX_AGE_SECS / (24 * 60 * 60),
+    );
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{Result, bail};
+use ngit::{
+    client::default_blaster_relays,
+    config::{Config, ConfigValue, get_config_file_path},
+};
+
+use crate::git::Repo;
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// print the effective configuration and where each value came from
+    #[arg(long, action)]
+    show: bool,
+}
+
+pub fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    if !args.show {
+        bail!("specify --show to print the effective configuration");
+    }
+    if let Some(path) = get_config_file_path(&Repo::discover().ok().as_ref()) {
+        println!(
+            "config file: {}{}",
+            path.display(),
+            if path.exists() { "" } else { " (not found)" }
+        );
+    }
+    print_value("fallback_relays", &config.fallback_relays, |v| v.join(" "));
+    print_value(
+        "default_grasp_servers",
+        &config.default_grasp_servers,
+        |v| v.join(" "),
+    );
+    print_value("color", &config.color, ToString::to_string);
+    print_value("editor", &config.editor, |v| {
+        v.clone().unwrap_or("(unset)".to_string())
+    });
+    print_value(
+        "relay_timeout_secs",
+        &config.relay_timeout_secs,
+        u64::to_string,
+    );
+    print_value(
+        "profile_cache_ttl_secs",
+        &config.profile_cache_ttl_secs,
+        u64::to_string,
+    );
+    print_value("proposal_expiry_days", &config.proposal_expiry_days, |v| {
+        v.map_or("(unset)".to_string(), |d| d.to_string())
+    });
+    print_value("web_viewer_url", &config.web_viewer_url, String::clone);
+    print_value(
+        "fetch_max_events_per_kind",
+        &config.fetch_max_events_per_kind,
+        |v| v.map_or("(unlimited)".to_string(), |max| max.to_string()),
+    );
+    print_value(
+        "max_concurrent_relays",
+        &config.max_concurrent_relays,
+        usize::to_string,
+    );
+    print_value("label_namespace", &config.label_namespace, String::clone);
+    print_value(
+        "labels_allow_anyone",
+        &config.labels_allow_anyone,
+        bool::to_string,
+    );
+    print_value("ci_bots", &config.ci_bots, |v| {
+        if v.is_empty() {
+            "(from announcement)".to_string()
+        } else {
+            v.join(" ")
+        }
+    });
+    print_value("use_blaster", &config.use_blaster, bool::to_string);
+    let blaster_relays = default_blaster_relays();
+    println!(
+        "blaster_relays = {} (built in)",
+        if blaster_relays.is_empty() {
+            "(none)".to_string()
+        } else {
+            blaster_relays.join(" ")
+        }
+    );
+    print_value("prs_as_refs", &config.prs_as_refs, ToString::to_string);
+    print_value(
+        "cache_max_age_secs",
+        &config.cache_max_age_secs,
+        u64::to_string,
+    );
+    print_value(
+        "background_fetch",
+        &config.background_fetch,
+        bool::to_string,
+    );
+    print_value("quiet_clone", &config.quiet_clone, bool::to_string);
+    print_value("relay_proxy", &config.relay_proxy, |v| {
+        v.map_or("(unset)".to_string(), |a| a.to_string())
+    });
+    print_value("git_proxy", &config.git_proxy, |v| {
+        v.clone().unwrap_or("(unset)".to_string())
+    });
+    Ok(())
+}
+
+fn print_value<T>(name: &str, value: &ConfigValue<T>, format: impl Fn(&T) -> String) {
+    println!("{name} = {} ({})", format(&value.value), value.source);
+}
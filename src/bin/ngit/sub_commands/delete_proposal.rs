@@ -0,0 +1,140 @@
+use anyhow::{Context, Result, anyhow, bail};
+use ngit::{
+    client::{
+        delete_events_from_local_cache, get_events_from_local_cache, get_repo_relays,
+        print_repo_relays_notice, save_event_in_local_cache, send_events,
+    },
+    error::NgitError,
+    git_events::{event_is_revision_root, event_to_cover_letter, generate_proposal_deletion_event},
+};
+use nostr_sdk::{EventId, Kind};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms},
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+    sub_commands::share::find_proposal,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one
+    pub(crate) id: String,
+}
+
+static RELAYS_MAY_IGNORE_WARNING: &str = "WARNING: relays are not obliged to honour deletion requests and others may already have copies. treat anything published as public and rotate any leaked secrets";
+
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let proposal = find_proposal(Some(&args.id), &git_repo, &repo_ref).await?;
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if !user_ref.public_key.eq(&proposal.pubkey) {
+        bail!("only the proposal author can delete it");
+    }
+
+    // the author's patches and revisions, and the patches of those revisions
+    let mut patches = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kind(Kind::GitPatch)
+            .event(proposal.id)
+            .author(proposal.pubkey),
+    ])
+    .await?;
+    let revision_roots: Vec<EventId> = patches
+        .iter()
+        .filter(|e| event_is_revision_root(e))
+        .map(|e| e.id)
+        .collect();
+    if !revision_roots.is_empty() {
+        patches.extend(
+            get_events_from_local_cache(git_repo_path, vec![
+                nostr::Filter::default()
+                    .kind(Kind::GitPatch)
+                    .events(revision_roots)
+                    .author(proposal.pubkey),
+            ])
+            .await?,
+        );
+    }
+    patches.retain(|e| e.id.ne(&proposal.id));
+    patches.sort_by_key(|e| e.id);
+    patches.dedup_by_key(|e| e.id);
+
+    let title = event_to_cover_letter(&proposal).map_or(proposal.id.to_hex(), |cl| cl.title);
+    println!("{RELAYS_MAY_IGNORE_WARNING}");
+    if !cli_args.yes
+        && !Interactor::default().confirm(
+            PromptConfirmParms::default()
+                .with_prompt(format!(
+                    "request deletion of proposal \"{title}\" and its {} other events?",
+                    patches.len()
+                ))
+                .with_flag("--yes")
+                .with_default(false),
+        )?
+    {
+        bail!(NgitError::UserAbort(anyhow!("proposal not deleted")));
+    }
+
+    let deletion =
+        generate_proposal_deletion_event(&proposal, &patches, &repo_ref, &signer).await?;
+
+    let (repo_relays, repo_relays_source) =
+        get_repo_relays(Some(git_repo_path), &repo_ref, client.get_fallback_relays()).await;
+    print_repo_relays_notice(repo_relays_source);
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![deletion.clone()],
+        user_ref.relays.write(),
+        repo_relays,
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+    client.disconnect().await?;
+
+    delete_events_from_local_cache(
+        git_repo_path,
+        std::iter::once(&proposal)
+            .chain(&patches)
+            .map(|e| e.id)
+            .collect(),
+    )
+    .await?;
+    // kept so proposals fetched again from relays that ignore it stay hidden
+    save_event_in_local_cache(git_repo_path, &deletion).await?;
+
+    println!(
+        "requested deletion of proposal \"{title}\" and {} other events",
+        patches.len()
+    );
+    Ok(())
+}
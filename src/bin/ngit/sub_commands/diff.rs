@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use ngit::{
+    client::get_all_proposal_patch_events_from_cache,
+    git_events::{event_has_expired, get_most_recent_patch_with_ancestors},
+    output,
+};
+use nostr_sdk::Timestamp;
+
+use crate::{
+    config::Config,
+    git::{Repo, RepoActions},
+    sub_commands::{
+        list::write_full_diff,
+        share::{fetch_repo_ref, find_proposal},
+    },
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one.
+    /// defaults to the proposal of the checked out branch
+    pub(crate) id: Option<String>,
+    /// print straight to the terminal rather than through core.pager or
+    /// $PAGER
+    #[arg(long, action)]
+    pub(crate) no_pager: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let repo_ref = fetch_repo_ref(&git_repo, config).await?;
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+
+    let now = Timestamp::now();
+    let patches =
+        get_all_proposal_patch_events_from_cache(git_repo.get_path()?, &repo_ref, &proposal.id)
+            .await?
+            .into_iter()
+            .filter(|e| !event_has_expired(e, now))
+            .collect();
+    let most_recent_proposal_patch_chain = get_most_recent_patch_with_ancestors(patches)
+        .context("failed to find any patches on this proposal")?;
+
+    let pager = if args.no_pager {
+        None
+    } else {
+        output::pager(&git_repo)?
+    };
+    output::write_with_pager(pager.as_deref(), |out| {
+        write_full_diff(&most_recent_proposal_patch_chain, out)
+    })
+}
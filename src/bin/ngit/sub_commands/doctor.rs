@@ -0,0 +1,446 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use ngit::{
+    client::{
+        detect_clock_skew, get_event_from_global_cache, get_events_from_local_cache,
+        get_fetch_filters, get_global_cache_path, get_local_cache_path,
+    },
+    login::{SignerInfo, SignerInfoSource, existing::get_signer_info},
+    repo_ref::{RepoRef, try_and_get_repo_coordinates_when_remote_unknown},
+};
+use nostr::{Keys, ToBech32, nips::nip01::Coordinate};
+use nostr_sdk::{Kind, PublicKey, RelayUrl, Timestamp};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, Params},
+    config::Config,
+    git::{Repo, RepoActions},
+};
+
+const RELAY_TIMEOUT: Duration = Duration::from_secs(5);
+const GIT_SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+/// outcome of a single doctor check. add a check by writing a function that
+/// returns one and calling it from `launch`
+pub struct CheckResult {
+    name: String,
+    status: CheckStatus,
+    detail: String,
+    hint: Option<String>,
+    /// a failure makes `ngit doctor` exit non-zero
+    critical: bool,
+}
+
+impl CheckResult {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+            critical: false,
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+            critical: false,
+        }
+    }
+
+    fn skip(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            hint: None,
+            critical: false,
+        }
+    }
+
+    fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    fn failed_critically(&self) -> bool {
+        self.critical && matches!(self.status, CheckStatus::Fail)
+    }
+
+    fn print(&self) {
+        let symbol = match self.status {
+            CheckStatus::Pass => "✓",
+            CheckStatus::Fail => "✗",
+            CheckStatus::Skip => "-",
+        };
+        println!("{symbol} {}: {}", self.name, self.detail);
+        if let Some(hint) = &self.hint {
+            println!("  hint: {hint}");
+        }
+    }
+}
+
+pub async fn launch(cli_args: &Cli, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    let git_repo_path = git_repo.as_ref().and_then(|r| r.get_path().ok());
+    let mut results = vec![];
+    let mut report = |result: CheckResult| {
+        result.print();
+        results.push(result);
+    };
+
+    report(check_helper_discoverable());
+    report(check_login(
+        &git_repo.as_ref(),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+    ));
+    if let Some(git_repo_path) = git_repo_path {
+        report(check_local_cache(git_repo_path).await);
+    }
+    report(check_global_cache(git_repo_path).await);
+
+    let client = Client::new(Params::with_config(config));
+    let mut relays = client
+        .get_fallback_relays()
+        .iter()
+        .filter_map(|r| RelayUrl::parse(r).ok())
+        .collect::<Vec<RelayUrl>>();
+    let mut git_servers = vec![];
+    if let Some(git_repo) = &git_repo {
+        let (result, repo_ref, events) = check_announcement(&client, git_repo, &relays).await;
+        report(result);
+        if let Some(repo_ref) = repo_ref {
+            report(check_clock_skew(&events));
+            relays.extend(repo_ref.relays.clone());
+            git_servers.clone_from(&repo_ref.git_server);
+        }
+    }
+    let mut seen = HashSet::new();
+    relays.retain(|relay| seen.insert(relay.clone()));
+    let mut reachable = 0;
+    for relay in &relays {
+        let result = check_relay(&client, relay).await;
+        if matches!(result.status, CheckStatus::Pass) {
+            reachable += 1;
+        }
+        report(result);
+    }
+    if !relays.is_empty() && reachable == 0 {
+        report(
+            CheckResult::fail(
+                "relays",
+                "none reachable",
+                "check your network connection and proxy settings (git config nostr.proxy)",
+            )
+            .critical(),
+        );
+    }
+    for git_server in &git_servers {
+        report(check_git_server(git_server).await);
+    }
+    client.disconnect().await?;
+
+    let failed = results.iter().filter(|r| r.failed_critically()).count();
+    if failed > 0 {
+        bail!(
+            "{failed} critical check{} failed",
+            if failed > 1 { "s" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// git looks for remote helpers in its exec-path and then PATH
+fn check_helper_discoverable() -> CheckResult {
+    let name = "git-remote-nostr discoverable by git";
+    let exe_name = format!("git-remote-nostr{}", env::consts::EXE_SUFFIX);
+    let git_exec_path = std::process::Command::new("git")
+        .arg("--exec-path")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()));
+    match git_exec_path
+        .into_iter()
+        .chain(env::var_os("PATH").iter().flat_map(env::split_paths))
+        .map(|dir| dir.join(&exe_name))
+        .find(|path| path.is_file())
+    {
+        Some(path) => CheckResult::pass(name, path.display().to_string()),
+        None => CheckResult::fail(
+            name,
+            format!("{exe_name} not found in git's exec-path or PATH"),
+            "install git-remote-nostr alongside ngit and add its directory to PATH",
+        )
+        .critical(),
+    }
+}
+
+/// inspects stored login details without prompting or connecting to a signer
+fn check_login(git_repo: &Option<&Repo>, signer_info: &Option<SignerInfo>) -> CheckResult {
+    let name = "login";
+    let Ok((signer_info, source)) = get_signer_info(git_repo, signer_info, &None, &None) else {
+        return CheckResult::fail(name, "not logged in", "run `ngit account login`");
+    };
+    let source = match source {
+        SignerInfoSource::GitLocal => "local git config",
+        SignerInfoSource::GitGlobal => "global git config",
+        SignerInfoSource::CommandLineArguments => "command line arguments",
+    };
+    match signer_info {
+        SignerInfo::Nsec { nsec, .. } if nsec.starts_with("ncryptsec") => {
+            CheckResult::pass(name, format!("password protected nsec via {source}"))
+        }
+        SignerInfo::Nsec { nsec, .. } => match Keys::from_str(&nsec) {
+            Ok(keys) => CheckResult::pass(
+                name,
+                format!(
+                    "nsec via {source} as {}",
+                    keys.public_key().to_bech32().unwrap_or_default()
+                ),
+            ),
+            Err(_) => CheckResult::fail(
+                name,
+                format!("nsec in {source} is invalid"),
+                "run `ngit account logout` and then `ngit account login`",
+            ),
+        },
+        SignerInfo::Bunker { npub, .. } => CheckResult::pass(
+            name,
+            format!(
+                "nostr connect via {source}{}",
+                npub.map(|npub| format!(" as {npub}")).unwrap_or_default()
+            ),
+        ),
+    }
+}
+
+async fn check_local_cache(git_repo_path: &Path) -> CheckResult {
+    let path = get_local_cache_path(git_repo_path);
+    check_cache(
+        "local cache",
+        &path,
+        get_events_from_local_cache(git_repo_path, vec![nostr::Filter::default().limit(1)])
+            .await
+            .map(|_| ()),
+    )
+}
+
+async fn check_global_cache(git_repo_path: Option<&Path>) -> CheckResult {
+    let Ok(path) = get_global_cache_path(git_repo_path) else {
+        return CheckResult::skip("global cache", "location unknown");
+    };
+    check_cache(
+        "global cache",
+        &path,
+        get_event_from_global_cache(git_repo_path, vec![nostr::Filter::default().limit(1)])
+            .await
+            .map(|_| ()),
+    )
+}
+
+fn check_cache(name: &str, path: &Path, query: Result<()>) -> CheckResult {
+    if !path.exists() {
+        // querying would create it
+        return CheckResult::pass(name, format!("{} (not created yet)", path.display()));
+    }
+    match query {
+        Ok(()) => CheckResult::pass(
+            name,
+            format!("{} ({})", path.display(), format_size(dir_size(path))),
+        ),
+        Err(error) => CheckResult::fail(
+            name,
+            format!("{} cannot be opened: {error}", path.display()),
+            format!(
+                "delete {} and it will be rebuilt from relays on the next fetch",
+                path.display()
+            ),
+        )
+        .critical(),
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.metadata().ok())
+                .filter(std::fs::Metadata::is_file)
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+fn format_size(bytes: u64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let mb = bytes as f64 / 1_048_576.0;
+    if mb >= 1.0 {
+        format!("{mb:.1} MB")
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+/// looks for the announcement on relays rather than in the cache so a stale
+/// cache doesn't hide a missing announcement
+async fn check_announcement(
+    client: &Client,
+    git_repo: &Repo,
+    fallback_relays: &[RelayUrl],
+) -> (CheckResult, Option<RepoRef>, Vec<nostr::Event>) {
+    let name = "repo announcement";
+    let Ok(coordinate) = try_and_get_repo_coordinates_when_remote_unknown(git_repo, None).await
+    else {
+        return (
+            CheckResult::skip(name, "no nostr remote or git config nostr.repo"),
+            None,
+            vec![],
+        );
+    };
+    let relays = coordinate
+        .relays
+        .iter()
+        .chain(fallback_relays)
+        .map(ToString::to_string)
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect::<Vec<String>>();
+    let events = tokio::time::timeout(
+        RELAY_TIMEOUT * 2,
+        client.get_events(
+            relays,
+            get_fetch_filters(
+                &HashSet::from([Coordinate {
+                    relays: vec![],
+                    ..coordinate.clone()
+                }]),
+                &HashSet::new(),
+                &HashSet::new(),
+            ),
+        ),
+    )
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .unwrap_or_default();
+    let repo_ref = events
+        .iter()
+        .filter(|e| {
+            e.kind == Kind::GitRepoAnnouncement
+                && e.pubkey == coordinate.public_key
+                && e.tags.identifier() == Some(coordinate.identifier.as_str())
+        })
+        .max_by_key(|e| e.created_at)
+        .and_then(|e| RepoRef::try_from((e.clone(), Some(coordinate.public_key))).ok());
+    let result = if let Some(repo_ref) = &repo_ref {
+        CheckResult::pass(
+            name,
+            format!(
+                "\"{}\" found with {} relay{} and {} git server{}",
+                repo_ref.name,
+                repo_ref.relays.len(),
+                if repo_ref.relays.len() == 1 { "" } else { "s" },
+                repo_ref.git_server.len(),
+                if repo_ref.git_server.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+            ),
+        )
+    } else {
+        CheckResult::fail(
+            name,
+            format!(
+                "{}/{} not found on relays",
+                coordinate.public_key.to_bech32().unwrap_or_default(),
+                coordinate.identifier
+            ),
+            "check the nostr remote url or, if you are the maintainer, run `ngit init`",
+        )
+        .critical()
+    };
+    (result, repo_ref, events)
+}
+
+fn check_clock_skew(events: &[nostr::Event]) -> CheckResult {
+    let mut newest_event_by_author: HashMap<PublicKey, Timestamp> = HashMap::new();
+    for event in events {
+        let newest = newest_event_by_author
+            .entry(event.pubkey)
+            .or_insert(event.created_at);
+        if event.created_at > *newest {
+            *newest = event.created_at;
+        }
+    }
+    match detect_clock_skew(Timestamp::now(), &newest_event_by_author) {
+        None => CheckResult::pass("clock", "no skew detected"),
+        Some(behind) => CheckResult::fail(
+            "clock",
+            format!("system clock appears to be {behind}s behind nostr relays"),
+            "correct your system clock or use --fix-timestamp when publishing",
+        ),
+    }
+}
+
+async fn check_relay(client: &Client, relay: &RelayUrl) -> CheckResult {
+    let name = format!("relay {relay}");
+    match tokio::time::timeout(RELAY_TIMEOUT, client.connect(relay)).await {
+        Ok(Ok(())) => CheckResult::pass(&name, "reachable"),
+        Ok(Err(error)) => CheckResult::fail(
+            &name,
+            format!("unreachable: {error}"),
+            "the relay may be down. other relays will be used",
+        ),
+        Err(_) => CheckResult::fail(
+            &name,
+            "unreachable: timed out",
+            "the relay may be down. other relays will be used",
+        ),
+    }
+}
+
+async fn check_git_server(git_server: &str) -> CheckResult {
+    let name = format!("git server {git_server}");
+    let ls_remote = tokio::process::Command::new("git")
+        .args(["ls-remote", "--heads", git_server])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    match tokio::time::timeout(GIT_SERVER_TIMEOUT, ls_remote).await {
+        Ok(Ok(status)) if status.success() => CheckResult::pass(&name, "reachable"),
+        Ok(_) => CheckResult::fail(
+            &name,
+            "unreachable or requires authentication",
+            format!("try `git ls-remote {git_server}` for details"),
+        ),
+        Err(_) => CheckResult::fail(
+            &name,
+            "unreachable: timed out",
+            format!("try `git ls-remote {git_server}` for details"),
+        ),
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::{
+        get_events_from_local_cache, get_proposals_and_revisions_from_cache, get_repo_relays,
+        print_repo_relays_notice, send_events,
+    },
+    git_events::{
+        PROPOSAL_EDIT_KIND, apply_proposal_edits, event_tag_from_nip19_or_hex,
+        event_to_cover_letter, generate_proposal_edit_event,
+    },
+};
+use nostr::nips::nip10::Marker;
+use nostr_sdk::TagStandard;
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{Interactor, InteractorPrompt, PromptInputParms},
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note or hex event id
+    pub(crate) id: String,
+    /// new title. prompted for when neither title nor description are given
+    #[arg(long)]
+    pub(crate) title: Option<String>,
+    /// new description
+    #[arg(long)]
+    pub(crate) description: Option<String>,
+}
+
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let invalid_reference = format!(
+        "{} is not a valid proposal reference. use nevent, note or hex event id",
+        args.id
+    );
+    let tag = event_tag_from_nip19_or_hex(&args.id, "proposal", Marker::Root, false, false)
+        .context(invalid_reference.clone())?;
+    let Some(TagStandard::Event { event_id, .. }) = tag.as_standardized() else {
+        bail!(invalid_reference);
+    };
+
+    let proposal = get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+        .await?
+        .into_iter()
+        .find(|e| e.id.eq(event_id))
+        .context(format!("failed to find proposal {}", args.id))?;
+
+    let edits = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kind(PROPOSAL_EDIT_KIND)
+            .event(proposal.id),
+    ])
+    .await?;
+    let current = apply_proposal_edits(
+        event_to_cover_letter(&proposal)?,
+        &proposal,
+        &edits,
+        &repo_ref.maintainers,
+    );
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if !user_ref.public_key.eq(&proposal.pubkey)
+        && !repo_ref.maintainers.contains(&user_ref.public_key)
+    {
+        bail!("only the proposal author or a maintainer can edit the proposal");
+    }
+
+    let (title, description) = if args.title.is_none() && args.description.is_none() {
+        (
+            Interactor::default().input(
+                PromptInputParms::default()
+                    .with_prompt("title")
+                    .with_flag("--title")
+                    .with_default(current.title.clone()),
+            )?,
+            Interactor::default().input(
+                PromptInputParms::default()
+                    .with_prompt("description")
+                    .with_flag("--description")
+                    .with_default(current.description.clone())
+                    .optional(),
+            )?,
+        )
+    } else {
+        (
+            args.title.clone().unwrap_or(current.title.clone()),
+            args.description
+                .clone()
+                .unwrap_or(current.description.clone()),
+        )
+    };
+
+    if title.trim().eq(&current.title) && description.trim().eq(&current.description) {
+        println!("no changes to the title or description");
+        return Ok(());
+    }
+    if title.trim().is_empty() {
+        bail!("title cannot be empty");
+    }
+
+    let (repo_relays, repo_relays_source) =
+        get_repo_relays(Some(git_repo_path), &repo_ref, client.get_fallback_relays()).await;
+    print_repo_relays_notice(repo_relays_source);
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![
+            generate_proposal_edit_event(&proposal, &repo_ref, &title, &description, &signer)
+                .await?,
+        ],
+        user_ref.relays.write(),
+        repo_relays,
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+    client.disconnect().await?;
+
+    println!("updated proposal title to \"{}\"", title.trim());
+    Ok(())
+}
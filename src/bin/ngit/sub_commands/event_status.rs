@@ -0,0 +1,82 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::get_event_from_cache_by_id,
+    git_events::event_tag_from_nip19_or_hex,
+    publish_status::{RelayResponse, get_relay_responses, record_relay_responses},
+};
+use nostr::nips::nip10::Marker;
+use nostr_sdk::TagStandard;
+
+use crate::{
+    client::{Client, Connect, Params},
+    config::Config,
+    git::{Repo, RepoActions},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// published event as nevent, note or hex event id
+    pub(crate) id: String,
+    /// send the event again to relays that rejected it
+    #[arg(long, action)]
+    pub(crate) resend: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let invalid_reference = format!(
+        "{} is not a valid event reference. use nevent, note or hex event id",
+        args.id
+    );
+    let tag = event_tag_from_nip19_or_hex(&args.id, "event", Marker::Root, false, false)
+        .context(invalid_reference.clone())?;
+    let Some(TagStandard::Event { event_id, .. }) = tag.as_standardized() else {
+        bail!(invalid_reference);
+    };
+
+    let responses = get_relay_responses(git_repo_path, event_id);
+    if responses.is_empty() {
+        println!(
+            "no relay responses recorded for this event. only events published from this repository are recorded"
+        );
+        return Ok(());
+    }
+    for (relay, response) in &responses {
+        println!("{relay} {response}");
+    }
+
+    let rejected_by = responses
+        .iter()
+        .filter(|(_, response)| !response.accepted)
+        .map(|(relay, _)| relay)
+        .collect::<Vec<&String>>();
+    if !args.resend || rejected_by.is_empty() {
+        return Ok(());
+    }
+
+    let event = get_event_from_cache_by_id(&git_repo, event_id).await?;
+    let client = Client::new(Params::with_config(config));
+    println!("resending to {} relay(s)...", rejected_by.len());
+    let mut new_responses = vec![];
+    for relay in rejected_by {
+        let result = client
+            .send_event_to(Some(git_repo_path), relay, event.clone())
+            .await;
+        match RelayResponse::from_send_result(&result) {
+            Some(response) => {
+                println!("{relay} {response}");
+                new_responses.push((*event_id, relay.clone(), response));
+            }
+            None => {
+                if let Err(error) = result {
+                    println!("{relay} failed: {error}");
+                }
+            }
+        }
+    }
+    record_relay_responses(git_repo_path, &new_responses)?;
+    client.disconnect().await?;
+    Ok(())
+}
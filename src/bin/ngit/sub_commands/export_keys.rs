@@ -3,7 +3,7 @@ use ngit::{
     cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms},
     login::{
         SignerInfo, SignerInfoSource,
-        existing::{get_signer_info, load_existing_login},
+        existing::{LoadLoginOptions, get_signer_info, load_existing_login},
         fresh::generate_qr,
     },
 };
@@ -26,9 +26,9 @@ pub async fn launch() -> Result<()> {
             &None,
             &Some(source),
             None,
-            true,
-            false,
-            false,
+            &LoadLoginOptions::default()
+                .silent()
+                .dont_prompt_for_password(),
         )
         .await
         {
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use ngit::{
+    background_fetch::try_lock_background_fetch, client::consolidate_fetch_reports,
+    output::print_human,
+};
+use nostr_sdk::Timestamp;
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report},
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// nostr git remote to use when several point at different repositories
+    #[arg(long)]
+    pub(crate) remote: Option<String>,
+    /// print a one line summary without progress, eg. when git-remote-nostr
+    /// refreshes the cache in the background
+    #[arg(long, action)]
+    pub(crate) quiet: bool,
+    /// print the number of updates of each kind as json on stdout
+    #[arg(long, action)]
+    pub(crate) json: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    // git-remote-nostr may start several at once
+    let Some(_lock) = try_lock_background_fetch(git_repo_path, Timestamp::now())? else {
+        print_human(args.json, "another ngit fetch is already running");
+        return Ok(());
+    };
+
+    let client = Client::new(Params::with_config(config));
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, args.remote.as_deref(), &client)
+            .await?;
+
+    let report = if args.quiet {
+        let (relay_reports, _) = client
+            .fetch_all(
+                Some(git_repo_path),
+                Some(&repo_coordinates),
+                &HashSet::new(),
+            )
+            .await?;
+        consolidate_fetch_reports(relay_reports)
+    } else {
+        fetching_with_report(git_repo_path, &client, &repo_coordinates, args.json).await?
+    };
+    if args.json {
+        println!("{}", serde_json::to_string(&report.counts())?);
+    } else if args.quiet {
+        if report.to_string().is_empty() {
+            println!("fetched at {}: no updates", Timestamp::now().as_u64());
+        } else {
+            println!("fetched at {}: {report}", Timestamp::now().as_u64());
+        }
+    }
+    client.disconnect().await?;
+    Ok(())
+}
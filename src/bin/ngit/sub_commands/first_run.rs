@@ -0,0 +1,107 @@
+use anyhow::Result;
+use ngit::{
+    login::existing::get_signer_info,
+    output::{dim, title},
+};
+use nostr::ToBech32;
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms},
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache},
+    config::Config,
+    git::{Repo, RepoActions},
+    login::fresh::fresh_login_or_signup,
+    repo_ref::{RepoRef, try_and_get_repo_coordinates_when_remote_unknown},
+    sub_commands,
+};
+
+/// guided flow for `ngit` run without a sub command inside a git repository
+pub async fn launch(cli_args: &Cli, git_repo: &Repo, config: &Config) -> Result<()> {
+    let client = Client::new(Params::with_config(config));
+
+    let signer_info = extract_signer_cli_arguments(cli_args)?;
+    if get_signer_info(&Some(git_repo), &signer_info, &None, &None).is_err() {
+        if !Interactor::default().confirm(
+            PromptConfirmParms::default()
+                .with_prompt("you are not logged in to nostr. login or create an account now?")
+                .with_default(true),
+        )? {
+            println!("run `ngit account login` when you are ready");
+            return Ok(());
+        }
+        fresh_login_or_signup(&Some(git_repo), Some(&client), signer_info, false, false).await?;
+    }
+
+    let repo_ref = if let Ok(repo_coordinates) =
+        try_and_get_repo_coordinates_when_remote_unknown(git_repo, None).await
+    {
+        fetching_with_report(git_repo.get_path()?, &client, &repo_coordinates, false).await?;
+        get_repo_ref_from_cache(Some(git_repo.get_path()?), &repo_coordinates)
+            .await
+            .ok()
+    } else {
+        None
+    };
+    client.disconnect().await?;
+
+    if let Some(repo_ref) = repo_ref {
+        print_repo_summary(git_repo, &repo_ref)?;
+        println!("run `ngit list` to view PRs or `ngit send` to submit one");
+    } else if Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt("this repository hasn't been announced on nostr. announce it now?")
+            .with_default(true),
+    )? {
+        sub_commands::init::launch(
+            cli_args,
+            &sub_commands::init::SubCommandArgs::default(),
+            config,
+        )
+        .await?;
+    } else {
+        println!("run `ngit init` when you are ready to announce this repository");
+    }
+    Ok(())
+}
+
+fn print_repo_summary(git_repo: &Repo, repo_ref: &RepoRef) -> Result<()> {
+    println!("{}", title(&repo_ref.name));
+    if !repo_ref.description.is_empty() {
+        println!("{}", repo_ref.description);
+    }
+    println!(
+        "{} {}",
+        dim("nostr url:"),
+        repo_ref.to_nostr_git_url(&Some(git_repo))
+    );
+    println!(
+        "{} {}",
+        dim("maintainers:"),
+        repo_ref
+            .maintainers
+            .iter()
+            .map(|public_key| public_key.to_bech32())
+            .collect::<Result<Vec<String>, _>>()?
+            .join(" ")
+    );
+    println!("{} {}", dim("git servers:"), repo_ref.git_server.join(" "));
+    println!(
+        "{} {}",
+        dim("relays:"),
+        repo_ref
+            .relays
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(" ")
+    );
+    if !repo_ref.submission_policy.is_empty() {
+        println!(
+            "{} {}",
+            dim("submission policy:"),
+            repo_ref.submission_policy.describe()
+        );
+    }
+    Ok(())
+}
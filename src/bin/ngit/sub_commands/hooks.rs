@@ -0,0 +1,89 @@
+use anyhow::{Context, Result, bail};
+use ngit::hooks::{PreSendHook, get_pre_send_hook, is_executable, pre_send_hook_script};
+
+use crate::git::Repo;
+
+#[derive(Debug, clap::Args)]
+pub struct InstallArgs {
+    /// shell command the hook runs, eg. 'cargo test'
+    pub(crate) command: Option<String>,
+    /// replace an existing hook
+    #[arg(long, action)]
+    pub(crate) force: bool,
+}
+
+fn describe(hook: &PreSendHook) -> String {
+    format!(
+        "{}{}",
+        hook.path.display(),
+        if hook.from_git_config {
+            " (set by nostr.pre-send-hook)"
+        } else {
+            ""
+        }
+    )
+}
+
+pub fn launch_install(args: &InstallArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let hook = get_pre_send_hook(&git_repo)?;
+    if hook.path.exists() && !args.force {
+        bail!(
+            "a pre-send hook already exists at {}. use --force to replace it",
+            describe(&hook)
+        );
+    }
+    if let Some(dir) = hook.path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create hooks directory")?;
+    }
+    std::fs::write(&hook.path, pre_send_hook_script(args.command.as_deref()))
+        .context(format!("failed to write hook {}", hook.path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook.path, std::fs::Permissions::from_mode(0o755)).context(
+            format!("failed to make hook {} executable", hook.path.display()),
+        )?;
+    }
+    println!(
+        "installed pre-send hook at {}. it runs before ngit publishes proposal events",
+        describe(&hook)
+    );
+    Ok(())
+}
+
+pub fn launch_uninstall() -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let hook = get_pre_send_hook(&git_repo)?;
+    if hook.from_git_config {
+        // it may be a script shared with the rest of the team
+        bail!(
+            "not removing {} as nostr.pre-send-hook points to it. run `git config --unset nostr.pre-send-hook` to stop using it",
+            hook.path.display()
+        );
+    }
+    if !hook.path.exists() {
+        println!("no pre-send hook installed");
+        return Ok(());
+    }
+    std::fs::remove_file(&hook.path)
+        .context(format!("failed to remove hook {}", hook.path.display()))?;
+    println!("removed pre-send hook {}", hook.path.display());
+    Ok(())
+}
+
+pub fn launch_status() -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let hook = get_pre_send_hook(&git_repo)?;
+    if !hook.path.exists() {
+        println!("no pre-send hook installed. add one with `ngit hooks install <command>`");
+    } else if is_executable(&hook.path) {
+        println!("pre-send hook: {}", describe(&hook));
+    } else {
+        println!(
+            "pre-send hook: {} is ignored as it isn't executable",
+            describe(&hook)
+        );
+    }
+    Ok(())
+}
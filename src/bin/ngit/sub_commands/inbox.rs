@@ -0,0 +1,163 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::Result;
+use ngit::{
+    activity_log::get_filter_activity_replies,
+    client::{
+        get_event_from_global_cache, get_filter_contributor_profiles,
+        get_filter_maintained_repo_announcements, get_filter_proposals_and_issues,
+        get_filter_repo_announcements_by_identifier, save_event_in_global_cache,
+    },
+    inbox::{build_inbox, maintained_repos, render_inbox},
+    read_state::{load_or_start_inbox_read_state, mark_inbox_seen},
+};
+use nostr::{Event, PublicKey, Timestamp};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, Params},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// print the summary as json. items are not marked as read
+    #[arg(long, action)]
+    pub(crate) json: bool,
+}
+
+/// fetch from `relays` into the global cache and return what the cache holds
+/// for `filters`, so repositories on unreachable relays still show what was
+/// fetched before
+async fn fetch_into_global_cache(
+    client: &Client,
+    git_repo_path: Option<&Path>,
+    relays: Vec<String>,
+    filters: Vec<nostr::Filter>,
+) -> Result<Vec<Event>> {
+    for event in client
+        .get_events(relays, filters.clone())
+        .await
+        .unwrap_or_default()
+    {
+        save_event_in_global_cache(git_repo_path, &event).await?;
+    }
+    get_event_from_global_cache(git_repo_path, filters).await
+}
+
+fn dedup(relays: Vec<String>) -> Vec<String> {
+    relays
+        .into_iter()
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    let git_repo_path = git_repo.as_ref().map(|r| r.get_path()).transpose()?;
+    let now = Timestamp::now();
+
+    let client = Client::new(Params::with_config(config));
+
+    let (_, user_ref, _) = login::login_or_signup(
+        &git_repo.as_ref(),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        false,
+    )
+    .await?;
+
+    let relays = dedup(
+        [
+            user_ref.relays.write(),
+            client.get_fallback_relays().clone(),
+        ]
+        .concat(),
+    );
+
+    let repos = {
+        let own = fetch_into_global_cache(
+            &client,
+            git_repo_path,
+            relays.clone(),
+            vec![get_filter_maintained_repo_announcements(
+                &user_ref.public_key,
+            )],
+        )
+        .await?;
+        let identifiers = own
+            .iter()
+            .filter_map(|e| e.tags.identifier().map(str::to_string))
+            .collect::<HashSet<String>>();
+        let announcements = if identifiers.is_empty() {
+            own
+        } else {
+            fetch_into_global_cache(
+                &client,
+                git_repo_path,
+                relays.clone(),
+                vec![get_filter_repo_announcements_by_identifier(&identifiers)],
+            )
+            .await?
+        };
+        maintained_repos(&user_ref.public_key, &announcements)
+    };
+
+    if repos.is_empty() {
+        client.disconnect().await?;
+        println!("no repositories found that you maintain. announce one with `ngit init`");
+        return Ok(());
+    }
+
+    let repo_relays = dedup(
+        [
+            relays,
+            repos.iter().flat_map(|r| r.relays.clone()).collect(),
+        ]
+        .concat(),
+    );
+    let threads = fetch_into_global_cache(
+        &client,
+        git_repo_path,
+        repo_relays.clone(),
+        vec![get_filter_proposals_and_issues(
+            &repos.iter().flat_map(|r| r.coordinates.clone()).collect(),
+        )],
+    )
+    .await?;
+    let mut events = threads.clone();
+    if !threads.is_empty() {
+        events.extend(
+            fetch_into_global_cache(
+                &client,
+                git_repo_path,
+                repo_relays,
+                vec![
+                    get_filter_activity_replies(&threads),
+                    get_filter_contributor_profiles(
+                        threads
+                            .iter()
+                            .map(|e| e.pubkey)
+                            .collect::<HashSet<PublicKey>>(),
+                    ),
+                ],
+            )
+            .await?,
+        );
+    }
+    client.disconnect().await?;
+
+    let mut read_state = load_or_start_inbox_read_state(git_repo_path)?;
+    let inbox = build_inbox(&repos, &events, &read_state, now);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&inbox)?);
+    } else {
+        println!("{}", render_inbox(&inbox, now));
+        mark_inbox_seen(git_repo_path, &mut read_state)?;
+    }
+    Ok(())
+}
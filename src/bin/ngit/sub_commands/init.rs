@@ -1,33 +1,51 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
 use console::{Style, Term};
 use ngit::{
-    cli_interactor::PromptConfirmParms,
+    cli_interactor::{self, PromptConfirmParms},
+    client::{get_event_from_global_cache, get_events_from_local_cache, sign_event},
+    error::{ErrorCategory, NgitError},
     git::nostr_url::{NostrUrlDecoded, save_nip05_to_git_config_cache},
+    login::user::UserRef,
+    proxy::{ProxyUse, ensure_onion_url_has_proxy},
 };
 use nostr::{
-    FromBech32, PublicKey, ToBech32,
+    EventBuilder, FromBech32, JsonUtil, PublicKey, Tag, ToBech32,
     nips::{
         nip01::Coordinate,
         nip05::{self},
+        nip98::{HttpData, HttpMethod},
     },
 };
-use nostr_sdk::{Kind, RelayUrl};
+use nostr_sdk::{Kind, NostrSigner, RelayUrl};
+use serde::Deserialize;
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
     cli_interactor::{Interactor, InteractorPrompt, PromptInputParms},
-    client::{Client, Connect, fetching_with_report, get_repo_ref_from_cache, send_events},
+    client::{
+        Client, Connect, Params, correct_clock_ahead_after_rejection, fetching_with_report,
+        get_repo_ref_from_cache, send_events, warn_on_clock_skew,
+    },
+    config::Config,
     git::{Repo, RepoActions, nostr_url::convert_clone_url_to_https},
     login,
     repo_ref::{
-        RepoRef, extract_pks, get_repo_config_from_yaml, save_repo_config_to_yaml,
-        try_and_get_repo_coordinates_when_remote_unknown,
+        RepoRef, SubmissionPolicy, announcement_from_tags, extract_pks, get_default_identifier,
+        get_repo_config_from_yaml, merge_announcement_tags, save_repo_config_to_yaml,
+        try_and_get_repo_coordinates_when_remote_unknown, validate_identifier,
     },
 };
 
-#[derive(Debug, clap::Args)]
+#[derive(Debug, Default, clap::Args)]
 pub struct SubCommandArgs {
     #[clap(short, long)]
     /// name of repository
@@ -51,26 +69,80 @@ pub struct SubCommandArgs {
     /// usually root commit but will be more recent commit for forks
     earliest_unique_commit: Option<String>,
     #[clap(short, long)]
-    /// shortname with no spaces or special characters
+    /// shortname with no spaces or special characters. defaults to the
+    /// repository name of a github remote or the directory name
     identifier: Option<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// gitignore-style patterns for refs to leave out of the nostr state
+    /// event, eg. "refs/heads/ci/*,refs/heads/tmp/*"
+    state_ref_ignore: Vec<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// git servers that are read-only mirrors so pushes skip them, eg.
+    /// "https://github.com/example/repo.git"
+    mirror: Vec<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// git servers, usually grasp servers, that accept contributor pushes to
+    /// refs/heads/contrib/<npub>/* so proposals can be fetched as git objects
+    contributor_push: Vec<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// blossom media servers that store proposal attachments sent with
+    /// `ngit send --attach`, eg. "https://blossom.example"
+    blossoms: Vec<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// npubs of bots whose ci results are shown on proposals, eg. in `ngit
+    /// list`
+    ci_bots: Vec<String>,
+    #[clap(long, value_delimiter = ',')]
+    /// what ngit send checks proposals against, eg.
+    /// "target-branch=develop,cover-letter=required,max-patches=20"
+    submission_policy: Vec<String>,
+    #[clap(long, action)]
+    /// don't create the repository on grasp servers listed as clone urls
+    /// before publishing the announcement
+    skip_server_setup: bool,
+    #[clap(long, action)]
+    /// don't publish the announcement to the blaster relays. overrides
+    /// nostr.use-blaster
+    no_blaster: bool,
+    #[clap(long, action)]
+    /// don't offer to import the name, description, homepage and topics of a
+    /// github or gitlab remote
+    no_import: bool,
 }
 
 #[allow(clippy::too_many_lines)]
-pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
     let git_repo = Repo::discover().context("failed to find a git repository")?;
     let git_repo_path = git_repo.get_path()?;
 
-    let root_commit = git_repo
-        .get_root_commit()
-        .context("failed to get root commit of the repository")?;
+    let submission_policy = if args.submission_policy.is_empty() {
+        None
+    } else {
+        Some(SubmissionPolicy::parse(&args.submission_policy).category(NgitError::Config)?)
+    };
+
+    let ci_bots = args
+        .ci_bots
+        .iter()
+        .map(|npub| {
+            PublicKey::parse(npub)
+                .context(format!("invalid --ci-bots npub {npub}"))
+                .category(NgitError::Config)
+        })
+        .collect::<Result<Vec<PublicKey>>>()?;
+
+    // none until the first commit, which adds it to the announcement on push
+    let root_commit = git_repo.get_root_commit().ok();
 
-    // TODO: check for empty repo
     // TODO: check for existing maintaiers file
 
-    let mut client = Client::default();
+    let mut client = Client::new(Params::with_config(config));
+    if args.no_blaster {
+        client.disable_blaster_relays();
+    }
 
     let repo_coordinate = if let Ok(repo_coordinate) =
-        try_and_get_repo_coordinates_when_remote_unknown(&git_repo).await
+        try_and_get_repo_coordinates_when_remote_unknown(&git_repo, None).await
     {
         Some(repo_coordinate)
     } else {
@@ -78,7 +150,8 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     };
 
     let repo_ref = if let Some(repo_coordinate) = &repo_coordinate {
-        fetching_with_report(git_repo_path, &client, repo_coordinate).await?;
+        let report = fetching_with_report(git_repo_path, &client, repo_coordinate, false).await?;
+        warn_on_clock_skew(&report, cli_args.fix_timestamp)?;
         if let Ok(repo_ref) = get_repo_ref_from_cache(Some(git_repo_path), repo_coordinate).await {
             Some(repo_ref)
         } else {
@@ -100,54 +173,107 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     let repo_config_result = get_repo_config_from_yaml(&git_repo);
     // TODO: check for other claims
 
+    let existing_identifier = if let Some(repo_ref) = &repo_ref {
+        Some(repo_ref.identifier.clone())
+    } else {
+        repo_coordinate
+            .as_ref()
+            .map(|coordinate| coordinate.identifier.clone())
+    };
+
+    // the user's latest announcement is the baseline for an update so fields
+    // left unchanged are published exactly as before
+    let latest_own_event = repo_ref.as_ref().and_then(|repo_ref| {
+        repo_ref
+            .events
+            .values()
+            .filter(|e| e.pubkey == user_ref.public_key)
+            .max_by_key(|e| e.created_at)
+            .cloned()
+    });
+
+    let mut identifier_arg = args.identifier.clone();
+    let (identifier, baseline_event) = loop {
+        let identifier = if let Some(identifier) = identifier_arg.take() {
+            if let Err(error) = validate_new_identifier(&identifier, existing_identifier.as_deref())
+            {
+                bail!(NgitError::Config(anyhow!("invalid --identifier: {error}")));
+            }
+            identifier
+        } else {
+            let allowed = existing_identifier.clone();
+            Interactor::default().input(
+                PromptInputParms::default()
+                    .with_prompt(
+                        "repo identifier (typically the short name with hypens instead of spaces)",
+                    )
+                    .with_flag("--identifier")
+                    .with_default(if let Some(identifier) = &existing_identifier {
+                        identifier.clone()
+                    } else if let Some(identifier) = repo_config_result
+                        .as_ref()
+                        .ok()
+                        .and_then(|config| config.identifier.clone())
+                    {
+                        identifier
+                    } else {
+                        get_default_identifier(&git_repo)
+                    })
+                    .with_validator(move |identifier| {
+                        validate_new_identifier(identifier, allowed.as_deref())
+                    }),
+            )?
+        };
+        if latest_own_event
+            .as_ref()
+            .is_some_and(|e| e.tags.identifier() == Some(identifier.as_str()))
+        {
+            break (identifier, latest_own_event);
+        }
+        let Some(announcement) =
+            find_own_announcement(git_repo_path, &client, &user_ref, &identifier).await?
+        else {
+            break (identifier, latest_own_event);
+        };
+        if confirm_unless_yes(
+            cli_args.yes,
+            PromptConfirmParms::default()
+                .with_prompt(format!(
+                    "you already announced a repository with identifier '{identifier}'. edit that one instead?"
+                ))
+                .with_default(true),
+        )? {
+            break (identifier, Some(announcement));
+        }
+        if args.identifier.is_some() {
+            bail!(NgitError::UserAbort(anyhow!(
+                "aborting so your existing announcement with identifier '{identifier}' is not replaced"
+            )));
+        }
+    };
+    let baseline_ref = baseline_event
+        .as_ref()
+        .and_then(|e| RepoRef::try_from((e.clone(), None)).ok());
+    let existing_ref = baseline_ref.as_ref().or(repo_ref.as_ref());
+
+    let imported = if existing_ref.is_none() && !args.no_import {
+        import_remote_metadata(&git_repo, cli_args.yes, config).await?
+    } else {
+        None
+    };
+
     let name = match &args.title {
         Some(t) => t.clone(),
         None => Interactor::default().input(
             PromptInputParms::default()
                 .with_prompt("repo name")
-                .with_default(if let Some(repo_ref) = &repo_ref {
+                .with_flag("--title")
+                .with_default(if let Some(repo_ref) = existing_ref {
                     repo_ref.name.clone()
-                } else if let Some(coordinate) = &repo_coordinate {
-                    coordinate.identifier.clone()
+                } else if let Some(imported) = imported.as_ref().filter(|i| !i.name.is_empty()) {
+                    imported.name.clone()
                 } else {
-                    String::new()
-                }),
-        )?,
-    };
-
-    let identifier = match &args.identifier {
-        Some(t) => t.clone(),
-        None => Interactor::default().input(
-            PromptInputParms::default()
-                .with_prompt(
-                    "repo identifier (typically the short name with hypens instead of spaces)",
-                )
-                .with_default(if let Some(repo_ref) = &repo_ref {
-                    repo_ref.identifier.clone()
-                } else if let Some(repo_coordinate) = &repo_coordinate {
-                    repo_coordinate.identifier.clone()
-                } else {
-                    let fallback = name
-                        .clone()
-                        .replace(' ', "-")
-                        .chars()
-                        .map(|c| {
-                            if c.is_ascii_alphanumeric() || c.eq(&'/') {
-                                c
-                            } else {
-                                '-'
-                            }
-                        })
-                        .collect();
-                    if let Ok(config) = &repo_config_result {
-                        if let Some(identifier) = &config.identifier {
-                            identifier.to_string()
-                        } else {
-                            fallback
-                        }
-                    } else {
-                        fallback
-                    }
+                    identifier.clone()
                 }),
         )?,
     };
@@ -157,23 +283,47 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         None => Interactor::default().input(
             PromptInputParms::default()
                 .with_prompt("repo description (one sentance)")
+                .with_flag("--description")
                 .optional()
-                .with_default(if let Some(repo_ref) = &repo_ref {
+                .with_default(if let Some(repo_ref) = existing_ref {
                     repo_ref.description.clone()
+                } else if let Some(imported) = &imported {
+                    imported.description.clone()
                 } else {
                     String::new()
                 }),
         )?,
     };
 
+    // only offered for topics imported from github or gitlab
+    let hashtags: Vec<String> = match &imported {
+        Some(imported) if !imported.topics.is_empty() => Interactor::default()
+            .input(
+                PromptInputParms::default()
+                    .with_prompt("hashtags (space seperated)")
+                    .optional()
+                    .with_default(imported.topics.join(" ")),
+            )?
+            .split_whitespace()
+            .map(std::string::ToString::to_string)
+            .collect(),
+        _ => vec![],
+    };
+
     let maintainers: Vec<PublicKey> = {
         let mut dont_ask = !args.other_maintainers.is_empty();
         let mut maintainers_string = if !args.other_maintainers.is_empty() {
             [args.other_maintainers.clone()].concat().join(" ")
-        } else if repo_ref.is_none() && repo_config_result.is_err() {
+        } else if existing_ref.is_none() && repo_config_result.is_err() {
             user_ref.public_key.to_bech32()?
         } else {
-            let maintainers = if let Ok(config) = &repo_config_result {
+            let maintainers = if let Some(baseline_ref) = &baseline_ref {
+                baseline_ref
+                    .maintainers
+                    .iter()
+                    .map(|k| k.to_bech32().unwrap())
+                    .collect()
+            } else if let Ok(config) = &repo_config_result {
                 config.maintainers.clone()
             } else if let Some(repo_ref) = &repo_ref {
                 repo_ref
@@ -203,7 +353,8 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         };
         'outer: loop {
             if !dont_ask && user_ref.public_key.to_bech32()?.eq(&maintainers_string) {
-                if Interactor::default().confirm(
+                if confirm_unless_yes(
+                    cli_args.yes,
                     PromptConfirmParms::default()
                         .with_prompt("are you the only maintainer?")
                         .with_default(true),
@@ -214,6 +365,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                     if !Interactor::default().confirm(
                         PromptConfirmParms::default()
                             .with_prompt("are the other maintainers on nostr?")
+                            .with_flag("--other-maintainers")
                             .with_default(true),
                     )? {
                         opt_out_default = true;
@@ -226,6 +378,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                     if Interactor::default().confirm(
                         PromptConfirmParms::default()
                             .with_prompt("opt-out of storing git state on nostr and relay on git server for now? you will still receive PRs and issues via nostr")
+                            .with_flag("--other-maintainers")
                             .with_default(true),
                     )? {
                         git_repo.save_git_config_item("nostr.nostate", "true", opt_out_default)?;
@@ -237,6 +390,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                 maintainers_string = Interactor::default().input(
                     PromptInputParms::default()
                         .with_prompt("maintainers - space seperated list of npubs")
+                        .with_flag("--other-maintainers")
                         .with_default(maintainers_string),
                 )?;
             }
@@ -258,7 +412,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         }
     };
 
-    let git_server = if args.clone_url.is_empty() {
+    let mut git_server: Vec<String> = if args.clone_url.is_empty() {
         let no_state = if let Ok(Some(s)) = git_repo.get_git_config_item("nostr.nostate", None) {
             s == "true"
         } else {
@@ -280,24 +434,37 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         println!(
             "a lightweight git server implementation for use with nostr, requiring no signup, is in development. several providers have shown interest in hosting it. for now use github, codeberg, or self-hosted song, forge, etc."
         );
+        let default_grasp_urls = config
+            .default_grasp_servers
+            .value
+            .iter()
+            .filter_map(|server| grasp_clone_url(server, &user_ref.public_key, &identifier));
         Interactor::default()
             .input(
                 PromptInputParms::default()
                     .with_prompt("git server remote url(s) (space seperated)")
-                    .with_default(if let Some(repo_ref) = &repo_ref {
+                    .with_flag("--clone-url")
+                    .with_default(if let Some(repo_ref) = existing_ref {
                         repo_ref.git_server.clone().join(" ")
-                    } else if let Ok(url) = git_repo.get_origin_url() {
-                        if let Ok(fetch_url) = convert_clone_url_to_https(&url) {
-                            fetch_url
-                        } else if url.starts_with("nostr://") {
-                            // nostr added as origin remote before repo announcement sent
-                            String::new()
-                        } else {
-                            // local repo or custom protocol
-                            url
-                        }
                     } else {
-                        String::new()
+                        let origin = if let Ok(url) = git_repo.get_origin_url() {
+                            if let Ok(fetch_url) = convert_clone_url_to_https(&url) {
+                                fetch_url
+                            } else if url.starts_with("nostr://") {
+                                // nostr added as origin remote before repo announcement sent
+                                String::new()
+                            } else {
+                                // local repo or custom protocol
+                                url
+                            }
+                        } else {
+                            String::new()
+                        };
+                        std::iter::once(origin)
+                            .chain(default_grasp_urls)
+                            .filter(|url| !url.is_empty())
+                            .collect::<Vec<String>>()
+                            .join(" ")
                     }),
             )?
             .split(' ')
@@ -306,13 +473,39 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     } else {
         args.clone_url.clone()
     };
+    if let Some(clone_url) = imported
+        .as_ref()
+        .map(|imported| imported.clone_url.clone())
+        .filter(|clone_url| {
+            !clone_url.is_empty()
+                && !git_server
+                    .iter()
+                    .any(|url| is_same_clone_url(url, clone_url))
+        })
+    {
+        if confirm_unless_yes(
+            cli_args.yes,
+            PromptConfirmParms::default()
+                .with_prompt(format!("also list {clone_url} as a git server?"))
+                .with_default(true),
+        )? {
+            git_server.retain(|url| !url.is_empty());
+            git_server.push(clone_url);
+        }
+    }
 
     // TODO: when NIP-66 is functional, use this to reccommend relays and filter out
     //       relays that won't accept contributors events. NIP-11 'limitations'
     //       isn't widely used enough to be usedful.
 
     let relays: Vec<RelayUrl> = {
-        let mut default = if let Ok(config) = &repo_config_result {
+        let mut default = if let Some(baseline_ref) = &baseline_ref {
+            baseline_ref
+                .relays
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>()
+        } else if let Ok(config) = &repo_config_result {
             config.relays.clone()
         } else if let Some(repo_ref) = &repo_ref {
             repo_ref
@@ -332,6 +525,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                     .input(
                         PromptInputParms::default()
                             .with_prompt("relays")
+                            .with_flag("--relays")
                             .with_default(default),
                     )?
                     .split(' ')
@@ -359,9 +553,17 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
             .input(
                 PromptInputParms::default()
                     .with_prompt("repo website")
+                    .with_flag("--web")
                     .optional()
-                    .with_default(if let Some(repo_ref) = &repo_ref {
+                    .with_default(if let Some(repo_ref) = existing_ref {
                         repo_ref.web.clone().join(" ")
+                    } else if let Some(imported) =
+                        imported.as_ref().filter(|i| !i.homepage.is_empty())
+                    {
+                        format!(
+                            "{} https://gitworkshop.dev/repo/{}",
+                            imported.homepage, &identifier
+                        )
                     } else {
                         format!("https://gitworkshop.dev/repo/{}", &identifier)
                     }),
@@ -375,12 +577,11 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
 
     let earliest_unique_commit = if let Some(t) = &args.earliest_unique_commit {
         t.clone()
-    } else {
-        let mut earliest_unique_commit = if let Some(repo_ref) = &repo_ref {
-            repo_ref.root_commit.clone()
-        } else {
-            root_commit.to_string()
-        };
+    } else if let Some(root_commit) = root_commit {
+        let mut earliest_unique_commit = existing_ref
+            .map(|repo_ref| repo_ref.root_commit.clone())
+            .filter(|commit| !commit.is_empty())
+            .unwrap_or(root_commit.to_string());
         println!(
             "the earliest unique commit helps with discoverability. It defaults to the root commit. Only change this if your repo has completely forked off an has formed its own identity."
         );
@@ -388,6 +589,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
             earliest_unique_commit = Interactor::default().input(
                 PromptInputParms::default()
                     .with_prompt("earliest unique commit (to help with discoverability)")
+                    .with_flag("--earliest-unique-commit")
                     .with_default(earliest_unique_commit.clone()),
             )?;
             if let Ok(exists) = git_repo.does_commit_exist(&earliest_unique_commit) {
@@ -402,9 +604,16 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                 println!("commit id must be 40 characters long");
             }
         }
+    } else {
+        println!(
+            "no commits yet so the root commit will be added to the announcement when you first push"
+        );
+        existing_ref
+            .map(|repo_ref| repo_ref.root_commit.clone())
+            .unwrap_or_default()
     };
 
-    println!("publishing repostory reference...");
+    let previous_maintainers = existing_ref.map(|repo_ref| repo_ref.maintainers.clone());
 
     let mut repo_ref = RepoRef {
         identifier: identifier.clone(),
@@ -416,23 +625,89 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         relays: relays.clone(),
         trusted_maintainer: user_ref.public_key,
         maintainers: maintainers.clone(),
+        state_ref_ignore: if args.state_ref_ignore.is_empty() {
+            existing_ref
+                .map(|repo_ref| repo_ref.state_ref_ignore.clone())
+                .unwrap_or_default()
+        } else {
+            args.state_ref_ignore.clone()
+        },
+        mirrors: if args.mirror.is_empty() {
+            existing_ref
+                .map(|repo_ref| repo_ref.mirrors.clone())
+                .unwrap_or_default()
+        } else {
+            args.mirror.clone()
+        },
+        contributor_push: if args.contributor_push.is_empty() {
+            existing_ref
+                .map(|repo_ref| repo_ref.contributor_push.clone())
+                .unwrap_or_default()
+        } else {
+            args.contributor_push.clone()
+        },
+        blossoms: if args.blossoms.is_empty() {
+            existing_ref
+                .map(|repo_ref| repo_ref.blossoms.clone())
+                .unwrap_or_default()
+        } else {
+            args.blossoms.clone()
+        },
+        ci_bots: if ci_bots.is_empty() {
+            existing_ref
+                .map(|repo_ref| repo_ref.ci_bots.clone())
+                .unwrap_or_default()
+        } else {
+            ci_bots
+        },
+        submission_policy: submission_policy.unwrap_or_else(|| {
+            existing_ref
+                .map(|repo_ref| repo_ref.submission_policy.clone())
+                .unwrap_or_default()
+        }),
         events: HashMap::new(),
         nostr_git_url: None,
     };
-    let repo_event = repo_ref.to_event(&signer).await?;
+    if let Some(previous_maintainers) = &previous_maintainers {
+        confirm_maintainers_removal(previous_maintainers, &maintainers, cli_args.yes)?;
+    }
+    let tags = if let Some(baseline_event) = &baseline_event {
+        let tags = merge_announcement_tags(baseline_event, &repo_ref);
+        confirm_announcement_changes(baseline_event, &tags, cli_args.yes)?
+    } else {
+        let mut tags = repo_ref.to_tags();
+        tags.extend(hashtags.into_iter().map(Tag::hashtag));
+        Some(tags)
+    };
+
+    if tags.is_some() && !args.skip_server_setup {
+        set_up_grasp_servers(&repo_ref, &user_ref.public_key, &signer, config).await?;
+    }
 
-    client.set_signer(signer).await;
+    if let Some(tags) = tags {
+        println!("publishing repostory reference...");
 
-    send_events(
-        &client,
-        Some(git_repo_path),
-        vec![repo_event],
-        user_ref.relays.write(),
-        relays.clone(),
-        !cli_args.disable_cli_spinners,
-        false,
-    )
-    .await?;
+        client.set_signer(signer.clone()).await;
+
+        // signed and sent again if relays reveal the local clock is ahead
+        loop {
+            let repo_event = announcement_from_tags(tags.clone(), &signer).await?;
+
+            let accepted = send_events(
+                &client,
+                Some(git_repo_path),
+                vec![repo_event.clone()],
+                user_ref.relays.write(),
+                relays.clone(),
+                !cli_args.disable_cli_spinners,
+                false,
+            )
+            .await?;
+            if !correct_clock_ahead_after_rejection(&accepted, &[repo_event])? {
+                break;
+            }
+        }
+    }
 
     // TODO - does this git config item do more harm than good?
     git_repo.save_git_config_item(
@@ -497,7 +772,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         }
     };
 
-    prompt_to_set_nostr_url_as_origin(&repo_ref, &git_repo).await?;
+    prompt_to_set_nostr_url_as_origin(&repo_ref, &git_repo, cli_args.yes).await?;
 
     if !hint_for_nip05_address.is_empty() {
         println!("{hint_for_nip05_address}");
@@ -546,7 +821,446 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     Ok(())
 }
 
-async fn prompt_to_set_nostr_url_as_origin(repo_ref: &RepoRef, git_repo: &Repo) -> Result<()> {
+/// how long to wait for each request to a grasp server
+static GRASP_SETUP_TIMEOUT_SECS: u64 = 30;
+
+enum GraspSetup {
+    Created,
+    AlreadyExists,
+    Failed(anyhow::Error),
+}
+
+impl Display for GraspSetup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraspSetup::Created => write!(f, "created"),
+            GraspSetup::AlreadyExists => write!(f, "already exists"),
+            GraspSetup::Failed(error) => write!(f, "failed: {error:#}"),
+        }
+    }
+}
+
+/// grasp servers host each maintainer's repository at /<npub>/<identifier>.git
+fn is_grasp_clone_url(url: &str, public_key: &PublicKey, identifier: &str) -> bool {
+    let Ok(npub) = public_key.to_bech32() else {
+        return false;
+    };
+    nostr::Url::parse(url).is_ok_and(|url| url.path() == format!("/{npub}/{identifier}.git"))
+}
+
+/// the clone url a grasp server would host the repository at, accepting
+/// servers listed as relay urls or bare domains
+fn grasp_clone_url(server: &str, public_key: &PublicKey, identifier: &str) -> Option<String> {
+    let server = server.trim().trim_end_matches('/');
+    let base = if let Some(rest) = server.strip_prefix("wss://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = server.strip_prefix("ws://") {
+        format!("http://{rest}")
+    } else if server.contains("://") {
+        server.to_string()
+    } else if server.is_empty() {
+        return None;
+    } else {
+        format!("https://{server}")
+    };
+    let npub = public_key.to_bech32().ok()?;
+    Some(format!("{base}/{npub}/{identifier}.git"))
+}
+
+/// create the repository on grasp servers before it is announced so the
+/// first push doesn't 404. a failure is reported but doesn't stop the
+/// announcement being published
+async fn set_up_grasp_servers(
+    repo_ref: &RepoRef,
+    public_key: &PublicKey,
+    signer: &Arc<dyn NostrSigner>,
+    config: &Config,
+) -> Result<()> {
+    let grasp_urls = repo_ref
+        .git_server
+        .iter()
+        .filter(|url| is_grasp_clone_url(url, public_key, &repo_ref.identifier))
+        .collect::<Vec<&String>>();
+    if grasp_urls.is_empty() {
+        return Ok(());
+    }
+    let mut http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(GRASP_SETUP_TIMEOUT_SECS))
+        .no_proxy();
+    if let Some(proxy) = &config.git_proxy.value {
+        http = http.proxy(reqwest::Proxy::all(proxy).context("invalid git server proxy")?);
+    }
+    let http = http.build()?;
+    for url in grasp_urls {
+        let outcome =
+            match create_repo_on_grasp_server(&http, url, signer, &config.git_proxy.value).await {
+                Ok(true) => GraspSetup::Created,
+                Ok(false) => GraspSetup::AlreadyExists,
+                Err(error) => GraspSetup::Failed(error),
+            };
+        println!("grasp server {url}: {outcome}");
+    }
+    Ok(())
+}
+
+/// returns false if the repository already exists
+async fn create_repo_on_grasp_server(
+    http: &reqwest::Client,
+    url: &str,
+    signer: &Arc<dyn NostrSigner>,
+    proxy: &Option<String>,
+) -> Result<bool> {
+    ensure_onion_url_has_proxy(url, proxy, ProxyUse::GitServers)?;
+    if answers_info_refs(http, url).await? {
+        return Ok(false);
+    }
+    let auth_event = sign_event(
+        EventBuilder::http_auth(HttpData::new(nostr::Url::parse(url)?, HttpMethod::POST)),
+        signer,
+    )
+    .await?;
+    let response = http
+        .post(url)
+        .header(
+            "Authorization",
+            format!("Nostr {}", BASE64_STANDARD.encode(auth_event.as_json())),
+        )
+        .send()
+        .await
+        .context("failed to request repository creation")?;
+    if response.status() == reqwest::StatusCode::CONFLICT {
+        return Ok(false);
+    }
+    if !response.status().is_success() {
+        bail!("repository creation responded {}", response.status());
+    }
+    if !answers_info_refs(http, url).await? {
+        bail!("repository created but {url}/info/refs doesn't answer");
+    }
+    Ok(true)
+}
+
+async fn answers_info_refs(http: &reqwest::Client, url: &str) -> Result<bool> {
+    Ok(http
+        .get(format!("{url}/info/refs?service=git-upload-pack"))
+        .send()
+        .await
+        .context(format!("failed to connect to {url}"))?
+        .status()
+        .is_success())
+}
+
+/// how long to wait for the github or gitlab api when importing metadata
+static IMPORT_TIMEOUT_SECS: u64 = 15;
+
+#[derive(Debug, PartialEq)]
+enum ImportPlatform {
+    GitHub,
+    GitLab,
+}
+
+/// a github or gitlab repository that metadata can be imported from
+#[derive(Debug, PartialEq)]
+struct ImportSource {
+    platform: ImportPlatform,
+    /// `owner/name`, or `group/subgroup/name` on gitlab
+    path: String,
+}
+
+impl Display for ImportSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let host = match self.platform {
+            ImportPlatform::GitHub => "github.com",
+            ImportPlatform::GitLab => "gitlab.com",
+        };
+        write!(f, "{host}/{}", self.path)
+    }
+}
+
+/// details of a github or gitlab repository, offered as prompt defaults
+#[derive(Debug)]
+struct ImportedMetadata {
+    name: String,
+    description: String,
+    homepage: String,
+    topics: Vec<String>,
+    clone_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    name: String,
+    description: Option<String>,
+    homepage: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    clone_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    http_url_to_repo: String,
+}
+
+/// the github or gitlab repository a remote url points to
+fn import_source_from_url(url: &str) -> Option<ImportSource> {
+    for (platform, host) in [
+        (ImportPlatform::GitHub, "github.com"),
+        (ImportPlatform::GitLab, "gitlab.com"),
+    ] {
+        let Some(path) = [
+            format!("https://{host}/"),
+            format!("http://{host}/"),
+            format!("git@{host}:"),
+            format!("ssh://git@{host}/"),
+        ]
+        .iter()
+        .find_map(|prefix| url.strip_prefix(prefix.as_str())) else {
+            continue;
+        };
+        // gitlab web urls continue with /-/tree/<branch> etc.
+        let path = path.split("/-/").next().unwrap_or_default();
+        let path = path.trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let segments = path.split('/').collect::<Vec<&str>>();
+        if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+            return None;
+        }
+        let path = if platform == ImportPlatform::GitHub {
+            segments[..2].join("/")
+        } else {
+            path.to_string()
+        };
+        return Some(ImportSource { platform, path });
+    }
+    None
+}
+
+/// the repository of the first github or gitlab remote, origin first
+fn get_import_source(git_repo: &Repo) -> Option<ImportSource> {
+    let mut remote_names = git_repo
+        .git_repo
+        .remotes()
+        .ok()?
+        .iter()
+        .flatten()
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    remote_names.sort_by_key(|name| name != "origin");
+    remote_names.iter().find_map(|name| {
+        git_repo
+            .git_repo
+            .find_remote(name)
+            .ok()
+            .and_then(|remote| remote.url().and_then(import_source_from_url))
+    })
+}
+
+/// offer to prefill prompts from the github or gitlab repository of a
+/// remote. a failed request is reported but doesn't stop init
+async fn import_remote_metadata(
+    git_repo: &Repo,
+    yes: bool,
+    config: &Config,
+) -> Result<Option<ImportedMetadata>> {
+    // without a terminal the import can't be confirmed so it isn't offered
+    if !yes && !cli_interactor::prompts_allowed() {
+        return Ok(None);
+    }
+    let Some(source) = get_import_source(git_repo) else {
+        return Ok(None);
+    };
+    if !confirm_unless_yes(
+        yes,
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "import name, description, homepage and topics from {source}?"
+            ))
+            .with_default(true),
+    )? {
+        return Ok(None);
+    }
+    match fetch_imported_metadata(&source, config).await {
+        Ok(imported) => Ok(Some(imported)),
+        Err(error) => {
+            eprintln!("couldn't import from {source}: {error:#}");
+            Ok(None)
+        }
+    }
+}
+
+/// the public api, or a local fixture in tests
+fn get_import_api_url(platform: &ImportPlatform) -> String {
+    let (url, test_env_var) = match platform {
+        ImportPlatform::GitHub => ("https://api.github.com", "NGITTEST_GITHUB_API_URL"),
+        ImportPlatform::GitLab => ("https://gitlab.com/api/v4", "NGITTEST_GITLAB_API_URL"),
+    };
+    if std::env::var("NGITTEST").is_ok() {
+        if let Ok(url) = std::env::var(test_env_var) {
+            return url;
+        }
+    }
+    url.to_string()
+}
+
+async fn fetch_imported_metadata(
+    source: &ImportSource,
+    config: &Config,
+) -> Result<ImportedMetadata> {
+    let mut http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(IMPORT_TIMEOUT_SECS))
+        // github rejects requests without one
+        .user_agent("ngit")
+        .no_proxy();
+    if let Some(proxy) = &config.git_proxy.value {
+        http = http.proxy(reqwest::Proxy::all(proxy).context("invalid git server proxy")?);
+    }
+    let http = http.build()?;
+    let api_url = get_import_api_url(&source.platform);
+    let request = match source.platform {
+        ImportPlatform::GitHub => {
+            let request = http
+                .get(format!("{api_url}/repos/{}", source.path))
+                .header("Accept", "application/vnd.github+json");
+            // public repositories don't need a token but private ones do
+            match std::env::var("GITHUB_TOKEN") {
+                Ok(token) if !token.is_empty() => request.bearer_auth(token),
+                _ => request,
+            }
+        }
+        ImportPlatform::GitLab => http.get(format!(
+            "{api_url}/projects/{}",
+            source.path.replace('/', "%2F")
+        )),
+    };
+    let response = request
+        .send()
+        .await
+        .context(format!("failed to connect to {api_url}"))?;
+    if !response.status().is_success() {
+        bail!("{api_url} responded {}", response.status());
+    }
+    let body = response.text().await?;
+    Ok(match source.platform {
+        ImportPlatform::GitHub => {
+            let repo: GitHubRepo =
+                serde_json::from_str(&body).context("unexpected response from github")?;
+            ImportedMetadata {
+                name: repo.name,
+                description: repo.description.unwrap_or_default(),
+                homepage: repo.homepage.unwrap_or_default(),
+                topics: repo.topics,
+                clone_url: repo.clone_url,
+            }
+        }
+        ImportPlatform::GitLab => {
+            let project: GitLabProject =
+                serde_json::from_str(&body).context("unexpected response from gitlab")?;
+            ImportedMetadata {
+                name: project.name,
+                description: project.description.unwrap_or_default(),
+                homepage: String::new(),
+                topics: project.topics,
+                clone_url: project.http_url_to_repo,
+            }
+        }
+    })
+}
+
+/// ignoring a trailing slash or .git
+fn is_same_clone_url(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let url = url.trim_end_matches('/');
+        url.strip_suffix(".git").unwrap_or(url).to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// removing a maintainer stops their announcement and state events being
+/// trusted so it has to be confirmed rather than slip through a default
+fn confirm_maintainers_removal(
+    previous: &[PublicKey],
+    updated: &[PublicKey],
+    yes: bool,
+) -> Result<()> {
+    let removed = previous
+        .iter()
+        .filter(|m| !updated.contains(m))
+        .map(|m| m.to_bech32())
+        .collect::<Result<Vec<String>, _>>()?;
+    if removed.is_empty() {
+        return Ok(());
+    }
+    println!("maintainers to be removed:");
+    for npub in &removed {
+        println!("  {npub}");
+    }
+    if !confirm_unless_yes(
+        yes,
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "remove {} maintainer{} from the repository announcement?",
+                removed.len(),
+                if removed.len() == 1 { "" } else { "s" }
+            ))
+            .with_default(false),
+    )? {
+        bail!(NgitError::UserAbort(anyhow!(
+            "aborting so maintainers are not removed from the repository announcement"
+        )));
+    }
+    Ok(())
+}
+
+/// shows how `tags` differ from the `baseline` announcement for confirmation
+/// before signing. None if there is nothing to publish
+fn confirm_announcement_changes(
+    baseline: &nostr::Event,
+    tags: &[Tag],
+    yes: bool,
+) -> Result<Option<Vec<Tag>>> {
+    let removed = baseline
+        .tags
+        .iter()
+        .filter(|t| !tags.contains(t))
+        .collect::<Vec<&Tag>>();
+    let added = tags
+        .iter()
+        .filter(|t| !baseline.tags.iter().any(|b| b == *t))
+        .collect::<Vec<&Tag>>();
+    if removed.is_empty() && added.is_empty() {
+        println!("repository announcement unchanged");
+        return Ok(None);
+    }
+    println!("repository announcement changes:");
+    for tag in removed {
+        println!("- {:?}", tag.as_slice());
+    }
+    for tag in added {
+        println!("+ {:?}", tag.as_slice());
+    }
+    if !confirm_unless_yes(
+        yes,
+        PromptConfirmParms::default()
+            .with_prompt("publish these changes?")
+            .with_default(true),
+    )? {
+        bail!(NgitError::UserAbort(anyhow!(
+            "aborting without publishing repository announcement changes"
+        )));
+    }
+    Ok(Some(tags.to_vec()))
+}
+
+async fn prompt_to_set_nostr_url_as_origin(
+    repo_ref: &RepoRef,
+    git_repo: &Repo,
+    yes: bool,
+) -> Result<()> {
     println!(
         "starting from your next commit, when you `git push` to a remote that uses your nostr url, it will store your repository state on nostr and update the state of the git server(s) you just listed."
     );
@@ -567,21 +1281,21 @@ async fn prompt_to_set_nostr_url_as_origin(repo_ref: &RepoRef, git_repo: &Repo)
                     println!(
                         "warning: currently git remote 'origin' is set to a different trusted maintainer with the same identifier"
                     );
-                    ask_to_set_origin_remote(repo_ref, git_repo)?;
+                    ask_to_set_origin_remote(repo_ref, git_repo, yes)?;
                 } else {
                     // origin is linked to a different identifier
                     println!(
                         "warning: currently git remote 'origin' is set to a different repository identifier"
                     );
-                    ask_to_set_origin_remote(repo_ref, git_repo)?;
+                    ask_to_set_origin_remote(repo_ref, git_repo, yes)?;
                 }
             } else {
                 // remote is non-nostr url
-                ask_to_set_origin_remote(repo_ref, git_repo)?;
+                ask_to_set_origin_remote(repo_ref, git_repo, yes)?;
             }
         } else {
             // no origin remote
-            ask_to_create_new_origin_remote(repo_ref, git_repo)?;
+            ask_to_create_new_origin_remote(repo_ref, git_repo, yes)?;
         }
     }
     println!("contributors can clone your repository by installing ngit and using this clone url:");
@@ -590,8 +1304,9 @@ async fn prompt_to_set_nostr_url_as_origin(repo_ref: &RepoRef, git_repo: &Repo)
     Ok(())
 }
 
-fn ask_to_set_origin_remote(repo_ref: &RepoRef, git_repo: &Repo) -> Result<()> {
-    if Interactor::default().confirm(
+fn ask_to_set_origin_remote(repo_ref: &RepoRef, git_repo: &Repo, yes: bool) -> Result<()> {
+    if confirm_unless_yes(
+        yes,
         PromptConfirmParms::default()
             .with_default(true)
             .with_prompt("set remote \"origin\" to the nostr url of your repository?"),
@@ -604,8 +1319,9 @@ fn ask_to_set_origin_remote(repo_ref: &RepoRef, git_repo: &Repo) -> Result<()> {
     Ok(())
 }
 
-fn ask_to_create_new_origin_remote(repo_ref: &RepoRef, git_repo: &Repo) -> Result<()> {
-    if Interactor::default().confirm(
+fn ask_to_create_new_origin_remote(repo_ref: &RepoRef, git_repo: &Repo, yes: bool) -> Result<()> {
+    if confirm_unless_yes(
+        yes,
         PromptConfirmParms::default()
             .with_default(true)
             .with_prompt("set remote \"origin\" to the nostr url of your repository?"),
@@ -617,3 +1333,129 @@ fn ask_to_create_new_origin_remote(repo_ref: &RepoRef, git_repo: &Repo) -> Resul
     }
     Ok(())
 }
+
+/// `--yes` answers yes without prompting
+/// identifiers already in use are accepted even if they wouldn't be allowed now
+fn validate_new_identifier(identifier: &str, existing: Option<&str>) -> Result<(), String> {
+    if existing == Some(identifier) {
+        Ok(())
+    } else {
+        validate_identifier(identifier)
+    }
+}
+
+/// the user's latest announcement with `identifier` in the caches, on their
+/// write relays or on the fallback relays
+async fn find_own_announcement(
+    git_repo_path: &Path,
+    client: &Client,
+    user_ref: &UserRef,
+    identifier: &str,
+) -> Result<Option<nostr::Event>> {
+    let filter = nostr::Filter::default()
+        .kind(Kind::GitRepoAnnouncement)
+        .author(user_ref.public_key)
+        .identifier(identifier);
+    let mut relays = user_ref.relays.write();
+    for relay in client.get_fallback_relays() {
+        if !relays.contains(relay) {
+            relays.push(relay.clone());
+        }
+    }
+    let mut events = get_events_from_local_cache(git_repo_path, vec![filter.clone()]).await?;
+    events.extend(get_event_from_global_cache(Some(git_repo_path), vec![filter.clone()]).await?);
+    events.extend(client.get_events(relays, vec![filter]).await?);
+    Ok(events
+        .into_iter()
+        .filter(|e| {
+            e.kind.eq(&Kind::GitRepoAnnouncement)
+                && e.pubkey.eq(&user_ref.public_key)
+                && e.tags.identifier() == Some(identifier)
+        })
+        .max_by_key(|e| e.created_at))
+}
+
+fn confirm_unless_yes(yes: bool, params: PromptConfirmParms) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    Interactor::default().confirm(params.with_flag("--yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod import_source_from_url {
+        use super::*;
+
+        fn github(path: &str) -> Option<ImportSource> {
+            Some(ImportSource {
+                platform: ImportPlatform::GitHub,
+                path: path.to_string(),
+            })
+        }
+
+        #[test]
+        fn github_https_and_ssh_urls() {
+            for url in [
+                "https://github.com/example/my-repo.git",
+                "https://github.com/example/my-repo/",
+                "git@github.com:example/my-repo.git",
+                "ssh://git@github.com/example/my-repo",
+                "https://github.com/example/my-repo/tree/main",
+            ] {
+                assert_eq!(import_source_from_url(url), github("example/my-repo"));
+            }
+        }
+
+        #[test]
+        fn gitlab_keeps_subgroups() {
+            assert_eq!(
+                import_source_from_url("https://gitlab.com/group/subgroup/my-repo/-/tree/main"),
+                Some(ImportSource {
+                    platform: ImportPlatform::GitLab,
+                    path: "group/subgroup/my-repo".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn other_hosts_and_incomplete_paths_are_ignored() {
+            for url in [
+                "https://codeberg.org/example/my-repo.git",
+                "https://github.com/example",
+                "https://localhost:1000",
+            ] {
+                assert_eq!(import_source_from_url(url), None);
+            }
+        }
+    }
+
+    mod grasp_clone_url {
+        use super::*;
+
+        #[test]
+        fn relay_urls_and_bare_domains_become_grasp_clone_urls() {
+            let public_key = nostr::Keys::generate().public_key();
+            let npub = public_key.to_bech32().unwrap();
+            for (server, base) in [
+                ("wss://relay.example.com", "https://relay.example.com"),
+                ("wss://relay.example.com/", "https://relay.example.com"),
+                ("ws://localhost:8080", "http://localhost:8080"),
+                ("relay.example.com", "https://relay.example.com"),
+                ("https://git.example.com", "https://git.example.com"),
+            ] {
+                let url = grasp_clone_url(server, &public_key, "my-repo").unwrap();
+                assert_eq!(url, format!("{base}/{npub}/my-repo.git"));
+                assert!(is_grasp_clone_url(&url, &public_key, "my-repo"));
+            }
+        }
+
+        #[test]
+        fn blank_server_is_skipped() {
+            let public_key = nostr::Keys::generate().public_key();
+            assert_eq!(grasp_clone_url(" ", &public_key, "my-repo"), None);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::{get_events_from_local_cache, get_repo_relays, print_repo_relays_notice, send_events},
+    labels::{apply_label_changes, format_labels, generate_label_event, proposal_labels},
+};
+use nostr_sdk::Kind;
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+    sub_commands::share::find_proposal,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one.
+    /// defaults to the proposal of the checked out branch
+    pub(crate) id: Option<String>,
+    /// labels to add, eg. "bug,breaking"
+    #[arg(long, value_delimiter = ',')]
+    pub(crate) add: Vec<String>,
+    /// labels to remove
+    #[arg(long, value_delimiter = ',')]
+    pub(crate) remove: Vec<String>,
+}
+
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+
+    let namespace = &config.label_namespace.value;
+    let trusted = if config.labels_allow_anyone.value {
+        None
+    } else {
+        Some(repo_ref.maintainers.as_slice())
+    };
+    let current = proposal_labels(
+        &proposal,
+        &get_events_from_local_cache(git_repo_path, vec![
+            nostr::Filter::default()
+                .kind(Kind::Label)
+                .event(proposal.id),
+        ])
+        .await?,
+        namespace,
+        trusted,
+    );
+
+    if args.add.is_empty() && args.remove.is_empty() {
+        if current.is_empty() {
+            println!("no labels");
+        } else {
+            println!("{}", format_labels(&current));
+        }
+        return Ok(());
+    }
+
+    let labels = apply_label_changes(&current, &args.add, &args.remove);
+    if labels.eq(&current) {
+        println!("no changes to labels");
+        return Ok(());
+    }
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if trusted.is_some_and(|trusted| !trusted.contains(&user_ref.public_key)) {
+        bail!(
+            "only maintainers can label proposals. set git config nostr.labels-allow-anyone to true to accept labels from anyone"
+        );
+    }
+
+    let (repo_relays, repo_relays_source) =
+        get_repo_relays(Some(git_repo_path), &repo_ref, client.get_fallback_relays()).await;
+    print_repo_relays_notice(repo_relays_source);
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![generate_label_event(&proposal, &repo_ref, namespace, &labels, &signer).await?],
+        user_ref.relays.write(),
+        repo_relays,
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+    client.disconnect().await?;
+
+    if labels.is_empty() {
+        println!("removed all labels");
+    } else {
+        println!("labels: {}", format_labels(&labels));
+    }
+    Ok(())
+}
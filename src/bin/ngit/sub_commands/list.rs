@@ -1,47 +1,128 @@
-use std::{io::Write, ops::Add};
+use std::{collections::HashSet, io::Write, ops::Add, path::Path};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use ngit::{
+    attachments::{Attachment, verify_attachment},
+    ci::{CiState, get_ci_bots, proposal_ci_status},
     client::{get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache},
+    error::NgitError,
     git_events::{
-        get_commit_id_from_patch, get_most_recent_patch_with_ancestors, status_kinds, tag_value,
+        PROPOSAL_EDIT_KIND, apply_proposal_edits, comment_kinds, compare_with_previous_revision,
+        event_has_expired, expiry_notice, get_commit_id_from_patch,
+        get_most_recent_patch_with_ancestors, status_kinds, tag_value,
     },
+    labels::{format_labels, proposal_labels},
+    output::{self, ahead_behind},
+    proposal_view::{proposal_attachments, proposal_status},
+    read_state::{UnreadActivity, load_or_start_read_state, mark_proposal_seen},
 };
-use nostr_sdk::Kind;
+use nostr_sdk::{Kind, Timestamp, hashes::sha1::Hash as Sha1Hash};
 
 use crate::{
-    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms},
+    cli_interactor::{
+        Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms,
+        PromptMultiChoiceParms, prompts_allowed,
+    },
     client::{
-        Client, Connect, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache,
+        Client, Connect, Params, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache_after_fetch,
+    },
+    config::Config,
+    git::{
+        Repo, RepoActions, backup::backup_branch, oid_to_sha1, oid_to_shorthand_string,
+        patch_id::is_content_merged, proposal_notes::get_proposal_notes, sha1_to_oid, str_to_sha1,
     },
-    git::{Repo, RepoActions, str_to_sha1},
     git_events::{
-        commit_msg_from_patch_oneliner, event_is_revision_root, event_to_cover_letter,
-        patch_supports_commit_ids,
+        commit_msg_from_patch_oneliner, diff_from_patch, event_is_deleted, event_is_revision_root,
+        event_to_cover_letter, patch_diffstat, patch_supports_commit_ids, sanitize_file_name,
+        unique_file_name,
     },
-    repo_ref::get_repo_coordinates_when_remote_unknown,
+    repo_ref::{RepoRef, get_repo_coordinates_when_remote_unknown},
+    sub_commands::{open_proposal::open_in_browser, send::blossom_http_client},
 };
 
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// print proposal metadata for each refs/remotes/*/pr/* ref as tab
+    /// separated lines: ref, status, ahead, behind, root event id, author,
+    /// title
+    #[arg(long, action)]
+    pub(crate) refs: bool,
+    /// nostr git remote to use when several point at different repositories
+    #[arg(long)]
+    pub(crate) remote: Option<String>,
+    /// only list proposals with activity since they were last viewed
+    #[arg(long, action)]
+    pub(crate) unread: bool,
+    /// only list proposals with this label
+    #[arg(long)]
+    pub(crate) label: Option<String>,
+    /// only list proposals whose latest ci result failed. see nostr.ci-bots
+    #[arg(long, action)]
+    pub(crate) ci_failed: bool,
+    /// print the full diff straight to the terminal rather than through
+    /// core.pager or $PAGER
+    #[arg(long, action)]
+    pub(crate) no_pager: bool,
+    /// overwrite existing files when downloading patches to ./patches or
+    /// attachments to ./attachments
+    #[arg(long, action)]
+    pub(crate) force: bool,
+}
+
 #[allow(clippy::too_many_lines)]
-pub async fn launch() -> Result<()> {
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
     let git_repo = Repo::discover().context("failed to find a git repository")?;
+    if args.refs {
+        return print_proposal_refs(&git_repo);
+    }
     let git_repo_path = git_repo.get_path()?;
 
     // TODO: check for empty repo
     // TODO: check for existing maintaiers file
     // TODO: check for other claims
 
-    let client = Client::default();
+    let client = Client::new(Params::with_config(config));
 
-    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, args.remote.as_deref(), &client)
+            .await?;
 
-    fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
 
-    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
 
-    let proposals_and_revisions: Vec<nostr::Event> =
-        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates()).await?;
+    let interactor = Interactor::default();
+    let now = Timestamp::now();
+    // relays may not have deleted expired events yet and the cache never does
+    let mut proposals_and_revisions: Vec<nostr::Event> =
+        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+            .await?
+            .into_iter()
+            .filter(|e| !event_has_expired(e, now))
+            .collect();
+    // nor may they honour deletions by the author
+    let deletions = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kind(Kind::EventDeletion)
+            .events(proposals_and_revisions.iter().map(|e| e.id)),
+    ])
+    .await?;
+    proposals_and_revisions.retain(|e| !event_is_deleted(e, &deletions));
     if proposals_and_revisions.is_empty() {
+        let unreachable = report.unreachable_repo_relays(&repo_ref.relays);
+        if !unreachable.is_empty() {
+            bail!(NgitError::Network(anyhow!(
+                "could not reach any of the repository's relays ({}) — proposals may exist but are unavailable",
+                unreachable
+                    .iter()
+                    .map(|r| r.as_str().trim_end_matches('/'))
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )));
+        }
         println!("no proposals found... create one? try `ngit send`");
         return Ok(());
     }
@@ -53,38 +134,118 @@ pub async fn launch() -> Result<()> {
                 .events(proposals_and_revisions.iter().map(|e| e.id)),
         ])
         .await?;
+        statuses.retain(|e| !event_has_expired(e, now));
         statuses.sort_by_key(|e| e.created_at);
         statuses.reverse();
         statuses
     };
 
+    let edits: Vec<nostr::Event> = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kind(PROPOSAL_EDIT_KIND)
+            .events(proposals_and_revisions.iter().map(|e| e.id)),
+    ])
+    .await?;
+
+    let label_events: Vec<nostr::Event> = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kind(Kind::Label)
+            .events(proposals_and_revisions.iter().map(|e| e.id)),
+    ])
+    .await?;
+    let labels_of = |proposal: &nostr::Event| {
+        proposal_labels(
+            proposal,
+            &label_events,
+            &config.label_namespace.value,
+            if config.labels_allow_anyone.value {
+                None
+            } else {
+                Some(repo_ref.maintainers.as_slice())
+            },
+        )
+    };
+
+    let ci_bots = get_ci_bots(&config.ci_bots.value, &repo_ref);
+    let comments: Vec<nostr::Event> = if ci_bots.is_empty() {
+        vec![]
+    } else {
+        get_events_from_local_cache(git_repo_path, vec![
+            nostr::Filter::default()
+                .kinds(comment_kinds())
+                .authors(ci_bots.clone())
+                .events(proposals_and_revisions.iter().map(|e| e.id)),
+        ])
+        .await?
+    };
+    let ci_status_of = |proposal: &nostr::Event| proposal_ci_status(proposal, &comments, &ci_bots);
+
     let mut open_proposals: Vec<&nostr::Event> = vec![];
     let mut draft_proposals: Vec<&nostr::Event> = vec![];
     let mut closed_proposals: Vec<&nostr::Event> = vec![];
     let mut applied_proposals: Vec<&nostr::Event> = vec![];
 
+    let mut read_state = load_or_start_read_state(git_repo_path)?;
+    let activity: Vec<nostr::Event> = [
+        get_events_from_local_cache(git_repo_path, vec![
+            nostr::Filter::default()
+                .kinds([vec![Kind::GitPatch], comment_kinds()].concat())
+                .events(proposals_and_revisions.iter().map(|e| e.id)),
+        ])
+        .await?,
+        statuses.clone(),
+    ]
+    .concat();
+
     let proposals: Vec<nostr::Event> = proposals_and_revisions
         .iter()
         .filter(|e| !event_is_revision_root(e))
+        .filter(|e| !args.unread || read_state.unread_activity(e, &activity).is_some())
+        .filter(|e| {
+            args.label
+                .as_ref()
+                .is_none_or(|label| labels_of(*e).contains(label))
+        })
+        .filter(|e| {
+            !args.ci_failed || ci_status_of(*e).is_some_and(|ci| ci.state == CiState::Failed)
+        })
         .cloned()
         .collect();
+    if args.unread && proposals.is_empty() {
+        println!("no proposals with activity since they were last viewed");
+        return Ok(());
+    }
+    if let Some(label) = &args.label {
+        if proposals.is_empty() {
+            println!("no proposals labelled {label}");
+            return Ok(());
+        }
+    }
+    if args.ci_failed && proposals.is_empty() {
+        println!("no proposals with failing ci");
+        return Ok(());
+    }
+    if report.proposals_capped() {
+        println!(
+            "showing most recent {} proposals (use --fetch-all for complete history)",
+            proposals.len()
+        );
+    }
 
+    let main_tip = git_repo
+        .get_main_or_master_branch()
+        .ok()
+        .map(|(_, tip)| tip);
     for proposal in &proposals {
-        let status = if let Some(e) = statuses
-            .iter()
-            .filter(|e| {
-                status_kinds().contains(&e.kind)
-                    && e.tags.iter().any(|t| {
-                        t.as_slice().len() > 1 && t.as_slice()[1].eq(&proposal.id.to_string())
-                    })
-            })
-            .collect::<Vec<&nostr::Event>>()
-            .first()
-        {
-            e.kind
-        } else {
-            Kind::GitStatusOpen
-        };
+        let mut status = proposal_status(proposal, &statuses);
+        // eg. squash merged without an applied status being published
+        if status.eq(&Kind::GitStatusOpen) {
+            if let Some(main_tip) = &main_tip {
+                if proposal_content_merged(&git_repo, &repo_ref, proposal, main_tip, now).await? {
+                    status = Kind::GitStatusApplied;
+                }
+            }
+        }
         if status.eq(&Kind::GitStatusOpen) {
             open_proposals.push(proposal);
         } else if status.eq(&Kind::GitStatusClosed) {
@@ -97,6 +258,8 @@ pub async fn launch() -> Result<()> {
     }
 
     let mut selected_status = Kind::GitStatusOpen;
+    // back to the actions of this proposal after viewing its diff
+    let mut reopen_proposal = None;
 
     loop {
         let proposals_for_status = if selected_status == Kind::GitStatusOpen {
@@ -127,69 +290,73 @@ pub async fn launch() -> Result<()> {
             "applied proposals"
         };
 
-        let mut choices: Vec<String> = proposals_for_status
+        let choices: Vec<String> = proposals_for_status
             .iter()
             .map(|e| {
-                if let Ok(cl) = event_to_cover_letter(e) {
-                    cl.title
+                let title = if let Ok(cl) = event_to_cover_letter(e) {
+                    apply_proposal_edits(cl, e, &edits, &repo_ref.maintainers).title
                 } else if let Ok(msg) = tag_value(e, "description") {
                     msg.split('\n').collect::<Vec<&str>>()[0].to_string()
                 } else {
                     e.id.to_string()
+                };
+                let mut label =
+                    label_with_unread_activity(title, read_state.unread_activity(e, &activity));
+                let labels = labels_of(*e);
+                if !labels.is_empty() {
+                    label = format!("{label} {}", format_labels(&labels));
+                }
+                if let Some(ci) = ci_status_of(*e) {
+                    label = format!("{label} {}", ci.state.indicator());
+                }
+                if let Some(notice) = expiry_notice(e, now) {
+                    format!("{label} {}", output::dim(notice))
+                } else {
+                    label
                 }
             })
             .collect();
 
-        if !selected_status.eq(&Kind::GitStatusOpen) && open_proposals.len().gt(&0) {
-            choices.push(format!("({}) Open proposals...", open_proposals.len()));
-        }
-        if !selected_status.eq(&Kind::GitStatusDraft) && draft_proposals.len().gt(&0) {
-            choices.push(format!("({}) Draft proposals...", draft_proposals.len()));
-        }
-        if !selected_status.eq(&Kind::GitStatusClosed) && closed_proposals.len().gt(&0) {
-            choices.push(format!("({}) Closed proposals...", closed_proposals.len()));
-        }
-        if !selected_status.eq(&Kind::GitStatusApplied) && applied_proposals.len().gt(&0) {
-            choices.push(format!(
-                "({}) Applied proposals...",
-                applied_proposals.len()
-            ));
-        }
-
-        let selected_index = Interactor::default().choice(
-            PromptChoiceParms::default()
-                .with_prompt(prompt)
-                .with_default(0)
-                .with_choices(choices.clone()),
-        )?;
-
-        if (selected_index + 1).gt(&proposals_for_status.len()) {
-            if choices[selected_index].contains("Open") {
-                selected_status = Kind::GitStatusOpen;
-            } else if choices[selected_index].contains("Draft") {
-                selected_status = Kind::GitStatusDraft;
-            } else if choices[selected_index].contains("Closed") {
-                selected_status = Kind::GitStatusClosed;
-            } else if choices[selected_index].contains("Applied") {
-                selected_status = Kind::GitStatusApplied;
+        let selected_index = if let Some(index) = reopen_proposal.take() {
+            index
+        } else {
+            match select_from_proposals_menu(&interactor, prompt, choices, selected_status, [
+                open_proposals.len(),
+                draft_proposals.len(),
+                closed_proposals.len(),
+                applied_proposals.len(),
+            ])? {
+                MenuSelection::Proposal(index) => index,
+                MenuSelection::Status(status) => {
+                    selected_status = status;
+                    continue;
+                }
             }
-            continue;
-        }
+        };
 
         let cover_letter = event_to_cover_letter(proposals_for_status[selected_index])
             .context("failed to extract proposal details from proposal root event")?;
 
+        mark_proposal_seen(
+            git_repo_path,
+            &mut read_state,
+            &proposals_for_status[selected_index].id,
+        )?;
+
         let commits_events: Vec<nostr::Event> = get_all_proposal_patch_events_from_cache(
             git_repo_path,
             &repo_ref,
             &proposals_for_status[selected_index].id,
         )
-        .await?;
+        .await?
+        .into_iter()
+        .filter(|e| !event_has_expired(e, now))
+        .collect();
 
         let Ok(most_recent_proposal_patch_chain) =
             get_most_recent_patch_with_ancestors(commits_events.clone())
         else {
-            if Interactor::default().confirm(
+            if interactor.confirm(
                 PromptConfirmParms::default()
                     .with_default(true)
                     .with_prompt(
@@ -204,6 +371,35 @@ pub async fn launch() -> Result<()> {
         //     println!("recent_event: {:?}", commit.as_json());
         // }
 
+        if let Some(comparison) =
+            compare_with_previous_revision(&commits_events, &most_recent_proposal_patch_chain)
+        {
+            println!("{comparison}");
+        }
+
+        let attachments =
+            proposal_attachments(proposals_for_status[selected_index], &commits_events);
+        if !attachments.is_empty() {
+            println!("attachments:");
+            for attachment in &attachments {
+                println!(
+                    "  {} sha256:{} {}",
+                    attachment.name,
+                    attachment.sha256,
+                    output::dim(&attachment.url)
+                );
+            }
+            if interactor.choice(PromptChoiceParms::default().with_default(0).with_choices(
+                vec![
+                    "continue".to_string(),
+                    "download attachments to ./attachments".to_string(),
+                ],
+            ))? == 1
+            {
+                return download_attachments(&attachments, &git_repo, config, args.force).await;
+            }
+        }
+
         let binding_patch_text_ref = format!("{} commits", most_recent_proposal_patch_chain.len());
         let patch_text_ref = if most_recent_proposal_patch_chain.len().gt(&1) {
             binding_patch_text_ref.as_str()
@@ -211,19 +407,57 @@ pub async fn launch() -> Result<()> {
             "1 commit"
         };
 
+        // a bare repository has no working tree to check out or apply to
+        if git_repo.git_repo.is_bare() {
+            println!("{patch_text_ref}");
+            return match interactor.choice(
+                PromptChoiceParms::default()
+                    .with_default(0)
+                    .with_choices(vec![
+                        format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
+                        "back".to_string(),
+                    ]),
+            )? {
+                0 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                1 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                2 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                3 => continue,
+                _ => {
+                    bail!("unexpected choice")
+                }
+            };
+        }
+
         let no_support_for_patches_as_branch = most_recent_proposal_patch_chain
             .iter()
             .any(|event| !patch_supports_commit_ids(event));
 
         if no_support_for_patches_as_branch {
             println!("{patch_text_ref}");
-            return match Interactor::default().choice(
+            return match interactor.choice(
                 PromptChoiceParms::default()
                     .with_default(0)
                     .with_choices(vec![
                         "learn why 'patch only' proposals can't be checked out".to_string(),
                         format!("apply to current branch with `git am`"),
+                        "select patches to apply…".to_string(),
                         format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
                         "back".to_string(),
                     ]),
             )? {
@@ -241,7 +475,7 @@ pub async fn launch() -> Result<()> {
                     println!(
                         "by default ngit posts proposals that support both the branch and patch model so either workflow can be used"
                     );
-                    Interactor::default().choice(
+                    interactor.choice(
                         PromptChoiceParms::default()
                             .with_default(0)
                             .with_choices(vec!["back".to_string()]),
@@ -249,8 +483,23 @@ pub async fn launch() -> Result<()> {
                     continue;
                 }
                 1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                2 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                4 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                5 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                6 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -284,19 +533,37 @@ pub async fn launch() -> Result<()> {
         if !git_repo.does_commit_exist(&proposal_base_commit.to_string())? {
             println!("your '{main_branch_name}' branch may not be up-to-date.");
             println!("the proposal parent commit doesnt exist in your local repository.");
-            return match Interactor::default().choice(PromptChoiceParms::default().with_default(0).with_choices(
+            return match interactor.choice(PromptChoiceParms::default().with_default(0).with_choices(
                 vec![
                     format!(
                         "manually run `git pull` on '{main_branch_name}' and select proposal again"
                     ),
                     format!("apply to current branch with `git am`"),
+                    "select patches to apply…".to_string(),
                     format!("download to ./patches"),
+                    "view full diff".to_string(),
+                    "open in browser".to_string(),
                     "back".to_string(),
                 ],
             ))? {
-                0 | 3 => continue,
+                0 | 6 => continue,
                 1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
+                2 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                4 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                5 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -313,21 +580,65 @@ pub async fn launch() -> Result<()> {
 
         let (_, proposal_behind_main) =
             git_repo.get_commits_ahead_behind(&master_tip, &proposal_base_commit)?;
+        // counting commits overstates a proposal that was squash or rebase merged
+        let proposal_in_main = git_repo.does_commit_exist(&proposal_tip.to_string())?
+            && is_content_merged(&git_repo, &proposal_tip, &master_tip)?;
+        let proposal_vs_main = |ahead: usize, behind: usize| {
+            if proposal_in_main {
+                output::content_merged(main_branch_name)
+            } else {
+                ahead_behind(ahead, behind, main_branch_name)
+            }
+        };
+
+        // eg. opened against a fork whose history has been rewritten
+        let base_not_in_main = !proposal_base_commit.eq(&master_tip)
+            && !git_repo.ancestor_of(&master_tip, &proposal_base_commit)?;
+        let base_warning = if base_not_in_main {
+            println!(
+                "WARNING: proposal is based on a commit not in '{main_branch_name}' - the author's fork may have diverged"
+            );
+            if let Some(diverged_at) =
+                git_repo.get_merge_base(&master_tip, &proposal_base_commit)?
+            {
+                println!(
+                    "it diverged from '{main_branch_name}' at {} \"{}\"",
+                    oid_to_shorthand_string(sha1_to_oid(&diverged_at)?)?,
+                    git_repo.get_commit_message_summary(&diverged_at)?,
+                );
+            }
+            format!(" - based on a commit not in '{main_branch_name}'")
+        } else {
+            String::new()
+        };
 
         // branch doesnt exist
         if !branch_exists {
-            return match Interactor::default()
-                .choice(PromptChoiceParms::default().with_default(0).with_choices(vec![
-                format!(
-                    "create and checkout proposal branch ({} ahead {} behind '{main_branch_name}')",
-                    most_recent_proposal_patch_chain.len(),
-                    proposal_behind_main.len(),
-                ),
-                format!("apply to current branch with `git am`"),
-                format!("download to ./patches"),
-                "back".to_string(),
-            ]))? {
+            return match interactor.choice(
+                PromptChoiceParms::default()
+                    .with_default(0)
+                    .with_choices(vec![
+                        format!(
+                            "create and checkout proposal branch {}{base_warning}",
+                            proposal_vs_main(
+                                most_recent_proposal_patch_chain.len(),
+                                proposal_behind_main.len(),
+                            ),
+                        ),
+                        format!("apply to current branch with `git am`"),
+                        "select patches to apply…".to_string(),
+                        format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
+                        "back".to_string(),
+                    ]),
+            )? {
                 0 => {
+                    if base_not_in_main
+                        && !confirm_checkout_based_on_commit_not_in(main_branch_name)?
+                    {
+                        continue;
+                    }
                     check_clean(&git_repo)?;
                     let _ = git_repo
                         .apply_patch_chain(
@@ -343,8 +654,23 @@ pub async fn launch() -> Result<()> {
                     Ok(())
                 }
                 1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                2 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                4 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                5 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                6 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -358,7 +684,7 @@ pub async fn launch() -> Result<()> {
         if proposal_tip.eq(&local_branch_tip) {
             if checked_out_proposal_branch {
                 println!("branch checked out and up-to-date");
-                return match Interactor::default().choice(
+                return match interactor.choice(
                     PromptChoiceParms::default()
                         .with_default(0)
                         .with_choices(vec!["exit".to_string(), "back".to_string()]),
@@ -371,17 +697,22 @@ pub async fn launch() -> Result<()> {
                 };
             }
 
-            return match Interactor::default().choice(
+            return match interactor.choice(
                 PromptChoiceParms::default()
                     .with_default(0)
                     .with_choices(vec![
                         format!(
-                            "checkout proposal branch ({} ahead {} behind '{main_branch_name}')",
-                            most_recent_proposal_patch_chain.len(),
-                            proposal_behind_main.len(),
+                            "checkout proposal branch {}",
+                            proposal_vs_main(
+                                most_recent_proposal_patch_chain.len(),
+                                proposal_behind_main.len(),
+                            ),
                         ),
                         format!("apply to current branch with `git am`"),
+                        "select patches to apply…".to_string(),
                         format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
                         "back".to_string(),
                     ]),
             )? {
@@ -397,8 +728,23 @@ pub async fn launch() -> Result<()> {
                     Ok(())
                 }
                 1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                2 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                4 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                5 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                6 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -407,6 +753,14 @@ pub async fn launch() -> Result<()> {
 
         let (local_ahead_of_main, local_beind_main) =
             git_repo.get_commits_ahead_behind(&master_tip, &local_branch_tip)?;
+        let local_in_main = is_content_merged(&git_repo, &local_branch_tip, &master_tip)?;
+        let local_vs_main = |ahead: usize, behind: usize| {
+            if local_in_main {
+                output::content_merged(main_branch_name)
+            } else {
+                ahead_behind(ahead, behind, main_branch_name)
+            }
+        };
 
         // new appendments to proposal
         if let Some(index) = most_recent_proposal_patch_chain.iter().position(|patch| {
@@ -414,13 +768,16 @@ pub async fn launch() -> Result<()> {
                 .unwrap_or_default()
                 .eq(&local_branch_tip.to_string())
         }) {
-            return match Interactor::default().choice(
+            return match interactor.choice(
                 PromptChoiceParms::default()
                     .with_default(0)
                     .with_choices(vec![
                         format!("checkout proposal branch and apply {} appendments", &index,),
                         format!("apply to current branch with `git am`"),
+                        "select patches to apply…".to_string(),
                         format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
                         "back".to_string(),
                     ]),
             )? {
@@ -436,16 +793,33 @@ pub async fn launch() -> Result<()> {
                         )
                         .context("failed to apply patch chain")?;
                     println!(
-                        "checked out proposal branch and applied {} appendments ({} ahead {} behind '{main_branch_name}')",
+                        "checked out proposal branch and applied {} appendments {}",
                         &index,
-                        local_ahead_of_main.len().add(&index),
-                        local_beind_main.len(),
+                        proposal_vs_main(
+                            local_ahead_of_main.len().add(&index),
+                            local_beind_main.len(),
+                        ),
                     );
                     Ok(())
                 }
                 1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                2 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                4 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                5 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                6 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -461,25 +835,38 @@ pub async fn launch() -> Result<()> {
                 .eq(&local_branch_tip.to_string())
         }) {
             println!(
-                "updated proposal available ({} ahead {} behind '{main_branch_name}'). existing version is {} ahead {} behind '{main_branch_name}'",
-                most_recent_proposal_patch_chain.len(),
-                proposal_behind_main.len(),
-                local_ahead_of_main.len(),
-                local_beind_main.len(),
+                "updated proposal available {}. existing version is {}",
+                proposal_vs_main(
+                    most_recent_proposal_patch_chain.len(),
+                    proposal_behind_main.len(),
+                ),
+                local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
             );
-            return match Interactor::default().choice(
+            return match interactor.choice(
                 PromptChoiceParms::default()
                     .with_default(0)
                     .with_choices(vec![
-                        format!("checkout and overwrite existing proposal branch"),
+                        format!("checkout and overwrite existing proposal branch{base_warning}"),
                         format!("checkout existing outdated proposal branch"),
                         format!("apply to current branch with `git am`"),
+                        "select patches to apply…".to_string(),
                         format!("download to ./patches"),
+                        "view full diff".to_string(),
+                        "open in browser".to_string(),
                         "back".to_string(),
                     ]),
             )? {
                 0 => {
+                    if base_not_in_main
+                        && !confirm_checkout_based_on_commit_not_in(main_branch_name)?
+                    {
+                        continue;
+                    }
                     check_clean(&git_repo)?;
+                    let backup_ref = backup_before_overwrite(
+                        &git_repo,
+                        &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
+                    )?;
                     git_repo.create_branch_at_commit(
                         &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
                         &proposal_base_commit.to_string(),
@@ -495,12 +882,11 @@ pub async fn launch() -> Result<()> {
                         )
                         .context("failed to apply patch chain")?;
                     println!(
-                        "checked out new version of proposal ({} ahead {} behind '{main_branch_name}'), replacing old version ({} ahead {} behind '{main_branch_name}')",
-                        chain_length,
-                        proposal_behind_main.len(),
-                        local_ahead_of_main.len(),
-                        local_beind_main.len(),
+                        "checked out new version of proposal {}, replacing old version {}",
+                        proposal_vs_main(chain_length, proposal_behind_main.len()),
+                        local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
                     );
+                    println!("previous tip saved as {backup_ref}");
                     Ok(())
                 }
                 1 => {
@@ -509,15 +895,29 @@ pub async fn launch() -> Result<()> {
                         &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
                     )?;
                     println!(
-                        "checked out old proposal in existing branch ({} ahead {} behind '{main_branch_name}')",
-                        local_ahead_of_main.len(),
-                        local_beind_main.len(),
+                        "checked out old proposal in existing branch {}",
+                        local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
                     );
                     Ok(())
                 }
                 2 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-                3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                4 => continue,
+                3 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+                4 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+                5 => {
+                    view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                    reopen_proposal = Some(selected_index);
+                    continue;
+                }
+                6 => {
+                    open_in_browser(
+                        &git_repo,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        config,
+                    )?;
+                    continue;
+                }
+                7 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -533,12 +933,11 @@ pub async fn launch() -> Result<()> {
                 )?;
 
             println!(
-                "local proposal branch exists with {} unpublished commits on top of the most up-to-date version of the proposal ({} ahead {} behind '{main_branch_name}')",
+                "local proposal branch exists with {} unpublished commits on top of the most up-to-date version of the proposal {}",
                 local_ahead_of_proposal.len(),
-                local_ahead_of_main.len(),
-                proposal_behind_main.len(),
+                local_vs_main(local_ahead_of_main.len(), proposal_behind_main.len()),
             );
-            return match Interactor::default().choice(
+            return match interactor.choice(
                 PromptChoiceParms::default()
                     .with_default(0)
                     .with_choices(vec![
@@ -554,10 +953,9 @@ pub async fn launch() -> Result<()> {
                         &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
                     )?;
                     println!(
-                        "checked out proposal branch with {} unpublished commits ({} ahead {} behind '{main_branch_name}')",
+                        "checked out proposal branch with {} unpublished commits {}",
                         local_ahead_of_proposal.len(),
-                        local_ahead_of_main.len(),
-                        proposal_behind_main.len(),
+                        local_vs_main(local_ahead_of_main.len(), proposal_behind_main.len()),
                     );
                     Ok(())
                 }
@@ -575,22 +973,24 @@ pub async fn launch() -> Result<()> {
         // amended and git clean up job hasn't removed them)
         if git_repo.does_commit_exist(&proposal_tip.to_string())? {
             println!(
-                "you have previously applied the latest version of the proposal ({} ahead {} behind '{main_branch_name}') but your local proposal branch has amended or rebased it ({} ahead {} behind '{main_branch_name}')",
-                most_recent_proposal_patch_chain.len(),
-                proposal_behind_main.len(),
-                local_ahead_of_main.len(),
-                local_beind_main.len(),
+                "you have previously applied the latest version of the proposal {} but your local proposal branch has amended or rebased it {}",
+                proposal_vs_main(
+                    most_recent_proposal_patch_chain.len(),
+                    proposal_behind_main.len(),
+                ),
+                local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
             );
         }
         // user probably has a unpublished amended or rebase version of an older
         // proposal version
         else {
             println!(
-                "your local proposal branch ({} ahead {} behind '{main_branch_name}') has conflicting changes with the latest published proposal ({} ahead {} behind '{main_branch_name}')",
-                local_ahead_of_main.len(),
-                local_beind_main.len(),
-                most_recent_proposal_patch_chain.len(),
-                proposal_behind_main.len(),
+                "your local proposal branch {} has conflicting changes with the latest published proposal {}",
+                local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
+                proposal_vs_main(
+                    most_recent_proposal_patch_chain.len(),
+                    proposal_behind_main.len(),
+                ),
             );
 
             println!(
@@ -606,14 +1006,17 @@ pub async fn launch() -> Result<()> {
 
         println!("if you are confident in your changes consider running `ngit push --force`");
 
-        return match Interactor::default().choice(
+        return match interactor.choice(
             PromptChoiceParms::default()
                 .with_default(0)
                 .with_choices(vec![
                     format!("checkout local branch with unpublished changes"),
                     format!("discard unpublished changes and checkout new revision",),
                     format!("apply to current branch with `git am`"),
+                    "select patches to apply…".to_string(),
                     format!("download to ./patches"),
+                    "view full diff".to_string(),
+                    "open in browser".to_string(),
                     "back".to_string(),
                 ]),
         )? {
@@ -622,14 +1025,17 @@ pub async fn launch() -> Result<()> {
                 git_repo
                     .checkout(&cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?)?;
                 println!(
-                    "checked out old proposal in existing branch ({} ahead {} behind '{main_branch_name}')",
-                    local_ahead_of_main.len(),
-                    local_beind_main.len(),
+                    "checked out old proposal in existing branch {}",
+                    local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
                 );
                 Ok(())
             }
             1 => {
                 check_clean(&git_repo)?;
+                let backup_ref = backup_before_overwrite(
+                    &git_repo,
+                    &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
+                )?;
                 git_repo.create_branch_at_commit(
                     &cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?,
                     &proposal_base_commit.to_string(),
@@ -645,17 +1051,31 @@ pub async fn launch() -> Result<()> {
                 git_repo
                     .checkout(&cover_letter.get_branch_name_with_pr_prefix_and_shorthand_id()?)?;
                 println!(
-                    "checked out latest version of proposal ({} ahead {} behind '{main_branch_name}'), replacing unpublished version ({} ahead {} behind '{main_branch_name}')",
-                    chain_length,
-                    proposal_behind_main.len(),
-                    local_ahead_of_main.len(),
-                    local_beind_main.len(),
+                    "checked out latest version of proposal {}, replacing unpublished version {}",
+                    proposal_vs_main(chain_length, proposal_behind_main.len()),
+                    local_vs_main(local_ahead_of_main.len(), local_beind_main.len()),
                 );
+                println!("previous tip saved as {backup_ref}");
                 Ok(())
             }
             2 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-            3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-            4 => continue,
+            3 => launch_git_am_with_selected_patches(most_recent_proposal_patch_chain),
+            4 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo, args.force),
+            5 => {
+                view_full_diff(&git_repo, &most_recent_proposal_patch_chain, args.no_pager)?;
+                reopen_proposal = Some(selected_index);
+                continue;
+            }
+            6 => {
+                open_in_browser(
+                    &git_repo,
+                    &repo_ref,
+                    proposals_for_status[selected_index],
+                    config,
+                )?;
+                continue;
+            }
+            7 => continue,
             _ => {
                 bail!("unexpected choice")
             }
@@ -663,16 +1083,174 @@ pub async fn launch() -> Result<()> {
     }
 }
 
-fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
+/// "● " prefix when there is activity since the proposal was last viewed
+/// statuses proposals are grouped by, in the order they are offered
+const PROPOSAL_GROUPS: [(Kind, &str); 4] = [
+    (Kind::GitStatusOpen, "Open"),
+    (Kind::GitStatusDraft, "Draft"),
+    (Kind::GitStatusClosed, "Closed"),
+    (Kind::GitStatusApplied, "Applied"),
+];
+
+/// what was picked from the menu of proposals
+#[derive(Debug, PartialEq)]
+enum MenuSelection {
+    Proposal(usize),
+    /// show the proposals with this status instead
+    Status(Kind),
+}
+
+/// offer the proposals with `selected_status` followed by the other groups
+/// that have any. `group_sizes` are in [`PROPOSAL_GROUPS`] order
+fn select_from_proposals_menu(
+    interactor: &dyn InteractorPrompt,
+    prompt: &str,
+    mut choices: Vec<String>,
+    selected_status: Kind,
+    group_sizes: [usize; 4],
+) -> Result<MenuSelection> {
+    let proposal_count = choices.len();
+    let mut other_groups = vec![];
+    for ((status, name), size) in PROPOSAL_GROUPS.into_iter().zip(group_sizes) {
+        if status != selected_status && size > 0 {
+            choices.push(format!("({size}) {name} proposals..."));
+            other_groups.push(status);
+        }
+    }
+    let selected_index = interactor.choice(
+        PromptChoiceParms::default()
+            .with_prompt(prompt)
+            .with_flag("--refs")
+            .with_default(0)
+            .with_choices(choices),
+    )?;
+    if let Some(index) = selected_index.checked_sub(proposal_count) {
+        Ok(MenuSelection::Status(
+            *other_groups.get(index).context("unexpected choice")?,
+        ))
+    } else {
+        Ok(MenuSelection::Proposal(selected_index))
+    }
+}
+
+/// whether the latest revision of `proposal` is in the local repository with
+/// its changes already in `main_tip`
+async fn proposal_content_merged(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposal: &nostr::Event,
+    main_tip: &Sha1Hash,
+    now: Timestamp,
+) -> Result<bool> {
+    let patches: Vec<nostr::Event> =
+        get_all_proposal_patch_events_from_cache(git_repo.get_path()?, repo_ref, &proposal.id)
+            .await?
+            .into_iter()
+            .filter(|e| !event_has_expired(e, now))
+            .collect();
+    let Some(tip) = get_most_recent_patch_with_ancestors(patches)
+        .ok()
+        .and_then(|chain| chain.first().and_then(|e| get_commit_id_from_patch(e).ok()))
+    else {
+        return Ok(false);
+    };
+    Ok(git_repo.does_commit_exist(&tip)?
+        && is_content_merged(git_repo, &str_to_sha1(&tip)?, main_tip)?)
+}
+
+fn label_with_unread_activity(title: String, unread: Option<UnreadActivity>) -> String {
+    match unread {
+        None => output::title(title).to_string(),
+        Some(UnreadActivity { comments: 0 }) => format!("● {}", output::title(title)),
+        Some(UnreadActivity { comments }) => format!(
+            "● {} {}",
+            output::title(title),
+            output::dim(format!(
+                "({comments} new comment{})",
+                if comments == 1 { "" } else { "s" }
+            ))
+        ),
+    }
+}
+
+fn confirm_checkout_based_on_commit_not_in(main_branch_name: &str) -> Result<bool> {
+    Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_default(false)
+            .with_prompt(format!(
+                "proposal is based on a commit not in '{main_branch_name}'. checkout anyway?"
+            )),
+    )
+}
+
+pub(crate) fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
     println!("applying to current branch with `git am`");
     // TODO: add PATCH x/n to appended patches
     patches.reverse();
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+
+    let mut three_way = false;
+    loop {
+        let output = run_git_am(&patches, three_way)?;
+        if output.status.success() {
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let Some(failure) = get_git_am_failure(&git_repo, &patches, &stderr) else {
+            bail!(NgitError::Git(anyhow!("git am failed: {}", stderr.trim())));
+        };
+        println!("{failure}");
 
-    let mut am = std::process::Command::new("git")
-        .arg("am")
+        let mut choices = vec![
+            "abort `git am` and restore the branch".to_string(),
+            "leave it to resolve manually".to_string(),
+        ];
+        if !three_way {
+            choices.push("retry with a 3-way merge".to_string());
+        }
+        // never leave an am session behind when nobody can be asked
+        let choice = if prompts_allowed() {
+            Interactor::default().choice(
+                PromptChoiceParms::default()
+                    .with_prompt("`git am` stopped")
+                    .with_default(0)
+                    .with_choices(choices),
+            )?
+        } else {
+            0
+        };
+        match choice {
+            1 => {
+                println!("resolve the conflicts, `git add` the files and run `git am --continue`");
+                println!("or skip the patch with `git am --skip` or give up with `git am --abort`");
+                return Ok(());
+            }
+            2 => {
+                abort_git_am()?;
+                println!("retrying with a 3-way merge");
+                three_way = true;
+            }
+            _ => {
+                abort_git_am()?;
+                bail!(NgitError::Git(anyhow!(
+                    "aborted `git am` so the branch is as it was before"
+                )));
+            }
+        }
+    }
+}
+
+fn run_git_am(patches: &[nostr::Event], three_way: bool) -> Result<std::process::Output> {
+    let mut command = std::process::Command::new("git");
+    command.arg("am");
+    if three_way {
+        command.arg("--3way");
+    }
+    let mut am = command
         .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
         .spawn()
         .context("failed to spawn git am")?;
 
@@ -687,48 +1265,344 @@ fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
             .context("failed to write patch content into git am stdin buffer")?;
     }
     stdin.flush()?;
-    let output = am
-        .wait_with_output()
-        .context("failed to read git am stdout")?;
-    print!("{:?}", output.stdout);
+    am.wait_with_output()
+        .context("failed to read git am output")
+}
+
+fn abort_git_am() -> Result<()> {
+    let output = std::process::Command::new("git")
+        .args(["am", "--abort"])
+        .output()
+        .context("failed to run git am --abort")?;
+    if !output.status.success() {
+        bail!(NgitError::Git(anyhow!(
+            "git am --abort failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
     Ok(())
 }
 
+/// where an interrupted `git am` stopped
+struct GitAmFailure {
+    patch_number: usize,
+    patch_count: usize,
+    subject: String,
+    files: Vec<String>,
+}
+
+impl std::fmt::Display for GitAmFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "patch {}/{} '{}' failed: ",
+            self.patch_number, self.patch_count, self.subject
+        )?;
+        if self.files.is_empty() {
+            write!(f, "it didn't apply")
+        } else {
+            write!(f, "{} had conflicts", self.files.join(", "))
+        }
+    }
+}
+
+/// read the am session `git am` left behind. None if it stopped before
+/// starting one
+fn get_git_am_failure(
+    git_repo: &Repo,
+    patches: &[nostr::Event],
+    stderr: &str,
+) -> Option<GitAmFailure> {
+    let rebase_apply = git_repo.git_repo.path().join("rebase-apply");
+    let read_number = |name: &str| -> Option<usize> {
+        std::fs::read_to_string(rebase_apply.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    let patch_number = read_number("next")?;
+    let patch_count = read_number("last").unwrap_or(patches.len());
+
+    let mut files = files_in_git_apply_errors(stderr);
+    // a 3-way merge leaves conflicts in the index rather than erroring
+    if let Ok(index) = git_repo.git_repo.index() {
+        if let Ok(conflicts) = index.conflicts() {
+            for conflict in conflicts.flatten() {
+                if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                    let path = String::from_utf8_lossy(&entry.path).to_string();
+                    if !files.contains(&path) {
+                        files.push(path);
+                    }
+                }
+            }
+        }
+    }
+
+    Some(GitAmFailure {
+        patch_number,
+        patch_count,
+        subject: patches
+            .get(patch_number.saturating_sub(1))
+            .and_then(|patch| commit_msg_from_patch_oneliner(patch).ok())
+            .unwrap_or_default(),
+        files,
+    })
+}
+
+/// files named in the `error:` lines `git apply` prints when a patch doesn't
+/// apply
+fn files_in_git_apply_errors(stderr: &str) -> Vec<String> {
+    let mut files: Vec<String> = vec![];
+    for line in stderr.lines() {
+        let Some(error) = line.strip_prefix("error: ") else {
+            continue;
+        };
+        let file = if let Some(file_and_line) = error.strip_prefix("patch failed: ") {
+            file_and_line
+                .rsplit_once(':')
+                .map_or(file_and_line, |(file, _)| file)
+        } else if let Some(file) = [
+            ": already exists in index",
+            ": already exists in working directory",
+            ": does not exist in index",
+            ": patch does not apply",
+        ]
+        .iter()
+        .find_map(|suffix| error.strip_suffix(suffix))
+        {
+            file
+        } else {
+            continue;
+        };
+        if !files.iter().any(|f| f == file) {
+            files.push(file.to_string());
+        }
+    }
+    files
+}
+
+/// choose which patches in the chain (newest first) to apply with `git am`
+fn launch_git_am_with_selected_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
+    patches.reverse();
+    let selected = select_patches(&patches)?;
+    launch_git_am_with_patch_selection(patches, &selected)
+}
+
+/// multi-select of `patches`, ordered oldest first, showing each subject and
+/// diffstat
+pub(crate) fn select_patches(patches: &[nostr::Event]) -> Result<Vec<usize>> {
+    let choices = patches
+        .iter()
+        .enumerate()
+        .map(|(i, patch)| {
+            Ok(format!(
+                "{}. {} ({})",
+                i + 1,
+                commit_msg_from_patch_oneliner(patch)?,
+                output::diffstat(&patch_diffstat(patch)),
+            ))
+        })
+        .collect::<Result<Vec<String>>>()?;
+    Interactor::default().multi_choice(
+        PromptMultiChoiceParms::default()
+            .with_prompt("select patches to apply")
+            .with_choices(choices),
+    )
+}
+
+/// apply the patches at `selected`, indexes into `patches` ordered oldest
+/// first, with `git am`
+pub(crate) fn launch_git_am_with_patch_selection(
+    patches: Vec<nostr::Event>,
+    selected: &[usize],
+) -> Result<()> {
+    if selected.is_empty() {
+        bail!("no patches selected");
+    }
+    if let Some(i) = selected.iter().find(|i| **i >= patches.len()) {
+        bail!(
+            "proposal has {} patches so there is no patch {}",
+            patches.len(),
+            i + 1
+        );
+    }
+    let last_selected = selected.iter().max().copied().unwrap_or_default();
+    if (0..last_selected).any(|i| !selected.contains(&i)) {
+        println!(
+            "WARNING: earlier patches were skipped so later patches may not apply. `git am` will stop at the first that fails"
+        );
+    }
+    let mut chosen = patches
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, patch)| patch)
+        .collect::<Vec<nostr::Event>>();
+    // launch_git_am_with_patches takes them newest first
+    chosen.reverse();
+    launch_git_am_with_patches(chosen)
+}
+
+/// write the diff of each patch in the chain (newest first) oldest first as
+/// one highlighted unified diff, a patch at a time
+pub(crate) fn write_full_diff(
+    patches: &[nostr::Event],
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    for patch in patches.iter().rev() {
+        let diff = diff_from_patch(patch);
+        if !diff.is_empty() {
+            writeln!(out, "{}", output::diff(diff))?;
+        }
+    }
+    Ok(())
+}
+
+/// page the full diff through the user's pager unless `no_pager`
+fn view_full_diff(git_repo: &Repo, patches: &[nostr::Event], no_pager: bool) -> Result<()> {
+    let pager = if no_pager {
+        None
+    } else {
+        output::pager(git_repo)?
+    };
+    output::write_with_pager(pager.as_deref(), |out| write_full_diff(patches, out))
+}
+
 fn event_id_extra_shorthand(event: &nostr::Event) -> String {
     event.id.to_string()[..5].to_string()
 }
 
-fn save_patches_to_dir(mut patches: Vec<nostr::Event>, git_repo: &Repo) -> Result<()> {
+fn save_patches_to_dir(mut patches: Vec<nostr::Event>, git_repo: &Repo, force: bool) -> Result<()> {
     // TODO: add PATCH x/n to appended patches
     patches.reverse();
-    let path = git_repo.get_path()?.join("patches");
-    std::fs::create_dir_all(&path)?;
+    let path = git_repo.get_workdir()?.join("patches");
+    create_download_dir(&path)?;
     let id = event_id_extra_shorthand(
         patches
             .first()
             .context("there must be at least one patch to save")?,
     );
+    let mut taken = HashSet::new();
+    let mut files = vec![];
     for (i, patch) in patches.iter().enumerate() {
-        let path = path.join(format!(
-            "{}-{:0>4}-{}.patch",
-            &id,
-            i.add(&1),
-            commit_msg_from_patch_oneliner(patch)?
-        ));
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .context("open new patch file with write and truncate options")?;
-        file.write_all(patch.content.as_bytes())?;
-        file.write_all("\n\n".as_bytes())?;
-        file.flush()?;
+        // names come from commit messages authored by others so must not be
+        // able to escape ./patches
+        let file_name = unique_file_name(
+            &format!(
+                "{}-{:0>4}-{}",
+                &id,
+                i.add(&1),
+                sanitize_file_name(&commit_msg_from_patch_oneliner(patch)?)
+            ),
+            "patch",
+            &mut taken,
+        );
+        let file_path = path.join(&file_name);
+        if !force && std::fs::symlink_metadata(&file_path).is_ok() {
+            bail!("./patches/{file_name} already exists. use --force to overwrite it");
+        }
+        files.push((file_path, patch));
+    }
+    for (file_path, patch) in files {
+        write_new_file(&file_path, format!("{}\n\n", patch.content).as_bytes())?;
     }
     println!("created {} patch files in ./patches/{id}-*", patches.len());
     Ok(())
 }
 
+/// create a directory in the working tree to download into. an existing
+/// directory is reused but not a symlink or a file
+fn create_download_dir(path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.is_dir() => bail!(
+            "{} exists but isn't a directory so nothing was saved",
+            path.display()
+        ),
+        Ok(_) => Ok(()),
+        Err(_) => std::fs::create_dir(path).context(format!("failed to create {}", path.display())),
+    }
+}
+
+/// write to `path`, replacing any file, or symlink, already there rather than
+/// writing through it
+fn write_new_file(path: &Path, content: &[u8]) -> Result<()> {
+    if std::fs::symlink_metadata(path).is_ok() {
+        std::fs::remove_file(path).context(format!("failed to remove {}", path.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .context(format!("failed to create {}", path.display()))?;
+    file.write_all(content)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// download attachments to ./attachments. only files matching the sha256
+/// they were tagged with are saved
+async fn download_attachments(
+    attachments: &[Attachment],
+    git_repo: &Repo,
+    config: &Config,
+    force: bool,
+) -> Result<()> {
+    let path = git_repo.get_workdir()?.join("attachments");
+    create_download_dir(&path)?;
+    let http = blossom_http_client(config)?;
+    let mut failed = 0;
+    for attachment in attachments {
+        let file_name = format!(
+            "{}-{}",
+            &attachment.sha256[..7],
+            sanitize_file_name(&attachment.name)
+        );
+        let file_path = path.join(&file_name);
+        if !force && std::fs::symlink_metadata(&file_path).is_ok() {
+            bail!("./attachments/{file_name} already exists. use --force to overwrite it");
+        }
+        match download_attachment(&http, attachment).await {
+            Ok(bytes) => {
+                write_new_file(&file_path, &bytes)?;
+                println!("saved ./attachments/{file_name} (sha256 verified)");
+            }
+            Err(error) => {
+                println!("{} not saved: {error:#}", attachment.name);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{failed} attachment{} couldn't be downloaded and verified",
+            if failed == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+async fn download_attachment(http: &reqwest::Client, attachment: &Attachment) -> Result<Vec<u8>> {
+    let response = http
+        .get(&attachment.url)
+        .send()
+        .await
+        .context(format!("failed to connect to {}", attachment.url))?;
+    if !response.status().is_success() {
+        bail!("{} responded {}", attachment.url, response.status());
+    }
+    let bytes = response.bytes().await?.to_vec();
+    verify_attachment(attachment, &bytes)?;
+    Ok(bytes)
+}
+
+/// keep the tip of a proposal branch that is about to be overwritten so it
+/// can be restored with `ngit backups restore`
+fn backup_before_overwrite(git_repo: &Repo, branch_name: &str) -> Result<String> {
+    backup_branch(git_repo, branch_name)?
+        .context(format!("failed to find branch {branch_name} to back up"))
+}
+
 fn check_clean(git_repo: &Repo) -> Result<()> {
     if git_repo.has_outstanding_changes()? {
         bail!(
@@ -737,3 +1611,142 @@ fn check_clean(git_repo: &Repo) -> Result<()> {
     }
     Ok(())
 }
+
+/// uses the proposal notes git-remote-nostr writes to refs/notes/nostr on
+/// fetch so it works offline
+fn print_proposal_refs(git_repo: &Repo) -> Result<()> {
+    let notes = get_proposal_notes(git_repo)?;
+    let (_, main_tip) = git_repo.get_main_or_master_branch()?;
+    let mut lines = vec![];
+    for reference in git_repo
+        .git_repo
+        .references_glob("refs/remotes/*/pr/*")
+        .context("failed to list remote tracking refs")?
+    {
+        let reference = reference?;
+        let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+            continue;
+        };
+        if let Some(note) = notes.get(&commit.id()) {
+            let (ahead, behind) =
+                git_repo.get_commits_ahead_behind(&main_tip, &oid_to_sha1(&commit.id()))?;
+            lines.push(format!(
+                "{name}\t{}\t{}\t{}\t{}\t{}\t{}",
+                note.status,
+                ahead.len(),
+                behind.len(),
+                note.root,
+                note.author,
+                note.title,
+            ));
+        }
+    }
+    lines.sort();
+    for line in lines {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ngit::cli_interactor::{ScriptedAnswer, ScriptedInteractor};
+
+    use super::*;
+
+    mod select_from_proposals_menu {
+        use super::*;
+
+        fn titles() -> Vec<String> {
+            vec!["proposal a".to_string(), "proposal b".to_string()]
+        }
+
+        #[test]
+        fn other_groups_with_proposals_are_offered_after_proposals() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(0)]);
+            select_from_proposals_menu(
+                &interactor,
+                "open proposals",
+                titles(),
+                Kind::GitStatusOpen,
+                [2, 0, 3, 1],
+            )?;
+            assert_eq!(interactor.asked()[0].prompt, "open proposals");
+            assert_eq!(interactor.asked()[0].choices, vec![
+                "proposal a",
+                "proposal b",
+                "(3) Closed proposals...",
+                "(1) Applied proposals...",
+            ]);
+            Ok(())
+        }
+
+        #[test]
+        fn picking_a_proposal() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(1)]);
+            assert_eq!(
+                select_from_proposals_menu(
+                    &interactor,
+                    "open proposals",
+                    titles(),
+                    Kind::GitStatusOpen,
+                    [2, 0, 3, 1],
+                )?,
+                MenuSelection::Proposal(1)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn picking_another_group() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(3)]);
+            assert_eq!(
+                select_from_proposals_menu(
+                    &interactor,
+                    "open proposals",
+                    titles(),
+                    Kind::GitStatusOpen,
+                    [2, 0, 3, 1],
+                )?,
+                MenuSelection::Status(Kind::GitStatusApplied)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn back_to_open_proposals_from_another_group() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(1)]);
+            assert_eq!(
+                select_from_proposals_menu(
+                    &interactor,
+                    "closed proposals",
+                    vec!["proposal c".to_string()],
+                    Kind::GitStatusClosed,
+                    [2, 0, 1, 0],
+                )?,
+                MenuSelection::Status(Kind::GitStatusOpen)
+            );
+            assert_eq!(interactor.asked()[0].choices, vec![
+                "proposal c",
+                "(2) Open proposals...",
+            ]);
+            Ok(())
+        }
+
+        #[test]
+        fn only_groups_offered_when_there_are_no_open_proposals() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(0)]);
+            assert_eq!(
+                select_from_proposals_menu(
+                    &interactor,
+                    "proposals menu",
+                    vec![],
+                    Kind::GitStatusOpen,
+                    [0, 1, 0, 0],
+                )?,
+                MenuSelection::Status(Kind::GitStatusDraft)
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use anyhow::{Context, Result};
+use ngit::{
+    activity_log::{
+        ActivityFilter, ActivityKind, build_activity_log, format_age, get_filter_activity_replies,
+        get_filters_repo_activity, parse_since,
+    },
+    login::user::get_names_for_display,
+    output,
+};
+use nostr::PublicKey;
+use nostr_sdk::{Kind, Timestamp};
+
+use crate::{
+    client::{
+        Client, Connect, Params, get_event_from_global_cache, get_events_from_local_cache,
+        get_filter_repo_events, get_repo_ref_from_cache,
+    },
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// only show activity since a unix timestamp or an age such as 12h, 7d
+    /// or 2w
+    #[arg(long)]
+    pub(crate) since: Option<String>,
+    /// only show activity by this npub, hex public key or profile name
+    #[arg(long)]
+    pub(crate) author: Option<String>,
+    /// only show these kinds of activity
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub(crate) kind: Vec<ActivityKind>,
+    /// one line per event. this is the default
+    #[arg(long, action, conflicts_with = "stat")]
+    pub(crate) oneline: bool,
+    /// list the refs each state event changed
+    #[arg(long, action)]
+    pub(crate) stat: bool,
+    /// nostr git remote to use when several point at different repositories
+    #[arg(long)]
+    pub(crate) remote: Option<String>,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+    let now = Timestamp::now();
+
+    let since = args
+        .since
+        .as_deref()
+        .map(|since| parse_since(since, now))
+        .transpose()?;
+
+    let client = Client::new(Params::with_config(config));
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, args.remote.as_deref(), &client)
+            .await?;
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    // activity is only read from the cache. `ngit list` and `git fetch` update it
+    let mut events = [
+        get_events_from_local_cache(
+            git_repo_path,
+            get_filters_repo_activity(&repo_ref.coordinates()),
+        )
+        .await?,
+        get_event_from_global_cache(
+            Some(git_repo_path),
+            vec![get_filter_repo_events(&repo_ref.coordinates())],
+        )
+        .await?,
+    ]
+    .concat();
+    let threads = events
+        .iter()
+        .filter(|e| [Kind::GitPatch, Kind::GitIssue].contains(&e.kind))
+        .cloned()
+        .collect::<Vec<nostr::Event>>();
+    if !threads.is_empty() {
+        events.extend(
+            get_events_from_local_cache(git_repo_path, vec![get_filter_activity_replies(&threads)])
+                .await?,
+        );
+    }
+
+    let names: HashMap<PublicKey, String> = get_names_for_display(
+        &events.iter().map(|e| e.pubkey).collect(),
+        Some(&client),
+        Some(git_repo_path),
+        &repo_ref
+            .relays
+            .iter()
+            .map(ToString::to_string)
+            .chain(client.get_fallback_relays().clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>(),
+    )
+    .await?;
+
+    let authors = if let Some(author) = &args.author {
+        Some(if let Ok(public_key) = PublicKey::parse(author) {
+            HashSet::from([public_key])
+        } else {
+            names
+                .iter()
+                .filter(|(_, name)| name.eq_ignore_ascii_case(author))
+                .map(|(public_key, _)| *public_key)
+                .collect()
+        })
+    } else {
+        None
+    };
+
+    let log = build_activity_log(
+        &events,
+        &ActivityFilter {
+            since,
+            authors,
+            kinds: args.kind.clone(),
+        },
+    );
+    if log.is_empty() {
+        println!("no activity found in the cache. `ngit list` or `git fetch` fetches updates");
+        return Ok(());
+    }
+
+    let mut lines = vec![];
+    for entry in log {
+        lines.push(format!(
+            "{} {} {} {}",
+            output::dim(format_age(entry.event.created_at, now)),
+            output::title(&names[&entry.event.pubkey]),
+            entry.label,
+            entry.summary,
+        ));
+        if args.stat {
+            for change in &entry.ref_changes {
+                lines.push(format!("    {change}"));
+            }
+        }
+    }
+    output::write_with_pager(output::pager(&git_repo)?.as_deref(), |out| {
+        writeln!(out, "{}", lines.join("\n"))
+    })
+}
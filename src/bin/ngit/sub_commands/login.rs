@@ -3,12 +3,16 @@ use clap;
 use ngit::{
     cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms},
     git::{get_git_config_item, remove_git_config_item},
-    login::{SignerInfoSource, existing::load_existing_login},
+    login::{
+        SignerInfoSource,
+        existing::{LoadLoginOptions, load_existing_login},
+    },
 };
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
-    client::{Client, Connect},
+    client::{Client, Connect, Params},
+    config::Config,
     git::Repo,
     login::fresh::fresh_login_or_signup,
 };
@@ -22,13 +26,18 @@ pub struct SubCommandArgs {
     /// don't fetch user metadata and relay list from relays
     #[arg(long, action)]
     offline: bool,
+
+    /// when creating an account, don't prompt for a display name and relays
+    /// or publish a profile and relay list
+    #[arg(long, action)]
+    skip_profile: bool,
 }
 
-pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
+pub async fn launch(args: &Cli, command_args: &SubCommandArgs, config: &Config) -> Result<()> {
     let client = if command_args.offline {
         None
     } else {
-        Some(Client::default())
+        Some(Client::new(Params::with_config(config)))
     };
 
     let git_repo_result = Repo::discover().context("failed to find a git repository");
@@ -46,6 +55,7 @@ pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
             client.as_ref(),
             extract_signer_cli_arguments(args)?,
             log_in_locally_only || command_args.local,
+            command_args.skip_profile,
         )
         .await?;
     }
@@ -70,9 +80,9 @@ async fn logout(git_repo: Option<&Repo>, local_only: bool) -> Result<(bool, bool
             &None,
             &Some(source),
             None,
-            true,
-            false,
-            false,
+            &LoadLoginOptions::default()
+                .silent()
+                .dont_prompt_for_password(),
         )
         .await
         {
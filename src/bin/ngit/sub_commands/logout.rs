@@ -1,7 +1,10 @@
 use anyhow::{Context, Result};
 use ngit::{
     git::remove_git_config_item,
-    login::{SignerInfoSource, existing::load_existing_login},
+    login::{
+        SignerInfoSource,
+        existing::{LoadLoginOptions, load_existing_login},
+    },
 };
 
 use crate::{
@@ -32,9 +35,9 @@ async fn logout(git_repo: Option<&Repo>) -> Result<()> {
             &None,
             &Some(source),
             None,
-            true,
-            false,
-            false,
+            &LoadLoginOptions::default()
+                .silent()
+                .dont_prompt_for_password(),
         )
         .await
         {
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ngit::{
+    cache_dir::{get_cache_to_move, move_local_cache},
+    git::nostr_url::{NostrUrlDecoded, is_legacy_nostr_url, migrate_legacy_remote_url},
+};
+use nostr::{ToBech32, nips::nip01::Coordinate};
+
+use crate::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms, prompts_allowed},
+    git::{Repo, RepoActions},
+};
+
+/// git config items renamed since older ngit versions as (old, current)
+static RENAMED_GIT_CONFIG_ITEMS: [(&str, &str); 2] = [
+    ("nostr.repo-naddr", "nostr.repo"),
+    ("nostr.bunker-app-secret", "nostr.bunker-app-key"),
+];
+
+/// cache files older ngit versions kept in .git. they can't be read by the
+/// current cache and events are fetched again so they are removed
+static LEGACY_LOCAL_CACHE_FILES: [&str; 3] = [
+    ".git/nostr-cache.sqlite",
+    ".git/nostr-cache.sqlite-shm",
+    ".git/nostr-cache.sqlite-wal",
+];
+
+pub async fn launch() -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let mut changes = migrate_remotes(&git_repo).await?;
+    changes.extend(migrate_git_config_items(&git_repo)?);
+    changes.extend(remove_legacy_local_cache_files(git_repo.get_path()?)?);
+    changes.extend(move_local_cache_to_cache_dir(git_repo.get_path()?)?);
+    if changes.is_empty() {
+        println!("nothing to migrate");
+    }
+    for change in changes {
+        println!("{change}");
+    }
+    Ok(())
+}
+
+async fn migrate_remotes(git_repo: &Repo) -> Result<Vec<String>> {
+    let mut changes = vec![];
+    for remote in git_repo.git_repo.remotes()?.iter().flatten() {
+        let Some(url) = git_repo
+            .git_repo
+            .find_remote(remote)?
+            .url()
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if !is_legacy_nostr_url(&url) {
+            continue;
+        }
+        let decoded_nostr_url = NostrUrlDecoded::parse_and_resolve(&url, &Some(git_repo))
+            .await
+            .context(format!("failed to resolve nostr url of remote '{remote}'"))?;
+        let naddr_url = migrate_legacy_remote_url(git_repo, remote, &decoded_nostr_url)?;
+        changes.push(format!("migrated remote '{remote}' to {naddr_url}"));
+    }
+    Ok(changes)
+}
+
+fn migrate_git_config_items(git_repo: &Repo) -> Result<Vec<String>> {
+    let mut changes = vec![];
+    for global in [false, true] {
+        let scope = if global { "global" } else { "local" };
+        for (old, current) in RENAMED_GIT_CONFIG_ITEMS {
+            let Some(value) = git_repo.get_git_config_item(old, Some(global))? else {
+                continue;
+            };
+            // a value already saved under the current name is newer
+            if git_repo
+                .get_git_config_item(current, Some(global))?
+                .is_none()
+            {
+                git_repo.save_git_config_item(current, &value, global)?;
+            }
+            git_repo.remove_git_config_item(old, global)?;
+            changes.push(format!(
+                "renamed {scope} git config item {old} to {current}"
+            ));
+        }
+    }
+    // older ngit versions saved the repository as a coordinate
+    if let Some(value) = git_repo.get_git_config_item("nostr.repo", Some(false))? {
+        if !value.starts_with("naddr") {
+            if let Ok(coordinate) = Coordinate::parse(&value) {
+                git_repo.save_git_config_item("nostr.repo", &coordinate.to_bech32()?, false)?;
+                changes.push("saved git config item nostr.repo as an naddr".to_string());
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn remove_legacy_local_cache_files(git_repo_path: &Path) -> Result<Vec<String>> {
+    let mut changes = vec![];
+    for file in LEGACY_LOCAL_CACHE_FILES {
+        let path = git_repo_path.join(file);
+        if path.exists() {
+            std::fs::remove_file(&path).context(format!("failed to remove {file}"))?;
+            changes.push(format!("removed old nostr cache {file}"));
+        }
+    }
+    Ok(changes)
+}
+
+fn move_local_cache_to_cache_dir(git_repo_path: &Path) -> Result<Vec<String>> {
+    let Some((from, to)) = get_cache_to_move(git_repo_path) else {
+        return Ok(vec![]);
+    };
+    move_local_cache(&from, &to)?;
+    Ok(vec![format!(
+        "moved nostr cache {} to {}",
+        from.display(),
+        to.display()
+    )])
+}
+
+/// when `nostr.cache-dir` has been set since the cache in .git was created,
+/// offer to move it rather than fetching everything again. once declined a
+/// new cache is created so it isn't offered again
+pub fn offer_to_move_local_cache(git_repo: &Repo) -> Result<()> {
+    let Some((from, to)) = get_cache_to_move(git_repo.get_path()?) else {
+        return Ok(());
+    };
+    if !prompts_allowed() {
+        return Ok(());
+    }
+    if Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "nostr.cache-dir is set. move the nostr cache from {} to {}?",
+                from.display(),
+                to.display()
+            ))
+            .with_default(true),
+    )? {
+        move_local_cache(&from, &to)?;
+    } else {
+        eprintln!("{} is no longer used and can be removed", from.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use auth_git2::GitAuthenticator;
+use git2::Oid;
+use ngit::{
+    client::get_state_from_cache,
+    proxy::{ProxyUse, ensure_onion_url_has_proxy, git_proxy_options},
+    repo_state::RepoState,
+};
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions, str_to_sha1},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// git remote name or url to keep in sync with the nostr state
+    pub(crate) remote: String,
+    /// allow non-fast-forward updates on the mirror
+    #[arg(long, action)]
+    pub(crate) force: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let nostr_state = get_state_from_cache(Some(git_repo_path), &repo_ref)
+        .await
+        .context("cannot find a nostr state event for this repository. the maintainer must push via the nostr remote before it can be mirrored")?;
+
+    let mirror_url = get_mirror_url(&git_repo, &args.remote)?;
+
+    let proxy = &config.git_proxy.value;
+
+    fetch_missing_oids(&git_repo, &nostr_state, &repo_ref.git_server, proxy);
+
+    let mirror_state = list_mirror_refs(&git_repo, &mirror_url, proxy)?;
+
+    let updates = plan_mirror_updates(&git_repo, &nostr_state.state, &mirror_state, args.force);
+
+    if updates.is_empty() {
+        println!("{} already in sync with nostr state", args.remote);
+        return Ok(());
+    }
+
+    let results = push_mirror_updates(&git_repo, &mirror_url, &updates, proxy)?;
+
+    let mut failures = 0;
+    for update in &updates {
+        match results.get(&update.name) {
+            Some(Err(error)) => {
+                failures += 1;
+                println!("! {} {error}", update.summary());
+            }
+            _ => println!("  {}", update.summary()),
+        }
+    }
+
+    if failures > 0 {
+        bail!(
+            "{failures} of {} refs failed to mirror to {}",
+            updates.len(),
+            args.remote
+        );
+    }
+    Ok(())
+}
+
+fn get_mirror_url(git_repo: &Repo, remote_name_or_url: &str) -> Result<String> {
+    if let Ok(remote) = git_repo.git_repo.find_remote(remote_name_or_url) {
+        let url = remote
+            .pushurl()
+            .or(remote.url())
+            .context(format!("git remote {remote_name_or_url} has no url"))?;
+        if url.starts_with("nostr://") {
+            bail!("mirror target must be a plain git remote, not a nostr remote");
+        }
+        Ok(url.to_string())
+    } else {
+        Ok(remote_name_or_url.to_string())
+    }
+}
+
+/// best effort fetch of state oids not yet present locally from the repo's
+/// git servers so they can be pushed to the mirror
+fn fetch_missing_oids(
+    git_repo: &Repo,
+    nostr_state: &RepoState,
+    git_servers: &[String],
+    proxy: &Option<String>,
+) {
+    let missing: Vec<String> = nostr_state
+        .state
+        .values()
+        .filter(|v| !v.starts_with("ref: "))
+        .filter(|v| {
+            !Oid::from_str(v)
+                .is_ok_and(|oid| git_repo.git_repo.odb().is_ok_and(|odb| odb.exists(oid)))
+        })
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    let Ok(git_config) = git_repo.git_repo.config() else {
+        return;
+    };
+    for url in git_servers {
+        if ensure_onion_url_has_proxy(url, proxy, ProxyUse::GitServers).is_err() {
+            continue;
+        }
+        if let Ok(mut remote) = git_repo.git_repo.remote_anonymous(url) {
+            let auth = GitAuthenticator::default();
+            let mut remote_callbacks = git2::RemoteCallbacks::new();
+            remote_callbacks.credentials(auth.credentials(&git_config));
+            let mut fetch_options = git2::FetchOptions::new();
+            fetch_options.remote_callbacks(remote_callbacks);
+            fetch_options.proxy_options(git_proxy_options(proxy));
+            if remote
+                .fetch(&missing, Some(&mut fetch_options), None)
+                .is_ok()
+            {
+                let _ = remote.disconnect();
+                return;
+            }
+        }
+    }
+}
+
+fn list_mirror_refs(
+    git_repo: &Repo,
+    mirror_url: &str,
+    proxy: &Option<String>,
+) -> Result<HashMap<String, String>> {
+    ensure_onion_url_has_proxy(mirror_url, proxy, ProxyUse::GitServers)?;
+    let git_config = git_repo.git_repo.config()?;
+    let mut remote = git_repo.git_repo.remote_anonymous(mirror_url)?;
+    let auth = GitAuthenticator::default();
+    let mut remote_callbacks = git2::RemoteCallbacks::new();
+    remote_callbacks.credentials(auth.credentials(&git_config));
+    remote
+        .connect_auth(
+            git2::Direction::Push,
+            Some(remote_callbacks),
+            Some(git_proxy_options(proxy)),
+        )
+        .context(format!("failed to connect to mirror {mirror_url}"))?;
+    let mut state = HashMap::new();
+    for head in remote.list()? {
+        if head.symref_target().is_none()
+            && (head.name().starts_with("refs/heads/") || head.name().starts_with("refs/tags/"))
+        {
+            state.insert(head.name().to_string(), head.oid().to_string());
+        }
+    }
+    remote.disconnect()?;
+    Ok(state)
+}
+
+#[derive(Debug, PartialEq)]
+enum MirrorUpdateKind {
+    Create,
+    FastForward,
+    Force,
+    Delete,
+    /// non-fast-forward without `--force`
+    Rejected,
+}
+
+#[derive(Debug)]
+struct MirrorUpdate {
+    name: String,
+    from: Option<String>,
+    to: Option<String>,
+    kind: MirrorUpdateKind,
+}
+
+impl MirrorUpdate {
+    fn summary(&self) -> String {
+        let short = |s: &Option<String>| {
+            s.as_ref()
+                .map(|s| s.chars().take(7).collect::<String>())
+                .unwrap_or_default()
+        };
+        match self.kind {
+            MirrorUpdateKind::Create => format!("* [new]    {}", self.name),
+            MirrorUpdateKind::FastForward => {
+                format!("  {}..{} {}", short(&self.from), short(&self.to), self.name)
+            }
+            MirrorUpdateKind::Force => format!(
+                "+ {}...{} {} (forced update)",
+                short(&self.from),
+                short(&self.to),
+                self.name
+            ),
+            MirrorUpdateKind::Delete => format!("- [delete] {}", self.name),
+            MirrorUpdateKind::Rejected => {
+                format!("! [rejected] {} (non-fast-forward, use --force)", self.name)
+            }
+        }
+    }
+
+    fn refspec(&self) -> Option<String> {
+        match self.kind {
+            MirrorUpdateKind::Create | MirrorUpdateKind::FastForward => {
+                Some(format!("{}:{}", mirror_tmp_ref_name(&self.name), self.name))
+            }
+            MirrorUpdateKind::Force => Some(format!(
+                "+{}:{}",
+                mirror_tmp_ref_name(&self.name),
+                self.name
+            )),
+            MirrorUpdateKind::Delete => Some(format!(":{}", self.name)),
+            MirrorUpdateKind::Rejected => None,
+        }
+    }
+}
+
+fn mirror_tmp_ref_name(name: &str) -> String {
+    format!("refs/ngit-mirror/{}", name.trim_start_matches("refs/"))
+}
+
+fn plan_mirror_updates(
+    git_repo: &Repo,
+    nostr_state: &HashMap<String, String>,
+    mirror_state: &HashMap<String, String>,
+    force: bool,
+) -> Vec<MirrorUpdate> {
+    let mut updates = vec![];
+    for (name, value) in nostr_state {
+        if value.starts_with("ref: ")
+            || !(name.starts_with("refs/heads/") || name.starts_with("refs/tags/"))
+            // proposals are not part of the maintainers' state
+            || name.starts_with("refs/heads/pr/")
+        {
+            continue;
+        }
+        match mirror_state.get(name) {
+            None => updates.push(MirrorUpdate {
+                name: name.clone(),
+                from: None,
+                to: Some(value.clone()),
+                kind: MirrorUpdateKind::Create,
+            }),
+            Some(mirror_value) if mirror_value == value => {}
+            Some(mirror_value) => {
+                let fast_forward =
+                    if let (Ok(to), Ok(from)) = (str_to_sha1(value), str_to_sha1(mirror_value)) {
+                        git_repo.ancestor_of(&to, &from).unwrap_or(false)
+                    } else {
+                        false
+                    };
+                updates.push(MirrorUpdate {
+                    name: name.clone(),
+                    from: Some(mirror_value.clone()),
+                    to: Some(value.clone()),
+                    kind: if fast_forward {
+                        MirrorUpdateKind::FastForward
+                    } else if force {
+                        MirrorUpdateKind::Force
+                    } else {
+                        MirrorUpdateKind::Rejected
+                    },
+                });
+            }
+        }
+    }
+    for (name, mirror_value) in mirror_state {
+        if !nostr_state.contains_key(name) && !name.starts_with("refs/heads/pr/") {
+            updates.push(MirrorUpdate {
+                name: name.clone(),
+                from: Some(mirror_value.clone()),
+                to: None,
+                kind: MirrorUpdateKind::Delete,
+            });
+        }
+    }
+    updates.sort_by(|a, b| a.name.cmp(&b.name));
+    updates
+}
+
+/// pushes updates to mirror and returns the outcome per ref
+fn push_mirror_updates(
+    git_repo: &Repo,
+    mirror_url: &str,
+    updates: &[MirrorUpdate],
+    proxy: &Option<String>,
+) -> Result<HashMap<String, Result<(), String>>> {
+    let mut results: HashMap<String, Result<(), String>> = HashMap::new();
+    let mut refspecs = vec![];
+    for update in updates {
+        if update.kind == MirrorUpdateKind::Rejected {
+            results.insert(update.name.clone(), Err("non-fast-forward".to_string()));
+            continue;
+        }
+        if let Some(to) = &update.to {
+            // libgit2 can only push from a reference so stage a temporary one
+            let created = Oid::from_str(to)
+                .map_err(anyhow::Error::from)
+                .and_then(|oid| {
+                    git_repo
+                        .git_repo
+                        .reference(&mirror_tmp_ref_name(&update.name), oid, true, "ngit mirror")
+                        .map_err(anyhow::Error::from)
+                });
+            if created.is_err() {
+                results.insert(update.name.clone(), Err(format!("{to} not found locally")));
+                continue;
+            }
+        }
+        if let Some(refspec) = update.refspec() {
+            refspecs.push(refspec);
+        }
+    }
+
+    if !refspecs.is_empty() {
+        let git_config = git_repo.git_repo.config()?;
+        let mut remote = git_repo.git_repo.remote_anonymous(mirror_url)?;
+        let auth = GitAuthenticator::default();
+        let mut remote_callbacks = git2::RemoteCallbacks::new();
+        remote_callbacks.credentials(auth.credentials(&git_config));
+        let mut ref_errors: HashMap<String, String> = HashMap::new();
+        remote_callbacks.push_update_reference(|name, error| {
+            if let Some(error) = error {
+                ref_errors.insert(name.to_string(), error.to_string());
+            }
+            Ok(())
+        });
+        let mut push_options = git2::PushOptions::new();
+        push_options.remote_callbacks(remote_callbacks);
+        push_options.proxy_options(git_proxy_options(proxy));
+        let push_result = remote.push(&refspecs, Some(&mut push_options));
+        let _ = remote.disconnect();
+        drop(push_options);
+        for update in updates {
+            if results.contains_key(&update.name) || update.kind == MirrorUpdateKind::Rejected {
+                continue;
+            }
+            let result = if let Err(error) = &push_result {
+                Err(error.message().to_string())
+            } else if let Some(error) = ref_errors.get(&update.name) {
+                Err(error.clone())
+            } else {
+                Ok(())
+            };
+            results.insert(update.name.clone(), result);
+        }
+    }
+
+    for update in updates {
+        if let Ok(mut reference) = git_repo
+            .git_repo
+            .find_reference(&mirror_tmp_ref_name(&update.name))
+        {
+            let _ = reference.delete();
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn state_from_test_repo(test_repo: &GitTestRepo) -> Result<HashMap<String, String>> {
+        let mut state = HashMap::new();
+        for reference in test_repo.git_repo.references()? {
+            let reference = reference?;
+            if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+                if name.starts_with("refs/heads/") || name.starts_with("refs/tags/") {
+                    state.insert(name.to_string(), target.to_string());
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    fn mirror(
+        git_repo: &Repo,
+        bare: &GitTestRepo,
+        nostr_state: &HashMap<String, String>,
+        force: bool,
+    ) -> Result<(Vec<MirrorUpdate>, HashMap<String, Result<(), String>>)> {
+        let url = bare.dir.to_str().unwrap().to_string();
+        let mirror_state = list_mirror_refs(git_repo, &url, &None)?;
+        let updates = plan_mirror_updates(git_repo, nostr_state, &mirror_state, force);
+        let results = push_mirror_updates(git_repo, &url, &updates, &None)?;
+        Ok((updates, results))
+    }
+
+    #[test]
+    fn adds_updates_and_deletes_branches_and_tags() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let bare = GitTestRepo::recreate_as_bare(&test_repo)?;
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+
+        // add branch and tag
+        test_repo.create_branch("feature")?;
+        test_repo.checkout("feature")?;
+        std::fs::write(test_repo.dir.join("f1.md"), "some content")?;
+        let feature_tip = test_repo.stage_and_commit("add f1.md")?;
+        test_repo
+            .git_repo
+            .reference("refs/tags/v1.0.0", feature_tip, true, "test")?;
+        let state = state_from_test_repo(&test_repo)?;
+        let (updates, results) = mirror(&git_repo, &bare, &state, false)?;
+        assert!(updates.iter().all(|u| u.kind == MirrorUpdateKind::Create));
+        assert!(results.values().all(Result::is_ok));
+        assert_eq!(
+            bare.git_repo.refname_to_id("refs/heads/feature")?,
+            feature_tip
+        );
+        assert_eq!(
+            bare.git_repo.refname_to_id("refs/tags/v1.0.0")?,
+            feature_tip
+        );
+
+        // fast-forward update
+        std::fs::write(test_repo.dir.join("f2.md"), "some content")?;
+        let new_tip = test_repo.stage_and_commit("add f2.md")?;
+        let state = state_from_test_repo(&test_repo)?;
+        let (updates, _) = mirror(&git_repo, &bare, &state, false)?;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].kind, MirrorUpdateKind::FastForward);
+        assert_eq!(bare.git_repo.refname_to_id("refs/heads/feature")?, new_tip);
+
+        // delete branch and tag
+        let mut state = state_from_test_repo(&test_repo)?;
+        state.remove("refs/heads/feature");
+        state.remove("refs/tags/v1.0.0");
+        let (updates, results) = mirror(&git_repo, &bare, &state, false)?;
+        assert!(updates.iter().all(|u| u.kind == MirrorUpdateKind::Delete));
+        assert!(results.values().all(Result::is_ok));
+        assert!(bare.git_repo.find_reference("refs/heads/feature").is_err());
+        assert!(bare.git_repo.find_reference("refs/tags/v1.0.0").is_err());
+
+        // in sync
+        let (updates, _) = mirror(&git_repo, &bare, &state, false)?;
+        assert!(updates.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn non_fast_forward_rejected_without_force() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let bare = GitTestRepo::recreate_as_bare(&test_repo)?;
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+        let original_tip = test_repo.get_tip_of_local_branch("main")?;
+
+        let mut state = state_from_test_repo(&test_repo)?;
+        let parent = test_repo.git_repo.find_commit(original_tip)?.parent_id(0)?;
+        state.insert("refs/heads/main".to_string(), parent.to_string());
+
+        let (updates, results) = mirror(&git_repo, &bare, &state, false)?;
+        assert_eq!(updates[0].kind, MirrorUpdateKind::Rejected);
+        assert!(results.get("refs/heads/main").unwrap().is_err());
+        assert_eq!(
+            bare.git_repo.refname_to_id("refs/heads/main")?,
+            original_tip
+        );
+
+        let (updates, results) = mirror(&git_repo, &bare, &state, true)?;
+        assert_eq!(updates[0].kind, MirrorUpdateKind::Force);
+        assert!(results.get("refs/heads/main").unwrap().is_ok());
+        assert_eq!(bare.git_repo.refname_to_id("refs/heads/main")?, parent);
+        Ok(())
+    }
+}
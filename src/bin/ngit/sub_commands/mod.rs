@@ -1,6 +1,32 @@
+pub mod alias;
+pub mod apply;
+pub mod backups;
+pub mod cache;
+pub mod config;
+pub mod delete_proposal;
+pub mod diff;
+pub mod doctor;
+pub mod edit_proposal;
+pub mod event_status;
 pub mod export_keys;
+pub mod fetch;
+pub mod first_run;
+pub mod hooks;
+pub mod inbox;
 pub mod init;
+pub mod label_proposal;
 pub mod list;
+pub mod log;
 pub mod login;
 pub mod logout;
+pub mod migrate;
+pub mod mirror;
+pub mod open_proposal;
+pub mod proposal_coverage;
+pub mod prune_branches;
 pub mod send;
+pub mod share;
+pub mod show_proposal;
+pub mod status;
+pub mod watch;
+pub mod watched;
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use ngit::{
+    repo_ref::RepoRef,
+    web_viewer::{open_url, proposal_web_urls, system_opener},
+};
+use nostr::Event;
+
+use crate::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms, prompts_allowed},
+    config::Config,
+    git::Repo,
+    sub_commands::share::{
+        SubCommandArgs, fetch_repo_ref, find_proposal, proposal_nevent, repo_naddr,
+    },
+};
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let repo_ref = fetch_repo_ref(&git_repo, config).await?;
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+    open_in_browser(&git_repo, &repo_ref, &proposal, config)
+}
+
+/// open the proposal in the configured web viewer, offering the pages listed
+/// in the repository announcement as alternatives
+pub(crate) fn open_in_browser(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposal: &Event,
+    config: &Config,
+) -> Result<()> {
+    let urls = proposal_web_urls(
+        &config.web_viewer_url.value,
+        &repo_ref.web,
+        &repo_naddr(git_repo, repo_ref)?,
+        &proposal_nevent(git_repo, repo_ref, proposal)?,
+    );
+    let url = if urls.len() > 1 && prompts_allowed() {
+        &urls[Interactor::default().choice(
+            PromptChoiceParms::default()
+                .with_prompt("open in")
+                .with_default(0)
+                .with_choices(urls.clone()),
+        )?]
+    } else {
+        &urls[0]
+    };
+    println!("{}", open_url(url, system_opener()));
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Context, Result};
+use ngit::{
+    client::{get_all_proposal_patch_events_from_cache, get_repo_relays, print_repo_relays_notice},
+    event_sources::{get_event_sources, record_event_sources},
+    git_events::{commit_msg_from_patch_oneliner, event_to_cover_letter},
+    login::get_likely_logged_in_user,
+    publish_status::{RelayResponse, get_relay_responses, record_relay_responses},
+};
+use nostr::{Event, Timestamp};
+use nostr_sdk::RelayUrl;
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+    sub_commands::share::find_proposal,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one.
+    /// defaults to the proposal of the checked out branch
+    pub(crate) id: Option<String>,
+    /// republish events you authored to the repo relays missing them
+    #[arg(long, action)]
+    pub(crate) repair: bool,
+    /// with --repair, also republish events authored by others. they are
+    /// already signed so this needs no login
+    #[arg(long, action, requires = "repair")]
+    pub(crate) include_others: bool,
+}
+
+fn short_id(event: &Event) -> String {
+    event.id.to_hex()[..7].to_string()
+}
+
+fn describe(event: &Event, proposal: &Event) -> String {
+    if event.id.eq(&proposal.id) {
+        let title = event_to_cover_letter(event).map_or_else(
+            |_| commit_msg_from_patch_oneliner(event).unwrap_or_default(),
+            |cl| cl.title,
+        );
+        format!("proposal {} \"{title}\"", short_id(event))
+    } else {
+        format!(
+            "patch    {} {}",
+            short_id(event),
+            commit_msg_from_patch_oneliner(event).unwrap_or_default()
+        )
+    }
+}
+
+/// whether the relay returned the event in a fetch or accepted it when it
+/// was published from this repository
+fn relay_holds(git_repo: &Repo, relay: &RelayUrl, event: &Event) -> Result<bool> {
+    let key = relay.as_str_without_trailing_slash();
+    Ok(
+        get_event_sources(git_repo.get_path()?, &event.id).contains_key(key)
+            || get_relay_responses(git_repo.get_path()?, &event.id)
+                .get(key)
+                .is_some_and(|response| response.accepted),
+    )
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+    let mut patches: Vec<Event> =
+        get_all_proposal_patch_events_from_cache(git_repo_path, &repo_ref, &proposal.id)
+            .await?
+            .into_iter()
+            .filter(|e| e.id.ne(&proposal.id))
+            .collect();
+    patches.sort_by_key(|e| (e.created_at, e.id));
+    patches.dedup_by_key(|e| e.id);
+    let events: Vec<Event> = std::iter::once(proposal.clone()).chain(patches).collect();
+
+    let (repo_relays, repo_relays_source) =
+        get_repo_relays(Some(git_repo_path), &repo_ref, client.get_fallback_relays()).await;
+    print_repo_relays_notice(repo_relays_source);
+
+    let column_width = events.len().to_string().len();
+    let relay_column_width = repo_relays
+        .iter()
+        .map(|r| r.as_str_without_trailing_slash().len())
+        .max()
+        .unwrap_or_default();
+
+    for (i, event) in events.iter().enumerate() {
+        println!("{: >column_width$} {}", i + 1, describe(event, &proposal));
+    }
+    println!();
+    println!(
+        "{: <relay_column_width$} {}",
+        "",
+        (1..=events.len())
+            .map(|i| format!("{i: >column_width$}"))
+            .collect::<Vec<String>>()
+            .join(" ")
+    );
+    let mut gaps: Vec<(RelayUrl, Vec<&Event>)> = vec![];
+    for relay in &repo_relays {
+        let mut marks = vec![];
+        let mut missing = vec![];
+        for event in &events {
+            if relay_holds(&git_repo, relay, event)? {
+                marks.push(format!("{: >column_width$}", "✔"));
+            } else {
+                marks.push(
+                    console::style(format!("{: >column_width$}", "✘"))
+                        .red()
+                        .to_string(),
+                );
+                missing.push(event);
+            }
+        }
+        println!(
+            "{: <relay_column_width$} {}{}",
+            relay.as_str_without_trailing_slash(),
+            marks.join(" "),
+            if missing.is_empty() {
+                String::new()
+            } else {
+                format!(" missing {} of {}", missing.len(), events.len())
+            }
+        );
+        if !missing.is_empty() {
+            gaps.push((relay.clone(), missing));
+        }
+    }
+
+    if gaps.is_empty() {
+        println!("every repo relay holds all {} events", events.len());
+        return Ok(());
+    }
+    if !args.repair {
+        println!(
+            "{} repo relay{} missing events. use --repair to republish them",
+            gaps.len(),
+            if gaps.len() == 1 { " is" } else { "s are" },
+        );
+        return Ok(());
+    }
+
+    let logged_in_user = get_likely_logged_in_user(git_repo_path).await?;
+    let mut skipped = 0;
+    let mut responses = vec![];
+    let mut received: HashMap<_, BTreeSet<String>> = HashMap::new();
+    for (relay, missing) in gaps {
+        let relay_str = relay.as_str_without_trailing_slash();
+        for event in missing {
+            if !args.include_others && !logged_in_user.is_some_and(|me| me.eq(&event.pubkey)) {
+                skipped += 1;
+                continue;
+            }
+            let result = client
+                .send_event_to(Some(git_repo_path), relay_str, event.clone())
+                .await;
+            match RelayResponse::from_send_result(&result) {
+                Some(response) => {
+                    println!("{relay_str} {} {response}", short_id(event));
+                    if response.accepted {
+                        received
+                            .entry(event.id)
+                            .or_default()
+                            .insert(relay_str.to_string());
+                    }
+                    responses.push((event.id, relay_str.to_string(), response));
+                }
+                None => {
+                    if let Err(error) = result {
+                        println!("{relay_str} {} failed: {error}", short_id(event));
+                    }
+                }
+            }
+        }
+    }
+    record_relay_responses(git_repo_path, &responses)?;
+    record_event_sources(git_repo_path, &received, Timestamp::now())?;
+    if skipped > 0 {
+        println!(
+            "skipped {skipped} missing event{} authored by others. use --include-others to republish {} too",
+            if skipped == 1 { "" } else { "s" },
+            if skipped == 1 { "it" } else { "them" },
+        );
+    }
+    client.disconnect().await?;
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use git2::Oid;
+use ngit::{
+    client::{
+        get_all_proposal_patch_events_from_cache, get_events_from_local_cache,
+        get_proposals_and_revisions_from_cache, get_repo_ref_from_cache,
+    },
+    git_events::{
+        event_is_revision_root, event_to_cover_letter, get_commit_id_from_patch,
+        get_most_recent_patch_with_ancestors, is_event_proposal_root_for_branch, status_kinds,
+    },
+    login::get_likely_logged_in_user,
+    repo_ref::RepoRef,
+};
+use nostr_sdk::{Event, Kind, hashes::sha1::Hash as Sha1Hash};
+
+use crate::{
+    cli::Cli,
+    cli_interactor::{Interactor, InteractorPrompt, PromptMultiChoiceParms},
+    client::{Client, Params},
+    config::Config,
+    git::{
+        Repo, RepoActions, oid_to_sha1, oid_to_shorthand_string, patch_id::is_content_merged,
+        sha1_to_oid,
+    },
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// list the branches that would be deleted without deleting them
+    #[arg(long, action)]
+    pub(crate) dry_run: bool,
+}
+
+struct PrunableBranch {
+    name: String,
+    tip: Sha1Hash,
+    description: String,
+    /// the tip, or its changes, are in the default branch so nothing is lost by
+    /// deleting it
+    merged: bool,
+}
+
+/// the kind of the most recent status event of `proposal`
+fn proposal_status(proposal: &Event, statuses: &[Event]) -> Kind {
+    statuses
+        .iter()
+        .filter(|e| {
+            status_kinds().contains(&e.kind) && e.tags.event_ids().any(|id| id == &proposal.id)
+        })
+        .max_by_key(|e| e.created_at)
+        .map_or(Kind::GitStatusOpen, |e| e.kind)
+}
+
+/// whether `tip` is the latest revision of `proposal`, or an ancestor of it
+async fn is_published(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposal: &Event,
+    tip: &Sha1Hash,
+) -> Result<bool> {
+    let Ok(patches) =
+        get_all_proposal_patch_events_from_cache(git_repo.get_path()?, repo_ref, &proposal.id)
+            .await
+    else {
+        return Ok(false);
+    };
+    let Some(latest_commit) = get_most_recent_patch_with_ancestors(patches)
+        .ok()
+        .and_then(|chain| chain.first().and_then(|e| get_commit_id_from_patch(e).ok()))
+    else {
+        return Ok(false);
+    };
+    if tip.to_string().eq(&latest_commit) {
+        return Ok(true);
+    }
+    Ok(git_repo.does_commit_exist(&latest_commit)?
+        && git_repo.ancestor_of(&oid_to_sha1(&Oid::from_str(&latest_commit)?), tip)?)
+}
+
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let proposals: Vec<Event> =
+        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+            .await?
+            .into_iter()
+            .filter(|e| !event_is_revision_root(e))
+            .collect();
+    let statuses = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kinds(status_kinds())
+            .events(proposals.iter().map(|e| e.id)),
+    ])
+    .await?;
+    let logged_in_user = get_likely_logged_in_user(git_repo_path).await?;
+    let (main_branch_name, main_tip) = git_repo.get_main_or_master_branch()?;
+    let checked_out_branch_name = git_repo.get_checked_out_branch_name().ok();
+
+    let mut branch_names: Vec<String> = git_repo
+        .get_local_branch_names()?
+        .into_iter()
+        .filter(|name| name.starts_with("pr/"))
+        .collect();
+    branch_names.sort();
+
+    let mut prunable = vec![];
+    for name in branch_names {
+        let Some(proposal) = proposals.iter().find(|e| {
+            is_event_proposal_root_for_branch(e, &name, logged_in_user.as_ref()).unwrap_or(false)
+        }) else {
+            continue;
+        };
+        let tip = git_repo.get_tip_of_branch(&name)?;
+        // including when squash or rebase merged
+        let merged = is_content_merged(&git_repo, &tip, &main_tip)?;
+        let status = match proposal_status(proposal, &statuses) {
+            Kind::GitStatusClosed => "closed",
+            Kind::GitStatusApplied => "applied",
+            // merged without an applied status being published
+            Kind::GitStatusOpen if merged => "applied",
+            _ => continue,
+        };
+        if !merged && !is_published(&git_repo, &repo_ref, proposal, &tip).await? {
+            println!(
+                "keeping {name} as it has commits that aren't in {main_branch_name} or the proposal"
+            );
+            continue;
+        }
+        if checked_out_branch_name
+            .as_ref()
+            .is_some_and(|b| b.eq(&name))
+        {
+            println!("keeping {name} as it is checked out");
+            continue;
+        }
+        let title = event_to_cover_letter(proposal).map_or(String::new(), |cl| cl.title);
+        prunable.push(PrunableBranch {
+            description: if merged {
+                format!("{status}, in {main_branch_name}: {title}")
+            } else {
+                format!("{status}, matches the proposal: {title}")
+            },
+            name,
+            tip,
+            merged,
+        });
+    }
+
+    if prunable.is_empty() {
+        println!("no branches of closed or applied proposals to delete");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        for branch in &prunable {
+            if branch.merged {
+                println!("would delete {} ({})", branch.name, branch.description);
+            } else {
+                println!(
+                    "would only delete {} if selected ({})",
+                    branch.name, branch.description
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let selected: Vec<&PrunableBranch> = if cli_args.yes {
+        prunable.iter().filter(|branch| branch.merged).collect()
+    } else {
+        Interactor::default()
+            .multi_choice(
+                PromptMultiChoiceParms::default()
+                    .with_prompt("delete branches of closed or applied proposals")
+                    .with_flag("--yes")
+                    .with_choices(
+                        prunable
+                            .iter()
+                            .map(|branch| format!("{} ({})", branch.name, branch.description))
+                            .collect(),
+                    )
+                    .with_defaults(prunable.iter().map(|branch| branch.merged).collect()),
+            )?
+            .into_iter()
+            .map(|i| &prunable[i])
+            .collect()
+    };
+
+    if selected.is_empty() {
+        println!("no branches deleted");
+    }
+    for branch in selected {
+        git_repo
+            .git_repo
+            .find_branch(&branch.name, git2::BranchType::Local)
+            .and_then(|mut b| b.delete())
+            .context(format!("failed to delete branch {}", branch.name))?;
+        println!(
+            "deleted {} (was {})",
+            branch.name,
+            oid_to_shorthand_string(sha1_to_oid(&branch.tip)?)?
+        );
+    }
+    Ok(())
+}
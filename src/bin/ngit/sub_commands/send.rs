@@ -1,24 +1,56 @@
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
 use console::Style;
-use ngit::{client::send_events, git_events::generate_cover_letter_and_patch_events};
+use ngit::{
+    attachments::{Attachment, BlobDescriptor, attachment_tag, blossom_upload_auth, sha256_hex},
+    client::{
+        get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache,
+        get_repo_relays, print_repo_relays_notice, send_events, sign_event,
+    },
+    error::NgitError,
+    git::patch_id::{find_commits_already_upstream, find_commits_in_patches},
+    git_events::{
+        binary_patch_size_warning, branch_name_from_title, event_is_revision_root,
+        event_to_cover_letter, generate_cover_letter_and_patch_events,
+        get_most_recent_patch_with_ancestors, is_event_proposal_root_for_branch,
+        proposal_expiration, status_kinds,
+    },
+    hooks::{commit_range, run_pre_send_hook},
+    login::{
+        get_likely_logged_in_user,
+        user::{get_names_for_display, short_npub},
+    },
+    output::{self, dim, print_human},
+    owners::{match_owners, paths_touched, read_owners_file},
+    repo_ref::{ProposalSubmission, RepoRef},
+};
 use nostr::{
-    ToBech32,
-    nips::{nip10::Marker, nip19::Nip19Event},
+    JsonUtil, ToBech32,
+    nips::{nip01::Coordinate, nip10::Marker, nip19::Nip19Event},
 };
-use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use nostr_sdk::{EventId, Kind, NostrSigner, PublicKey, RelayUrl, hashes::sha1::Hash as Sha1Hash};
+use serde::Serialize;
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
     cli_interactor::{
-        Interactor, InteractorPrompt, PromptConfirmParms, PromptInputParms, PromptMultiChoiceParms,
+        Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms, PromptInputParms,
+        PromptMultiChoiceParms,
     },
     client::{
-        Client, Connect, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache,
+        Client, Connect, Params, correct_clock_ahead_after_rejection, fetching_with_report,
+        get_events_from_local_cache, get_repo_ref_from_cache, warn_on_clock_skew,
     },
-    git::{Repo, RepoActions, identify_ahead_behind},
-    git_events::{event_is_patch_set_root, event_tag_from_nip19_or_hex},
+    config::Config,
+    git::{Repo, RepoActions, get_git_dir, identify_ahead_behind},
+    git_events::{event_is_cover_letter, event_is_patch_set_root, event_tag_from_nip19_or_hex},
     login,
     repo_ref::get_repo_coordinates_when_remote_unknown,
 };
@@ -26,47 +58,186 @@ use crate::{
 #[derive(Debug, clap::Args)]
 pub struct SubCommandArgs {
     #[arg(default_value = "")]
-    /// commits to send as proposal; like in `git format-patch` eg. HEAD~2
+    /// commits to send as proposal: a range eg. main..feature, a branch name
+    /// meaning upstream..branch, or a commit eg. HEAD~2 meaning HEAD~2..HEAD
     pub(crate) since_or_range: String,
     #[clap(long, value_parser, num_args = 0.., value_delimiter = ' ')]
     /// references to an existing proposal for which this is a new
-    /// version and/or events / npubs to tag as mentions
+    /// version and/or events / npubs to tag as mentions. accepts nevent,
+    /// note, naddr, npub or hex event id. a repository naddr first revises
+    /// its proposal for the checked out branch
     pub(crate) in_reply_to: Vec<String>,
     /// don't prompt for a cover letter
     #[arg(long, action)]
     pub(crate) no_cover_letter: bool,
-    /// optional cover letter title
+    /// optional cover letter title. without --title or --description the
+    /// cover letter is written in your git editor
     #[clap(short, long)]
     pub(crate) title: Option<String>,
     #[clap(short, long)]
     /// optional cover letter description
     pub(crate) description: Option<String>,
+    /// label for the proposal branch instead of the checked out branch name.
+    /// derived from the title when sending from a detached HEAD
+    #[clap(long)]
+    pub(crate) branch_name: Option<String>,
+    /// nostr git remote to use when several point at different repositories
+    #[clap(long)]
+    pub(crate) remote: Option<String>,
+    /// stable, line-oriented output on stdout for scripts and editor plugins
+    ///
+    /// all other output goes to stderr and spinners are disabled. once sent,
+    /// stdout has one record per line:
+    ///   event <id> kind <kind> status <published|failed> relays <accepted>/<attempted>
+    ///     for each event, in the order they were created
+    ///   proposal-root <id>
+    ///     the proposal, or the proposal being revised
+    ///   branch <name>
+    ///     as listed by git-remote-nostr eg. pr/feature(1a2b3c4d)
+    /// exit codes are as listed in `ngit --help`
+    #[arg(long, action)]
+    pub(crate) porcelain: bool,
+    /// once sent, write a json summary for CI scripts to PATH
+    ///
+    /// defaults to .git/nostr/send-summary.json. the schema is stable,
+    /// fields may be added but existing fields won't change:
+    ///   proposal_root  hex id of the proposal, or the proposal being revised
+    ///   patches        hex ids of the patch events, oldest first
+    ///   repo           repository coordinate eg. 30617:<pubkey hex>:<identifier>
+    ///   branch         as listed by git-remote-nostr eg. pr/feature(1a2b3c4d)
+    ///   relays         map of relay url to hex ids of the events it accepted
+    #[arg(long, value_name = "PATH", num_args = 0..=1, verbatim_doc_comment)]
+    pub(crate) emit_summary: Option<Option<PathBuf>>,
+    /// print the --emit-summary json on stdout. all other output goes to
+    /// stderr and spinners are disabled
+    #[arg(long, action, conflicts_with = "porcelain")]
+    pub(crate) summary_stdout: bool,
+    /// drop commits already upstream or in your open proposals without asking
+    #[arg(long, action, conflicts_with = "keep_duplicates")]
+    pub(crate) skip_duplicates: bool,
+    /// keep commits already upstream or in your open proposals without asking
+    #[arg(long, action)]
+    pub(crate) keep_duplicates: bool,
+    /// add a NIP-40 expiration to the cover letter and patches so relays
+    /// can delete them after DAYS. overrides nostr.proposal-expiry-days
+    #[arg(long, value_name = "DAYS")]
+    pub(crate) expiry_days: Option<u64>,
+    /// send even if the proposal doesn't meet the repository's submission
+    /// policy
+    #[arg(long, action)]
+    pub(crate) ignore_policy: bool,
+    /// don't publish to the blaster relays. overrides nostr.use-blaster
+    #[arg(long, action)]
+    pub(crate) no_blaster: bool,
+    /// don't tag the owners of the paths changed, as listed in .nostr-owners
+    /// or the file set by nostr.owners-file
+    #[arg(long, action)]
+    pub(crate) no_notify_owners: bool,
+    /// upload FILE, eg. a screenshot, to the repository's blossom servers
+    /// and tag it on the proposal. can be used more than once
+    #[arg(long, value_name = "FILE")]
+    pub(crate) attach: Vec<PathBuf>,
+}
+
+/// the --emit-summary json documented in --help. fields may be added but
+/// existing fields must not change
+#[derive(Serialize)]
+struct SendSummary {
+    proposal_root: String,
+    patches: Vec<String>,
+    repo: String,
+    branch: String,
+    relays: BTreeMap<String, Vec<String>>,
+}
+
+impl SendSummary {
+    fn new(
+        events: &[nostr::Event],
+        accepted: &HashMap<String, HashSet<EventId>>,
+        proposal_root: &nostr::Event,
+        repo_coordinate: &Coordinate,
+    ) -> Result<Self> {
+        Ok(Self {
+            proposal_root: proposal_root.id.to_hex(),
+            patches: events
+                .iter()
+                .filter(|e| !event_is_cover_letter(e))
+                .map(|e| e.id.to_hex())
+                .collect(),
+            repo: repo_coordinate.to_string(),
+            branch: event_to_cover_letter(proposal_root)?
+                .get_branch_name_with_pr_prefix_and_shorthand_id()?,
+            relays: accepted
+                .iter()
+                .map(|(relay, ids)| {
+                    (
+                        relay.clone(),
+                        events
+                            .iter()
+                            .filter(|e| ids.contains(&e.id))
+                            .map(|e| e.id.to_hex())
+                            .collect(),
+                    )
+                })
+                .collect(),
+        })
+    }
 }
 
 #[allow(clippy::too_many_lines)]
-pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Result<()> {
+pub async fn launch(
+    cli_args: &Cli,
+    args: &SubCommandArgs,
+    config: &Config,
+    no_fetch: bool,
+) -> Result<()> {
+    let interactor = Interactor::default();
     let git_repo = Repo::discover().context("failed to find a git repository")?;
     let git_repo_path = git_repo.get_path()?;
+    // stdout is reserved for machine readable output
+    let machine_output = args.porcelain || args.summary_stdout;
 
     let (main_branch_name, main_tip) = git_repo
         .get_main_or_master_branch()
         .context("the default branches (main or master) do not exist")?;
 
-    let mut client = Client::default();
+    let mut client = Client::new(Params::with_config(config));
+    if args.no_blaster {
+        client.disable_blaster_relays();
+    }
 
-    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, args.remote.as_deref(), &client)
+            .await?;
 
     if !no_fetch {
-        fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+        let report =
+            fetching_with_report(git_repo_path, &client, &repo_coordinates, machine_output).await?;
+        warn_on_clock_skew(&report, cli_args.fix_timestamp)?;
     }
 
-    let (root_proposal_id, mention_tags) =
-        get_root_proposal_id_and_mentions_from_in_reply_to(git_repo.get_path()?, &args.in_reply_to)
-            .await?;
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let (repo_relays, repo_relays_source) =
+        get_repo_relays(Some(git_repo_path), &repo_ref, client.get_fallback_relays()).await;
+    print_repo_relays_notice(repo_relays_source);
+
+    let (root_proposal, mut mention_tags) = get_root_proposal_and_mentions_from_in_reply_to(
+        git_repo.get_path()?,
+        &args.in_reply_to,
+        &client,
+        &repo_relays,
+        cli_args.yes,
+    )
+    .await?;
+    let root_proposal_id = root_proposal.as_ref().map(|e| e.id.to_string());
 
     if let Some(root_ref) = args.in_reply_to.first() {
         if root_proposal_id.is_some() {
-            println!("creating proposal revision for: {root_ref}");
+            print_human(
+                machine_output,
+                &format!("creating proposal revision for: {root_ref}"),
+            );
         }
     }
 
@@ -90,14 +261,19 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     if commits.is_empty() {
         bail!("no commits selected");
     }
-    println!("creating proposal from {} commits:", commits.len());
+    print_human(
+        machine_output,
+        &format!("creating proposal from {} commits:", commits.len()),
+    );
 
-    let dim = Style::new().color256(247);
     for commit in &commits {
-        println!(
-            "{} {}",
-            dim.apply_to(commit.to_string().chars().take(7).collect::<String>()),
-            git_repo.get_commit_message_summary(commit)?
+        print_human(
+            machine_output,
+            &format!(
+                "{} {}",
+                dim(commit.to_string().chars().take(7).collect::<String>()),
+                git_repo.get_commit_message_summary(commit)?
+            ),
         );
     }
 
@@ -105,77 +281,151 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
         git_repo.get_commits_ahead_behind(&main_tip, commits.last().context("no commits")?)?;
 
     // check proposal ahead of origin/main
-    if first_commit_ahead.len().gt(&1) && !Interactor::default().confirm(
+    if first_commit_ahead.len().gt(&1) && !confirm_unless_yes(cli_args.yes,
             PromptConfirmParms::default()
                 .with_prompt(
                     format!("proposal builds on a commit {} ahead of '{main_branch_name}' - do you want to continue?", first_commit_ahead.len() - 1)
                 )
                 .with_default(false)
         ).context("failed to get confirmation response from interactor confirm")? {
-        bail!("aborting because selected commits were ahead of origin/master");
+        bail!(NgitError::UserAbort(anyhow!("aborting because selected commits were ahead of origin/master")));
     }
 
     // check if a selected commit is already in origin
     if commits.iter().any(|c| c.eq(&main_tip)) {
-        if !Interactor::default().confirm(
+        if !confirm_unless_yes(cli_args.yes,
             PromptConfirmParms::default()
                 .with_prompt(
                     format!("proposal contains commit(s) already in  '{main_branch_name}'. proceed anyway?")
                 )
                 .with_default(false)
         ).context("failed to get confirmation response from interactor confirm")? {
-            bail!("aborting as proposal contains commit(s) already in '{main_branch_name}'");
+            bail!(NgitError::UserAbort(anyhow!("aborting as proposal contains commit(s) already in '{main_branch_name}'")));
         }
     }
     // check proposal isn't behind origin/main
-    else if !behind.is_empty() && !Interactor::default().confirm(
+    else if !behind.is_empty() && !confirm_unless_yes(cli_args.yes,
             PromptConfirmParms::default()
                 .with_prompt(
-                    format!("proposal is {} behind '{main_branch_name}'. consider rebasing before submission. proceed anyway?", behind.len())
+                    format!("proposal is {} '{main_branch_name}'. consider rebasing before submission. proceed anyway?", output::behind(behind.len()))
                 )
                 .with_default(false)
         ).context("failed to get confirmation response from interactor confirm")? {
-        bail!("aborting so commits can be rebased");
+        bail!(NgitError::UserAbort(anyhow!("aborting so commits can be rebased")));
     }
 
-    let title = if args.no_cover_letter {
-        None
-    } else {
-        match &args.title {
-            Some(t) => Some(t.clone()),
-            None => {
-                if Interactor::default().confirm(
-                    PromptConfirmParms::default()
-                        .with_default(false)
-                        .with_prompt("include cover letter?"),
-                )? {
-                    Some(
-                        Interactor::default()
-                            .input(PromptInputParms::default().with_prompt("title"))?
-                            .clone(),
-                    )
-                } else {
-                    None
-                }
+    let duplicates = find_duplicate_commits(
+        &git_repo,
+        &commits,
+        &main_tip,
+        &repo_ref,
+        root_proposal.as_ref(),
+    )
+    .await?;
+    if !duplicates.is_empty() {
+        for (commit, duplicate_of) in &duplicates {
+            print_human(
+                machine_output,
+                &format!(
+                    "commit {} appears to already be {duplicate_of}",
+                    commit.to_string().chars().take(7).collect::<String>(),
+                ),
+            );
+        }
+        if drop_duplicates(&interactor, args.skip_duplicates, args.keep_duplicates)? {
+            commits.retain(|c| !duplicates.iter().any(|(d, _)| d.eq(c)));
+            if commits.is_empty() {
+                bail!("no commits left once those already upstream or proposed are dropped");
             }
+            print_human(
+                machine_output,
+                &format!(
+                    "dropped {} commit{}",
+                    duplicates.len(),
+                    if duplicates.len() == 1 { "" } else { "s" }
+                ),
+            );
         }
-    };
+    }
 
-    let cover_letter_title_description = if let Some(title) = title {
-        Some((
-            title,
-            if let Some(t) = &args.description {
-                t.clone()
-            } else {
-                Interactor::default()
-                    .input(PromptInputParms::default().with_prompt("cover letter description"))?
-                    .clone()
-            },
-        ))
+    // revisions keep the cover letter of the proposal they revise
+    let cover_letter_required =
+        repo_ref.submission_policy.cover_letter_required && root_proposal.is_none();
+
+    let include_cover_letter = include_cover_letter(
+        &interactor,
+        args.no_cover_letter,
+        args.title.as_ref(),
+        cover_letter_required,
+    )?;
+
+    let violations = repo_ref.submission_policy.violations(&ProposalSubmission {
+        builds_on_target: repo_ref
+            .submission_policy
+            .target_branch
+            .as_ref()
+            .and_then(|branch| builds_on_branch(&git_repo, commits.last()?, branch)),
+        has_cover_letter: include_cover_letter || !cover_letter_required,
+        patch_count: commits.len(),
+    });
+    if !violations.is_empty() {
+        if !args.ignore_policy {
+            bail!(NgitError::Config(anyhow!(
+                "proposal doesn't meet the repository's submission policy: {}. use --ignore-policy to send anyway",
+                violations.join(", ")
+            )));
+        }
+        for violation in &violations {
+            print_human(
+                machine_output,
+                &format!("WARNING: ignoring submission policy: {violation}"),
+            );
+        }
+    }
+
+    if !args.attach.is_empty() && repo_ref.blossoms.is_empty() {
+        bail!(NgitError::Config(anyhow!(
+            "the repository announcement lists no blossom servers to upload attachments to. maintainers can add them with `ngit init --blossoms <url>`"
+        )));
+    }
+    if let Some(path) = args.attach.iter().find(|path| !path.is_file()) {
+        bail!(NgitError::Config(anyhow!(
+            "attachment {} isn't a file",
+            path.display()
+        )));
+    }
+
+    // before the cover letter is written so no effort is lost when it fails
+    run_pre_send_hook(
+        &git_repo,
+        &commit_range(
+            &git_repo,
+            commits.last().context("no commits")?,
+            commits.first().context("no commits")?,
+        ),
+        &repo_ref.coordinate_with_hint(),
+    )?;
+
+    let cover_letter_title_description = if include_cover_letter {
+        Some(if args.title.is_none() && args.description.is_none() {
+            edit_cover_letter(&git_repo, &commits, config, machine_output)?
+        } else {
+            cover_letter_from_flags(&interactor, args.title.as_ref(), args.description.as_ref())?
+        })
     } else {
         None
     };
 
+    let branch_name = get_branch_name_label(
+        &git_repo,
+        args.branch_name.as_ref(),
+        cover_letter_title_description
+            .as_ref()
+            .map(|(title, _)| title.clone()),
+        commits.first(),
+        machine_output,
+    )?;
+
     let (signer, user_ref, _) = login::login_or_signup(
         &Some(&git_repo),
         &extract_signer_cli_arguments(cli_args).unwrap_or(None),
@@ -187,75 +437,149 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
 
     client.set_signer(signer.clone()).await;
 
-    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+    if !args.no_notify_owners {
+        for public_key in choose_owners_to_notify(
+            &git_repo,
+            &commits,
+            &client,
+            &repo_relays,
+            &user_ref.public_key,
+            machine_output,
+        )
+        .await?
+        {
+            let tag = nostr::Tag::public_key(public_key);
+            if !mention_tags.contains(&tag) {
+                mention_tags.push(tag);
+            }
+        }
+    }
+
+    for path in &args.attach {
+        let attachment = upload_attachment(path, &repo_ref.blossoms, &signer, config).await?;
+        print_human(
+            machine_output,
+            &format!("attached {} {}", attachment.name, dim(&attachment.url)),
+        );
+        mention_tags.push(attachment_tag(&attachment));
+    }
 
     // oldest first
     commits.reverse();
 
-    let events = generate_cover_letter_and_patch_events(
-        cover_letter_title_description.clone(),
-        &git_repo,
-        &commits,
-        &signer,
-        &repo_ref,
-        &root_proposal_id,
-        &mention_tags,
-    )
-    .await?;
+    // signed and sent again if relays reveal the local clock is ahead
+    let (events, accepted) = loop {
+        let events = generate_cover_letter_and_patch_events(
+            cover_letter_title_description.clone(),
+            &git_repo,
+            &commits,
+            &signer,
+            &repo_ref,
+            &root_proposal_id,
+            &branch_name,
+            &mention_tags,
+            proposal_expiration(args.expiry_days.or(config.proposal_expiry_days.value)),
+        )
+        .await?;
 
-    println!(
-        "posting {} patch{} {} a covering letter...",
-        if cover_letter_title_description.is_none() {
-            events.len()
-        } else {
-            events.len() - 1
-        },
-        if cover_letter_title_description.is_none() && events.len().eq(&1)
-            || cover_letter_title_description.is_some() && events.len().eq(&2)
-        {
-            ""
-        } else {
-            "es"
-        },
-        if cover_letter_title_description.is_none() {
-            "without"
-        } else {
-            "with"
+        for warning in events.iter().filter_map(binary_patch_size_warning) {
+            eprintln!("{warning}");
         }
-    );
 
-    send_events(
-        &client,
-        Some(git_repo_path),
-        events.clone(),
-        user_ref.relays.write(),
-        repo_ref.relays.clone(),
-        !cli_args.disable_cli_spinners,
-        false,
-    )
-    .await?;
+        let posting = format!(
+            "posting {} patch{} {} a covering letter...",
+            if cover_letter_title_description.is_none() {
+                events.len()
+            } else {
+                events.len() - 1
+            },
+            if cover_letter_title_description.is_none() && events.len().eq(&1)
+                || cover_letter_title_description.is_some() && events.len().eq(&2)
+            {
+                ""
+            } else {
+                "es"
+            },
+            if cover_letter_title_description.is_none() {
+                "without"
+            } else {
+                "with"
+            }
+        );
+        print_human(machine_output, &posting);
+
+        let accepted = send_events(
+            &client,
+            Some(git_repo_path),
+            events.clone(),
+            user_ref.relays.write(),
+            repo_relays.clone(),
+            !cli_args.disable_cli_spinners && !machine_output,
+            false,
+        )
+        .await?;
+        if !correct_clock_ahead_after_rejection(&accepted, &events)? {
+            break (events, accepted);
+        }
+    };
+
+    let proposal_root = root_proposal
+        .as_ref()
+        .or(events.first())
+        .context("no events were created")?;
+
+    if args.porcelain {
+        print_porcelain_records(&events, &accepted, proposal_root)?;
+    }
+
+    if args.emit_summary.is_some() || args.summary_stdout {
+        let summary = serde_json::to_string_pretty(&SendSummary::new(
+            &events,
+            &accepted,
+            proposal_root,
+            &repo_coordinates,
+        )?)?;
+        if args.summary_stdout {
+            println!("{summary}");
+        }
+        if let Some(path) = &args.emit_summary {
+            let path = path
+                .clone()
+                .unwrap_or(get_git_dir(git_repo_path).join("nostr/send-summary.json"));
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).context("failed to create send summary directory")?;
+            }
+            std::fs::write(&path, summary).context("failed to write send summary")?;
+            print_human(
+                machine_output,
+                &format!("wrote send summary to {}", path.display()),
+            );
+        }
+    }
 
     if root_proposal_id.is_none() {
         if let Some(event) = events.first() {
-            let event_bech32 = if let Some(relay) = repo_ref.relays.first() {
+            let event_bech32 = if let Some(relay) = repo_relays.first() {
                 Nip19Event::new(event.id, vec![relay.to_string()]).to_bech32()?
             } else {
                 event.id.to_bech32()?
             };
-            println!(
-                "{}",
-                dim.apply_to(format!(
+            print_human(
+                machine_output,
+                &dim(format!(
                     "view in gitworkshop.dev: https://gitworkshop.dev/repo/{}/proposal/{}",
                     repo_ref.coordinate_with_hint().to_bech32()?,
                     &event_bech32,
                 ))
+                .to_string(),
             );
-            println!(
-                "{}",
-                dim.apply_to(format!(
+            print_human(
+                machine_output,
+                &dim(format!(
                     "view in another client:  https://njump.me/{}",
                     &event_bech32,
                 ))
+                .to_string(),
             );
         }
     }
@@ -263,6 +587,406 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     Ok(())
 }
 
+/// commits, newest first, with the same patch id as a commit in
+/// `main_tip` or a patch in one of the user's other open proposals, with a
+/// description of where eg. "upstream as 1a2b3c4"
+async fn find_duplicate_commits(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    main_tip: &Sha1Hash,
+    repo_ref: &RepoRef,
+    root_proposal: Option<&nostr::Event>,
+) -> Result<Vec<(Sha1Hash, String)>> {
+    let mut duplicates = find_commits_already_upstream(git_repo, commits, main_tip)?
+        .into_iter()
+        .map(|(commit, upstream)| {
+            (
+                commit,
+                format!(
+                    "upstream as {}",
+                    upstream.to_string().chars().take(7).collect::<String>()
+                ),
+            )
+        })
+        .collect::<Vec<(Sha1Hash, String)>>();
+
+    let git_repo_path = git_repo.get_path()?;
+    let Ok(Some(me)) = get_likely_logged_in_user(git_repo_path).await else {
+        return Ok(duplicates);
+    };
+    let proposals = get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+        .await?
+        .into_iter()
+        .filter(|e| {
+            e.pubkey.eq(&me)
+                && !event_is_revision_root(e)
+                && !root_proposal.is_some_and(|root| root.id.eq(&e.id))
+        })
+        .collect::<Vec<nostr::Event>>();
+    let statuses = get_events_from_local_cache(git_repo_path, vec![
+        nostr::Filter::default()
+            .kinds(status_kinds())
+            .events(proposals.iter().map(|e| e.id)),
+    ])
+    .await?;
+    for proposal in &proposals {
+        let status = statuses
+            .iter()
+            .filter(|e| e.tags.event_ids().any(|id| id.eq(&proposal.id)))
+            .max_by_key(|e| e.created_at)
+            .map_or(Kind::GitStatusOpen, |e| e.kind);
+        if status.ne(&Kind::GitStatusOpen) && status.ne(&Kind::GitStatusDraft) {
+            continue;
+        }
+        let commit_events =
+            get_all_proposal_patch_events_from_cache(git_repo_path, repo_ref, &proposal.id).await?;
+        let Ok(patches) = get_most_recent_patch_with_ancestors(commit_events) else {
+            continue;
+        };
+        let title = event_to_cover_letter(proposal).map_or(String::new(), |c| c.title);
+        for (commit, _) in find_commits_in_patches(git_repo, commits, &patches)? {
+            if !duplicates.iter().any(|(c, _)| c.eq(&commit)) {
+                duplicates.push((commit, format!("in your open proposal \"{title}\"")));
+            }
+        }
+    }
+    duplicates.sort_by_key(|(commit, _)| commits.iter().position(|c| c.eq(commit)));
+    Ok(duplicates)
+}
+
+/// owners of the paths `commits` change, as listed in the owners file, that
+/// weren't deselected. the sender isn't tagged in their own proposal
+async fn choose_owners_to_notify(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    client: &Client,
+    repo_relays: &[RelayUrl],
+    sender: &PublicKey,
+    machine_output: bool,
+) -> Result<Vec<PublicKey>> {
+    let (rules, warnings) = read_owners_file(git_repo)?;
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    let mut owners = match_owners(&rules, &paths_touched(git_repo, commits)?);
+    owners.remove(sender);
+    if owners.is_empty() {
+        return Ok(vec![]);
+    }
+    let names = get_names_for_display(
+        &owners.keys().copied().collect(),
+        Some(client),
+        Some(git_repo.get_path()?),
+        &repo_relays
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>(),
+    )
+    .await?;
+    let choices = owners
+        .iter()
+        .map(|(public_key, patterns)| {
+            format!(
+                "{} ({})",
+                names
+                    .get(public_key)
+                    .cloned()
+                    .unwrap_or_else(|| short_npub(public_key)),
+                patterns.iter().copied().collect::<Vec<&str>>().join(", ")
+            )
+        })
+        .collect::<Vec<String>>();
+    let selected = Interactor::default().multi_choice(
+        PromptMultiChoiceParms::default()
+            .with_prompt("notify the owners of the paths changed")
+            .with_flag("--yes or --no-notify-owners")
+            .dont_report()
+            .with_defaults(vec![true; choices.len()])
+            .with_choices(choices.clone()),
+    )?;
+    if !selected.is_empty() {
+        print_human(
+            machine_output,
+            &format!(
+                "notifying: {}",
+                selected
+                    .iter()
+                    .map(|i| choices[*i].as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        );
+    }
+    let owners = owners.into_keys().collect::<Vec<PublicKey>>();
+    Ok(selected.iter().map(|i| owners[*i]).collect())
+}
+
+/// how long to wait for each request to a blossom server
+static BLOSSOM_TIMEOUT_SECS: u64 = 60;
+
+pub(crate) fn blossom_http_client(config: &Config) -> Result<reqwest::Client> {
+    let mut http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(BLOSSOM_TIMEOUT_SECS))
+        .no_proxy();
+    if let Some(proxy) = &config.git_proxy.value {
+        http = http.proxy(reqwest::Proxy::all(proxy).context("invalid git server proxy")?);
+    }
+    Ok(http.build()?)
+}
+
+/// upload `path` to the first of `blossoms` that accepts it
+async fn upload_attachment(
+    path: &Path,
+    blossoms: &[String],
+    signer: &Arc<dyn NostrSigner>,
+    config: &Config,
+) -> Result<Attachment> {
+    let bytes =
+        std::fs::read(path).context(format!("failed to read attachment {}", path.display()))?;
+    let sha256 = sha256_hex(&bytes);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let http = blossom_http_client(config)?;
+    let mut errors = vec![];
+    for server in blossoms {
+        match upload_to_blossom(&http, server, &bytes, &sha256, &name, signer).await {
+            Ok(url) => return Ok(Attachment { url, sha256, name }),
+            Err(error) => errors.push(format!("{server} {error:#}")),
+        }
+    }
+    bail!(NgitError::Network(anyhow!(
+        "failed to upload {} to a blossom server: {}",
+        path.display(),
+        errors.join(", ")
+    )))
+}
+
+/// BUD-02 upload. returns the url the blob is served from
+async fn upload_to_blossom(
+    http: &reqwest::Client,
+    server: &str,
+    bytes: &[u8],
+    sha256: &str,
+    name: &str,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<String> {
+    let auth_event = sign_event(blossom_upload_auth(sha256, name), signer).await?;
+    let response = http
+        .put(format!("{}/upload", server.trim_end_matches('/')))
+        .header(
+            "Authorization",
+            format!("Nostr {}", BASE64_STANDARD.encode(auth_event.as_json())),
+        )
+        .header("Content-Type", "application/octet-stream")
+        .body(bytes.to_vec())
+        .send()
+        .await
+        .context("failed to connect")?;
+    if !response.status().is_success() {
+        bail!("upload responded {}", response.status());
+    }
+    let descriptor: BlobDescriptor = serde_json::from_str(&response.text().await?)
+        .context("upload responded with an invalid blob descriptor")?;
+    if descriptor.sha256 != sha256 {
+        bail!("stored a blob with a different hash {}", descriptor.sha256);
+    }
+    Ok(descriptor.url)
+}
+
+/// the --porcelain records. the format is relied upon by other tools so
+/// existing records must not change
+fn print_porcelain_records(
+    events: &[nostr::Event],
+    accepted: &HashMap<String, HashSet<EventId>>,
+    proposal_root: &nostr::Event,
+) -> Result<()> {
+    for event in events {
+        let relays = accepted
+            .values()
+            .filter(|accepted| accepted.contains(&event.id))
+            .count();
+        println!(
+            "event {} kind {} status {} relays {relays}/{}",
+            event.id.to_hex(),
+            event.kind.as_u16(),
+            if relays > 0 { "published" } else { "failed" },
+            accepted.len(),
+        );
+    }
+    println!("proposal-root {}", proposal_root.id.to_hex());
+    println!(
+        "branch {}",
+        event_to_cover_letter(proposal_root)?.get_branch_name_with_pr_prefix_and_shorthand_id()?
+    );
+    Ok(())
+}
+
+/// whether `oldest_commit` builds on a commit in `branch`, preferring the
+/// origin copy of the branch. none when neither is available
+fn builds_on_branch(git_repo: &Repo, oldest_commit: &Sha1Hash, branch: &str) -> Option<bool> {
+    let tip = git_repo
+        .get_tip_of_branch(&format!("origin/{branch}"))
+        .or_else(|_| git_repo.get_tip_of_branch(branch))
+        .ok()?;
+    let base = git_repo.get_commit_parent(oldest_commit).ok()?;
+    Some(base.eq(&tip) || git_repo.ancestor_of(&tip, &base).unwrap_or(false))
+}
+
+/// cover letter title and description written in the user's editor, like
+/// `git commit` does for commit messages. commits are newest first
+fn edit_cover_letter(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    config: &Config,
+    machine_output: bool,
+) -> Result<(String, String)> {
+    let mut template = format!(
+        "{}\n\n\
+        # Please enter the title and description of the cover letter for your\n\
+        # proposal. The first line is the title and the rest is the description.\n\
+        # Lines starting with '#' will be ignored, and an empty message aborts\n\
+        # sending the proposal.\n\
+        #\n\
+        # Commits being sent:\n",
+        match commits.last() {
+            Some(commit) => git_repo.get_commit_message_summary(commit)?,
+            None => String::new(),
+        }
+    );
+    for commit in commits.iter().rev() {
+        template.push_str(&format!(
+            "#   {} {}\n",
+            commit.to_string().chars().take(7).collect::<String>(),
+            git_repo.get_commit_message_summary(commit)?
+        ));
+    }
+    let edited = if std::env::var("NGITTEST").is_ok() {
+        // fake editor so tests can supply the edited message through the pty
+        print_human(machine_output, &template);
+        Interactor::default()
+            .input(
+                PromptInputParms::default()
+                    .with_prompt("edited cover letter (\\n for new lines)")
+                    .optional(),
+            )?
+            .replace("\\n", "\n")
+    } else {
+        let path = git_repo.git_repo.path().join("NGIT_COVER_LETTER_EDITMSG");
+        std::fs::write(&path, &template)
+            .context("failed to write cover letter template for editor")?;
+        launch_editor(&get_editor(config.editor.value.as_ref())?, &path)?;
+        std::fs::read_to_string(&path).context("failed to read cover letter from editor")?
+    };
+    parse_cover_letter(&edited).ok_or_else(|| {
+        NgitError::UserAbort(anyhow!("aborting proposal due to empty cover letter")).into()
+    })
+}
+
+/// resolved in the same order as git: GIT_EDITOR, core.editor, VISUAL, EDITOR.
+/// `configured_editor` is core.editor, or `editor` from the config file
+fn get_editor(configured_editor: Option<&String>) -> Result<String> {
+    resolve_editor(
+        std::env::var("GIT_EDITOR").ok(),
+        configured_editor,
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+        std::env::var("TERM").is_ok_and(|term| term == "dumb"),
+    )
+}
+
+fn resolve_editor(
+    git_editor: Option<String>,
+    configured_editor: Option<&String>,
+    visual: Option<String>,
+    editor: Option<String>,
+    terminal_is_dumb: bool,
+) -> Result<String> {
+    if let Some(editor) = git_editor.or_else(|| configured_editor.cloned()) {
+        return Ok(editor);
+    }
+    if let Some(editor) = visual.filter(|_| !terminal_is_dumb).or(editor) {
+        return Ok(editor);
+    }
+    if terminal_is_dumb {
+        bail!(NgitError::Config(anyhow!(
+            "no editor set. set GIT_EDITOR, core.editor, VISUAL or EDITOR, or use --title and --description"
+        )));
+    }
+    Ok("vi".to_string())
+}
+
+/// run through the shell, as git does, so the editor can include arguments
+fn launch_editor(editor: &str, path: &Path) -> Result<()> {
+    let status = std::process::Command::new("sh")
+        .args(["-c", &format!("{editor} \"$@\""), editor])
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed to launch editor '{editor}'"))?;
+    if !status.success() {
+        bail!("there was a problem with the editor '{editor}'");
+    }
+    Ok(())
+}
+
+/// title and description from an edited cover letter, ignoring comment lines.
+/// None when nothing is left
+fn parse_cover_letter(edited: &str) -> Option<(String, String)> {
+    let lines = edited
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect::<Vec<&str>>();
+    let mut lines = lines.into_iter().skip_while(|line| line.is_empty());
+    let title = lines.next()?.trim().to_string();
+    let description = lines.collect::<Vec<&str>>().join("\n").trim().to_string();
+    Some((title, description))
+}
+
+/// label to stamp as the branch-name tag. a detached HEAD has no branch name
+/// so one is synthesized from the title, or the most recent commit's summary
+fn get_branch_name_label(
+    git_repo: &Repo,
+    branch_name: Option<&String>,
+    title: Option<String>,
+    most_recent_commit: Option<&Sha1Hash>,
+    machine_output: bool,
+) -> Result<Option<String>> {
+    if let Some(branch_name) = branch_name {
+        if !git2::Branch::name_is_valid(&format!("pr/{branch_name}"))? {
+            bail!(NgitError::Config(anyhow!("invalid branch name '{branch_name}'")));
+        }
+    }
+    if !git_repo.git_repo.head_detached()? {
+        return Ok(branch_name.cloned());
+    }
+    let branch_name = if let Some(branch_name) = branch_name {
+        branch_name.clone()
+    } else {
+        let title = if let Some(title) = title {
+            title
+        } else if let Some(commit) = most_recent_commit {
+            git_repo.get_commit_message_summary(commit)?
+        } else {
+            String::new()
+        };
+        let branch_name = branch_name_from_title(&title);
+        if branch_name.is_empty() {
+            "proposal".to_string()
+        } else {
+            branch_name
+        }
+    };
+    print_human(
+        machine_output,
+        &format!(
+            "warning: HEAD is detached so no local branch will track this proposal. it will be listed as 'pr/{branch_name}'"
+        ),
+    );
+    Ok(Some(branch_name))
+}
+
 fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Vec<Sha1Hash>> {
     let mut proposed_commits = if proposed_commits.len().gt(&10) {
         vec![]
@@ -270,7 +994,7 @@ fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Ve
         proposed_commits
     };
 
-    let tip_of_head = git_repo.get_tip_of_branch(&git_repo.get_checked_out_branch_name()?)?;
+    let tip_of_head = git_repo.get_head_commit()?;
     let most_recent_commit = proposed_commits.first().unwrap_or(&tip_of_head);
 
     let mut last_15_commits = vec![*most_recent_commit];
@@ -290,6 +1014,7 @@ fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Ve
         let selected = Interactor::default().multi_choice(
             PromptMultiChoiceParms::default()
                 .with_prompt("select commits for proposal")
+                .with_flag("[SINCE_OR_RANGE] eg. HEAD~2")
                 .dont_report()
                 .with_choices(
                     last_15_commits
@@ -328,9 +1053,96 @@ fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Ve
     Ok(selected_commits)
 }
 
+/// whether to drop commits already upstream or in another proposal, asking
+/// unless a flag decides
+fn drop_duplicates(
+    interactor: &dyn InteractorPrompt,
+    skip_duplicates: bool,
+    keep_duplicates: bool,
+) -> Result<bool> {
+    if skip_duplicates {
+        return Ok(true);
+    }
+    if keep_duplicates {
+        return Ok(false);
+    }
+    match interactor.choice(
+        PromptChoiceParms::default()
+            .with_prompt("drop these commits from the proposal?")
+            .with_flag("--skip-duplicates or --keep-duplicates")
+            .with_default(0)
+            .with_choices(vec![
+                "drop them".to_string(),
+                "keep them".to_string(),
+                "abort".to_string(),
+            ]),
+    )? {
+        0 => Ok(true),
+        1 => Ok(false),
+        _ => bail!(NgitError::UserAbort(anyhow!(
+            "aborting as commits appear to already be upstream or proposed"
+        ))),
+    }
+}
+
+/// whether to include a cover letter, asking unless the flags or the
+/// repository's submission policy decide
+fn include_cover_letter(
+    interactor: &dyn InteractorPrompt,
+    no_cover_letter: bool,
+    title: Option<&String>,
+    cover_letter_required: bool,
+) -> Result<bool> {
+    Ok(!no_cover_letter
+        && (title.is_some()
+            || cover_letter_required
+            || interactor.confirm(
+                PromptConfirmParms::default()
+                    .with_default(false)
+                    .with_prompt("include cover letter?")
+                    .with_flag("--title or --no-cover-letter"),
+            )?))
+}
+
+/// cover letter title and description from the flags, asking for whichever
+/// wasn't given
+fn cover_letter_from_flags(
+    interactor: &dyn InteractorPrompt,
+    title: Option<&String>,
+    description: Option<&String>,
+) -> Result<(String, String)> {
+    Ok((
+        if let Some(title) = title {
+            title.clone()
+        } else {
+            interactor.input(
+                PromptInputParms::default()
+                    .with_prompt("title")
+                    .with_flag("--title"),
+            )?
+        },
+        if let Some(description) = description {
+            description.clone()
+        } else {
+            interactor.input(
+                PromptInputParms::default()
+                    .with_prompt("cover letter description")
+                    .with_flag("--description"),
+            )?
+        },
+    ))
+}
+
+/// `--yes` answers yes without prompting
+fn confirm_unless_yes(yes: bool, params: PromptConfirmParms) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    Interactor::default().confirm(params.with_flag("--yes"))
+}
+
 fn summarise_commit_for_selection(git_repo: &Repo, commit: &Sha1Hash) -> Result<String> {
     let references = git_repo.get_refs(commit)?;
-    let dim = Style::new().color256(247);
     let prefix = format!("({})", git_repo.get_commit_author(commit)?[0],);
     let references_string = if references.is_empty() {
         String::new()
@@ -347,62 +1159,168 @@ fn summarise_commit_for_selection(git_repo: &Repo, commit: &Sha1Hash) -> Result<
 
     Ok(format!(
         "{} {}{} {}",
-        dim.apply_to(prefix),
+        dim(prefix),
         git_repo.get_commit_message_summary(commit)?,
         Style::new().magenta().apply_to(references_string),
-        dim.apply_to(commit.to_string().chars().take(7).collect::<String>(),),
+        dim(commit.to_string().chars().take(7).collect::<String>()),
     ))
 }
 
-async fn get_root_proposal_id_and_mentions_from_in_reply_to(
+async fn get_root_proposal_and_mentions_from_in_reply_to(
     git_repo_path: &Path,
     in_reply_to: &[String],
-) -> Result<(Option<String>, Vec<nostr::Tag>)> {
-    let root_proposal_id = if let Some(first) = in_reply_to.first() {
-        match event_tag_from_nip19_or_hex(first, "in-reply-to", Marker::Root, true, false)?
-            .as_standardized()
-        {
+    client: &Client,
+    repo_relays: &[RelayUrl],
+    yes: bool,
+) -> Result<(Option<nostr::Event>, Vec<nostr::Tag>)> {
+    let mut root_proposal = None;
+    let mut mention_tags: Vec<nostr::Tag> = vec![];
+    let mut authors_to_notify: Vec<PublicKey> = vec![];
+
+    for (i, reply_to) in in_reply_to.iter().enumerate() {
+        let tag = event_tag_from_nip19_or_hex(
+            reply_to,
+            "in-reply-to",
+            if i.eq(&0) {
+                Marker::Root
+            } else {
+                Marker::Mention
+            },
+            true,
+            false,
+        )
+        .context(format!(
+            "{reply_to} in 'in-reply-to' is not a valid nostr reference. use nevent, note, naddr, npub or hex event id"
+        ))?;
+
+        match tag.as_standardized() {
             Some(nostr_sdk::TagStandard::Event {
                 event_id,
-                relay_url: _,
-                marker: _,
-                public_key: _,
-                uppercase: false,
+                relay_url,
+                ..
             }) => {
-                let events = get_events_from_local_cache(git_repo_path, vec![
-                    nostr::Filter::new().id(*event_id),
-                ])
-                .await?;
-
-                if let Some(first) = events.iter().find(|e| e.id.eq(event_id)) {
-                    if event_is_patch_set_root(first) {
-                        Some(event_id.to_string())
-                    } else {
-                        None
+                if let Some(event) =
+                    find_in_reply_to_event(git_repo_path, client, repo_relays, event_id, relay_url)
+                        .await?
+                {
+                    authors_to_notify.push(event.pubkey);
+                    if i.eq(&0) && event_is_patch_set_root(&event) {
+                        root_proposal = Some(event);
+                        continue;
+                    }
+                } else if !confirm_unless_yes(
+                    yes,
+                    PromptConfirmParms::default()
+                        .with_prompt(format!(
+                            "in-reply-to event {reply_to} cannot be found in the local cache or on the repository relays. continue anyway?"
+                        ))
+                        .with_default(false),
+                )? {
+                    bail!("aborting as in-reply-to event {reply_to} cannot be found");
+                }
+            }
+            Some(nostr_sdk::TagStandard::Coordinate { coordinate, .. }) => {
+                if i.eq(&0) {
+                    if let Some(proposal) =
+                        find_proposal_for_checked_out_branch(git_repo_path, coordinate).await?
+                    {
+                        authors_to_notify.push(proposal.pubkey);
+                        root_proposal = Some(proposal);
+                        continue;
                     }
-                } else {
-                    None
+                    eprintln!(
+                        "{reply_to} doesn't identify a proposal for the checked out branch so it will be tagged as a mention"
+                    );
                 }
+                authors_to_notify.push(coordinate.public_key);
             }
-            _ => None,
+            _ => {}
         }
-    } else {
-        return Ok((None, vec![]));
-    };
+        if i.eq(&0) {
+            // only the first reference can be the root so the others are mentions
+            mention_tags.push(event_tag_from_nip19_or_hex(
+                reply_to,
+                "in-reply-to",
+                Marker::Mention,
+                true,
+                false,
+            )?);
+        } else {
+            mention_tags.push(tag);
+        }
+    }
 
-    let mut mention_tags = vec![];
-    for (i, reply_to) in in_reply_to.iter().enumerate() {
-        if i.ne(&0) || root_proposal_id.is_none() {
-            mention_tags.push(
-                event_tag_from_nip19_or_hex(reply_to, "in-reply-to", Marker::Mention, true, false)
-                    .context(format!(
-                        "{reply_to} in 'in-reply-to' not a valid nostr reference"
-                    ))?,
-            );
+    // notify the authors of referenced events
+    for public_key in authors_to_notify {
+        let tag = nostr::Tag::public_key(public_key);
+        if !mention_tags.contains(&tag) {
+            mention_tags.push(tag);
         }
     }
 
-    Ok((root_proposal_id, mention_tags))
+    Ok((root_proposal, mention_tags))
+}
+
+/// proposals aren't addressable so an naddr of a repository is resolved to
+/// the cached proposal of that repository the checked out branch revises
+async fn find_proposal_for_checked_out_branch(
+    git_repo_path: &Path,
+    coordinate: &Coordinate,
+) -> Result<Option<nostr::Event>> {
+    if !coordinate.kind.eq(&Kind::GitRepoAnnouncement) {
+        return Ok(None);
+    }
+    let Ok(branch_name) =
+        Repo::from_path(&git_repo_path.to_path_buf())?.get_checked_out_branch_name()
+    else {
+        return Ok(None);
+    };
+    let logged_in_user = get_likely_logged_in_user(git_repo_path)
+        .await
+        .ok()
+        .flatten();
+    Ok(
+        get_proposals_and_revisions_from_cache(git_repo_path, HashSet::from([coordinate.clone()]))
+            .await?
+            .into_iter()
+            .find(|e| {
+                is_event_proposal_root_for_branch(e, &branch_name, logged_in_user.as_ref())
+                    .unwrap_or(false)
+            }),
+    )
+}
+
+/// look for event in local cache, then on repo relays and relay hint
+async fn find_in_reply_to_event(
+    git_repo_path: &Path,
+    client: &Client,
+    repo_relays: &[RelayUrl],
+    event_id: &nostr::EventId,
+    relay_hint: &Option<RelayUrl>,
+) -> Result<Option<nostr::Event>> {
+    let filter = nostr::Filter::new().id(*event_id);
+    if let Some(event) = get_events_from_local_cache(git_repo_path, vec![filter.clone()])
+        .await?
+        .into_iter()
+        .find(|e| e.id.eq(event_id))
+    {
+        return Ok(Some(event));
+    }
+    let mut relays: Vec<String> = repo_relays.iter().map(ToString::to_string).collect();
+    if let Some(relay_hint) = relay_hint {
+        if !relays.contains(&relay_hint.to_string()) {
+            relays.push(relay_hint.to_string());
+        }
+    }
+    if relays.is_empty() {
+        return Ok(None);
+    }
+    Ok(client
+        .get_events(relays, vec![filter])
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|e| e.id.eq(event_id)))
 }
 
 // TODO
@@ -410,3 +1328,152 @@ async fn get_root_proposal_id_and_mentions_from_in_reply_to(
 // - file relays
 // - find repo events
 // -
+
+#[cfg(test)]
+mod tests {
+    use ngit::cli_interactor::{ScriptedAnswer, ScriptedInteractor};
+
+    use super::*;
+
+    mod resolve_editor {
+        use super::*;
+
+        fn vim() -> Option<String> {
+            Some("vim".to_string())
+        }
+
+        #[test]
+        fn configured_editor_used_before_visual_and_editor() -> Result<()> {
+            assert_eq!(
+                resolve_editor(
+                    None,
+                    vim().as_ref(),
+                    Some("code".into()),
+                    Some("nano".into()),
+                    false
+                )?,
+                "vim"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn git_editor_overrides_configured_editor() -> Result<()> {
+            assert_eq!(
+                resolve_editor(Some("emacs".into()), vim().as_ref(), None, None, false)?,
+                "emacs"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn configured_editor_used_on_dumb_terminal() -> Result<()> {
+            assert_eq!(
+                resolve_editor(None, vim().as_ref(), None, None, true)?,
+                "vim"
+            );
+            assert!(resolve_editor(None, None, Some("code".into()), None, true).is_err());
+            Ok(())
+        }
+    }
+
+    mod drop_duplicates {
+        use super::*;
+
+        #[test]
+        fn flags_answer_without_prompting() -> Result<()> {
+            let interactor = ScriptedInteractor::default();
+            assert!(drop_duplicates(&interactor, true, false)?);
+            assert!(!drop_duplicates(&interactor, false, true)?);
+            assert!(interactor.asked().is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn prompts_when_no_flag() -> Result<()> {
+            let interactor =
+                ScriptedInteractor::new(vec![ScriptedAnswer::Choice(0), ScriptedAnswer::Choice(1)]);
+            assert!(drop_duplicates(&interactor, false, false)?);
+            assert!(!drop_duplicates(&interactor, false, false)?);
+            assert_eq!(
+                interactor.asked()[0].prompt,
+                "drop these commits from the proposal?"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn abort_choice_errors() {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(2)]);
+            assert!(drop_duplicates(&interactor, false, false).is_err());
+        }
+    }
+
+    mod include_cover_letter {
+        use super::*;
+
+        #[test]
+        fn no_cover_letter_flag_wins() -> Result<()> {
+            let interactor = ScriptedInteractor::default();
+            assert!(!include_cover_letter(
+                &interactor,
+                true,
+                Some(&"title".to_string()),
+                true
+            )?);
+            assert!(interactor.asked().is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn title_or_policy_includes_without_prompting() -> Result<()> {
+            let interactor = ScriptedInteractor::default();
+            assert!(include_cover_letter(
+                &interactor,
+                false,
+                Some(&"title".to_string()),
+                false
+            )?);
+            assert!(include_cover_letter(&interactor, false, None, true)?);
+            assert!(interactor.asked().is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn otherwise_prompts() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Confirm(true)]);
+            assert!(include_cover_letter(&interactor, false, None, false)?);
+            assert_eq!(interactor.asked()[0].prompt, "include cover letter?");
+            Ok(())
+        }
+    }
+
+    mod cover_letter_from_flags {
+        use super::*;
+
+        #[test]
+        fn prompts_for_missing_description() -> Result<()> {
+            let interactor =
+                ScriptedInteractor::new(vec![ScriptedAnswer::Input("description".to_string())]);
+            assert_eq!(
+                cover_letter_from_flags(&interactor, Some(&"title".to_string()), None)?,
+                ("title".to_string(), "description".to_string())
+            );
+            assert_eq!(interactor.asked().len(), 1);
+            assert_eq!(interactor.asked()[0].prompt, "cover letter description");
+            Ok(())
+        }
+
+        #[test]
+        fn prompts_for_missing_title() -> Result<()> {
+            let interactor =
+                ScriptedInteractor::new(vec![ScriptedAnswer::Input("title".to_string())]);
+            assert_eq!(
+                cover_letter_from_flags(&interactor, None, Some(&"description".to_string()))?,
+                ("title".to_string(), "description".to_string())
+            );
+            assert_eq!(interactor.remaining(), 0);
+            Ok(())
+        }
+    }
+}
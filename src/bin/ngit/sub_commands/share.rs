@@ -0,0 +1,161 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::get_proposals_and_revisions_from_cache,
+    git_events::{event_tag_from_nip19_or_hex, is_event_proposal_root_for_branch},
+    login::get_likely_logged_in_user,
+    relay_hints::{get_relays_that_returned, relay_hints_key_for_coordinate, select_relay_hints},
+    repo_ref::RepoRef,
+    web_viewer::proposal_web_url,
+};
+use nostr::{
+    Event, ToBech32,
+    nips::{nip01::Coordinate, nip10::Marker, nip19::Nip19Event},
+};
+use nostr_sdk::TagStandard;
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one.
+    /// defaults to the proposal of the checked out branch
+    pub(crate) id: Option<String>,
+}
+
+pub(crate) async fn fetch_repo_ref(git_repo: &Repo, config: &Config) -> Result<RepoRef> {
+    let git_repo_path = git_repo.get_path()?;
+    let client = Client::new(Params::with_config(config));
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(git_repo, None, &client).await?;
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+    get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report).await
+}
+
+/// the repository's naddr with relay hints, preferring announced relays that
+/// returned the announcement in the last fetch
+pub(crate) fn repo_naddr(git_repo: &Repo, repo_ref: &RepoRef) -> Result<String> {
+    let coordinate = repo_ref.coordinate_with_hint();
+    let returned = get_relays_that_returned(
+        git_repo.get_path()?,
+        &relay_hints_key_for_coordinate(&coordinate),
+    );
+    Ok(Coordinate {
+        relays: select_relay_hints(&repo_ref.relays, &returned),
+        ..coordinate
+    }
+    .to_bech32()?)
+}
+
+pub async fn launch_repo(config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let repo_ref = fetch_repo_ref(&git_repo, config).await?;
+    let naddr = repo_naddr(&git_repo, &repo_ref)?;
+    println!("{naddr}");
+    println!("git clone nostr://{naddr}");
+    println!("https://gitworkshop.dev/repo/{naddr}");
+    Ok(())
+}
+
+/// the proposal with root event `id_or_branch`, or the start of its hex id,
+/// or of the local branch `id_or_branch`, or of the checked out branch
+pub(crate) async fn find_proposal(
+    id_or_branch: Option<&str>,
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+) -> Result<Event> {
+    let git_repo_path = git_repo.get_path()?;
+    let proposals =
+        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates()).await?;
+
+    // a local branch is found like the checked out branch
+    let id = id_or_branch.filter(|id| {
+        !git_repo
+            .get_local_branch_names()
+            .is_ok_and(|names| names.iter().any(|name| name == id))
+    });
+    let Some(id) = id else {
+        let branch_name = match id_or_branch {
+            Some(branch_name) => branch_name.to_string(),
+            None => git_repo.get_checked_out_branch_name()?,
+        };
+        let logged_in_user = get_likely_logged_in_user(git_repo_path).await?;
+        return proposals
+            .into_iter()
+            .find(|e| {
+                is_event_proposal_root_for_branch(e, &branch_name, logged_in_user.as_ref())
+                    .unwrap_or(false)
+            })
+            .context(if id_or_branch.is_some() {
+                format!("branch '{branch_name}' isn't a proposal")
+            } else {
+                format!(
+                    "checked out branch '{branch_name}' isn't a proposal. specify one by its event id"
+                )
+            });
+    };
+    if !id.is_empty() && id.len() < 64 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        let prefix = id.to_lowercase();
+        let mut matching = proposals
+            .into_iter()
+            .filter(|e| e.id.to_hex().starts_with(&prefix));
+        let proposal = matching
+            .next()
+            .context(format!("failed to find proposal {id}"))?;
+        if matching.next().is_some() {
+            bail!("{id} matches more than one proposal. use more of its event id");
+        }
+        return Ok(proposal);
+    }
+    let invalid_reference =
+        format!("{id} is not a valid proposal reference. use nevent, note or hex event id");
+    let tag = event_tag_from_nip19_or_hex(id, "proposal", Marker::Root, false, false)
+        .context(invalid_reference.clone())?;
+    let Some(TagStandard::Event { event_id, .. }) = tag.as_standardized() else {
+        bail!(invalid_reference);
+    };
+    proposals
+        .into_iter()
+        .find(|e| e.id.eq(event_id))
+        .context(format!("failed to find proposal {id}"))
+}
+
+/// the proposal's nevent with relay hints, preferring repository relays that
+/// returned it in the last fetch
+pub(crate) fn proposal_nevent(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposal: &Event,
+) -> Result<String> {
+    Ok(Nip19Event::new(
+        proposal.id,
+        select_relay_hints(
+            &repo_ref.relays,
+            &get_relays_that_returned(git_repo.get_path()?, &proposal.id.to_hex()),
+        )
+        .iter()
+        .map(|r| r.as_str_without_trailing_slash().to_string()),
+    )
+    .to_bech32()?)
+}
+
+pub async fn launch_proposal(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let repo_ref = fetch_repo_ref(&git_repo, config).await?;
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+    let nevent = proposal_nevent(&git_repo, &repo_ref, &proposal)?;
+    println!("{nevent}");
+    println!(
+        "{}",
+        proposal_web_url(
+            &config.web_viewer_url.value,
+            &repo_naddr(&git_repo, &repo_ref)?,
+            &nevent
+        )
+    );
+    Ok(())
+}
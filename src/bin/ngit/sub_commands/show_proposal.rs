@@ -0,0 +1,160 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{Context, Result};
+use ngit::{
+    ci::{CiStatus, get_ci_bots, proposal_ci_status},
+    client::{
+        get_all_proposal_patch_events_from_cache, get_event_from_global_cache,
+        get_events_from_local_cache, get_filter_contributor_profiles,
+        get_proposals_and_revisions_from_cache,
+    },
+    git_events::{PROPOSAL_EDIT_KIND, comment_kinds, event_is_revision_root, status_kinds},
+    proposal_view::{ProposalViewBuilder, render_proposal_view},
+};
+use nostr_sdk::{Kind, PublicKey, Timestamp};
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    output,
+    repo_ref::{RepoRef, get_repo_coordinates_when_remote_unknown},
+    sub_commands::share::find_proposal,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal root event as nevent, note, hex event id or the start of one,
+    /// or a proposal branch. defaults to the proposal of the checked out
+    /// branch
+    pub(crate) id: Option<String>,
+    /// print everything as json
+    #[arg(long, action)]
+    pub(crate) json: bool,
+    /// append the full diff, or include each patch's diff in the json
+    #[arg(long, action)]
+    pub(crate) patch: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, args.json).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+    client.disconnect().await?;
+
+    let proposal = find_proposal(args.id.as_deref(), &git_repo, &repo_ref).await?;
+
+    let mut events = [
+        get_all_proposal_patch_events_from_cache(git_repo_path, &repo_ref, &proposal.id).await?,
+        // includes revisions that start with a cover letter
+        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
+            .await?
+            .into_iter()
+            .filter(event_is_revision_root)
+            .collect(),
+        get_events_from_local_cache(
+            git_repo_path,
+            vec![
+                nostr::Filter::default()
+                    .kinds(
+                        [
+                            status_kinds(),
+                            comment_kinds(),
+                            vec![PROPOSAL_EDIT_KIND, Kind::Label],
+                        ]
+                        .concat(),
+                    )
+                    .event(proposal.id),
+            ],
+        )
+        .await?,
+    ]
+    .concat();
+    // profiles are cached by every repository
+    let authors = std::iter::once(&proposal)
+        .chain(events.iter())
+        .map(|e| e.pubkey)
+        .collect::<HashSet<PublicKey>>();
+    let profiles = get_event_from_global_cache(Some(git_repo_path), vec![
+        get_filter_contributor_profiles(authors),
+    ])
+    .await?;
+    events.extend(profiles);
+
+    let view = ProposalViewBuilder::new(&proposal, &events)
+        .with_maintainers(&repo_ref.maintainers)
+        .with_labels(
+            &config.label_namespace.value,
+            if config.labels_allow_anyone.value {
+                None
+            } else {
+                Some(repo_ref.maintainers.as_slice())
+            },
+        )
+        .with_ci_bots(&get_ci_bots(&config.ci_bots.value, &repo_ref))
+        .with_target_branch(
+            repo_ref
+                .submission_policy
+                .target_branch
+                .clone()
+                .or_else(|| {
+                    git_repo
+                        .get_main_or_master_branch()
+                        .ok()
+                        .map(|(name, _)| name.to_string())
+                }),
+        )
+        .with_diffs(args.patch)
+        .build(Timestamp::now());
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&view)?);
+        return Ok(());
+    }
+    println!("{}", render_proposal_view(&view, Timestamp::now()));
+    if args.patch {
+        for diff in view
+            .patches
+            .iter()
+            .filter_map(|patch| patch.diff.as_deref())
+            .filter(|diff| !diff.is_empty())
+        {
+            println!("\n{}", output::diff(diff));
+        }
+    }
+    Ok(())
+}
+
+/// the latest result from a ci bot on `proposal` in the cache
+pub(crate) async fn get_cached_ci_status(
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    proposal: &nostr::Event,
+    config: &Config,
+) -> Result<Option<CiStatus>> {
+    let ci_bots = get_ci_bots(&config.ci_bots.value, repo_ref);
+    if ci_bots.is_empty() {
+        return Ok(None);
+    }
+    let comments = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            nostr::Filter::default()
+                .kinds(comment_kinds())
+                .authors(ci_bots.clone())
+                .event(proposal.id),
+        ],
+    )
+    .await?;
+    Ok(proposal_ci_status(proposal, &comments, &ci_bots))
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use ngit::{
+    ci::format_ci_status, client::get_state_from_cache,
+    repo_ref::get_repo_coordinates_from_nostr_remotes, repo_state::find_rewound_branches,
+};
+
+use crate::{
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+    sub_commands::{share::find_proposal, show_proposal::get_cached_ci_status},
+};
+
+pub async fn launch(config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+    client.disconnect().await?;
+
+    // on a proposal branch
+    if let Ok(proposal) = find_proposal(None, &git_repo, &repo_ref).await {
+        if let Some(ci) = get_cached_ci_status(git_repo_path, &repo_ref, &proposal, config).await? {
+            for line in format_ci_status(&ci) {
+                println!("{line}");
+            }
+        }
+    }
+
+    let nostr_state = get_state_from_cache(Some(git_repo_path), &repo_ref)
+        .await
+        .context("cannot find a nostr state event for this repository")?;
+
+    let mut remotes = get_repo_coordinates_from_nostr_remotes(&git_repo)
+        .await?
+        .into_iter()
+        .filter(|(_, coordinate)| coordinate.identifier == repo_ref.identifier)
+        .map(|(remote, _)| remote)
+        .collect::<Vec<String>>();
+    remotes.sort();
+
+    let mut in_sync = true;
+    for remote in &remotes {
+        for rewound in find_rewound_branches(&git_repo, remote, &nostr_state.state) {
+            in_sync = false;
+            for line in rewound.guidance() {
+                println!("{line}");
+            }
+        }
+        // ancestry of tips that haven't been fetched is unknown
+        let mut unfetched = nostr_state
+            .state
+            .iter()
+            .filter_map(|(name, value)| {
+                let branch = name.strip_prefix("refs/heads/")?;
+                (!branch.starts_with("pr/")
+                    && git_repo
+                        .get_commit_or_tip_of_reference(&format!("refs/remotes/{remote}/{branch}"))
+                        .is_ok()
+                    && !git_repo.does_commit_exist(value).is_ok_and(|exists| exists))
+                .then_some(branch)
+            })
+            .collect::<Vec<&str>>();
+        unfetched.sort_unstable();
+        for branch in unfetched {
+            in_sync = false;
+            println!("{remote}/{branch} has updates on nostr. run `git fetch {remote}`");
+        }
+    }
+    if in_sync {
+        println!("branches are up to date with nostr");
+    }
+    Ok(())
+}
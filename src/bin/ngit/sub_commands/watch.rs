@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use ngit::{
+    client::{send_events, sign_event},
+    lists::{fetch_git_repositories_list, update_git_repositories_list},
+};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, Params, fetching_with_report, get_repo_ref_from_cache_after_fetch},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+/// add (`watch == true`) or remove this repository from the user's git
+/// repositories list
+pub async fn launch(cli_args: &Cli, config: &Config, watch: bool) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let repo_coordinates =
+        get_repo_coordinates_when_remote_unknown(&git_repo, None, &client).await?;
+
+    let report = fetching_with_report(git_repo_path, &client, &repo_coordinates, false).await?;
+
+    let repo_ref =
+        get_repo_ref_from_cache_after_fetch(Some(git_repo_path), &repo_coordinates, &report)
+            .await?;
+    let repo_coordinate = repo_ref.coordinate_with_hint();
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    client.set_signer(signer.clone()).await;
+
+    let list = fetch_git_repositories_list(
+        &client,
+        Some(git_repo_path),
+        &user_ref.public_key,
+        [
+            user_ref.relays.write(),
+            client.get_fallback_relays().clone(),
+        ]
+        .concat(),
+    )
+    .await?;
+
+    let Some(event_builder) = update_git_repositories_list(list.as_ref(), &repo_coordinate, watch)
+    else {
+        if watch {
+            println!("already watching {}", repo_ref.name);
+        } else {
+            println!("not watching {}", repo_ref.name);
+        }
+        return Ok(());
+    };
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![sign_event(event_builder, &signer).await?],
+        user_ref.relays.write(),
+        vec![],
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+    client.disconnect().await?;
+
+    if watch {
+        println!("watching {}", repo_ref.name);
+    } else {
+        println!("stopped watching {}", repo_ref.name);
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use ngit::lists::{
+    fetch_git_repositories_list, get_filters_watched_repo_activity, get_watched_repo_coordinates,
+    summarise_watched_repos,
+};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, Params},
+    config::Config,
+    git::{Repo, RepoActions},
+    login,
+};
+
+pub async fn launch(cli_args: &Cli, config: &Config) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    let git_repo_path = git_repo.as_ref().map(|r| r.get_path()).transpose()?;
+
+    let client = Client::new(Params::with_config(config));
+
+    let (_, user_ref, _) = login::login_or_signup(
+        &git_repo.as_ref(),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        false,
+    )
+    .await?;
+
+    let relays = [
+        user_ref.relays.write(),
+        client.get_fallback_relays().clone(),
+    ]
+    .concat()
+    .into_iter()
+    .collect::<HashSet<String>>()
+    .into_iter()
+    .collect::<Vec<String>>();
+
+    let coordinates = if let Some(list) =
+        fetch_git_repositories_list(&client, git_repo_path, &user_ref.public_key, relays.clone())
+            .await?
+    {
+        get_watched_repo_coordinates(&list)
+    } else {
+        vec![]
+    };
+
+    if coordinates.is_empty() {
+        client.disconnect().await?;
+        println!("not watching any repositories. run `ngit watch` inside a repository to watch it");
+        return Ok(());
+    }
+
+    let events = client
+        .get_events(relays, get_filters_watched_repo_activity(&coordinates))
+        .await
+        .context("failed to fetch activity for watched repositories")?;
+    client.disconnect().await?;
+
+    for summary in summarise_watched_repos(&coordinates, &events) {
+        println!(
+            "{} by {} (last activity {})",
+            summary.name,
+            summary.maintainer,
+            summary
+                .last_activity
+                .map_or("unknown".to_string(), |t| t.to_human_datetime()),
+        );
+    }
+    Ok(())
+}
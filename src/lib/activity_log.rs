@@ -0,0 +1,520 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use anyhow::{Context, Result, bail};
+use nostr::{Event, EventId, PublicKey, Timestamp, nips::nip01::Coordinate};
+use nostr_sdk::Kind;
+
+use crate::{
+    client::{STATE_KIND, get_filter_repo_events, get_filter_state_events},
+    git_events::{
+        comment_kinds, commit_msg_from_patch_oneliner, event_is_patch_set_root,
+        event_is_revision_root, get_event_root, status_kinds, tag_value,
+    },
+    repo_state::RepoState,
+};
+
+/// activity `ngit log --kind` can select
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ActivityKind {
+    /// repository announcements
+    Announcement,
+    /// state events recording where branches and tags point
+    State,
+    /// proposals and their revisions
+    Proposal,
+    /// proposals opened, applied, closed or marked as draft
+    Status,
+    Comment,
+    Issue,
+}
+
+impl ActivityKind {
+    /// None for patches after the first in a series, which are part of their
+    /// proposal's entry
+    fn of(event: &Event) -> Option<Self> {
+        if event.kind == Kind::GitRepoAnnouncement {
+            Some(Self::Announcement)
+        } else if event.kind == STATE_KIND {
+            Some(Self::State)
+        } else if event.kind == Kind::GitPatch {
+            event_is_patch_set_root(event).then_some(Self::Proposal)
+        } else if status_kinds().contains(&event.kind) {
+            Some(Self::Status)
+        } else if comment_kinds().contains(&event.kind) {
+            Some(Self::Comment)
+        } else if event.kind == Kind::GitIssue {
+            Some(Self::Issue)
+        } else {
+            None
+        }
+    }
+}
+
+/// how a ref differs from the previous state event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefChange {
+    Added {
+        name: String,
+        value: String,
+    },
+    Moved {
+        name: String,
+        from: String,
+        to: String,
+    },
+    Removed {
+        name: String,
+    },
+}
+
+/// shorten commit ids, leaving symbolic refs such as `ref: refs/heads/main`
+fn short_ref_value(value: &str) -> &str {
+    if value.starts_with("ref: ") {
+        value
+    } else {
+        &value[..value.len().min(7)]
+    }
+}
+
+impl fmt::Display for RefChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { name, value } => write!(f, "{name} new {}", short_ref_value(value)),
+            Self::Moved { name, from, to } => write!(
+                f,
+                "{name} {}..{}",
+                short_ref_value(from),
+                short_ref_value(to)
+            ),
+            Self::Removed { name } => write!(f, "{name} deleted"),
+        }
+    }
+}
+
+fn ref_changes(
+    previous: Option<&HashMap<String, String>>,
+    state: &HashMap<String, String>,
+) -> Vec<RefChange> {
+    let empty = HashMap::new();
+    let previous = previous.unwrap_or(&empty);
+    let mut changes = vec![];
+    for (name, value) in state {
+        match previous.get(name) {
+            None => changes.push(RefChange::Added {
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            Some(from) if from != value => changes.push(RefChange::Moved {
+                name: name.clone(),
+                from: from.clone(),
+                to: value.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for name in previous.keys() {
+        if !state.contains_key(name) {
+            changes.push(RefChange::Removed { name: name.clone() });
+        }
+    }
+    changes.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    changes
+}
+
+pub struct ActivityEntry {
+    pub event: Event,
+    pub kind: ActivityKind,
+    /// eg. "proposal", "revision" or "closed"
+    pub label: &'static str,
+    pub summary: String,
+    /// refs changed since the previous state event. only set for state events
+    pub ref_changes: Vec<RefChange>,
+}
+
+#[derive(Default)]
+pub struct ActivityFilter {
+    pub since: Option<Timestamp>,
+    /// None for any author
+    pub authors: Option<HashSet<PublicKey>>,
+    /// empty for every kind
+    pub kinds: Vec<ActivityKind>,
+}
+
+impl ActivityFilter {
+    fn matches(&self, entry: &ActivityEntry) -> bool {
+        self.since
+            .is_none_or(|since| entry.event.created_at >= since)
+            && self
+                .authors
+                .as_ref()
+                .is_none_or(|authors| authors.contains(&entry.event.pubkey))
+            && (self.kinds.is_empty() || self.kinds.contains(&entry.kind))
+    }
+}
+
+/// announcements, state events, proposals and issues of the repository
+pub fn get_filters_repo_activity(repo_coordinates: &HashSet<Coordinate>) -> Vec<nostr::Filter> {
+    vec![
+        get_filter_repo_events(repo_coordinates),
+        get_filter_state_events(repo_coordinates),
+        nostr::Filter::default()
+            .kinds(vec![Kind::GitPatch, Kind::GitIssue])
+            .custom_tag(
+                nostr::SingleLetterTag::lowercase(nostr::Alphabet::A),
+                repo_coordinates
+                    .iter()
+                    .map(std::string::ToString::to_string)
+                    .collect::<Vec<String>>(),
+            ),
+    ]
+}
+
+/// statuses and comments on `events`, eg. proposals and issues
+pub fn get_filter_activity_replies(events: &[Event]) -> nostr::Filter {
+    nostr::Filter::default()
+        .kinds([status_kinds(), comment_kinds()].concat())
+        .events(events.iter().map(|e| e.id))
+}
+
+/// the proposal or issue a status or comment is about. NIP-22 comments tag
+/// it with an uppercase E
+fn replied_to(event: &Event) -> Option<EventId> {
+    get_event_root(event).ok().or_else(|| {
+        event
+            .tags
+            .iter()
+            .find(|t| t.as_slice().first().is_some_and(|n| n == "E"))
+            .and_then(|t| EventId::parse(t.as_slice().get(1)?).ok())
+    })
+}
+
+fn first_line(text: &str) -> String {
+    text.lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+pub(crate) fn event_title(event: &Event) -> String {
+    if event.kind == Kind::GitPatch {
+        commit_msg_from_patch_oneliner(event).unwrap_or_default()
+    } else if event.kind == Kind::GitIssue {
+        tag_value(event, "subject").unwrap_or_else(|_| first_line(&event.content))
+    } else {
+        first_line(&event.content)
+    }
+}
+
+fn status_label(kind: Kind) -> &'static str {
+    match kind {
+        Kind::GitStatusOpen => "opened",
+        Kind::GitStatusApplied => "applied",
+        Kind::GitStatusClosed => "closed",
+        _ => "draft",
+    }
+}
+
+/// the repository's activity from `events`, newest first. `events` can
+/// overlap as they are usually read from more than one cache
+pub fn build_activity_log(events: &[Event], filter: &ActivityFilter) -> Vec<ActivityEntry> {
+    let mut events = events
+        .iter()
+        .map(|e| (e.id, e))
+        .collect::<HashMap<EventId, &Event>>()
+        .into_values()
+        .collect::<Vec<&Event>>();
+    events.sort_by_key(|e| (e.created_at, e.id));
+
+    let titles = events
+        .iter()
+        .filter(|e| [Kind::GitPatch, Kind::GitIssue].contains(&e.kind))
+        .map(|e| (e.id, event_title(e)))
+        .collect::<HashMap<EventId, String>>();
+    let about = |event: &Event| {
+        replied_to(event).map_or("unknown".to_string(), |id| {
+            titles
+                .get(&id)
+                .cloned()
+                .unwrap_or(id.to_hex()[..8].to_string())
+        })
+    };
+
+    let mut previous_state: Option<HashMap<String, String>> = None;
+    let mut entries = vec![];
+    for event in events {
+        let Some(kind) = ActivityKind::of(event) else {
+            continue;
+        };
+        let mut ref_changes_since_previous = vec![];
+        let (label, summary) = match kind {
+            ActivityKind::Announcement => (
+                "announcement",
+                tag_value(event, "name")
+                    .unwrap_or(event.tags.identifier().unwrap_or_default().to_string()),
+            ),
+            ActivityKind::State => {
+                let Ok(state) = RepoState::try_from(vec![event.clone()]) else {
+                    continue;
+                };
+                ref_changes_since_previous = ref_changes(previous_state.as_ref(), &state.state);
+                previous_state = Some(state.state);
+                (
+                    "state",
+                    format!(
+                        "{} ref{} changed",
+                        ref_changes_since_previous.len(),
+                        if ref_changes_since_previous.len() == 1 {
+                            ""
+                        } else {
+                            "s"
+                        }
+                    ),
+                )
+            }
+            ActivityKind::Proposal => (
+                if event_is_revision_root(event) {
+                    "revision"
+                } else {
+                    "proposal"
+                },
+                event_title(event),
+            ),
+            ActivityKind::Status => (status_label(event.kind), about(event)),
+            ActivityKind::Comment => (
+                "comment",
+                format!("on {}: {}", about(event), first_line(&event.content)),
+            ),
+            ActivityKind::Issue => ("issue", event_title(event)),
+        };
+        let entry = ActivityEntry {
+            event: event.clone(),
+            kind,
+            label,
+            summary,
+            ref_changes: ref_changes_since_previous,
+        };
+        if filter.matches(&entry) {
+            entries.push(entry);
+        }
+    }
+    entries.reverse();
+    entries
+}
+
+/// `--since` as a unix timestamp or an age such as 30m, 12h, 7d or 2w
+pub fn parse_since(value: &str, now: Timestamp) -> Result<Timestamp> {
+    if let Ok(timestamp) = value.parse::<u64>() {
+        return Ok(Timestamp::from(timestamp));
+    }
+    let unit_secs = match value.chars().last() {
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => {
+            bail!("invalid --since '{value}'. use a unix timestamp or an age such as 12h, 7d or 2w")
+        }
+    };
+    let count = value[..value.len() - 1].parse::<u64>().context(format!(
+        "invalid --since '{value}'. use a unix timestamp or an age such as 12h, 7d or 2w"
+    ))?;
+    Ok(Timestamp::from(
+        now.as_u64().saturating_sub(count * unit_secs),
+    ))
+}
+
+/// eg. "3 hours ago"
+pub fn format_age(created_at: Timestamp, now: Timestamp) -> String {
+    let secs = now.as_u64().saturating_sub(created_at.as_u64());
+    let (count, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..1_209_600 => (secs / 86_400, "day"),
+        1_209_600..5_184_000 => (secs / 604_800, "week"),
+        5_184_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag, TagKind, TagStandard, nips::nip10::Marker};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn event_at(keys: &Keys, kind: Kind, created_at: u64, tags: Vec<Tag>) -> Event {
+        EventBuilder::new(kind, "")
+            .tags(tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn state_event(created_at: u64, main: &str) -> Event {
+        event_at(
+            &TEST_KEY_1_KEYS,
+            STATE_KIND,
+            created_at,
+            vec![
+                Tag::identifier("example"),
+                Tag::custom(TagKind::Custom("refs/heads/main".into()), vec![main]),
+            ],
+        )
+    }
+
+    fn mixed_events() -> Vec<Event> {
+        let issue = event_at(
+            &TEST_KEY_2_KEYS,
+            Kind::GitIssue,
+            300,
+            vec![Tag::custom(
+                TagKind::Custom("subject".into()),
+                vec!["it broke"],
+            )],
+        );
+        vec![
+            event_at(
+                &TEST_KEY_1_KEYS,
+                Kind::GitRepoAnnouncement,
+                100,
+                vec![
+                    Tag::identifier("example"),
+                    Tag::custom(TagKind::Custom("name".into()), vec!["example name"]),
+                ],
+            ),
+            state_event(200, "1111111111111111111111111111111111111111"),
+            issue.clone(),
+            state_event(400, "2222222222222222222222222222222222222222"),
+            event_at(
+                &TEST_KEY_1_KEYS,
+                Kind::GitStatusClosed,
+                500,
+                vec![Tag::from_standardized(TagStandard::Event {
+                    event_id: issue.id,
+                    relay_url: None,
+                    marker: Some(Marker::Root),
+                    public_key: None,
+                    uppercase: false,
+                })],
+            ),
+        ]
+    }
+
+    mod build_activity_log {
+        use super::*;
+
+        #[test]
+        fn newest_first() {
+            let log = build_activity_log(&mixed_events(), &ActivityFilter::default());
+            assert_eq!(
+                log.iter()
+                    .map(|e| format!("{} {}", e.label, e.summary))
+                    .collect::<Vec<String>>(),
+                vec![
+                    "closed it broke",
+                    "state 1 ref changed",
+                    "issue it broke",
+                    "state 1 ref changed",
+                    "announcement example name",
+                ],
+            );
+        }
+
+        #[test]
+        fn state_ref_changes_compared_with_previous_state() {
+            let log = build_activity_log(
+                &mixed_events(),
+                &ActivityFilter {
+                    kinds: vec![ActivityKind::State],
+                    ..ActivityFilter::default()
+                },
+            );
+            assert_eq!(
+                log[0].ref_changes,
+                vec![RefChange::Moved {
+                    name: "refs/heads/main".to_string(),
+                    from: "1111111111111111111111111111111111111111".to_string(),
+                    to: "2222222222222222222222222222222222222222".to_string(),
+                }]
+            );
+            assert_eq!(
+                log[0].ref_changes[0].to_string(),
+                "refs/heads/main 1111111..2222222"
+            );
+            assert_eq!(
+                log[1].ref_changes[0].to_string(),
+                "refs/heads/main new 1111111"
+            );
+        }
+
+        #[test]
+        fn filtered_by_since_author_and_kind() {
+            let events = mixed_events();
+            let summaries = |filter: ActivityFilter| {
+                build_activity_log(&events, &filter)
+                    .iter()
+                    .map(|e| e.label)
+                    .collect::<Vec<&str>>()
+            };
+            assert_eq!(
+                summaries(ActivityFilter {
+                    since: Some(Timestamp::from(400)),
+                    ..ActivityFilter::default()
+                }),
+                vec!["closed", "state"],
+            );
+            assert_eq!(
+                summaries(ActivityFilter {
+                    authors: Some(HashSet::from([TEST_KEY_2_KEYS.public_key()])),
+                    ..ActivityFilter::default()
+                }),
+                vec!["issue"],
+            );
+            assert_eq!(
+                summaries(ActivityFilter {
+                    kinds: vec![ActivityKind::Announcement, ActivityKind::Status],
+                    ..ActivityFilter::default()
+                }),
+                vec!["closed", "announcement"],
+            );
+        }
+    }
+
+    mod parse_since {
+        use super::*;
+
+        #[test]
+        fn age_and_timestamp() -> Result<()> {
+            let now = Timestamp::from(1_000_000);
+            assert_eq!(
+                parse_since("2d", now)?,
+                Timestamp::from(1_000_000 - 172_800)
+            );
+            assert_eq!(parse_since("12345", now)?, Timestamp::from(12_345));
+            assert!(parse_since("yesterday", now).is_err());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn format_age_uses_largest_unit() {
+        let now = Timestamp::from(10_000_000);
+        assert_eq!(format_age(Timestamp::from(9_999_990), now), "just now");
+        assert_eq!(
+            format_age(Timestamp::from(10_000_000 - 3_600), now),
+            "1 hour ago"
+        );
+        assert_eq!(
+            format_age(Timestamp::from(10_000_000 - 3 * 86_400), now),
+            "3 days ago"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Result, bail};
+use nostr::{
+    EventBuilder, Kind, Tag, TagKind, Timestamp,
+    hashes::{Hash, sha256::Hash as Sha256Hash},
+};
+use serde::{Deserialize, Serialize};
+
+/// kind of the event authorising a blossom upload (BUD-02)
+pub static BLOSSOM_AUTH_KIND: u16 = 24242;
+
+/// how long a blossom upload authorisation is valid for
+static BLOSSOM_AUTH_EXPIRY_SECS: u64 = 300;
+
+/// a file stored on a blossom server, eg. a screenshot of a ui change, and
+/// tagged on a proposal as `["attachment", <url>, <sha256>, <file name>]`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attachment {
+    pub url: String,
+    pub sha256: String,
+    pub name: String,
+}
+
+/// what a blossom server returns once a blob is uploaded
+#[derive(Debug, Deserialize)]
+pub struct BlobDescriptor {
+    pub url: String,
+    pub sha256: String,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256Hash::hash(bytes).to_string()
+}
+
+pub fn attachment_tag(attachment: &Attachment) -> Tag {
+    Tag::custom(
+        TagKind::Custom(std::borrow::Cow::Borrowed("attachment")),
+        vec![
+            attachment.url.clone(),
+            attachment.sha256.clone(),
+            attachment.name.clone(),
+        ],
+    )
+}
+
+/// attachments tagged on `event`. tags without a url and a valid sha256 are
+/// ignored
+pub fn event_attachments(event: &nostr::Event) -> Vec<Attachment> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [t, url, sha256, rest @ ..]
+                if t == "attachment"
+                    && !url.is_empty()
+                    && sha256.len() == 64
+                    && sha256.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Some(Attachment {
+                    url: url.clone(),
+                    sha256: sha256.to_lowercase(),
+                    name: rest.first().cloned().unwrap_or_default(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// error unless `bytes` are the file the attachment was tagged with
+pub fn verify_attachment(attachment: &Attachment, bytes: &[u8]) -> Result<()> {
+    let sha256 = sha256_hex(bytes);
+    if sha256 != attachment.sha256 {
+        bail!(
+            "hash mismatch for {}: expected {} but downloaded {sha256}",
+            attachment.url,
+            attachment.sha256
+        );
+    }
+    Ok(())
+}
+
+/// unsigned event authorising the upload of a blob to a blossom server
+pub fn blossom_upload_auth(sha256: &str, file_name: &str) -> EventBuilder {
+    EventBuilder::new(
+        Kind::Custom(BLOSSOM_AUTH_KIND),
+        format!("Upload {file_name}"),
+    )
+    .tags([
+        Tag::hashtag("upload"),
+        Tag::custom(
+            TagKind::Custom(std::borrow::Cow::Borrowed("x")),
+            vec![sha256.to_string()],
+        ),
+        Tag::expiration(Timestamp::from(
+            Timestamp::now().as_u64() + BLOSSOM_AUTH_EXPIRY_SECS,
+        )),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::TEST_KEY_1_KEYS;
+
+    use super::*;
+
+    fn attachment(bytes: &[u8]) -> Attachment {
+        Attachment {
+            url: "https://blossom.example/abc.png".to_string(),
+            sha256: sha256_hex(bytes),
+            name: "screenshot.png".to_string(),
+        }
+    }
+
+    mod event_attachments {
+        use super::*;
+
+        #[test]
+        fn round_trips_through_tag_and_ignores_invalid_hashes() {
+            let attachment = attachment(b"image");
+            let event = EventBuilder::new(Kind::GitPatch, "")
+                .tags([
+                    attachment_tag(&attachment),
+                    Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("attachment")),
+                        vec!["https://blossom.example/x", "not-a-hash"],
+                    ),
+                ])
+                .sign_with_keys(&TEST_KEY_1_KEYS)
+                .unwrap();
+            assert_eq!(event_attachments(&event), vec![attachment]);
+        }
+    }
+
+    mod verify_attachment {
+        use super::*;
+
+        #[test]
+        fn accepts_matching_bytes() {
+            assert!(verify_attachment(&attachment(b"image"), b"image").is_ok());
+        }
+
+        #[test]
+        fn rejects_tampered_bytes() {
+            assert!(
+                verify_attachment(&attachment(b"image"), b"tampered")
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("hash mismatch for https://blossom.example/abc.png")
+            );
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::Timestamp;
+
+use crate::git::get_git_dir;
+
+/// how long a background fetch holds its lock. locks left by a crashed or
+/// hung process are ignored after this
+pub static BACKGROUND_FETCH_LOCK_SECS: u64 = 300;
+
+/// output of the most recent background fetch
+pub fn get_background_fetch_log_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr/last-background-fetch.log")
+}
+
+fn get_background_fetch_lock_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr/background-fetch.lock")
+}
+
+/// whether the lock, `<pid> <unix timestamp>`, was taken within
+/// [`BACKGROUND_FETCH_LOCK_SECS`] of `now`
+fn lock_is_held(lock: &str, now: u64) -> bool {
+    let Some(taken_at) = lock
+        .trim()
+        .split_once(' ')
+        .and_then(|(_, taken_at)| taken_at.parse::<u64>().ok())
+    else {
+        return false;
+    };
+    // a lock from the future is treated as stale
+    now.checked_sub(taken_at)
+        .is_some_and(|ago| ago < BACKGROUND_FETCH_LOCK_SECS)
+}
+
+/// whether another process is fetching in the background
+pub fn background_fetch_running(git_repo_path: &Path, now: Timestamp) -> bool {
+    std::fs::read_to_string(get_background_fetch_lock_path(git_repo_path))
+        .is_ok_and(|lock| lock_is_held(&lock, now.as_u64()))
+}
+
+/// removes the lock when the fetch finishes or fails
+pub struct BackgroundFetchLock {
+    path: PathBuf,
+}
+
+impl Drop for BackgroundFetchLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// take the lock so several processes started at once don't all fetch. None
+/// when another process holds it
+pub fn try_lock_background_fetch(
+    git_repo_path: &Path,
+    now: Timestamp,
+) -> Result<Option<BackgroundFetchLock>> {
+    let path = get_background_fetch_lock_path(git_repo_path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create .git/nostr directory")?;
+    }
+    for _ in 0..2 {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                let lock = BackgroundFetchLock { path };
+                writeln!(file, "{} {}", std::process::id(), now.as_u64())
+                    .context("failed to write background fetch lock")?;
+                return Ok(Some(lock));
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                if background_fetch_running(git_repo_path, now) {
+                    return Ok(None);
+                }
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(error) => {
+                return Err(error).context("failed to create background fetch lock");
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    mod lock_is_held {
+        use super::*;
+
+        #[test]
+        fn recent_lock() {
+            assert!(lock_is_held("100 1000\n", 1005));
+        }
+
+        #[test]
+        fn stale_lock() {
+            assert!(!lock_is_held(
+                "100 1000\n",
+                1000 + BACKGROUND_FETCH_LOCK_SECS
+            ));
+        }
+
+        #[test]
+        fn lock_from_the_future() {
+            assert!(!lock_is_held("100 2000\n", 1005));
+        }
+
+        #[test]
+        fn malformed_lock() {
+            assert!(!lock_is_held("garbage", 1005));
+        }
+    }
+
+    mod try_lock_background_fetch {
+        use super::*;
+
+        #[test]
+        fn only_one_process_holds_the_lock() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let now = Timestamp::from(1000);
+            let lock = try_lock_background_fetch(&test_repo.dir, now)?;
+            assert!(lock.is_some());
+            assert!(background_fetch_running(&test_repo.dir, now));
+            assert!(try_lock_background_fetch(&test_repo.dir, now)?.is_none());
+            drop(lock);
+            assert!(!background_fetch_running(&test_repo.dir, now));
+            assert!(try_lock_background_fetch(&test_repo.dir, now)?.is_some());
+            Ok(())
+        }
+
+        #[test]
+        fn stale_lock_is_replaced() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let lock = try_lock_background_fetch(&test_repo.dir, Timestamp::from(1000))?;
+            std::mem::forget(lock);
+            assert!(
+                try_lock_background_fetch(
+                    &test_repo.dir,
+                    Timestamp::from(1000 + BACKGROUND_FETCH_LOCK_SECS)
+                )?
+                .is_some()
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use nostr::hashes::{Hash, sha256::Hash as Sha256Hash};
+
+use crate::git::{Repo, RepoActions, get_git_dir};
+
+/// environment variable that overrides git config item `nostr.cache-dir`
+pub static NGIT_CACHE_DIR_ENV: &str = "NGIT_CACHE_DIR";
+
+/// directory to keep repository caches in instead of .git, eg. when
+/// repositories are on a network filesystem where lmdb is slow or locks
+/// unreliably. from `NGIT_CACHE_DIR` or git config `nostr.cache-dir`
+pub fn get_cache_dir_override(git_repo_path: &Path) -> Option<PathBuf> {
+    let dir = std::env::var(NGIT_CACHE_DIR_ENV)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .or_else(|| {
+            Repo::from_path(&git_repo_path.to_path_buf())
+                .ok()?
+                .get_git_config_item("nostr.cache-dir", None)
+                .ok()
+                .flatten()
+                .filter(|dir| !dir.trim().is_empty())
+        })?;
+    Some(PathBuf::from(dir.trim()))
+}
+
+/// where the cache for the repository at `git_repo_path` is kept when
+/// `nostr.cache-dir` isn't set
+pub fn get_default_local_cache_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-cache.lmdb")
+}
+
+/// `<hash of the repository's git directory>.lmdb` so repositories sharing a
+/// cache directory don't share a cache
+pub fn cache_file_name(git_repo_path: &Path) -> String {
+    let git_dir = get_git_dir(git_repo_path);
+    let git_dir = git_dir.canonicalize().unwrap_or(git_dir);
+    format!(
+        "{}.lmdb",
+        Sha256Hash::hash(git_dir.to_string_lossy().as_bytes())
+    )
+}
+
+/// the cache in .git that is no longer used because `nostr.cache-dir` was set
+/// after it was created, along with where it should move to
+pub fn get_cache_to_move(git_repo_path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let dir = get_cache_dir_override(git_repo_path)?;
+    let from = get_default_local_cache_path(git_repo_path);
+    let to = dir.join(cache_file_name(git_repo_path));
+    (from.exists() && !to.exists()).then_some((from, to))
+}
+
+/// move a cache, copying it when `to` is on a different filesystem
+pub fn move_local_cache(from: &Path, to: &Path) -> Result<()> {
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir).context(format!(
+            "failed to create cache directory {}",
+            dir.display()
+        ))?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(to).context(format!("failed to create {}", to.display()))?;
+    for entry in std::fs::read_dir(from).context(format!("failed to read {}", from.display()))? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), to.join(entry.file_name())).context(format!(
+            "failed to copy {} to {}",
+            entry.path().display(),
+            to.display()
+        ))?;
+    }
+    std::fs::remove_dir_all(from).context(format!("failed to remove {}", from.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    mod cache_file_name {
+        use super::*;
+
+        #[test]
+        fn differs_between_repositories() {
+            let repo_1 = GitTestRepo::default();
+            let repo_2 = GitTestRepo::default();
+            assert_ne!(cache_file_name(&repo_1.dir), cache_file_name(&repo_2.dir));
+            assert_eq!(cache_file_name(&repo_1.dir), cache_file_name(&repo_1.dir));
+        }
+    }
+
+    mod get_cache_to_move {
+        use super::*;
+
+        #[test]
+        fn none_without_override() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            std::fs::create_dir_all(get_default_local_cache_path(&test_repo.dir))?;
+            assert_eq!(get_cache_to_move(&test_repo.dir), None);
+            Ok(())
+        }
+
+        #[test]
+        fn default_cache_moved_to_cache_dir() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let cache_dir = test_repo.dir.join("cache-dir");
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.cache-dir", cache_dir.to_str().unwrap())?;
+            let default_path = get_default_local_cache_path(&test_repo.dir);
+            std::fs::create_dir_all(&default_path)?;
+            std::fs::write(default_path.join("data.mdb"), "events")?;
+
+            let (from, to) = get_cache_to_move(&test_repo.dir).unwrap();
+            assert_eq!(from, default_path);
+            assert_eq!(to, cache_dir.join(cache_file_name(&test_repo.dir)));
+
+            move_local_cache(&from, &to)?;
+            assert!(!default_path.exists());
+            assert_eq!(std::fs::read_to_string(to.join("data.mdb"))?, "events");
+            assert_eq!(get_cache_to_move(&test_repo.dir), None);
+            Ok(())
+        }
+    }
+}
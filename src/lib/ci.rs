@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use nostr::{Event, PublicKey, Timestamp};
+use serde::Serialize;
+
+use crate::{git_events::comment_kinds, repo_ref::RepoRef};
+
+/// NIP-32 namespace a ci bot labels its comment on a proposal with, eg.
+/// `["L", "ci"]` and `["l", "failed", "ci"]`
+pub static CI_LABEL_NAMESPACE: &str = "ci";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiState {
+    Passed,
+    Failed,
+    Running,
+}
+
+impl CiState {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "passed" | "success" => Some(CiState::Passed),
+            "failed" | "failure" => Some(CiState::Failed),
+            "running" | "pending" => Some(CiState::Running),
+            _ => None,
+        }
+    }
+
+    /// shown after a proposal title
+    pub fn indicator(self) -> &'static str {
+        match self {
+            CiState::Passed => "✓",
+            CiState::Failed => "✗",
+            CiState::Running => "●",
+        }
+    }
+}
+
+impl Display for CiState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CiState::Passed => write!(f, "passed"),
+            CiState::Failed => write!(f, "failed"),
+            CiState::Running => write!(f, "running"),
+        }
+    }
+}
+
+/// the latest ci result on a proposal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CiStatus {
+    pub state: CiState,
+    pub message: String,
+    /// link to the ci run from an `r` tag
+    pub url: Option<String>,
+    pub bot: PublicKey,
+    pub created_at: Timestamp,
+}
+
+/// npubs in `nostr.ci-bots` or, when it isn't set, the ci bots listed in the
+/// repository announcement
+pub fn get_ci_bots(configured: &[String], repo_ref: &RepoRef) -> Vec<PublicKey> {
+    if configured.is_empty() {
+        repo_ref.ci_bots.clone()
+    } else {
+        configured
+            .iter()
+            .filter_map(|npub| PublicKey::parse(npub).ok())
+            .collect()
+    }
+}
+
+fn references_proposal(event: &Event, proposal: &Event) -> bool {
+    event.tags.iter().any(|t| match t.as_slice() {
+        [name, id, ..] => (name == "e" || name == "E") && id == &proposal.id.to_hex(),
+        _ => false,
+    })
+}
+
+fn ci_state(event: &Event) -> Option<CiState> {
+    if !event.tags.iter().any(|t| match t.as_slice() {
+        [name, namespace, ..] => name == "L" && namespace == CI_LABEL_NAMESPACE,
+        _ => false,
+    }) {
+        return None;
+    }
+    event.tags.iter().find_map(|t| match t.as_slice() {
+        [name, state, namespace, ..] if name == "l" && namespace == CI_LABEL_NAMESPACE => {
+            CiState::parse(state)
+        }
+        _ => None,
+    })
+}
+
+/// result in the most recent ci comment on `proposal` by one of `bots`
+pub fn proposal_ci_status(
+    proposal: &Event,
+    events: &[Event],
+    bots: &[PublicKey],
+) -> Option<CiStatus> {
+    events
+        .iter()
+        .filter(|e| {
+            comment_kinds().contains(&e.kind)
+                && bots.contains(&e.pubkey)
+                && references_proposal(e, proposal)
+        })
+        .filter_map(|e| ci_state(e).map(|state| (e, state)))
+        .max_by_key(|(e, _)| e.created_at)
+        .map(|(e, state)| CiStatus {
+            state,
+            message: e.content.trim().to_string(),
+            url: e.tags.iter().find_map(|t| match t.as_slice() {
+                [name, url, ..] if name == "r" && url.starts_with("http") => Some(url.clone()),
+                _ => None,
+            }),
+            bot: e.pubkey,
+            created_at: e.created_at,
+        })
+}
+
+/// ci state, message and link, eg. for `ngit status`
+pub fn format_ci_status(status: &CiStatus) -> Vec<String> {
+    [
+        vec![format!("ci: {} {}", status.state.indicator(), status.state)],
+        status
+            .message
+            .lines()
+            .map(|line| format!("  {line}"))
+            .collect(),
+        status.url.iter().map(|url| format!("  {url}")).collect(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind, Tag, TagKind};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn proposal() -> Event {
+        EventBuilder::new(Kind::GitPatch, "")
+            .tags([Tag::hashtag("cover-letter"), Tag::hashtag("root")])
+            .sign_with_keys(&TEST_KEY_1_KEYS)
+            .unwrap()
+    }
+
+    fn ci_comment(proposal: &Event, keys: &Keys, state: &str, created_at: u64) -> Event {
+        EventBuilder::new(Kind::Custom(1111), format!("build {state}"))
+            .tags([
+                Tag::event(proposal.id),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("L")),
+                    vec![CI_LABEL_NAMESPACE],
+                ),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("l")),
+                    vec![state, CI_LABEL_NAMESPACE],
+                ),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("r")),
+                    vec!["https://ci.example/runs/1"],
+                ),
+            ])
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    mod proposal_ci_status {
+        use super::*;
+
+        #[test]
+        fn most_recent_result_from_a_bot_used() {
+            let proposal = proposal();
+            let bot = TEST_KEY_2_KEYS.public_key();
+            let status = proposal_ci_status(
+                &proposal,
+                &[
+                    ci_comment(&proposal, &TEST_KEY_2_KEYS, "running", 100),
+                    ci_comment(&proposal, &TEST_KEY_2_KEYS, "failed", 200),
+                ],
+                &[bot],
+            )
+            .unwrap();
+            assert_eq!(status.state, CiState::Failed);
+            assert_eq!(status.message, "build failed");
+            assert_eq!(status.url, Some("https://ci.example/runs/1".to_string()));
+        }
+
+        #[test]
+        fn results_from_other_authors_and_unlabelled_comments_ignored() {
+            let proposal = proposal();
+            let unlabelled = EventBuilder::new(Kind::Custom(1111), "looks good")
+                .tags([Tag::event(proposal.id)])
+                .custom_created_at(Timestamp::from(300))
+                .sign_with_keys(&TEST_KEY_2_KEYS)
+                .unwrap();
+            let status = proposal_ci_status(
+                &proposal,
+                &[
+                    ci_comment(&proposal, &TEST_KEY_2_KEYS, "failed", 100),
+                    ci_comment(&proposal, &TEST_KEY_1_KEYS, "passed", 200),
+                    unlabelled,
+                ],
+                &[TEST_KEY_2_KEYS.public_key()],
+            );
+            assert_eq!(status.map(|s| s.state), Some(CiState::Failed));
+        }
+
+        #[test]
+        fn none_without_bots() {
+            let proposal = proposal();
+            assert!(
+                proposal_ci_status(
+                    &proposal,
+                    &[ci_comment(&proposal, &TEST_KEY_2_KEYS, "passed", 100)],
+                    &[],
+                )
+                .is_none()
+            );
+        }
+    }
+}
@@ -1,14 +1,125 @@
-use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::IsTerminal,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Context, Result, anyhow, bail};
 use dialoguer::{Confirm, Input, Password, theme::ColorfulTheme};
 use indicatif::TermLike;
 #[cfg(test)]
 use mockall::*;
 
+use crate::error::NgitError;
+
+/// when set to anything other than empty, `0` or `false` ngit never prompts
+pub static NONINTERACTIVE_ENV_VAR: &str = "NGIT_NONINTERACTIVE";
+
+static PROMPT_ON_STDERR_TERMINAL: AtomicBool = AtomicBool::new(false);
+
+/// prompt when stderr, rather than stdin, is a terminal. for
+/// git-remote-nostr, whose stdin is git's pipe
+pub fn prompt_when_stderr_is_terminal() {
+    PROMPT_ON_STDERR_TERMINAL.store(true, Ordering::Relaxed);
+}
+
+static ACCEPT_DEFAULTS: AtomicBool = AtomicBool::new(false);
+
+/// input prompts already answered with their default, to catch callers that
+/// prompt again when the default is rejected
+static DEFAULTS_TAKEN: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// answer every prompt with its default, without prompting, and fail when a
+/// prompt has none. for `--yes`
+pub fn accept_defaults() {
+    ACCEPT_DEFAULTS.store(true, Ordering::Relaxed);
+}
+
+fn accepting_defaults() -> bool {
+    ACCEPT_DEFAULTS.load(Ordering::Relaxed)
+}
+
+/// false when there is no terminal to prompt on or `NGIT_NONINTERACTIVE` is
+/// set. callers with a safe default can use it to skip optional prompts
+pub fn prompts_allowed() -> bool {
+    if std::env::var(NONINTERACTIVE_ENV_VAR)
+        .is_ok_and(|v| !v.is_empty() && v != "0" && !v.eq_ignore_ascii_case("false"))
+    {
+        return false;
+    }
+    if PROMPT_ON_STDERR_TERMINAL.load(Ordering::Relaxed) {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdin().is_terminal()
+    }
+}
+
+/// fail fast, naming the flag that supplies the value, instead of waiting on
+/// input that will never come
+fn ensure_prompts_allowed(prompt: &str, flag: Option<&str>) -> Result<()> {
+    if prompts_allowed() {
+        return Ok(());
+    }
+    bail!(input_required_error(
+        "interactive input required",
+        prompt,
+        flag
+    ))
+}
+
+fn input_required_error(reason: &str, prompt: &str, flag: Option<&str>) -> NgitError {
+    let prompt = prompt.trim().trim_end_matches(['?', ':']);
+    NgitError::Config(anyhow!(match flag {
+        Some(flag) if flag.starts_with(['-', '[']) => {
+            format!("{reason} for '{prompt}'; pass {flag}")
+        }
+        Some(config) => format!("{reason} for '{prompt}'; set {config}"),
+        None => format!("{reason} for '{prompt}'; run in a terminal"),
+    }))
+}
+
+fn no_default_error(prompt: &str, flag: Option<&str>) -> NgitError {
+    input_required_error("--yes can't answer as there is no default", prompt, flag)
+}
+
+/// the default of an input prompt when accepting defaults
+fn take_input_default(parms: &PromptInputParms) -> Result<String> {
+    if parms.default.is_empty() && !parms.optional {
+        bail!(no_default_error(&parms.prompt, parms.flag.as_deref()));
+    }
+    if let Some(Err(error)) = parms.validator.as_ref().map(|v| v(&parms.default)) {
+        bail!(input_required_error(
+            &format!("--yes can't answer as the default is invalid ({error})"),
+            &parms.prompt,
+            parms.flag.as_deref(),
+        ));
+    }
+    let mut taken = DEFAULTS_TAKEN
+        .lock()
+        .map_err(|_| anyhow!("failed to record prompt default"))?;
+    if taken.contains(&parms.prompt) {
+        bail!(input_required_error(
+            "--yes can't answer as the default was rejected",
+            &parms.prompt,
+            parms.flag.as_deref(),
+        ));
+    }
+    taken.push(parms.prompt.clone());
+    Ok(parms.default.clone())
+}
+
 #[derive(Default)]
 pub struct Interactor {
     theme: ColorfulTheme,
 }
 
+/// the prompts ngit asks. [`Interactor`] asks them in the terminal with
+/// dialoguer and [`ScriptedInteractor`] answers them from a script, so flows
+/// can be unit tested without a pty
 #[cfg_attr(test, automock)]
 pub trait InteractorPrompt {
     fn input(&self, parms: PromptInputParms) -> Result<String>;
@@ -19,15 +130,26 @@ pub trait InteractorPrompt {
 }
 impl InteractorPrompt for Interactor {
     fn input(&self, parms: PromptInputParms) -> Result<String> {
+        if accepting_defaults() {
+            return take_input_default(&parms);
+        }
+        ensure_prompts_allowed(&parms.prompt, parms.flag.as_deref())?;
         let mut input = Input::with_theme(&self.theme);
         input.with_prompt(parms.prompt).allow_empty(parms.optional);
         if !parms.default.is_empty() {
             input.default(parms.default);
         }
         input.report(parms.report);
+        if let Some(validator) = parms.validator {
+            input.validate_with(move |value: &String| validator(value));
+        }
         Ok(input.interact_text()?)
     }
     fn password(&self, parms: PromptPasswordParms) -> Result<String> {
+        if accepting_defaults() {
+            bail!(no_default_error(&parms.prompt, parms.flag.as_deref()));
+        }
+        ensure_prompts_allowed(&parms.prompt, parms.flag.as_deref())?;
         let mut p = Password::with_theme(&self.theme);
         p.with_prompt(parms.prompt);
         p.report(parms.report);
@@ -38,6 +160,10 @@ impl InteractorPrompt for Interactor {
         Ok(pass)
     }
     fn confirm(&self, params: PromptConfirmParms) -> Result<bool> {
+        if accepting_defaults() {
+            return Ok(params.default);
+        }
+        ensure_prompts_allowed(&params.prompt, params.flag.as_deref())?;
         let confirm: bool = Confirm::with_theme(&self.theme)
             .with_prompt(params.prompt)
             .default(params.default)
@@ -45,6 +171,13 @@ impl InteractorPrompt for Interactor {
         Ok(confirm)
     }
     fn choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        if accepting_defaults() {
+            let Some(default) = parms.default else {
+                bail!(no_default_error(&parms.prompt, parms.flag.as_deref()));
+            };
+            return Ok(default);
+        }
+        ensure_prompts_allowed(&parms.prompt, parms.flag.as_deref())?;
         let mut choice = dialoguer::Select::with_theme(&self.theme);
         choice
             .with_prompt(parms.prompt)
@@ -58,6 +191,18 @@ impl InteractorPrompt for Interactor {
         choice.interact().context("failed to get choice")
     }
     fn multi_choice(&self, parms: PromptMultiChoiceParms) -> Result<Vec<usize>> {
+        if accepting_defaults() {
+            let Some(defaults) = parms.defaults else {
+                bail!(no_default_error(&parms.prompt, parms.flag.as_deref()));
+            };
+            return Ok(defaults
+                .iter()
+                .enumerate()
+                .filter(|(_, selected)| **selected)
+                .map(|(i, _)| i)
+                .collect());
+        }
+        ensure_prompts_allowed(&parms.prompt, parms.flag.as_deref())?;
         // the colorful theme is not very clear so falling back to default
         let mut choice = dialoguer::MultiSelect::default();
         choice
@@ -71,11 +216,123 @@ impl InteractorPrompt for Interactor {
     }
 }
 
+/// a canned answer for [`ScriptedInteractor`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptedAnswer {
+    Input(String),
+    Password(String),
+    Confirm(bool),
+    Choice(usize),
+    MultiChoice(Vec<usize>),
+}
+
+/// a prompt [`ScriptedInteractor`] was asked
+#[derive(Debug, Clone, PartialEq)]
+pub struct AskedPrompt {
+    pub prompt: String,
+    /// empty unless it was a choice
+    pub choices: Vec<String>,
+}
+
+/// answers prompts in order from a queue of canned answers and records what
+/// was asked. fails when asked more prompts than there are answers or when
+/// the next answer is for a different kind of prompt
+#[derive(Debug, Default)]
+pub struct ScriptedInteractor {
+    answers: RefCell<VecDeque<ScriptedAnswer>>,
+    asked: RefCell<Vec<AskedPrompt>>,
+}
+
+impl ScriptedInteractor {
+    pub fn new(answers: Vec<ScriptedAnswer>) -> Self {
+        Self {
+            answers: RefCell::new(answers.into()),
+            asked: RefCell::new(vec![]),
+        }
+    }
+
+    /// prompts asked so far, oldest first
+    pub fn asked(&self) -> Vec<AskedPrompt> {
+        self.asked.borrow().clone()
+    }
+
+    /// answers that were never used
+    pub fn remaining(&self) -> usize {
+        self.answers.borrow().len()
+    }
+
+    fn next_answer(&self, prompt: &str, choices: &[String]) -> Result<ScriptedAnswer> {
+        self.asked.borrow_mut().push(AskedPrompt {
+            prompt: prompt.to_string(),
+            choices: choices.to_vec(),
+        });
+        self.answers
+            .borrow_mut()
+            .pop_front()
+            .context(format!("no scripted answer for '{prompt}'"))
+    }
+}
+
+impl InteractorPrompt for ScriptedInteractor {
+    fn input(&self, parms: PromptInputParms) -> Result<String> {
+        match self.next_answer(&parms.prompt, &[])? {
+            ScriptedAnswer::Input(input) => {
+                if let Some(Err(error)) = parms.validator.as_ref().map(|v| v(&input)) {
+                    bail!("scripted answer for '{}' is invalid: {error}", parms.prompt);
+                }
+                Ok(input)
+            }
+            answer => bail!("expected input for '{}' not {answer:?}", parms.prompt),
+        }
+    }
+    fn password(&self, parms: PromptPasswordParms) -> Result<String> {
+        match self.next_answer(&parms.prompt, &[])? {
+            ScriptedAnswer::Password(password) => Ok(password),
+            answer => bail!("expected password for '{}' not {answer:?}", parms.prompt),
+        }
+    }
+    fn confirm(&self, params: PromptConfirmParms) -> Result<bool> {
+        match self.next_answer(&params.prompt, &[])? {
+            ScriptedAnswer::Confirm(confirm) => Ok(confirm),
+            answer => bail!("expected confirm for '{}' not {answer:?}", params.prompt),
+        }
+    }
+    fn choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        match self.next_answer(&parms.prompt, &parms.choices)? {
+            ScriptedAnswer::Choice(index) if index < parms.choices.len() => Ok(index),
+            answer => bail!(
+                "expected one of {} choices for '{}' not {answer:?}",
+                parms.choices.len(),
+                parms.prompt
+            ),
+        }
+    }
+    fn multi_choice(&self, parms: PromptMultiChoiceParms) -> Result<Vec<usize>> {
+        match self.next_answer(&parms.prompt, &parms.choices)? {
+            ScriptedAnswer::MultiChoice(indexes)
+                if indexes.iter().all(|i| *i < parms.choices.len()) =>
+            {
+                Ok(indexes)
+            }
+            answer => bail!(
+                "expected some of {} choices for '{}' not {answer:?}",
+                parms.choices.len(),
+                parms.prompt
+            ),
+        }
+    }
+}
+
 pub struct PromptInputParms {
     pub prompt: String,
     pub default: String,
     pub report: bool,
     pub optional: bool,
+    /// flag, or config item, named in the error when there is no terminal to
+    /// prompt on
+    pub flag: Option<String>,
+    /// rejects input with the returned message and prompts again
+    pub validator: Option<Box<dyn Fn(&str) -> Result<(), String>>>,
 }
 
 impl Default for PromptInputParms {
@@ -85,6 +342,8 @@ impl Default for PromptInputParms {
             default: String::new(),
             optional: false,
             report: true,
+            flag: None,
+            validator: None,
         }
     }
 }
@@ -107,12 +366,26 @@ impl PromptInputParms {
         self.report = false;
         self
     }
+
+    pub fn with_flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flag = Some(flag.into());
+        self
+    }
+
+    pub fn with_validator<F: Fn(&str) -> Result<(), String> + 'static>(
+        mut self,
+        validator: F,
+    ) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
 }
 
 pub struct PromptPasswordParms {
     pub prompt: String,
     pub confirm: bool,
     pub report: bool,
+    pub flag: Option<String>,
 }
 
 impl Default for PromptPasswordParms {
@@ -121,6 +394,7 @@ impl Default for PromptPasswordParms {
             prompt: String::new(),
             confirm: false,
             report: true,
+            flag: None,
         }
     }
 }
@@ -138,12 +412,17 @@ impl PromptPasswordParms {
         self.report = false;
         self
     }
+    pub fn with_flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flag = Some(flag.into());
+        self
+    }
 }
 
 #[derive(Default)]
 pub struct PromptConfirmParms {
     pub prompt: String,
     pub default: bool,
+    pub flag: Option<String>,
 }
 
 impl PromptConfirmParms {
@@ -155,6 +434,10 @@ impl PromptConfirmParms {
         self.default = default;
         self
     }
+    pub fn with_flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flag = Some(flag.into());
+        self
+    }
 }
 
 pub struct PromptChoiceParms {
@@ -162,6 +445,7 @@ pub struct PromptChoiceParms {
     pub choices: Vec<String>,
     pub default: Option<usize>,
     pub report: bool,
+    pub flag: Option<String>,
 }
 
 impl Default for PromptChoiceParms {
@@ -171,6 +455,7 @@ impl Default for PromptChoiceParms {
             choices: vec![],
             default: None,
             report: true,
+            flag: None,
         }
     }
 }
@@ -195,6 +480,11 @@ impl PromptChoiceParms {
         self.default = Some(index);
         self
     }
+
+    pub fn with_flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flag = Some(flag.into());
+        self
+    }
 }
 
 pub struct PromptMultiChoiceParms {
@@ -202,6 +492,7 @@ pub struct PromptMultiChoiceParms {
     pub choices: Vec<String>,
     pub defaults: Option<Vec<bool>>,
     pub report: bool,
+    pub flag: Option<String>,
 }
 
 impl Default for PromptMultiChoiceParms {
@@ -211,6 +502,7 @@ impl Default for PromptMultiChoiceParms {
             choices: vec![],
             defaults: None,
             report: true,
+            flag: None,
         }
     }
 }
@@ -235,6 +527,11 @@ impl PromptMultiChoiceParms {
         self.defaults = Some(defaults);
         self
     }
+
+    pub fn with_flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flag = Some(flag.into());
+        self
+    }
 }
 
 #[derive(Debug, Default)]
@@ -283,3 +580,94 @@ pub fn count_lines_per_msg_vec(width: u16, msgs: &[String], prefix_len: usize) -
         .map(|msg| count_lines_per_msg(width, msg, prefix_len))
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod scripted_interactor {
+        use super::*;
+
+        #[test]
+        fn answers_in_order_and_records_prompts() -> Result<()> {
+            let interactor = ScriptedInteractor::new(vec![
+                ScriptedAnswer::Confirm(true),
+                ScriptedAnswer::Choice(1),
+                ScriptedAnswer::Input("my title".to_string()),
+            ]);
+            assert!(interactor.confirm(PromptConfirmParms::default().with_prompt("continue?"))?);
+            assert_eq!(
+                interactor.choice(
+                    PromptChoiceParms::default()
+                        .with_prompt("pick")
+                        .with_choices(vec!["a".to_string(), "b".to_string()]),
+                )?,
+                1
+            );
+            assert_eq!(
+                interactor.input(PromptInputParms::default().with_prompt("title"))?,
+                "my title"
+            );
+            assert_eq!(
+                interactor
+                    .asked()
+                    .iter()
+                    .map(|a| a.prompt.as_str())
+                    .collect::<Vec<&str>>(),
+                vec!["continue?", "pick", "title"]
+            );
+            assert_eq!(interactor.asked()[1].choices, vec!["a", "b"]);
+            assert_eq!(interactor.remaining(), 0);
+            Ok(())
+        }
+
+        #[test]
+        fn fails_when_out_of_answers() {
+            let interactor = ScriptedInteractor::default();
+            assert!(
+                interactor
+                    .confirm(PromptConfirmParms::default().with_prompt("continue?"))
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn fails_when_answer_is_for_another_kind_of_prompt() {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Confirm(true)]);
+            assert!(
+                interactor
+                    .input(PromptInputParms::default().with_prompt("title"))
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn fails_when_choice_is_out_of_range() {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Choice(2)]);
+            assert!(
+                interactor
+                    .choice(
+                        PromptChoiceParms::default()
+                            .with_choices(vec!["a".to_string(), "b".to_string()])
+                    )
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn rejects_input_that_fails_validation() {
+            let interactor = ScriptedInteractor::new(vec![ScriptedAnswer::Input(String::new())]);
+            assert!(
+                interactor
+                    .input(PromptInputParms::default().with_validator(|v| {
+                        if v.is_empty() {
+                            Err("required".to_string())
+                        } else {
+                            Ok(())
+                        }
+                    }))
+                    .is_err()
+            );
+        }
+    }
+}
@@ -11,12 +11,16 @@
 // certain that the implementation is going to make it to stable but we don't
 // want to inadvertlty use other features of nightly that might be removed.
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::{Display, Write},
     fs::create_dir_all,
-    path::Path,
-    sync::Arc,
-    time::Duration,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, bail};
@@ -26,26 +30,43 @@ use futures::{
     future::join_all,
     stream::{self, StreamExt},
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use indicatif::{
+    MultiProgress, ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressState, ProgressStyle,
+};
 #[cfg(test)]
 use mockall::*;
-use nostr::{Event, nips::nip01::Coordinate, signer::SignerBackend};
+use nostr::{Event, JsonUtil, nips::nip01::Coordinate, signer::SignerBackend};
 use nostr_database::NostrEventsDatabase;
 use nostr_lmdb::NostrLMDB;
 use nostr_sdk::{
     EventBuilder, EventId, Kind, NostrSigner, Options, PublicKey, RelayUrl, SingleLetterTag,
-    Timestamp, prelude::RelayLimits,
+    Timestamp, ToBech32,
+    prelude::{Connection, ConnectionTarget, RelayLimits},
 };
 
 use crate::{
+    cache_dir::{cache_file_name, get_cache_dir_override, get_default_local_cache_path},
+    config::Config,
+    error::{ErrorCategory, NgitError},
+    event_sources::record_event_sources,
     get_dirs,
-    git::{Repo, RepoActions},
+    git::{Repo, RepoActions, get_git_dir, nostr_url::update_remote_relay_hints},
     git_events::{
-        event_is_cover_letter, event_is_patch_set_root, event_is_revision_root, status_kinds,
+        PROPOSAL_EDIT_KIND, comment_kinds, event_is_cover_letter, event_is_patch_set_root,
+        event_is_revision_root, status_kinds,
     },
     login::{get_likely_logged_in_user, user::get_user_ref_from_cache},
-    repo_ref::RepoRef,
+    output::{dim, multi_progress, print_human},
+    profile_cache::{DEFAULT_PROFILE_CACHE_TTL_SECS, get_stale_profiles, record_profiles_fetched},
+    proxy::{ProxyUse, ensure_onion_url_has_proxy},
+    publish_status::{RelayResponse, record_relay_responses},
+    relay_hints::{record_relays_that_returned_events, relay_hints_key},
+    relay_info::{SubscriptionLimits, get_subscription_limits},
+    relay_stats::{order_by_reliability, record_relay_fetches},
+    repo_fetched_at::record_repo_fetched,
+    repo_ref::{RepoRef, describe_hosting_changes},
     repo_state::RepoState,
+    timeout,
 };
 
 #[allow(clippy::struct_field_names)]
@@ -55,6 +76,60 @@ pub struct Client {
     more_fallback_relays: Vec<String>,
     blaster_relays: Vec<String>,
     fallback_signer_relays: Vec<String>,
+    relay_timeout_secs: u64,
+    profile_cache_ttl_secs: u64,
+    relay_proxy: Option<SocketAddr>,
+    /// None fetches complete history
+    fetch_max_events_per_kind: Option<usize>,
+    max_concurrent_relays: usize,
+}
+
+pub fn default_fallback_relays() -> Vec<String> {
+    if std::env::var("NGITTEST").is_ok() {
+        vec![
+            "ws://localhost:8051".to_string(),
+            "ws://localhost:8052".to_string(),
+        ]
+    } else {
+        vec![
+            "wss://relay.damus.io".to_string(), /* free, good reliability, have been known
+                                                 * to delete all messages */
+            "wss://nos.lol".to_string(),
+            "wss://relay.nostr.band".to_string(),
+        ]
+    }
+}
+
+fn default_more_fallback_relays() -> Vec<String> {
+    if std::env::var("NGITTEST").is_ok() {
+        vec![
+            "ws://localhost:8055".to_string(),
+            "ws://localhost:8056".to_string(),
+        ]
+    } else {
+        vec![
+            "wss://purplerelay.com".to_string(), // free but reliability not tested
+            "wss://purplepages.es".to_string(),  // for profile events but unreliable
+            "wss://relayable.org".to_string(),   // free but not always reliable
+        ]
+    }
+}
+
+/// relays that rebroadcast repository announcements to many others
+pub fn default_blaster_relays() -> Vec<String> {
+    if std::env::var("NGITTEST").is_ok() {
+        vec!["ws://localhost:8057".to_string()]
+    } else {
+        vec![]
+    }
+}
+
+fn default_fallback_signer_relays() -> Vec<String> {
+    if std::env::var("NGITTEST").is_ok() {
+        vec!["ws://localhost:8051".to_string()]
+    } else {
+        vec!["wss://relay.nsec.app".to_string()]
+    }
 }
 
 #[cfg_attr(test, automock)]
@@ -68,6 +143,9 @@ pub trait Connect {
     fn get_fallback_relays(&self) -> &Vec<String>;
     fn get_more_fallback_relays(&self) -> &Vec<String>;
     fn get_blaster_relays(&self) -> &Vec<String>;
+    /// stop publishing to the blaster relays, eg. for the `no-blaster` push
+    /// option
+    fn disable_blaster_relays(&mut self);
     fn get_fallback_signer_relays(&self) -> &Vec<String>;
     async fn send_event_to<'a>(
         &self,
@@ -96,6 +174,7 @@ pub trait Connect {
         &self,
         git_repo_path: Option<&'a Path>,
         request: FetchRequest,
+        newest_per_filter: Option<&'a Mutex<NewestPerFilter>>,
         pb: &Option<ProgressBar>,
     ) -> Result<FetchReport>;
 }
@@ -103,59 +182,30 @@ pub trait Connect {
 #[async_trait]
 impl Connect for Client {
     fn default() -> Self {
-        let fallback_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec![
-                "ws://localhost:8051".to_string(),
-                "ws://localhost:8052".to_string(),
-            ]
-        } else {
-            vec![
-                "wss://relay.damus.io".to_string(), /* free, good reliability, have been known
-                                                     * to delete all messages */
-                "wss://nos.lol".to_string(),
-                "wss://relay.nostr.band".to_string(),
-            ]
-        };
-
-        let more_fallback_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec![
-                "ws://localhost:8055".to_string(),
-                "ws://localhost:8056".to_string(),
-            ]
-        } else {
-            vec![
-                "wss://purplerelay.com".to_string(), // free but reliability not tested
-                "wss://purplepages.es".to_string(),  // for profile events but unreliable
-                "wss://relayable.org".to_string(),   // free but not always reliable
-            ]
-        };
-
-        let blaster_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec!["ws://localhost:8057".to_string()]
-        } else {
-            vec![]
-        };
-
-        let fallback_signer_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec!["ws://localhost:8051".to_string()]
-        } else {
-            vec!["wss://relay.nsec.app".to_string()]
-        };
-
         Client {
             client: nostr_sdk::ClientBuilder::new()
                 .opts(Options::new().relay_limits(RelayLimits::disable()))
                 .build(),
-            fallback_relays,
-            more_fallback_relays,
-            blaster_relays,
-            fallback_signer_relays,
+            fallback_relays: default_fallback_relays(),
+            more_fallback_relays: default_more_fallback_relays(),
+            blaster_relays: default_blaster_relays(),
+            fallback_signer_relays: default_fallback_signer_relays(),
+            relay_timeout_secs: GET_EVENTS_TIMEOUT,
+            profile_cache_ttl_secs: DEFAULT_PROFILE_CACHE_TTL_SECS,
+            relay_proxy: None,
+            fetch_max_events_per_kind: Some(DEFAULT_FETCH_MAX_EVENTS_PER_KIND),
+            max_concurrent_relays: DEFAULT_MAX_CONCURRENT_RELAYS,
         }
     }
     fn new(opts: Params) -> Self {
+        let options = Options::new().relay_limits(RelayLimits::disable());
         Client {
             client: nostr_sdk::ClientBuilder::new()
-                .opts(Options::new().relay_limits(RelayLimits::disable()))
+                .opts(if let Some(proxy) = opts.relay_proxy {
+                    options.connection(Connection::new().proxy(proxy).target(ConnectionTarget::All))
+                } else {
+                    options
+                })
                 .signer(opts.keys.unwrap_or(nostr::Keys::generate()))
                 // .database(
                 //     SQLiteDatabase::open(get_dirs()?.cache_dir().join("nostr-cache.lmdb")).
@@ -165,6 +215,16 @@ impl Connect for Client {
             more_fallback_relays: opts.more_fallback_relays,
             blaster_relays: opts.blaster_relays,
             fallback_signer_relays: opts.fallback_signer_relays,
+            relay_timeout_secs: opts.relay_timeout_secs.unwrap_or(GET_EVENTS_TIMEOUT),
+            profile_cache_ttl_secs: opts
+                .profile_cache_ttl_secs
+                .unwrap_or(DEFAULT_PROFILE_CACHE_TTL_SECS),
+            relay_proxy: opts.relay_proxy,
+            fetch_max_events_per_kind: opts.fetch_max_events_per_kind,
+            max_concurrent_relays: opts
+                .max_concurrent_relays
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_RELAYS)
+                .max(1),
         }
     }
 
@@ -173,6 +233,7 @@ impl Connect for Client {
     }
 
     async fn connect(&self, relay_url: &RelayUrl) -> Result<()> {
+        ensure_onion_url_has_proxy(relay_url.as_str(), &self.relay_proxy, ProxyUse::Relays)?;
         self.client
             .add_relay(relay_url)
             .await
@@ -210,6 +271,10 @@ impl Connect for Client {
         &self.blaster_relays
     }
 
+    fn disable_blaster_relays(&mut self) {
+        self.blaster_relays.clear();
+    }
+
     fn get_fallback_signer_relays(&self) -> &Vec<String> {
         &self.fallback_signer_relays
     }
@@ -220,6 +285,7 @@ impl Connect for Client {
         url: &str,
         event: Event,
     ) -> Result<nostr::EventId> {
+        ensure_onion_url_has_proxy(url, &self.relay_proxy, ProxyUse::Relays)?;
         self.client.add_relay(url).await?;
         #[allow(clippy::large_futures)]
         self.client.connect_relay(url).await?;
@@ -246,7 +312,7 @@ impl Connect for Client {
             .get_events_per_relay(
                 relays.iter().map(|r| RelayUrl::parse(r).unwrap()).collect(),
                 filters,
-                MultiProgress::new(),
+                multi_progress(),
             )
             .await?;
         Ok(get_dedup_events(relay_results))
@@ -279,15 +345,26 @@ impl Connect for Client {
                     let pb = progress_reporter.add(
                         ProgressBar::new(1)
                             .with_prefix(format!("{: <11}{}", "connecting", relay.url()))
-                            .with_style(pb_style()?),
+                            .with_style(pb_style(self.relay_timeout_secs)?)
+                            .with_finish(ProgressFinish::AndClear),
                     );
                     pb.enable_steady_tick(Duration::from_millis(300));
                     Some(pb)
                 } else {
                     None
                 };
-                #[allow(clippy::large_futures)]
-                match get_events_of(relay, filters, &pb).await {
+                let res = if let Err(error) = ensure_onion_url_has_proxy(
+                    relay.url().as_str(),
+                    &self.relay_proxy,
+                    ProxyUse::Relays,
+                ) {
+                    Err(error)
+                } else {
+                    let limits = get_subscription_limits(relay.url(), None, self.relay_proxy).await;
+                    #[allow(clippy::large_futures)]
+                    get_events_of(relay, filters, limits, self.relay_timeout_secs, &pb).await
+                };
+                match res {
                     Err(error) => {
                         if let Some(pb) = pb {
                             pb.set_style(pb_after_style(false));
@@ -305,22 +382,32 @@ impl Connect for Client {
                     }
                     Ok(res) => {
                         if let Some(pb) = pb {
-                            pb.set_style(pb_after_style(true));
+                            if res.missing_eose {
+                                pb.set_style(pb_partial_style());
+                            } else {
+                                pb.set_style(pb_after_style(true));
+                            }
                             pb.set_prefix(format!(
                                 "{: <11}{}",
-                                format!("{} events", res.len()),
+                                format!("{} events", res.events.len()),
                                 relay.url()
                             ));
-                            pb.finish_with_message("");
+                            pb.finish_with_message(if res.missing_eose {
+                                "partial results (no EOSE)"
+                            } else {
+                                ""
+                            });
                         }
-                        Ok(res)
+                        Ok(res.events)
                     }
                 }
             })
             .collect();
 
-        let relay_results: Vec<Result<Vec<nostr::Event>>> =
-            stream::iter(futures).buffer_unordered(15).collect().await;
+        let relay_results: Vec<Result<Vec<nostr::Event>>> = stream::iter(futures)
+            .buffer_unordered(self.max_concurrent_relays)
+            .collect()
+            .await;
 
         Ok((relay_results, progress_reporter))
     }
@@ -332,6 +419,23 @@ impl Connect for Client {
         trusted_maintainer_coordinate: Option<&'a Coordinate>,
         user_profiles: &HashSet<PublicKey>,
     ) -> Result<(Vec<Result<FetchReport>>, MultiProgress)> {
+        let mut in_flight_marker = None;
+        if let (Some(git_repo_path), Some(_)) = (git_repo_path, trusted_maintainer_coordinate) {
+            if let Some(ago) = wait_for_fetch_by_another_process(git_repo_path).await {
+                eprintln!("another ngit process fetched {ago}s ago — using cache");
+                return Ok((vec![], multi_progress()));
+            }
+            let started_at = Timestamp::now().as_u64();
+            // failing to write the marker only means other processes fetch too
+            if write_fetch_in_flight_marker(git_repo_path, started_at, None).is_ok() {
+                in_flight_marker = Some(FetchInFlightMarker {
+                    git_repo_path,
+                    started_at,
+                    completed: false,
+                });
+            }
+        }
+
         let fallback_relays = &self
             .fallback_relays
             .iter()
@@ -343,15 +447,31 @@ impl Connect for Client {
             trusted_maintainer_coordinate,
             user_profiles,
             fallback_relays.clone(),
+            self.profile_cache_ttl_secs,
         )
         .await?;
 
-        let progress_reporter = MultiProgress::new();
+        let progress_reporter = multi_progress();
 
         let mut processed_relays = HashSet::new();
 
         let mut relay_reports: Vec<Result<FetchReport>> = vec![];
 
+        let mut repo_relays_source = RepoRelaysSource::Announcement;
+
+        let announced_before = match trusted_maintainer_coordinate {
+            Some(coordinate) => get_repo_ref_from_cache(git_repo_path, coordinate)
+                .await
+                .ok(),
+            None => None,
+        };
+
+        // newest event returned by each repo relay for each filter so far
+        let newest_per_filter: Mutex<NewestPerFilter> = Mutex::new(HashMap::new());
+        // with complete history requested each relay may hold older events
+        let skip_when_converged = self.fetch_max_events_per_kind.is_some();
+        let relay_fetches: Mutex<Vec<(RelayUrl, bool, Duration)>> = Mutex::new(vec![]);
+
         loop {
             let relays = request
                 .repo_relays
@@ -377,9 +497,12 @@ impl Connect for Client {
                     .context("failed to add relay")?;
             }
 
-            let dim = Style::new().color256(247);
+            let dim = Style::new().color256(247).for_stderr();
 
-            let futures: Vec<_> = relays
+            let ordered_relays =
+                order_by_reliability(git_repo_path, relays.iter().cloned().collect());
+
+            let futures: Vec<_> = ordered_relays
                 .iter()
                 .map(|r| {
                     if profile_relays_only.contains(r) {
@@ -416,6 +539,8 @@ impl Connect for Client {
                         .clone()
                         .context("fetch_all_from_relay called without a relay")?;
 
+                    let repo_relay = !profile_relays_only.contains(&&relay_url);
+
                     let pb = if std::env::var("NGITTEST").is_err() {
                         let pb = progress_reporter.add(
                             ProgressBar::new(1)
@@ -426,7 +551,8 @@ impl Connect for Client {
                                     ))
                                     .to_string(),
                                 )
-                                .with_style(pb_style()?),
+                                .with_style(pb_style(self.relay_timeout_secs)?)
+                                .with_finish(ProgressFinish::AndClear),
                         );
                         pb.enable_steady_tick(Duration::from_millis(300));
                         Some(pb)
@@ -434,8 +560,23 @@ impl Connect for Client {
                         None
                     };
 
+                    timeout::relay_asked();
+                    let started = Instant::now();
                     #[allow(clippy::large_futures)]
-                    match self.fetch_all_from_relay(git_repo_path, request, &pb).await {
+                    let result = self
+                        .fetch_all_from_relay(
+                            git_repo_path,
+                            request,
+                            (repo_relay && skip_when_converged).then_some(&newest_per_filter),
+                            &pb,
+                        )
+                        .await;
+                    // a relay that wasn't asked says nothing about its reliability
+                    let queried = result.as_ref().map_or(true, |res| res.relay_queried);
+                    if let (true, Ok(mut fetches)) = (queried, relay_fetches.lock()) {
+                        fetches.push((relay_url.clone(), result.is_ok(), started.elapsed()));
+                    }
+                    match result {
                         Err(error) => {
                             if let Some(pb) = pb {
                                 pb.set_style(pb_after_style(false));
@@ -454,13 +595,16 @@ impl Connect for Client {
                             }
                             Err(error)
                         }
-                        Ok(res) => Ok(res),
+                        Ok(res) => {
+                            timeout::relay_answered();
+                            Ok(res)
+                        }
                     }
                 })
                 .collect();
 
             for report in stream::iter(futures)
-                .buffer_unordered(15)
+                .buffer_unordered(self.max_concurrent_relays)
                 .collect::<Vec<Result<FetchReport>>>()
                 .await
             {
@@ -468,11 +612,16 @@ impl Connect for Client {
             }
             processed_relays.extend(relays.clone());
 
+            // relays added by a newer announcement are fetched from in the
+            // next pass of this loop
             if let Some(trusted_maintainer_coordinate) = trusted_maintainer_coordinate {
                 if let Ok(repo_ref) =
                     get_repo_ref_from_cache(git_repo_path, trusted_maintainer_coordinate).await
                 {
-                    request.repo_relays = repo_ref.relays.iter().cloned().collect();
+                    let (repo_relays, source) =
+                        get_repo_relays(git_repo_path, &repo_ref, &self.fallback_relays).await;
+                    repo_relays_source = source;
+                    request.repo_relays = repo_relays.into_iter().collect();
                 }
             }
 
@@ -495,6 +644,62 @@ impl Connect for Client {
                 set
             };
         }
+        print_repo_relays_notice(repo_relays_source);
+        if let (Some(announced_before), Some(trusted_maintainer_coordinate)) =
+            (&announced_before, trusted_maintainer_coordinate)
+        {
+            report_hosting_changes(
+                git_repo_path,
+                announced_before,
+                trusted_maintainer_coordinate,
+            )
+            .await;
+        }
+        if let Ok(fetches) = relay_fetches.lock() {
+            // failing to record only means relays aren't ordered by reliability
+            let _ = record_relay_fetches(git_repo_path, &fetches);
+        }
+        if let Some(git_repo_path) = git_repo_path {
+            // failing to record only means fewer useful relay hints when sharing
+            let _ = record_relays_that_returned_events(
+                git_repo_path,
+                relays_that_returned_shareable_events(&relay_reports),
+            );
+            // failing to record only means coverage reports miss these relays
+            let _ = record_event_sources(
+                git_repo_path,
+                &relays_that_returned_events(&relay_reports),
+                Timestamp::now(),
+            );
+        }
+        if let (Some(git_repo_path), Some(trusted_maintainer_coordinate), true) = (
+            git_repo_path,
+            trusted_maintainer_coordinate,
+            relay_reports.iter().any(Result::is_ok),
+        ) {
+            // failing to record only means the next `list` fetches again
+            let _ = record_repo_fetched(
+                git_repo_path,
+                trusted_maintainer_coordinate,
+                Timestamp::now(),
+            );
+        }
+        if relay_reports.iter().any(Result::is_ok) {
+            // failing to record only means these profiles are fetched again
+            let _ = record_profiles_fetched(
+                git_repo_path,
+                &request
+                    .missing_contributor_profiles
+                    .iter()
+                    .chain(request.profiles_to_fetch_from_user_relays.keys())
+                    .copied()
+                    .collect(),
+                Timestamp::now(),
+            );
+        }
+        if let Some(marker) = &mut in_flight_marker {
+            marker.complete();
+        }
         Ok((relay_reports, progress_reporter))
     }
 
@@ -502,6 +707,7 @@ impl Connect for Client {
         &self,
         git_repo_path: Option<&'a Path>,
         request: FetchRequest,
+        newest_per_filter: Option<&'a Mutex<NewestPerFilter>>,
         pb: &Option<ProgressBar>,
     ) -> Result<FetchReport> {
         let mut fresh_coordinates: HashSet<Coordinate> = HashSet::new();
@@ -530,13 +736,35 @@ impl Connect for Client {
 
         let relay_column_width = request.relay_column_width;
 
+        // other relays already agree on the newest event the filter returns
+        let converged = |filter: &nostr::Filter| {
+            newest_per_filter.is_some_and(|newest_per_filter| {
+                newest_per_filter.lock().is_ok_and(|newest_per_filter| {
+                    newest_per_filter
+                        .get(&filter.as_json())
+                        .is_some_and(|newest| results_converged(newest))
+                })
+            })
+        };
+        let filters = get_fetch_filters(&fresh_coordinates, &fresh_proposal_roots, &fresh_profiles);
+        if !filters.is_empty() && filters.iter().all(converged) {
+            report.relays_skipped.insert(relay_url);
+            return Ok(report);
+        }
+
         self.connect(&relay_url).await?;
 
-        let dim = Style::new().color256(247);
+        let limits = get_subscription_limits(&relay_url, git_repo_path, self.relay_proxy).await;
+
+        let dim = Style::new().color256(247).for_stderr();
 
         loop {
-            let filters =
+            let mut filters =
                 get_fetch_filters(&fresh_coordinates, &fresh_proposal_roots, &fresh_profiles);
+            filters.retain(|filter| !converged(filter));
+            if filters.is_empty() {
+                break;
+            }
 
             if let Some(pb) = &pb {
                 pb.set_prefix(
@@ -558,13 +786,45 @@ impl Connect for Client {
             fresh_profiles = HashSet::new();
 
             let relay = self.client.relay(&relay_url).await?;
-            let events: Vec<nostr::Event> = get_events_of(&relay, filters.clone(), &None)
-                .await?
+            report.relay_queried = true;
+            let fetched = get_events_of_paginated(
+                &relay,
+                filters.clone(),
+                limits,
+                self.relay_timeout_secs,
+                self.fetch_max_events_per_kind,
+            )
+            .await?;
+            if fetched.missing_eose {
+                report.relays_without_eose.insert(relay_url.clone());
+            }
+            report.capped_kinds.extend(fetched.capped_kinds);
+            let events: Vec<nostr::Event> = fetched
+                .events
                 .iter()
                 // don't process events that don't match filters
                 .filter(|e| filters.iter().any(|f| f.match_event(e)))
                 .cloned()
                 .collect();
+            report
+                .returned_shareable_events
+                .extend(events.iter().filter_map(relay_hints_key));
+            report
+                .returned_event_ids
+                .extend(events.iter().map(|e| e.id));
+            if let (Some(newest_per_filter), false) = (newest_per_filter, fetched.missing_eose) {
+                if let Ok(mut newest_per_filter) = newest_per_filter.lock() {
+                    for filter in &filters {
+                        newest_per_filter.entry(filter.as_json()).or_default().push(
+                            events
+                                .iter()
+                                .filter(|e| filter.match_event(e))
+                                .map(|e| e.created_at)
+                                .max(),
+                        );
+                    }
+                }
+            }
             // TODO: try reconcile
 
             process_fetched_events(
@@ -578,15 +838,22 @@ impl Connect for Client {
             )
             .await?;
 
-            if fresh_coordinates.is_empty()
-                && fresh_proposal_roots.is_empty()
-                && fresh_profiles.is_empty()
+            // waiting out another deadline for follow-up filters would only
+            // delay the other relays
+            if report.relays_without_eose.contains(&relay_url)
+                || (fresh_coordinates.is_empty()
+                    && fresh_proposal_roots.is_empty()
+                    && fresh_profiles.is_empty())
             {
                 break;
             }
         }
         if let Some(pb) = pb {
-            pb.set_style(pb_after_style(true));
+            if report.relays_without_eose.is_empty() {
+                pb.set_style(pb_after_style(true));
+            } else {
+                pb.set_style(pb_partial_style());
+            }
             pb.set_prefix(
                 dim.apply_to(format!(
                     "{: <relay_column_width$} {}",
@@ -599,20 +866,61 @@ impl Connect for Client {
                 ))
                 .to_string(),
             );
-            pb.finish_with_message("");
+            pb.finish_with_message(if report.relays_without_eose.is_empty() {
+                ""
+            } else {
+                "partial results (no EOSE)"
+            });
         }
+        report.relays_responded.insert(relay_url);
         Ok(report)
     }
 }
 
+fn relays_that_returned_shareable_events(
+    relay_reports: &[Result<FetchReport>],
+) -> HashMap<String, BTreeSet<String>> {
+    let mut returned: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for report in relay_reports.iter().flatten() {
+        for key in &report.returned_shareable_events {
+            returned.entry(key.clone()).or_default().extend(
+                report
+                    .relays_responded
+                    .iter()
+                    .map(|r| r.as_str_without_trailing_slash().to_string()),
+            );
+        }
+    }
+    returned
+}
+
+fn relays_that_returned_events(
+    relay_reports: &[Result<FetchReport>],
+) -> HashMap<EventId, BTreeSet<String>> {
+    let mut returned: HashMap<EventId, BTreeSet<String>> = HashMap::new();
+    for report in relay_reports.iter().flatten() {
+        for event_id in &report.returned_event_ids {
+            returned.entry(*event_id).or_default().extend(
+                report
+                    .relays_responded
+                    .iter()
+                    .map(|r| r.as_str_without_trailing_slash().to_string()),
+            );
+        }
+    }
+    returned
+}
+
 static CONNECTION_TIMEOUT: u64 = 3;
-static GET_EVENTS_TIMEOUT: u64 = 7;
+pub static GET_EVENTS_TIMEOUT: u64 = 7;
 
 async fn get_events_of(
     relay: &nostr_sdk::Relay,
     filters: Vec<nostr::Filter>,
+    limits: SubscriptionLimits,
+    timeout_secs: u64,
     pb: &Option<ProgressBar>,
-) -> Result<Vec<Event>> {
+) -> Result<FetchedEvents> {
     // relay.reconcile(filter, opts).await?;
 
     if !relay.is_connected() {
@@ -627,16 +935,255 @@ async fn get_events_of(
     } else if let Some(pb) = pb {
         pb.set_prefix(format!("connected  {}", relay.url()));
     }
-    let events = relay
-        .fetch_events(
-            filters,
-            // 20 is nostr_sdk default
-            std::time::Duration::from_secs(GET_EVENTS_TIMEOUT),
-            nostr_sdk::FilterOptions::ExitOnEOSE,
-        )
-        .await?
-        .to_vec();
-    Ok(events)
+    let filter_count = filters.len();
+    let requests = limits.split_filters(filters);
+    if requests.len() > 1 {
+        print_verbose(&format!(
+            "{} advertises max_filters {}: splitting {filter_count} filters into {} requests",
+            relay.url(),
+            limits.max_filters.unwrap_or_default(),
+            requests.len(),
+        ));
+    }
+    let mut fetched = FetchedEvents::default();
+    for filters in requests {
+        // some relays never send EOSE or stall mid subscription. events are
+        // collected as they arrive so that those received before the deadline,
+        // which fires before the sdk's own timeout, are kept
+        let mut events = relay
+            .stream_events(
+                filters,
+                Duration::from_secs(timeout_secs + 1),
+                nostr_sdk::FilterOptions::ExitOnEOSE,
+            )
+            .await?;
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+        loop {
+            match tokio::time::timeout_at(deadline, events.next()).await {
+                Ok(Some(event)) => fetched.events.push(event),
+                Ok(None) => break,
+                Err(_) => {
+                    print_verbose(&format!(
+                        "{} sent no EOSE within {timeout_secs}s: using partial results",
+                        relay.url(),
+                    ));
+                    fetched.missing_eose = true;
+                    break;
+                }
+            }
+        }
+    }
+    Ok(fetched)
+}
+
+#[derive(Default)]
+struct FetchedEvents {
+    events: Vec<Event>,
+    /// a subscription reached its deadline before the relay sent EOSE
+    missing_eose: bool,
+    /// kinds where fetching stopped at the maximum events per kind
+    capped_kinds: HashSet<Kind>,
+}
+
+/// events requested per page for kinds that can grow without bound. some
+/// relays silently cap responses so asking for everything at once returns an
+/// arbitrary subset
+pub static FETCH_PAGE_SIZE: usize = 500;
+
+/// events fetched per kind from each relay unless `--fetch-all` is used
+pub static DEFAULT_FETCH_MAX_EVENTS_PER_KIND: usize = 5000;
+
+/// relays fetched from at once unless `nostr.max-concurrent-relays` is set
+pub static DEFAULT_MAX_CONCURRENT_RELAYS: usize = 4;
+
+/// repo relays that must agree on the newest event before the rest are
+/// skipped
+pub static CONVERGED_RELAY_COUNT: usize = 3;
+
+/// newest event each repo relay returned for a filter, or none when it returned
+/// nothing, keyed by the filter's json
+pub type NewestPerFilter = HashMap<String, Vec<Option<Timestamp>>>;
+
+/// whether [`CONVERGED_RELAY_COUNT`] relays returned the same newest event
+/// for a filter that any relay returned, or all returned nothing
+fn results_converged(newest_created_at: &[Option<Timestamp>]) -> bool {
+    newest_created_at.iter().max().is_some_and(|newest| {
+        newest_created_at.iter().filter(|t| t.eq(&newest)).count() >= CONVERGED_RELAY_COUNT
+    })
+}
+
+fn paginated_kinds() -> Vec<Kind> {
+    [
+        vec![
+            Kind::GitPatch,
+            Kind::GitIssue,
+            Kind::EventDeletion,
+            PROPOSAL_EDIT_KIND,
+            Kind::Label,
+        ],
+        status_kinds(),
+        comment_kinds(),
+    ]
+    .concat()
+}
+
+/// one limited filter per kind for each filter of paginated kinds, and the
+/// remaining filters unchanged
+fn split_paginated_filters(
+    filters: Vec<nostr::Filter>,
+    page_size: usize,
+) -> (Vec<nostr::Filter>, Vec<nostr::Filter>) {
+    let mut paginated = vec![];
+    let mut other = vec![];
+    for filter in filters {
+        match &filter.kinds {
+            Some(kinds)
+                if filter.ids.is_none()
+                    && filter.limit.is_none()
+                    && !kinds.is_empty()
+                    && kinds.iter().all(|k| paginated_kinds().contains(k)) =>
+            {
+                for kind in kinds {
+                    let mut page = filter.clone().limit(page_size);
+                    page.kinds = Some(BTreeSet::from([*kind]));
+                    paginated.push(page);
+                }
+            }
+            _ => other.push(filter),
+        }
+    }
+    (paginated, other)
+}
+
+/// fetch `filters`, walking back through paginated kinds with `until` until
+/// a page comes back short or `max_events_per_kind` is reached
+async fn get_events_of_paginated(
+    relay: &nostr_sdk::Relay,
+    filters: Vec<nostr::Filter>,
+    limits: SubscriptionLimits,
+    timeout_secs: u64,
+    max_events_per_kind: Option<usize>,
+) -> Result<FetchedEvents> {
+    let page_size = [Some(FETCH_PAGE_SIZE), limits.max_limit, max_events_per_kind]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(FETCH_PAGE_SIZE);
+    let (mut pages, other) = split_paginated_filters(filters, page_size);
+    let mut fetched = get_events_of(
+        relay,
+        [other, pages.clone()].concat(),
+        limits,
+        timeout_secs,
+        &None,
+    )
+    .await?;
+    let mut seen = fetched
+        .events
+        .iter()
+        .map(|e| e.id)
+        .collect::<HashSet<EventId>>();
+    let mut round = fetched.events.clone();
+    let mut fetched_by_kind: HashMap<Kind, HashSet<EventId>> = HashMap::new();
+    loop {
+        let next_pages = next_pages(
+            &pages,
+            &round,
+            &mut fetched_by_kind,
+            page_size,
+            max_events_per_kind,
+            &mut fetched.capped_kinds,
+        );
+        for page in &next_pages {
+            if let (Some(kind), Some(until)) =
+                (page.kinds.as_ref().and_then(|k| k.first()), page.until)
+            {
+                print_verbose(&format!(
+                    "{} returned a full page of kind {kind}: fetching events up to {until}",
+                    relay.url(),
+                ));
+            }
+        }
+        if next_pages.is_empty() || fetched.missing_eose {
+            break;
+        }
+        let next = get_events_of(relay, next_pages.clone(), limits, timeout_secs, &None).await?;
+        fetched.missing_eose = next.missing_eose;
+        round = next.events;
+        for event in &round {
+            if seen.insert(event.id) {
+                fetched.events.push(event.clone());
+            }
+        }
+        pages = next_pages;
+    }
+    Ok(fetched)
+}
+
+/// the page before each of `pages` that came back full in `round`, recording
+/// the events returned against their kind in `fetched_by_kind`. `until` is
+/// inclusive so a full page with nothing new, eg. when more than a page of
+/// events share a timestamp, steps back past that timestamp
+fn next_pages(
+    pages: &[nostr::Filter],
+    round: &[Event],
+    fetched_by_kind: &mut HashMap<Kind, HashSet<EventId>>,
+    page_size: usize,
+    max_events_per_kind: Option<usize>,
+    capped_kinds: &mut HashSet<Kind>,
+) -> Vec<nostr::Filter> {
+    let fetched_before_round = fetched_by_kind.clone();
+    let mut next_pages = vec![];
+    for page in pages {
+        let Some(kind) = page.kinds.as_ref().and_then(|k| k.first()) else {
+            continue;
+        };
+        let page_events = round
+            .iter()
+            .filter(|e| page.match_event(e))
+            .collect::<Vec<&Event>>();
+        let is_new = |e: &&&Event| {
+            !fetched_before_round
+                .get(kind)
+                .is_some_and(|ids| ids.contains(&e.id))
+        };
+        let new = page_events.iter().filter(is_new).count();
+        let fetched = fetched_by_kind.entry(*kind).or_default();
+        fetched.extend(page_events.iter().map(|e| e.id));
+        if page_events.len() < page_size {
+            continue;
+        }
+        if max_events_per_kind.is_some_and(|max| fetched.len() >= max) {
+            capped_kinds.insert(*kind);
+            continue;
+        }
+        let Some(oldest) = page_events.iter().map(|e| e.created_at).min() else {
+            continue;
+        };
+        let until = if new > 0 {
+            oldest
+        } else if let Some(before) = oldest.as_u64().checked_sub(1) {
+            Timestamp::from(before)
+        } else {
+            continue;
+        };
+        next_pages.push(page.clone().until(until));
+    }
+    next_pages
+}
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// print extra detail about relay interactions with [`print_verbose`]
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+/// printed to stderr only when verbose output is enabled with [`set_verbose`]
+pub fn print_verbose(message: &str) {
+    if VERBOSE.load(Ordering::Relaxed) {
+        let _ = console::Term::stderr().write_line(message);
+    }
 }
 
 #[derive(Default)]
@@ -646,6 +1193,34 @@ pub struct Params {
     pub more_fallback_relays: Vec<String>,
     pub blaster_relays: Vec<String>,
     pub fallback_signer_relays: Vec<String>,
+    pub relay_timeout_secs: Option<u64>,
+    pub profile_cache_ttl_secs: Option<u64>,
+    pub relay_proxy: Option<SocketAddr>,
+    /// None fetches complete history
+    pub fetch_max_events_per_kind: Option<usize>,
+    pub max_concurrent_relays: Option<usize>,
+}
+
+impl Params {
+    /// default relays with user configuration applied
+    pub fn with_config(config: &Config) -> Self {
+        Params {
+            keys: None,
+            fallback_relays: config.fallback_relays.value.clone(),
+            more_fallback_relays: default_more_fallback_relays(),
+            blaster_relays: if config.use_blaster.value {
+                default_blaster_relays()
+            } else {
+                vec![]
+            },
+            fallback_signer_relays: default_fallback_signer_relays(),
+            relay_timeout_secs: Some(config.relay_timeout_secs.value),
+            profile_cache_ttl_secs: Some(config.profile_cache_ttl_secs.value),
+            relay_proxy: config.relay_proxy.value,
+            fetch_max_events_per_kind: config.fetch_max_events_per_kind.value,
+            max_concurrent_relays: Some(config.max_concurrent_relays.value),
+        }
+    }
 }
 
 fn get_dedup_events(relay_results: Vec<Result<Vec<nostr::Event>>>) -> Vec<Event> {
@@ -663,6 +1238,28 @@ fn get_dedup_events(relay_results: Vec<Result<Vec<nostr::Event>>>) -> Vec<Event>
 pub async fn sign_event(
     event_builder: EventBuilder,
     signer: &Arc<dyn NostrSigner>,
+) -> Result<nostr::Event> {
+    let event_builder = if TIMESTAMP_OFFSET_SECS.load(Ordering::Relaxed) == 0 {
+        event_builder
+    } else {
+        event_builder.custom_created_at(timestamp_now())
+    };
+    sign_event_builder(event_builder, signer).await
+}
+
+/// sign with `created_at` rather than the current time, eg. so a replaceable
+/// event supersedes one published moments earlier
+pub async fn sign_event_at(
+    event_builder: EventBuilder,
+    created_at: Timestamp,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<nostr::Event> {
+    sign_event_builder(event_builder.custom_created_at(created_at), signer).await
+}
+
+async fn sign_event_builder(
+    event_builder: EventBuilder,
+    signer: &Arc<dyn NostrSigner>,
 ) -> Result<nostr::Event> {
     if signer.backend() == SignerBackend::NostrConnect {
         let term = console::Term::stderr();
@@ -681,53 +1278,255 @@ pub async fn sign_event(
     }
 }
 
-pub async fn fetch_public_key(signer: &Arc<dyn NostrSigner>) -> Result<nostr::PublicKey> {
-    if signer.backend() == SignerBackend::NostrConnect {
-        let term = console::Term::stderr();
-        term.write_line("fetching npub from remote signer...")?;
-        let public_key = signer
-            .get_public_key()
-            .await
-            .context("failed to get npub from remote signer")?;
-        term.clear_last_lines(1)?;
-        Ok(public_key)
+/// relays commonly reject events with a created_at more than a few minutes
+/// away from their own clock
+pub static CLOCK_SKEW_THRESHOLD_SECS: u64 = 300;
+
+static TIMESTAMP_OFFSET_SECS: AtomicI64 = AtomicI64::new(0);
+
+/// adjust the created_at of all subsequently signed events by `secs`
+pub fn set_timestamp_offset(secs: i64) {
+    TIMESTAMP_OFFSET_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// local time adjusted by any offset set with [`set_timestamp_offset`]
+pub fn timestamp_now() -> Timestamp {
+    Timestamp::from(
+        Timestamp::now()
+            .as_u64()
+            .saturating_add_signed(TIMESTAMP_OFFSET_SECS.load(Ordering::Relaxed)),
+    )
+}
+
+/// relay time inferred from the newest event of the second most recently
+/// active author so a single author with a fast clock doesn't skew the result.
+/// this is only a lower bound
+pub fn relay_inferred_now(
+    newest_event_by_author: &HashMap<PublicKey, Timestamp>,
+) -> Option<Timestamp> {
+    let mut newest: Vec<&Timestamp> = newest_event_by_author.values().collect();
+    newest.sort_unstable_by(|a, b| b.cmp(a));
+    newest.get(1).map(|t| **t)
+}
+
+/// returns how many seconds the local clock is behind relays, if this is more
+/// than [`CLOCK_SKEW_THRESHOLD_SECS`].
+///
+/// as [`relay_inferred_now`] is only a lower bound a local clock running fast
+/// cannot be detected this way; relays rejecting our events as dated in the
+/// future is the signal for that. see [`correct_clock_ahead_after_rejection`]
+pub fn detect_clock_skew(
+    local_now: Timestamp,
+    newest_event_by_author: &HashMap<PublicKey, Timestamp>,
+) -> Option<u64> {
+    let behind = relay_inferred_now(newest_event_by_author)?
+        .as_u64()
+        .saturating_sub(local_now.as_u64());
+    if behind > CLOCK_SKEW_THRESHOLD_SECS {
+        Some(behind)
     } else {
-        signer
-            .get_public_key()
-            .await
-            .context("failed to get public key from local keys")
+        None
     }
 }
 
-fn pb_style() -> Result<ProgressStyle> {
-    Ok(
-        ProgressStyle::with_template(" {spinner} {prefix} {msg} {timeout_in}")?.with_key(
-            "timeout_in",
-            |state: &ProgressState, w: &mut dyn Write| {
-                if state.elapsed().as_secs() > 3 && state.elapsed().as_secs() < GET_EVENTS_TIMEOUT {
-                    let dim = Style::new().color256(247);
-                    write!(
-                        w,
-                        "{}",
-                        dim.apply_to(format!(
-                            "timeout in {:.1}s",
-                            GET_EVENTS_TIMEOUT - state.elapsed().as_secs()
-                        ))
-                    )
-                    .unwrap();
-                }
-            },
-        ),
-    )
+/// most that timestamps are moved back after relays reject events as dated in
+/// the future. [`relay_inferred_now`] is only a lower bound and may be days old
+/// in a quiet repository, and backdating by that much would make replaceable
+/// events lose to the ones they replace
+pub static MAX_CLOCK_AHEAD_CORRECTION_SECS: u64 = 3600;
+
+/// how many seconds to move timestamps back when the local clock is ahead of
+/// the relay-inferred time by more than [`CLOCK_SKEW_THRESHOLD_SECS`], capped
+/// at [`MAX_CLOCK_AHEAD_CORRECTION_SECS`]
+fn detect_clock_ahead(local_now: Timestamp, relay_now: Timestamp) -> Option<u64> {
+    let ahead = local_now.as_u64().saturating_sub(relay_now.as_u64());
+    if ahead > CLOCK_SKEW_THRESHOLD_SECS {
+        Some(ahead.min(MAX_CLOCK_AHEAD_CORRECTION_SECS))
+    } else {
+        None
+    }
 }
 
-fn pb_after_style(succeed: bool) -> indicatif::ProgressStyle {
-    ProgressStyle::with_template(
-        format!(
-            " {} {}",
-            if succeed {
-                console::style("✔".to_string())
-                    .for_stderr()
+/// whether a relay rejection message refers to the event's created_at
+pub fn is_created_at_rejection(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["created_at", "timestamp", "future", "too old"]
+        .iter()
+        .any(|s| message.contains(s))
+}
+
+/// whether a relay rejected the event for being dated in the future
+pub fn is_future_created_at_rejection(message: &str) -> bool {
+    let lowercase = message.to_lowercase();
+    is_created_at_rejection(message)
+        && ["future", "too late", "ahead"]
+            .iter()
+            .any(|s| lowercase.contains(s))
+}
+
+static FIX_TIMESTAMP: AtomicBool = AtomicBool::new(false);
+
+/// relay-inferred time when the last fetch completed, 0 if unknown
+static RELAY_INFERRED_NOW: AtomicU64 = AtomicU64::new(0);
+
+static FUTURE_CREATED_AT_REJECTION: AtomicBool = AtomicBool::new(false);
+
+/// warn if the fetch suggests the local clock is behind relays and, when
+/// `fix_timestamp` is set, sign subsequent events with the relay-inferred time
+pub fn warn_on_clock_skew(report: &FetchReport, fix_timestamp: bool) -> Result<()> {
+    FIX_TIMESTAMP.store(fix_timestamp, Ordering::Relaxed);
+    RELAY_INFERRED_NOW.store(
+        relay_inferred_now(&report.newest_event_by_author).map_or(0, Timestamp::as_u64),
+        Ordering::Relaxed,
+    );
+    let Some(behind) = detect_clock_skew(Timestamp::now(), &report.newest_event_by_author) else {
+        return Ok(());
+    };
+    let term = console::Term::stderr();
+    term.write_line(
+        &console::style(format!(
+            "WARNING: your system clock appears to be {} behind nostr relays. relays may reject or hide events you publish",
+            format_duration(behind),
+        ))
+        .for_stderr()
+        .yellow()
+        .bold()
+        .to_string(),
+    )?;
+    if fix_timestamp {
+        #[allow(clippy::cast_possible_wrap)]
+        set_timestamp_offset(behind as i64);
+        term.write_line("publishing events using relay-inferred time")?;
+    } else {
+        term.write_line(
+            "correct your system clock or use --fix-timestamp (or git config nostr.fix-timestamp true for git push) to publish events using relay-inferred time",
+        )?;
+    }
+    Ok(())
+}
+
+/// after relays rejected `events` as dated in the future, revealing a local
+/// clock that is ahead, sign subsequent events with the relay-inferred time
+/// when fix-timestamp is enabled. returns whether the events should be signed
+/// and sent again, which is only once and only if no relay accepted them so
+/// they are never published twice
+pub fn correct_clock_ahead_after_rejection(
+    accepted: &HashMap<String, HashSet<EventId>>,
+    events: &[Event],
+) -> Result<bool> {
+    if !FUTURE_CREATED_AT_REJECTION.swap(false, Ordering::Relaxed)
+        || !FIX_TIMESTAMP.load(Ordering::Relaxed)
+        || TIMESTAMP_OFFSET_SECS.load(Ordering::Relaxed) != 0
+        || events
+            .iter()
+            .any(|event| event_reached_any_relay(accepted, &event.id, &[]))
+    {
+        return Ok(false);
+    }
+    let relay_now = RELAY_INFERRED_NOW.load(Ordering::Relaxed);
+    let Some(correction) = (relay_now > 0)
+        .then(|| detect_clock_ahead(Timestamp::now(), Timestamp::from(relay_now)))
+        .flatten()
+    else {
+        return Ok(false);
+    };
+    #[allow(clippy::cast_possible_wrap)]
+    set_timestamp_offset(-(correction as i64));
+    console::Term::stderr().write_line(&format!(
+        "your system clock appears to be ahead of nostr relays. publishing again with timestamps {} earlier",
+        format_duration(correction),
+    ))?;
+    Ok(true)
+}
+
+fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else {
+        format!("{}m", secs / 60)
+    }
+}
+
+pub async fn fetch_public_key(signer: &Arc<dyn NostrSigner>) -> Result<nostr::PublicKey> {
+    if signer.backend() == SignerBackend::NostrConnect {
+        let term = console::Term::stderr();
+        term.write_line("fetching npub from remote signer...")?;
+        let public_key = signer
+            .get_public_key()
+            .await
+            .context("failed to get npub from remote signer")?;
+        term.clear_last_lines(1)?;
+        Ok(public_key)
+    } else {
+        signer
+            .get_public_key()
+            .await
+            .context("failed to get public key from local keys")
+    }
+}
+
+/// long running steps show how long they have been running after this
+static SHOW_ELAPSED_AFTER_SECS: u64 = 5;
+
+fn write_elapsed_when_slow(state: &ProgressState, w: &mut dyn Write) {
+    if state.elapsed().as_secs() >= SHOW_ELAPSED_AFTER_SECS {
+        let dim = Style::new().color256(247).for_stderr();
+        write!(
+            w,
+            "{}",
+            dim.apply_to(format!("{}s", state.elapsed().as_secs()))
+        )
+        .unwrap();
+    }
+}
+
+fn pb_style(timeout_secs: u64) -> Result<ProgressStyle> {
+    Ok(
+        ProgressStyle::with_template(" {spinner} {prefix} {msg} {elapsed_when_slow} {timeout_in}")?
+            .with_key("elapsed_when_slow", write_elapsed_when_slow)
+            .with_key(
+                "timeout_in",
+                move |state: &ProgressState, w: &mut dyn Write| {
+                    if state.elapsed().as_secs() > 3 && state.elapsed().as_secs() < timeout_secs {
+                        let dim = Style::new().color256(247).for_stderr();
+                        write!(
+                            w,
+                            "{}",
+                            dim.apply_to(format!(
+                                "timeout in {:.1}s",
+                                timeout_secs - state.elapsed().as_secs()
+                            ))
+                        )
+                        .unwrap();
+                    }
+                },
+            ),
+    )
+}
+
+/// a relay that responded but whose results may be incomplete
+fn pb_partial_style() -> indicatif::ProgressStyle {
+    ProgressStyle::with_template(
+        format!(
+            " {} {}",
+            console::style("⚠".to_string())
+                .for_stderr()
+                .yellow()
+                .to_string(),
+            "{prefix} {msg}",
+        )
+        .as_str(),
+    )
+    .unwrap()
+}
+
+fn pb_after_style(succeed: bool) -> indicatif::ProgressStyle {
+    ProgressStyle::with_template(
+        format!(
+            " {} {}",
+            if succeed {
+                console::style("✔".to_string())
+                    .for_stderr()
                     .green()
                     .to_string()
             } else {
@@ -743,29 +1542,180 @@ fn pb_after_style(succeed: bool) -> indicatif::ProgressStyle {
     .unwrap()
 }
 
+/// .git/nostr-cache.lmdb unless `nostr.cache-dir` is set
+pub fn get_local_cache_path(git_repo_path: &Path) -> PathBuf {
+    if let Some(dir) = get_cache_dir_override(git_repo_path) {
+        dir.join(cache_file_name(git_repo_path))
+    } else {
+        get_default_local_cache_path(git_repo_path)
+    }
+}
+
+/// how long a process waits for a full fetch started by another process, eg.
+/// `git pull` during `ngit list`, to complete rather than fetching itself.
+/// markers left by a crashed process are ignored after this too
+pub static FETCH_IN_FLIGHT_SECS: u64 = 30;
+
+fn get_fetch_in_flight_marker_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr/fetch-in-flight")
+}
+
+#[derive(Debug, PartialEq)]
+enum FetchByAnotherProcess {
+    InFlight,
+    CompletedAt(u64),
+}
+
+/// full fetch recorded by another process's marker, unless it started over
+/// [`FETCH_IN_FLIGHT_SECS`] ago. the marker is `<pid> <unix timestamp>` while
+/// in flight, with the completion timestamp appended once complete
+fn parse_fetch_by_another_process(
+    marker: &str,
+    pid: u32,
+    now: u64,
+) -> Option<FetchByAnotherProcess> {
+    let mut parts = marker.split_whitespace();
+    if parts.next()?.parse::<u32>().ok()? == pid {
+        return None;
+    }
+    // a marker from the future is treated as stale
+    let ago = now.checked_sub(parts.next()?.parse::<u64>().ok()?)?;
+    if ago >= FETCH_IN_FLIGHT_SECS {
+        return None;
+    }
+    Some(match parts.next() {
+        Some(completed_at) => FetchByAnotherProcess::CompletedAt(completed_at.parse().ok()?),
+        None => FetchByAnotherProcess::InFlight,
+    })
+}
+
+fn read_fetch_by_another_process(git_repo_path: &Path) -> Option<FetchByAnotherProcess> {
+    parse_fetch_by_another_process(
+        &std::fs::read_to_string(get_fetch_in_flight_marker_path(git_repo_path)).ok()?,
+        std::process::id(),
+        Timestamp::now().as_u64(),
+    )
+}
+
+/// waits for a full fetch that another process has in flight and returns how
+/// many seconds ago it completed. None when there isn't one or it is
+/// cancelled, crashes or doesn't complete within [`FETCH_IN_FLIGHT_SECS`]. a
+/// fetch that completed before this process looked isn't reused as events
+/// may have been published since
+async fn wait_for_fetch_by_another_process(git_repo_path: &Path) -> Option<u64> {
+    if read_fetch_by_another_process(git_repo_path)? != FetchByAnotherProcess::InFlight {
+        return None;
+    }
+    loop {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        if let FetchByAnotherProcess::CompletedAt(completed_at) =
+            read_fetch_by_another_process(git_repo_path)?
+        {
+            return Some(Timestamp::now().as_u64().saturating_sub(completed_at));
+        }
+    }
+}
+
+fn write_fetch_in_flight_marker(
+    git_repo_path: &Path,
+    started_at: u64,
+    completed_at: Option<u64>,
+) -> Result<()> {
+    let path = get_fetch_in_flight_marker_path(git_repo_path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create .git/nostr directory")?;
+    }
+    let tmp_path = path.with_extension(std::process::id().to_string());
+    std::fs::write(
+        &tmp_path,
+        if let Some(completed_at) = completed_at {
+            format!("{} {started_at} {completed_at}\n", std::process::id())
+        } else {
+            format!("{} {started_at}\n", std::process::id())
+        },
+    )
+    .context("failed to write fetch in-flight marker")?;
+    std::fs::rename(tmp_path, path).context("failed to write fetch in-flight marker")
+}
+
+/// removes this process's fetch in-flight marker when dropped before the
+/// fetch completes, eg. when cancelled with ctrl-c, so other processes don't
+/// rely on a partially updated cache
+struct FetchInFlightMarker<'a> {
+    git_repo_path: &'a Path,
+    started_at: u64,
+    completed: bool,
+}
+
+impl FetchInFlightMarker<'_> {
+    /// record completion so processes waiting on this fetch use the cache
+    fn complete(&mut self) {
+        // failing to only means waiting processes fetch too
+        if write_fetch_in_flight_marker(
+            self.git_repo_path,
+            self.started_at,
+            Some(Timestamp::now().as_u64()),
+        )
+        .is_ok()
+        {
+            self.completed = true;
+        }
+    }
+}
+
+impl Drop for FetchInFlightMarker<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let path = get_fetch_in_flight_marker_path(self.git_repo_path);
+        if std::fs::read_to_string(&path)
+            .is_ok_and(|marker| marker.starts_with(&format!("{} ", std::process::id())))
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 async fn get_local_cache_database(git_repo_path: &Path) -> Result<NostrLMDB> {
-    NostrLMDB::open(git_repo_path.join(".git/nostr-cache.lmdb"))
-        .context("failed to open or create nostr cache database at .git/nostr-cache.lmdb")
+    let path = get_local_cache_path(git_repo_path);
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).context(format!(
+            "failed to create cache directory {}",
+            dir.display()
+        ))?;
+    }
+    NostrLMDB::open(&path).context(format!(
+        "failed to open or create nostr cache database at {}",
+        path.display()
+    ))
 }
 
-async fn get_global_cache_database(git_repo_path: Option<&Path>) -> Result<NostrLMDB> {
-    let path = if std::env::var("NGITTEST").is_ok() {
+pub fn get_global_cache_path(git_repo_path: Option<&Path>) -> Result<PathBuf> {
+    Ok(if std::env::var("NGITTEST").is_ok() {
         if let Some(git_repo_path) = git_repo_path {
-            git_repo_path.join(".git/test-global-cache.lmdb")
+            get_git_dir(git_repo_path).join("test-global-cache.lmdb")
         } else {
             bail!("git_repo must be supplied to get_global_cache_database during integration tests")
         }
     } else {
+        get_dirs()?.cache_dir().join("nostr-cache.lmdb")
+    })
+}
+
+async fn get_global_cache_database(git_repo_path: Option<&Path>) -> Result<NostrLMDB> {
+    let path = get_global_cache_path(git_repo_path)?;
+    if std::env::var("NGITTEST").is_err() {
         create_dir_all(get_dirs()?.cache_dir()).context(format!(
             "failed to create cache directory in: {:?}",
             get_dirs()?.cache_dir()
         ))?;
-        get_dirs()?.cache_dir().join("nostr-cache.lmdb")
-    };
+    }
 
     NostrLMDB::open(path).context("failed to open ngit global nostr cache database")
 }
 
+/// events are verified before they are cached so those returned can be trusted
 pub async fn get_events_from_local_cache(
     git_repo_path: &Path,
     filters: Vec<nostr::Filter>,
@@ -774,9 +1724,7 @@ pub async fn get_events_from_local_cache(
         .await?
         .query(filters.clone())
         .await
-        .context(
-            "failed to execute query on opened git repo nostr cache database .git/nostr-cache.lmdb",
-        )?
+        .context("failed to execute query on opened git repo nostr cache database")?
         .to_vec())
 }
 
@@ -792,7 +1740,13 @@ pub async fn get_event_from_global_cache(
         .to_vec())
 }
 
+/// the id and signature are verified here so cached events don't need to be
+/// verified again when read back
 pub async fn save_event_in_local_cache(git_repo_path: &Path, event: &nostr::Event) -> Result<bool> {
+    event.verify().context(format!(
+        "refusing to cache event {} with invalid signature",
+        event.id
+    ))?;
     get_local_cache_database(git_repo_path)
         .await?
         .save_event(event)
@@ -800,10 +1754,25 @@ pub async fn save_event_in_local_cache(git_repo_path: &Path, event: &nostr::Even
         .context("failed to save event in local cache")
 }
 
+pub async fn delete_events_from_local_cache(
+    git_repo_path: &Path,
+    event_ids: Vec<EventId>,
+) -> Result<()> {
+    get_local_cache_database(git_repo_path)
+        .await?
+        .delete(nostr::Filter::default().ids(event_ids))
+        .await
+        .context("failed to delete events from local cache")
+}
+
 pub async fn save_event_in_global_cache(
     git_repo_path: Option<&Path>,
     event: &nostr::Event,
 ) -> Result<bool> {
+    event.verify().context(format!(
+        "refusing to cache event {} with invalid signature",
+        event.id
+    ))?;
     get_global_cache_database(git_repo_path)
         .await?
         .save_event(event)
@@ -859,7 +1828,8 @@ pub async fn get_repo_ref_from_cache(
     let repo_ref = RepoRef::try_from((
         repo_events
             .first()
-            .context("no repo announcement event found at specified coordinates. if you are the repository maintainer consider running `ngit init` to create one")?
+            .context("no repo announcement event found at specified coordinates. if you are the repository maintainer consider running `ngit init` to create one")
+            .category(NgitError::NotFound)?
             .clone(),
         Some(repo_coordinate.public_key),
     ))?;
@@ -888,6 +1858,21 @@ pub async fn get_repo_ref_from_cache(
     })
 }
 
+/// `get_repo_ref_from_cache` after fetching. when no relay could be reached a
+/// missing announcement is a network error rather than not found
+pub async fn get_repo_ref_from_cache_after_fetch(
+    git_repo_path: Option<&Path>,
+    repo_coordinate: &Coordinate,
+    report: &FetchReport,
+) -> Result<RepoRef> {
+    let repo_ref = get_repo_ref_from_cache(git_repo_path, repo_coordinate).await;
+    if report.relays_unreachable {
+        repo_ref.category(NgitError::Network)
+    } else {
+        repo_ref
+    }
+}
+
 pub async fn get_state_from_cache(
     git_repo_path: Option<&Path>,
     repo_ref: &RepoRef,
@@ -915,6 +1900,7 @@ async fn create_relays_request(
     trusted_maintainer_coordinate: Option<&Coordinate>,
     user_profiles: &HashSet<PublicKey>,
     fallback_relays: HashSet<RelayUrl>,
+    profile_cache_ttl_secs: u64,
 ) -> Result<FetchRequest> {
     let repo_ref = if let Some(trusted_maintainer_coordinate) = trusted_maintainer_coordinate {
         if let Ok(repo_ref) =
@@ -966,6 +1952,8 @@ async fn create_relays_request(
     let mut contributors: HashSet<PublicKey> = HashSet::new();
 
     if !repo_coordinates_without_relays.is_empty() {
+        // proposal authors' profiles are fetched when their names are shown,
+        // see `get_names_for_display`
         if let Some(repo_ref) = &repo_ref {
             for m in &repo_ref.maintainers {
                 contributors.insert(m.to_owned());
@@ -988,7 +1976,6 @@ async fn create_relays_request(
             {
                 if event_is_patch_set_root(event) || event_is_revision_root(event) {
                     proposals.insert(event.id);
-                    contributors.insert(event.pubkey);
                 }
             }
         }
@@ -998,6 +1985,13 @@ async fn create_relays_request(
                 contributors.clone(),
             )])
             .await?;
+        // profiles cached by any repository are reused until they are stale
+        let stale_profiles = get_stale_profiles(
+            git_repo_path,
+            &contributors,
+            profile_cache_ttl_secs,
+            Timestamp::now(),
+        );
         for c in &contributors {
             if let Some(event) = profile_events
                 .iter()
@@ -1006,6 +2000,9 @@ async fn create_relays_request(
                 if let Some(git_repo_path) = git_repo_path {
                     save_event_in_local_cache(git_repo_path, event).await?;
                 }
+                if stale_profiles.contains(c) {
+                    missing_contributor_profiles.insert(c.to_owned());
+                }
             } else {
                 missing_contributor_profiles.insert(c.to_owned());
             }
@@ -1085,9 +2082,19 @@ async fn create_relays_request(
     };
 
     let relays = {
-        let mut relays = fallback_relays;
+        let mut relays = fallback_relays.clone();
         if let Some(repo_ref) = &repo_ref {
-            for r in repo_ref.relays.clone() {
+            for r in get_repo_relays(
+                git_repo_path,
+                repo_ref,
+                &fallback_relays
+                    .iter()
+                    .map(std::string::ToString::to_string)
+                    .collect::<Vec<String>>(),
+            )
+            .await
+            .0
+            {
                 relays.insert(r);
             }
         }
@@ -1159,6 +2166,13 @@ async fn process_fetched_events(
     report: &mut FetchReport,
 ) -> Result<()> {
     for event in &events {
+        let newest = report
+            .newest_event_by_author
+            .entry(event.pubkey)
+            .or_insert(event.created_at);
+        if event.created_at.gt(newest) {
+            *newest = event.created_at;
+        }
         if !request.existing_events.contains(&event.id) {
             if let Some(git_repo_path) = git_repo_path {
                 save_event_in_local_cache(git_repo_path, event).await?;
@@ -1246,11 +2260,10 @@ async fn process_fetched_events(
                 }
             } else if event_is_patch_set_root(event) {
                 fresh_proposal_roots.insert(event.id);
-                report.proposals.insert(event.id);
-                if !request.contributors.contains(&event.pubkey)
-                    && !fresh_profiles.contains(&event.pubkey)
-                {
-                    fresh_profiles.insert(event.pubkey);
+                if event_is_revision_root(event) {
+                    report.revisions.insert(event.id);
+                } else {
+                    report.proposals.insert(event.id);
                 }
             } else if [Kind::RelayList, Kind::Metadata].contains(&event.kind) {
                 if request.missing_contributor_profiles.contains(&event.pubkey) {
@@ -1271,11 +2284,17 @@ async fn process_fetched_events(
         }
     }
     for event in &events {
-        if !request.existing_events.contains(&event.id)
-            && !event
-                .tags
-                .event_ids()
-                .any(|id| report.proposals.contains(id))
+        if request.existing_events.contains(&event.id) {
+            continue;
+        }
+        if comment_kinds().contains(&event.kind) {
+            report.comments.insert(event.id);
+        } else if event.kind.eq(&Kind::GitIssue) {
+            report.issues.insert(event.id);
+        } else if !event
+            .tags
+            .event_ids()
+            .any(|id| report.proposals.contains(id) || report.revisions.contains(id))
         {
             if event.kind.eq(&Kind::GitPatch) && !event_is_patch_set_root(event) {
                 report.commits.insert(event.id);
@@ -1288,7 +2307,11 @@ async fn process_fetched_events(
 }
 
 pub fn consolidate_fetch_reports(reports: Vec<Result<FetchReport>>) -> FetchReport {
-    let mut report = FetchReport::default();
+    let mut report = FetchReport {
+        relays_unreachable: !reports.is_empty() && reports.iter().all(Result::is_err),
+        fetch_attempted: !reports.is_empty(),
+        ..FetchReport::default()
+    };
     for relay_report in reports.into_iter().flatten() {
         for c in relay_report.repo_coordinates_without_relays {
             if !report
@@ -1327,9 +2350,18 @@ pub fn consolidate_fetch_reports(reports: Vec<Result<FetchReport>>) -> FetchRepo
         for c in relay_report.proposals {
             report.proposals.insert(c);
         }
+        for c in relay_report.revisions {
+            report.revisions.insert(c);
+        }
         for c in relay_report.commits {
             report.commits.insert(c);
         }
+        for c in relay_report.comments {
+            report.comments.insert(c);
+        }
+        for c in relay_report.issues {
+            report.issues.insert(c);
+        }
         for c in relay_report.statuses {
             report.statuses.insert(c);
         }
@@ -1339,6 +2371,20 @@ pub fn consolidate_fetch_reports(reports: Vec<Result<FetchReport>>) -> FetchRepo
         for c in relay_report.profile_updates {
             report.profile_updates.insert(c);
         }
+        report
+            .relays_without_eose
+            .extend(relay_report.relays_without_eose);
+        report
+            .relays_responded
+            .extend(relay_report.relays_responded);
+        report.capped_kinds.extend(relay_report.capped_kinds);
+        report.relays_skipped.extend(relay_report.relays_skipped);
+        for (public_key, t) in relay_report.newest_event_by_author {
+            let newest = report.newest_event_by_author.entry(public_key).or_insert(t);
+            if t.gt(newest) {
+                *newest = t;
+            }
+        }
     }
     report
 }
@@ -1355,7 +2401,7 @@ pub fn get_fetch_filters(
                 get_filter_state_events(repo_coordinates),
                 get_filter_repo_events(repo_coordinates),
                 nostr::Filter::default()
-                    .kinds(vec![Kind::GitPatch, Kind::EventDeletion])
+                    .kinds(vec![Kind::GitPatch, Kind::GitIssue, Kind::EventDeletion])
                     .custom_tag(
                         SingleLetterTag::lowercase(nostr_sdk::Alphabet::A),
                         repo_coordinates
@@ -1369,9 +2415,19 @@ pub fn get_fetch_filters(
             vec![]
         } else {
             vec![
-                nostr::Filter::default()
-                    .events(proposal_ids.clone())
-                    .kinds([vec![Kind::GitPatch, Kind::EventDeletion], status_kinds()].concat()),
+                nostr::Filter::default().events(proposal_ids.clone()).kinds(
+                    [
+                        vec![
+                            Kind::GitPatch,
+                            Kind::EventDeletion,
+                            PROPOSAL_EDIT_KIND,
+                            Kind::Label,
+                        ],
+                        status_kinds(),
+                        comment_kinds(),
+                    ]
+                    .concat(),
+                ),
             ]
         },
         if required_profiles.is_empty() {
@@ -1424,95 +2480,217 @@ pub fn get_filter_contributor_profiles(contributors: HashSet<PublicKey>) -> nost
         .authors(contributors)
 }
 
+/// announcements authored by `public_key`
+pub fn get_filter_maintained_repo_announcements(public_key: &PublicKey) -> nostr::Filter {
+    nostr::Filter::default()
+        .kind(Kind::GitRepoAnnouncement)
+        .author(*public_key)
+}
+
+/// announcements by any author using one of `identifiers`. the maintainers
+/// tag can't be queried so this is how co-maintained repositories are found
+pub fn get_filter_repo_announcements_by_identifier(identifiers: &HashSet<String>) -> nostr::Filter {
+    nostr::Filter::default()
+        .kind(Kind::GitRepoAnnouncement)
+        .identifiers(identifiers.clone())
+}
+
+/// proposals and issues across several repositories
+pub fn get_filter_proposals_and_issues(repo_coordinates: &HashSet<Coordinate>) -> nostr::Filter {
+    nostr::Filter::default()
+        .kinds(vec![Kind::GitPatch, Kind::GitIssue])
+        .custom_tag(
+            SingleLetterTag::lowercase(nostr_sdk::Alphabet::A),
+            repo_coordinates
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>(),
+        )
+}
+
 #[derive(Default)]
 pub struct FetchReport {
     repo_coordinates_without_relays: HashSet<Coordinate>,
     updated_repo_announcements: Vec<(Coordinate, Timestamp)>,
     updated_state: Option<(Timestamp, EventId)>,
+    /// new proposals, excluding revisions
     proposals: HashSet<EventId>,
+    /// new revisions of proposals
+    revisions: HashSet<EventId>,
     /// commits against existing propoals
     commits: HashSet<EventId>,
+    comments: HashSet<EventId>,
+    issues: HashSet<EventId>,
     statuses: HashSet<EventId>,
     contributor_profiles: HashSet<PublicKey>,
     profile_updates: HashSet<PublicKey>,
+    /// used to infer relay time
+    newest_event_by_author: HashMap<PublicKey, Timestamp>,
+    /// every relay fetch failed
+    relays_unreachable: bool,
+    /// relays that hit the subscription deadline without sending EOSE
+    relays_without_eose: HashSet<RelayUrl>,
+    /// relays fetched from successfully
+    relays_responded: HashSet<RelayUrl>,
+    /// announcements and proposals the relay returned, new or not. see
+    /// [`relay_hints_key`]
+    returned_shareable_events: HashSet<String>,
+    /// every event the relay returned, new or not
+    returned_event_ids: HashSet<EventId>,
+    /// false when no relay was asked, eg. another process fetched recently
+    fetch_attempted: bool,
+    /// kinds where a relay had more events than the maximum fetched per kind
+    capped_kinds: HashSet<Kind>,
+    /// relays not asked as others already agreed on the newest event for each
+    /// filter
+    relays_skipped: HashSet<RelayUrl>,
+    /// the relay was sent a filter, so its result says something about its
+    /// reliability
+    relay_queried: bool,
 }
 
-impl Display for FetchReport {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // report: "1 new maintainer, 1 announcement, 1 proposal, 3 commits, 2 statuses"
-        let mut display_items: Vec<String> = vec![];
-        if !self.repo_coordinates_without_relays.is_empty() {
-            display_items.push(format!(
-                "{} new maintainer{}",
-                self.repo_coordinates_without_relays.len(),
-                if self.repo_coordinates_without_relays.len() > 1 {
-                    "s"
-                } else {
-                    ""
-                },
-            ));
-        }
-        if !self.updated_repo_announcements.is_empty() {
-            display_items.push(format!(
-                "{} announcement update{}",
-                self.updated_repo_announcements.len(),
-                if self.updated_repo_announcements.len() > 1 {
-                    "s"
-                } else {
-                    ""
-                },
-            ));
+impl FetchReport {
+    /// `repo_relays` when a fetch was attempted but none of them responded,
+    /// so an empty result can't be trusted
+    pub fn unreachable_repo_relays(&self, repo_relays: &[RelayUrl]) -> Vec<RelayUrl> {
+        let responded = self
+            .relays_responded
+            .iter()
+            .map(|r| remove_trailing_slash(r.as_str()))
+            .collect::<HashSet<String>>();
+        if !self.fetch_attempted
+            || repo_relays
+                .iter()
+                .any(|r| responded.contains(&remove_trailing_slash(r.as_str())))
+        {
+            vec![]
+        } else {
+            repo_relays.to_vec()
         }
-        if self.updated_state.is_some() {
-            display_items.push("new state".to_string());
+    }
+
+    /// a relay had more proposals than the maximum fetched per kind so older
+    /// ones may be missing
+    pub fn proposals_capped(&self) -> bool {
+        self.capped_kinds.contains(&Kind::GitPatch)
+    }
+
+    pub fn has_partial_results(&self) -> bool {
+        !self.relays_without_eose.is_empty()
+    }
+
+    /// "partial results from relay X (no EOSE)" when any relay stalled
+    pub fn partial_results_warning(&self) -> Option<String> {
+        if !self.has_partial_results() {
+            return None;
         }
-        if !self.proposals.is_empty() {
-            display_items.push(format!(
-                "{} proposal{}",
-                self.proposals.len(),
-                if self.proposals.len() > 1 { "s" } else { "" },
-            ));
+        let mut relays = self
+            .relays_without_eose
+            .iter()
+            .map(|r| remove_trailing_slash(r.as_str()))
+            .collect::<Vec<String>>();
+        relays.sort();
+        Some(format!(
+            "WARNING: partial results from {} (no EOSE)",
+            relays.join(", ")
+        ))
+    }
+
+    /// the number of updates of each kind, eg. for `ngit fetch --json`
+    pub fn counts(&self) -> FetchReportCounts {
+        FetchReportCounts {
+            new_maintainers: self.repo_coordinates_without_relays.len(),
+            announcement_updates: self.updated_repo_announcements.len(),
+            state_updated: self.updated_state.is_some(),
+            proposals: self.proposals.len(),
+            revisions: self.revisions.len(),
+            commits: self.commits.len(),
+            comments: self.comments.len(),
+            issues: self.issues.len(),
+            status_changes: self.statuses.len(),
+            user_profiles: self.contributor_profiles.len(),
+            profile_updates: self.profile_updates.len(),
         }
-        if !self.commits.is_empty() {
-            display_items.push(format!(
-                "{} commit{}",
-                self.commits.len(),
-                if self.commits.len() > 1 { "s" } else { "" },
-            ));
+    }
+
+    /// "skipped 2 relays ..." when other relays already agreed on the newest
+    /// event
+    pub fn skipped_relays_note(&self) -> Option<String> {
+        match self.relays_skipped.len() {
+            0 => None,
+            n => Some(format!(
+                "skipped {n} relay{} as {CONVERGED_RELAY_COUNT} others returned the same newest event",
+                if n == 1 { "" } else { "s" },
+            )),
         }
-        if !self.statuses.is_empty() {
-            display_items.push(format!(
-                "{} status{}",
-                self.statuses.len(),
-                if self.statuses.len() > 1 { "es" } else { "" },
-            ));
+    }
+}
+
+impl Display for FetchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // report: "1 new maintainer, 2 new proposals, 1 revision, 3 comments, state
+        // updated"
+        fn count(n: usize, singular: &str, plural: &str) -> Option<String> {
+            match n {
+                0 => None,
+                1 => Some(format!("1 {singular}")),
+                n => Some(format!("{n} {plural}")),
+            }
         }
-        if !self.contributor_profiles.is_empty() {
-            display_items.push(format!(
-                "{} user profile{}",
+        let display_items: Vec<String> = [
+            count(
+                self.repo_coordinates_without_relays.len(),
+                "new maintainer",
+                "new maintainers",
+            ),
+            count(
+                self.updated_repo_announcements.len(),
+                "announcement update",
+                "announcement updates",
+            ),
+            self.updated_state.map(|_| "state updated".to_string()),
+            count(self.proposals.len(), "new proposal", "new proposals"),
+            count(self.revisions.len(), "revision", "revisions"),
+            count(self.commits.len(), "commit", "commits"),
+            count(self.comments.len(), "comment", "comments"),
+            count(self.issues.len(), "issue", "issues"),
+            count(self.statuses.len(), "status change", "status changes"),
+            count(
                 self.contributor_profiles.len(),
-                if self.contributor_profiles.len() > 1 {
-                    "s"
-                } else {
-                    ""
-                },
-            ));
-        }
-        if !self.profile_updates.is_empty() {
-            display_items.push(format!(
-                "{} profile update{}",
+                "user profile",
+                "user profiles",
+            ),
+            count(
                 self.profile_updates.len(),
-                if self.profile_updates.len() > 1 {
-                    "s"
-                } else {
-                    ""
-                },
-            ));
-        }
+                "profile update",
+                "profile updates",
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
         write!(f, "{}", display_items.join(", "))
     }
 }
 
+/// the updates in a [`FetchReport`], counted by kind
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct FetchReportCounts {
+    pub new_maintainers: usize,
+    pub announcement_updates: usize,
+    pub state_updated: bool,
+    /// new proposals, excluding revisions
+    pub proposals: usize,
+    pub revisions: usize,
+    /// commits against existing proposals
+    pub commits: usize,
+    pub comments: usize,
+    pub issues: usize,
+    pub status_changes: usize,
+    pub user_profiles: usize,
+    pub profile_updates: usize,
+}
+
 #[derive(Default, Clone)]
 pub struct FetchRequest {
     repo_relays: HashSet<RelayUrl>,
@@ -1533,6 +2711,7 @@ pub async fn fetching_with_report(
     #[cfg(test)] client: &crate::client::MockConnect,
     #[cfg(not(test))] client: &Client,
     trusted_maintainer_coordinate: &Coordinate,
+    machine_output: bool,
 ) -> Result<FetchReport> {
     let term = console::Term::stderr();
     term.write_line("fetching updates...")?;
@@ -1543,14 +2722,34 @@ pub async fn fetching_with_report(
             &HashSet::new(),
         )
         .await?;
-    if !relay_reports.iter().any(std::result::Result::is_err) {
+    // keep the progress lines showing which relays failed or stalled
+    if !relay_reports
+        .iter()
+        .any(|r| r.as_ref().map_or(true, FetchReport::has_partial_results))
+    {
         let _ = progress_reporter.clear();
     }
     let report = consolidate_fetch_reports(relay_reports);
+    let dim_for_output = |text: &str| {
+        if machine_output {
+            dim(text).for_stderr().to_string()
+        } else {
+            dim(text).to_string()
+        }
+    };
     if report.to_string().is_empty() {
-        println!("no updates");
+        print_human(machine_output, &dim_for_output("no updates"));
     } else {
-        println!("updates: {report}");
+        print_human(
+            machine_output,
+            &format!("{} {report}", dim_for_output("updates:")),
+        );
+    }
+    if let Some(note) = report.skipped_relays_note() {
+        print_human(machine_output, &dim_for_output(&note));
+    }
+    if let Some(warning) = report.partial_results_warning() {
+        term.write_line(&warning)?;
     }
     Ok(report)
 }
@@ -1606,7 +2805,7 @@ pub async fn get_all_proposal_patch_events_from_cache(
     .iter()
     .copied()
     .collect();
-    commit_events.retain(|e| permissioned_users.contains(&e.pubkey));
+    exclude_patches_from_interlopers(&mut commit_events, &permissioned_users);
 
     let revision_roots: HashSet<nostr::EventId> = commit_events
         .iter()
@@ -1629,11 +2828,29 @@ pub async fn get_all_proposal_patch_events_from_cache(
 
     Ok(commit_events
         .iter()
-        .filter(|e| !event_is_cover_letter(e) && permissioned_users.contains(&e.pubkey))
+        .filter(|e| !event_is_cover_letter(e))
         .cloned()
         .collect())
 }
 
+/// a relay could otherwise slip someone else's commits into a proposal
+fn exclude_patches_from_interlopers(
+    patches: &mut Vec<nostr::Event>,
+    permissioned_users: &HashSet<PublicKey>,
+) {
+    patches.retain(|e| {
+        if permissioned_users.contains(&e.pubkey) {
+            return true;
+        }
+        let _ = console::Term::stderr().write_line(&format!(
+            "WARNING: ignoring patch {} from {} who is neither the proposal author nor a maintainer",
+            e.id,
+            e.pubkey.to_bech32().unwrap_or(e.pubkey.to_hex()),
+        ));
+        false
+    });
+}
+
 pub async fn get_event_from_cache_by_id(git_repo: &Repo, event_id: &EventId) -> Result<Event> {
     Ok(get_events_from_local_cache(git_repo.get_path()?, vec![
         nostr::Filter::default().id(*event_id),
@@ -1644,6 +2861,8 @@ pub async fn get_event_from_cache_by_id(git_repo: &Repo, event_id: &EventId) ->
     .clone())
 }
 
+/// returns the ids of the events each relay accepted, keyed by relay url
+/// without a trailing slash
 #[allow(clippy::module_name_repetitions)]
 #[allow(clippy::too_many_lines)]
 pub async fn send_events(
@@ -1655,10 +2874,14 @@ pub async fn send_events(
     repo_read_relays: Vec<RelayUrl>,
     animate: bool,
     silent: bool,
-) -> Result<()> {
+) -> Result<HashMap<String, HashSet<EventId>>> {
     let fallback = [
         client.get_fallback_relays().clone(),
-        if events.iter().any(|e| e.kind.eq(&Kind::GitRepoAnnouncement)) {
+        // deletions are blasted too so they reach relays holding copies
+        if events
+            .iter()
+            .any(|e| [Kind::GitRepoAnnouncement, Kind::EventDeletion].contains(&e.kind))
+        {
             client.get_blaster_relays().clone()
         } else {
             vec![]
@@ -1702,13 +2925,14 @@ pub async fn send_events(
     let m = if silent {
         MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
     } else {
-        MultiProgress::new()
+        multi_progress()
     };
     let pb_style = ProgressStyle::with_template(if animate {
-        " {spinner} {prefix} {bar} {pos}/{len} {msg}"
+        " {spinner} {prefix} {bar} {pos}/{len} {msg} {elapsed_when_slow}"
     } else {
         " - {prefix} {bar} {pos}/{len} {msg}"
     })?
+    .with_key("elapsed_when_slow", write_elapsed_when_slow)
     .progress_chars("##-");
 
     let pb_after_style =
@@ -1732,7 +2956,7 @@ pub async fn send_events(
     })?;
 
     #[allow(clippy::borrow_deref_ref)]
-    join_all(relays.iter().map(|&relay| async {
+    let relay_results = join_all(relays.iter().map(|&relay| async {
         let relay_clean = remove_trailing_slash(relay);
         let details = format!(
             "{}{}{} {}",
@@ -1765,20 +2989,34 @@ pub async fn send_events(
         let pb = m.add(
             ProgressBar::new(events.len() as u64)
                 .with_prefix(details.to_string())
-                .with_style(pb_style.clone()),
+                .with_style(pb_style.clone())
+                .with_finish(ProgressFinish::AndClear),
         );
         if animate {
             pb.enable_steady_tick(Duration::from_millis(300));
         }
         pb.inc(0); // need to make pb display intially
         let mut failed = false;
+        let mut created_at_rejection = false;
+        let mut accepted = HashSet::new();
+        let mut responses = vec![];
         for event in &events {
-            match client
+            let result = client
                 .send_event_to(git_repo_path, relay, event.clone())
-                .await
-            {
-                Ok(_) => pb.inc(1),
+                .await;
+            if let Some(response) = RelayResponse::from_send_result(&result) {
+                responses.push((event.id, relay_clean.clone(), response));
+            }
+            match result {
+                Ok(_) => {
+                    accepted.insert(event.id);
+                    pb.inc(1);
+                }
                 Err(e) => {
+                    created_at_rejection = is_created_at_rejection(&e.to_string());
+                    if is_future_created_at_rejection(&e.to_string()) {
+                        FUTURE_CREATED_AT_REJECTION.store(true, Ordering::Relaxed);
+                    }
                     pb.set_style(pb_after_style_failed.clone());
                     pb.finish_with_message(
                         console::style(
@@ -1799,9 +3037,200 @@ pub async fn send_events(
             pb.set_style(pb_after_style_succeeded.clone());
             pb.finish_with_message("");
         }
+        (relay_clean, accepted, created_at_rejection, responses)
     }))
     .await;
-    Ok(())
+    let responses = relay_results
+        .iter()
+        .flat_map(|(_, _, _, responses)| responses.iter().cloned())
+        .collect::<Vec<_>>();
+    if let Some(git_repo_path) = git_repo_path {
+        // losing the record only affects `ngit events status`
+        let _ = record_relay_responses(git_repo_path, &responses);
+    }
+    if !silent
+        && relay_results
+            .iter()
+            .any(|(_, _, created_at_rejection, _)| *created_at_rejection)
+    {
+        console::Term::stderr().write_line(
+            &console::style(
+                "WARNING: some relays rejected events because of their created_at timestamp. check your system clock is correct",
+            )
+            .for_stderr()
+            .yellow()
+            .bold()
+            .to_string(),
+        )?;
+        if FUTURE_CREATED_AT_REJECTION.load(Ordering::Relaxed)
+            && !FIX_TIMESTAMP.load(Ordering::Relaxed)
+        {
+            console::Term::stderr().write_line(
+                "events were dated in the future. correct your system clock or use --fix-timestamp (or git config nostr.fix-timestamp true for git push) to publish using relay-inferred time",
+            )?;
+        }
+    }
+    if let Some((rejected_event_id, _, _)) =
+        responses.iter().find(|(_, _, response)| !response.accepted)
+    {
+        if !silent && git_repo_path.is_some() {
+            console::Term::stderr().write_line(&format!(
+                "some relays rejected events. run `ngit events status {}` for details",
+                rejected_event_id.to_hex(),
+            ))?;
+        }
+    }
+    Ok(relay_results
+        .into_iter()
+        .map(|(relay, accepted, _, _)| (relay, accepted))
+        .collect())
+}
+
+/// whether any of `relays` accepted the event, or any relay at all if
+/// `relays` is empty
+pub fn event_reached_any_relay(
+    accepted: &HashMap<String, HashSet<EventId>>,
+    event_id: &EventId,
+    relays: &[RelayUrl],
+) -> bool {
+    accepted.iter().any(|(relay, accepted)| {
+        accepted.contains(event_id)
+            && (relays.is_empty()
+                || relays
+                    .iter()
+                    .any(|r| remove_trailing_slash(r.as_str()).eq(relay)))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepoRelaysSource {
+    Announcement,
+    MaintainerRelayList,
+    UserRelayList,
+    Fallback,
+}
+
+/// relays to find and publish repository events on. when the announcement
+/// lists none, the first non-empty of: the trusted maintainer's NIP-65 write
+/// relays, the user's write relays, the fallback relays
+pub fn resolve_repo_relays(
+    announced: &[RelayUrl],
+    maintainer_write_relays: &[String],
+    user_write_relays: &[String],
+    fallback_relays: &[String],
+) -> (Vec<RelayUrl>, RepoRelaysSource) {
+    if !announced.is_empty() {
+        return (announced.to_vec(), RepoRelaysSource::Announcement);
+    }
+    let parse = |relays: &[String]| {
+        relays
+            .iter()
+            .filter_map(|r| RelayUrl::parse(r).ok())
+            .collect::<Vec<RelayUrl>>()
+    };
+    for (relays, source) in [
+        (
+            maintainer_write_relays,
+            RepoRelaysSource::MaintainerRelayList,
+        ),
+        (user_write_relays, RepoRelaysSource::UserRelayList),
+    ] {
+        let relays = parse(relays);
+        if !relays.is_empty() {
+            return (relays, source);
+        }
+    }
+    (parse(fallback_relays), RepoRelaysSource::Fallback)
+}
+
+/// `repo_ref.relays`, or if the announcement lists none, relays resolved with
+/// `resolve_repo_relays` from cached relay lists
+pub async fn get_repo_relays(
+    git_repo_path: Option<&Path>,
+    repo_ref: &RepoRef,
+    fallback_relays: &[String],
+) -> (Vec<RelayUrl>, RepoRelaysSource) {
+    if !repo_ref.relays.is_empty() {
+        return (repo_ref.relays.clone(), RepoRelaysSource::Announcement);
+    }
+    let maintainer_write_relays = if let Ok(user_ref) =
+        get_user_ref_from_cache(git_repo_path, &repo_ref.trusted_maintainer).await
+    {
+        user_ref.relays.write()
+    } else {
+        vec![]
+    };
+    let user_write_relays = if let Some(git_repo_path) = git_repo_path {
+        if let Ok(Some(public_key)) = get_likely_logged_in_user(git_repo_path).await {
+            if let Ok(user_ref) = get_user_ref_from_cache(Some(git_repo_path), &public_key).await {
+                user_ref.relays.write()
+            } else {
+                vec![]
+            }
+        } else {
+            vec![]
+        }
+    } else {
+        vec![]
+    };
+    resolve_repo_relays(
+        &repo_ref.relays,
+        &maintainer_write_relays,
+        &user_write_relays,
+        fallback_relays,
+    )
+}
+
+static REPO_RELAYS_NOTICE_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// printed once per run when the announcement relays aren't used
+pub fn print_repo_relays_notice(source: RepoRelaysSource) {
+    let using = match source {
+        RepoRelaysSource::Announcement => return,
+        RepoRelaysSource::MaintainerRelayList => "maintainer's relay list",
+        RepoRelaysSource::UserRelayList => "your relay list",
+        RepoRelaysSource::Fallback => "fallback relays",
+    };
+    if !REPO_RELAYS_NOTICE_SHOWN.swap(true, Ordering::Relaxed) {
+        let _ = console::Term::stderr()
+            .write_line(format!("repo announcement lists no relays — using {using}").as_str());
+    }
+}
+
+/// tell the user when the fetch found a newer announcement that moved the
+/// repository to different relays or git servers, and point git remotes
+/// hinting at relays it left to the new ones
+async fn report_hosting_changes(
+    git_repo_path: Option<&Path>,
+    announced_before: &RepoRef,
+    coordinate: &Coordinate,
+) {
+    let Ok(announced_now) = get_repo_ref_from_cache(git_repo_path, coordinate).await else {
+        return;
+    };
+    let changes = describe_hosting_changes(announced_before, &announced_now);
+    if changes.is_empty() {
+        return;
+    }
+    let term = console::Term::stderr();
+    for change in changes {
+        let _ = term.write_line(&change);
+    }
+    let Some(Ok(git_repo)) = git_repo_path.map(|path| Repo::from_path(&path.to_path_buf())) else {
+        return;
+    };
+    match update_remote_relay_hints(&git_repo, coordinate, &announced_now.relays).await {
+        Ok(remotes) => {
+            for remote in remotes {
+                let _ = term.write_line(&format!("updated relay hints in git remote '{remote}'"));
+            }
+        }
+        Err(error) => {
+            let _ = term.write_line(&format!(
+                "WARNING: failed to update relay hints in git remotes: {error}"
+            ));
+        }
+    }
 }
 
 fn remove_trailing_slash(s: &str) -> String {
@@ -1811,3 +3240,535 @@ fn remove_trailing_slash(s: &str) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod fetch_report_display {
+        use super::*;
+
+        pub fn event_ids(n: u8) -> HashSet<EventId> {
+            (0..n)
+                .map(|i| EventId::from_slice(&[i; 32]).unwrap())
+                .collect()
+        }
+
+        #[test]
+        fn empty_when_no_updates() {
+            assert_eq!(FetchReport::default().to_string(), "");
+        }
+
+        #[test]
+        fn counts_each_kind_with_plurals() {
+            let report = FetchReport {
+                updated_state: Some((Timestamp::from(0), EventId::all_zeros())),
+                proposals: event_ids(2),
+                revisions: event_ids(1),
+                comments: event_ids(3),
+                issues: event_ids(1),
+                statuses: event_ids(2),
+                ..FetchReport::default()
+            };
+            assert_eq!(
+                report.to_string(),
+                "state updated, 2 new proposals, 1 revision, 3 comments, 1 issue, 2 status changes",
+            );
+        }
+    }
+
+    mod fetch_report_counts {
+        use super::*;
+
+        #[test]
+        fn serializes_count_of_each_kind() -> Result<()> {
+            let report = FetchReport {
+                updated_state: Some((Timestamp::from(0), EventId::all_zeros())),
+                proposals: fetch_report_display::event_ids(2),
+                comments: fetch_report_display::event_ids(3),
+                ..FetchReport::default()
+            };
+            assert_eq!(
+                serde_json::to_string(&report.counts())?,
+                r#"{"new_maintainers":0,"announcement_updates":0,"state_updated":true,"proposals":2,"revisions":0,"commits":0,"comments":3,"issues":0,"status_changes":0,"user_profiles":0,"profile_updates":0}"#,
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn all_zero_when_no_updates() {
+            assert_eq!(
+                FetchReport::default().counts(),
+                FetchReportCounts::default()
+            );
+        }
+    }
+
+    mod detect_clock_skew {
+        use super::*;
+
+        fn newest_events(timestamps: &[u64]) -> HashMap<PublicKey, Timestamp> {
+            timestamps
+                .iter()
+                .map(|t| (nostr::Keys::generate().public_key(), Timestamp::from(*t)))
+                .collect()
+        }
+
+        #[test]
+        fn none_when_within_threshold() {
+            assert_eq!(
+                detect_clock_skew(
+                    Timestamp::from(10_000),
+                    &newest_events(&[10_000 + CLOCK_SKEW_THRESHOLD_SECS, 10_100])
+                ),
+                None,
+            );
+        }
+
+        #[test]
+        fn none_when_relay_events_are_older() {
+            assert_eq!(
+                detect_clock_skew(Timestamp::from(100_000), &newest_events(&[10_000, 20_000])),
+                None,
+            );
+        }
+
+        #[test]
+        fn seconds_behind_when_two_authors_are_ahead() {
+            assert_eq!(
+                detect_clock_skew(Timestamp::from(10_000), &newest_events(&[14_000, 13_600])),
+                Some(3_600),
+            );
+        }
+
+        #[test]
+        fn ignores_single_author_ahead() {
+            assert_eq!(
+                detect_clock_skew(Timestamp::from(10_000), &newest_events(&[20_000, 10_000])),
+                None,
+            );
+        }
+
+        #[test]
+        fn none_with_fewer_than_two_authors() {
+            assert_eq!(
+                detect_clock_skew(Timestamp::from(10_000), &newest_events(&[20_000])),
+                None,
+            );
+        }
+    }
+
+    mod relay_inferred_now {
+        use super::*;
+
+        #[test]
+        fn newest_event_of_second_most_recently_active_author() {
+            let newest_event_by_author = [20_000, 13_600, 10_000]
+                .iter()
+                .map(|t| (nostr::Keys::generate().public_key(), Timestamp::from(*t)))
+                .collect();
+            assert_eq!(
+                relay_inferred_now(&newest_event_by_author),
+                Some(Timestamp::from(13_600)),
+            );
+        }
+    }
+
+    mod detect_clock_ahead {
+        use super::*;
+
+        #[test]
+        fn seconds_ahead_beyond_threshold() {
+            assert_eq!(
+                detect_clock_ahead(Timestamp::from(13_600), Timestamp::from(10_000)),
+                Some(3_600),
+            );
+        }
+
+        #[test]
+        fn capped_when_relay_events_are_old() {
+            // the newest cached events are months old so the relay-inferred
+            // time is far behind the relays' real clock
+            assert_eq!(
+                detect_clock_ahead(Timestamp::from(10_000_000), Timestamp::from(10_000)),
+                Some(MAX_CLOCK_AHEAD_CORRECTION_SECS),
+            );
+        }
+
+        #[test]
+        fn none_when_within_threshold_or_behind() {
+            assert_eq!(
+                detect_clock_ahead(
+                    Timestamp::from(10_000 + CLOCK_SKEW_THRESHOLD_SECS),
+                    Timestamp::from(10_000)
+                ),
+                None,
+            );
+            assert_eq!(
+                detect_clock_ahead(Timestamp::from(10_000), Timestamp::from(13_600)),
+                None,
+            );
+        }
+    }
+
+    mod is_future_created_at_rejection {
+        use super::*;
+
+        #[test]
+        fn matches_future_dated_rejections() {
+            for message in [
+                "invalid: created_at too late",
+                "invalid: created_at too far in the future",
+                "invalid: event creation date is too far off from the current time. Is your system clock in sync? (future)",
+            ] {
+                assert!(is_future_created_at_rejection(message), "{message}");
+            }
+        }
+
+        #[test]
+        fn ignores_other_rejections() {
+            assert!(!is_future_created_at_rejection(
+                "invalid: created_at too old"
+            ));
+            assert!(!is_future_created_at_rejection("error: Payment Required"));
+        }
+    }
+
+    mod is_created_at_rejection {
+        use super::*;
+
+        #[test]
+        fn matches_common_relay_messages() {
+            for message in [
+                "invalid: created_at too late",
+                "invalid: event creation date is too far off from the current time. Is your system clock in sync? (future)",
+                "error: Timestamp out of range",
+            ] {
+                assert!(is_created_at_rejection(message), "{message}");
+            }
+        }
+
+        #[test]
+        fn ignores_other_rejections() {
+            assert!(!is_created_at_rejection("error: Payment Required"));
+        }
+    }
+
+    mod resolve_repo_relays {
+        use super::*;
+
+        fn urls(relays: &[&str]) -> Vec<String> {
+            relays
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect()
+        }
+
+        #[test]
+        fn announced_relays_used_when_present() -> Result<()> {
+            let announced = vec![RelayUrl::parse("ws://localhost:8055")?];
+            assert_eq!(
+                resolve_repo_relays(
+                    &announced,
+                    &urls(&["ws://localhost:8053"]),
+                    &urls(&["ws://localhost:8054"]),
+                    &urls(&["ws://localhost:8051"]),
+                ),
+                (announced, RepoRelaysSource::Announcement),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn maintainer_relays_used_when_none_announced() -> Result<()> {
+            assert_eq!(
+                resolve_repo_relays(
+                    &[],
+                    &urls(&["ws://localhost:8053"]),
+                    &urls(&["ws://localhost:8054"]),
+                    &urls(&["ws://localhost:8051"]),
+                ),
+                (
+                    vec![RelayUrl::parse("ws://localhost:8053")?],
+                    RepoRelaysSource::MaintainerRelayList
+                ),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn user_relays_used_when_maintainer_relays_unknown() -> Result<()> {
+            assert_eq!(
+                resolve_repo_relays(
+                    &[],
+                    &[],
+                    &urls(&["ws://localhost:8054"]),
+                    &urls(&["ws://localhost:8051"]),
+                ),
+                (
+                    vec![RelayUrl::parse("ws://localhost:8054")?],
+                    RepoRelaysSource::UserRelayList
+                ),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn fallback_relays_used_as_last_resort() -> Result<()> {
+            assert_eq!(
+                resolve_repo_relays(
+                    &[],
+                    &urls(&["not a relay"]),
+                    &[],
+                    &urls(&["ws://localhost:8051"]),
+                ),
+                (
+                    vec![RelayUrl::parse("ws://localhost:8051")?],
+                    RepoRelaysSource::Fallback
+                ),
+            );
+            Ok(())
+        }
+    }
+
+    mod event_reached_any_relay {
+        use super::*;
+
+        fn accepted(relays: &[(&str, &[EventId])]) -> HashMap<String, HashSet<EventId>> {
+            relays
+                .iter()
+                .map(|(relay, ids)| (relay.to_string(), ids.iter().copied().collect()))
+                .collect()
+        }
+
+        #[test]
+        fn true_when_a_listed_relay_accepted_event() -> Result<()> {
+            let id = EventId::all_zeros();
+            assert!(event_reached_any_relay(
+                &accepted(&[("ws://localhost:8055", &[id]), ("ws://localhost:8056", &[])]),
+                &id,
+                &[RelayUrl::parse("ws://localhost:8055/")?],
+            ));
+            Ok(())
+        }
+
+        #[test]
+        fn false_when_only_other_relays_accepted_event() -> Result<()> {
+            let id = EventId::all_zeros();
+            assert!(!event_reached_any_relay(
+                &accepted(&[("ws://localhost:8051", &[id]), ("ws://localhost:8055", &[])]),
+                &id,
+                &[RelayUrl::parse("ws://localhost:8055")?],
+            ));
+            Ok(())
+        }
+
+        #[test]
+        fn any_relay_counts_when_none_listed() {
+            let id = EventId::all_zeros();
+            assert!(event_reached_any_relay(
+                &accepted(&[("ws://localhost:8051", &[id])]),
+                &id,
+                &[],
+            ));
+        }
+    }
+
+    mod results_converged {
+        use super::*;
+
+        fn timestamps(created_at: &[u64]) -> Vec<Option<Timestamp>> {
+            created_at
+                .iter()
+                .map(|t| Some(Timestamp::from(*t)))
+                .collect()
+        }
+
+        #[test]
+        fn three_relays_with_newest_event() {
+            assert!(results_converged(&timestamps(&[200, 100, 200, 200])));
+        }
+
+        #[test]
+        fn not_when_agreed_event_isnt_the_newest() {
+            assert!(!results_converged(&timestamps(&[100, 100, 100, 200])));
+        }
+
+        #[test]
+        fn three_relays_returning_nothing() {
+            assert!(results_converged(&[None, None, None]));
+            let newest = Some(Timestamp::from(100));
+            assert!(!results_converged(&[None, None, None, newest]));
+        }
+
+        #[test]
+        fn not_with_fewer_than_three_relays() {
+            assert!(!results_converged(&timestamps(&[200, 200])));
+            assert!(!results_converged(&[]));
+        }
+    }
+
+    mod parse_fetch_by_another_process {
+        use super::*;
+
+        #[test]
+        fn in_flight_marker_from_another_process() {
+            assert_eq!(
+                parse_fetch_by_another_process("100 1000\n", 200, 1005),
+                Some(FetchByAnotherProcess::InFlight)
+            );
+        }
+
+        #[test]
+        fn completed_marker_from_another_process() {
+            assert_eq!(
+                parse_fetch_by_another_process("100 1000 1003\n", 200, 1005),
+                Some(FetchByAnotherProcess::CompletedAt(1003))
+            );
+        }
+
+        #[test]
+        fn marker_from_this_process_is_ignored() {
+            assert_eq!(
+                parse_fetch_by_another_process("200 1000\n", 200, 1005),
+                None
+            );
+        }
+
+        #[test]
+        fn stale_marker_is_ignored() {
+            assert_eq!(
+                parse_fetch_by_another_process("100 1000\n", 200, 1000 + FETCH_IN_FLIGHT_SECS),
+                None
+            );
+        }
+
+        #[test]
+        fn malformed_marker_is_ignored() {
+            assert_eq!(parse_fetch_by_another_process("garbage", 200, 1005), None);
+            assert_eq!(
+                parse_fetch_by_another_process("100 1000 garbage", 200, 1005),
+                None
+            );
+        }
+    }
+
+    mod split_paginated_filters {
+        use super::*;
+
+        #[test]
+        fn one_limited_filter_per_paginated_kind() {
+            let (paginated, other) = split_paginated_filters(
+                vec![
+                    nostr::Filter::default().kinds([Kind::GitPatch, Kind::GitIssue]),
+                    nostr::Filter::default().kind(STATE_KIND),
+                ],
+                500,
+            );
+            assert_eq!(
+                paginated,
+                vec![
+                    nostr::Filter::default().kind(Kind::GitPatch).limit(500),
+                    nostr::Filter::default().kind(Kind::GitIssue).limit(500),
+                ]
+            );
+            assert_eq!(other, vec![nostr::Filter::default().kind(STATE_KIND)]);
+        }
+
+        #[test]
+        fn filters_with_ids_or_limit_not_paginated() {
+            let filters = vec![
+                nostr::Filter::default()
+                    .kind(Kind::GitPatch)
+                    .id(EventId::all_zeros()),
+                nostr::Filter::default().kind(Kind::GitPatch).limit(10),
+            ];
+            let (paginated, other) = split_paginated_filters(filters.clone(), 500);
+            assert!(paginated.is_empty());
+            assert_eq!(other, filters);
+        }
+    }
+
+    mod next_pages {
+        use super::*;
+
+        fn patches_at(created_at: u64, n: usize) -> Vec<Event> {
+            (0..n)
+                .map(|i| {
+                    EventBuilder::new(Kind::GitPatch, format!("patch {i}"))
+                        .custom_created_at(Timestamp::from(created_at))
+                        .sign_with_keys(&nostr::Keys::generate())
+                        .unwrap()
+                })
+                .collect()
+        }
+
+        fn page() -> nostr::Filter {
+            nostr::Filter::default().kind(Kind::GitPatch).limit(3)
+        }
+
+        #[test]
+        fn short_page_is_complete() {
+            let mut fetched_by_kind = HashMap::new();
+            let mut capped_kinds = HashSet::new();
+            assert!(
+                next_pages(
+                    &[page()],
+                    &patches_at(1000, 2),
+                    &mut fetched_by_kind,
+                    3,
+                    None,
+                    &mut capped_kinds,
+                )
+                .is_empty()
+            );
+        }
+
+        #[test]
+        fn full_page_sharing_one_timestamp_steps_back_past_it() {
+            let events = patches_at(1000, 3);
+            let mut fetched_by_kind = HashMap::new();
+            let mut capped_kinds = HashSet::new();
+            let pages = next_pages(
+                &[page()],
+                &events,
+                &mut fetched_by_kind,
+                3,
+                None,
+                &mut capped_kinds,
+            );
+            // inclusive so others with the same timestamp aren't missed
+            assert_eq!(pages, vec![page().until(Timestamp::from(1000))]);
+            // the relay returns the same page again
+            let pages = next_pages(
+                &pages,
+                &events,
+                &mut fetched_by_kind,
+                3,
+                None,
+                &mut capped_kinds,
+            );
+            assert_eq!(pages, vec![page().until(Timestamp::from(999))]);
+            assert_eq!(fetched_by_kind[&Kind::GitPatch].len(), 3);
+        }
+
+        #[test]
+        fn counts_events_by_unique_id_towards_max_per_kind() {
+            let events = patches_at(1000, 3);
+            let mut fetched_by_kind = HashMap::new();
+            let mut capped_kinds = HashSet::new();
+            // two filters of the same kind returning the same events
+            let pages = next_pages(
+                &[page(), page().author(events[0].pubkey)],
+                &events,
+                &mut fetched_by_kind,
+                1,
+                Some(4),
+                &mut capped_kinds,
+            );
+            assert_eq!(fetched_by_kind[&Kind::GitPatch].len(), 3);
+            assert!(capped_kinds.is_empty());
+            assert_eq!(pages.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,589 @@
+use std::{fmt::Display, net::SocketAddr, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    client::{
+        DEFAULT_FETCH_MAX_EVENTS_PER_KIND, DEFAULT_MAX_CONCURRENT_RELAYS, GET_EVENTS_TIMEOUT,
+        default_fallback_relays,
+    },
+    get_dirs,
+    git::{Repo, RepoActions, get_git_config_item, get_git_dir, parse_git_config_bool},
+    labels::DEFAULT_LABEL_NAMESPACE,
+    profile_cache::DEFAULT_PROFILE_CACHE_TTL_SECS,
+    proxy::{ProxyUse, get_proxy, socks_proxy_addr},
+    repo_fetched_at::DEFAULT_CACHE_MAX_AGE_SECS,
+    web_viewer::DEFAULT_WEB_VIEWER_URL,
+};
+
+/// user level defaults stored in `config.toml` in the ngit config directory
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub fallback_relays: Option<Vec<String>>,
+    pub default_grasp_servers: Option<Vec<String>>,
+    pub color: Option<ColorChoice>,
+    pub editor: Option<String>,
+    pub relay_timeout_secs: Option<u64>,
+    pub profile_cache_ttl_secs: Option<u64>,
+    pub proposal_expiry_days: Option<u64>,
+    pub web_viewer_url: Option<String>,
+    pub fetch_max_events_per_kind: Option<usize>,
+    pub max_concurrent_relays: Option<usize>,
+    pub label_namespace: Option<String>,
+    pub labels_allow_anyone: Option<bool>,
+    pub ci_bots: Option<Vec<String>>,
+    pub use_blaster: Option<bool>,
+    pub prs_as_refs: Option<PrsAsRefs>,
+    pub cache_max_age_secs: Option<u64>,
+    pub background_fetch: Option<bool>,
+    pub quiet_clone: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Display for ColorChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorChoice::Auto => write!(f, "auto"),
+            ColorChoice::Always => write!(f, "always"),
+            ColorChoice::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => anyhow::bail!("color must be auto, always or never"),
+        }
+    }
+}
+
+/// how git-remote-nostr advertises open proposals to git
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrsAsRefs {
+    /// as `refs/heads/pr/*` branches
+    #[default]
+    Heads,
+    /// as `refs/prs/*`, which aren't listed as remote branches
+    Prs,
+    /// not at all
+    None,
+}
+
+impl Display for PrsAsRefs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrsAsRefs::Heads => write!(f, "heads"),
+            PrsAsRefs::Prs => write!(f, "prs"),
+            PrsAsRefs::None => write!(f, "none"),
+        }
+    }
+}
+
+impl std::str::FromStr for PrsAsRefs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "heads" => Ok(PrsAsRefs::Heads),
+            "prs" => Ok(PrsAsRefs::Prs),
+            "none" => Ok(PrsAsRefs::None),
+            _ => anyhow::bail!("prs-as-refs must be heads, prs or none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    ConfigFile(PathBuf),
+    GitConfig(String),
+    Environment(String),
+    CommandLine,
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::ConfigFile(path) => write!(f, "{}", path.display()),
+            ConfigSource::GitConfig(item) => write!(f, "git config {item}"),
+            ConfigSource::Environment(var) => write!(f, "environment variable {var}"),
+            ConfigSource::CommandLine => write!(f, "command line argument"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValue<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> ConfigValue<T> {
+    fn default(value: T) -> Self {
+        Self {
+            value,
+            source: ConfigSource::Default,
+        }
+    }
+
+    /// override with a value from a higher precedence source
+    pub fn set(&mut self, value: T, source: ConfigSource) {
+        self.value = value;
+        self.source = source;
+    }
+}
+
+/// effective configuration. precedence: command line arguments, git config,
+/// user config file, defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub fallback_relays: ConfigValue<Vec<String>>,
+    pub default_grasp_servers: ConfigValue<Vec<String>>,
+    pub color: ConfigValue<ColorChoice>,
+    pub editor: ConfigValue<Option<String>>,
+    pub relay_timeout_secs: ConfigValue<u64>,
+    /// how long user profiles in the global cache are reused before being
+    /// fetched again
+    pub profile_cache_ttl_secs: ConfigValue<u64>,
+    /// days until published proposal, patch and status events expire (NIP-40)
+    pub proposal_expiry_days: ConfigValue<Option<u64>>,
+    /// proposal page url with `{naddr}` and `{nevent}` placeholders
+    pub web_viewer_url: ConfigValue<String>,
+    /// events fetched per kind from each relay, newest first. None fetches
+    /// complete history
+    pub fetch_max_events_per_kind: ConfigValue<Option<usize>>,
+    /// relays fetched from at once
+    pub max_concurrent_relays: ConfigValue<usize>,
+    /// NIP-32 namespace of proposal labels
+    pub label_namespace: ConfigValue<String>,
+    /// publish and show proposal labels from anyone, not just maintainers
+    pub labels_allow_anyone: ConfigValue<bool>,
+    /// npubs whose ci results are shown on proposals. when empty, those
+    /// listed in the repository announcement are used
+    pub ci_bots: ConfigValue<Vec<String>>,
+    /// also publish repository announcements to the built in blaster relays
+    pub use_blaster: ConfigValue<bool>,
+    /// how git-remote-nostr lists open proposals
+    pub prs_as_refs: ConfigValue<PrsAsRefs>,
+    /// how long after a fetch git-remote-nostr answers `list` from the cache.
+    /// 0 always fetches
+    pub cache_max_age_secs: ConfigValue<u64>,
+    /// when the cache is older than `cache_max_age_secs`, git-remote-nostr
+    /// answers `list` from it anyway and refreshes it in the background
+    pub background_fetch: ConfigValue<bool>,
+    /// don't print the repository's name, links and maintainers after a
+    /// clone
+    pub quiet_clone: ConfigValue<bool>,
+    pub relay_proxy: ConfigValue<Option<SocketAddr>>,
+    pub git_proxy: ConfigValue<Option<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            fallback_relays: ConfigValue::default(default_fallback_relays()),
+            default_grasp_servers: ConfigValue::default(vec![]),
+            color: ConfigValue::default(ColorChoice::Auto),
+            editor: ConfigValue::default(None),
+            relay_timeout_secs: ConfigValue::default(GET_EVENTS_TIMEOUT),
+            profile_cache_ttl_secs: ConfigValue::default(DEFAULT_PROFILE_CACHE_TTL_SECS),
+            proposal_expiry_days: ConfigValue::default(None),
+            web_viewer_url: ConfigValue::default(DEFAULT_WEB_VIEWER_URL.to_string()),
+            fetch_max_events_per_kind: ConfigValue::default(Some(
+                DEFAULT_FETCH_MAX_EVENTS_PER_KIND,
+            )),
+            max_concurrent_relays: ConfigValue::default(DEFAULT_MAX_CONCURRENT_RELAYS),
+            label_namespace: ConfigValue::default(DEFAULT_LABEL_NAMESPACE.to_string()),
+            labels_allow_anyone: ConfigValue::default(false),
+            ci_bots: ConfigValue::default(vec![]),
+            use_blaster: ConfigValue::default(true),
+            prs_as_refs: ConfigValue::default(PrsAsRefs::Heads),
+            cache_max_age_secs: ConfigValue::default(default_cache_max_age_secs()),
+            background_fetch: ConfigValue::default(false),
+            quiet_clone: ConfigValue::default(false),
+            relay_proxy: ConfigValue::default(None),
+            git_proxy: ConfigValue::default(None),
+        }
+    }
+}
+
+impl Config {
+    /// load config file and apply git config overrides. cli arguments should
+    /// be applied by the caller
+    pub fn load(git_repo: &Option<&Repo>) -> Result<Self> {
+        let mut config = Config::default();
+        if let Some(path) = get_config_file_path(git_repo) {
+            if path.exists() {
+                let file = std::fs::read_to_string(&path)
+                    .context(format!("failed to read config file {}", path.display()))?;
+                let file: ConfigFile = toml::from_str(&file)
+                    .context(format!("failed to parse config file {}", path.display()))?;
+                config.apply_config_file(file, &path);
+            }
+        }
+        config.apply_git_config(git_repo)?;
+        config.apply_proxy(git_repo)?;
+        Ok(config)
+    }
+
+    fn apply_config_file(&mut self, file: ConfigFile, path: &std::path::Path) {
+        let source = ConfigSource::ConfigFile(path.to_path_buf());
+        if let Some(v) = file.fallback_relays {
+            self.fallback_relays.set(v, source.clone());
+        }
+        if let Some(v) = file.default_grasp_servers {
+            self.default_grasp_servers.set(v, source.clone());
+        }
+        if let Some(v) = file.color {
+            self.color.set(v, source.clone());
+        }
+        if let Some(v) = file.editor {
+            self.editor.set(Some(v), source.clone());
+        }
+        if let Some(v) = file.relay_timeout_secs {
+            self.relay_timeout_secs.set(v, source.clone());
+        }
+        if let Some(v) = file.profile_cache_ttl_secs {
+            self.profile_cache_ttl_secs.set(v, source.clone());
+        }
+        if let Some(v) = file.proposal_expiry_days {
+            self.proposal_expiry_days.set(Some(v), source.clone());
+        }
+        if let Some(v) = file.web_viewer_url {
+            self.web_viewer_url.set(v, source.clone());
+        }
+        if let Some(v) = file.fetch_max_events_per_kind {
+            self.fetch_max_events_per_kind
+                .set(Some(v).filter(|max| *max > 0), source.clone());
+        }
+        if let Some(v) = file.max_concurrent_relays {
+            self.max_concurrent_relays.set(v.max(1), source.clone());
+        }
+        if let Some(v) = file.label_namespace {
+            self.label_namespace.set(v, source.clone());
+        }
+        if let Some(v) = file.labels_allow_anyone {
+            self.labels_allow_anyone.set(v, source.clone());
+        }
+        if let Some(v) = file.ci_bots {
+            self.ci_bots.set(v, source.clone());
+        }
+        if let Some(v) = file.use_blaster {
+            self.use_blaster.set(v, source.clone());
+        }
+        if let Some(v) = file.prs_as_refs {
+            self.prs_as_refs.set(v, source.clone());
+        }
+        if let Some(v) = file.cache_max_age_secs {
+            self.cache_max_age_secs.set(v, source.clone());
+        }
+        if let Some(v) = file.background_fetch {
+            self.background_fetch.set(v, source.clone());
+        }
+        if let Some(v) = file.quiet_clone {
+            self.quiet_clone.set(v, source);
+        }
+    }
+
+    fn apply_git_config(&mut self, git_repo: &Option<&Repo>) -> Result<()> {
+        let git_config_list = |item: &str| -> Result<Option<Vec<String>>> {
+            Ok(get_git_config_item(git_repo, item)?.map(|s| {
+                s.split([',', ' '])
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect()
+            }))
+        };
+        let git_config_bool = |item: &str| -> Result<Option<bool>> {
+            get_git_config_item(git_repo, item)?
+                .map(|v| parse_git_config_bool(&v))
+                .transpose()
+                .context(format!("invalid git config item {item}"))
+        };
+        if let Some(v) = git_config_list("nostr.fallback-relays")? {
+            self.fallback_relays.set(
+                v,
+                ConfigSource::GitConfig("nostr.fallback-relays".to_string()),
+            );
+        }
+        if let Some(v) = git_config_list("nostr.grasp-servers")? {
+            self.default_grasp_servers.set(
+                v,
+                ConfigSource::GitConfig("nostr.grasp-servers".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.color")? {
+            self.color.set(
+                v.parse().context("invalid git config item nostr.color")?,
+                ConfigSource::GitConfig("nostr.color".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "core.editor")? {
+            self.editor
+                .set(Some(v), ConfigSource::GitConfig("core.editor".to_string()));
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.relay-timeout-secs")? {
+            self.relay_timeout_secs.set(
+                v.parse()
+                    .context("invalid git config item nostr.relay-timeout-secs")?,
+                ConfigSource::GitConfig("nostr.relay-timeout-secs".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.profile-cache-ttl-secs")? {
+            self.profile_cache_ttl_secs.set(
+                v.parse()
+                    .context("invalid git config item nostr.profile-cache-ttl-secs")?,
+                ConfigSource::GitConfig("nostr.profile-cache-ttl-secs".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.proposal-expiry-days")? {
+            self.proposal_expiry_days.set(
+                Some(
+                    v.parse()
+                        .context("invalid git config item nostr.proposal-expiry-days")?,
+                ),
+                ConfigSource::GitConfig("nostr.proposal-expiry-days".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.web-viewer-url")? {
+            self.web_viewer_url.set(
+                v,
+                ConfigSource::GitConfig("nostr.web-viewer-url".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.fetch-max-events-per-kind")? {
+            self.fetch_max_events_per_kind.set(
+                Some(
+                    v.parse::<usize>()
+                        .context("invalid git config item nostr.fetch-max-events-per-kind")?,
+                )
+                .filter(|max| *max > 0),
+                ConfigSource::GitConfig("nostr.fetch-max-events-per-kind".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.max-concurrent-relays")? {
+            self.max_concurrent_relays.set(
+                v.parse::<usize>()
+                    .context("invalid git config item nostr.max-concurrent-relays")?
+                    .max(1),
+                ConfigSource::GitConfig("nostr.max-concurrent-relays".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.label-namespace")? {
+            self.label_namespace.set(
+                v,
+                ConfigSource::GitConfig("nostr.label-namespace".to_string()),
+            );
+        }
+        if let Some(v) = git_config_bool("nostr.labels-allow-anyone")? {
+            self.labels_allow_anyone.set(
+                v,
+                ConfigSource::GitConfig("nostr.labels-allow-anyone".to_string()),
+            );
+        }
+        if let Some(v) = git_config_list("nostr.ci-bots")? {
+            self.ci_bots
+                .set(v, ConfigSource::GitConfig("nostr.ci-bots".to_string()));
+        }
+        if let Some(v) = git_config_bool("nostr.use-blaster")? {
+            self.use_blaster
+                .set(v, ConfigSource::GitConfig("nostr.use-blaster".to_string()));
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.prs-as-refs")? {
+            self.prs_as_refs.set(
+                v.parse()
+                    .context("invalid git config item nostr.prs-as-refs")?,
+                ConfigSource::GitConfig("nostr.prs-as-refs".to_string()),
+            );
+        }
+        if let Some(v) = get_git_config_item(git_repo, "nostr.cache-max-age")? {
+            self.cache_max_age_secs.set(
+                v.parse()
+                    .context("invalid git config item nostr.cache-max-age")?,
+                ConfigSource::GitConfig("nostr.cache-max-age".to_string()),
+            );
+        }
+        if let Some(v) = git_config_bool("nostr.background-fetch")? {
+            self.background_fetch.set(
+                v,
+                ConfigSource::GitConfig("nostr.background-fetch".to_string()),
+            );
+        }
+        if let Some(v) = git_config_bool("nostr.quiet-clone")? {
+            self.quiet_clone
+                .set(v, ConfigSource::GitConfig("nostr.quiet-clone".to_string()));
+        }
+        Ok(())
+    }
+
+    fn apply_proxy(&mut self, git_repo: &Option<&Repo>) -> Result<()> {
+        if let Some(proxy) = get_proxy(git_repo, ProxyUse::Relays)? {
+            self.relay_proxy
+                .set(Some(socks_proxy_addr(&proxy.value)?), proxy.source);
+        }
+        if let Some(proxy) = get_proxy(git_repo, ProxyUse::GitServers)? {
+            self.git_proxy.set(Some(proxy.value), proxy.source);
+        }
+        Ok(())
+    }
+}
+
+/// integration tests run the remote helper in quick succession against
+/// changing relays so the cache is only used when a test opts in
+fn default_cache_max_age_secs() -> u64 {
+    if std::env::var("NGITTEST").is_ok() {
+        0
+    } else {
+        DEFAULT_CACHE_MAX_AGE_SECS
+    }
+}
+
+/// during integration tests the config file lives in the test git repo so
+/// it doesn't interfere with the user's config
+pub fn get_config_file_path(git_repo: &Option<&Repo>) -> Option<PathBuf> {
+    if std::env::var("NGITTEST").is_ok() {
+        git_repo
+            .as_ref()
+            .and_then(|git_repo| git_repo.get_path().ok())
+            .map(|path| get_git_dir(path).join("test-config.toml"))
+    } else {
+        get_dirs()
+            .ok()
+            .map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod apply_config_file {
+        use super::*;
+
+        fn parse(s: &str) -> Result<ConfigFile> {
+            Ok(toml::from_str(s)?)
+        }
+
+        #[test]
+        fn overrides_defaults_and_records_source() -> Result<()> {
+            let mut config = Config::default();
+            let path = PathBuf::from("/home/user/.config/ngit/config.toml");
+            config.apply_config_file(
+                parse(
+                    "fallback_relays = [\"wss://relay.example.com\"]\ncolor = \"never\"\nrelay_timeout_secs = 15\n",
+                )?,
+                &path,
+            );
+            assert_eq!(config.fallback_relays.value, vec![
+                "wss://relay.example.com".to_string()
+            ]);
+            assert_eq!(
+                config.fallback_relays.source,
+                ConfigSource::ConfigFile(path.clone())
+            );
+            assert_eq!(config.color.value, ColorChoice::Never);
+            assert_eq!(config.relay_timeout_secs.value, 15);
+            Ok(())
+        }
+
+        #[test]
+        fn missing_keys_keep_defaults() -> Result<()> {
+            let mut config = Config::default();
+            config.apply_config_file(parse("editor = \"vim\"\n")?, &PathBuf::from("config.toml"));
+            assert_eq!(config.fallback_relays, Config::default().fallback_relays);
+            assert_eq!(config.editor.value, Some("vim".to_string()));
+            Ok(())
+        }
+
+        #[test]
+        fn unknown_keys_error() {
+            assert!(parse("fallback_relay = []\n").is_err());
+        }
+
+        #[test]
+        fn invalid_color_errors() {
+            assert!(parse("color = \"sometimes\"\n").is_err());
+        }
+    }
+
+    mod apply_git_config {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        fn config_with(item: &str, value: &str) -> Result<Config> {
+            let test_repo = GitTestRepo::default();
+            test_repo.git_repo.config()?.set_str(item, value)?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let mut config = Config::default();
+            config.apply_git_config(&Some(&git_repo))?;
+            Ok(config)
+        }
+
+        #[test]
+        fn labels_allow_anyone_accepts_git_booleans() -> Result<()> {
+            assert!(
+                config_with("nostr.labels-allow-anyone", "yes")?
+                    .labels_allow_anyone
+                    .value
+            );
+            assert!(
+                !config_with("nostr.labels-allow-anyone", "off")?
+                    .labels_allow_anyone
+                    .value
+            );
+            assert!(config_with("nostr.labels-allow-anyone", "maybe").is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn use_blaster_accepts_git_booleans() -> Result<()> {
+            assert!(config_with("nostr.use-blaster", "yes")?.use_blaster.value);
+            assert!(!config_with("nostr.use-blaster", "off")?.use_blaster.value);
+            assert!(config_with("nostr.use-blaster", "maybe").is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn background_fetch_accepts_git_booleans() -> Result<()> {
+            assert!(
+                config_with("nostr.background-fetch", "yes")?
+                    .background_fetch
+                    .value
+            );
+            assert!(
+                !config_with("nostr.background-fetch", "off")?
+                    .background_fetch
+                    .value
+            );
+            assert!(config_with("nostr.background-fetch", "maybe").is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn quiet_clone_accepts_git_booleans() -> Result<()> {
+            assert!(config_with("nostr.quiet-clone", "yes")?.quiet_clone.value);
+            assert!(!config_with("nostr.quiet-clone", "off")?.quiet_clone.value);
+            assert!(config_with("nostr.quiet-clone", "maybe").is_err());
+            Ok(())
+        }
+    }
+}
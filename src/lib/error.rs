@@ -0,0 +1,154 @@
+use std::{fmt, process::ExitCode};
+
+use anyhow::Result;
+
+/// failure categories that scripts can tell apart by exit code. the wrapped
+/// error is displayed unchanged so messages stay the same
+#[derive(Debug)]
+pub enum NgitError {
+    Config(anyhow::Error),
+    Auth(anyhow::Error),
+    Network(anyhow::Error),
+    Git(anyhow::Error),
+    NotFound(anyhow::Error),
+    UserAbort(anyhow::Error),
+}
+
+pub static EXIT_CODES_HELP: &str = "exit codes:\n  0    success\n  1    other error\n  2    config error, including invalid arguments\n  3    auth / login error\n  4    network / relay error\n  5    git error\n  6    not found\n  130  aborted by user";
+
+impl NgitError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Config(_) => 2,
+            Self::Auth(_) => 3,
+            Self::Network(_) => 4,
+            Self::Git(_) => 5,
+            Self::NotFound(_) => 6,
+            Self::UserAbort(_) => 130,
+        }
+    }
+
+    fn inner(&self) -> &anyhow::Error {
+        match self {
+            Self::Config(e)
+            | Self::Auth(e)
+            | Self::Network(e)
+            | Self::Git(e)
+            | Self::NotFound(e)
+            | Self::UserAbort(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for NgitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+impl std::error::Error for NgitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner().source()
+    }
+}
+
+pub trait ErrorCategory<T> {
+    /// tag the error with a category, eg. `.category(NgitError::Network)`
+    fn category(self, category: fn(anyhow::Error) -> NgitError) -> Result<T>;
+}
+
+impl<T, E> ErrorCategory<T> for std::result::Result<T, E>
+where
+    E: Into<anyhow::Error>,
+{
+    fn category(self, category: fn(anyhow::Error) -> NgitError) -> Result<T> {
+        self.map_err(|e| category(e.into()).into())
+    }
+}
+
+/// exit code for an error reaching the binary boundary. the outermost
+/// category wins; untagged git2 errors count as git errors and interrupted
+/// prompts as user aborts
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    if let Some(e) = error.chain().find_map(|e| e.downcast_ref::<NgitError>()) {
+        return e.exit_code();
+    }
+    if error
+        .chain()
+        .any(|e| e.downcast_ref::<git2::Error>().is_some())
+    {
+        return 5;
+    }
+    if error.chain().any(|e| {
+        e.downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::Interrupted)
+    }) {
+        return 130;
+    }
+    1
+}
+
+/// print the error as returning it from `main` would and exit with its code
+pub fn report_and_exit(result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, anyhow};
+
+    use super::*;
+
+    mod exit_code {
+        use super::*;
+
+        #[test]
+        fn untagged_error_is_1() {
+            assert_eq!(exit_code(&anyhow!("something went wrong")), 1);
+        }
+
+        #[test]
+        fn category_survives_later_context() {
+            let error = Err::<(), _>(anyhow!("relay unreachable"))
+                .category(NgitError::Network)
+                .context("failed to fetch")
+                .unwrap_err();
+            assert_eq!(exit_code(&error), 4);
+        }
+
+        #[test]
+        fn outermost_category_wins() {
+            let error = Err::<(), _>(anyhow!("no announcement"))
+                .category(NgitError::NotFound)
+                .category(NgitError::Network)
+                .unwrap_err();
+            assert_eq!(exit_code(&error), 4);
+        }
+
+        #[test]
+        fn git2_error_is_git_error() {
+            let error = Err::<(), _>(git2::Error::from_str("not a repository"))
+                .context("failed to find a git repository")
+                .unwrap_err();
+            assert_eq!(exit_code(&error), 5);
+        }
+    }
+
+    #[test]
+    fn message_is_unchanged_by_category() {
+        let untagged = Err::<(), _>(anyhow!("Invalid secret key"))
+            .context("invalid nsec parameter")
+            .unwrap_err();
+        let tagged = Err::<(), _>(anyhow!("Invalid secret key"))
+            .context("invalid nsec parameter")
+            .category(NgitError::Auth)
+            .unwrap_err();
+        assert_eq!(format!("{tagged:?}"), format!("{untagged:?}"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::{EventId, Timestamp};
+
+use crate::git::get_git_dir;
+
+/// relays each cached event was received from, with the unix timestamp it
+/// was last received, keyed by hex event id then relay url
+type EventSources = HashMap<String, BTreeMap<String, u64>>;
+
+fn get_event_sources_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-event-sources.json")
+}
+
+fn read_event_sources(git_repo_path: &Path) -> EventSources {
+    std::fs::read_to_string(get_event_sources_path(git_repo_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// record that each event was received from its relays at `now`. relays that
+/// didn't return an event this time are kept as fetches are often limited
+pub fn record_event_sources(
+    git_repo_path: &Path,
+    received: &HashMap<EventId, BTreeSet<String>>,
+    now: Timestamp,
+) -> Result<()> {
+    if received.is_empty() {
+        return Ok(());
+    }
+    let mut sources = read_event_sources(git_repo_path);
+    add_event_sources(&mut sources, received, now);
+    let path = get_event_sources_path(git_repo_path);
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&sources)?)
+        .context("failed to save which relays returned events")?;
+    std::fs::rename(tmp_path, path).context("failed to save which relays returned events")
+}
+
+fn add_event_sources(
+    sources: &mut EventSources,
+    received: &HashMap<EventId, BTreeSet<String>>,
+    now: Timestamp,
+) {
+    for (event_id, relays) in received {
+        let entry = sources.entry(event_id.to_hex()).or_default();
+        for relay in relays {
+            entry.insert(relay.trim_end_matches('/').to_string(), now.as_u64());
+        }
+    }
+}
+
+/// relays `event_id` was received from, with when it was last received
+pub fn get_event_sources(git_repo_path: &Path, event_id: &EventId) -> BTreeMap<String, u64> {
+    read_event_sources(git_repo_path)
+        .remove(&event_id.to_hex())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod add_event_sources {
+        use super::*;
+
+        #[test]
+        fn updates_when_received_and_keeps_relays_that_didnt_return_it() {
+            let event_id = EventId::all_zeros();
+            let mut sources = EventSources::new();
+            add_event_sources(
+                &mut sources,
+                &HashMap::from([(
+                    event_id,
+                    BTreeSet::from(["wss://a.relay/".to_string(), "wss://b.relay".to_string()]),
+                )]),
+                Timestamp::from(1_000),
+            );
+            add_event_sources(
+                &mut sources,
+                &HashMap::from([(event_id, BTreeSet::from(["wss://a.relay".to_string()]))]),
+                Timestamp::from(2_000),
+            );
+            assert_eq!(
+                sources.get(&event_id.to_hex()),
+                Some(&BTreeMap::from([
+                    ("wss://a.relay".to_string(), 2_000),
+                    ("wss://b.relay".to_string(), 1_000),
+                ]))
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result, bail};
+use git2::Oid;
+use nostr_sdk::Timestamp;
+
+use super::{Repo, RepoActions};
+
+/// backups of local branches ngit overwrote are kept under
+/// `refs/ngit/backup/<branch>/<unix timestamp>`
+pub static BACKUP_REF_PREFIX: &str = "refs/ngit/backup/";
+
+/// how long `ngit cache prune` keeps backups
+pub static BACKUP_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub ref_name: String,
+    pub branch_name: String,
+    /// unix timestamp the backup was taken
+    pub created_at: u64,
+    pub tip: Oid,
+}
+
+impl Backup {
+    fn from_ref_name(ref_name: &str, tip: Oid) -> Option<Self> {
+        let (branch_name, created_at) =
+            ref_name.strip_prefix(BACKUP_REF_PREFIX)?.rsplit_once('/')?;
+        Some(Self {
+            ref_name: ref_name.to_string(),
+            branch_name: branch_name.to_string(),
+            created_at: created_at.parse().ok()?,
+            tip,
+        })
+    }
+}
+
+/// save the tip of local branch `branch_name` before it is overwritten.
+/// returns the backup ref name, or None if there is no such branch
+pub fn backup_branch(git_repo: &Repo, branch_name: &str) -> Result<Option<String>> {
+    let Ok(branch) = git_repo
+        .git_repo
+        .find_branch(branch_name, git2::BranchType::Local)
+    else {
+        return Ok(None);
+    };
+    let tip = branch.into_reference().peel_to_commit()?.id();
+    // a second backup of the branch in the same second must not replace the
+    // first
+    let mut created_at = Timestamp::now().as_u64();
+    loop {
+        let ref_name = format!("{BACKUP_REF_PREFIX}{branch_name}/{created_at}");
+        match git_repo.git_repo.find_reference(&ref_name) {
+            Ok(existing) if existing.target() == Some(tip) => return Ok(Some(ref_name)),
+            Ok(_) => created_at += 1,
+            Err(_) => {
+                git_repo
+                    .git_repo
+                    .reference(&ref_name, tip, false, "ngit: backup before overwrite")
+                    .context(format!("failed to create backup ref {ref_name}"))?;
+                return Ok(Some(ref_name));
+            }
+        }
+    }
+}
+
+/// backups newest first
+pub fn list_backups(git_repo: &Repo) -> Result<Vec<Backup>> {
+    let mut backups = vec![];
+    for reference in git_repo
+        .git_repo
+        .references_glob(&format!("{BACKUP_REF_PREFIX}*"))?
+    {
+        let reference = reference?;
+        if let (Some(ref_name), Some(tip)) = (reference.name(), reference.target()) {
+            if let Some(backup) = Backup::from_ref_name(ref_name, tip) {
+                backups.push(backup);
+            }
+        }
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// find a backup by its ref name, with or without the `refs/ngit/backup/`
+/// prefix
+pub fn find_backup(git_repo: &Repo, name: &str) -> Result<Backup> {
+    let ref_name = if name.starts_with(BACKUP_REF_PREFIX) {
+        name.to_string()
+    } else {
+        format!("{BACKUP_REF_PREFIX}{name}")
+    };
+    list_backups(git_repo)?
+        .into_iter()
+        .find(|backup| backup.ref_name == ref_name)
+        .context(format!(
+            "no backup {ref_name}. run `ngit backups list` to see backups"
+        ))
+}
+
+/// point the backup's branch at its tip again. the branch's current tip is
+/// backed up first and its ref name returned
+pub fn restore_backup(git_repo: &Repo, backup: &Backup) -> Result<Option<String>> {
+    if git_repo.get_checked_out_branch_name()? == backup.branch_name
+        && git_repo.has_outstanding_changes()?
+    {
+        bail!(
+            "failed to restore '{}' when repository is not clean. discard or stash (un)staged changes and try again.",
+            backup.branch_name
+        );
+    }
+    let current_tip_backup = backup_branch(git_repo, &backup.branch_name)?;
+    git_repo.create_branch_at_commit(&backup.branch_name, &backup.tip.to_string())?;
+    Ok(current_tip_backup)
+}
+
+/// delete backups taken more than `max_age_secs` before `now`. returns
+/// those deleted
+pub fn prune_backups(git_repo: &Repo, max_age_secs: u64, now: Timestamp) -> Result<Vec<Backup>> {
+    let mut pruned = vec![];
+    for backup in list_backups(git_repo)? {
+        if now.as_u64().saturating_sub(backup.created_at) > max_age_secs {
+            git_repo
+                .git_repo
+                .find_reference(&backup.ref_name)?
+                .delete()
+                .context(format!("failed to delete backup ref {}", backup.ref_name))?;
+            pruned.push(backup);
+        }
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn prep_repo_with_feature_branch() -> Result<(GitTestRepo, Repo, Oid)> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        test_repo.create_branch("feature")?;
+        test_repo.checkout("feature")?;
+        std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+        let tip = test_repo.stage_and_commit("add t3.md")?;
+        test_repo.checkout("main")?;
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+        Ok((test_repo, git_repo, tip))
+    }
+
+    #[test]
+    fn backup_then_restore_brings_back_old_tip() -> Result<()> {
+        let (test_repo, git_repo, tip) = prep_repo_with_feature_branch()?;
+        let ref_name = backup_branch(&git_repo, "feature")?.unwrap();
+        assert!(ref_name.starts_with("refs/ngit/backup/feature/"));
+
+        let main_tip = test_repo.get_tip_of_local_branch("main")?;
+        git_repo.create_branch_at_commit("feature", &main_tip.to_string())?;
+        assert_eq!(test_repo.get_tip_of_local_branch("feature")?, main_tip);
+
+        restore_backup(&git_repo, &find_backup(&git_repo, &ref_name)?)?;
+        assert_eq!(test_repo.get_tip_of_local_branch("feature")?, tip);
+        Ok(())
+    }
+
+    #[test]
+    fn second_backup_in_same_second_kept() -> Result<()> {
+        let (test_repo, git_repo, _) = prep_repo_with_feature_branch()?;
+        backup_branch(&git_repo, "feature")?;
+        let main_tip = test_repo.get_tip_of_local_branch("main")?;
+        git_repo.create_branch_at_commit("feature", &main_tip.to_string())?;
+        backup_branch(&git_repo, "feature")?;
+        assert_eq!(list_backups(&git_repo)?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn no_backup_without_branch() -> Result<()> {
+        let (_test_repo, git_repo, _) = prep_repo_with_feature_branch()?;
+        assert_eq!(backup_branch(&git_repo, "missing")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn prune_only_removes_old_backups() -> Result<()> {
+        let (_test_repo, git_repo, _) = prep_repo_with_feature_branch()?;
+        let ref_name = backup_branch(&git_repo, "feature")?.unwrap();
+        let created_at = find_backup(&git_repo, &ref_name)?.created_at;
+
+        let not_yet = Timestamp::from(created_at + BACKUP_MAX_AGE_SECS);
+        assert!(prune_backups(&git_repo, BACKUP_MAX_AGE_SECS, not_yet)?.is_empty());
+        assert_eq!(list_backups(&git_repo)?.len(), 1);
+
+        let later = Timestamp::from(created_at + BACKUP_MAX_AGE_SECS + 1);
+        assert_eq!(
+            prune_backups(&git_repo, BACKUP_MAX_AGE_SECS, later)?.len(),
+            1
+        );
+        assert!(list_backups(&git_repo)?.is_empty());
+        Ok(())
+    }
+}
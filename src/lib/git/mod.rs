@@ -1,7 +1,4 @@
-use std::{
-    env::current_dir,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, bail};
 use git2::{DiffOptions, Oid, Revwalk};
@@ -12,8 +9,11 @@ use nostr_sdk::{
 };
 
 use crate::git_events::{get_commit_id_from_patch, tag_value};
+pub mod backup;
 pub mod identify_ahead_behind;
 pub mod nostr_url;
+pub mod patch_id;
+pub mod proposal_notes;
 pub mod utils;
 
 pub struct Repo {
@@ -21,23 +21,96 @@ pub struct Repo {
 }
 
 impl Repo {
+    /// the repository in GIT_DIR, as scripts run by git see it, otherwise
+    /// the one containing the current directory. it may be bare
     pub fn discover() -> Result<Self> {
         Ok(Self {
-            git_repo: git2::Repository::discover(current_dir()?)?,
+            git_repo: git2::Repository::open_from_env()?,
         })
     }
+    /// `path` can be a git directory or a .git file pointing to one, as
+    /// linked worktrees use
     pub fn from_path(path: &PathBuf) -> Result<Self> {
+        let git_dir = if path.is_file() {
+            resolve_gitdir_file(path)?
+        } else {
+            path.clone()
+        };
         Ok(Self {
-            git_repo: git2::Repository::open(path)?,
+            git_repo: git2::Repository::open(git_dir)?,
         })
     }
+
+    fn find_commit(&self, rev: &str) -> Result<Sha1Hash> {
+        Ok(oid_to_sha1(
+            &self
+                .git_repo
+                .revparse_single(rev)
+                .and_then(git2::Object::peel_to_commit)
+                .context(format!("failed to find commit {rev}"))?
+                .id(),
+        ))
+    }
+
+    /// commits in the history of `to` but not `from`, youngest first, as
+    /// `git rev-list from..to` lists them
+    fn rev_list(&self, from: &Sha1Hash, to: &Sha1Hash) -> Result<Vec<Sha1Hash>> {
+        let mut revwalk = self
+            .git_repo
+            .revwalk()
+            .context("revwalk should be created from git repo")?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(sha1_to_oid(to)?)?;
+        revwalk.hide(sha1_to_oid(from)?)?;
+        revwalk
+            .map(|oid| Ok(oid_to_sha1(&oid?)))
+            .collect::<Result<Vec<Sha1Hash>>>()
+    }
+}
+
+/// follow the `gitdir: <path>` line of a .git file
+fn resolve_gitdir_file(path: &Path) -> Result<PathBuf> {
+    let content = std::fs::read_to_string(path)
+        .context(format!("failed to read git dir file {}", path.display()))?;
+    let git_dir = content
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .context(format!(
+            "{} is neither a git directory nor a gitdir file",
+            path.display()
+        ))?
+        .trim();
+    // relative paths are relative to the directory containing the file
+    Ok(path.parent().unwrap_or(Path::new("")).join(git_dir))
+}
+
+/// where git, and ngit's cache, keep files for the repository at
+/// `git_repo_path`, as returned by [`RepoActions::get_path`]. this is .git
+/// unless the repository is bare
+pub fn get_git_dir(git_repo_path: &Path) -> PathBuf {
+    let dot_git = git_repo_path.join(".git");
+    if !dot_git.exists()
+        && git_repo_path.join("HEAD").is_file()
+        && git_repo_path.join("objects").is_dir()
+    {
+        git_repo_path.to_path_buf()
+    } else {
+        dot_git
+    }
 }
 
 // pub type CommitId = [u8; 7];
 // pub type Sha1 = [u8; 20];
 
 pub trait RepoActions {
+    /// directory containing the main .git directory, shared by all linked
+    /// worktrees so they use the same nostr cache, or a bare repository
+    /// itself. see [`get_git_dir`]
     fn get_path(&self) -> Result<&Path>;
+    /// checked out files, which in a linked worktree are not under
+    /// [`RepoActions::get_path`]. a bare repository has none so this is
+    /// the repository itself
+    fn get_workdir(&self) -> Result<&Path>;
     fn get_origin_url(&self) -> Result<String>;
     fn get_remote_branch_names(&self) -> Result<Vec<String>>;
     fn get_local_branch_names(&self) -> Result<Vec<String>>;
@@ -48,6 +121,7 @@ pub trait RepoActions {
     fn get_tip_of_branch(&self, branch_name: &str) -> Result<Sha1Hash>;
     fn get_commit_or_tip_of_reference(&self, reference: &str) -> Result<Sha1Hash>;
     fn get_root_commit(&self) -> Result<Sha1Hash>;
+    fn get_root_commit_of(&self, commit: &Sha1Hash) -> Result<Sha1Hash>;
     fn does_commit_exist(&self, commit: &str) -> Result<bool>;
     fn get_head_commit(&self) -> Result<Sha1Hash>;
     fn get_commit_parent(&self, commit: &Sha1Hash) -> Result<Sha1Hash>;
@@ -89,6 +163,8 @@ pub trait RepoActions {
     ) -> Result<Oid>;
     fn parse_starting_commits(&self, starting_commits: &str) -> Result<Vec<Sha1Hash>>;
     fn ancestor_of(&self, decendant: &Sha1Hash, ancestor: &Sha1Hash) -> Result<bool>;
+    /// most recent commit in the history of both, or None if they share none
+    fn get_merge_base(&self, a: &Sha1Hash, b: &Sha1Hash) -> Result<Option<Sha1Hash>>;
     fn get_git_config_item(&self, item: &str, global: Option<bool>) -> Result<Option<String>>;
     fn save_git_config_item(&self, item: &str, value: &str, global: bool) -> Result<()>;
     fn remove_git_config_item(&self, item: &str, global: bool) -> Result<bool>;
@@ -96,12 +172,22 @@ pub trait RepoActions {
 
 impl RepoActions for Repo {
     fn get_path(&self) -> Result<&Path> {
+        if self.git_repo.is_bare() {
+            return Ok(self.git_repo.commondir());
+        }
         self.git_repo
-            .path()
+            .commondir()
             .parent()
             .context("failed to find repositiory path as .git has  no parent")
     }
 
+    fn get_workdir(&self) -> Result<&Path> {
+        match self.git_repo.workdir() {
+            Some(workdir) => Ok(workdir),
+            None => self.get_path(),
+        }
+    }
+
     fn get_origin_url(&self) -> Result<String> {
         Ok(self
             .git_repo
@@ -246,12 +332,16 @@ impl RepoActions for Repo {
     }
 
     fn get_root_commit(&self) -> Result<Sha1Hash> {
+        self.get_root_commit_of(&self.get_head_commit()?)
+    }
+
+    fn get_root_commit_of(&self, commit: &Sha1Hash) -> Result<Sha1Hash> {
         let mut revwalk = self
             .git_repo
             .revwalk()
             .context("revwalk should be created from git repo")?;
         revwalk
-            .push(sha1_to_oid(&self.get_head_commit()?)?)
+            .push(sha1_to_oid(commit)?)
             .context("revwalk should accept tip oid")?;
         Ok(oid_to_sha1(
             &revwalk
@@ -363,14 +453,28 @@ impl RepoActions for Repo {
                 &commit
             ))?)
             .context(format!("failed to find commit {}", &commit))?;
-        let mut options = git2::EmailCreateOptions::default();
-        if let Some((n, total)) = series_count {
-            options.subject_prefix(format!("PATCH {n}/{total}"));
-        }
-        let patch = git2::Email::from_commit(&c, &mut options)
-            .context(format!("failed to create patch from commit {}", &commit))?;
+        let email = |force_binary: bool| {
+            let mut options = git2::EmailCreateOptions::default();
+            if let Some((n, total)) = series_count {
+                options.subject_prefix(format!("PATCH {n}/{total}"));
+            }
+            // binary files as base85 `GIT binary patch` literals and deltas,
+            // like `git format-patch --binary`
+            options
+                .diff_options()
+                .show_binary(true)
+                .force_binary(force_binary);
+            git2::Email::from_commit(&c, &mut options)
+                .context(format!("failed to create patch from commit {}", &commit))
+        };
 
-        Ok(std::str::from_utf8(patch.as_slice())
+        let patch = email(false)?;
+        if let Ok(patch) = std::str::from_utf8(patch.as_slice()) {
+            return Ok(patch.to_owned());
+        }
+        // files git treats as text, such as latin-1, can't be put in event
+        // content as is. binary patches of them are plain ascii
+        Ok(std::str::from_utf8(email(true)?.as_slice())
             .context("patch content could not be converted to a utf8 string")?
             .to_owned())
     }
@@ -661,46 +765,49 @@ impl RepoActions for Repo {
         Ok(applied_oid)
     }
     fn parse_starting_commits(&self, starting_commits: &str) -> Result<Vec<Sha1Hash>> {
-        let revspec = self
-            .git_repo
-            .revparse(starting_commits)
-            .context("specified value not in a valid format")?;
-        if revspec.mode().is_no_single() {
-            let (ahead, _) = self
-                .get_commits_ahead_behind(
-                    &oid_to_sha1(
-                        &revspec
-                            .from()
-                            .context("failed to get starting commit from specified value")?
-                            .id(),
-                    ),
-                    &self
-                        .get_head_commit()
-                        .context("failed to get head commit with gitlib2")?,
-                )
-                .context("specified commit is not an ancestor of current head")?;
-            Ok(ahead)
-        } else if revspec.mode().is_range() {
-            let (ahead, _) = self
-                .get_commits_ahead_behind(
-                    &oid_to_sha1(
-                        &revspec
-                            .from()
-                            .context("failed to get starting commit of range from specified value")?
-                            .id(),
-                    ),
-                    &oid_to_sha1(
-                        &revspec
-                            .to()
-                            .context("failed to get end of range commit from specified value")?
-                            .id(),
-                    ),
-                )
-                .context("specified commit is not an ancestor of current head")?;
-            Ok(ahead)
-        } else {
-            bail!("specified value not in a supported format")
+        let (from, to) = match parse_commit_range(starting_commits)? {
+            CommitRange::Between(from, to) => (self.find_commit(from)?, self.find_commit(to)?),
+            CommitRange::Single(name) => {
+                if let Ok(branch) = self.git_repo.find_branch(name, git2::BranchType::Local) {
+                    let upstream = if let Ok(upstream) = branch.upstream() {
+                        oid_to_sha1(
+                            &upstream
+                                .get()
+                                .peel_to_commit()
+                                .context("failed to find commit of upstream branch")?
+                                .id(),
+                        )
+                    } else {
+                        self.get_main_or_master_branch()?.1
+                    };
+                    (
+                        upstream,
+                        oid_to_sha1(
+                            &branch
+                                .get()
+                                .peel_to_commit()
+                                .context("failed to find commit of branch")?
+                                .id(),
+                        ),
+                    )
+                } else {
+                    (
+                        self.find_commit(name)?,
+                        self.get_head_commit()
+                            .context("failed to get head commit with gitlib2")?,
+                    )
+                }
+            }
+        };
+        let commits = self.rev_list(&from, &to)?;
+        if commits.is_empty() {
+            bail!(
+                "{starting_commits} contains no commits as {} is already in the history of {}",
+                &to.to_string()[..7],
+                &from.to_string()[..7],
+            );
         }
+        Ok(commits)
     }
 
     fn ancestor_of(&self, decendant: &Sha1Hash, ancestor: &Sha1Hash) -> Result<bool> {
@@ -715,6 +822,14 @@ impl RepoActions for Repo {
         }
     }
 
+    fn get_merge_base(&self, a: &Sha1Hash, b: &Sha1Hash) -> Result<Option<Sha1Hash>> {
+        match self.git_repo.merge_base(sha1_to_oid(a)?, sha1_to_oid(b)?) {
+            Ok(oid) => Ok(Some(oid_to_sha1(&oid))),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(error) => Err(error).context("could not run merge_base in gitlib2"),
+        }
+    }
+
     /// setting global to None will suppliment local config with global items
     /// not in local
     fn get_git_config_item(&self, item: &str, global: Option<bool>) -> Result<Option<String>> {
@@ -823,6 +938,39 @@ pub fn oid_to_shorthand_string(oid: Oid) -> Result<String> {
 // }
 
 // git2 Oid object to Sha1Hash
+/// a range of commits as accepted by `ngit send`
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommitRange<'a> {
+    /// `A..B`: commits in the history of B but not A. either side defaults to
+    /// HEAD
+    Between(&'a str, &'a str),
+    /// a branch name meaning `upstream..branch`, otherwise a commit such as
+    /// `HEAD~2` meaning `commit..HEAD`
+    Single(&'a str),
+}
+
+pub fn parse_commit_range(range: &str) -> Result<CommitRange<'_>> {
+    let range = range.trim();
+    if range.is_empty() {
+        bail!("no commits or range specified");
+    }
+    if range.contains("...") {
+        bail!(
+            "symmetric difference ranges like {range} are not supported. use A..B to send the commits in B that are not in A"
+        );
+    }
+    match range.split_once("..") {
+        None => Ok(CommitRange::Single(range)),
+        Some((_, to)) if to.contains("..") => {
+            bail!("{range} is not a valid range. use A..B, a branch name or a commit like HEAD~2")
+        }
+        Some((from, to)) => Ok(CommitRange::Between(
+            if from.is_empty() { "HEAD" } else { from },
+            if to.is_empty() { "HEAD" } else { to },
+        )),
+    }
+}
+
 pub fn oid_to_sha1(oid: &Oid) -> Sha1Hash {
     Sha1Hash::from_byte_array(oid_to_u8_20_bytes(oid))
 }
@@ -909,6 +1057,12 @@ pub fn remove_git_config_item(git_repo: &Option<&Repo>, item: &str) -> Result<bo
     }
 }
 
+/// parse a boolean git config value the way git does so `yes`, `on` and `1`
+/// are true as well as `true`
+pub fn parse_git_config_bool(value: &str) -> Result<bool> {
+    git2::Config::parse_bool(value).context(format!("{value} isn't a boolean"))
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -1004,6 +1158,26 @@ mod tests {
         Ok(())
     }
 
+    mod parse_git_config_bool {
+        use super::*;
+
+        #[test]
+        fn accepts_git_boolean_values() -> Result<()> {
+            for value in ["true", "yes", "on", "1", "TRUE"] {
+                assert!(parse_git_config_bool(value)?, "{value}");
+            }
+            for value in ["false", "no", "off", "0", ""] {
+                assert!(!parse_git_config_bool(value)?, "{value}");
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn rejects_other_values() {
+            assert!(parse_git_config_bool("maybe").is_err());
+        }
+    }
+
     mod get_commit_message {
         use super::*;
         fn run(message: &str) -> Result<()> {
@@ -1417,6 +1591,76 @@ mod tests {
         }
     }
 
+    mod linked_worktree {
+        use super::*;
+
+        #[test]
+        fn get_path_is_main_repository_and_workdir_is_worktree() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let worktree = test_repo.add_worktree("feature")?;
+
+            let git_repo = Repo::from_path(&worktree.dir)?;
+            assert_eq!(
+                git_repo.get_path()?.canonicalize()?,
+                test_repo.dir.canonicalize()?
+            );
+            assert_eq!(
+                git_repo.get_workdir()?.canonicalize()?,
+                worktree.dir.canonicalize()?
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn from_path_follows_gitdir_file() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let worktree = test_repo.add_worktree("feature")?;
+
+            let git_repo = Repo::from_path(&worktree.dir.join(".git"))?;
+            assert_eq!(
+                git_repo.get_path()?.canonicalize()?,
+                test_repo.dir.canonicalize()?
+            );
+            Ok(())
+        }
+    }
+
+    mod bare_repository {
+        use super::*;
+
+        #[test]
+        fn get_path_is_repository_and_git_dir_is_repository() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let bare_repo = GitTestRepo::recreate_as_bare(&test_repo)?;
+
+            let git_repo = Repo::from_path(&bare_repo.dir)?;
+            assert_eq!(
+                git_repo.get_path()?.canonicalize()?,
+                bare_repo.dir.canonicalize()?
+            );
+            assert_eq!(
+                get_git_dir(git_repo.get_path()?).canonicalize()?,
+                bare_repo.dir.canonicalize()?
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn git_dir_of_non_bare_repository_is_dot_git() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            assert_eq!(
+                get_git_dir(git_repo.get_path()?).canonicalize()?,
+                test_repo.dir.join(".git").canonicalize()?
+            );
+            Ok(())
+        }
+    }
+
     mod get_origin_url {
         use super::*;
 
@@ -1722,6 +1966,7 @@ mod tests {
                 None,
                 &None,
                 &[],
+                None,
             )
             .await
         }
@@ -1845,6 +2090,54 @@ mod tests {
                 )
             }
         }
+
+        mod binary_files {
+            use super::*;
+
+            // a png header includes bytes that are neither utf8 nor text
+            static PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x00\x01";
+
+            fn blob_id(git_repo: &git2::Repository, commit: Oid, path: &str) -> Result<Oid> {
+                Ok(git_repo
+                    .find_commit(commit)?
+                    .tree()?
+                    .get_path(Path::new(path))?
+                    .id())
+            }
+
+            #[tokio::test]
+            async fn added_and_modified_blobs_identical_after_applying() -> Result<()> {
+                let source_repo = GitTestRepo::default();
+                source_repo.populate()?;
+                fs::write(source_repo.dir.join("image.png"), PNG)?;
+                // latin-1 text is diffed as text but isn't valid utf8
+                fs::write(source_repo.dir.join("latin1.txt"), b"caf\xe9 au lait\n")?;
+                source_repo.stage_and_commit("add image.png and latin1.txt")?;
+                let added = generate_patch_from_head_commit(&source_repo).await?;
+                fs::write(
+                    source_repo.dir.join("image.png"),
+                    [PNG, &b"\x00\xff\xfe more pixels"[..]].concat(),
+                )?;
+                let source_tip = source_repo.stage_and_commit("modify image.png")?;
+                let modified = generate_patch_from_head_commit(&source_repo).await?;
+                assert!(added.content.contains("GIT binary patch"));
+                assert!(modified.content.contains("GIT binary patch"));
+
+                let test_repo = GitTestRepo::default();
+                test_repo.populate()?;
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                git_repo.create_commit_from_patch(&added, None)?;
+                let tip = git_repo.create_commit_from_patch(&modified, None)?;
+                assert_eq!(tip, source_tip);
+                for path in ["image.png", "latin1.txt"] {
+                    assert_eq!(
+                        blob_id(&test_repo.git_repo, tip, path)?,
+                        blob_id(&source_repo.git_repo, source_tip, path)?,
+                    );
+                }
+                Ok(())
+            }
+        }
     }
 
     mod apply_patch_chain {
@@ -1871,7 +2164,9 @@ mod tests {
                 &TEST_KEY_1_SIGNER,
                 &RepoRef::try_from((generate_repo_ref_event(), None)).unwrap(),
                 &None,
+                &None,
                 &[],
+                None,
             )
             .await?;
 
@@ -2199,6 +2494,57 @@ mod tests {
             }
         }
     }
+    mod parse_commit_range {
+        use super::*;
+
+        #[test]
+        fn dot_dot_range() -> Result<()> {
+            assert_eq!(
+                parse_commit_range("main..feature")?,
+                CommitRange::Between("main", "feature")
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn missing_side_of_range_is_head() -> Result<()> {
+            assert_eq!(
+                parse_commit_range("main..")?,
+                CommitRange::Between("main", "HEAD")
+            );
+            assert_eq!(
+                parse_commit_range("..feature")?,
+                CommitRange::Between("HEAD", "feature")
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn branch_name_or_commit() -> Result<()> {
+            assert_eq!(parse_commit_range("HEAD~2")?, CommitRange::Single("HEAD~2"));
+            assert_eq!(
+                parse_commit_range("feature")?,
+                CommitRange::Single("feature")
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn symmetric_difference_errors() {
+            assert_eq!(
+                parse_commit_range("main...feature")
+                    .unwrap_err()
+                    .to_string(),
+                "symmetric difference ranges like main...feature are not supported. use A..B to send the commits in B that are not in A"
+            );
+        }
+
+        #[test]
+        fn more_than_one_range_errors() {
+            assert!(parse_commit_range("a..b..c").is_err());
+        }
+    }
+
     mod parse_starting_commits {
         use super::*;
 
@@ -2264,6 +2610,43 @@ mod tests {
                 Ok(())
             }
         }
+        mod branch_name_returns_commits_ahead_of_main_when_no_upstream {
+            use super::*;
+
+            #[test]
+            fn when_on_main() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                test_repo.populate_with_test_branch()?;
+                test_repo.checkout("main")?;
+
+                assert_eq!(git_repo.parse_starting_commits("add-example-feature")?, vec![
+                    str_to_sha1("82ff2bcc9aa94d1bd8faee723d4c8cc190d6061c")?,
+                    str_to_sha1("a23e6b05aaeb7d1471b4a838b51f337d5644eeb0")?,
+                    str_to_sha1("7ab82116068982671a8111f27dc10599172334b2")?,
+                ],);
+                Ok(())
+            }
+        }
+        mod empty_range_errors {
+            use super::*;
+
+            #[test]
+            fn when_range_end_is_in_history_of_start() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                test_repo.populate_with_test_branch()?;
+
+                assert_eq!(
+                    git_repo
+                        .parse_starting_commits("HEAD..main")
+                        .unwrap_err()
+                        .to_string(),
+                    "HEAD..main contains no commits as 431b84e is already in the history of 82ff2bc"
+                );
+                Ok(())
+            }
+        }
         mod range_of_3_commits_not_in_branch_history_returns_3_commits_youngest_first {
             use super::*;
 
@@ -2376,4 +2759,30 @@ mod tests {
             Ok(())
         }
     }
+
+    mod get_merge_base {
+        use super::*;
+
+        #[test]
+        fn returns_commit_where_branches_diverged() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let diverged_at = test_repo.populate()?;
+
+            test_repo.create_branch("feature")?;
+            std::fs::write(test_repo.dir.join("notfeature.md"), "some content")?;
+            let main_oid = test_repo.stage_and_commit("add notfeature.md")?;
+
+            test_repo.checkout("feature")?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            let feature_oid = test_repo.stage_and_commit("add t3.md")?;
+
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            assert_eq!(
+                git_repo.get_merge_base(&oid_to_sha1(&main_oid), &oid_to_sha1(&feature_oid))?,
+                Some(oid_to_sha1(&diverged_at)),
+            );
+            Ok(())
+        }
+    }
 }
@@ -1,11 +1,23 @@
 use core::fmt;
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+};
 
 use anyhow::{Context, Error, Result, anyhow, bail};
 use nostr::nips::{nip01::Coordinate, nip05};
-use nostr_sdk::{PublicKey, RelayUrl, ToBech32, Url};
-
-use super::{Repo, get_git_config_item, save_git_config_item};
+use nostr_sdk::{Kind, PublicKey, RelayUrl, ToBech32, Url};
+
+use super::{Repo, RepoActions, get_git_config_item, remove_git_config_item, save_git_config_item};
+use crate::{
+    client::{
+        Client, Connect, Params, get_filter_repo_events, get_repo_ref_from_cache,
+        save_event_in_global_cache,
+    },
+    config::Config,
+    relay_hints::MAX_RELAY_HINTS,
+    repo_ref::RepoRef,
+};
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub enum ServerProtocol {
@@ -99,6 +111,8 @@ impl fmt::Display for NostrUrlDecoded {
 
 static INCORRECT_NOSTR_URL_FORMAT_ERROR: &str = "incorrect nostr git url format. try nostr://naddr123 or nostr://npub123/my-repo or nostr://ssh/npub123/relay.damus.io/my-repo";
 
+pub static NOSTR_URL_ALIAS_PREFIX: &str = "nostr://alias/";
+
 impl NostrUrlDecoded {
     pub async fn parse_and_resolve(url: &str, git_repo: &Option<&Repo>) -> Result<Self> {
         let mut protocol = None;
@@ -109,6 +123,27 @@ impl NostrUrlDecoded {
         if !url.starts_with("nostr://") {
             bail!("nostr git url must start with nostr://");
         }
+        // nostr://alias/<name> is displayed as is but resolved via git config
+        let original_string = url;
+        let resolved_alias;
+        let url = if let Some(name) = url.strip_prefix(NOSTR_URL_ALIAS_PREFIX) {
+            let name = name.trim_end_matches('/');
+            resolved_alias = get_nostr_url_alias(name, git_repo)?.context(format!(
+                "no nostr url alias '{name}'. add it with `ngit alias add {name} <nostr-url>`"
+            ))?;
+            if resolved_alias.starts_with(NOSTR_URL_ALIAS_PREFIX) {
+                bail!("nostr url alias '{name}' cannot point to another alias");
+            }
+            if !resolved_alias.starts_with("nostr://") {
+                bail!("nostr url alias '{name}' is not a nostr git url: {resolved_alias}");
+            }
+            resolved_alias.as_str()
+        } else {
+            url
+        };
+        // remotes written by older ngit versions are read in npub/identifier form
+        let legacy_as_npub_url = legacy_nostr_url_as_npub_url(url);
+        let url = legacy_as_npub_url.as_deref().unwrap_or(url);
         // process get url parameters if present
         for (name, value) in Url::parse(url)?.query_pairs() {
             if name.contains("relay") {
@@ -166,7 +201,7 @@ impl NostrUrlDecoded {
         // extract naddr npub/<optional-relays>/identifer
         let part = parts.first().context(INCORRECT_NOSTR_URL_FORMAT_ERROR)?;
         // naddr used
-        let coordinate = if let Ok(coordinate) = Coordinate::parse(part) {
+        let mut coordinate = if let Ok(coordinate) = Coordinate::parse(part) {
             if coordinate.kind.eq(&nostr_sdk::Kind::GitRepoAnnouncement) {
                 coordinate
             } else {
@@ -232,15 +267,237 @@ impl NostrUrlDecoded {
                 relays,
             }
         };
+        if legacy_as_npub_url.is_some() && coordinate.relays.is_empty() {
+            coordinate.relays = find_repo_relays_for_legacy_url(&coordinate, git_repo).await;
+        }
 
         Ok(Self {
-            original_string: url.to_string(),
+            original_string: original_string.to_string(),
             coordinate,
             protocol,
             user,
             nip05,
         })
     }
+
+    /// see [`is_legacy_nostr_url`]
+    pub fn is_legacy(&self) -> bool {
+        is_legacy_nostr_url(&self.original_string)
+    }
+
+    /// `nostr://[<user>@][<protocol>/]<naddr>` including resolved relays
+    pub fn to_naddr_url(&self) -> Result<String> {
+        let mut url = "nostr://".to_string();
+        if let Some(protocol) = &self.protocol {
+            if let Some(user) = &self.user {
+                url.push_str(&format!("{user}@"));
+            }
+            url.push_str(&format!("{protocol}/"));
+        }
+        url.push_str(&self.coordinate.to_bech32()?);
+        Ok(url)
+    }
+}
+
+/// whether the url is in a format written by older ngit versions. see
+/// [`NostrUrlDecoded::to_naddr_url`] for the current format
+pub fn is_legacy_nostr_url(url: &str) -> bool {
+    legacy_nostr_url_as_npub_url(url).is_some()
+}
+
+/// older ngit versions wrote remotes as a coordinate rather than an naddr or
+/// npub/identifier, ie. `nostr://30617:<pubkey>:<identifier>` or
+/// `nostr://<pubkey>:<identifier>`, optionally with a protocol prefix and
+/// url parameters. returns the same url in npub/identifier form
+fn legacy_nostr_url_as_npub_url(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("nostr://")?;
+    let (path, parameters) = match rest.split_once('?') {
+        Some((path, parameters)) => (path, format!("?{parameters}")),
+        None => (rest, String::new()),
+    };
+    let (prefix, coordinate) = match path.rsplit_once('/') {
+        Some((prefix, coordinate)) => (format!("{prefix}/"), coordinate),
+        None => (String::new(), path),
+    };
+    let (public_key, identifier) = match coordinate.splitn(3, ':').collect::<Vec<&str>>()[..] {
+        [kind, public_key, identifier]
+            if kind.parse::<u16>().ok().map(Kind::from) == Some(Kind::GitRepoAnnouncement) =>
+        {
+            (public_key, identifier)
+        }
+        [public_key, identifier] => (public_key, identifier),
+        _ => return None,
+    };
+    if identifier.is_empty() {
+        return None;
+    }
+    let npub = PublicKey::parse(public_key).ok()?.to_bech32().ok()?;
+    Some(format!("nostr://{prefix}{npub}/{identifier}{parameters}"))
+}
+
+/// legacy urls don't include relays so they are taken from the cached
+/// announcement or, failing that, an announcement found on fallback relays
+async fn find_repo_relays_for_legacy_url(
+    coordinate: &Coordinate,
+    git_repo: &Option<&Repo>,
+) -> Vec<RelayUrl> {
+    let git_repo_path = git_repo.and_then(|git_repo| git_repo.get_path().ok());
+    if let Ok(repo_ref) = get_repo_ref_from_cache(git_repo_path, coordinate).await {
+        return repo_ref.relays;
+    }
+    let Ok(config) = Config::load(git_repo) else {
+        return vec![];
+    };
+    let client = Client::new(Params::with_config(&config));
+    let events = client
+        .get_events(
+            client.get_fallback_relays().clone(),
+            vec![get_filter_repo_events(&HashSet::from([coordinate.clone()]))],
+        )
+        .await
+        .unwrap_or_default();
+    let _ = client.disconnect().await;
+    let Some(event) = events
+        .into_iter()
+        .filter(|e| e.pubkey == coordinate.public_key)
+        .max_by_key(|e| e.created_at)
+    else {
+        return vec![];
+    };
+    let _ = save_event_in_global_cache(git_repo_path, &event).await;
+    RepoRef::try_from((event, None))
+        .map(|repo_ref| repo_ref.relays)
+        .unwrap_or_default()
+}
+
+/// points a git remote with a legacy nostr url at its naddr form, returning
+/// the new url
+pub fn migrate_legacy_remote_url(
+    git_repo: &Repo,
+    remote: &str,
+    decoded_nostr_url: &NostrUrlDecoded,
+) -> Result<String> {
+    let url = decoded_nostr_url.to_naddr_url()?;
+    git_repo
+        .git_repo
+        .remote_set_url(remote, &url)
+        .context(format!("failed to update url of git remote '{remote}'"))?;
+    Ok(url)
+}
+
+/// points git remotes for the repository at `coordinate` that hint at
+/// relays it no longer lists at `relays` instead, returning the names of the
+/// remotes updated. aliases and nip05 urls are left as they are
+pub async fn update_remote_relay_hints(
+    git_repo: &Repo,
+    coordinate: &Coordinate,
+    relays: &[RelayUrl],
+) -> Result<Vec<String>> {
+    let mut updated = vec![];
+    for remote in git_repo.git_repo.remotes()?.iter().flatten() {
+        let Some(url) = git_repo
+            .git_repo
+            .find_remote(remote)?
+            .url()
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if !url.starts_with("nostr://") || url.starts_with(NOSTR_URL_ALIAS_PREFIX) {
+            continue;
+        }
+        let Ok(mut decoded_nostr_url) =
+            NostrUrlDecoded::parse_and_resolve(&url, &Some(git_repo)).await
+        else {
+            continue;
+        };
+        let hints = &decoded_nostr_url.coordinate.relays;
+        if decoded_nostr_url.nip05.is_some()
+            || decoded_nostr_url.coordinate.public_key != coordinate.public_key
+            || decoded_nostr_url.coordinate.identifier != coordinate.identifier
+            || hints.is_empty()
+            || hints.iter().all(|r| relays.contains(r))
+        {
+            continue;
+        }
+        decoded_nostr_url.coordinate.relays =
+            relays.iter().take(MAX_RELAY_HINTS).cloned().collect();
+        git_repo
+            .git_repo
+            .remote_set_url(remote, &decoded_nostr_url.to_naddr_url()?)
+            .context(format!("failed to update url of git remote '{remote}'"))?;
+        updated.push(remote.to_string());
+    }
+    Ok(updated)
+}
+
+fn nostr_url_alias_config_item(name: &str) -> String {
+    format!("nostr.alias.{name}")
+}
+
+/// alias names are used as git config keys which are case insensitive and
+/// only allow alphanumeric characters and '-'
+pub fn validate_nostr_url_alias_name(name: &str) -> Result<()> {
+    if !name.starts_with(|c: char| c.is_ascii_lowercase())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        bail!(
+            "invalid alias name '{name}'. use lowercase letters, numbers and '-' starting with a letter"
+        );
+    }
+    Ok(())
+}
+
+/// lookup alias in repository config (which includes global) or just global
+/// config if no repository
+pub fn get_nostr_url_alias(name: &str, git_repo: &Option<&Repo>) -> Result<Option<String>> {
+    validate_nostr_url_alias_name(name)?;
+    let item = nostr_url_alias_config_item(name);
+    if let Some(git_repo) = git_repo {
+        git_repo.get_git_config_item(&item, None)
+    } else {
+        get_git_config_item(&None, &item)
+    }
+}
+
+pub fn save_nostr_url_alias(name: &str, url: &str, git_repo: &Option<&Repo>) -> Result<()> {
+    validate_nostr_url_alias_name(name)?;
+    save_git_config_item(git_repo, &nostr_url_alias_config_item(name), url)
+        .context(format!("failed to save nostr url alias '{name}'"))
+}
+
+/// returns false if alias doesn't exist
+pub fn remove_nostr_url_alias(name: &str, git_repo: &Option<&Repo>) -> Result<bool> {
+    validate_nostr_url_alias_name(name)?;
+    remove_git_config_item(git_repo, &nostr_url_alias_config_item(name))
+        .context(format!("failed to remove nostr url alias '{name}'"))
+}
+
+/// (name, nostr url) pairs sorted by name
+pub fn list_nostr_url_aliases(git_repo: &Option<&Repo>) -> Result<Vec<(String, String)>> {
+    let config = if let Some(git_repo) = git_repo {
+        git_repo
+            .git_repo
+            .config()
+            .context("failed to open git config")?
+    } else {
+        git2::Config::open_default()?
+            .open_global()
+            .context("failed to open global git config")?
+    };
+    let mut aliases = BTreeMap::new();
+    let mut entries = config.entries(Some(r"nostr\.alias\..*"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+            if let Some(name) = name.strip_prefix("nostr.alias.") {
+                aliases.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    Ok(aliases.into_iter().collect())
 }
 
 fn resolve_nip05_from_git_config_cache(nip05: &str, git_repo: &Option<&Repo>) -> Result<PublicKey> {
@@ -1327,5 +1584,92 @@ mod tests {
                 }
             }
         }
+
+        mod from_legacy_coordinate {
+            use super::*;
+
+            static PUBLIC_KEY_HEX: &str =
+                "a008def15796fba9a0d6fab04e8fd57089285d9fd505da5a83fe8aad57a3564d";
+
+            #[tokio::test]
+            async fn kind_public_key_and_identifier() -> Result<()> {
+                let url = format!("nostr://30617:{PUBLIC_KEY_HEX}:ngit?relay=nos.lol");
+                let decoded = NostrUrlDecoded::parse_and_resolve(&url, &None).await?;
+                assert_eq!(
+                    decoded,
+                    NostrUrlDecoded {
+                        original_string: url.clone(),
+                        coordinate: get_model_coordinate(true),
+                        protocol: None,
+                        user: None,
+                        nip05: None,
+                    }
+                );
+                assert!(decoded.is_legacy());
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn public_key_and_identifier() -> Result<()> {
+                let url = format!("nostr://{PUBLIC_KEY_HEX}:ngit?relay=nos.lol");
+                let decoded = NostrUrlDecoded::parse_and_resolve(&url, &None).await?;
+                assert_eq!(decoded.coordinate, get_model_coordinate(true));
+                assert!(decoded.is_legacy());
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn with_server_protocol_and_user() -> Result<()> {
+                let url = format!("nostr://fred@ssh/30617:{PUBLIC_KEY_HEX}:ngit?relay=nos.lol");
+                let decoded = NostrUrlDecoded::parse_and_resolve(&url, &None).await?;
+                assert_eq!(decoded.coordinate, get_model_coordinate(true));
+                assert_eq!(decoded.protocol, Some(ServerProtocol::Ssh));
+                assert_eq!(decoded.user, Some("fred".to_string()));
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn naddr_url_resolves_to_same_repository_and_is_not_legacy() -> Result<()> {
+                let url = format!("nostr://fred@ssh/30617:{PUBLIC_KEY_HEX}:ngit?relay=nos.lol");
+                let naddr_url = NostrUrlDecoded::parse_and_resolve(&url, &None)
+                    .await?
+                    .to_naddr_url()?;
+                assert!(naddr_url.starts_with("nostr://fred@ssh/naddr1"));
+                let decoded = NostrUrlDecoded::parse_and_resolve(&naddr_url, &None).await?;
+                assert_eq!(decoded.coordinate, get_model_coordinate(true));
+                assert_eq!(decoded.protocol, Some(ServerProtocol::Ssh));
+                assert_eq!(decoded.user, Some("fred".to_string()));
+                assert!(!decoded.is_legacy());
+                Ok(())
+            }
+
+            #[test]
+            fn current_formats_are_not_legacy() {
+                for url in [
+                    "nostr://npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr/ngit",
+                    "nostr://npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr/localhost:8055/ngit",
+                    "nostr://ssh/npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr/ngit?relay=nos.lol",
+                ] {
+                    assert_eq!(legacy_nostr_url_as_npub_url(url), None, "{url}");
+                }
+            }
+        }
+    }
+
+    mod validate_nostr_url_alias_name {
+        use super::*;
+
+        #[test]
+        fn accepts_lowercase_letters_numbers_and_dashes() {
+            assert!(validate_nostr_url_alias_name("upstream").is_ok());
+            assert!(validate_nostr_url_alias_name("my-fork-2").is_ok());
+        }
+
+        #[test]
+        fn rejects_names_git_config_cannot_store_as_is() {
+            for name in ["", "Upstream", "2fork", "my_fork", "a.b", "a/b"] {
+                assert!(validate_nostr_url_alias_name(name).is_err(), "{name}");
+            }
+        }
     }
 }
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use git2::{Diff, Oid};
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+
+use super::{Repo, oid_to_sha1, sha1_to_oid};
+
+/// `git patch-id` of a commit's changes, which stays the same when it is
+/// cherry-picked or rebased. None for merges and commits that change nothing
+pub fn get_commit_patch_id(git_repo: &Repo, commit: &Sha1Hash) -> Result<Option<Oid>> {
+    let commit = git_repo
+        .git_repo
+        .find_commit(sha1_to_oid(commit)?)
+        .context(format!("failed to find commit {commit}"))?;
+    if commit.parent_count() > 1 {
+        return Ok(None);
+    }
+    let parent_tree = if commit.parent_count() == 1 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+    let tree = commit.tree()?;
+    let diff = git_repo
+        .git_repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    get_diff_patch_id(&diff)
+}
+
+/// patch id of a patch in `git format-patch` format, eg. patch event content
+pub fn get_patch_patch_id(patch: &str) -> Result<Option<Oid>> {
+    let diff = Diff::from_buffer(patch.as_bytes()).context("failed to parse patch")?;
+    get_diff_patch_id(&diff)
+}
+
+fn get_diff_patch_id(diff: &Diff) -> Result<Option<Oid>> {
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(diff.patchid(None).context("failed to get patch id")?))
+}
+
+fn get_patch_ids(git_repo: &Repo, commits: &[Sha1Hash]) -> Result<HashMap<Oid, Sha1Hash>> {
+    let mut patch_ids = HashMap::new();
+    for commit in commits {
+        if let Some(patch_id) = get_commit_patch_id(git_repo, commit)? {
+            patch_ids.insert(patch_id, *commit);
+        }
+    }
+    Ok(patch_ids)
+}
+
+fn sort_by_position_in<T>(matches: &mut [(Sha1Hash, T)], commits: &[Sha1Hash]) {
+    matches.sort_by_key(|(commit, _)| commits.iter().position(|c| c == commit));
+}
+
+/// `commits` that match a commit reachable from `upstream_tip`, but not from
+/// `commits`, as (commit, upstream commit). like `git cherry`
+pub fn find_commits_already_upstream(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    upstream_tip: &Sha1Hash,
+) -> Result<Vec<(Sha1Hash, Sha1Hash)>> {
+    let patch_ids = get_patch_ids(git_repo, commits)?;
+    if patch_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut revwalk = git_repo.git_repo.revwalk()?;
+    revwalk.push(sha1_to_oid(upstream_tip)?)?;
+    for commit in commits {
+        revwalk.hide(sha1_to_oid(commit)?)?;
+    }
+    let mut matches = vec![];
+    for oid in revwalk {
+        let upstream_commit = oid_to_sha1(&oid?);
+        if let Some(patch_id) = get_commit_patch_id(git_repo, &upstream_commit)? {
+            if let Some(commit) = patch_ids.get(&patch_id) {
+                matches.push((*commit, upstream_commit));
+            }
+        }
+    }
+    sort_by_position_in(&mut matches, commits);
+    Ok(matches)
+}
+
+/// `commits` that match one of `patches`, as (commit, patch). patches that
+/// can't be parsed are ignored
+pub fn find_commits_in_patches<'a>(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    patches: &'a [nostr::Event],
+) -> Result<Vec<(Sha1Hash, &'a nostr::Event)>> {
+    let patch_ids = get_patch_ids(git_repo, commits)?;
+    if patch_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut matches = vec![];
+    for patch in patches {
+        if let Ok(Some(patch_id)) = get_patch_patch_id(&patch.content) {
+            if let Some(commit) = patch_ids.get(&patch_id) {
+                matches.push((*commit, patch));
+            }
+        }
+    }
+    sort_by_position_in(&mut matches, commits);
+    Ok(matches)
+}
+
+/// whether the changes `tip` makes since it diverged from `upstream_tip` are
+/// already upstream, eg. after it was merged, rebased and merged or squash
+/// merged
+pub fn is_content_merged(git_repo: &Repo, tip: &Sha1Hash, upstream_tip: &Sha1Hash) -> Result<bool> {
+    let commits = git_repo.rev_list(upstream_tip, tip)?;
+    if commits.is_empty() {
+        return Ok(true);
+    }
+
+    // rebased or cherry-picked one commit at a time
+    let already_upstream = find_commits_already_upstream(git_repo, &commits, upstream_tip)?;
+    let mut all_upstream = true;
+    for commit in &commits {
+        // merge commits have no patch id
+        if !already_upstream.iter().any(|(c, _)| c == commit)
+            && get_commit_patch_id(git_repo, commit)?.is_some()
+        {
+            all_upstream = false;
+            break;
+        }
+    }
+    if all_upstream {
+        return Ok(true);
+    }
+
+    let repo = &git_repo.git_repo;
+    let tip_commit = repo.find_commit(sha1_to_oid(tip)?)?;
+    let upstream_commit = repo.find_commit(sha1_to_oid(upstream_tip)?)?;
+
+    // merging would change nothing, eg. squash merged
+    let mut merged = repo
+        .merge_commits(&upstream_commit, &tip_commit, None)
+        .context("failed to merge in memory")?;
+    if !merged.has_conflicts() && merged.write_tree_to(repo)? == upstream_commit.tree_id() {
+        return Ok(true);
+    }
+
+    // squash merged and changed upstream since
+    let Ok(merge_base) = repo.merge_base(tip_commit.id(), upstream_commit.id()) else {
+        return Ok(false);
+    };
+    let diff = repo.diff_tree_to_tree(
+        Some(&repo.find_commit(merge_base)?.tree()?),
+        Some(&tip_commit.tree()?),
+        None,
+    )?;
+    let Some(patch_id) = get_diff_patch_id(&diff)? else {
+        return Ok(true);
+    };
+    for upstream_commit in git_repo.rev_list(&oid_to_sha1(&merge_base), upstream_tip)? {
+        if get_commit_patch_id(git_repo, &upstream_commit)? == Some(patch_id) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+    use crate::git::RepoActions;
+
+    /// main with a commit adding t3.md and a feature branch, from before
+    /// it, with its own commits adding t3.md and t4.md. returns the feature
+    /// commits newest first and the tip of main
+    fn prep_cherry_picked_upstream() -> Result<(GitTestRepo, Repo, Vec<Sha1Hash>, Sha1Hash)> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        test_repo.create_branch("feature")?;
+        test_repo.checkout("feature")?;
+        std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+        let t3 = oid_to_sha1(&test_repo.stage_and_commit("add t3.md")?);
+        std::fs::write(test_repo.dir.join("t4.md"), "some content")?;
+        let t4 = oid_to_sha1(&test_repo.stage_and_commit("add t4.md")?);
+        test_repo.checkout("main")?;
+        std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+        let upstream_t3 = oid_to_sha1(&test_repo.stage_and_commit("t3 with another message")?);
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+        Ok((test_repo, git_repo, vec![t4, t3], upstream_t3))
+    }
+
+    mod find_commits_already_upstream {
+        use super::*;
+
+        #[test]
+        fn finds_commit_with_matching_patch_id() -> Result<()> {
+            let (_test_repo, git_repo, commits, upstream_t3) = prep_cherry_picked_upstream()?;
+            assert_eq!(
+                find_commits_already_upstream(&git_repo, &commits, &upstream_t3)?,
+                vec![(commits[1], upstream_t3)],
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn none_when_commits_are_only_in_series() -> Result<()> {
+            let (_test_repo, git_repo, commits, _) = prep_cherry_picked_upstream()?;
+            let main_before_t3 = git_repo.get_commit_parent(&commits[1])?;
+            assert_eq!(
+                find_commits_already_upstream(&git_repo, &commits, &main_before_t3)?,
+                vec![],
+            );
+            Ok(())
+        }
+    }
+
+    mod is_content_merged {
+        use super::*;
+
+        /// main and a feature branch with commits adding t3.md and t4.md.
+        /// returns the feature tip
+        fn prep_feature() -> Result<(GitTestRepo, Repo, Sha1Hash)> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo.create_branch("feature")?;
+            test_repo.checkout("feature")?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            test_repo.stage_and_commit("add t3.md")?;
+            std::fs::write(test_repo.dir.join("t4.md"), "some content")?;
+            let tip = oid_to_sha1(&test_repo.stage_and_commit("add t4.md")?);
+            test_repo.checkout("main")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            Ok((test_repo, git_repo, tip))
+        }
+
+        #[test]
+        fn squash_merged() -> Result<()> {
+            let (test_repo, git_repo, tip) = prep_feature()?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            std::fs::write(test_repo.dir.join("t4.md"), "some content")?;
+            let main_tip = oid_to_sha1(&test_repo.stage_and_commit("add t3.md and t4.md")?);
+            assert!(is_content_merged(&git_repo, &tip, &main_tip)?);
+            Ok(())
+        }
+
+        #[test]
+        fn squash_merged_then_changed() -> Result<()> {
+            let (test_repo, git_repo, tip) = prep_feature()?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            std::fs::write(test_repo.dir.join("t4.md"), "some content")?;
+            test_repo.stage_and_commit("add t3.md and t4.md")?;
+            std::fs::write(test_repo.dir.join("t3.md"), "changed content")?;
+            let main_tip = oid_to_sha1(&test_repo.stage_and_commit("change t3.md")?);
+            assert!(is_content_merged(&git_repo, &tip, &main_tip)?);
+            Ok(())
+        }
+
+        #[test]
+        fn rebased_and_merged() -> Result<()> {
+            let (test_repo, git_repo, tip) = prep_feature()?;
+            std::fs::write(test_repo.dir.join("t5.md"), "some content")?;
+            test_repo.stage_and_commit("add t5.md")?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            test_repo.stage_and_commit("add t3.md")?;
+            std::fs::write(test_repo.dir.join("t4.md"), "some content")?;
+            let main_tip = oid_to_sha1(&test_repo.stage_and_commit("add t4.md")?);
+            assert!(is_content_merged(&git_repo, &tip, &main_tip)?);
+            Ok(())
+        }
+
+        #[test]
+        fn merged() -> Result<()> {
+            let (_test_repo, git_repo, tip) = prep_feature()?;
+            let parent = git_repo.get_commit_parent(&tip)?;
+            assert!(is_content_merged(&git_repo, &parent, &tip)?);
+            Ok(())
+        }
+
+        #[test]
+        fn unmerged() -> Result<()> {
+            let (test_repo, git_repo, tip) = prep_feature()?;
+            std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+            let main_tip = oid_to_sha1(&test_repo.stage_and_commit("add t3.md")?);
+            assert!(!is_content_merged(&git_repo, &tip, &main_tip)?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn patch_and_commit_patch_ids_match() -> Result<()> {
+        let (_test_repo, git_repo, commits, upstream_t3) = prep_cherry_picked_upstream()?;
+        let patch = git_repo.make_patch_from_commit(&commits[1], &None)?;
+        assert_eq!(
+            get_patch_patch_id(&patch)?,
+            get_commit_patch_id(&git_repo, &upstream_t3)?,
+        );
+        assert_ne!(
+            get_patch_patch_id(&patch)?,
+            get_commit_patch_id(&git_repo, &commits[0])?,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use git2::Oid;
+
+use super::Repo;
+
+/// notes ref holding nostr proposal metadata against the tip of each `pr/*`
+/// branch so tools that only read refs and notes can show it
+pub static PROPOSAL_NOTES_REF: &str = "refs/notes/nostr";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposalNote {
+    /// branch name as listed by the remote helper eg. `pr/feature(1a2b3c4d)`
+    pub branch_name: String,
+    pub title: String,
+    /// npub
+    pub author: String,
+    /// hex event id of the proposal root
+    pub root: String,
+    /// `open` or `draft`
+    pub status: String,
+    /// coordinate of the repository the proposal belongs to. empty for notes
+    /// written before more than one nostr remote was supported
+    pub repo: String,
+}
+
+impl ProposalNote {
+    pub fn to_note(&self) -> String {
+        format!(
+            "branch: {}\ntitle: {}\nauthor: {}\nroot: {}\nstatus: {}\nrepo: {}\n",
+            self.branch_name,
+            self.title.replace('\n', " "),
+            self.author,
+            self.root,
+            self.status,
+            self.repo,
+        )
+    }
+
+    pub fn from_note(note: &str) -> Option<Self> {
+        let value = |key: &str| {
+            note.lines()
+                .find_map(|line| line.strip_prefix(&format!("{key}: ")))
+                .map(str::to_string)
+        };
+        Some(Self {
+            branch_name: value("branch")?,
+            title: value("title")?,
+            author: value("author")?,
+            root: value("root")?,
+            status: value("status")?,
+            repo: value("repo").unwrap_or_default(),
+        })
+    }
+}
+
+/// proposal notes keyed by the commit they annotate
+pub fn get_proposal_notes(git_repo: &Repo) -> Result<HashMap<Oid, ProposalNote>> {
+    let mut notes = HashMap::new();
+    // the notes ref doesn't exist until the first proposal is fetched
+    let Ok(iter) = git_repo.git_repo.notes(Some(PROPOSAL_NOTES_REF)) else {
+        return Ok(notes);
+    };
+    for item in iter {
+        let (_, annotated_id) = item.context("failed to read proposal notes")?;
+        if let Some(note) = git_repo
+            .git_repo
+            .find_note(Some(PROPOSAL_NOTES_REF), annotated_id)
+            .ok()
+            .and_then(|note| note.message().and_then(ProposalNote::from_note))
+        {
+            notes.insert(annotated_id, note);
+        }
+    }
+    Ok(notes)
+}
+
+/// make the proposal notes for `repo` match `notes`. notes for proposals
+/// that have closed, or whose refs are no longer listed, are removed. notes
+/// for other repositories, eg. from another nostr remote, are left alone
+pub fn update_proposal_notes(
+    git_repo: &Repo,
+    repo: &str,
+    notes: &HashMap<Oid, ProposalNote>,
+) -> Result<()> {
+    let existing = get_proposal_notes(git_repo)?;
+    let stale = existing
+        .iter()
+        .filter(|(oid, note)| {
+            (note.repo.is_empty() || note.repo == repo) && !notes.contains_key(oid)
+        })
+        .map(|(oid, _)| *oid)
+        .collect::<Vec<Oid>>();
+    if stale.is_empty()
+        && notes
+            .iter()
+            .all(|(oid, note)| existing.get(oid) == Some(note))
+    {
+        return Ok(());
+    }
+    let signature = git_repo
+        .git_repo
+        .signature()
+        .or_else(|_| git2::Signature::now("ngit", "ngit@localhost"))?;
+    for annotated_id in &stale {
+        git_repo
+            .git_repo
+            .note_delete(
+                *annotated_id,
+                Some(PROPOSAL_NOTES_REF),
+                &signature,
+                &signature,
+            )
+            .context("failed to remove stale proposal note")?;
+    }
+    for (annotated_id, note) in notes {
+        if existing.get(annotated_id) != Some(note) {
+            git_repo
+                .git_repo
+                .note(
+                    &signature,
+                    &signature,
+                    Some(PROPOSAL_NOTES_REF),
+                    *annotated_id,
+                    &note.to_note(),
+                    true,
+                )
+                .context("failed to write proposal note")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn proposal_note(branch_name: &str) -> ProposalNote {
+        ProposalNote {
+            branch_name: branch_name.to_string(),
+            title: "add feature".to_string(),
+            author: "npub1example".to_string(),
+            root: "a".repeat(64),
+            status: "open".to_string(),
+            repo: "30617:pubkey:repo".to_string(),
+        }
+    }
+
+    #[test]
+    fn note_round_trips() {
+        let note = proposal_note("pr/feature(aaaaaaaa)");
+        assert_eq!(ProposalNote::from_note(&note.to_note()), Some(note));
+    }
+
+    mod update_proposal_notes {
+        use super::*;
+
+        #[test]
+        fn writes_notes_and_removes_stale_ones() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let first = test_repo.populate_minus_1()?;
+            std::fs::write(test_repo.dir.join("t2.md"), "some content")?;
+            let second = test_repo.stage_and_commit("add t2.md")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let notes = HashMap::from([
+                (first, proposal_note("pr/first(aaaaaaaa)")),
+                (second, proposal_note("pr/second(bbbbbbbb)")),
+            ]);
+            update_proposal_notes(&git_repo, "30617:pubkey:repo", &notes)?;
+            assert_eq!(get_proposal_notes(&git_repo)?, notes);
+
+            let notes = HashMap::from([(second, proposal_note("pr/second(bbbbbbbb)"))]);
+            update_proposal_notes(&git_repo, "30617:pubkey:repo", &notes)?;
+            assert_eq!(get_proposal_notes(&git_repo)?, notes);
+            Ok(())
+        }
+
+        #[test]
+        fn leaves_notes_for_other_repos() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let first = test_repo.populate_minus_1()?;
+            std::fs::write(test_repo.dir.join("t2.md"), "some content")?;
+            let second = test_repo.stage_and_commit("add t2.md")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let other_repo_note = ProposalNote {
+                repo: "30617:pubkey:fork".to_string(),
+                ..proposal_note("pr/fork(aaaaaaaa)")
+            };
+            let fork_notes = HashMap::from([(first, other_repo_note)]);
+            update_proposal_notes(&git_repo, "30617:pubkey:fork", &fork_notes)?;
+            let notes = HashMap::from([(second, proposal_note("pr/second(bbbbbbbb)"))]);
+            update_proposal_notes(&git_repo, "30617:pubkey:repo", &notes)?;
+
+            let mut expected = fork_notes;
+            expected.extend(notes);
+            assert_eq!(get_proposal_notes(&git_repo)?, expected);
+            Ok(())
+        }
+
+        #[test]
+        fn no_notes_when_ref_missing() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            assert!(get_proposal_notes(&git_repo)?.is_empty());
+            Ok(())
+        }
+    }
+}
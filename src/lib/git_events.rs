@@ -1,11 +1,13 @@
-use std::{str::FromStr, sync::Arc};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 
 use anyhow::{Context, Result, bail};
 use nostr::nips::{nip01::Coordinate, nip10::Marker, nip19::Nip19};
 use nostr_sdk::{
-    Event, EventBuilder, EventId, FromBech32, Kind, NostrSigner, PublicKey, RelayUrl, Tag, TagKind,
-    TagStandard, hashes::sha1::Hash as Sha1Hash,
+    Alphabet, Event, EventBuilder, EventId, FromBech32, JsonUtil, Kind, NostrSigner, PublicKey,
+    RelayUrl, SingleLetterTag, Tag, TagKind, TagStandard, Timestamp,
+    hashes::{Hash, sha1::Hash as Sha1Hash},
 };
+use serde::Serialize;
 
 use crate::{
     cli_interactor::{Interactor, InteractorPrompt, PromptInputParms},
@@ -58,6 +60,52 @@ pub fn status_kinds() -> Vec<Kind> {
     ]
 }
 
+/// NIP-22 comments and NIP-34 replies on proposals
+pub fn comment_kinds() -> Vec<Kind> {
+    vec![Kind::Custom(1111), Kind::GitReply]
+}
+
+/// proposals expiring within this are labelled in `ngit list`
+pub static EXPIRY_NOTICE_SECS: u64 = 14 * 24 * 60 * 60;
+
+/// NIP-40 expiration for cover letters, patches and status events when
+/// `nostr.proposal-expiry-days` or `--expiry-days` is set. repository
+/// announcements and state events never expire
+pub fn proposal_expiration(expiry_days: Option<u64>) -> Option<Timestamp> {
+    expiry_days.map(|days| Timestamp::from(Timestamp::now().as_u64() + days * 24 * 60 * 60))
+}
+
+pub fn expiration_tags(expiration: Option<Timestamp>) -> Vec<Tag> {
+    expiration.map(Tag::expiration).into_iter().collect()
+}
+
+pub fn event_expiration(event: &Event) -> Option<Timestamp> {
+    tag_value(event, "expiration")
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(Timestamp::from)
+}
+
+pub fn event_has_expired(event: &Event, now: Timestamp) -> bool {
+    event_expiration(event).is_some_and(|expiration| expiration <= now)
+}
+
+/// eg. "(expires in 12d)" when the event expires within
+/// [`EXPIRY_NOTICE_SECS`] of `now`
+pub fn expiry_notice(event: &Event, now: Timestamp) -> Option<String> {
+    let secs_left = event_expiration(event)?
+        .as_u64()
+        .checked_sub(now.as_u64())?;
+    if secs_left > EXPIRY_NOTICE_SECS {
+        return None;
+    }
+    Some(format!(
+        "(expires in {}d)",
+        secs_left.div_ceil(24 * 60 * 60)
+    ))
+}
+
 pub fn event_is_patch_set_root(event: &Event) -> bool {
     event.kind.eq(&Kind::GitPatch)
         && event
@@ -96,6 +144,7 @@ pub async fn generate_patch_event(
     branch_name: Option<String>,
     root_proposal_id: &Option<String>,
     mentions: &[nostr::Tag],
+    expiration: Option<Timestamp>,
 ) -> Result<nostr::Event> {
     let commit_parent = git_repo
         .get_commit_parent(commit)
@@ -234,6 +283,7 @@ pub async fn generate_patch_event(
                         git_repo.get_commit_comitter(commit)?,
                     ),
                 ],
+                expiration_tags(expiration),
             ]
             .concat(),
         ),
@@ -257,6 +307,8 @@ pub fn event_tag_from_nip19_or_hex(
                 PromptInputParms::default().with_prompt(format!("{reference_name} reference")),
             )?;
         }
+        // accept references copied from clients as nostr:nevent123 or with whitespace
+        bech32 = bech32.trim().trim_start_matches("nostr:").to_string();
         if let Ok(nip19) = Nip19::from_bech32(bech32.clone()) {
             match nip19 {
                 Nip19::Event(n) => {
@@ -303,7 +355,7 @@ pub fn event_tag_from_nip19_or_hex(
             }));
         }
         if prompt_for_correction {
-            println!("not a valid {reference_name} event reference");
+            eprintln!("not a valid {reference_name} event reference");
         } else {
             bail!(format!("not a valid {reference_name} event reference"));
         }
@@ -312,6 +364,89 @@ pub fn event_tag_from_nip19_or_hex(
     }
 }
 
+/// value for the `branch-name` tag. `branch_name` is used when supplied,
+/// otherwise the checked out branch unless it is main, master or a detached
+/// HEAD
+fn get_branch_name_tag_value(git_repo: &Repo, branch_name: &Option<String>) -> Option<String> {
+    let branch_name = if let Some(branch_name) = branch_name {
+        branch_name.clone()
+    } else {
+        let branch_name = git_repo.get_checked_out_branch_name().ok()?;
+        if ["main", "master", "origin/main", "origin/master", "HEAD"]
+            .contains(&branch_name.as_str())
+        {
+            return None;
+        }
+        branch_name
+    };
+    Some(
+        branch_name
+            .strip_prefix("pr/")
+            .unwrap_or(&branch_name)
+            .chars()
+            .take(60)
+            .collect::<String>(),
+    )
+}
+
+/// branch-name label derived from a title, eg. "Fix the Parser!" becomes
+/// "fix-the-parser"
+pub fn branch_name_from_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+        .chars()
+        .take(60)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// longest file name stem [`sanitize_file_name`] returns, leaving room for a
+/// prefix and extension within the usual 255 byte limit
+static MAX_FILE_NAME_STEM_CHARS: usize = 60;
+
+/// a file name stem from untrusted text such as a patch subject, eg.
+/// "../../.git/hooks/post-checkout" becomes "git-hooks-post-checkout". path
+/// separators, control and other unsafe characters become dashes, leading
+/// dots are dropped so it can't be hidden or refer to a parent directory, and
+/// it is capped in length
+pub fn sanitize_file_name(text: &str) -> String {
+    let sanitized = text
+        .split(|c: char| !(c.is_alphanumeric() || matches!(c, '.' | '_' | '-')))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+        .trim_start_matches(['.', '-'])
+        .chars()
+        .take(MAX_FILE_NAME_STEM_CHARS)
+        .collect::<String>()
+        .trim_end_matches(['.', '-'])
+        .to_string();
+    if sanitized.is_empty() {
+        "untitled".to_string()
+    } else {
+        sanitized
+    }
+}
+
+/// `<stem>.<extension>`, or `<stem>-<n>.<extension>` when that is already in
+/// `taken`, which it is added to. compared ignoring case as some file systems
+/// do
+pub fn unique_file_name(stem: &str, extension: &str, taken: &mut HashSet<String>) -> String {
+    let mut file_name = format!("{stem}.{extension}");
+    let mut n = 2;
+    while !taken.insert(file_name.to_lowercase()) {
+        file_name = format!("{stem}-{n}.{extension}");
+        n += 1;
+    }
+    file_name
+}
+
+#[allow(clippy::too_many_arguments)]
 #[allow(clippy::too_many_lines)]
 pub async fn generate_cover_letter_and_patch_events(
     cover_letter_title_description: Option<(String, String)>,
@@ -320,7 +455,9 @@ pub async fn generate_cover_letter_and_patch_events(
     signer: &Arc<dyn NostrSigner>,
     repo_ref: &RepoRef,
     root_proposal_id: &Option<String>,
+    branch_name: &Option<String>,
     mentions: &[nostr::Tag],
+    expiration: Option<Timestamp>,
 ) -> Result<Vec<nostr::Event>> {
     let root_commit = git_repo
         .get_root_commit()
@@ -369,24 +506,13 @@ pub async fn generate_cover_letter_and_patch_events(
             // eventually a prefix will be needed of the event id to stop 2 proposals with the same name colliding
             // a change like this, or the removal of this tag will require the actual branch name to be tracked
             // so pulling and pushing still work
-            if let Ok(branch_name) = git_repo.get_checked_out_branch_name() {
-                if !branch_name.eq("main")
-                    && !branch_name.eq("master")
-                    && !branch_name.eq("origin/main")
-                    && !branch_name.eq("origin/master")
-                {
-                    vec![
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
-                            vec![if let Some(branch_name) = branch_name.strip_prefix("pr/") {
-                                branch_name.to_string()
-                            } else {
-                                branch_name
-                            }.chars().take(60).collect::<String>()],
-                        ),
-                    ]
-                }
-                else { vec![] }
+            if let Some(branch_name) = get_branch_name_tag_value(git_repo, branch_name) {
+                vec![
+                    Tag::custom(
+                        nostr::TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
+                        vec![branch_name],
+                    ),
+                ]
             } else {
                 vec![]
             },
@@ -394,6 +520,7 @@ pub async fn generate_cover_letter_and_patch_events(
                 .iter()
                 .map(|pk| Tag::public_key(*pk))
                 .collect(),
+            expiration_tags(expiration),
         ].concat(),
     ), signer).await
     .context("failed to create cover-letter event")?);
@@ -415,33 +542,13 @@ pub async fn generate_cover_letter_and_patch_events(
                     Some(((i + 1).try_into()?, commits.len().try_into()?))
                 },
                 if events.is_empty() {
-                    if let Ok(branch_name) = git_repo.get_checked_out_branch_name() {
-                        if !branch_name.eq("main")
-                            && !branch_name.eq("master")
-                            && !branch_name.eq("origin/main")
-                            && !branch_name.eq("origin/master")
-                        {
-                            Some(
-                                if let Some(branch_name) = branch_name.strip_prefix("pr/") {
-                                    branch_name.to_string()
-                                } else {
-                                    branch_name
-                                }
-                                .chars()
-                                .take(60)
-                                .collect::<String>(),
-                            )
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                    get_branch_name_tag_value(git_repo, branch_name)
                 } else {
                     None
                 },
                 root_proposal_id,
                 if events.is_empty() { mentions } else { &[] },
+                expiration,
             )
             .await
             .context("failed to generate patch event")?,
@@ -508,6 +615,93 @@ pub fn commit_msg_from_patch_oneliner(patch: &nostr::Event) -> Result<String> {
         .to_string())
 }
 
+/// files changed and lines added and removed by one or more patches
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Diffstat {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl std::fmt::Display for Diffstat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} +{} -{}",
+            self.files,
+            if self.files == 1 { "file" } else { "files" },
+            self.insertions,
+            self.deletions,
+        )
+    }
+}
+
+/// counted from the diffs in the patches' content. a file changed by more
+/// than one patch is counted once
+pub fn patches_diffstat(patches: &[nostr::Event]) -> Diffstat {
+    let mut files = HashSet::new();
+    let mut diffstat = Diffstat::default();
+    for patch in patches {
+        let mut in_diff = false;
+        for line in patch.content.lines() {
+            if let Some(paths) = line.strip_prefix("diff --git ") {
+                files.insert(paths.to_string());
+                in_diff = true;
+            } else if !in_diff || line.starts_with("+++ ") || line.starts_with("--- ") {
+                continue;
+            } else if line.eq("-- ") {
+                // signature that follows the last diff
+                in_diff = false;
+            } else if line.starts_with('+') {
+                diffstat.insertions += 1;
+            } else if line.starts_with('-') {
+                diffstat.deletions += 1;
+            }
+        }
+    }
+    diffstat.files = files.len();
+    diffstat
+}
+
+/// "2 files +10 -3" counted from the diff in the patch content
+pub fn patch_diffstat(patch: &nostr::Event) -> String {
+    patches_diffstat(std::slice::from_ref(patch)).to_string()
+}
+
+/// the diff in a patch's content without the email headers, commit message,
+/// diffstat or signature. empty for a cover letter
+pub fn diff_from_patch(patch: &nostr::Event) -> &str {
+    let content = patch.content.as_str();
+    let Some(start) = content
+        .match_indices("diff --git ")
+        .map(|(i, _)| i)
+        .find(|i| *i == 0 || content[..*i].ends_with('\n'))
+    else {
+        return "";
+    };
+    let end = content[start..]
+        .rfind("\n-- \n")
+        .map_or(content.len(), |end| start + end + 1);
+    &content[start..end]
+}
+
+/// many relays reject events over 64 KiB
+pub static PATCH_SIZE_WARNING_BYTES: usize = 60 * 1024;
+
+/// warning for a patch whose binary file changes make it big enough that
+/// relays may reject it
+pub fn binary_patch_size_warning(patch: &nostr::Event) -> Option<String> {
+    let size = patch.as_json().len();
+    if size < PATCH_SIZE_WARNING_BYTES || !patch.content.contains("\nGIT binary patch\n") {
+        return None;
+    }
+    Some(format!(
+        "WARNING: patch '{}' is {} KiB as it includes binary files. relays often reject events over 64 KiB",
+        commit_msg_from_patch_oneliner(patch).unwrap_or_default(),
+        size / 1024,
+    ))
+}
+
 pub fn event_to_cover_letter(event: &nostr::Event) -> Result<CoverLetter> {
     if !event_is_patch_set_root(event) {
         bail!("event is not a patch set root event (root patch or cover letter)")
@@ -547,6 +741,228 @@ fn safe_branch_name_for_pr(s: &str) -> String {
         .collect()
 }
 
+/// a replacement title and description for a proposal published after it, so
+/// a typo can be fixed without a new revision resetting review state
+pub static PROPOSAL_EDIT_KIND: Kind = Kind::Custom(1624);
+
+fn tags_proposal_with_edit_marker(event: &Event, proposal_id: &EventId) -> bool {
+    event.tags.iter().any(|t| {
+        let t = t.as_slice();
+        t.len() > 3 && t[0].eq("e") && t[1].eq(&proposal_id.to_hex()) && t[3].eq("edit")
+    })
+}
+
+pub async fn generate_proposal_edit_event(
+    proposal: &Event,
+    repo_ref: &RepoRef,
+    title: &str,
+    description: &str,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<Event> {
+    let e = TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::E));
+    sign_event(
+        EventBuilder::new(
+            PROPOSAL_EDIT_KIND,
+            format!("{}\n\n{}", title.trim(), description.trim()),
+        )
+        .tags(
+            [
+                vec![
+                    Tag::custom(e, vec![
+                        proposal.id.to_hex(),
+                        repo_ref
+                            .relays
+                            .first()
+                            .map(ToString::to_string)
+                            .unwrap_or_default(),
+                        "edit".to_string(),
+                    ]),
+                    Tag::public_key(proposal.pubkey),
+                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("alt")), vec![
+                        format!("git proposal edit: {}", title.trim()),
+                    ]),
+                ],
+                repo_ref
+                    .coordinates()
+                    .into_iter()
+                    .map(Tag::coordinate)
+                    .collect::<Vec<Tag>>(),
+            ]
+            .concat(),
+        ),
+        signer,
+    )
+    .await
+    .context("failed to sign proposal edit event")
+}
+
+/// `cover_letter` with the title and description of the most recent edit to
+/// `proposal` by its author or a maintainer. other edits are ignored
+pub fn apply_proposal_edits(
+    mut cover_letter: CoverLetter,
+    proposal: &Event,
+    edits: &[Event],
+    maintainers: &[PublicKey],
+) -> CoverLetter {
+    if let Some(edit) = edits
+        .iter()
+        .filter(|e| {
+            e.kind.eq(&PROPOSAL_EDIT_KIND)
+                && (e.pubkey.eq(&proposal.pubkey) || maintainers.contains(&e.pubkey))
+                && tags_proposal_with_edit_marker(e, &proposal.id)
+        })
+        .max_by_key(|e| e.created_at)
+    {
+        let (title, description) = edit
+            .content
+            .split_once("\n\n")
+            .unwrap_or((&edit.content, ""));
+        cover_letter.title = title.trim().to_string();
+        cover_letter.description = description.trim().to_string();
+    }
+    cover_letter
+}
+
+/// a NIP-09 deletion of `proposal` and `patches`, which should only include
+/// events by the same author as relays ignore deletions of others' events
+pub async fn generate_proposal_deletion_event(
+    proposal: &Event,
+    patches: &[Event],
+    repo_ref: &RepoRef,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<Event> {
+    sign_event(
+        EventBuilder::new(Kind::EventDeletion, "proposal retracted by its author").tags(
+            [
+                std::iter::once(proposal)
+                    .chain(patches.iter().filter(|e| e.id.ne(&proposal.id)))
+                    .map(|e| Tag::event(e.id))
+                    .collect::<Vec<Tag>>(),
+                vec![
+                    Tag::custom(
+                        TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::K)),
+                        vec![Kind::GitPatch.as_u16().to_string()],
+                    ),
+                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("alt")), vec![
+                        "git proposal deletion".to_string(),
+                    ]),
+                ],
+                repo_ref
+                    .coordinates()
+                    .into_iter()
+                    .map(Tag::coordinate)
+                    .collect::<Vec<Tag>>(),
+            ]
+            .concat(),
+        ),
+        signer,
+    )
+    .await
+    .context("failed to sign proposal deletion event")
+}
+
+/// whether `event` is deleted by one of `deletions` published by its author
+pub fn event_is_deleted(event: &Event, deletions: &[Event]) -> bool {
+    deletions.iter().any(|deletion| {
+        deletion.kind.eq(&Kind::EventDeletion)
+            && deletion.pubkey.eq(&event.pubkey)
+            && deletion.tags.event_ids().any(|id| id.eq(&event.id))
+    })
+}
+
+/// hash of the changes in a patch, ignoring details that change when the
+/// same change is rebased (commit ids, blob ids and hunk line numbers)
+pub fn patch_diff_hash(patch: &nostr::Event) -> Option<Sha1Hash> {
+    let diff_start = patch.content.find("diff --git ")?;
+    let diff = &patch.content[diff_start..];
+    // strip version signature that git format-patch appends
+    let diff = diff.rsplit_once("\n-- \n").map_or(diff, |(d, _)| d);
+    let normalised = diff
+        .lines()
+        .filter(|l| !l.starts_with("index "))
+        .map(|l| if l.starts_with("@@ ") { "@@" } else { l })
+        .collect::<Vec<&str>>()
+        .join("\n");
+    Some(Sha1Hash::hash(normalised.as_bytes()))
+}
+
+/// patches represent the same change if they share a commit id or, when the
+/// commit id has changed due to a rebase, identical diffs
+pub fn patches_are_equivalent(a: &nostr::Event, b: &nostr::Event) -> bool {
+    if let (Ok(a_commit), Ok(b_commit)) = (get_commit_id_from_patch(a), get_commit_id_from_patch(b))
+    {
+        if a_commit.eq(&b_commit) {
+            return true;
+        }
+    }
+    match (patch_diff_hash(a), patch_diff_hash(b)) {
+        (Some(a_hash), Some(b_hash)) => a_hash.eq(&b_hash),
+        _ => false,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RevisionComparison {
+    /// 1 for the original proposal, 2 for its first revision, etc.
+    pub revision_number: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+}
+
+impl std::fmt::Display for RevisionComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "revision {}: {} patch{} changed, {} unchanged",
+            self.revision_number,
+            self.changed,
+            if self.changed == 1 { "" } else { "es" },
+            self.unchanged,
+        )
+    }
+}
+
+/// compare the most recent patch chain against the previous revision.
+/// returns None if the proposal has not been revised
+pub fn compare_with_previous_revision(
+    all_patches: &[nostr::Event],
+    most_recent_patch_chain: &[nostr::Event],
+) -> Option<RevisionComparison> {
+    let previous_revision_patches: Vec<nostr::Event> = all_patches
+        .iter()
+        .filter(|e| !most_recent_patch_chain.iter().any(|p| p.id.eq(&e.id)))
+        .cloned()
+        .collect();
+    let previous_chain = get_most_recent_patch_with_ancestors(previous_revision_patches).ok()?;
+    if previous_chain.is_empty() {
+        return None;
+    }
+    let mut comparison = RevisionComparison {
+        revision_number: all_patches
+            .iter()
+            .filter(|e| event_is_patch_set_root(e))
+            .count()
+            .max(2),
+        changed: 0,
+        unchanged: 0,
+    };
+    for patch in most_recent_patch_chain
+        .iter()
+        // cover letters dont contain a change
+        .filter(|e| !event_is_cover_letter(e))
+    {
+        if previous_chain
+            .iter()
+            .any(|previous| patches_are_equivalent(patch, previous))
+        {
+            comparison.unchanged += 1;
+        } else {
+            comparison.changed += 1;
+        }
+    }
+    Some(comparison)
+}
+
 pub fn get_most_recent_patch_with_ancestors(
     mut patches: Vec<nostr::Event>,
 ) -> Result<Vec<nostr::Event>> {
@@ -608,6 +1024,13 @@ fn get_event_parent_id(event: &nostr::Event) -> Result<String> {
         .clone())
 }
 
+/// the shorthand proposal id at the end of a branch name, eg. "1a2b3c4d" from
+/// "pr/feature(1a2b3c4d)" as listed by git-remote-nostr
+pub fn proposal_id_prefix_from_branch_name(branch_name: &str) -> Option<&str> {
+    let (_, prefix) = branch_name.strip_suffix(')')?.rsplit_once('(')?;
+    (prefix.len() >= 8 && prefix.chars().all(|c| c.is_ascii_hexdigit())).then_some(prefix)
+}
+
 pub fn is_event_proposal_root_for_branch(
     e: &Event,
     branch_name_or_refstr: &str,
@@ -620,6 +1043,9 @@ pub fn is_event_proposal_root_for_branch(
             || cl
                 .get_branch_name_with_pr_prefix_and_shorthand_id()
                 .is_ok_and(|s| s.eq(&branch_name))
+            // branch renamed after checking out the git-remote-nostr ref
+            || proposal_id_prefix_from_branch_name(&branch_name)
+                .is_some_and(|prefix| e.id.to_hex().starts_with(&prefix.to_lowercase()))
     }) && !event_is_revision_root(e))
 }
 
@@ -732,4 +1158,587 @@ mod tests {
             }
         }
     }
+
+    mod expiry_notice {
+        use super::*;
+
+        fn expiring_at(expiration: u64) -> Result<nostr::Event> {
+            Ok(EventBuilder::new(Kind::GitPatch, "")
+                .tags(expiration_tags(Some(Timestamp::from(expiration))))
+                .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        #[test]
+        fn rounds_up_to_whole_days() -> Result<()> {
+            let event = expiring_at(1_000 + 11 * 24 * 60 * 60 + 1)?;
+            assert_eq!(
+                expiry_notice(&event, Timestamp::from(1_000)),
+                Some("(expires in 12d)".to_string()),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn none_when_not_nearing_expiry() -> Result<()> {
+            let event = expiring_at(1_000 + EXPIRY_NOTICE_SECS + 1)?;
+            assert_eq!(expiry_notice(&event, Timestamp::from(1_000)), None);
+            Ok(())
+        }
+
+        #[test]
+        fn none_when_expired() -> Result<()> {
+            let event = expiring_at(1_000)?;
+            assert_eq!(expiry_notice(&event, Timestamp::from(1_001)), None);
+            assert!(event_has_expired(&event, Timestamp::from(1_001)));
+            Ok(())
+        }
+
+        #[test]
+        fn none_without_expiration() -> Result<()> {
+            let event = EventBuilder::new(Kind::GitPatch, "")
+                .sign_with_keys(&nostr::Keys::generate())?;
+            assert_eq!(expiry_notice(&event, Timestamp::from(1_000)), None);
+            assert!(!event_has_expired(&event, Timestamp::from(1_000)));
+            Ok(())
+        }
+    }
+
+    mod compare_with_previous_revision {
+        use nostr_sdk::Timestamp;
+
+        use super::*;
+
+        fn patch(
+            commit: &str,
+            change: &str,
+            parent: Option<&nostr::Event>,
+            root_tags: &[&str],
+            created_at: u64,
+        ) -> Result<nostr::Event> {
+            let mut tags = vec![Tag::custom(TagKind::Custom("commit".into()), vec![commit])];
+            for t in root_tags {
+                tags.push(Tag::hashtag(*t));
+            }
+            if let Some(parent) = parent {
+                tags.push(Tag::from_standardized(TagStandard::Event {
+                    event_id: parent.id,
+                    relay_url: None,
+                    marker: Some(Marker::Reply),
+                    public_key: None,
+                    uppercase: false,
+                }));
+            }
+            Ok(EventBuilder::new(
+                Kind::GitPatch,
+                format!(
+                    "From {commit} Mon Sep 17 00:00:00 2001\nSubject: [PATCH] update t.md\n\n---\n t.md | 2 +-\n\ndiff --git a/t.md b/t.md\nindex {}..{} 100644\n--- a/t.md\n+++ b/t.md\n@@ -1 +{created_at} @@\n-old\n+{change}\n-- \nlibgit2 1.8.1\n\n",
+                    &commit[..7],
+                    &commit[33..],
+                ),
+            )
+            .tags(tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        fn commit(c: char) -> String {
+            std::iter::repeat(c).take(40).collect()
+        }
+
+        #[test]
+        fn none_when_not_revised() -> Result<()> {
+            let p1 = patch(&commit('a'), "one", None, &["root"], 10)?;
+            let p2 = patch(&commit('b'), "two", Some(&p1), &[], 10)?;
+            let chain = get_most_recent_patch_with_ancestors(vec![p1.clone(), p2.clone()])?;
+            assert_eq!(compare_with_previous_revision(&[p1, p2], &chain), None);
+            Ok(())
+        }
+
+        #[test]
+        fn rebased_patch_with_identical_diff_is_unchanged() -> Result<()> {
+            let p1 = patch(&commit('a'), "one", None, &["root"], 10)?;
+            let p2 = patch(&commit('b'), "two", Some(&p1), &[], 10)?;
+            let p3 = patch(&commit('c'), "three", Some(&p2), &[], 10)?;
+            // revision rebased so commit ids and line numbers change
+            let r1 = patch(&commit('d'), "one", None, &["root", "revision-root"], 20)?;
+            let r2 = patch(&commit('e'), "two", Some(&r1), &[], 20)?;
+            let r3 = patch(&commit('f'), "three changed", Some(&r2), &[], 20)?;
+            let all = vec![p1, p2, p3, r1, r2, r3];
+            let chain = get_most_recent_patch_with_ancestors(all.clone())?;
+            assert_eq!(
+                compare_with_previous_revision(&all, &chain),
+                Some(RevisionComparison {
+                    revision_number: 2,
+                    changed: 1,
+                    unchanged: 2,
+                }),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn same_commit_id_is_unchanged() -> Result<()> {
+            let p1 = patch(&commit('a'), "one", None, &["root"], 10)?;
+            let r1 = patch(&commit('a'), "one", None, &["root", "revision-root"], 20)?;
+            let r2 = patch(&commit('b'), "two", Some(&r1), &[], 20)?;
+            let all = vec![p1, r1, r2];
+            let chain = get_most_recent_patch_with_ancestors(all.clone())?;
+            assert_eq!(
+                compare_with_previous_revision(&all, &chain),
+                Some(RevisionComparison {
+                    revision_number: 2,
+                    changed: 1,
+                    unchanged: 1,
+                }),
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn display() {
+            assert_eq!(
+                RevisionComparison {
+                    revision_number: 4,
+                    changed: 1,
+                    unchanged: 19,
+                }
+                .to_string(),
+                "revision 4: 1 patch changed, 19 unchanged",
+            );
+        }
+    }
+
+    mod event_tag_from_nip19_or_hex {
+        use nostr::{ToBech32, nips::nip19::Nip19Event};
+
+        use super::*;
+
+        fn event_id() -> EventId {
+            EventId::from_str("431e58eb8e1b4e20292d1d5bbe81d5cfb042e1bc165de32eddfdd52245a4cce4")
+                .unwrap()
+        }
+
+        fn tag_event_id(tag: &Tag) -> Option<EventId> {
+            match tag.as_standardized() {
+                Some(TagStandard::Event { event_id, .. }) => Some(*event_id),
+                _ => None,
+            }
+        }
+
+        #[test]
+        fn nevent() -> Result<()> {
+            let nevent = Nip19Event::new(event_id(), vec!["wss://relay.damus.io".to_string()])
+                .to_bech32()?;
+            let tag =
+                event_tag_from_nip19_or_hex(&nevent, "in-reply-to", Marker::Root, true, false)?;
+            assert_eq!(tag_event_id(&tag), Some(event_id()));
+            Ok(())
+        }
+
+        #[test]
+        fn note() -> Result<()> {
+            let tag = event_tag_from_nip19_or_hex(
+                &event_id().to_bech32()?,
+                "in-reply-to",
+                Marker::Root,
+                true,
+                false,
+            )?;
+            assert_eq!(tag_event_id(&tag), Some(event_id()));
+            Ok(())
+        }
+
+        #[test]
+        fn hex() -> Result<()> {
+            let tag = event_tag_from_nip19_or_hex(
+                &event_id().to_hex(),
+                "in-reply-to",
+                Marker::Root,
+                true,
+                false,
+            )?;
+            assert_eq!(tag_event_id(&tag), Some(event_id()));
+            Ok(())
+        }
+
+        #[test]
+        fn nostr_prefix_and_whitespace_stripped() -> Result<()> {
+            let tag = event_tag_from_nip19_or_hex(
+                &format!(" nostr:{} ", event_id().to_bech32()?),
+                "in-reply-to",
+                Marker::Root,
+                true,
+                false,
+            )?;
+            assert_eq!(tag_event_id(&tag), Some(event_id()));
+            Ok(())
+        }
+
+        #[test]
+        fn naddr() -> Result<()> {
+            let coordinate = Coordinate {
+                kind: Kind::GitRepoAnnouncement,
+                public_key: nostr::Keys::generate().public_key(),
+                identifier: "ngit".to_string(),
+                relays: vec![],
+            };
+            let tag = event_tag_from_nip19_or_hex(
+                &coordinate.to_bech32()?,
+                "in-reply-to",
+                Marker::Root,
+                true,
+                false,
+            )?;
+            assert_eq!(tag, Tag::coordinate(coordinate));
+            Ok(())
+        }
+
+        #[test]
+        fn invalid_reference_errors() {
+            assert!(
+                event_tag_from_nip19_or_hex("abc123", "in-reply-to", Marker::Root, true, false)
+                    .is_err()
+            );
+        }
+    }
+
+    mod is_event_proposal_root_for_branch {
+        use super::*;
+
+        fn generate_proposal() -> Result<nostr::Event> {
+            Ok(nostr::event::EventBuilder::new(
+                nostr::event::Kind::GitPatch,
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] the title\n\ndescription",
+            )
+            .tags([
+                Tag::hashtag("cover-letter"),
+                Tag::hashtag("root"),
+                Tag::custom(TagKind::Custom("branch-name".into()), vec!["feature"]),
+            ])
+            .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        #[test]
+        fn branch_listed_by_remote_helper() -> Result<()> {
+            let proposal = generate_proposal()?;
+            assert!(is_event_proposal_root_for_branch(
+                &proposal,
+                &format!("refs/heads/pr/feature({})", &proposal.id.to_hex()[..8]),
+                None,
+            )?);
+            Ok(())
+        }
+
+        #[test]
+        fn renamed_branch_with_id_fragment() -> Result<()> {
+            let proposal = generate_proposal()?;
+            assert!(is_event_proposal_root_for_branch(
+                &proposal,
+                &format!("pr/my-fix({})", &proposal.id.to_hex()[..8]),
+                None,
+            )?);
+            Ok(())
+        }
+
+        #[test]
+        fn other_id_fragment_or_name_not_matched() -> Result<()> {
+            let proposal = generate_proposal()?;
+            let other_prefix = if proposal.id.to_hex().starts_with('0') {
+                "11111111"
+            } else {
+                "00000000"
+            };
+            assert!(!is_event_proposal_root_for_branch(
+                &proposal,
+                &format!("pr/feature({other_prefix})"),
+                None,
+            )?);
+            assert!(!is_event_proposal_root_for_branch(
+                &proposal,
+                "pr/my-fix",
+                None
+            )?);
+            Ok(())
+        }
+    }
+
+    mod branch_name_from_title {
+        use super::*;
+
+        #[test]
+        fn lowercases_and_joins_words_with_dashes() {
+            assert_eq!(
+                branch_name_from_title("Fix the Parser! (again)"),
+                "fix-the-parser-again"
+            );
+        }
+
+        #[test]
+        fn truncated_without_trailing_dash() {
+            let branch_name = branch_name_from_title(&format!("{} word", "a".repeat(59)));
+            assert_eq!(branch_name, "a".repeat(59));
+        }
+    }
+
+    mod sanitize_file_name {
+        use super::*;
+
+        #[test]
+        fn path_traversal_stays_in_directory() {
+            assert_eq!(
+                sanitize_file_name("../../.git/hooks/post-checkout"),
+                "git-hooks-post-checkout"
+            );
+            assert_eq!(sanitize_file_name("..\\..\\evil"), "evil");
+        }
+
+        #[test]
+        fn absolute_path_and_dot_file_made_relative_and_visible() {
+            assert_eq!(sanitize_file_name("/etc/passwd"), "etc-passwd");
+            assert_eq!(sanitize_file_name(".bashrc"), "bashrc");
+        }
+
+        #[test]
+        fn control_and_bidi_characters_removed() {
+            assert_eq!(
+                sanitize_file_name("fix\n\r\t\u{0}bug\u{202e}txt.exe"),
+                "fix-bug-txt.exe"
+            );
+        }
+
+        #[test]
+        fn nothing_safe_left_is_untitled() {
+            assert_eq!(sanitize_file_name(""), "untitled");
+            assert_eq!(sanitize_file_name(".."), "untitled");
+            assert_eq!(sanitize_file_name("///"), "untitled");
+        }
+
+        #[test]
+        fn capped_in_length() {
+            assert_eq!(
+                sanitize_file_name(&"é".repeat(300)),
+                "é".repeat(MAX_FILE_NAME_STEM_CHARS)
+            );
+        }
+    }
+
+    mod unique_file_name {
+        use super::*;
+
+        #[test]
+        fn numeric_suffix_added_to_names_already_taken_ignoring_case() {
+            let mut taken = HashSet::new();
+            assert_eq!(unique_file_name("fix", "patch", &mut taken), "fix.patch");
+            assert_eq!(unique_file_name("Fix", "patch", &mut taken), "Fix-2.patch");
+            assert_eq!(unique_file_name("fix", "patch", &mut taken), "fix-3.patch");
+        }
+    }
+
+    mod patch_diffstat {
+        use super::*;
+
+        fn patch(content: &str) -> Result<Event> {
+            Ok(EventBuilder::new(Kind::GitPatch, content)
+                .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        #[test]
+        fn counts_files_insertions_and_deletions() -> Result<()> {
+            let patch = patch(
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH] update t.md\n\n---\n t.md | 2 +-\n a.md | 1 +\n\ndiff --git a/t.md b/t.md\nindex 1234567..7654321 100644\n--- a/t.md\n+++ b/t.md\n@@ -1 +1 @@\n-old\n+new\ndiff --git a/a.md b/a.md\nnew file mode 100644\n--- /dev/null\n+++ b/a.md\n@@ -0,0 +1 @@\n+added\n-- \nlibgit2 1.8.1\n\n",
+            )?;
+            assert_eq!(patch_diffstat(&patch), "2 files +2 -1");
+            Ok(())
+        }
+
+        #[test]
+        fn cover_letter_has_no_changes() -> Result<()> {
+            let patch = patch(
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] the title\n\n- a list item in the description",
+            )?;
+            assert_eq!(patch_diffstat(&patch), "0 files +0 -0");
+            Ok(())
+        }
+    }
+
+    mod patches_diffstat {
+        use super::*;
+
+        fn patch(content: &str) -> Result<Event> {
+            Ok(EventBuilder::new(Kind::GitPatch, content)
+                .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        #[test]
+        fn file_changed_by_more_than_one_patch_counted_once() -> Result<()> {
+            let first = patch(
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 1/2] add t.md\n\ndiff --git a/t.md b/t.md\nnew file mode 100644\n--- /dev/null\n+++ b/t.md\n@@ -0,0 +1 @@\n+old\n-- \nlibgit2 1.8.1\n\n",
+            )?;
+            let second = patch(
+                "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nSubject: [PATCH 2/2] update t.md\n\ndiff --git a/t.md b/t.md\n--- a/t.md\n+++ b/t.md\n@@ -1 +1 @@\n-old\n+new\n-- \nlibgit2 1.8.1\n\n",
+            )?;
+            assert_eq!(patches_diffstat(&[first, second]), Diffstat {
+                files: 1,
+                insertions: 2,
+                deletions: 1,
+            });
+            Ok(())
+        }
+    }
+
+    mod diff_from_patch {
+        use super::*;
+
+        fn patch(content: &str) -> Result<nostr::Event> {
+            Ok(
+                nostr::event::EventBuilder::new(nostr::event::Kind::GitPatch, content)
+                    .sign_with_keys(&nostr::Keys::generate())?,
+            )
+        }
+
+        #[test]
+        fn headers_message_and_signature_removed() -> Result<()> {
+            let diff =
+                "diff --git a/t3.md b/t3.md\n--- a/t3.md\n+++ b/t3.md\n@@ -1 +1 @@\n-old\n+new\n";
+            assert_eq!(
+                diff_from_patch(&patch(&format!(
+                    "From 1a2b Mon Sep 17 00:00:00 2001\nSubject: [PATCH] mentions diff --git in message\n\n---\n t3.md | 2 +-\n\n{diff}-- \n2.40.0\n"
+                ))?),
+                diff,
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn cover_letter_has_no_diff() -> Result<()> {
+            assert_eq!(
+                diff_from_patch(&patch(
+                    "From 1a2b Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] the title\n\ndescription"
+                )?),
+                "",
+            );
+            Ok(())
+        }
+    }
+
+    mod binary_patch_size_warning {
+        use super::*;
+
+        fn patch_with_payload(binary: bool, payload_bytes: usize) -> Result<Event> {
+            Ok(EventBuilder::new(
+                Kind::GitPatch,
+                format!(
+                    "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH] add image.png\n\n---\n\ndiff --git a/image.png b/image.png\n{}literal 100\n{}\n",
+                    if binary { "GIT binary patch\n" } else { "" },
+                    "z".repeat(payload_bytes),
+                ),
+            )
+            .sign_with_keys(&nostr::Keys::generate())?)
+        }
+
+        #[test]
+        fn warns_when_binary_patch_is_large() -> Result<()> {
+            let warning = binary_patch_size_warning(&patch_with_payload(true, 70 * 1024)?);
+            assert!(warning.is_some_and(|w| w.contains("'add image.png' is 70 KiB")));
+            Ok(())
+        }
+
+        #[test]
+        fn none_when_small_or_not_binary() -> Result<()> {
+            assert!(binary_patch_size_warning(&patch_with_payload(true, 1024)?).is_none());
+            assert!(binary_patch_size_warning(&patch_with_payload(false, 70 * 1024)?).is_none());
+            Ok(())
+        }
+    }
+
+    mod apply_proposal_edits {
+        use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+        use super::*;
+
+        fn proposal() -> Result<Event> {
+            Ok(EventBuilder::new(
+                Kind::GitPatch,
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] the tilte\n\ndescription here",
+            )
+            .tags([Tag::hashtag("cover-letter"), Tag::hashtag("root")])
+            .sign_with_keys(&TEST_KEY_1_KEYS)?)
+        }
+
+        fn edit(
+            proposal: &Event,
+            keys: &nostr::Keys,
+            content: &str,
+            created_at: u64,
+        ) -> Result<Event> {
+            Ok(EventBuilder::new(PROPOSAL_EDIT_KIND, content)
+                .tags([Tag::custom(
+                    TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::E)),
+                    vec![proposal.id.to_hex(), String::new(), "edit".to_string()],
+                )])
+                .custom_created_at(nostr::Timestamp::from(created_at))
+                .sign_with_keys(keys)?)
+        }
+
+        #[test]
+        fn most_recent_edit_by_author_used() -> Result<()> {
+            let proposal = proposal()?;
+            let edits = vec![
+                edit(&proposal, &TEST_KEY_1_KEYS, "first\n\nfirst desc", 100)?,
+                edit(&proposal, &TEST_KEY_1_KEYS, "the title\n\nnew desc", 200)?,
+            ];
+            let cover_letter =
+                apply_proposal_edits(event_to_cover_letter(&proposal)?, &proposal, &edits, &[]);
+            assert_eq!(cover_letter.title, "the title");
+            assert_eq!(cover_letter.description, "new desc");
+            // branch name stays the same so checkouts are unaffected
+            assert_eq!(cover_letter.branch_name_without_id_or_prefix, "the-tilte");
+            Ok(())
+        }
+
+        #[test]
+        fn edits_by_others_ignored_unless_maintainer() -> Result<()> {
+            let proposal = proposal()?;
+            let edits = vec![edit(&proposal, &TEST_KEY_2_KEYS, "the title", 100)?];
+            let cover_letter =
+                apply_proposal_edits(event_to_cover_letter(&proposal)?, &proposal, &edits, &[]);
+            assert_eq!(cover_letter.title, "the tilte");
+            let cover_letter = apply_proposal_edits(
+                event_to_cover_letter(&proposal)?,
+                &proposal,
+                &edits,
+                &[TEST_KEY_2_KEYS.public_key()],
+            );
+            assert_eq!(cover_letter.title, "the title");
+            Ok(())
+        }
+    }
+
+    mod event_is_deleted {
+        use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+        use super::*;
+
+        fn deletion(event: &Event, keys: &nostr::Keys) -> Result<Event> {
+            Ok(EventBuilder::new(Kind::EventDeletion, "")
+                .tags([Tag::event(event.id)])
+                .sign_with_keys(keys)?)
+        }
+
+        #[test]
+        fn deleted_by_author() -> Result<()> {
+            let event = EventBuilder::new(Kind::GitPatch, "").sign_with_keys(&TEST_KEY_1_KEYS)?;
+            let deletion = deletion(&event, &TEST_KEY_1_KEYS)?;
+            assert!(event_is_deleted(&event, &[deletion]));
+            Ok(())
+        }
+
+        #[test]
+        fn deletion_by_someone_else_ignored() -> Result<()> {
+            let event = EventBuilder::new(Kind::GitPatch, "").sign_with_keys(&TEST_KEY_1_KEYS)?;
+            let deletion = deletion(&event, &TEST_KEY_2_KEYS)?;
+            assert!(!event_is_deleted(&event, &[deletion]));
+            Ok(())
+        }
+    }
 }
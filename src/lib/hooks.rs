@@ -0,0 +1,113 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use nostr::nips::nip01::Coordinate;
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+
+use crate::git::{Repo, RepoActions, get_git_config_item};
+
+/// name of the hook in the hooks directory when `nostr.pre-send-hook` isn't set
+pub static PRE_SEND_HOOK_NAME: &str = "ngit-pre-send";
+
+/// where the pre-send hook is and whether `nostr.pre-send-hook` chose it
+pub struct PreSendHook {
+    pub path: PathBuf,
+    pub from_git_config: bool,
+}
+
+/// `nostr.pre-send-hook`, relative to the top of the working tree, or
+/// `ngit-pre-send` in the hooks directory
+pub fn get_pre_send_hook(git_repo: &Repo) -> Result<PreSendHook> {
+    Ok(
+        match get_git_config_item(&Some(git_repo), "nostr.pre-send-hook")? {
+            Some(path) => PreSendHook {
+                path: git_repo.get_workdir()?.join(path),
+                from_git_config: true,
+            },
+            None => PreSendHook {
+                path: git_repo
+                    .git_repo
+                    .commondir()
+                    .join("hooks")
+                    .join(PRE_SEND_HOOK_NAME),
+                from_git_config: false,
+            },
+        },
+    )
+}
+
+/// hooks that aren't executable are ignored, as git does
+pub fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// script written by `ngit hooks install`. `command` defaults to a
+/// placeholder that always passes
+pub fn pre_send_hook_script(command: Option<&str>) -> String {
+    format!(
+        "#!/bin/sh
+# run by ngit before `ngit send` or `git push` publishes proposal events.
+# publishing is aborted when this exits non-zero.
+#   $1 or NGIT_COMMIT_RANGE     commits to publish, eg. 1a2b3c4..5d6e7f8
+#   $2 or NGIT_REPO_COORDINATE  repository, eg. 30617:<pubkey hex>:<identifier>
+# it runs in the working tree, which may not have the range checked out
+{}
+",
+        command.unwrap_or("# add checks here, eg. cargo test\nexit 0")
+    )
+}
+
+/// `<parent of oldest>..<newest>`, or just `newest` when `oldest` is the root
+/// commit
+pub fn commit_range(git_repo: &Repo, oldest: &Sha1Hash, newest: &Sha1Hash) -> String {
+    match git_repo.get_commit_parent(oldest) {
+        Ok(parent) => format!("{parent}..{newest}"),
+        Err(_) => newest.to_string(),
+    }
+}
+
+/// run the pre-send hook, if installed, and error when it exits non-zero.
+/// its output goes to stderr so it never mixes with the git protocol or
+/// machine readable output
+pub fn run_pre_send_hook(git_repo: &Repo, range: &str, coordinate: &Coordinate) -> Result<()> {
+    let hook = get_pre_send_hook(git_repo)?;
+    if !hook.path.exists() {
+        return Ok(());
+    }
+    if !is_executable(&hook.path) {
+        eprintln!(
+            "hint: the '{}' hook was ignored because it's not set as executable",
+            hook.path.display()
+        );
+        return Ok(());
+    }
+    let coordinate = coordinate.to_string();
+    let status = Command::new(&hook.path)
+        .args([range, &coordinate])
+        .env("NGIT_COMMIT_RANGE", range)
+        .env("NGIT_REPO_COORDINATE", &coordinate)
+        .current_dir(git_repo.get_workdir()?)
+        .stdin(Stdio::null())
+        .stdout(std::io::stderr())
+        .stderr(std::io::stderr())
+        .status()
+        .context(format!("failed to run hook {}", hook.path.display()))?;
+    if !status.success() {
+        bail!(
+            "hook {} exited with {status} so nothing was published",
+            hook.path.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+
+use nostr::{Event, Kind, PublicKey, Timestamp, ToBech32, nips::nip01::Coordinate};
+use serde::Serialize;
+
+use crate::{
+    activity_log::{event_title, format_age},
+    git_events::{
+        event_has_expired, event_is_patch_set_root, event_is_revision_root, status_kinds,
+    },
+    login::user::extract_user_metadata,
+    read_state::ReadState,
+    repo_ref::RepoRef,
+};
+
+/// a repository `ngit inbox` covers, combining the announcements of each of
+/// its maintainers
+pub struct MaintainedRepo {
+    pub identifier: String,
+    pub name: String,
+    pub coordinates: HashSet<Coordinate>,
+    pub relays: Vec<String>,
+}
+
+/// repositories `public_key` announced or is listed as a maintainer of,
+/// sorted by name
+pub fn maintained_repos(public_key: &PublicKey, announcements: &[Event]) -> Vec<MaintainedRepo> {
+    let mut latest: HashMap<(PublicKey, String), &Event> = HashMap::new();
+    for event in announcements
+        .iter()
+        .filter(|e| e.kind == Kind::GitRepoAnnouncement)
+    {
+        let Some(identifier) = event.tags.identifier() else {
+            continue;
+        };
+        let entry = latest
+            .entry((event.pubkey, identifier.to_string()))
+            .or_insert(event);
+        if event.created_at > entry.created_at {
+            *entry = event;
+        }
+    }
+
+    let mut repos: HashMap<String, (Option<RepoRef>, Vec<RepoRef>)> = HashMap::new();
+    for event in latest.into_values() {
+        let Ok(repo_ref) = RepoRef::try_from((event.clone(), None)) else {
+            continue;
+        };
+        if event.pubkey != *public_key && !repo_ref.maintainers.contains(public_key) {
+            continue;
+        }
+        let (mine, others) = repos.entry(repo_ref.identifier.clone()).or_default();
+        if event.pubkey == *public_key {
+            *mine = Some(repo_ref);
+        } else {
+            others.push(repo_ref);
+        }
+    }
+
+    let mut maintained = repos
+        .into_iter()
+        .map(|(identifier, (mine, others))| {
+            let repo_refs = mine.into_iter().chain(others).collect::<Vec<RepoRef>>();
+            let mut relays = vec![];
+            for relay in repo_refs.iter().flat_map(|r| &r.relays) {
+                if !relays.contains(&relay.to_string()) {
+                    relays.push(relay.to_string());
+                }
+            }
+            MaintainedRepo {
+                name: repo_refs
+                    .iter()
+                    .map(|r| r.name.clone())
+                    .find(|name| !name.is_empty())
+                    .unwrap_or(identifier.clone()),
+                identifier,
+                coordinates: repo_refs.iter().flat_map(RepoRef::coordinates).collect(),
+                relays,
+            }
+        })
+        .collect::<Vec<MaintainedRepo>>();
+    maintained.sort_by(|a, b| (&a.name, &a.identifier).cmp(&(&b.name, &b.identifier)));
+    maintained
+}
+
+#[derive(Serialize)]
+pub struct InboxItem {
+    pub id: String,
+    /// "proposal" or "issue"
+    pub kind: &'static str,
+    pub title: String,
+    pub author: String,
+    pub author_npub: String,
+    pub created_at: u64,
+    /// activity since the inbox was last viewed
+    pub unread: bool,
+}
+
+#[derive(Serialize)]
+pub struct RepoInbox {
+    pub name: String,
+    pub identifier: String,
+    pub coordinates: Vec<String>,
+    pub items: Vec<InboxItem>,
+}
+
+fn is_open(event: &Event, events: &[Event], now: Timestamp) -> bool {
+    events
+        .iter()
+        .filter(|e| {
+            status_kinds().contains(&e.kind)
+                && !event_has_expired(e, now)
+                && e.tags.event_ids().any(|id| id == &event.id)
+        })
+        .max_by_key(|e| e.created_at)
+        .is_none_or(|status| status.kind == Kind::GitStatusOpen)
+}
+
+/// open proposals and issues of each repository from `events`, newest first.
+/// `events` also holds the statuses, comments and profiles fetched for them
+pub fn build_inbox(
+    repos: &[MaintainedRepo],
+    events: &[Event],
+    read_state: &ReadState,
+    now: Timestamp,
+) -> Vec<RepoInbox> {
+    repos
+        .iter()
+        .map(|repo| {
+            let coordinates = repo
+                .coordinates
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<HashSet<String>>();
+            let mut threads = events
+                .iter()
+                .filter(|e| {
+                    (e.kind == Kind::GitIssue
+                        || (event_is_patch_set_root(e) && !event_is_revision_root(e)))
+                        && !event_has_expired(e, now)
+                        && e.tags.iter().any(|t| match t.as_slice() {
+                            [name, value, ..] => name == "a" && coordinates.contains(value),
+                            _ => false,
+                        })
+                        && is_open(e, events, now)
+                })
+                .collect::<Vec<&Event>>();
+            threads.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+            let mut coordinates = coordinates.into_iter().collect::<Vec<String>>();
+            coordinates.sort();
+            RepoInbox {
+                name: repo.name.clone(),
+                identifier: repo.identifier.clone(),
+                coordinates,
+                items: threads
+                    .into_iter()
+                    .map(|e| InboxItem {
+                        id: e.id.to_hex(),
+                        kind: if e.kind == Kind::GitIssue {
+                            "issue"
+                        } else {
+                            "proposal"
+                        },
+                        title: event_title(e),
+                        author: extract_user_metadata(&e.pubkey, events)
+                            .map(|metadata| metadata.name)
+                            .unwrap_or(e.pubkey.to_string()),
+                        author_npub: e.pubkey.to_bech32().unwrap_or(e.pubkey.to_string()),
+                        created_at: e.created_at.as_u64(),
+                        unread: read_state.unread_activity(e, events).is_some(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// grouped summary printed by `ngit inbox`. unread items are marked with `*`
+pub fn render_inbox(inbox: &[RepoInbox], now: Timestamp) -> String {
+    let mut lines = vec![];
+    for repo in inbox {
+        lines.push(repo.name.clone());
+        if repo.items.is_empty() {
+            lines.push("  no open proposals or issues".to_string());
+        }
+        for item in &repo.items {
+            lines.push(format!(
+                "{} {} {} by {} ({})",
+                if item.unread { "*" } else { " " },
+                item.kind,
+                item.title,
+                item.author,
+                format_age(Timestamp::from(item.created_at), now),
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag, TagKind};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn announcement(keys: &Keys, identifier: &str, maintainers: &[&Keys]) -> Event {
+        EventBuilder::new(Kind::GitRepoAnnouncement, "")
+            .tags(vec![
+                Tag::identifier(identifier),
+                Tag::custom(TagKind::Custom("name".into()), vec![identifier.to_string()]),
+                Tag::custom(
+                    TagKind::Custom("maintainers".into()),
+                    maintainers
+                        .iter()
+                        .map(|k| k.public_key().to_string())
+                        .collect::<Vec<String>>(),
+                ),
+            ])
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn coordinate(keys: &Keys, identifier: &str) -> String {
+        format!("30617:{}:{identifier}", keys.public_key())
+    }
+
+    fn issue(keys: &Keys, coordinate: &str, subject: &str, created_at: u64) -> Event {
+        EventBuilder::new(Kind::GitIssue, "")
+            .tags(vec![
+                Tag::coordinate(Coordinate::parse(coordinate).unwrap()),
+                Tag::custom(TagKind::Custom("subject".into()), vec![subject.to_string()]),
+            ])
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn read_state(since: u64) -> ReadState {
+        ReadState {
+            since: Timestamp::from(since),
+            last_seen: HashMap::new(),
+        }
+    }
+
+    mod maintained_repos {
+        use super::*;
+
+        #[test]
+        fn includes_own_and_co_maintained_but_not_others() {
+            let other = Keys::generate();
+            let repos = maintained_repos(
+                &TEST_KEY_1_KEYS.public_key(),
+                &[
+                    announcement(&TEST_KEY_1_KEYS, "mine", &[&TEST_KEY_1_KEYS]),
+                    announcement(
+                        &TEST_KEY_2_KEYS,
+                        "shared",
+                        &[&TEST_KEY_2_KEYS, &TEST_KEY_1_KEYS],
+                    ),
+                    announcement(&other, "theirs", &[&other]),
+                ],
+            );
+            assert_eq!(
+                repos.iter().map(|r| r.name.as_str()).collect::<Vec<&str>>(),
+                vec!["mine", "shared"]
+            );
+        }
+
+        #[test]
+        fn announcements_with_same_identifier_grouped() {
+            let repos = maintained_repos(
+                &TEST_KEY_1_KEYS.public_key(),
+                &[
+                    announcement(
+                        &TEST_KEY_1_KEYS,
+                        "repo",
+                        &[&TEST_KEY_1_KEYS, &TEST_KEY_2_KEYS],
+                    ),
+                    announcement(
+                        &TEST_KEY_2_KEYS,
+                        "repo",
+                        &[&TEST_KEY_2_KEYS, &TEST_KEY_1_KEYS],
+                    ),
+                ],
+            );
+            assert_eq!(repos.len(), 1);
+            assert_eq!(repos[0].coordinates.len(), 2);
+        }
+    }
+
+    mod build_inbox {
+        use super::*;
+
+        #[test]
+        fn closed_items_and_other_repos_excluded_newest_first() {
+            let repos = maintained_repos(
+                &TEST_KEY_1_KEYS.public_key(),
+                &[announcement(&TEST_KEY_1_KEYS, "repo", &[&TEST_KEY_1_KEYS])],
+            );
+            let in_repo = coordinate(&TEST_KEY_1_KEYS, "repo");
+            let older = issue(&TEST_KEY_2_KEYS, &in_repo, "older", 100);
+            let newer = issue(&TEST_KEY_2_KEYS, &in_repo, "newer", 200);
+            let closed = issue(&TEST_KEY_2_KEYS, &in_repo, "closed", 150);
+            let elsewhere = issue(
+                &TEST_KEY_2_KEYS,
+                &coordinate(&TEST_KEY_2_KEYS, "repo"),
+                "elsewhere",
+                150,
+            );
+            let close = EventBuilder::new(Kind::GitStatusClosed, "")
+                .tags(vec![Tag::event(closed.id)])
+                .custom_created_at(Timestamp::from(160))
+                .sign_with_keys(&TEST_KEY_1_KEYS)
+                .unwrap();
+            let inbox = build_inbox(
+                &repos,
+                &[older, newer, closed, elsewhere, close],
+                &read_state(150),
+                Timestamp::from(300),
+            );
+            assert_eq!(
+                inbox[0]
+                    .items
+                    .iter()
+                    .map(|i| (i.title.as_str(), i.unread))
+                    .collect::<Vec<(&str, bool)>>(),
+                vec![("newer", true), ("older", false)]
+            );
+        }
+    }
+
+    mod render_inbox {
+        use super::*;
+
+        #[test]
+        fn grouped_by_repo_with_unread_marker() {
+            let inbox = vec![
+                RepoInbox {
+                    name: "alpha".to_string(),
+                    identifier: "alpha".to_string(),
+                    coordinates: vec![],
+                    items: vec![InboxItem {
+                        id: String::new(),
+                        kind: "proposal",
+                        title: "add feature".to_string(),
+                        author: "carole".to_string(),
+                        author_npub: String::new(),
+                        created_at: 0,
+                        unread: true,
+                    }],
+                },
+                RepoInbox {
+                    name: "beta".to_string(),
+                    identifier: "beta".to_string(),
+                    coordinates: vec![],
+                    items: vec![],
+                },
+            ];
+            assert_eq!(
+                render_inbox(&inbox, Timestamp::from(2 * 86_400)),
+                "alpha\n* proposal add feature by carole (2 days ago)\nbeta\n  no open proposals or issues"
+            );
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::{Context, Result};
+use nostr_sdk::{
+    Alphabet, Event, EventBuilder, Kind, NostrSigner, PublicKey, SingleLetterTag, Tag, TagKind,
+};
+
+use crate::{client::sign_event, repo_ref::RepoRef};
+
+/// NIP-32 namespace of proposal labels unless `nostr.label-namespace` is set
+pub static DEFAULT_LABEL_NAMESPACE: &str = "ngit.proposal";
+
+fn labels_proposal(event: &Event, proposal: &Event) -> bool {
+    event.tags.iter().any(|t| {
+        let t = t.as_slice();
+        t.len() > 1 && t[0].eq("e") && t[1].eq(&proposal.id.to_hex())
+    })
+}
+
+fn in_namespace(event: &Event, namespace: &str) -> bool {
+    event.tags.iter().any(|t| match t.as_slice() {
+        [name, value, ..] => name == "L" && value == namespace,
+        _ => false,
+    })
+}
+
+/// a label event holding every label of `proposal`. the latest one replaces
+/// earlier ones, so a label is removed by publishing the set without it
+pub async fn generate_label_event(
+    proposal: &Event,
+    repo_ref: &RepoRef,
+    namespace: &str,
+    labels: &[String],
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<Event> {
+    sign_event(
+        EventBuilder::new(Kind::Label, "").tags(
+            [
+                vec![Tag::custom(
+                    TagKind::SingleLetter(SingleLetterTag::uppercase(Alphabet::L)),
+                    vec![namespace.to_string()],
+                )],
+                labels
+                    .iter()
+                    .map(|label| {
+                        Tag::custom(
+                            TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
+                            vec![label.clone(), namespace.to_string()],
+                        )
+                    })
+                    .collect::<Vec<Tag>>(),
+                vec![
+                    Tag::custom(
+                        TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::E)),
+                        vec![
+                            proposal.id.to_hex(),
+                            repo_ref
+                                .relays
+                                .first()
+                                .map(ToString::to_string)
+                                .unwrap_or_default(),
+                        ],
+                    ),
+                    Tag::public_key(proposal.pubkey),
+                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("alt")), vec![
+                        format!("git proposal labels: {}", labels.join(", ")),
+                    ]),
+                ],
+            ]
+            .concat(),
+        ),
+        signer,
+    )
+    .await
+    .context("failed to sign label event")
+}
+
+/// labels on `proposal` in `namespace` from the most recent label event by
+/// one of `trusted` authors, or by anyone when `trusted` is none
+pub fn proposal_labels(
+    proposal: &Event,
+    label_events: &[Event],
+    namespace: &str,
+    trusted: Option<&[PublicKey]>,
+) -> Vec<String> {
+    label_events
+        .iter()
+        .filter(|e| {
+            e.kind.eq(&Kind::Label)
+                && trusted.is_none_or(|trusted| trusted.contains(&e.pubkey))
+                && in_namespace(e, namespace)
+                && labels_proposal(e, proposal)
+        })
+        .max_by_key(|e| e.created_at)
+        .map(|e| {
+            e.tags
+                .iter()
+                .filter_map(|t| match t.as_slice() {
+                    [name, label, label_namespace, ..]
+                        if name == "l" && label_namespace == namespace =>
+                    {
+                        Some(label.clone())
+                    }
+                    _ => None,
+                })
+                .collect::<BTreeSet<String>>()
+                .into_iter()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `current` with `add` and without `remove`, sorted and without duplicates
+pub fn apply_label_changes(current: &[String], add: &[String], remove: &[String]) -> Vec<String> {
+    current
+        .iter()
+        .chain(add)
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty() && !remove.iter().any(|r| r.trim() == label))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// labels shown after a proposal title, eg. "[breaking, bug]"
+pub fn format_labels(labels: &[String]) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", labels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::{Keys, Timestamp};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn proposal() -> Event {
+        EventBuilder::new(Kind::GitPatch, "")
+            .tags([Tag::hashtag("cover-letter"), Tag::hashtag("root")])
+            .sign_with_keys(&TEST_KEY_2_KEYS)
+            .unwrap()
+    }
+
+    fn label_event(
+        proposal: &Event,
+        keys: &Keys,
+        namespace: &str,
+        labels: &[&str],
+        created_at: u64,
+    ) -> Event {
+        EventBuilder::new(Kind::Label, "")
+            .tags(
+                [
+                    vec![
+                        Tag::custom(
+                            TagKind::SingleLetter(SingleLetterTag::uppercase(Alphabet::L)),
+                            vec![namespace.to_string()],
+                        ),
+                        Tag::event(proposal.id),
+                    ],
+                    labels
+                        .iter()
+                        .map(|label| {
+                            Tag::custom(
+                                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
+                                vec![(*label).to_string(), namespace.to_string()],
+                            )
+                        })
+                        .collect(),
+                ]
+                .concat(),
+            )
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    mod proposal_labels {
+        use super::*;
+
+        #[test]
+        fn most_recent_label_event_by_maintainer_used() {
+            let proposal = proposal();
+            let events = vec![
+                label_event(
+                    &proposal,
+                    &TEST_KEY_1_KEYS,
+                    DEFAULT_LABEL_NAMESPACE,
+                    &["bug", "breaking"],
+                    100,
+                ),
+                label_event(
+                    &proposal,
+                    &TEST_KEY_1_KEYS,
+                    DEFAULT_LABEL_NAMESPACE,
+                    &["bug"],
+                    200,
+                ),
+            ];
+            assert_eq!(
+                proposal_labels(
+                    &proposal,
+                    &events,
+                    DEFAULT_LABEL_NAMESPACE,
+                    Some(&[TEST_KEY_1_KEYS.public_key()]),
+                ),
+                vec!["bug".to_string()],
+            );
+        }
+
+        #[test]
+        fn non_maintainer_labels_ignored_unless_anyone_allowed() {
+            let proposal = proposal();
+            let events = vec![label_event(
+                &proposal,
+                &TEST_KEY_2_KEYS,
+                DEFAULT_LABEL_NAMESPACE,
+                &["bug"],
+                100,
+            )];
+            assert!(
+                proposal_labels(
+                    &proposal,
+                    &events,
+                    DEFAULT_LABEL_NAMESPACE,
+                    Some(&[TEST_KEY_1_KEYS.public_key()]),
+                )
+                .is_empty()
+            );
+            assert_eq!(
+                proposal_labels(&proposal, &events, DEFAULT_LABEL_NAMESPACE, None),
+                vec!["bug".to_string()],
+            );
+        }
+
+        #[test]
+        fn other_namespaces_ignored() {
+            let proposal = proposal();
+            let events = vec![label_event(
+                &proposal,
+                &TEST_KEY_1_KEYS,
+                "other.namespace",
+                &["bug"],
+                100,
+            )];
+            assert!(proposal_labels(&proposal, &events, DEFAULT_LABEL_NAMESPACE, None).is_empty());
+        }
+    }
+
+    mod apply_label_changes {
+        use super::*;
+
+        #[test]
+        fn adds_and_removes_sorted_without_duplicates() {
+            assert_eq!(
+                apply_label_changes(
+                    &["breaking".to_string(), "bug".to_string()],
+                    &["good-first-review".to_string(), "bug".to_string()],
+                    &["breaking".to_string()],
+                ),
+                vec!["bug".to_string(), "good-first-review".to_string()],
+            );
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{Result, anyhow};
+use nostr::{
+    Event, EventBuilder, Kind, PublicKey, RelayUrl, Tag, Timestamp, nips::nip01::Coordinate,
+};
+
+#[cfg(not(test))]
+use crate::client::Client;
+#[cfg(test)]
+use crate::client::MockConnect;
+use crate::{
+    client::{
+        Connect, STATE_KIND, get_event_from_global_cache, get_filter_contributor_profiles,
+        get_filter_repo_events, get_filter_state_events, save_event_in_global_cache,
+    },
+    error::{ErrorCategory, NgitError},
+    login::user::extract_user_metadata,
+    output::multi_progress,
+};
+
+/// NIP-51 git repositories list. gitworkshop.dev uses it for watched repos
+pub static GIT_REPOSITORIES_LIST_KIND: Kind = Kind::Custom(10018);
+
+pub fn get_filter_git_repositories_list(public_key: &PublicKey) -> nostr::Filter {
+    nostr::Filter::default()
+        .kind(GIT_REPOSITORIES_LIST_KIND)
+        .author(*public_key)
+}
+
+/// repository coordinates listed in a git repositories list event
+pub fn get_watched_repo_coordinates(list: &Event) -> Vec<Coordinate> {
+    list.tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, value, ..] if name == "a" => Coordinate::parse(value).ok(),
+            _ => None,
+        })
+        .filter(|c| c.kind == Kind::GitRepoAnnouncement)
+        .collect()
+}
+
+fn is_same_repo(a: &Coordinate, b: &Coordinate) -> bool {
+    a.kind == b.kind && a.public_key == b.public_key && a.identifier == b.identifier
+}
+
+/// updated list adding or removing `coordinate`. other tags, and any private
+/// items in content, are preserved. returns None if no change is needed
+pub fn update_git_repositories_list(
+    existing: Option<&Event>,
+    coordinate: &Coordinate,
+    watch: bool,
+) -> Option<EventBuilder> {
+    let watching = existing.is_some_and(|list| {
+        get_watched_repo_coordinates(list)
+            .iter()
+            .any(|c| is_same_repo(c, coordinate))
+    });
+    if watching == watch {
+        return None;
+    }
+    let mut tags: Vec<Tag> = existing
+        .map(|list| {
+            list.tags
+                .iter()
+                .filter(|tag| match tag.as_slice() {
+                    [name, value, ..] if name == "a" => {
+                        !Coordinate::parse(value).is_ok_and(|c| is_same_repo(&c, coordinate))
+                    }
+                    _ => true,
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    if watch {
+        tags.push(Tag::coordinate(Coordinate {
+            kind: coordinate.kind,
+            public_key: coordinate.public_key,
+            identifier: coordinate.identifier.clone(),
+            relays: vec![],
+        }));
+    }
+    Some(
+        EventBuilder::new(
+            GIT_REPOSITORIES_LIST_KIND,
+            existing.map_or(String::new(), |list| list.content.clone()),
+        )
+        .tags(tags),
+    )
+}
+
+/// newest git repositories list from `relays` or the cache. fetched lists are
+/// saved in the global cache
+pub async fn fetch_git_repositories_list(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+    git_repo_path: Option<&Path>,
+    public_key: &PublicKey,
+    relays: Vec<String>,
+) -> Result<Option<Event>> {
+    let filter = get_filter_git_repositories_list(public_key);
+    let relays = relays
+        .into_iter()
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect::<Vec<String>>();
+    let (relay_results, _) = client
+        .get_events_per_relay(
+            relays
+                .iter()
+                .filter_map(|r| RelayUrl::parse(r).ok())
+                .collect(),
+            vec![filter.clone()],
+            multi_progress(),
+        )
+        .await?;
+    // a list built without the latest one would drop the repositories it lists
+    if !relay_results.iter().any(Result::is_ok) {
+        return Err(anyhow!(
+            "failed to fetch your git repositories list from any of: {}",
+            relays.join(", ")
+        ))
+        .category(NgitError::Network);
+    }
+    let fetched = relay_results
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect::<Vec<Event>>();
+    for event in &fetched {
+        save_event_in_global_cache(git_repo_path, event).await?;
+    }
+    Ok(get_event_from_global_cache(git_repo_path, vec![filter])
+        .await?
+        .into_iter()
+        .chain(fetched)
+        .filter(|e| e.kind == GIT_REPOSITORIES_LIST_KIND && e.pubkey == *public_key)
+        .max_by_key(|e| e.created_at))
+}
+
+pub struct WatchedRepoSummary {
+    pub coordinate: Coordinate,
+    pub name: String,
+    pub maintainer: String,
+    /// newest announcement, state or patch event seen
+    pub last_activity: Option<Timestamp>,
+}
+
+pub fn get_filters_watched_repo_activity(coordinates: &[Coordinate]) -> Vec<nostr::Filter> {
+    let coordinates = coordinates.iter().cloned().collect::<HashSet<Coordinate>>();
+    vec![
+        get_filter_repo_events(&coordinates),
+        get_filter_state_events(&coordinates),
+        nostr::Filter::default().kind(Kind::GitPatch).custom_tag(
+            nostr::SingleLetterTag::lowercase(nostr::Alphabet::A),
+            coordinates
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>(),
+        ),
+        get_filter_contributor_profiles(coordinates.iter().map(|c| c.public_key).collect()),
+    ]
+}
+
+pub fn summarise_watched_repos(
+    coordinates: &[Coordinate],
+    events: &[Event],
+) -> Vec<WatchedRepoSummary> {
+    coordinates
+        .iter()
+        .map(|coordinate| {
+            let is_repo_event = |e: &&Event| {
+                [Kind::GitRepoAnnouncement, STATE_KIND].contains(&e.kind)
+                    && e.pubkey == coordinate.public_key
+                    && e.tags.identifier() == Some(coordinate.identifier.as_str())
+            };
+            let announcement = events
+                .iter()
+                .filter(is_repo_event)
+                .filter(|e| e.kind == Kind::GitRepoAnnouncement)
+                .max_by_key(|e| e.created_at);
+            let repo_coordinate = coordinate.to_string();
+            let last_activity = events
+                .iter()
+                .filter(|e| {
+                    is_repo_event(e)
+                        || (e.kind == Kind::GitPatch
+                            && e.tags.iter().any(|t| {
+                                t.as_slice().first().is_some_and(|n| n == "a")
+                                    && t.as_slice().get(1) == Some(&repo_coordinate)
+                            }))
+                })
+                .map(|e| e.created_at)
+                .max();
+            WatchedRepoSummary {
+                coordinate: coordinate.clone(),
+                name: announcement
+                    .and_then(|e| {
+                        e.tags
+                            .iter()
+                            .find(|t| t.as_slice().first().is_some_and(|n| n == "name"))
+                            .and_then(|t| t.as_slice().get(1).cloned())
+                    })
+                    .unwrap_or(coordinate.identifier.clone()),
+                maintainer: extract_user_metadata(&coordinate.public_key, events)
+                    .map(|metadata| metadata.name)
+                    .unwrap_or(coordinate.public_key.to_string()),
+                last_activity,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Keys;
+
+    use super::*;
+
+    fn repo_coordinate(identifier: &str) -> Coordinate {
+        Coordinate {
+            kind: Kind::GitRepoAnnouncement,
+            public_key: Keys::generate().public_key(),
+            identifier: identifier.to_string(),
+            relays: vec![],
+        }
+    }
+
+    fn sign(builder: EventBuilder) -> Result<Event> {
+        Ok(builder.sign_with_keys(&Keys::generate())?)
+    }
+
+    mod update_git_repositories_list {
+        use super::*;
+
+        #[test]
+        fn watch_adds_coordinate_to_new_list() -> Result<()> {
+            let coordinate = repo_coordinate("repo");
+            let list = sign(update_git_repositories_list(None, &coordinate, true).unwrap())?;
+            assert_eq!(list.kind, GIT_REPOSITORIES_LIST_KIND);
+            assert_eq!(get_watched_repo_coordinates(&list), vec![coordinate]);
+            Ok(())
+        }
+
+        #[test]
+        fn watch_and_unwatch_round_trip() -> Result<()> {
+            let keep = repo_coordinate("keep");
+            let coordinate = repo_coordinate("repo");
+            let list = sign(update_git_repositories_list(None, &keep, true).unwrap())?;
+            let list = sign(update_git_repositories_list(Some(&list), &coordinate, true).unwrap())?;
+            assert_eq!(get_watched_repo_coordinates(&list), vec![
+                keep.clone(),
+                coordinate.clone()
+            ]);
+            let list =
+                sign(update_git_repositories_list(Some(&list), &coordinate, false).unwrap())?;
+            assert_eq!(get_watched_repo_coordinates(&list), vec![keep]);
+            Ok(())
+        }
+
+        #[test]
+        fn no_change_when_already_in_desired_state() -> Result<()> {
+            let coordinate = repo_coordinate("repo");
+            assert!(update_git_repositories_list(None, &coordinate, false).is_none());
+            let list = sign(update_git_repositories_list(None, &coordinate, true).unwrap())?;
+            assert!(update_git_repositories_list(Some(&list), &coordinate, true).is_none());
+            Ok(())
+        }
+
+        #[test]
+        fn preserves_other_tags_and_content() -> Result<()> {
+            let coordinate = repo_coordinate("repo");
+            let list = sign(
+                EventBuilder::new(GIT_REPOSITORIES_LIST_KIND, "encrypted private items")
+                    .tags(vec![Tag::hashtag("nostr")]),
+            )?;
+            let list = sign(update_git_repositories_list(Some(&list), &coordinate, true).unwrap())?;
+            assert_eq!(list.content, "encrypted private items");
+            assert!(
+                list.tags
+                    .iter()
+                    .any(|t| t.as_slice() == ["t", "nostr"].as_slice())
+            );
+            Ok(())
+        }
+    }
+
+    mod summarise_watched_repos {
+        use super::*;
+
+        #[test]
+        fn uses_announcement_name_and_newest_activity() -> Result<()> {
+            let keys = Keys::generate();
+            let coordinate = Coordinate {
+                kind: Kind::GitRepoAnnouncement,
+                public_key: keys.public_key(),
+                identifier: "repo".to_string(),
+                relays: vec![],
+            };
+            let announcement = EventBuilder::new(Kind::GitRepoAnnouncement, "")
+                .tags(vec![
+                    Tag::identifier("repo"),
+                    Tag::custom(nostr::TagKind::Custom("name".into()), vec![
+                        "example name".to_string(),
+                    ]),
+                ])
+                .custom_created_at(Timestamp::from(100))
+                .sign_with_keys(&keys)?;
+            let patch = EventBuilder::new(Kind::GitPatch, "")
+                .tags(vec![Tag::coordinate(coordinate.clone())])
+                .custom_created_at(Timestamp::from(200))
+                .sign_with_keys(&Keys::generate())?;
+            let summaries = summarise_watched_repos(
+                &[coordinate.clone(), repo_coordinate("other")],
+                &[announcement, patch],
+            );
+            assert_eq!(summaries[0].name, "example name");
+            assert_eq!(summaries[0].last_activity, Some(Timestamp::from(200)));
+            assert_eq!(summaries[1].name, "other");
+            assert_eq!(summaries[1].last_activity, None);
+            Ok(())
+        }
+    }
+}
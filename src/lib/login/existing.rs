@@ -1,4 +1,4 @@
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, bail};
 use nostr::nips::nip46::NostrConnectURI;
@@ -18,16 +18,79 @@ use crate::client::MockConnect;
 use crate::{
     cli_interactor::{Interactor, InteractorPrompt, PromptPasswordParms},
     client::fetch_public_key,
+    error::{ErrorCategory, NgitError},
     git::{Repo, RepoActions, get_git_config_item},
 };
 
+/// how [`load_existing_login`] behaves. defaults to printing who is logged
+/// in and prompting for the password of an encrypted nsec
+#[derive(Debug, Clone)]
+pub struct LoadLoginOptions {
+    /// don't print who is logged in
+    pub silent: bool,
+    /// ask for the password of an encrypted nsec when none was supplied,
+    /// rather than failing
+    pub prompt_for_password: bool,
+    /// fetch the user's profile from relays even when it is cached
+    pub fetch_profile_updates: bool,
+    /// when false, fail with [`NeedsInteraction`] instead of prompting. for
+    /// git-remote-nostr, which must never prompt
+    pub allow_prompts: bool,
+}
+
+impl Default for LoadLoginOptions {
+    fn default() -> Self {
+        Self {
+            silent: false,
+            prompt_for_password: true,
+            fetch_profile_updates: false,
+            allow_prompts: true,
+        }
+    }
+}
+
+impl LoadLoginOptions {
+    pub fn silent(mut self) -> Self {
+        self.silent = true;
+        self
+    }
+
+    pub fn dont_prompt_for_password(mut self) -> Self {
+        self.prompt_for_password = false;
+        self
+    }
+
+    pub fn with_fetch_profile_updates(mut self, fetch_profile_updates: bool) -> Self {
+        self.fetch_profile_updates = fetch_profile_updates;
+        self
+    }
+
+    pub fn without_prompts(mut self) -> Self {
+        self.allow_prompts = false;
+        self
+    }
+}
+
+/// loading a login needed a prompt but [`LoadLoginOptions::allow_prompts`]
+/// was false
+#[derive(Debug)]
+pub struct NeedsInteraction {
+    pub prompt: String,
+}
+
+impl fmt::Display for NeedsInteraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "login needs interactive input for '{}'", self.prompt)
+    }
+}
+
+impl std::error::Error for NeedsInteraction {}
+
 /// load signer from git config and UserProfile from cache or relays
 ///
 /// # Parameters
 /// - `client`: include client to fetch profiles from relays that are missing
 ///   from cache
-/// - `silent`: do not print outcome in termianl
-#[allow(clippy::too_many_arguments)]
 pub async fn load_existing_login(
     git_repo: &Option<&Repo>,
     signer_info: &Option<SignerInfo>,
@@ -35,13 +98,11 @@ pub async fn load_existing_login(
     source: &Option<SignerInfoSource>,
     #[cfg(test)] client: Option<&MockConnect>,
     #[cfg(not(test))] client: Option<&Client>,
-    silent: bool,
-    prompt_for_password: bool,
-    fetch_profile_updates: bool,
+    options: &LoadLoginOptions,
 ) -> Result<(Arc<dyn NostrSigner>, UserRef, SignerInfoSource)> {
     let (signer_info, source) = get_signer_info(git_repo, signer_info, password, source)?;
 
-    let (signer, public_key) = get_signer(&signer_info, prompt_for_password).await?;
+    let (signer, public_key) = get_signer(&signer_info, options).await?;
 
     let user_ref = get_user_details(
         &public_key,
@@ -51,12 +112,12 @@ pub async fn load_existing_login(
         } else {
             None
         },
-        silent,
-        fetch_profile_updates,
+        options.silent,
+        options.fetch_profile_updates,
     )
     .await?;
 
-    if !silent {
+    if !options.silent {
         print_logged_in_as(&user_ref, client.is_none(), &source)?;
     }
     Ok((signer, user_ref, source))
@@ -163,7 +224,7 @@ pub fn get_signer_info(
 
 async fn get_signer(
     signer_info: &SignerInfo,
-    prompt_for_ncryptsec_password: bool,
+    options: &LoadLoginOptions,
 ) -> Result<(Arc<dyn NostrSigner>, PublicKey)> {
     match signer_info {
         SignerInfo::Nsec {
@@ -178,20 +239,33 @@ async fn get_signer(
                 let password = if let Some(password) = password {
                     password.clone()
                 } else {
-                    if !prompt_for_ncryptsec_password {
+                    if !options.prompt_for_password {
                         bail!(
                             "failed to login without prompts a nsec is encrypted with a password"
                         );
                     }
+                    if !options.allow_prompts {
+                        return Err(NeedsInteraction {
+                            prompt: "password".to_string(),
+                        }
+                        .into());
+                    }
                     Interactor::default()
-                        .password(PromptPasswordParms::default().with_prompt("password"))
+                        .password(
+                            PromptPasswordParms::default()
+                                .with_prompt("password")
+                                .with_flag("--password"),
+                        )
                         .context("failed to get password input from interactor.password")?
                 };
                 decrypt_key(nsec, password.clone().as_str())
                     .context("failed to decrypt key with provided password")
-                    .context("failed to decrypt ncryptsec supplied as nsec with password")?
+                    .context("failed to decrypt ncryptsec supplied as nsec with password")
+                    .category(NgitError::Auth)?
             } else {
-                nostr::Keys::from_str(nsec).context("invalid nsec parameter")?
+                nostr::Keys::from_str(nsec)
+                    .context("invalid nsec parameter")
+                    .category(NgitError::Auth)?
             };
             let public_key = keys.public_key();
             Ok((Arc::new(keys), public_key))
@@ -223,3 +297,48 @@ async fn get_signer(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod get_signer {
+        use test_utils::{TEST_KEY_1_ENCRYPTED_WEAK, TEST_KEY_1_KEYS, TEST_WEAK_PASSWORD};
+
+        use super::*;
+
+        fn encrypted_nsec(password: Option<&str>) -> SignerInfo {
+            SignerInfo::Nsec {
+                nsec: TEST_KEY_1_ENCRYPTED_WEAK.to_string(),
+                password: password.map(ToString::to_string),
+                npub: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn needs_interaction_when_prompts_arent_allowed() {
+            let error = get_signer(
+                &encrypted_nsec(None),
+                &LoadLoginOptions::default().without_prompts(),
+            )
+            .await
+            .err()
+            .unwrap();
+            assert_eq!(
+                error.downcast_ref::<NeedsInteraction>().unwrap().prompt,
+                "password"
+            );
+        }
+
+        #[tokio::test]
+        async fn decrypts_with_supplied_password_when_prompts_arent_allowed() -> Result<()> {
+            let (_, public_key) = get_signer(
+                &encrypted_nsec(Some(TEST_WEAK_PASSWORD)),
+                &LoadLoginOptions::default().without_prompts(),
+            )
+            .await?;
+            assert_eq!(public_key, TEST_KEY_1_KEYS.public_key());
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{path::Path, str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, bail};
 use console::Style;
@@ -11,7 +11,7 @@ use tokio::{signal, sync::Mutex};
 
 use super::{
     SignerInfo, SignerInfoSource,
-    existing::load_existing_login,
+    existing::{LoadLoginOptions, load_existing_login},
     key_encryption::decrypt_key,
     print_logged_in_as,
     user::{UserRef, get_user_details},
@@ -25,8 +25,9 @@ use crate::{
         Interactor, InteractorPrompt, Printer, PromptChoiceParms, PromptConfirmParms,
         PromptInputParms, PromptPasswordParms,
     },
-    client::{Connect, send_events},
+    client::{Connect, get_repo_ref_from_cache, save_event_in_global_cache, send_events},
     git::{Repo, RepoActions, remove_git_config_item, save_git_config_item},
+    repo_ref::try_and_get_repo_coordinates_when_remote_unknown,
 };
 
 pub async fn fresh_login_or_signup(
@@ -35,6 +36,7 @@ pub async fn fresh_login_or_signup(
     #[cfg(not(test))] client: Option<&Client>,
     signer_info: Option<SignerInfo>,
     save_local: bool,
+    skip_profile: bool,
 ) -> Result<(Arc<dyn NostrSigner>, UserRef, SignerInfoSource)> {
     let (signer, public_key, signer_info, source) = loop {
         if let Some(signer_info) = signer_info {
@@ -44,9 +46,7 @@ pub async fn fresh_login_or_signup(
                 &None,
                 &Some(SignerInfoSource::CommandLineArguments),
                 client,
-                true,
-                true,
-                false,
+                &LoadLoginOptions::default().silent(),
             )
             .await?;
             break (signer, user_ref.public_key, signer_info, source);
@@ -54,6 +54,7 @@ pub async fn fresh_login_or_signup(
         match Interactor::default().choice(
             PromptChoiceParms::default()
                 .with_prompt("login to nostr")
+                .with_flag("--nsec or --bunker-uri")
                 .with_default(0)
                 .with_choices(vec![
                     "secret key (nsec / ncryptsec)".to_string(),
@@ -79,7 +80,7 @@ pub async fn fresh_login_or_signup(
                     continue;
                 }
             },
-            2 => match signup(client).await {
+            2 => match signup(git_repo, client, skip_profile).await {
                 Ok(Some(res)) => break res,
                 Ok(None) => continue,
                 Err(e) => {
@@ -123,6 +124,7 @@ pub async fn get_fresh_nsec_signer() -> Result<
             .input(
                 PromptInputParms::default()
                     .with_prompt("nsec")
+                    .with_flag("--nsec")
                     .optional()
                     .dont_report(),
             )
@@ -132,6 +134,7 @@ pub async fn get_fresh_nsec_signer() -> Result<
                 .password(
                     PromptPasswordParms::default()
                         .with_prompt("password")
+                        .with_flag("--password")
                         .dont_report(),
                 )
                 .context("failed to get password input from interactor.password")?;
@@ -549,9 +552,7 @@ async fn save_to_git_config(
                             &None,
                             &Some(SignerInfoSource::GitGlobal),
                             None,
-                            true,
-                            true,
-                            false,
+                            &LoadLoginOptions::default().silent(),
                         )
                         .await
                         {
@@ -679,8 +680,10 @@ fn silently_save_to_git_config(
 }
 
 async fn signup(
+    git_repo: &Option<&Repo>,
     #[cfg(test)] client: Option<&MockConnect>,
     #[cfg(not(test))] client: Option<&Client>,
+    skip_profile: bool,
 ) -> Result<
     Option<(
         Arc<dyn NostrSigner>,
@@ -690,16 +693,22 @@ async fn signup(
     )>,
 > {
     eprintln!("create account");
-    loop {
-        let name = Interactor::default()
-            .input(
-                PromptInputParms::default()
-                    .with_prompt("user display name")
-                    .optional()
-                    .dont_report(),
-            )
-            .context("failed to get display name input from interactor")?;
-        if name.is_empty() {
+    let name = if skip_profile {
+        None
+    } else {
+        loop {
+            let name = Interactor::default()
+                .input(
+                    PromptInputParms::default()
+                        .with_prompt("user display name")
+                        .optional()
+                        .dont_report(),
+                )
+                .context("failed to get display name input from interactor")?;
+            if !name.is_empty() {
+                show_prompt_success("user display name", &name);
+                break Some(name);
+            }
             show_prompt_error("empty display name", "");
             match Interactor::default().choice(
                 PromptChoiceParms::default()
@@ -711,50 +720,109 @@ async fn signup(
                     .dont_report(),
             )? {
                 0 => continue,
-                _ => break Ok(None),
+                _ => return Ok(None),
             }
         }
-        let keys = nostr::Keys::generate();
-        let nsec = keys.secret_key().to_bech32()?;
-        show_prompt_success("user display name", &name);
-        let signer_info = SignerInfo::Nsec {
-            nsec,
-            password: None,
-            npub: Some(keys.public_key().to_bech32()?),
+    };
+    let keys = nostr::Keys::generate();
+    let signer_info = SignerInfo::Nsec {
+        nsec: keys.secret_key().to_bech32()?,
+        password: None,
+        npub: Some(keys.public_key().to_bech32()?),
+    };
+    let public_key = keys.public_key();
+    if let (Some(name), Some(client)) = (name, client) {
+        let git_repo_path = if let Some(git_repo) = git_repo {
+            Some(git_repo.get_path()?)
+        } else {
+            None
         };
-        let public_key = keys.public_key();
-        if let Some(client) = client {
-            let profile =
-                EventBuilder::metadata(&Metadata::new().name(name)).sign_with_keys(&keys)?;
-            let relay_list = EventBuilder::relay_list(
-                client
-                    .get_fallback_relays()
-                    .iter()
-                    .map(|s| (RelayUrl::parse(s).unwrap(), None)),
-            )
-            .sign_with_keys(&keys)?;
-            eprintln!("publishing user profile to relays");
-            send_events(
-                client,
-                None,
-                vec![profile, relay_list],
-                client.get_fallback_relays().clone(),
-                vec![],
-                true,
-                false,
+        let relays = ask_for_signup_relays(
+            default_signup_relays(git_repo, git_repo_path, client.get_fallback_relays()).await,
+        )?;
+        let events = vec![
+            EventBuilder::metadata(&Metadata::new().name(name)).sign_with_keys(&keys)?,
+            EventBuilder::relay_list(relays.iter().map(|r| (r.clone(), None)))
+                .sign_with_keys(&keys)?,
+        ];
+        eprintln!("publishing user profile and relay list to relays");
+        send_events(
+            client,
+            git_repo_path,
+            events.clone(),
+            relays.iter().map(ToString::to_string).collect(),
+            vec![],
+            true,
+            false,
+        )
+        .await?;
+        // so the next command finds them without fetching
+        for event in &events {
+            save_event_in_global_cache(git_repo_path, event).await?;
+        }
+    }
+    eprintln!(
+        "to login to other nostr clients eg. gitworkshop.dev with this account run `ngit export-keys` at any time to reveal your nostr account secret"
+    );
+    Ok(Some((
+        Arc::new(keys),
+        public_key,
+        signer_info,
+        // TODO factor in source
+        SignerInfoSource::GitGlobal,
+    )))
+}
+
+/// the relays of the repository in `git_repo`, if it has been announced, or
+/// else `fallback_relays`
+async fn default_signup_relays(
+    git_repo: &Option<&Repo>,
+    git_repo_path: Option<&Path>,
+    fallback_relays: &[String],
+) -> Vec<String> {
+    if let Some(git_repo) = git_repo {
+        if let Ok(repo_coordinates) =
+            try_and_get_repo_coordinates_when_remote_unknown(git_repo, None).await
+        {
+            if let Ok(repo_ref) = get_repo_ref_from_cache(git_repo_path, &repo_coordinates).await {
+                if !repo_ref.relays.is_empty() {
+                    return repo_ref.relays.iter().map(ToString::to_string).collect();
+                }
+            }
+        }
+    }
+    fallback_relays.to_vec()
+}
+
+fn ask_for_signup_relays(default: Vec<String>) -> Result<Vec<RelayUrl>> {
+    let mut default = default.join(" ");
+    'outer: loop {
+        let relays = Interactor::default()
+            .input(
+                PromptInputParms::default()
+                    .with_prompt("relays")
+                    .with_default(default),
             )
-            .await?;
+            .context("failed to get relays input from interactor")?
+            .split(' ')
+            .filter(|r| !r.is_empty())
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        let mut relay_urls = vec![];
+        for r in &relays {
+            if let Ok(r) = RelayUrl::parse(r) {
+                relay_urls.push(r);
+            } else {
+                show_prompt_error("invalid relay url", r);
+                default = relays.join(" ");
+                continue 'outer;
+            }
         }
-        eprintln!(
-            "to login to other nostr clients eg. gitworkshop.dev with this account run `ngit export-keys` at any time to reveal your nostr account secret"
-        );
-        break Ok(Some((
-            Arc::new(keys),
-            public_key,
-            signer_info,
-            // TODO factor in source
-            SignerInfoSource::GitGlobal,
-        )));
+        if relay_urls.is_empty() {
+            show_prompt_error("no relays", "");
+            continue;
+        }
+        break Ok(relay_urls);
     }
 }
 
@@ -13,7 +13,7 @@ use crate::git::{Repo, RepoActions};
 
 pub mod existing;
 mod key_encryption;
-use existing::load_existing_login;
+use existing::{LoadLoginOptions, load_existing_login};
 pub mod user;
 use user::UserRef;
 pub mod fresh;
@@ -32,15 +32,13 @@ pub async fn login_or_signup(
         password,
         &None,
         client,
-        false,
-        true,
-        fetch_profile_updates,
+        &LoadLoginOptions::default().with_fetch_profile_updates(fetch_profile_updates),
     )
     .await;
     if res.is_ok() {
         res
     } else {
-        fresh_login_or_signup(git_repo, client, None, false).await
+        fresh_login_or_signup(git_repo, client, None, false, false).await
     }
 }
 
@@ -1,15 +1,22 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Duration,
+};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Result, bail};
 use nostr::PublicKey;
-use nostr_sdk::{Alphabet, JsonUtil, Kind, SingleLetterTag, Timestamp, ToBech32};
+use nostr_sdk::{Alphabet, Kind, SingleLetterTag, Timestamp, ToBech32};
 use serde::{self, Deserialize, Serialize};
 
 #[cfg(not(test))]
 use crate::client::Client;
 #[cfg(test)]
 use crate::client::MockConnect;
-use crate::client::{Connect, get_event_from_global_cache};
+use crate::{
+    client::{Connect, get_event_from_global_cache, save_event_in_global_cache},
+    profile_cache::record_profiles_fetched,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct UserRef {
@@ -106,6 +113,73 @@ pub async fn get_user_details(
     }
 }
 
+/// how long to wait for profiles that are only needed to show names
+static NAMES_TIMEOUT_SECS: u64 = 3;
+
+/// names to show for `public_keys`. cached profiles are used first, the
+/// missing ones are requested from `relays` in a single batch and any still
+/// missing are shown as a short npub. the main fetch only gets the profiles of
+/// maintainers and the logged in user so other authors are resolved here
+pub async fn get_names_for_display(
+    public_keys: &HashSet<PublicKey>,
+    #[cfg(test)] client: Option<&MockConnect>,
+    #[cfg(not(test))] client: Option<&Client>,
+    git_repo_path: Option<&Path>,
+    relays: &[String],
+) -> Result<HashMap<PublicKey, String>> {
+    let mut names = HashMap::new();
+    let mut missing = HashSet::new();
+    for public_key in public_keys {
+        if let Ok(user_ref) = get_user_ref_from_cache(git_repo_path, public_key).await {
+            names.insert(*public_key, user_ref.metadata.name);
+        } else {
+            missing.insert(*public_key);
+        }
+    }
+    let fetched = match client {
+        Some(client) if !missing.is_empty() && !relays.is_empty() => tokio::time::timeout(
+            Duration::from_secs(NAMES_TIMEOUT_SECS),
+            client.get_events(relays.to_vec(), vec![
+                nostr::Filter::default()
+                    .kind(Kind::Metadata)
+                    .authors(missing.clone()),
+            ]),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok),
+        _ => None,
+    };
+    if let Some(events) = fetched {
+        for event in &events {
+            save_event_in_global_cache(git_repo_path, event).await?;
+        }
+        let _ = record_profiles_fetched(git_repo_path, &missing, Timestamp::now());
+        for public_key in &missing {
+            if events.iter().any(|e| e.pubkey.eq(public_key)) {
+                names.insert(
+                    *public_key,
+                    extract_user_metadata(public_key, &events)?.name,
+                );
+            }
+        }
+    }
+    for public_key in public_keys {
+        names
+            .entry(*public_key)
+            .or_insert_with(|| short_npub(public_key));
+    }
+    Ok(names)
+}
+
+/// the start of the npub, for when there is no profile to take a name from
+pub fn short_npub(public_key: &PublicKey) -> String {
+    match public_key.to_bech32() {
+        Ok(npub) => format!("{}…", &npub[..12]),
+        Err(_) => public_key.to_string(),
+    }
+}
+
 pub async fn get_user_ref_from_cache(
     git_repo_path: Option<&Path>,
     public_key: &PublicKey,
@@ -131,6 +205,32 @@ pub async fn get_user_ref_from_cache(
     })
 }
 
+/// fields of kind 0 `content`, or none if it isn't a json object
+fn parse_metadata_content(content: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    match serde_json::from_str(content) {
+        Ok(serde_json::Value::Object(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+/// the first of `keys` with a non-empty string value. clients publish
+/// numbers, nulls and other types in place of strings so these are ignored
+fn metadata_string(
+    fields: &serde_json::Map<String, serde_json::Value>,
+    keys: &[&str],
+) -> Option<String> {
+    keys.iter().find_map(|key| {
+        fields
+            .get(*key)?
+            .as_str()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// lenient as kind 0 events from other clients are often malformed. missing
+/// or unreadable fields fall back to the npub as the name
 pub fn extract_user_metadata(
     public_key: &nostr::PublicKey,
     events: &[nostr::Event],
@@ -140,39 +240,25 @@ pub fn extract_user_metadata(
         .filter(|e| e.kind.eq(&nostr::Kind::Metadata) && e.pubkey.eq(public_key))
         .max_by_key(|e| e.created_at);
 
-    let metadata: Option<nostr::Metadata> = if let Some(event) = event {
-        Some(
-            nostr::Metadata::from_json(event.content.clone())
-                .context("metadata cannot be found in kind 0 event content")?,
-        )
+    let fields = if let Some(event) = event {
+        let fields = parse_metadata_content(&event.content);
+        if fields.is_none() {
+            eprintln!(
+                "WARNING: ignoring unreadable profile metadata of {}",
+                public_key.to_bech32()?
+            );
+        }
+        fields.unwrap_or_default()
     } else {
-        None
+        serde_json::Map::new()
     };
 
     Ok(UserMetadata {
-        name: if let Some(metadata) = metadata.clone() {
-            if let Some(n) = metadata.name {
-                n
-            } else if let Some(n) = metadata.custom.get("displayName") {
-                // strip quote marks that custom.get() adds
-                let binding = n.to_string();
-                let mut chars = binding.chars();
-                chars.next();
-                chars.next_back();
-                chars.as_str().to_string()
-            } else if let Some(n) = metadata.display_name {
-                n
-            } else {
-                public_key.to_bech32()?
-            }
-        } else {
-            public_key.to_bech32()?
-        },
-        nip05: if let Some(metadata) = metadata {
-            metadata.nip05
-        } else {
-            None
+        name: match metadata_string(&fields, &["name", "displayName", "display_name"]) {
+            Some(name) => name,
+            None => public_key.to_bech32()?,
         },
+        nip05: metadata_string(&fields, &["nip05"]),
         created_at: if let Some(event) = event {
             event.created_at
         } else {
@@ -215,3 +301,82 @@ pub fn extract_user_relays(public_key: &nostr::PublicKey, events: &[nostr::Event
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    fn metadata_event(keys: &Keys, content: &str) -> nostr::Event {
+        EventBuilder::new(Kind::Metadata, content)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    mod extract_user_metadata {
+        use super::*;
+
+        fn extract(content: &str) -> Result<(UserMetadata, String)> {
+            let keys = Keys::generate();
+            Ok((
+                extract_user_metadata(&keys.public_key(), &[metadata_event(&keys, content)])?,
+                keys.public_key().to_bech32()?,
+            ))
+        }
+
+        #[test]
+        fn wrongly_typed_fields_ignored() -> Result<()> {
+            let (metadata, _) = extract(
+                r#"{"name":"fred","created_at":1721404213,"lud16":123,"about":null,"nip05":42}"#,
+            )?;
+            assert_eq!(metadata.name, "fred");
+            assert_eq!(metadata.nip05, None);
+            Ok(())
+        }
+
+        #[test]
+        fn null_name_falls_back_to_display_name() -> Result<()> {
+            let (metadata, _) = extract(
+                r#"{"name":null,"display_name":"Fred Bloggs","nip05":"fred@example.com"}"#,
+            )?;
+            assert_eq!(metadata.name, "Fred Bloggs");
+            assert_eq!(metadata.nip05, Some("fred@example.com".to_string()));
+            Ok(())
+        }
+
+        #[test]
+        fn camel_case_display_name_used() -> Result<()> {
+            let (metadata, _) = extract(r#"{"displayName":"fred"}"#)?;
+            assert_eq!(metadata.name, "fred");
+            Ok(())
+        }
+
+        #[test]
+        fn emoji_only_name_kept() -> Result<()> {
+            let (metadata, _) = extract(r#"{"name":"🦀🦀","website":["https://a.b"]}"#)?;
+            assert_eq!(metadata.name, "🦀🦀");
+            Ok(())
+        }
+
+        #[test]
+        fn numeric_or_blank_name_falls_back_to_npub() -> Result<()> {
+            for content in [r#"{"name":12345}"#, r#"{"name":"  "}"#, "{}"] {
+                let (metadata, npub) = extract(content)?;
+                assert_eq!(metadata.name, npub);
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn unparsable_content_falls_back_to_npub() -> Result<()> {
+            for content in ["not json", "", r#"["fred"]"#, r#"{"name":"fred""#] {
+                let (metadata, npub) = extract(content)?;
+                assert_eq!(metadata.name, npub);
+                assert_eq!(metadata.nip05, None);
+                assert_ne!(metadata.created_at, Timestamp::from(0));
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,10 +1,35 @@
+pub mod activity_log;
+pub mod attachments;
+pub mod background_fetch;
+pub mod cache_dir;
+pub mod ci;
 pub mod cli_interactor;
 pub mod client;
+pub mod config;
+pub mod error;
+pub mod event_sources;
 pub mod git;
 pub mod git_events;
+pub mod hooks;
+pub mod inbox;
+pub mod labels;
+pub mod lists;
 pub mod login;
+pub mod output;
+pub mod owners;
+pub mod profile_cache;
+pub mod proposal_view;
+pub mod proxy;
+pub mod publish_status;
+pub mod read_state;
+pub mod relay_hints;
+pub mod relay_info;
+pub mod relay_stats;
+pub mod repo_fetched_at;
 pub mod repo_ref;
 pub mod repo_state;
+pub mod timeout;
+pub mod web_viewer;
 
 use anyhow::{Result, anyhow};
 use directories::ProjectDirs;
@@ -0,0 +1,285 @@
+use std::{
+    ffi::OsString,
+    io::{ErrorKind, IsTerminal, Write},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result};
+use console::{Style, StyledObject};
+use indicatif::{MultiProgress, ProgressDrawTarget};
+
+use crate::git::{Repo, RepoActions};
+
+/// when to style output with colors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorChoice {
+    /// when writing to a terminal. setting NO_COLOR or CLICOLOR_FORCE
+    /// overrides this
+    #[default]
+    Auto,
+    Always,
+    /// also hides progress spinners as they are redrawn with escape codes
+    Never,
+}
+
+static PROGRESS_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// enable or disable styling of stdout and stderr, each depending on whether
+/// it is a terminal when `choice` is auto
+pub fn init_color(choice: ColorChoice) {
+    let no_color = std::env::var_os("NO_COLOR");
+    let clicolor_force = std::env::var_os("CLICOLOR_FORCE");
+    console::set_colors_enabled(use_color(
+        choice,
+        std::io::stdout().is_terminal(),
+        no_color.as_ref(),
+        clicolor_force.as_ref(),
+    ));
+    console::set_colors_enabled_stderr(use_color(
+        choice,
+        std::io::stderr().is_terminal(),
+        no_color.as_ref(),
+        clicolor_force.as_ref(),
+    ));
+    PROGRESS_HIDDEN.store(choice == ColorChoice::Never, Ordering::Relaxed);
+}
+
+/// an explicit --color always or never wins over the environment. NO_COLOR
+/// wins over CLICOLOR_FORCE. see no-color.org and bixense.com/clicolors
+fn use_color(
+    choice: ColorChoice,
+    is_terminal: bool,
+    no_color: Option<&OsString>,
+    clicolor_force: Option<&OsString>,
+) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if no_color.is_some_and(|v| !v.is_empty()) {
+                false
+            } else if clicolor_force.is_some_and(|v| !v.is_empty() && v != "0") {
+                true
+            } else {
+                is_terminal
+            }
+        }
+    }
+}
+
+/// progress reporter for relay interactions. indicatif already hides it when
+/// stderr isn't a terminal
+pub fn multi_progress() -> MultiProgress {
+    if PROGRESS_HIDDEN.load(Ordering::Relaxed) {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+/// print a line meant for people. when stdout is reserved for machine output,
+/// eg. with --porcelain, it goes to stderr instead
+pub fn print_human(machine_output: bool, line: &str) {
+    if machine_output {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+// each style is for stdout. use `.for_stderr()` on the result when writing to
+// stderr so it follows the stderr color setting
+
+/// proposal and repository titles
+pub fn title<D>(text: D) -> StyledObject<D> {
+    Style::new().bold().apply_to(text)
+}
+
+/// secondary details eg. commit ids, urls and comment counts
+pub fn dim<D>(text: D) -> StyledObject<D> {
+    Style::new().color256(247).apply_to(text)
+}
+
+/// eg. "2 ahead"
+pub fn ahead(count: usize) -> StyledObject<String> {
+    Style::new().green().apply_to(format!("{count} ahead"))
+}
+
+/// eg. "3 behind", only highlighted when not 0
+pub fn behind(count: usize) -> StyledObject<String> {
+    let style = if count == 0 {
+        Style::new()
+    } else {
+        Style::new().red()
+    };
+    style.apply_to(format!("{count} behind"))
+}
+
+/// eg. "(2 ahead 3 behind 'main')"
+pub fn ahead_behind(ahead_count: usize, behind_count: usize, branch_name: &str) -> String {
+    format!(
+        "({} {} '{branch_name}')",
+        ahead(ahead_count),
+        behind(behind_count)
+    )
+}
+
+/// shown instead of `ahead_behind` when a branch's changes are already in
+/// `branch_name`, eg. after a squash merge
+pub fn content_merged(branch_name: &str) -> String {
+    format!("(content merged into '{branch_name}')")
+}
+
+/// a diffstat such as "2 files +2 -1" with insertions green and deletions
+/// red
+pub fn diffstat(stat: &str) -> String {
+    stat.split(' ')
+        .map(|part| {
+            if part.starts_with('+') {
+                Style::new().green().apply_to(part).to_string()
+            } else if part.starts_with('-') {
+                Style::new().red().apply_to(part).to_string()
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// highlight a patch in `git format-patch` format. added and removed lines
+/// are only highlighted within hunks so the email headers, diffstat and
+/// signature are left alone
+pub fn diff(patch: &str) -> String {
+    let mut in_hunk = false;
+    patch
+        .lines()
+        .map(|line| {
+            if line.starts_with("diff --git ") {
+                in_hunk = false;
+                Style::new().bold().apply_to(line).to_string()
+            } else if line == "-- " {
+                // signature
+                in_hunk = false;
+                line.to_string()
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+                Style::new().cyan().apply_to(line).to_string()
+            } else if !in_hunk {
+                if line.starts_with("--- ") || line.starts_with("+++ ") {
+                    Style::new().bold().apply_to(line).to_string()
+                } else {
+                    line.to_string()
+                }
+            } else if line.starts_with('+') {
+                Style::new().green().apply_to(line).to_string()
+            } else if line.starts_with('-') {
+                Style::new().red().apply_to(line).to_string()
+            } else {
+                if !line.starts_with(' ') && !line.starts_with('\\') {
+                    in_hunk = false;
+                }
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// the pager git would use: `core.pager` then `$PAGER`. none when stdout
+/// isn't a terminal or the pager is `cat`
+pub fn pager(git_repo: &Repo) -> Result<Option<String>> {
+    if !std::io::stdout().is_terminal() {
+        return Ok(None);
+    }
+    Ok(git_repo
+        .get_git_config_item("core.pager", None)?
+        .or_else(|| std::env::var("PAGER").ok())
+        .filter(|pager| !pager.is_empty() && pager != "cat"))
+}
+
+/// stream what `write` produces through `pager`, or straight to stdout
+/// without one. a reader that stops early, such as a quit pager or `| head`,
+/// isn't an error
+pub fn write_with_pager(
+    pager: Option<&str>,
+    write: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+) -> Result<()> {
+    let Some(pager) = pager else {
+        let mut stdout = std::io::stdout().lock();
+        return match write(&mut stdout).and_then(|()| stdout.flush()) {
+            Err(error) if error.kind() == ErrorKind::BrokenPipe => Ok(()),
+            result => result.context("failed to write to stdout"),
+        };
+    };
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = command
+        .spawn()
+        .context(format!("failed to start pager '{pager}'"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // the pager may quit before reading everything
+        let _ = write(&mut stdin);
+    }
+    child
+        .wait()
+        .context(format!("failed to wait for pager '{pager}'"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod use_color {
+        use super::*;
+
+        fn os(s: &str) -> OsString {
+            OsString::from(s)
+        }
+
+        #[test]
+        fn auto_follows_terminal() {
+            assert!(use_color(ColorChoice::Auto, true, None, None));
+            assert!(!use_color(ColorChoice::Auto, false, None, None));
+        }
+
+        #[test]
+        fn no_color_disables_auto_even_when_forced() {
+            assert!(!use_color(
+                ColorChoice::Auto,
+                true,
+                Some(&os("1")),
+                Some(&os("1"))
+            ));
+        }
+
+        #[test]
+        fn empty_no_color_is_ignored() {
+            assert!(use_color(ColorChoice::Auto, true, Some(&os("")), None));
+        }
+
+        #[test]
+        fn clicolor_force_enables_auto_when_not_a_terminal() {
+            assert!(use_color(ColorChoice::Auto, false, None, Some(&os("1"))));
+            assert!(!use_color(ColorChoice::Auto, false, None, Some(&os("0"))));
+        }
+
+        #[test]
+        fn explicit_choice_ignores_environment() {
+            assert!(use_color(ColorChoice::Always, false, Some(&os("1")), None));
+            assert!(!use_color(ColorChoice::Never, true, None, Some(&os("1"))));
+        }
+    }
+
+    #[test]
+    fn diff_text_unchanged_apart_from_styling() {
+        let patch = "From 1a2b Mon Sep 17 00:00:00 2001\nSubject: [PATCH] add t3.md\n\n---\n t3.md | 2 +-\n\ndiff --git a/t3.md b/t3.md\n--- a/t3.md\n+++ b/t3.md\n@@ -1 +1 @@\n-old\n+new\n-- \n2.40.0";
+        assert_eq!(console::strip_ansi_codes(&diff(patch)), patch);
+    }
+}
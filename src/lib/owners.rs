@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use nostr::PublicKey;
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+
+use crate::{
+    git::{Repo, RepoActions, get_git_config_item, sha1_to_oid},
+    repo_state::matches_gitignore_pattern,
+};
+
+/// name of the owners file at the top of the working tree when
+/// `nostr.owners-file` isn't set
+pub static DEFAULT_OWNERS_FILE: &str = ".nostr-owners";
+
+/// a line of the owners file: a gitignore-style path pattern and the npubs
+/// that own matching paths
+#[derive(Debug, PartialEq)]
+pub struct OwnersRule {
+    pub pattern: String,
+    pub owners: Vec<PublicKey>,
+}
+
+/// `nostr.owners-file`, relative to the top of the working tree, or
+/// `.nostr-owners`
+pub fn get_owners_file_path(git_repo: &Repo) -> Result<PathBuf> {
+    Ok(git_repo.get_workdir()?.join(
+        get_git_config_item(&Some(git_repo), "nostr.owners-file")?
+            .unwrap_or_else(|| DEFAULT_OWNERS_FILE.to_string()),
+    ))
+}
+
+/// rules in the owners file, if there is one, and warnings for npubs that
+/// couldn't be parsed
+pub fn read_owners_file(git_repo: &Repo) -> Result<(Vec<OwnersRule>, Vec<String>)> {
+    let path = get_owners_file_path(git_repo)?;
+    if !path.exists() {
+        return Ok((vec![], vec![]));
+    }
+    Ok(parse_owners(
+        &std::fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?,
+    ))
+}
+
+/// one rule per line as `<pattern> <npub>...`. blank lines and lines starting
+/// with `#` are skipped
+pub fn parse_owners(content: &str) -> (Vec<OwnersRule>, Vec<String>) {
+    let mut rules = vec![];
+    let mut warnings = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let mut parts = line.split_whitespace();
+        let Some(pattern) = parts.next().filter(|p| !p.starts_with('#')) else {
+            continue;
+        };
+        let mut owners = vec![];
+        for npub in parts {
+            match PublicKey::parse(npub) {
+                Ok(public_key) => owners.push(public_key),
+                Err(_) => warnings.push(format!(
+                    "WARNING: owners file line {} '{npub}' isn't a valid npub so was ignored",
+                    i + 1
+                )),
+            }
+        }
+        rules.push(OwnersRule {
+            pattern: pattern.to_string(),
+            owners,
+        });
+    }
+    (rules, warnings)
+}
+
+/// owners of `paths` with the patterns that matched. as in CODEOWNERS, the
+/// last rule matching a path decides its owners, so a later rule with no
+/// npubs leaves a path unowned
+pub fn match_owners<'a>(
+    rules: &'a [OwnersRule],
+    paths: &BTreeSet<String>,
+) -> BTreeMap<PublicKey, BTreeSet<&'a str>> {
+    let mut owners: BTreeMap<PublicKey, BTreeSet<&str>> = BTreeMap::new();
+    for path in paths {
+        if let Some(rule) = rules
+            .iter()
+            .rev()
+            .find(|rule| matches_gitignore_pattern(&rule.pattern, path))
+        {
+            for public_key in &rule.owners {
+                owners.entry(*public_key).or_default().insert(&rule.pattern);
+            }
+        }
+    }
+    owners
+}
+
+/// paths added, changed, renamed or deleted by `commits`
+pub fn paths_touched(git_repo: &Repo, commits: &[Sha1Hash]) -> Result<BTreeSet<String>> {
+    let mut paths = BTreeSet::new();
+    for commit in commits {
+        let commit = git_repo.git_repo.find_commit(sha1_to_oid(commit)?)?;
+        let parent_tree = commit.parents().next().map(|p| p.tree()).transpose()?;
+        let diff = git_repo.git_repo.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit.tree()?),
+            None,
+        )?;
+        for delta in diff.deltas() {
+            for file in [delta.old_file(), delta.new_file()] {
+                if let Some(path) = file.path() {
+                    paths.insert(path.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn rule(pattern: &str, owners: &[PublicKey]) -> OwnersRule {
+        OwnersRule {
+            pattern: pattern.to_string(),
+            owners: owners.to_vec(),
+        }
+    }
+
+    fn paths(paths: &[&str]) -> BTreeSet<String> {
+        paths.iter().map(ToString::to_string).collect()
+    }
+
+    mod parse_owners {
+        use nostr::ToBech32;
+
+        use super::*;
+
+        #[test]
+        fn skips_comments_and_warns_about_invalid_npubs() {
+            let alice = TEST_KEY_1_KEYS.public_key();
+            let (rules, warnings) = parse_owners(&format!(
+                "# owners\n\nsrc/parser/** {} npub1invalid\n",
+                alice.to_bech32().unwrap()
+            ));
+            assert_eq!(rules, vec![rule("src/parser/**", &[alice])]);
+            assert_eq!(
+                warnings,
+                vec![
+                    "WARNING: owners file line 3 'npub1invalid' isn't a valid npub so was ignored"
+                        .to_string()
+                ]
+            );
+        }
+    }
+
+    mod match_owners {
+        use super::*;
+
+        #[test]
+        fn double_star_matches_files_at_any_depth_within_directory() {
+            let alice = TEST_KEY_1_KEYS.public_key();
+            let rules = vec![rule("src/parser/**", &[alice])];
+            assert_eq!(
+                match_owners(&rules, &paths(&["src/parser/grammar/expr.rs"])),
+                BTreeMap::from([(alice, BTreeSet::from(["src/parser/**"]))])
+            );
+            assert!(match_owners(&rules, &paths(&["src/lexer.rs"])).is_empty());
+        }
+
+        #[test]
+        fn pattern_without_slash_matches_at_any_depth() {
+            let alice = TEST_KEY_1_KEYS.public_key();
+            let rules = vec![rule("*.md", &[alice])];
+            assert_eq!(
+                match_owners(&rules, &paths(&["docs/guide/intro.md"])).len(),
+                1
+            );
+            assert!(match_owners(&rules, &paths(&["docs/guide/intro.rs"])).is_empty());
+        }
+
+        #[test]
+        fn leading_slash_anchors_to_repository_root() {
+            let alice = TEST_KEY_1_KEYS.public_key();
+            let rules = vec![rule("/docs/", &[alice])];
+            assert_eq!(match_owners(&rules, &paths(&["docs/intro.md"])).len(), 1);
+            assert!(match_owners(&rules, &paths(&["src/docs/intro.md"])).is_empty());
+        }
+
+        #[test]
+        fn last_matching_rule_decides_owners() {
+            let (alice, bob) = (TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key());
+            let rules = vec![rule("*", &[alice]), rule("docs/**", &[bob])];
+            assert_eq!(
+                match_owners(&rules, &paths(&["docs/intro.md", "src/main.rs"])),
+                BTreeMap::from([
+                    (alice, BTreeSet::from(["*"])),
+                    (bob, BTreeSet::from(["docs/**"])),
+                ])
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::{PublicKey, Timestamp};
+
+use crate::client::get_global_cache_path;
+
+/// how long profiles in the global cache are used before they are fetched
+/// again
+pub static DEFAULT_PROFILE_CACHE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// unix timestamp each profile was last fetched keyed by hex public key
+type ProfilesFetchedAt = HashMap<String, u64>;
+
+/// kept alongside the global cache so every repository shares it
+fn get_profiles_fetched_at_path(git_repo_path: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_global_cache_path(git_repo_path)?.with_file_name("profiles-fetched-at.json"))
+}
+
+fn read_profiles_fetched_at(git_repo_path: Option<&Path>) -> ProfilesFetchedAt {
+    get_profiles_fetched_at_path(git_repo_path)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// record that profiles were fetched at `now`. the file is replaced by a
+/// rename so other ngit processes never read it half written. if two
+/// processes record at once one set of entries may be lost, which only means
+/// those profiles are fetched again
+pub fn record_profiles_fetched(
+    git_repo_path: Option<&Path>,
+    public_keys: &HashSet<PublicKey>,
+    now: Timestamp,
+) -> Result<()> {
+    if public_keys.is_empty() {
+        return Ok(());
+    }
+    let mut fetched_at = read_profiles_fetched_at(git_repo_path);
+    for public_key in public_keys {
+        fetched_at.insert(public_key.to_hex(), now.as_u64());
+    }
+    let path = get_profiles_fetched_at_path(git_repo_path)?;
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&fetched_at)?)
+        .context("failed to save when profiles were fetched")?;
+    std::fs::rename(tmp_path, path).context("failed to save when profiles were fetched")
+}
+
+/// `public_keys` whose cached profile was fetched more than `ttl_secs` ago,
+/// or never recorded as fetched
+pub fn get_stale_profiles(
+    git_repo_path: Option<&Path>,
+    public_keys: &HashSet<PublicKey>,
+    ttl_secs: u64,
+    now: Timestamp,
+) -> HashSet<PublicKey> {
+    stale_profiles(
+        &read_profiles_fetched_at(git_repo_path),
+        public_keys,
+        ttl_secs,
+        now,
+    )
+}
+
+fn stale_profiles(
+    fetched_at: &ProfilesFetchedAt,
+    public_keys: &HashSet<PublicKey>,
+    ttl_secs: u64,
+    now: Timestamp,
+) -> HashSet<PublicKey> {
+    public_keys
+        .iter()
+        .filter(|public_key| {
+            fetched_at
+                .get(&public_key.to_hex())
+                .map_or(true, |fetched_at| {
+                    now.as_u64().saturating_sub(*fetched_at) >= ttl_secs
+                })
+        })
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    mod stale_profiles {
+        use super::*;
+
+        fn fetched_at(public_key: &PublicKey, at: u64) -> ProfilesFetchedAt {
+            HashMap::from([(public_key.to_hex(), at)])
+        }
+
+        #[test]
+        fn fetched_within_ttl_is_fresh() {
+            let public_key = TEST_KEY_1_KEYS.public_key();
+            assert!(
+                stale_profiles(
+                    &fetched_at(&public_key, 1_000),
+                    &HashSet::from([public_key]),
+                    600,
+                    Timestamp::from(1_500),
+                )
+                .is_empty()
+            );
+        }
+
+        #[test]
+        fn fetched_before_ttl_is_stale() {
+            let public_key = TEST_KEY_1_KEYS.public_key();
+            assert_eq!(
+                stale_profiles(
+                    &fetched_at(&public_key, 1_000),
+                    &HashSet::from([public_key]),
+                    600,
+                    Timestamp::from(1_600),
+                ),
+                HashSet::from([public_key]),
+            );
+        }
+
+        #[test]
+        fn never_fetched_is_stale() {
+            let public_key = TEST_KEY_2_KEYS.public_key();
+            assert_eq!(
+                stale_profiles(
+                    &fetched_at(&TEST_KEY_1_KEYS.public_key(), 1_000),
+                    &HashSet::from([public_key]),
+                    600,
+                    Timestamp::from(1_000),
+                ),
+                HashSet::from([public_key]),
+            );
+        }
+    }
+}
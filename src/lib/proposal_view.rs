@@ -0,0 +1,618 @@
+use std::collections::HashSet;
+
+use nostr::{Event, EventId, Kind, PublicKey, Timestamp, ToBech32};
+use serde::Serialize;
+
+use crate::{
+    activity_log::format_age,
+    attachments::{Attachment, event_attachments},
+    ci::{CiStatus, format_ci_status, proposal_ci_status},
+    git_events::{
+        Diffstat, PROPOSAL_EDIT_KIND, apply_proposal_edits, comment_kinds,
+        commit_msg_from_patch_oneliner, diff_from_patch, event_has_expired, event_is_cover_letter,
+        event_is_revision_root, event_to_cover_letter, get_commit_id_from_patch,
+        get_most_recent_patch_with_ancestors, patches_diffstat, status_kinds, tag_value,
+    },
+    labels::{DEFAULT_LABEL_NAMESPACE, format_labels, proposal_labels},
+    login::user::extract_user_metadata,
+};
+
+/// a patch in the most recent revision of a proposal
+#[derive(Debug, Serialize)]
+pub struct PatchView {
+    pub id: String,
+    /// none for patches published without one
+    pub commit_id: Option<String>,
+    pub subject: String,
+    pub diffstat: Diffstat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// a comment on a proposal with the replies to it
+#[derive(Debug, Serialize)]
+pub struct CommentView {
+    pub id: String,
+    pub author: String,
+    pub author_npub: String,
+    pub created_at: u64,
+    pub content: String,
+    pub replies: Vec<CommentView>,
+}
+
+/// everything `ngit proposal show` prints about a proposal. parts whose
+/// events haven't been fetched are left empty
+#[derive(Debug, Serialize)]
+pub struct ProposalView {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub author_npub: String,
+    pub created_at: u64,
+    /// "open", "draft", "closed" or "applied"
+    pub status: &'static str,
+    pub target_branch: Option<String>,
+    /// 1 until the proposal is revised
+    pub revisions: usize,
+    /// of the most recent revision
+    pub diffstat: Diffstat,
+    /// the most recent revision, oldest first
+    pub patches: Vec<PatchView>,
+    pub labels: Vec<String>,
+    pub ci: Option<CiStatus>,
+    pub attachments: Vec<Attachment>,
+    /// oldest first
+    pub comments: Vec<CommentView>,
+}
+
+/// the latest status of `proposal` in `statuses`, open if there isn't one
+pub fn proposal_status(proposal: &Event, statuses: &[Event]) -> Kind {
+    statuses
+        .iter()
+        .filter(|e| status_kinds().contains(&e.kind) && references(e, &proposal.id))
+        .max_by_key(|e| e.created_at)
+        .map_or(Kind::GitStatusOpen, |e| e.kind)
+}
+
+/// eg. "open" for [`Kind::GitStatusOpen`]
+pub fn status_name(status: Kind) -> &'static str {
+    match status {
+        Kind::GitStatusApplied => "applied",
+        Kind::GitStatusClosed => "closed",
+        Kind::GitStatusDraft => "draft",
+        _ => "open",
+    }
+}
+
+/// attachments of the proposal and its revision roots in `events`, each
+/// listed once
+pub fn proposal_attachments(proposal: &Event, events: &[Event]) -> Vec<Attachment> {
+    let mut attachments: Vec<Attachment> = vec![];
+    let revisions = events.iter().filter(|e| event_is_revision_root(e));
+    for event in std::iter::once(proposal).chain(revisions) {
+        for attachment in event_attachments(event) {
+            if !attachments.iter().any(|a| a.sha256 == attachment.sha256) {
+                attachments.push(attachment);
+            }
+        }
+    }
+    attachments
+}
+
+fn references(event: &Event, id: &EventId) -> bool {
+    event.tags.iter().any(|t| match t.as_slice() {
+        [name, value, ..] => (name == "e" || name == "E") && value == &id.to_hex(),
+        _ => false,
+    })
+}
+
+/// the event a comment replies to: a NIP-10 reply marker or a NIP-22
+/// lowercase e tag that isn't the proposal
+fn replies_to(comment: &Event, proposal: &Event) -> Option<EventId> {
+    let e_tags = comment
+        .tags
+        .iter()
+        .filter_map(|t| match t.as_slice() {
+            [name, id, rest @ ..] if name == "e" => Some((id, rest.get(1))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    e_tags
+        .iter()
+        .find(|(_, marker)| marker.is_some_and(|m| m == "reply"))
+        .or_else(|| {
+            e_tags.iter().find(|(id, marker)| {
+                *id != &proposal.id.to_hex() && !marker.is_some_and(|m| m == "root")
+            })
+        })
+        .and_then(|(id, _)| EventId::from_hex(id).ok())
+}
+
+fn author_name(public_key: &PublicKey, events: &[Event]) -> String {
+    extract_user_metadata(public_key, events)
+        .map(|metadata| metadata.name)
+        .unwrap_or(public_key.to_string())
+}
+
+fn comment_thread(
+    parent: Option<EventId>,
+    comments: &[(&Event, Option<EventId>)],
+    events: &[Event],
+) -> Vec<CommentView> {
+    comments
+        .iter()
+        .filter(|(_, replies_to)| *replies_to == parent)
+        .map(|(comment, _)| CommentView {
+            id: comment.id.to_hex(),
+            author: author_name(&comment.pubkey, events),
+            author_npub: comment
+                .pubkey
+                .to_bech32()
+                .unwrap_or(comment.pubkey.to_string()),
+            created_at: comment.created_at.as_u64(),
+            content: comment.content.trim().to_string(),
+            replies: comment_thread(Some(comment.id), comments, events),
+        })
+        .collect()
+}
+
+/// builds a [`ProposalView`] from whatever the cache holds for a proposal
+pub struct ProposalViewBuilder<'a> {
+    proposal: &'a Event,
+    events: &'a [Event],
+    maintainers: Vec<PublicKey>,
+    label_namespace: String,
+    labellers: Option<Vec<PublicKey>>,
+    ci_bots: Vec<PublicKey>,
+    target_branch: Option<String>,
+    diffs: bool,
+}
+
+impl<'a> ProposalViewBuilder<'a> {
+    /// `events` holds the proposal's patches, revisions, statuses, edits,
+    /// labels and comments and the profiles of their authors
+    pub fn new(proposal: &'a Event, events: &'a [Event]) -> Self {
+        Self {
+            proposal,
+            events,
+            maintainers: vec![],
+            label_namespace: DEFAULT_LABEL_NAMESPACE.to_string(),
+            labellers: None,
+            ci_bots: vec![],
+            target_branch: None,
+            diffs: false,
+        }
+    }
+
+    /// whose edits apply, along with the proposal author's, and whose
+    /// revisions are counted
+    pub fn with_maintainers(mut self, maintainers: &[PublicKey]) -> Self {
+        self.maintainers = maintainers.to_vec();
+        self
+    }
+
+    /// labels from anyone when `labellers` is none
+    pub fn with_labels(mut self, namespace: &str, labellers: Option<&[PublicKey]>) -> Self {
+        self.label_namespace = namespace.to_string();
+        self.labellers = labellers.map(<[PublicKey]>::to_vec);
+        self
+    }
+
+    pub fn with_ci_bots(mut self, ci_bots: &[PublicKey]) -> Self {
+        self.ci_bots = ci_bots.to_vec();
+        self
+    }
+
+    pub fn with_target_branch(mut self, target_branch: Option<String>) -> Self {
+        self.target_branch = target_branch;
+        self
+    }
+
+    /// include the diff of each patch
+    pub fn with_diffs(mut self, diffs: bool) -> Self {
+        self.diffs = diffs;
+        self
+    }
+
+    pub fn build(self, now: Timestamp) -> ProposalView {
+        let proposal = self.proposal;
+        // relays may not have deleted expired events yet and the cache never does.
+        // `events` can overlap as they are usually read from more than one cache
+        let mut ids = HashSet::new();
+        let events = self
+            .events
+            .iter()
+            .filter(|e| !event_has_expired(e, now) && ids.insert(e.id))
+            .cloned()
+            .collect::<Vec<Event>>();
+
+        let (title, description) = if let Ok(cover_letter) = event_to_cover_letter(proposal) {
+            let edits = events
+                .iter()
+                .filter(|e| e.kind == PROPOSAL_EDIT_KIND)
+                .cloned()
+                .collect::<Vec<Event>>();
+            let cover_letter =
+                apply_proposal_edits(cover_letter, proposal, &edits, &self.maintainers);
+            (cover_letter.title, cover_letter.description)
+        } else {
+            (
+                tag_value(proposal, "description")
+                    .ok()
+                    .and_then(|d| d.lines().next().map(str::to_string))
+                    .unwrap_or(proposal.id.to_string()),
+                String::new(),
+            )
+        };
+
+        let permitted =
+            |e: &Event| e.pubkey == proposal.pubkey || self.maintainers.contains(&e.pubkey);
+        let revision_roots = events
+            .iter()
+            .filter(|e| event_is_revision_root(e) && permitted(e) && references(e, &proposal.id))
+            .collect::<Vec<&Event>>();
+        let patches = std::iter::once(proposal)
+            .chain(events.iter().filter(|e| {
+                e.id != proposal.id
+                    && std::iter::once(proposal)
+                        .chain(revision_roots.iter().copied())
+                        .any(|root| e.id == root.id || references(e, &root.id))
+            }))
+            .filter(|e| e.kind == Kind::GitPatch && !event_is_cover_letter(e) && permitted(e))
+            .cloned()
+            .collect::<Vec<Event>>();
+        let mut most_recent_patches =
+            get_most_recent_patch_with_ancestors(patches).unwrap_or_default();
+        most_recent_patches.reverse();
+
+        let mut comments = events
+            .iter()
+            .filter(|e| comment_kinds().contains(&e.kind) && references(e, &proposal.id))
+            .collect::<Vec<&Event>>();
+        comments.sort_by_key(|e| e.created_at);
+        let comments = comments
+            .iter()
+            .map(|comment| {
+                (
+                    *comment,
+                    replies_to(comment, proposal).filter(|id| comments.iter().any(|c| c.id == *id)),
+                )
+            })
+            .collect::<Vec<(&Event, Option<EventId>)>>();
+
+        ProposalView {
+            id: proposal.id.to_hex(),
+            title,
+            description,
+            author: author_name(&proposal.pubkey, &events),
+            author_npub: proposal
+                .pubkey
+                .to_bech32()
+                .unwrap_or(proposal.pubkey.to_string()),
+            created_at: proposal.created_at.as_u64(),
+            status: status_name(proposal_status(proposal, &events)),
+            target_branch: self.target_branch,
+            revisions: 1 + revision_roots.len(),
+            diffstat: patches_diffstat(&most_recent_patches),
+            patches: most_recent_patches
+                .iter()
+                .map(|patch| PatchView {
+                    id: patch.id.to_hex(),
+                    commit_id: get_commit_id_from_patch(patch).ok(),
+                    subject: commit_msg_from_patch_oneliner(patch).unwrap_or_default(),
+                    diffstat: patches_diffstat(std::slice::from_ref(patch)),
+                    diff: self.diffs.then(|| diff_from_patch(patch).to_string()),
+                })
+                .collect(),
+            labels: proposal_labels(
+                proposal,
+                &events,
+                &self.label_namespace,
+                self.labellers.as_deref(),
+            ),
+            ci: proposal_ci_status(proposal, &events, &self.ci_bots),
+            attachments: proposal_attachments(
+                proposal,
+                &revision_roots.into_iter().cloned().collect::<Vec<Event>>(),
+            ),
+            comments: comment_thread(None, &comments, &events),
+        }
+    }
+}
+
+fn render_comments(
+    comments: &[CommentView],
+    depth: usize,
+    now: Timestamp,
+    lines: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth + 1);
+    for comment in comments {
+        lines.push(format!(
+            "{indent}{} {}",
+            comment.author,
+            format_age(Timestamp::from(comment.created_at), now)
+        ));
+        for line in comment.content.lines() {
+            lines.push(format!("{indent}  {line}"));
+        }
+        render_comments(&comment.replies, depth + 1, now, lines);
+    }
+}
+
+/// the detail view printed by `ngit proposal show`
+pub fn render_proposal_view(view: &ProposalView, now: Timestamp) -> String {
+    let mut lines = vec![
+        view.title.clone(),
+        format!(
+            "{} by {} {}",
+            view.status,
+            view.author,
+            format_age(Timestamp::from(view.created_at), now)
+        ),
+        format!("id: {}", view.id),
+    ];
+    if let Some(target_branch) = &view.target_branch {
+        lines.push(format!("target: {target_branch}"));
+    }
+    lines.push(format!(
+        "{} revision{}, {} patch{}, {}",
+        view.revisions,
+        if view.revisions == 1 { "" } else { "s" },
+        view.patches.len(),
+        if view.patches.len() == 1 { "" } else { "es" },
+        view.diffstat,
+    ));
+    if !view.labels.is_empty() {
+        lines.push(format!("labels: {}", format_labels(&view.labels)));
+    }
+    if let Some(ci) = &view.ci {
+        lines.extend(format_ci_status(ci));
+    } else {
+        lines.push("ci: no results".to_string());
+    }
+    if !view.description.is_empty() {
+        lines.push(String::new());
+        lines.extend(view.description.lines().map(|line| format!("  {line}")));
+    }
+
+    lines.push(String::new());
+    if view.patches.is_empty() {
+        lines.push("patches: none in the cache".to_string());
+    } else {
+        lines.push("patches:".to_string());
+        for patch in &view.patches {
+            lines.push(format!(
+                "  {} {} ({})",
+                patch
+                    .commit_id
+                    .as_deref()
+                    .map_or(&patch.id[..7], |commit_id| &commit_id
+                        [..commit_id.len().min(7)]),
+                patch.subject,
+                patch.diffstat,
+            ));
+        }
+    }
+    if !view.attachments.is_empty() {
+        lines.push("attachments:".to_string());
+        for attachment in &view.attachments {
+            lines.push(format!(
+                "  {} sha256:{} {}",
+                attachment.name, attachment.sha256, attachment.url
+            ));
+        }
+    }
+    if view.comments.is_empty() {
+        lines.push("comments: none".to_string());
+    } else {
+        lines.push("comments:".to_string());
+        render_comments(&view.comments, 0, now, &mut lines);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS};
+
+    use super::*;
+
+    fn event_at(keys: &Keys, kind: Kind, content: &str, tags: Vec<Tag>, created_at: u64) -> Event {
+        EventBuilder::new(kind, content)
+            .tags(tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn proposal() -> Event {
+        event_at(
+            &TEST_KEY_1_KEYS,
+            Kind::GitPatch,
+            "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nSubject: [PATCH] add t.md\n\ndiff --git a/t.md b/t.md\nnew file mode 100644\n--- /dev/null\n+++ b/t.md\n@@ -0,0 +1 @@\n+hello\n-- \nlibgit2 1.8.1\n\n",
+            vec![
+                Tag::hashtag("root"),
+                Tag::custom(
+                    nostr::TagKind::Custom("commit".into()),
+                    vec!["431b84edc0d2fa118d63faa3c2db9c73d630a5ae"],
+                ),
+            ],
+            100,
+        )
+    }
+
+    fn comment(keys: &Keys, content: &str, tags: Vec<Tag>, created_at: u64) -> Event {
+        event_at(keys, Kind::Custom(1111), content, tags, created_at)
+    }
+
+    mod proposal_status {
+        use super::*;
+
+        #[test]
+        fn latest_status_used_and_open_without_one() {
+            let proposal = proposal();
+            assert_eq!(proposal_status(&proposal, &[]), Kind::GitStatusOpen);
+            assert_eq!(
+                proposal_status(
+                    &proposal,
+                    &[
+                        event_at(
+                            &TEST_KEY_1_KEYS,
+                            Kind::GitStatusApplied,
+                            "",
+                            vec![Tag::event(proposal.id)],
+                            300
+                        ),
+                        event_at(
+                            &TEST_KEY_1_KEYS,
+                            Kind::GitStatusClosed,
+                            "",
+                            vec![Tag::event(proposal.id)],
+                            200
+                        ),
+                    ]
+                ),
+                Kind::GitStatusApplied
+            );
+        }
+    }
+
+    mod proposal_view_builder {
+        use super::*;
+
+        #[test]
+        fn patches_labels_and_status_from_events() {
+            let proposal = proposal();
+            let events = vec![
+                proposal.clone(),
+                event_at(
+                    &TEST_KEY_1_KEYS,
+                    Kind::GitStatusClosed,
+                    "",
+                    vec![Tag::event(proposal.id)],
+                    200,
+                ),
+            ];
+            let view = ProposalViewBuilder::new(&proposal, &events)
+                .with_target_branch(Some("main".to_string()))
+                .build(Timestamp::from(300));
+            assert_eq!(view.title, "add t.md");
+            assert_eq!(view.status, "closed");
+            assert_eq!(view.revisions, 1);
+            assert_eq!(
+                view.patches
+                    .iter()
+                    .map(|p| (p.commit_id.as_deref(), p.subject.as_str()))
+                    .collect::<Vec<_>>(),
+                vec![(Some("431b84edc0d2fa118d63faa3c2db9c73d630a5ae"), "add t.md")]
+            );
+            assert_eq!(view.diffstat.to_string(), "1 file +1 -0");
+            assert!(view.patches[0].diff.is_none());
+        }
+
+        #[test]
+        fn only_the_root_shown_when_nothing_else_is_cached() {
+            let proposal = proposal();
+            let view = ProposalViewBuilder::new(&proposal, &[]).build(Timestamp::from(300));
+            assert_eq!(view.title, "add t.md");
+            assert_eq!(view.status, "open");
+            assert_eq!(view.patches.len(), 1);
+            assert!(view.comments.is_empty());
+            assert!(view.author.starts_with("npub"));
+        }
+
+        #[test]
+        fn comments_threaded_by_reply() {
+            let proposal = proposal();
+            let top = comment(
+                &TEST_KEY_2_KEYS,
+                "looks good",
+                vec![Tag::event(proposal.id)],
+                150,
+            );
+            let reply = comment(
+                &TEST_KEY_1_KEYS,
+                "thanks",
+                vec![
+                    Tag::custom(
+                        nostr::TagKind::Custom("E".into()),
+                        vec![proposal.id.to_hex()],
+                    ),
+                    Tag::event(top.id),
+                ],
+                160,
+            );
+            let events = vec![reply, top];
+            let view = ProposalViewBuilder::new(&proposal, &events).build(Timestamp::from(300));
+            assert_eq!(view.comments.len(), 1);
+            assert_eq!(view.comments[0].content, "looks good");
+            assert_eq!(
+                view.comments[0]
+                    .replies
+                    .iter()
+                    .map(|c| c.content.as_str())
+                    .collect::<Vec<&str>>(),
+                vec!["thanks"]
+            );
+        }
+
+        #[test]
+        fn patches_from_others_ignored() {
+            let proposal = proposal();
+            let interloper = event_at(
+                &TEST_KEY_2_KEYS,
+                Kind::GitPatch,
+                "From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001\nSubject: [PATCH] sneaky\n\n",
+                vec![Tag::event(proposal.id)],
+                200,
+            );
+            let events = vec![proposal.clone(), interloper];
+            let view = ProposalViewBuilder::new(&proposal, &events).build(Timestamp::from(300));
+            assert_eq!(
+                view.patches
+                    .iter()
+                    .map(|p| p.subject.as_str())
+                    .collect::<Vec<&str>>(),
+                vec!["add t.md"]
+            );
+        }
+    }
+
+    mod render_proposal_view {
+        use super::*;
+
+        #[test]
+        fn metadata_patches_and_comment_thread() {
+            let proposal = proposal();
+            let top = comment(
+                &TEST_KEY_2_KEYS,
+                "looks good",
+                vec![Tag::event(proposal.id)],
+                100,
+            );
+            let reply = comment(
+                &TEST_KEY_1_KEYS,
+                "thanks",
+                vec![Tag::event(proposal.id), Tag::event(top.id)],
+                100,
+            );
+            let events = vec![proposal.clone(), top, reply];
+            let mut view = ProposalViewBuilder::new(&proposal, &events)
+                .with_target_branch(Some("main".to_string()))
+                .build(Timestamp::from(100));
+            view.author = "fred".to_string();
+            view.comments[0].author = "carole".to_string();
+            view.comments[0].replies[0].author = "fred".to_string();
+            assert_eq!(
+                render_proposal_view(&view, Timestamp::from(100 + 2 * 86_400)),
+                format!(
+                    "add t.md\nopen by fred 2 days ago\nid: {}\ntarget: main\n1 revision, 1 patch, 1 file +1 -0\nci: no results\n\npatches:\n  431b84e add t.md (1 file +1 -0)\ncomments:\n  carole 2 days ago\n    looks good\n    fred 2 days ago\n      thanks",
+                    proposal.id
+                )
+            );
+        }
+    }
+}
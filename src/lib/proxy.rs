@@ -0,0 +1,218 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    config::{ConfigSource, ConfigValue},
+    git::{Repo, get_git_config_item},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProxyUse {
+    /// nostr-sdk only supports socks5 proxies
+    Relays,
+    /// libgit2 only supports http and https proxies
+    GitServers,
+}
+
+/// proxy to use for relay or git server connections. candidates in order of
+/// precedence: git config `nostr.proxy`, `ALL_PROXY` and `HTTPS_PROXY`. the
+/// first candidate supported for `proxy_use` is selected
+pub fn get_proxy(
+    git_repo: &Option<&Repo>,
+    proxy_use: ProxyUse,
+) -> Result<Option<ConfigValue<String>>> {
+    let mut candidates = vec![];
+    if let Some(proxy) = get_git_config_item(git_repo, "nostr.proxy")? {
+        candidates.push((proxy, ConfigSource::GitConfig("nostr.proxy".to_string())));
+    }
+    for var in ["ALL_PROXY", "all_proxy", "HTTPS_PROXY", "https_proxy"] {
+        if let Ok(proxy) = std::env::var(var) {
+            candidates.push((proxy, ConfigSource::Environment(var.to_string())));
+        }
+    }
+    Ok(select_proxy(candidates, proxy_use))
+}
+
+fn select_proxy(
+    candidates: Vec<(String, ConfigSource)>,
+    proxy_use: ProxyUse,
+) -> Option<ConfigValue<String>> {
+    candidates
+        .into_iter()
+        .map(|(proxy, source)| (proxy.trim().to_string(), source))
+        .find(|(proxy, _)| {
+            !proxy.is_empty()
+                && match proxy_use {
+                    ProxyUse::Relays => is_socks_proxy(proxy),
+                    ProxyUse::GitServers => {
+                        proxy.starts_with("http://") || proxy.starts_with("https://")
+                    }
+                }
+        })
+        .map(|(value, source)| ConfigValue { value, source })
+}
+
+/// a proxy without a scheme, eg. tor's `127.0.0.1:9050`, is assumed to be
+/// socks5
+fn is_socks_proxy(proxy: &str) -> bool {
+    match proxy.split_once("://") {
+        Some((scheme, _)) => ["socks5", "socks5h"].contains(&scheme),
+        None => true,
+    }
+}
+
+pub fn socks_proxy_addr(proxy: &str) -> Result<SocketAddr> {
+    if !is_socks_proxy(proxy) {
+        bail!("relay connections only support socks5 proxies, not {proxy}");
+    }
+    let host_port = proxy
+        .split_once("://")
+        .map_or(proxy, |(_, rest)| rest)
+        .trim_end_matches('/');
+    host_port
+        .to_socket_addrs()
+        .context(format!("failed to resolve proxy address {proxy}"))?
+        .next()
+        .context(format!("failed to resolve proxy address {proxy}"))
+}
+
+pub fn is_onion_url(url: &str) -> bool {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    host.split(':')
+        .next()
+        .unwrap_or_default()
+        .ends_with(".onion")
+}
+
+/// .onion addresses can only be reached via a proxy such as tor
+pub fn ensure_onion_url_has_proxy(
+    url: &str,
+    proxy: &Option<impl std::fmt::Display>,
+    proxy_use: ProxyUse,
+) -> Result<()> {
+    if proxy.is_none() && is_onion_url(url) {
+        bail!(
+            "cannot connect to {url} as .onion addresses require a proxy. set git config nostr.proxy or {} to a {} proxy such as tor",
+            match proxy_use {
+                ProxyUse::Relays => "ALL_PROXY",
+                ProxyUse::GitServers => "HTTPS_PROXY",
+            },
+            match proxy_use {
+                ProxyUse::Relays => "socks5",
+                ProxyUse::GitServers => "http",
+            },
+        );
+    }
+    Ok(())
+}
+
+pub fn git_proxy_options(proxy: &Option<String>) -> git2::ProxyOptions<'_> {
+    let mut proxy_options = git2::ProxyOptions::new();
+    if let Some(proxy) = proxy {
+        proxy_options.url(proxy);
+    }
+    proxy_options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod select_proxy {
+        use super::*;
+
+        fn candidates(proxies: &[&str]) -> Vec<(String, ConfigSource)> {
+            proxies
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p.to_string(), ConfigSource::Environment(i.to_string())))
+                .collect()
+        }
+
+        #[test]
+        fn relays_use_first_socks_proxy() {
+            let proxy = select_proxy(
+                candidates(&["http://proxy:8080", "socks5h://127.0.0.1:9050"]),
+                ProxyUse::Relays,
+            )
+            .unwrap();
+            assert_eq!(proxy.value, "socks5h://127.0.0.1:9050");
+            assert_eq!(proxy.source, ConfigSource::Environment("1".to_string()));
+        }
+
+        #[test]
+        fn git_servers_use_first_http_proxy() {
+            let proxy = select_proxy(
+                candidates(&["socks5://127.0.0.1:9050", "http://proxy:8080"]),
+                ProxyUse::GitServers,
+            )
+            .unwrap();
+            assert_eq!(proxy.value, "http://proxy:8080");
+        }
+
+        #[test]
+        fn proxy_without_scheme_is_socks() {
+            assert!(select_proxy(candidates(&["127.0.0.1:9050"]), ProxyUse::Relays).is_some());
+            assert!(select_proxy(candidates(&["127.0.0.1:9050"]), ProxyUse::GitServers).is_none());
+        }
+
+        #[test]
+        fn empty_values_ignored() {
+            assert!(select_proxy(candidates(&["", " "]), ProxyUse::Relays).is_none());
+        }
+    }
+
+    mod socks_proxy_addr {
+        use super::*;
+
+        #[test]
+        fn parses_with_and_without_scheme() -> Result<()> {
+            let expected: SocketAddr = "127.0.0.1:9050".parse()?;
+            assert_eq!(socks_proxy_addr("socks5://127.0.0.1:9050")?, expected);
+            assert_eq!(socks_proxy_addr("socks5h://127.0.0.1:9050/")?, expected);
+            assert_eq!(socks_proxy_addr("127.0.0.1:9050")?, expected);
+            Ok(())
+        }
+
+        #[test]
+        fn http_proxy_errors() {
+            assert!(socks_proxy_addr("http://127.0.0.1:8080").is_err());
+        }
+    }
+
+    mod is_onion_url {
+        use super::*;
+
+        #[test]
+        fn detects_onion_hosts() {
+            for url in [
+                "ws://abcdef.onion",
+                "wss://abcdef.onion/",
+                "http://abcdef.onion:8080/repo.git",
+                "ssh://git@abcdef.onion/repo.git",
+                "git@abcdef.onion:repo.git",
+            ] {
+                assert!(is_onion_url(url), "{url}");
+            }
+        }
+
+        #[test]
+        fn ignores_other_hosts() {
+            for url in [
+                "wss://relay.damus.io",
+                "https://github.com/onion/repo.onion",
+                "git@github.com:user/x.onion",
+            ] {
+                assert!(!is_onion_url(url), "{url}");
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::EventId;
+use serde::{Deserialize, Serialize};
+
+use crate::git::get_git_dir;
+
+/// a relay's OK response to an event we published
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RelayResponse {
+    pub accepted: bool,
+    /// reason given by the relay, eg. "blocked: pubkey not admitted"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+}
+
+impl RelayResponse {
+    /// the relay's response when `result` of sending an event came from an OK
+    /// message, rather than eg. a failure to connect
+    pub fn from_send_result<T>(result: &Result<T>) -> Option<Self> {
+        match result {
+            Ok(_) => Some(Self {
+                accepted: true,
+                message: String::new(),
+            }),
+            Err(error) => error
+                .to_string()
+                .strip_prefix("event not published: ")
+                .map(|message| Self {
+                    accepted: false,
+                    message: message.to_string(),
+                }),
+        }
+    }
+}
+
+impl std::fmt::Display for RelayResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.accepted {
+            write!(f, "accepted")
+        } else if self.message.is_empty() {
+            write!(f, "rejected")
+        } else {
+            write!(f, "rejected: {}", self.message)
+        }
+    }
+}
+
+/// relay responses keyed by event id then relay url
+type PublishStatus = HashMap<String, BTreeMap<String, RelayResponse>>;
+
+fn get_publish_status_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-publish-status.json")
+}
+
+fn read_publish_status(git_repo_path: &Path) -> PublishStatus {
+    std::fs::read_to_string(get_publish_status_path(git_repo_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// store each relay's response to an event, replacing any earlier response
+/// from that relay
+pub fn record_relay_responses(
+    git_repo_path: &Path,
+    responses: &[(EventId, String, RelayResponse)],
+) -> Result<()> {
+    if responses.is_empty() {
+        return Ok(());
+    }
+    let mut status = read_publish_status(git_repo_path);
+    for (event_id, relay, response) in responses {
+        status
+            .entry(event_id.to_hex())
+            .or_default()
+            .insert(relay.trim_end_matches('/').to_string(), response.clone());
+    }
+    let path = get_publish_status_path(git_repo_path);
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&status)?)
+        .context("failed to save relay responses to published events")?;
+    std::fs::rename(tmp_path, path).context("failed to save relay responses to published events")
+}
+
+/// responses recorded for `event_id` keyed by relay url
+pub fn get_relay_responses(
+    git_repo_path: &Path,
+    event_id: &EventId,
+) -> BTreeMap<String, RelayResponse> {
+    read_publish_status(git_repo_path)
+        .remove(&event_id.to_hex())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    mod from_send_result {
+        use super::*;
+
+        #[test]
+        fn rejection_message_kept() {
+            let result: Result<()> = Err(anyhow!("event not published: blocked: not admitted"));
+            assert_eq!(
+                RelayResponse::from_send_result(&result),
+                Some(RelayResponse {
+                    accepted: false,
+                    message: "blocked: not admitted".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn none_when_relay_never_responded() {
+            let result: Result<()> = Err(anyhow!("relay not connected"));
+            assert_eq!(RelayResponse::from_send_result(&result), None);
+        }
+    }
+
+    #[test]
+    fn latest_response_per_relay_is_kept() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let event_id = EventId::all_zeros();
+        let rejected = RelayResponse {
+            accepted: false,
+            message: "rate-limited: slow down".to_string(),
+        };
+        let accepted = RelayResponse {
+            accepted: true,
+            message: String::new(),
+        };
+        record_relay_responses(&test_repo.dir, &[
+            (event_id, "wss://a.relay/".to_string(), rejected.clone()),
+            (event_id, "wss://b.relay".to_string(), rejected.clone()),
+        ])?;
+        record_relay_responses(&test_repo.dir, &[(
+            event_id,
+            "wss://a.relay".to_string(),
+            accepted.clone(),
+        )])?;
+        assert_eq!(
+            get_relay_responses(&test_repo.dir, &event_id),
+            BTreeMap::from([
+                ("wss://a.relay".to_string(), accepted),
+                ("wss://b.relay".to_string(), rejected),
+            ])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::{Event, EventId, Timestamp};
+
+use crate::{client::get_global_cache_path, git::get_git_dir, git_events::comment_kinds};
+
+/// when each proposal was last viewed, so activity since can be marked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadState {
+    /// when read state started being tracked. proposals never viewed only
+    /// show activity after this so existing proposals don't all appear new
+    pub since: Timestamp,
+    pub last_seen: HashMap<EventId, Timestamp>,
+}
+
+impl ReadState {
+    fn to_file(&self) -> String {
+        let mut last_seen = self.last_seen.iter().collect::<Vec<_>>();
+        last_seen.sort();
+        let mut file = format!("since {}\n", self.since.as_u64());
+        for (id, seen) in last_seen {
+            file.push_str(&format!("{} {}\n", id.to_hex(), seen.as_u64()));
+        }
+        file
+    }
+
+    fn from_file(file: &str) -> Self {
+        let mut since = None;
+        let mut last_seen = HashMap::new();
+        for line in file.lines() {
+            match line.split_once(' ') {
+                Some(("since", timestamp)) => {
+                    since = timestamp.parse::<u64>().ok().map(Timestamp::from);
+                }
+                Some((id, timestamp)) => {
+                    if let (Ok(id), Ok(timestamp)) =
+                        (EventId::from_hex(id), timestamp.parse::<u64>())
+                    {
+                        last_seen.insert(id, Timestamp::from(timestamp));
+                    }
+                }
+                None => {}
+            }
+        }
+        Self {
+            since: since.unwrap_or(Timestamp::from(0)),
+            last_seen,
+        }
+    }
+
+    /// activity on `proposal` since it was last viewed, or None if there is
+    /// nothing new. `events` are patches, comments and statuses that tag it
+    pub fn unread_activity(&self, proposal: &Event, events: &[Event]) -> Option<UnreadActivity> {
+        let seen = self
+            .last_seen
+            .get(&proposal.id)
+            .copied()
+            .unwrap_or(self.since);
+        let new_events = events
+            .iter()
+            .filter(|e| e.created_at > seen && e.tags.event_ids().any(|id| id == &proposal.id))
+            .collect::<Vec<&Event>>();
+        if proposal.created_at <= seen && new_events.is_empty() {
+            return None;
+        }
+        Some(UnreadActivity {
+            comments: new_events
+                .iter()
+                .filter(|e| comment_kinds().contains(&e.kind))
+                .count(),
+        })
+    }
+}
+
+pub struct UnreadActivity {
+    pub comments: usize,
+}
+
+fn get_read_state_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-read-state")
+}
+
+/// read state for the repository, starting to track it from now if this is
+/// the first time
+pub fn load_or_start_read_state(git_repo_path: &Path) -> Result<ReadState> {
+    load_or_start_read_state_at(&get_read_state_path(git_repo_path))
+}
+
+fn load_or_start_read_state_at(path: &Path) -> Result<ReadState> {
+    if path.exists() {
+        return Ok(ReadState::from_file(
+            &std::fs::read_to_string(path).context("failed to read proposal read state")?,
+        ));
+    }
+    let read_state = ReadState {
+        since: Timestamp::now(),
+        last_seen: HashMap::new(),
+    };
+    save_read_state_at(path, &read_state)?;
+    Ok(read_state)
+}
+
+fn save_read_state(git_repo_path: &Path, read_state: &ReadState) -> Result<()> {
+    save_read_state_at(&get_read_state_path(git_repo_path), read_state)
+}
+
+fn save_read_state_at(path: &Path, read_state: &ReadState) -> Result<()> {
+    let tmp_path = path.with_extension(std::process::id().to_string());
+    std::fs::write(&tmp_path, read_state.to_file())
+        .context("failed to save proposal read state")?;
+    std::fs::rename(tmp_path, path).context("failed to save proposal read state")
+}
+
+/// `ngit inbox` covers repositories that may not be cloned so keeps its own
+/// read state alongside the global cache
+fn get_inbox_read_state_path(git_repo_path: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_global_cache_path(git_repo_path)?.with_file_name("inbox-read-state"))
+}
+
+/// read state for `ngit inbox`, starting to track it from now if this is the
+/// first time
+pub fn load_or_start_inbox_read_state(git_repo_path: Option<&Path>) -> Result<ReadState> {
+    load_or_start_read_state_at(&get_inbox_read_state_path(git_repo_path)?)
+}
+
+/// record that the inbox was viewed now so only later activity is marked
+pub fn mark_inbox_seen(git_repo_path: Option<&Path>, read_state: &mut ReadState) -> Result<()> {
+    read_state.since = Timestamp::now();
+    read_state.last_seen.clear();
+    save_read_state_at(&get_inbox_read_state_path(git_repo_path)?, read_state)
+}
+
+/// record that `proposal_id` was viewed now
+pub fn mark_proposal_seen(
+    git_repo_path: &Path,
+    read_state: &mut ReadState,
+    proposal_id: &EventId,
+) -> Result<()> {
+    read_state.last_seen.insert(*proposal_id, Timestamp::now());
+    save_read_state(git_repo_path, read_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind, Tag};
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn event_at(kind: Kind, created_at: u64, tags: Vec<Tag>) -> Result<Event> {
+        Ok(EventBuilder::new(kind, "")
+            .tags(tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(&Keys::generate())?)
+    }
+
+    #[test]
+    fn file_round_trips() -> Result<()> {
+        let proposal = event_at(Kind::GitPatch, 100, vec![])?;
+        let read_state = ReadState {
+            since: Timestamp::from(50),
+            last_seen: HashMap::from([(proposal.id, Timestamp::from(200))]),
+        };
+        assert_eq!(ReadState::from_file(&read_state.to_file()), read_state);
+        Ok(())
+    }
+
+    mod unread_activity {
+        use super::*;
+
+        #[test]
+        fn none_when_nothing_newer_than_last_seen() -> Result<()> {
+            let proposal = event_at(Kind::GitPatch, 100, vec![])?;
+            let comment = event_at(Kind::Custom(1111), 150, vec![Tag::event(proposal.id)])?;
+            let read_state = ReadState {
+                since: Timestamp::from(0),
+                last_seen: HashMap::from([(proposal.id, Timestamp::from(200))]),
+            };
+            assert!(read_state.unread_activity(&proposal, &[comment]).is_none());
+            Ok(())
+        }
+
+        #[test]
+        fn counts_comments_since_last_seen() -> Result<()> {
+            let proposal = event_at(Kind::GitPatch, 100, vec![])?;
+            let events = vec![
+                event_at(Kind::Custom(1111), 150, vec![Tag::event(proposal.id)])?,
+                event_at(Kind::Custom(1111), 250, vec![Tag::event(proposal.id)])?,
+                event_at(Kind::GitStatusClosed, 260, vec![Tag::event(proposal.id)])?,
+                // on another proposal
+                event_at(Kind::Custom(1111), 250, vec![])?,
+            ];
+            let read_state = ReadState {
+                since: Timestamp::from(0),
+                last_seen: HashMap::from([(proposal.id, Timestamp::from(200))]),
+            };
+            let unread = read_state.unread_activity(&proposal, &events).unwrap();
+            assert_eq!(unread.comments, 1);
+            Ok(())
+        }
+
+        #[test]
+        fn never_viewed_proposal_compared_with_since() -> Result<()> {
+            let older = event_at(Kind::GitPatch, 100, vec![])?;
+            let newer = event_at(Kind::GitPatch, 300, vec![])?;
+            let read_state = ReadState {
+                since: Timestamp::from(200),
+                last_seen: HashMap::new(),
+            };
+            assert!(read_state.unread_activity(&older, &[]).is_none());
+            assert!(read_state.unread_activity(&newer, &[]).is_some());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mark_proposal_seen_persists() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let proposal = event_at(Kind::GitPatch, 100, vec![])?;
+        let mut read_state = load_or_start_read_state(&test_repo.dir)?;
+        mark_proposal_seen(&test_repo.dir, &mut read_state, &proposal.id)?;
+        let read_state = load_or_start_read_state(&test_repo.dir)?;
+        assert!(read_state.last_seen.contains_key(&proposal.id));
+        Ok(())
+    }
+}
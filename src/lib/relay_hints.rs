@@ -0,0 +1,189 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::{Event, Kind, nips::nip01::Coordinate};
+use nostr_sdk::RelayUrl;
+
+use crate::{git::get_git_dir, git_events::event_is_patch_set_root};
+
+/// relays embedded in shared naddr and nevent strings
+pub static MAX_RELAY_HINTS: usize = 3;
+
+/// relays that returned each event in the last fetch that included it,
+/// keyed by [`relay_hints_key_for_coordinate`] or event id
+type RelaysByEvent = HashMap<String, BTreeSet<String>>;
+
+fn get_relay_hints_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-relay-hints.json")
+}
+
+fn read_relays_by_event(git_repo_path: &Path) -> RelaysByEvent {
+    std::fs::read_to_string(get_relay_hints_path(git_repo_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// announcements are replaced when updated so are tracked by coordinate
+pub fn relay_hints_key_for_coordinate(coordinate: &Coordinate) -> String {
+    format!(
+        "{}:{}:{}",
+        coordinate.kind.as_u16(),
+        coordinate.public_key.to_hex(),
+        coordinate.identifier
+    )
+}
+
+/// key for events worth sharing, ie. announcements and proposals
+pub fn relay_hints_key(event: &Event) -> Option<String> {
+    if event.kind.eq(&Kind::GitRepoAnnouncement) {
+        Some(relay_hints_key_for_coordinate(&Coordinate {
+            kind: event.kind,
+            public_key: event.pubkey,
+            identifier: event.tags.identifier()?.to_string(),
+            relays: vec![],
+        }))
+    } else if event_is_patch_set_root(event) {
+        Some(event.id.to_hex())
+    } else {
+        None
+    }
+}
+
+/// replace the relays recorded for each key with those that returned it in
+/// this fetch. keys not returned by any relay keep what was recorded before
+pub fn record_relays_that_returned_events(
+    git_repo_path: &Path,
+    returned: HashMap<String, BTreeSet<String>>,
+) -> Result<()> {
+    if returned.is_empty() {
+        return Ok(());
+    }
+    let mut relays_by_event = read_relays_by_event(git_repo_path);
+    relays_by_event.extend(returned);
+    let path = get_relay_hints_path(git_repo_path);
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&relays_by_event)?)
+        .context("failed to save relays that returned events")?;
+    std::fs::rename(tmp_path, path).context("failed to save relays that returned events")
+}
+
+/// relays that returned `key` in the last fetch that included it
+pub fn get_relays_that_returned(git_repo_path: &Path, key: &str) -> Vec<String> {
+    read_relays_by_event(git_repo_path)
+        .remove(key)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+/// up to [`MAX_RELAY_HINTS`] of `candidates`, those that returned the event
+/// first. `returned` is used when there are no candidates
+pub fn select_relay_hints(candidates: &[RelayUrl], returned: &[String]) -> Vec<RelayUrl> {
+    let returned_it = |relay: &RelayUrl| {
+        returned
+            .iter()
+            .any(|r| r.trim_end_matches('/') == relay.as_str_without_trailing_slash())
+    };
+    if candidates.is_empty() {
+        return returned
+            .iter()
+            .filter_map(|r| RelayUrl::parse(r).ok())
+            .take(MAX_RELAY_HINTS)
+            .collect();
+    }
+    candidates
+        .iter()
+        .filter(|r| returned_it(r))
+        .chain(candidates.iter().filter(|r| !returned_it(r)))
+        .take(MAX_RELAY_HINTS)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn relays(urls: &[&str]) -> Vec<RelayUrl> {
+        urls.iter().map(|r| RelayUrl::parse(r).unwrap()).collect()
+    }
+
+    mod select_relay_hints {
+        use super::*;
+
+        #[test]
+        fn relays_that_returned_the_event_come_first() {
+            assert_eq!(
+                select_relay_hints(
+                    &relays(&["wss://a.relay", "wss://b.relay"]),
+                    &["wss://b.relay/".to_string()]
+                ),
+                relays(&["wss://b.relay", "wss://a.relay"]),
+            );
+        }
+
+        #[test]
+        fn limited_to_max_relay_hints() {
+            assert_eq!(
+                select_relay_hints(
+                    &relays(&[
+                        "wss://a.relay",
+                        "wss://b.relay",
+                        "wss://c.relay",
+                        "wss://d.relay"
+                    ]),
+                    &["wss://d.relay".to_string()]
+                ),
+                relays(&["wss://d.relay", "wss://a.relay", "wss://b.relay"]),
+            );
+        }
+
+        #[test]
+        fn relays_that_returned_the_event_used_without_candidates() {
+            assert_eq!(
+                select_relay_hints(&[], &["wss://a.relay".to_string()]),
+                relays(&["wss://a.relay"]),
+            );
+        }
+    }
+
+    #[test]
+    fn latest_fetch_replaces_relays_recorded_for_same_event() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        record_relays_that_returned_events(
+            &test_repo.dir,
+            HashMap::from([
+                (
+                    "a".to_string(),
+                    BTreeSet::from(["wss://a.relay".to_string()]),
+                ),
+                (
+                    "b".to_string(),
+                    BTreeSet::from(["wss://a.relay".to_string()]),
+                ),
+            ]),
+        )?;
+        record_relays_that_returned_events(
+            &test_repo.dir,
+            HashMap::from([(
+                "a".to_string(),
+                BTreeSet::from(["wss://b.relay".to_string()]),
+            )]),
+        )?;
+        assert_eq!(
+            get_relays_that_returned(&test_repo.dir, "a"),
+            vec!["wss://b.relay".to_string()]
+        );
+        assert_eq!(
+            get_relays_that_returned(&test_repo.dir, "b"),
+            vec!["wss://a.relay".to_string()]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use nostr::{Filter, Timestamp, Url, nips::nip11::RelayInformationDocument};
+use nostr_sdk::RelayUrl;
+use serde::{Deserialize, Serialize};
+
+use crate::{get_dirs, git::get_git_dir};
+
+/// how long a relay's advertised limits are used before its NIP-11 document
+/// is fetched again
+pub static RELAY_INFO_TTL_SECS: u64 = 60 * 60 * 24;
+
+static RELAY_INFO_TIMEOUT_SECS: u64 = 3;
+
+/// subscription limits a relay advertises in its NIP-11 information document
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionLimits {
+    pub max_filters: Option<usize>,
+    pub max_limit: Option<usize>,
+}
+
+impl SubscriptionLimits {
+    fn from_document(document: &RelayInformationDocument) -> Self {
+        let limitation = document.limitation.as_ref();
+        // some relays advertise 0 or -1 to mean unlimited
+        let positive = |value: Option<i32>| {
+            value
+                .and_then(|v| usize::try_from(v).ok())
+                .filter(|v| *v > 0)
+        };
+        Self {
+            max_filters: positive(limitation.and_then(|l| l.max_filters)),
+            max_limit: positive(limitation.and_then(|l| l.max_limit)),
+        }
+    }
+
+    /// clamp each filter's `limit` to `max_limit` and group the filters into
+    /// requests of no more than `max_filters`
+    pub fn split_filters(&self, filters: Vec<Filter>) -> Vec<Vec<Filter>> {
+        let filters = filters
+            .into_iter()
+            .map(|mut filter| {
+                if let (Some(max_limit), Some(limit)) = (self.max_limit, filter.limit) {
+                    filter.limit = Some(limit.min(max_limit));
+                }
+                filter
+            })
+            .collect::<Vec<Filter>>();
+        match self.max_filters {
+            Some(max_filters) if filters.len() > max_filters => filters
+                .chunks(max_filters)
+                .map(<[Filter]>::to_vec)
+                .collect(),
+            _ => vec![filters],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSubscriptionLimits {
+    fetched_at: u64,
+    #[serde(flatten)]
+    limits: SubscriptionLimits,
+}
+
+fn get_relay_info_cache_path(git_repo_path: Option<&Path>) -> Result<Option<PathBuf>> {
+    Ok(if std::env::var("NGITTEST").is_ok() {
+        git_repo_path.map(|path| get_git_dir(path).join("test-relay-info.json"))
+    } else {
+        Some(get_dirs()?.cache_dir().join("relay-info.json"))
+    })
+}
+
+fn read_relay_info_cache(path: &Path) -> HashMap<String, CachedSubscriptionLimits> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// add `limits` to the cache as it is now, rather than as it was before the
+/// document was fetched, so limits cached meanwhile for other relays are
+/// kept. the file is replaced by a rename so other ngit processes never read
+/// it half written
+fn record_subscription_limits(
+    path: &Path,
+    key: String,
+    limits: SubscriptionLimits,
+    now: Timestamp,
+) -> Result<()> {
+    let mut cache = read_relay_info_cache(path);
+    cache.insert(key, CachedSubscriptionLimits {
+        fetched_at: now.as_u64(),
+        limits,
+    });
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create relay info cache directory")?;
+    }
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&cache)?)
+        .context("failed to save relay info cache")?;
+    std::fs::rename(tmp_path, path).context("failed to save relay info cache")
+}
+
+/// limits advertised by `relay_url`, cached for [`RELAY_INFO_TTL_SECS`].
+/// when the document is unavailable no limits are applied
+pub async fn get_subscription_limits(
+    relay_url: &RelayUrl,
+    git_repo_path: Option<&Path>,
+    proxy: Option<SocketAddr>,
+) -> SubscriptionLimits {
+    let cache_path = get_relay_info_cache_path(git_repo_path).ok().flatten();
+    let cache = cache_path
+        .as_deref()
+        .map(read_relay_info_cache)
+        .unwrap_or_default();
+    let key = relay_url.as_str().trim_end_matches('/').to_string();
+    if let Some(cached) = cache.get(&key) {
+        if Timestamp::now().as_u64().saturating_sub(cached.fetched_at) < RELAY_INFO_TTL_SECS {
+            return cached.limits;
+        }
+    }
+    let limits = fetch_subscription_limits(relay_url, proxy).await;
+    if let Some(cache_path) = cache_path {
+        // a failure to cache only means the document is fetched again next time
+        let _ = record_subscription_limits(&cache_path, key, limits, Timestamp::now());
+    }
+    limits
+}
+
+async fn fetch_subscription_limits(
+    relay_url: &RelayUrl,
+    proxy: Option<SocketAddr>,
+) -> SubscriptionLimits {
+    let Ok(url) = Url::parse(relay_url.as_str()) else {
+        return SubscriptionLimits::default();
+    };
+    match tokio::time::timeout(
+        Duration::from_secs(RELAY_INFO_TIMEOUT_SECS),
+        RelayInformationDocument::get(url, proxy),
+    )
+    .await
+    {
+        Ok(Ok(document)) => SubscriptionLimits::from_document(&document),
+        _ => SubscriptionLimits::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Kind, nips::nip11::Limitation};
+
+    use super::*;
+
+    fn filters(n: usize) -> Vec<Filter> {
+        (0..n)
+            .map(|i| Filter::default().kind(Kind::Custom(u16::try_from(i).unwrap())))
+            .collect()
+    }
+
+    mod split_filters {
+        use super::*;
+
+        #[test]
+        fn single_request_without_limits() {
+            let requests = SubscriptionLimits::default().split_filters(filters(3));
+            assert_eq!(requests, vec![filters(3)]);
+        }
+
+        #[test]
+        fn requests_contain_no_more_than_max_filters() {
+            let limits = SubscriptionLimits {
+                max_filters: Some(2),
+                max_limit: None,
+            };
+            let requests = limits.split_filters(filters(5));
+            assert_eq!(
+                requests.iter().map(Vec::len).collect::<Vec<usize>>(),
+                vec![2, 2, 1]
+            );
+            assert_eq!(requests.concat(), filters(5));
+        }
+
+        #[test]
+        fn limits_clamped_to_max_limit() {
+            let limits = SubscriptionLimits {
+                max_filters: None,
+                max_limit: Some(100),
+            };
+            let requests = limits.split_filters(vec![
+                Filter::default().limit(500),
+                Filter::default().limit(50),
+                Filter::default(),
+            ]);
+            assert_eq!(
+                requests[0].iter().map(|f| f.limit).collect::<Vec<_>>(),
+                vec![Some(100), Some(50), None]
+            );
+        }
+    }
+
+    #[test]
+    fn non_positive_document_limits_are_ignored() {
+        let document = RelayInformationDocument {
+            limitation: Some(Limitation {
+                max_filters: Some(1),
+                max_limit: Some(-1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            SubscriptionLimits::from_document(&document),
+            SubscriptionLimits {
+                max_filters: Some(1),
+                max_limit: None,
+            }
+        );
+    }
+
+    mod record_subscription_limits {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[test]
+        fn keeps_limits_cached_for_other_relays_meanwhile() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let path = test_repo.dir.join("relay-info.json");
+            let limits = |max_limit| SubscriptionLimits {
+                max_filters: None,
+                max_limit: Some(max_limit),
+            };
+            record_subscription_limits(&path, "wss://a".to_string(), limits(1), Timestamp::now())?;
+            record_subscription_limits(&path, "wss://b".to_string(), limits(2), Timestamp::now())?;
+            let cache = read_relay_info_cache(&path);
+            assert_eq!(cache.get("wss://a").map(|c| c.limits), Some(limits(1)));
+            assert_eq!(cache.get("wss://b").map(|c| c.limits), Some(limits(2)));
+            Ok(())
+        }
+    }
+}
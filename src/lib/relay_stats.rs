@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use nostr_sdk::RelayUrl;
+use serde::{Deserialize, Serialize};
+
+use crate::{get_dirs, git::get_git_dir};
+
+/// how often fetching from a relay succeeded and how long it took
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayStats {
+    pub successes: u64,
+    pub failures: u64,
+    /// mean duration of successful fetches
+    pub mean_latency_ms: u64,
+}
+
+impl RelayStats {
+    fn record(&mut self, success: bool, latency: Duration) {
+        if success {
+            let latency_ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+            self.mean_latency_ms = self
+                .mean_latency_ms
+                .saturating_mul(self.successes)
+                .saturating_add(latency_ms)
+                / (self.successes + 1);
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+    }
+
+    /// failures per thousand fetches
+    fn failure_rate(&self) -> u64 {
+        self.failures * 1000 / (self.successes + self.failures).max(1)
+    }
+}
+
+fn get_relay_stats_cache_path(git_repo_path: Option<&Path>) -> Result<Option<PathBuf>> {
+    Ok(if std::env::var("NGITTEST").is_ok() {
+        git_repo_path.map(|path| get_git_dir(path).join("test-relay-stats.json"))
+    } else {
+        Some(get_dirs()?.cache_dir().join("relay-stats.json"))
+    })
+}
+
+fn read_relay_stats_cache(path: &Path) -> HashMap<String, RelayStats> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn relay_stats_key(relay_url: &RelayUrl) -> String {
+    relay_url.as_str().trim_end_matches('/').to_string()
+}
+
+/// `relays` with the historically most reliable first, then the fastest.
+/// relays without history follow those that have never failed
+pub fn order_by_reliability(git_repo_path: Option<&Path>, relays: Vec<RelayUrl>) -> Vec<RelayUrl> {
+    let stats = get_relay_stats_cache_path(git_repo_path)
+        .ok()
+        .flatten()
+        .as_deref()
+        .map(read_relay_stats_cache)
+        .unwrap_or_default();
+    sort_by_reliability(&stats, relays)
+}
+
+fn sort_by_reliability(
+    stats: &HashMap<String, RelayStats>,
+    mut relays: Vec<RelayUrl>,
+) -> Vec<RelayUrl> {
+    relays.sort_by_cached_key(|relay| {
+        let key = relay_stats_key(relay);
+        let (failure_rate, latency) = match stats.get(&key) {
+            Some(stats) => (stats.failure_rate(), stats.mean_latency_ms),
+            None => (0, u64::MAX),
+        };
+        (failure_rate, latency, key)
+    });
+    relays
+}
+
+/// record whether fetching from each relay succeeded and how long it took
+pub fn record_relay_fetches(
+    git_repo_path: Option<&Path>,
+    fetches: &[(RelayUrl, bool, Duration)],
+) -> Result<()> {
+    let Some(path) = get_relay_stats_cache_path(git_repo_path)? else {
+        return Ok(());
+    };
+    if fetches.is_empty() {
+        return Ok(());
+    }
+    let mut cache = read_relay_stats_cache(&path);
+    for (relay_url, success, latency) in fetches {
+        cache
+            .entry(relay_stats_key(relay_url))
+            .or_default()
+            .record(*success, *latency);
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create relay stats cache directory")?;
+    }
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&cache)?)
+        .context("failed to save relay stats")?;
+    std::fs::rename(tmp_path, path).context("failed to save relay stats")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(port: u16) -> RelayUrl {
+        RelayUrl::parse(&format!("ws://localhost:{port}")).unwrap()
+    }
+
+    mod record {
+        use super::*;
+
+        #[test]
+        fn mean_latency_only_counts_successes() {
+            let mut stats = RelayStats::default();
+            stats.record(true, Duration::from_millis(100));
+            stats.record(false, Duration::from_millis(7000));
+            stats.record(true, Duration::from_millis(300));
+            assert_eq!(stats, RelayStats {
+                successes: 2,
+                failures: 1,
+                mean_latency_ms: 200,
+            });
+        }
+    }
+
+    mod sort_by_reliability {
+        use super::*;
+
+        #[test]
+        fn fewer_failures_then_faster_then_unknown_then_failing() {
+            let stats = HashMap::from([
+                ("ws://localhost:8051".to_string(), RelayStats {
+                    successes: 5,
+                    failures: 5,
+                    mean_latency_ms: 50,
+                }),
+                ("ws://localhost:8052".to_string(), RelayStats {
+                    successes: 10,
+                    failures: 0,
+                    mean_latency_ms: 900,
+                }),
+                ("ws://localhost:8053".to_string(), RelayStats {
+                    successes: 10,
+                    failures: 0,
+                    mean_latency_ms: 100,
+                }),
+            ]);
+            assert_eq!(
+                sort_by_reliability(&stats, vec![url(8051), url(8052), url(8053), url(8054)]),
+                vec![url(8053), url(8052), url(8054), url(8051)],
+            );
+        }
+    }
+}
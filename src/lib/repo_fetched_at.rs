@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr::{Timestamp, nips::nip01::Coordinate};
+
+use crate::git::get_git_dir;
+
+/// how long git-remote-nostr answers `list` from the cache after the
+/// repository was fetched
+pub static DEFAULT_CACHE_MAX_AGE_SECS: u64 = 60;
+
+/// unix timestamp each repository was last fetched keyed by
+/// `<kind>:<hex public key>:<identifier>`
+type ReposFetchedAt = HashMap<String, u64>;
+
+fn get_repos_fetched_at_path(git_repo_path: &Path) -> PathBuf {
+    get_git_dir(git_repo_path).join("nostr-fetched-at.json")
+}
+
+fn read_repos_fetched_at(git_repo_path: &Path) -> ReposFetchedAt {
+    std::fs::read_to_string(get_repos_fetched_at_path(git_repo_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// relay hints are ignored so each repository has one entry
+fn repo_fetched_at_key(coordinate: &Coordinate) -> String {
+    format!(
+        "{}:{}:{}",
+        coordinate.kind.as_u16(),
+        coordinate.public_key.to_hex(),
+        coordinate.identifier
+    )
+}
+
+/// record that the repository at `coordinate` was fetched at `now`. the file
+/// is replaced by a rename so other processes never read it half written
+pub fn record_repo_fetched(
+    git_repo_path: &Path,
+    coordinate: &Coordinate,
+    now: Timestamp,
+) -> Result<()> {
+    let mut fetched_at = read_repos_fetched_at(git_repo_path);
+    fetched_at.insert(repo_fetched_at_key(coordinate), now.as_u64());
+    let path = get_repos_fetched_at_path(git_repo_path);
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&tmp_path, serde_json::to_string(&fetched_at)?)
+        .context("failed to save when the repository was fetched")?;
+    std::fs::rename(tmp_path, path).context("failed to save when the repository was fetched")
+}
+
+/// seconds since the repository at `coordinate` was last fetched, if ever
+pub fn seconds_since_repo_fetched(
+    git_repo_path: &Path,
+    coordinate: &Coordinate,
+    now: Timestamp,
+) -> Option<u64> {
+    seconds_since_fetched(&read_repos_fetched_at(git_repo_path), coordinate, now)
+}
+
+fn seconds_since_fetched(
+    fetched_at: &ReposFetchedAt,
+    coordinate: &Coordinate,
+    now: Timestamp,
+) -> Option<u64> {
+    // a fetch recorded in the future is treated as never having happened
+    now.as_u64()
+        .checked_sub(*fetched_at.get(&repo_fetched_at_key(coordinate))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Kind;
+    use test_utils::TEST_KEY_1_KEYS;
+
+    use super::*;
+
+    fn coordinate(identifier: &str) -> Coordinate {
+        Coordinate {
+            kind: Kind::GitRepoAnnouncement,
+            public_key: TEST_KEY_1_KEYS.public_key(),
+            identifier: identifier.to_string(),
+            relays: vec![],
+        }
+    }
+
+    mod seconds_since_fetched {
+        use super::*;
+
+        #[test]
+        fn recorded_fetch_is_aged_from_now() {
+            let fetched_at = HashMap::from([(repo_fetched_at_key(&coordinate("example")), 1_000)]);
+            assert_eq!(
+                seconds_since_fetched(&fetched_at, &coordinate("example"), Timestamp::from(1_012)),
+                Some(12)
+            );
+        }
+
+        #[test]
+        fn other_repository_was_never_fetched() {
+            let fetched_at = HashMap::from([(repo_fetched_at_key(&coordinate("example")), 1_000)]);
+            assert_eq!(
+                seconds_since_fetched(&fetched_at, &coordinate("other"), Timestamp::from(1_012)),
+                None
+            );
+        }
+
+        #[test]
+        fn fetch_in_the_future_is_ignored() {
+            let fetched_at = HashMap::from([(repo_fetched_at_key(&coordinate("example")), 2_000)]);
+            assert_eq!(
+                seconds_since_fetched(&fetched_at, &coordinate("example"), Timestamp::from(1_012)),
+                None
+            );
+        }
+    }
+}
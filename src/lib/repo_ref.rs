@@ -19,6 +19,7 @@ use crate::{
         Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms, PromptInputParms,
     },
     client::{Connect, consolidate_fetch_reports, get_repo_ref_from_cache, sign_event},
+    error::{ErrorCategory, NgitError},
     git::{
         Repo, RepoActions,
         nostr_url::{NostrUrlDecoded, use_nip05_git_config_cache_to_find_nip05_from_public_key},
@@ -36,6 +37,22 @@ pub struct RepoRef {
     pub web: Vec<String>,
     pub relays: Vec<RelayUrl>,
     pub maintainers: Vec<PublicKey>,
+    /// gitignore-style patterns for refs to leave out of the state event
+    pub state_ref_ignore: Vec<String>,
+    /// git servers that are read-only mirrors. they are fetched from but
+    /// skipped when pushing
+    pub mirrors: Vec<String>,
+    /// git servers, usually grasp servers, that let contributors push
+    /// proposal commits to `refs/heads/contrib/<npub>/*`
+    pub contributor_push: Vec<String>,
+    /// blossom media servers that store proposal attachments, eg. screenshots,
+    /// that don't belong in git history or nostr events
+    pub blossoms: Vec<String>,
+    /// npubs whose comments labelled with a ci result are shown as the
+    /// proposal's ci status
+    pub ci_bots: Vec<PublicKey>,
+    /// what maintainers expect of proposals, checked by `ngit send`
+    pub submission_policy: SubmissionPolicy,
     pub trusted_maintainer: PublicKey,
     pub events: HashMap<Coordinate, nostr::Event>,
     pub nostr_git_url: Option<NostrUrlDecoded>,
@@ -59,6 +76,12 @@ impl TryFrom<(nostr::Event, Option<PublicKey>)> for RepoRef {
             web: Vec::new(),
             relays: Vec::new(),
             maintainers: Vec::new(),
+            state_ref_ignore: Vec::new(),
+            mirrors: Vec::new(),
+            contributor_push: Vec::new(),
+            blossoms: Vec::new(),
+            ci_bots: Vec::new(),
+            submission_policy: SubmissionPolicy::default(),
             trusted_maintainer: trusted_maintainer.unwrap_or(event.pubkey),
             events: HashMap::new(),
             nostr_git_url: None,
@@ -97,6 +120,27 @@ impl TryFrom<(nostr::Event, Option<PublicKey>)> for RepoRef {
                         }
                     }
                 }
+                [t, patterns @ ..] if t == "state-ref-ignore" => {
+                    r.state_ref_ignore = patterns.to_vec();
+                }
+                [t, mirrors @ ..] if t == "mirror" => {
+                    r.mirrors = mirrors.to_vec();
+                }
+                [t, servers @ ..] if t == "contributor-push" => {
+                    r.contributor_push = servers.to_vec();
+                }
+                [t, servers @ ..] if t == "blossoms" => {
+                    r.blossoms = servers.to_vec();
+                }
+                [t, bots @ ..] if t == "ci-bots" => {
+                    r.ci_bots = bots
+                        .iter()
+                        .filter_map(|pk| PublicKey::parse(pk).ok())
+                        .collect();
+                }
+                [t, policy @ ..] if t == "submission-policy" => {
+                    r.submission_policy = SubmissionPolicy::from_tag_values(policy);
+                }
                 [t, maintainers @ ..] if t == "maintainers" => {
                     if !maintainers.contains(&event.pubkey.to_string()) {
                         r.maintainers.push(event.pubkey);
@@ -133,67 +177,122 @@ impl TryFrom<(nostr::Event, Option<PublicKey>)> for RepoRef {
 
 impl RepoRef {
     pub async fn to_event(&self, signer: &Arc<dyn NostrSigner>) -> Result<nostr::Event> {
-        sign_event(
-            nostr_sdk::EventBuilder::new(nostr::event::Kind::GitRepoAnnouncement, "").tags(
-                [
-                    vec![
-                        Tag::identifier(if self.identifier.to_string().is_empty() {
-                            // fiatjaf thought a random string. its not in the draft nip.
-                            // thread_rng()
-                            //     .sample_iter(&Alphanumeric)
-                            //     .take(15)
-                            //     .map(char::from)
-                            //     .collect()
-
-                            // an identifier based on first commit is better so that users dont
-                            // accidentally create two seperate identifiers for the same repo
-                            // there is a hesitancy to use the commit id
-                            // in another conversaion with fiatjaf he suggested the first 6
-                            // character of the commit id
-                            // here we are using 7 which is the standard for shorthand commit id
-                            self.root_commit.to_string()[..7].to_string()
-                        } else {
-                            self.identifier.to_string()
-                        }),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("r")),
-                            vec![self.root_commit.to_string(), "euc".to_string()],
-                        ),
-                        Tag::from_standardized(TagStandard::Name(self.name.clone())),
-                        Tag::from_standardized(TagStandard::Description(self.description.clone())),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("clone")),
-                            self.git_server.clone(),
-                        ),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("web")),
-                            self.web.clone(),
-                        ),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("relays")),
-                            self.relays.iter().map(|r| r.to_string()),
-                        ),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("maintainers")),
-                            self.maintainers
-                                .iter()
-                                .map(std::string::ToString::to_string)
-                                .collect::<Vec<String>>(),
-                        ),
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
-                            vec![format!("git repository: {}", self.name.clone())],
-                        ),
-                    ],
-                    // code languages and hashtags
-                ]
-                .concat(),
-            ),
-            signer,
-        )
-        .await
-        .context("failed to create repository reference event")
+        announcement_from_tags(self.to_tags(), signer).await
     }
+
+    pub fn to_tags(&self) -> Vec<Tag> {
+        [
+            vec![Tag::identifier(if self.identifier.to_string().is_empty() {
+                // fiatjaf thought a random string. its not in the draft nip.
+                // thread_rng()
+                //     .sample_iter(&Alphanumeric)
+                //     .take(15)
+                //     .map(char::from)
+                //     .collect()
+
+                // an identifier based on first commit is better so that users dont
+                // accidentally create two seperate identifiers for the same repo
+                // there is a hesitancy to use the commit id
+                // in another conversaion with fiatjaf he suggested the first 6
+                // character of the commit id
+                // here we are using 7 which is the standard for shorthand commit id
+                self.root_commit.to_string()[..7].to_string()
+            } else {
+                self.identifier.to_string()
+            })],
+            // a repository announced before its first commit gets its root
+            // commit on first push
+            if self.root_commit.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("r")),
+                    vec![self.root_commit.to_string(), "euc".to_string()],
+                )]
+            },
+            vec![
+                Tag::from_standardized(TagStandard::Name(self.name.clone())),
+                Tag::from_standardized(TagStandard::Description(self.description.clone())),
+                Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("clone")),
+                    self.git_server.clone(),
+                ),
+                Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("web")),
+                    self.web.clone(),
+                ),
+                Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("relays")),
+                    self.relays.iter().map(|r| r.to_string()),
+                ),
+                Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("maintainers")),
+                    self.maintainers
+                        .iter()
+                        .map(std::string::ToString::to_string)
+                        .collect::<Vec<String>>(),
+                ),
+                Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+                    vec![format!("git repository: {}", self.name.clone())],
+                ),
+            ],
+            if self.state_ref_ignore.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("state-ref-ignore")),
+                    self.state_ref_ignore.clone(),
+                )]
+            },
+            if self.mirrors.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("mirror")),
+                    self.mirrors.clone(),
+                )]
+            },
+            if self.contributor_push.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("contributor-push")),
+                    self.contributor_push.clone(),
+                )]
+            },
+            if self.blossoms.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("blossoms")),
+                    self.blossoms.clone(),
+                )]
+            },
+            if self.ci_bots.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("ci-bots")),
+                    self.ci_bots
+                        .iter()
+                        .map(PublicKey::to_hex)
+                        .collect::<Vec<String>>(),
+                )]
+            },
+            if self.submission_policy.is_empty() {
+                vec![]
+            } else {
+                vec![Tag::custom(
+                    nostr::TagKind::Custom(std::borrow::Cow::Borrowed("submission-policy")),
+                    self.submission_policy.to_tag_values(),
+                )]
+            },
+            // code languages and hashtags
+        ]
+        .concat()
+    }
+
     /// coordinates without relay hints
     pub fn coordinates(&self) -> HashSet<Coordinate> {
         let mut res = HashSet::new();
@@ -260,22 +359,393 @@ impl RepoRef {
     }
 }
 
+/// what maintainers expect of proposals, from the announcement's
+/// `submission-policy` tag eg. `["submission-policy", "target-branch=develop",
+/// "cover-letter=required", "max-patches=20"]`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubmissionPolicy {
+    /// branch proposals must build on
+    pub target_branch: Option<String>,
+    pub cover_letter_required: bool,
+    pub max_patches: Option<usize>,
+}
+
+/// the parts of a proposal a [`SubmissionPolicy`] is checked against
+pub struct ProposalSubmission {
+    /// whether the proposal builds on a commit in the target branch. none
+    /// when the target branch isn't available locally
+    pub builds_on_target: Option<bool>,
+    pub has_cover_letter: bool,
+    pub patch_count: usize,
+}
+
+impl SubmissionPolicy {
+    /// unrecognised entries are ignored so policies can grow without breaking
+    /// older clients
+    pub fn from_tag_values(values: &[String]) -> Self {
+        let mut policy = Self::default();
+        for value in values {
+            match value.split_once('=') {
+                Some(("target-branch", branch)) if !branch.is_empty() => {
+                    policy.target_branch = Some(branch.to_string());
+                }
+                Some(("cover-letter", requirement)) => {
+                    policy.cover_letter_required = requirement == "required";
+                }
+                Some(("max-patches", max)) => {
+                    policy.max_patches = max.parse().ok().filter(|max| *max > 0);
+                }
+                _ => {}
+            }
+        }
+        policy
+    }
+
+    /// parse policy entries given to `ngit init`, rejecting ones that would be
+    /// ignored
+    pub fn parse(values: &[String]) -> Result<Self> {
+        for value in values {
+            match value.split_once('=') {
+                Some(("target-branch", branch)) if !branch.is_empty() => {}
+                Some(("cover-letter", "required" | "optional")) => {}
+                Some(("max-patches", max)) if max.parse::<usize>().is_ok_and(|max| max > 0) => {}
+                _ => bail!(
+                    "invalid submission policy entry '{value}'. expected target-branch=<branch>, cover-letter=required|optional or max-patches=<number>"
+                ),
+            }
+        }
+        Ok(Self::from_tag_values(values))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.eq(&Self::default())
+    }
+
+    pub fn to_tag_values(&self) -> Vec<String> {
+        [
+            self.target_branch
+                .as_ref()
+                .map(|branch| format!("target-branch={branch}")),
+            self.cover_letter_required
+                .then(|| "cover-letter=required".to_string()),
+            self.max_patches.map(|max| format!("max-patches={max}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// eg. "proposals must target develop, cover letter required"
+    pub fn describe(&self) -> String {
+        [
+            self.target_branch
+                .as_ref()
+                .map(|branch| format!("proposals must target {branch}")),
+            self.cover_letter_required
+                .then(|| "cover letter required".to_string()),
+            self.max_patches
+                .map(|max| format!("max {max} patches per proposal")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>()
+        .join(", ")
+    }
+
+    /// how `proposal` falls short of the policy
+    pub fn violations(&self, proposal: &ProposalSubmission) -> Vec<String> {
+        [
+            match (&self.target_branch, proposal.builds_on_target) {
+                (Some(branch), Some(false)) => {
+                    Some(format!("proposals must build on a commit in '{branch}'"))
+                }
+                _ => None,
+            },
+            (self.cover_letter_required && !proposal.has_cover_letter)
+                .then(|| "a cover letter is required".to_string()),
+            self.max_patches
+                .filter(|max| proposal.patch_count > *max)
+                .map(|max| {
+                    format!(
+                        "proposals can have at most {max} patches but this has {}",
+                        proposal.patch_count
+                    )
+                }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+pub async fn announcement_from_tags(
+    tags: Vec<Tag>,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<nostr::Event> {
+    sign_event(
+        nostr_sdk::EventBuilder::new(nostr::event::Kind::GitRepoAnnouncement, "").tags(tags),
+        signer,
+    )
+    .await
+    .context("failed to create repository reference event")
+}
+
+/// tags for an update to `baseline`, the maintainer's latest announcement.
+/// only tags for fields that differ from the baseline are replaced so tags
+/// for unchanged fields, and tags ngit doesn't recognise, are kept as
+/// published
+pub fn merge_announcement_tags(baseline: &nostr::Event, updated: &RepoRef) -> Vec<Tag> {
+    let Ok(previous) = RepoRef::try_from((baseline.clone(), None)) else {
+        return updated.to_tags();
+    };
+    let changed_tag_names = [
+        (previous.identifier != updated.identifier, vec!["d"]),
+        (previous.root_commit != updated.root_commit, vec!["r"]),
+        (previous.name != updated.name, vec!["name", "alt"]),
+        (
+            previous.description != updated.description,
+            vec!["description"],
+        ),
+        (previous.git_server != updated.git_server, vec!["clone"]),
+        (previous.web != updated.web, vec!["web"]),
+        (previous.relays != updated.relays, vec!["relays"]),
+        (
+            previous.maintainers.iter().collect::<HashSet<_>>()
+                != updated.maintainers.iter().collect::<HashSet<_>>(),
+            vec!["maintainers"],
+        ),
+        (
+            previous.state_ref_ignore != updated.state_ref_ignore,
+            vec!["state-ref-ignore"],
+        ),
+        (previous.mirrors != updated.mirrors, vec!["mirror"]),
+        (
+            previous.contributor_push != updated.contributor_push,
+            vec!["contributor-push"],
+        ),
+        (previous.blossoms != updated.blossoms, vec!["blossoms"]),
+        (previous.ci_bots != updated.ci_bots, vec!["ci-bots"]),
+        (
+            previous.submission_policy != updated.submission_policy,
+            vec!["submission-policy"],
+        ),
+    ]
+    .into_iter()
+    .filter(|(changed, _)| *changed)
+    .flat_map(|(_, names)| names)
+    .collect::<HashSet<&str>>();
+
+    let tag_name = |tag: &Tag| tag.as_slice().first().cloned().unwrap_or_default();
+    let updated_tags = updated.to_tags();
+    let mut replaced = HashSet::new();
+    let mut tags = vec![];
+    for tag in baseline.tags.iter() {
+        let name = tag_name(tag);
+        if !changed_tag_names.contains(name.as_str()) {
+            tags.push(tag.clone());
+        } else if replaced.insert(name.clone()) {
+            tags.extend(updated_tags.iter().filter(|t| tag_name(t) == name).cloned());
+        }
+    }
+    // changed fields the baseline had no tag for
+    tags.extend(updated_tags.into_iter().filter(|t| {
+        let name = tag_name(t);
+        changed_tag_names.contains(name.as_str()) && !replaced.contains(&name)
+    }));
+    tags
+}
+
+/// where a repository is hosted that changed between two of its
+/// announcements, eg. "repo relays changed: removed wss://relay.old, added
+/// wss://relay.new". reordering isn't a change
+pub fn describe_hosting_changes(previous: &RepoRef, updated: &RepoRef) -> Vec<String> {
+    let relays = |repo_ref: &RepoRef| {
+        repo_ref
+            .relays
+            .iter()
+            .map(|r| r.as_str_without_trailing_slash().to_string())
+            .collect::<Vec<String>>()
+    };
+    [
+        ("repo relays", relays(previous), relays(updated)),
+        (
+            "repo git servers",
+            previous.git_server.clone(),
+            updated.git_server.clone(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, previous, updated)| {
+        let removed: Vec<&str> = previous
+            .iter()
+            .filter(|s| !updated.contains(s))
+            .map(String::as_str)
+            .collect();
+        let added: Vec<&str> = updated
+            .iter()
+            .filter(|s| !previous.contains(s))
+            .map(String::as_str)
+            .collect();
+        let mut changes = vec![];
+        if !removed.is_empty() {
+            changes.push(format!("removed {}", removed.join(", ")));
+        }
+        if !added.is_empty() {
+            changes.push(format!("added {}", added.join(", ")));
+        }
+        (!changes.is_empty()).then(|| format!("{name} changed: {}", changes.join(", ")))
+    })
+    .collect()
+}
+
+/// git servers to skip when pushing. combines mirrors listed in the repo
+/// announcement with git config `nostr.push-skip-server`, which can be set
+/// more than once or be comma separated
+pub fn get_read_only_git_servers(git_repo: &Repo, repo_ref: &RepoRef) -> Result<Vec<String>> {
+    let mut skip = repo_ref.mirrors.clone();
+    let config = git_repo
+        .git_repo
+        .config()
+        .context("failed to open git config")?;
+    let mut entries = config
+        .multivar("nostr.push-skip-server", None)
+        .context("failed to read git config nostr.push-skip-server")?;
+    while let Some(entry) = entries.next() {
+        if let Some(value) = entry?.value() {
+            skip.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+    let normalise = |url: &str| url.trim_end_matches('/').to_string();
+    let skip = skip
+        .iter()
+        .map(|url| normalise(url))
+        .collect::<HashSet<String>>();
+    Ok(repo_ref
+        .git_server
+        .iter()
+        .filter(|url| skip.contains(&normalise(url)))
+        .cloned()
+        .collect())
+}
+
+/// lowercase ascii letters and digits separated by single hyphens, eg. "My
+/// Repo!" becomes "my-repo"
+pub fn slugify_identifier(s: &str) -> String {
+    s.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+/// the slugified repository name of a github clone url
+pub fn identifier_from_github_url(url: &str) -> Option<String> {
+    let path = [
+        "https://github.com/",
+        "http://github.com/",
+        "git@github.com:",
+        "ssh://git@github.com/",
+    ]
+    .iter()
+    .find_map(|prefix| url.strip_prefix(prefix))?;
+    let (_owner, name) = path.split_once('/')?;
+    let name = name.split('/').next().unwrap_or_default();
+    let identifier = slugify_identifier(name.strip_suffix(".git").unwrap_or(name));
+    if identifier.is_empty() {
+        None
+    } else {
+        Some(identifier)
+    }
+}
+
+/// the repository name of a github remote, origin first, otherwise the name
+/// of the repository directory
+pub fn get_default_identifier(git_repo: &Repo) -> String {
+    let mut remote_names = git_repo
+        .git_repo
+        .remotes()
+        .map(|remotes| {
+            remotes
+                .iter()
+                .flatten()
+                .map(str::to_string)
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    remote_names.sort_by_key(|name| name != "origin");
+    for name in remote_names {
+        if let Some(identifier) = git_repo
+            .git_repo
+            .find_remote(&name)
+            .ok()
+            .and_then(|remote| remote.url().and_then(identifier_from_github_url))
+        {
+            return identifier;
+        }
+    }
+    git_repo
+        .get_path()
+        .ok()
+        .and_then(|path| path.file_name())
+        .map(|name| slugify_identifier(&name.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+/// identifiers are used in nostr urls and grasp server paths so are limited
+/// to ascii letters, digits, '-', '_' and '.'
+pub fn validate_identifier(identifier: &str) -> Result<(), String> {
+    if identifier.is_empty() {
+        return Err("identifier cannot be empty".to_string());
+    }
+    if let Some(c) = identifier
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !['-', '_', '.'].contains(c))
+    {
+        return Err(format!(
+            "identifier cannot contain '{c}'. use letters, digits, '-', '_' or '.'"
+        ));
+    }
+    Ok(())
+}
+
 pub async fn get_repo_coordinates_when_remote_unknown(
     git_repo: &Repo,
+    remote: Option<&str>,
     #[cfg(test)] client: &crate::client::MockConnect,
     #[cfg(not(test))] client: &Client,
 ) -> Result<Coordinate> {
-    if let Ok(c) = try_and_get_repo_coordinates_when_remote_unknown(git_repo).await {
+    if remote.is_some() {
+        // an explicitly chosen remote must resolve rather than prompting
+        try_and_get_repo_coordinates_when_remote_unknown(git_repo, remote).await
+    } else if let Ok(c) = try_and_get_repo_coordinates_when_remote_unknown(git_repo, None).await {
         Ok(c)
     } else {
         get_repo_coordinate_from_user_prompt(git_repo, client).await
     }
 }
 
+/// git config item naming the nostr remote to use when several git remotes
+/// point at different nostr repositories
+pub static DEFAULT_REMOTE_GIT_CONFIG_ITEM: &str = "nostr.default-remote";
+
 pub async fn try_and_get_repo_coordinates_when_remote_unknown(
     git_repo: &Repo,
+    remote: Option<&str>,
 ) -> Result<Coordinate> {
     let remote_coordinates = get_repo_coordinates_from_nostr_remotes(git_repo).await?;
+    if let Some(remote) = remote {
+        return remote_coordinates
+            .get(remote)
+            .cloned()
+            .with_context(|| format!("\"{remote}\" is not a git remote with a nostr url"))
+            .category(NgitError::Config);
+    }
     if remote_coordinates.is_empty() {
         if let Ok(c) = get_repo_coordinates_from_git_config(git_repo) {
             Ok(c)
@@ -293,35 +763,50 @@ pub async fn try_and_get_repo_coordinates_when_remote_unknown(
     {
         Ok(remote_coordinates.values().next().unwrap().clone())
     } else {
+        if let Some(default_remote) =
+            git_repo.get_git_config_item(DEFAULT_REMOTE_GIT_CONFIG_ITEM, Some(false))?
+        {
+            if let Some(c) = remote_coordinates.get(&default_remote) {
+                return Ok(c.clone());
+            }
+            // git remote rename doesn't update our config item
+            eprintln!(
+                "warning: git config \"{DEFAULT_REMOTE_GIT_CONFIG_ITEM}\" is set to \"{default_remote}\" which is no longer a nostr git remote. it may have been renamed"
+            );
+        }
+        let mut remote_names = remote_coordinates.keys().cloned().collect::<Vec<String>>();
+        remote_names.sort();
         let choice_index = Interactor::default().choice(
             PromptChoiceParms::default()
                 .with_prompt("select nostr repository from those listed as git remotes")
+                .with_flag(format!("git config {DEFAULT_REMOTE_GIT_CONFIG_ITEM}"))
                 .with_default(0)
                 .with_choices(
-                    get_nostr_git_remote_selection_labels(git_repo, &remote_coordinates).await?,
+                    get_nostr_git_remote_selection_labels(
+                        git_repo,
+                        &remote_names,
+                        &remote_coordinates,
+                    )
+                    .await?,
                 ),
         )?;
-
-        Ok(remote_coordinates
-            .get(
-                remote_coordinates
-                    .keys()
-                    .cloned()
-                    .collect::<Vec<String>>()
-                    .get(choice_index)
-                    .unwrap(),
-            )
-            .unwrap()
-            .clone())
+        let remote_name = remote_names.get(choice_index).unwrap();
+        git_repo.save_git_config_item(DEFAULT_REMOTE_GIT_CONFIG_ITEM, remote_name, false)?;
+        eprintln!(
+            "saved \"{remote_name}\" as git config \"{DEFAULT_REMOTE_GIT_CONFIG_ITEM}\". use --remote to choose another"
+        );
+        Ok(remote_coordinates.get(remote_name).unwrap().clone())
     }
 }
 
 async fn get_nostr_git_remote_selection_labels(
     git_repo: &Repo,
+    remote_names: &[String],
     remote_coordinates: &HashMap<String, Coordinate>,
 ) -> Result<Vec<String>> {
     let mut res = vec![];
-    for (remote, c) in remote_coordinates {
+    for remote in remote_names {
+        let c = &remote_coordinates[remote];
         res.push(format!(
             "{remote} - {}/{}",
             get_user_details(&c.public_key, None, Some(git_repo.get_path()?), true, false)
@@ -343,7 +828,8 @@ fn get_repo_coordinates_from_git_config(git_repo: &Repo) -> Result<Coordinate> {
     .context("git config item \"nostr.repo\" is not an naddr")
 }
 
-async fn get_repo_coordinates_from_nostr_remotes(
+/// coordinates of each git remote with a nostr url, by remote name
+pub async fn get_repo_coordinates_from_nostr_remotes(
     git_repo: &Repo,
 ) -> Result<HashMap<String, Coordinate>> {
     let mut repo_coordinates = HashMap::new();
@@ -390,7 +876,7 @@ async fn get_repo_coordinate_from_user_prompt(
     // TODO: present list of events filter by root_commit
     // TODO: fallback to search based on identifier
     let dim = Style::new().color256(247);
-    println!(
+    eprintln!(
         "{}",
         dim.apply_to(
             "hint: https://gitworkshop.dev/repos lists repositories and their nostr address"
@@ -399,8 +885,11 @@ async fn get_repo_coordinate_from_user_prompt(
     let git_repo_path = git_repo.get_path()?;
     let coordinate = {
         loop {
-            let input = Interactor::default()
-                .input(PromptInputParms::default().with_prompt("nostr repository"))?;
+            let input = Interactor::default().input(
+                PromptInputParms::default()
+                    .with_prompt("nostr repository")
+                    .with_flag("git config nostr.repo"),
+            )?;
             let coordinate = if let Ok(c) = Coordinate::parse(&input) {
                 c
             } else if let Ok(nostr_url) =
@@ -476,7 +965,7 @@ pub struct RepoConfigYaml {
 }
 
 pub fn get_repo_config_from_yaml(git_repo: &Repo) -> Result<RepoConfigYaml> {
-    let path = git_repo.get_path()?.join("maintainers.yaml");
+    let path = git_repo.get_workdir()?.join("maintainers.yaml");
     let file = File::open(path)
         .context("should open maintainers.yaml if it exists")
         .context("maintainers.yaml doesnt exist")?;
@@ -505,7 +994,7 @@ pub fn save_repo_config_to_yaml(
     maintainers: Vec<PublicKey>,
     relays: Vec<String>,
 ) -> Result<()> {
-    let path = git_repo.get_path()?.join("maintainers.yaml");
+    let path = git_repo.get_workdir()?.join("maintainers.yaml");
     let file = if path.exists() {
         std::fs::OpenOptions::new()
             .create(true)
@@ -554,6 +1043,12 @@ mod tests {
             ],
             trusted_maintainer: TEST_KEY_1_KEYS.public_key(),
             maintainers: vec![TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key()],
+            state_ref_ignore: vec![],
+            mirrors: vec![],
+            contributor_push: vec![],
+            blossoms: vec![],
+            ci_bots: vec![],
+            submission_policy: SubmissionPolicy::default(),
             events: HashMap::new(),
             nostr_git_url: None,
         }
@@ -691,6 +1186,54 @@ mod tests {
                 vec![TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key()],
             )
         }
+
+        #[tokio::test]
+        async fn state_ref_ignore() {
+            let mut repo_ref = RepoRef::try_from((create().await, None)).unwrap();
+            assert!(repo_ref.state_ref_ignore.is_empty());
+            repo_ref.state_ref_ignore = vec!["refs/heads/ci/*".to_string()];
+            let event = repo_ref.to_event(&TEST_KEY_1_SIGNER).await.unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().state_ref_ignore,
+                vec!["refs/heads/ci/*".to_string()],
+            )
+        }
+
+        #[tokio::test]
+        async fn contributor_push() {
+            let mut repo_ref = RepoRef::try_from((create().await, None)).unwrap();
+            assert!(repo_ref.contributor_push.is_empty());
+            repo_ref.contributor_push = vec!["https://grasp.example/npub123/repo.git".to_string()];
+            let event = repo_ref.to_event(&TEST_KEY_1_SIGNER).await.unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().contributor_push,
+                vec!["https://grasp.example/npub123/repo.git".to_string()],
+            )
+        }
+
+        #[tokio::test]
+        async fn blossoms() {
+            let mut repo_ref = RepoRef::try_from((create().await, None)).unwrap();
+            assert!(repo_ref.blossoms.is_empty());
+            repo_ref.blossoms = vec!["https://blossom.example".to_string()];
+            let event = repo_ref.to_event(&TEST_KEY_1_SIGNER).await.unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().blossoms,
+                vec!["https://blossom.example".to_string()],
+            )
+        }
+
+        #[tokio::test]
+        async fn ci_bots() {
+            let mut repo_ref = RepoRef::try_from((create().await, None)).unwrap();
+            assert!(repo_ref.ci_bots.is_empty());
+            repo_ref.ci_bots = vec![TEST_KEY_2_KEYS.public_key()];
+            let event = repo_ref.to_event(&TEST_KEY_1_SIGNER).await.unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().ci_bots,
+                vec![TEST_KEY_2_KEYS.public_key()],
+            )
+        }
     }
 
     mod to_event {
@@ -807,4 +1350,318 @@ mod tests {
             }
         }
     }
+
+    mod describe_hosting_changes {
+        use super::*;
+
+        #[test]
+        fn lists_removed_and_added_relays_and_git_servers() {
+            let previous = RepoRef::try_from((generate_repo_ref_event(), None)).unwrap();
+            let updated = RepoRef {
+                relays: vec![
+                    RelayUrl::parse("ws://localhost:8055").unwrap(),
+                    RelayUrl::parse("wss://relay.new").unwrap(),
+                ],
+                git_server: vec!["https://git.new/test".to_string()],
+                ..previous.clone()
+            };
+            assert_eq!(describe_hosting_changes(&previous, &updated), vec![
+                "repo relays changed: removed ws://localhost:8056, added wss://relay.new",
+                "repo git servers changed: removed git:://123.gitexample.com/test, added https://git.new/test",
+            ]);
+        }
+
+        #[test]
+        fn reordering_isnt_a_change() {
+            let previous = RepoRef::try_from((generate_repo_ref_event(), None)).unwrap();
+            let updated = RepoRef {
+                relays: previous.relays.iter().rev().cloned().collect(),
+                ..previous.clone()
+            };
+            assert!(describe_hosting_changes(&previous, &updated).is_empty());
+        }
+    }
+
+    mod merge_announcement_tags {
+        use super::*;
+
+        fn tag_values(tags: &[Tag], name: &str) -> Vec<Vec<String>> {
+            tags.iter()
+                .filter(|t| t.as_slice()[0].eq(name))
+                .map(|t| t.as_slice().to_vec())
+                .collect()
+        }
+
+        #[test]
+        fn only_changed_field_tags_are_replaced() {
+            let baseline = generate_repo_ref_event();
+            let updated = RepoRef {
+                description: "updated description".to_string(),
+                ..RepoRef::try_from((baseline.clone(), None)).unwrap()
+            };
+            let tags = merge_announcement_tags(&baseline, &updated);
+            let expected = vec!["description".to_string(), "updated description".to_string()];
+            assert_eq!(tag_values(&tags, "description"), vec![expected]);
+            // left exactly as published, eg. the r tag keeps its lack of euc marker
+            for tag in baseline.tags.iter() {
+                assert!(tag.as_slice()[0].eq("description") || tags.contains(tag));
+            }
+            assert_eq!(tags.len(), baseline.tags.len());
+        }
+
+        #[test]
+        fn unrecognised_tags_are_kept() {
+            let baseline = generate_repo_ref_event();
+            let baseline = nostr::EventBuilder::new(Kind::GitRepoAnnouncement, "")
+                .tags(
+                    baseline
+                        .tags
+                        .iter()
+                        .cloned()
+                        .chain([Tag::hashtag("rust")])
+                        .collect::<Vec<Tag>>(),
+                )
+                .sign_with_keys(&TEST_KEY_1_KEYS)
+                .unwrap();
+            let updated = RepoRef {
+                name: "new name".to_string(),
+                ..RepoRef::try_from((baseline.clone(), None)).unwrap()
+            };
+            let tags = merge_announcement_tags(&baseline, &updated);
+            assert!(tags.contains(&Tag::hashtag("rust")));
+            let expected = vec!["alt".to_string(), "git repository: new name".to_string()];
+            assert_eq!(tag_values(&tags, "alt"), vec![expected]);
+        }
+
+        #[test]
+        fn reordered_maintainers_are_not_a_change() {
+            let baseline = generate_repo_ref_event();
+            let mut updated = RepoRef::try_from((baseline.clone(), None)).unwrap();
+            updated.maintainers.reverse();
+            let expected = baseline.tags.iter().cloned().collect::<Vec<Tag>>();
+            assert_eq!(merge_announcement_tags(&baseline, &updated), expected);
+        }
+
+        #[test]
+        fn changed_maintainers_are_replaced() {
+            let baseline = generate_repo_ref_event();
+            let updated = RepoRef {
+                maintainers: vec![TEST_KEY_1_KEYS.public_key()],
+                ..RepoRef::try_from((baseline.clone(), None)).unwrap()
+            };
+            let tags = merge_announcement_tags(&baseline, &updated);
+            let expected = vec![
+                "maintainers".to_string(),
+                TEST_KEY_1_KEYS.public_key().to_string(),
+            ];
+            assert_eq!(tag_values(&tags, "maintainers"), vec![expected]);
+        }
+    }
+
+    mod submission_policy {
+        use super::*;
+
+        fn values(values: &[&str]) -> Vec<String> {
+            values.iter().map(ToString::to_string).collect()
+        }
+
+        fn policy() -> SubmissionPolicy {
+            SubmissionPolicy {
+                target_branch: Some("develop".to_string()),
+                cover_letter_required: true,
+                max_patches: Some(20),
+            }
+        }
+
+        #[test]
+        fn parsed_from_and_written_to_tag_values() -> Result<()> {
+            let tag_values = values(&[
+                "target-branch=develop",
+                "cover-letter=required",
+                "max-patches=20",
+            ]);
+            assert_eq!(SubmissionPolicy::from_tag_values(&tag_values), policy());
+            assert_eq!(policy().to_tag_values(), tag_values);
+            assert_eq!(SubmissionPolicy::parse(&tag_values)?, policy());
+            Ok(())
+        }
+
+        #[test]
+        fn unknown_entries_ignored_in_announcements_but_rejected_by_parse() {
+            let tag_values = values(&["cover-letter=required", "signed-commits=required"]);
+            assert_eq!(
+                SubmissionPolicy::from_tag_values(&tag_values),
+                SubmissionPolicy {
+                    cover_letter_required: true,
+                    ..SubmissionPolicy::default()
+                }
+            );
+            assert!(SubmissionPolicy::parse(&tag_values).is_err());
+            assert!(SubmissionPolicy::parse(&values(&["max-patches=many"])).is_err());
+        }
+
+        #[test]
+        fn written_as_announcement_tag() -> Result<()> {
+            let repo_ref = RepoRef {
+                submission_policy: policy(),
+                ..RepoRef::try_from((generate_repo_ref_event(), None))?
+            };
+            let tags = repo_ref.to_tags();
+            assert!(tags.iter().any(|t| t.as_slice()
+                == [
+                    "submission-policy",
+                    "target-branch=develop",
+                    "cover-letter=required",
+                    "max-patches=20"
+                ]));
+            Ok(())
+        }
+
+        #[test]
+        fn describe_lists_each_requirement() {
+            assert_eq!(
+                policy().describe(),
+                "proposals must target develop, cover letter required, max 20 patches per proposal"
+            );
+        }
+
+        #[test]
+        fn violations_listed() {
+            assert_eq!(
+                policy().violations(&ProposalSubmission {
+                    builds_on_target: Some(false),
+                    has_cover_letter: false,
+                    patch_count: 21,
+                }),
+                vec![
+                    "proposals must build on a commit in 'develop'".to_string(),
+                    "a cover letter is required".to_string(),
+                    "proposals can have at most 20 patches but this has 21".to_string(),
+                ]
+            );
+        }
+
+        #[test]
+        fn no_violations_when_met_or_target_branch_unavailable() {
+            assert!(
+                policy()
+                    .violations(&ProposalSubmission {
+                        builds_on_target: None,
+                        has_cover_letter: true,
+                        patch_count: 20,
+                    })
+                    .is_empty()
+            );
+        }
+    }
+
+    mod get_read_only_git_servers {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[test]
+        fn combines_announcement_mirrors_and_git_config() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            git_repo.git_repo.config()?.set_multivar(
+                "nostr.push-skip-server",
+                "^$",
+                "https://b.io/repo.git/",
+            )?;
+            let repo_ref = RepoRef {
+                git_server: vec![
+                    "https://a.io/repo.git".to_string(),
+                    "https://b.io/repo.git".to_string(),
+                    "https://c.io/repo.git".to_string(),
+                ],
+                mirrors: vec!["https://c.io/repo.git".to_string()],
+                ..RepoRef::try_from((generate_repo_ref_event(), None))?
+            };
+            assert_eq!(
+                get_read_only_git_servers(&git_repo, &repo_ref)?,
+                vec![
+                    "https://b.io/repo.git".to_string(),
+                    "https://c.io/repo.git".to_string(),
+                ],
+            );
+            Ok(())
+        }
+    }
+
+    mod identifier_from_github_url {
+        use super::*;
+
+        #[test]
+        fn https_ssh_and_scp_style_urls() {
+            for url in [
+                "https://github.com/example/My-Repo.git",
+                "https://github.com/example/My-Repo",
+                "https://github.com/example/my_repo/",
+                "git@github.com:example/My-Repo.git",
+                "ssh://git@github.com/example/My-Repo.git",
+            ] {
+                assert_eq!(
+                    identifier_from_github_url(url),
+                    Some("my-repo".to_string()),
+                    "{url}"
+                );
+            }
+        }
+
+        #[test]
+        fn none_for_other_hosts_or_without_repo_name() {
+            for url in [
+                "https://gitlab.com/example/my-repo.git",
+                "https://github.com/example",
+                "nostr://npub123/my-repo",
+            ] {
+                assert_eq!(identifier_from_github_url(url), None, "{url}");
+            }
+        }
+    }
+
+    mod get_default_identifier {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[test]
+        fn github_remote_name_preferred_over_directory_name() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.add_remote("upstream", "https://gitlab.com/example/other.git")?;
+            test_repo.add_remote("origin", "https://github.com/example/My-Repo.git")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            assert_eq!(get_default_identifier(&git_repo), "my-repo");
+            Ok(())
+        }
+
+        #[test]
+        fn directory_name_slug_without_github_remote() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            assert_eq!(
+                get_default_identifier(&git_repo),
+                slugify_identifier(&test_repo.dir.file_name().unwrap().to_string_lossy())
+            );
+            Ok(())
+        }
+    }
+
+    mod validate_identifier {
+        use super::*;
+
+        #[test]
+        fn letters_digits_hyphens_underscores_and_dots_allowed() {
+            assert_eq!(validate_identifier("my-repo_v2.0"), Ok(()));
+        }
+
+        #[test]
+        fn spaces_slashes_and_empty_rejected() {
+            for identifier in ["my repo", "example/my-repo", ""] {
+                assert!(validate_identifier(identifier).is_err(), "{identifier}");
+            }
+        }
+    }
 }
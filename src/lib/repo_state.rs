@@ -2,6 +2,12 @@ use std::collections::HashMap;
 
 use anyhow::{Context, Result};
 use git2::Oid;
+use nostr::{Timestamp, hashes::sha1::Hash as Sha1Hash};
+
+use crate::{
+    git::{Repo, RepoActions, proposal_notes::PROPOSAL_NOTES_REF, str_to_sha1},
+    repo_ref::RepoRef,
+};
 
 pub struct RepoState {
     pub identifier: String,
@@ -16,7 +22,7 @@ impl RepoState {
         let mut state = HashMap::new();
         for tag in event.tags.iter() {
             if let Some(name) = tag.as_slice().first() {
-                if ["refs/heads/", "refs/tags", "HEAD"]
+                if ["refs/heads/", "refs/tags", "refs/notes/", "HEAD"]
                     .iter()
                     .any(|s| name.starts_with(*s))
                 {
@@ -39,3 +45,370 @@ impl RepoState {
         })
     }
 }
+
+/// created_at for a new state event. relays keep the state event with the
+/// newest created_at, and the lowest id when they tie, so it must be later
+/// than the latest one published, even by a push earlier in the same second
+pub fn next_state_created_at(latest: Option<Timestamp>, now: Timestamp) -> Timestamp {
+    match latest {
+        Some(latest) if latest >= now => Timestamp::from(latest.as_u64() + 1),
+        _ => now,
+    }
+}
+
+/// the state event relays keep out of several from the same maintainer
+pub fn current_state_event(state_events: &[nostr::Event]) -> Option<&nostr::Event> {
+    state_events
+        .iter()
+        .max_by(|a, b| a.created_at.cmp(&b.created_at).then(b.id.cmp(&a.id)))
+}
+
+/// drop symbolic refs, such as HEAD, that point at a branch missing from
+/// `state` and, when there is no HEAD, point it at main or master if either
+/// exists. a repository with no commits yet has no HEAD to advertise
+pub fn point_head_at_existing_branch(state: &mut HashMap<String, String>) {
+    let names = state.keys().cloned().collect::<Vec<String>>();
+    state.retain(|_, value| {
+        value
+            .strip_prefix("ref: ")
+            .is_none_or(|target| names.iter().any(|name| name == target))
+    });
+    if !state.contains_key("HEAD") {
+        if let Some(branch) = ["refs/heads/main", "refs/heads/master"]
+            .into_iter()
+            .find(|branch| state.contains_key(*branch))
+        {
+            state.insert("HEAD".to_string(), format!("ref: {branch}"));
+        }
+    }
+}
+
+/// gitignore-style patterns for refs left out of the state event. combines
+/// git config `nostr.state-ref-ignore` (comma separated) with patterns
+/// published in the repo announcement
+pub fn get_state_ref_ignore_patterns(git_repo: &Repo, repo_ref: &RepoRef) -> Result<Vec<String>> {
+    let mut patterns = repo_ref.state_ref_ignore.clone();
+    if let Some(config) = git_repo.get_git_config_item("nostr.state-ref-ignore", None)? {
+        for pattern in config.split(',').map(str::trim) {
+            if !pattern.is_empty() && !patterns.iter().any(|p| p == pattern) {
+                patterns.push(pattern.to_string());
+            }
+        }
+    }
+    Ok(patterns)
+}
+
+/// true if `ref_name`, or a directory containing it, matches a pattern. see
+/// [`matches_gitignore_pattern`]. [`PROPOSAL_NOTES_REF`] is always ignored as
+/// it is generated locally from proposals rather than pushed
+pub fn is_state_ref_ignored(ref_name: &str, patterns: &[String]) -> bool {
+    let ref_name = ref_name.strip_suffix("^{}").unwrap_or(ref_name);
+    ref_name == PROPOSAL_NOTES_REF
+        || patterns
+            .iter()
+            .any(|pattern| matches_gitignore_pattern(pattern, ref_name))
+}
+
+/// true if `path`, or a directory containing it, matches `pattern`. as in
+/// gitignore `*` and `?` don't match `/`, `**` matches anything and a pattern
+/// without a `/`, other than a trailing one, matches at any depth
+pub(crate) fn matches_gitignore_pattern(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    if pattern.contains('/') {
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        // match the path or any of its parent directories
+        path.match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .chain([path])
+            .any(|path| glob_match(pattern, path))
+    } else {
+        path.split('/').any(|part| glob_match(pattern, part))
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .any(|i| glob_match(rest, &text[i..]));
+    }
+    if let Some(rest) = pattern.strip_prefix('*') {
+        return text
+            .char_indices()
+            .take_while(|(_, c)| *c != '/')
+            .map(|(i, _)| i)
+            .chain([text.find('/').unwrap_or(text.len())])
+            .any(|i| glob_match(rest, &text[i..]));
+    }
+    match (pattern.chars().next(), text.chars().next()) {
+        (None, None) => true,
+        (Some('?'), Some(c)) if c != '/' => glob_match(&pattern[1..], &text[c.len_utf8()..]),
+        (Some(p), Some(c)) if p == c => glob_match(&pattern[p.len_utf8()..], &text[c.len_utf8()..]),
+        _ => false,
+    }
+}
+
+/// a branch whose nostr state moved to a commit that doesn't build on the tip
+/// last fetched from `remote`, ie. a maintainer force pushed it
+pub struct RewoundBranch {
+    pub remote: String,
+    pub branch: String,
+    pub old_tip: Sha1Hash,
+    pub new_tip: Sha1Hash,
+}
+
+impl RewoundBranch {
+    /// explanation and recovery commands, one per line
+    pub fn guidance(&self) -> Vec<String> {
+        let (remote, branch) = (&self.remote, &self.branch);
+        let old_tip = &self.old_tip.to_string()[..7];
+        let new_tip = &self.new_tip.to_string()[..7];
+        vec![
+            format!(
+                "{remote}/{branch} was force pushed on nostr. it moved from {old_tip} to {new_tip}, which doesn't build on {old_tip}"
+            ),
+            format!(
+                "  to drop your local commits on {branch}: git fetch {remote} && git reset --hard {remote}/{branch}"
+            ),
+            format!(
+                "  to keep them: git fetch {remote} && git rebase --onto {remote}/{branch} {old_tip} {branch}"
+            ),
+        ]
+    }
+}
+
+/// branches in `state` that were force pushed since they were last fetched
+/// into `refs/remotes/<remote>/`. tips not yet fetched are skipped as their
+/// ancestry is unknown
+pub fn find_rewound_branches(
+    git_repo: &Repo,
+    remote: &str,
+    state: &HashMap<String, String>,
+) -> Vec<RewoundBranch> {
+    let mut rewound = state
+        .iter()
+        .filter_map(|(name, value)| {
+            let branch = name.strip_prefix("refs/heads/")?;
+            if branch.starts_with("pr/") {
+                return None;
+            }
+            let new_tip = str_to_sha1(value).ok()?;
+            let old_tip = git_repo
+                .get_commit_or_tip_of_reference(&format!("refs/remotes/{remote}/{branch}"))
+                .ok()?;
+            if old_tip == new_tip
+                || !git_repo.does_commit_exist(value).is_ok_and(|exists| exists)
+                || git_repo
+                    .ancestor_of(&new_tip, &old_tip)
+                    .is_ok_and(|builds_on| builds_on)
+            {
+                return None;
+            }
+            Some(RewoundBranch {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+                old_tip,
+                new_tip,
+            })
+        })
+        .collect::<Vec<RewoundBranch>>();
+    rewound.sort_by(|a, b| a.branch.cmp(&b.branch));
+    rewound
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+    use crate::client::STATE_KIND;
+
+    mod try_from {
+        use nostr::{Tag, TagKind};
+
+        use super::*;
+
+        #[test]
+        fn notes_refs_kept_alongside_branches_and_tags() -> Result<()> {
+            let oid = "9ee507fc4357d7ee16a5d8901bedcd103f23c17d";
+            let event = EventBuilder::new(STATE_KIND, "")
+                .tags(
+                    [
+                        "refs/heads/main",
+                        "refs/tags/v1",
+                        "refs/notes/commits",
+                        "refs/other/x",
+                    ]
+                    .map(|name| Tag::custom(TagKind::Custom(name.into()), [oid]))
+                    .into_iter()
+                    .chain([Tag::identifier("example")]),
+                )
+                .sign_with_keys(&Keys::generate())?;
+            let mut names = RepoState::try_from(vec![event])?
+                .state
+                .into_keys()
+                .collect::<Vec<String>>();
+            names.sort();
+            assert_eq!(
+                names,
+                vec!["refs/heads/main", "refs/notes/commits", "refs/tags/v1"]
+            );
+            Ok(())
+        }
+    }
+
+    mod next_state_created_at {
+        use super::*;
+
+        #[test]
+        fn now_when_latest_is_older() {
+            assert_eq!(
+                next_state_created_at(Some(Timestamp::from(99)), Timestamp::from(100)),
+                Timestamp::from(100)
+            );
+        }
+
+        #[test]
+        fn bumped_past_latest_published_in_same_second() {
+            assert_eq!(
+                next_state_created_at(Some(Timestamp::from(100)), Timestamp::from(100)),
+                Timestamp::from(101)
+            );
+        }
+
+        #[test]
+        fn bumped_past_latest_from_clock_ahead() {
+            assert_eq!(
+                next_state_created_at(Some(Timestamp::from(105)), Timestamp::from(100)),
+                Timestamp::from(106)
+            );
+        }
+    }
+
+    mod current_state_event {
+        use super::*;
+
+        fn state_event(keys: &Keys, created_at: u64, content: &str) -> nostr::Event {
+            EventBuilder::new(STATE_KIND, content)
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(keys)
+                .unwrap()
+        }
+
+        #[test]
+        fn newest_wins() {
+            let keys = Keys::generate();
+            let events = vec![state_event(&keys, 101, ""), state_event(&keys, 100, "")];
+            assert_eq!(current_state_event(&events), Some(&events[0]));
+        }
+
+        #[test]
+        fn lowest_id_wins_tie() {
+            let keys = Keys::generate();
+            let events = vec![state_event(&keys, 100, "a"), state_event(&keys, 100, "b")];
+            assert_eq!(
+                current_state_event(&events).map(|e| e.id),
+                events.iter().map(|e| e.id).min()
+            );
+        }
+    }
+
+    mod is_state_ref_ignored {
+        use super::*;
+
+        fn patterns() -> Vec<String> {
+            vec!["refs/heads/ci/*".to_string(), "tmp-*".to_string()]
+        }
+
+        #[test]
+        fn matches_glob() {
+            assert!(is_state_ref_ignored("refs/heads/ci/build-1", &patterns()));
+        }
+
+        #[test]
+        fn matches_refs_within_ignored_directory() {
+            assert!(is_state_ref_ignored(
+                "refs/heads/ci/jobs/build-1",
+                &patterns()
+            ));
+        }
+
+        #[test]
+        fn pattern_without_slash_matches_at_any_depth() {
+            assert!(is_state_ref_ignored(
+                "refs/heads/feature/tmp-1",
+                &patterns()
+            ));
+            assert!(is_state_ref_ignored("refs/tags/tmp-1^{}", &patterns()));
+        }
+
+        #[test]
+        fn star_does_not_cross_slash() {
+            let patterns = vec!["refs/heads/*-ci".to_string()];
+            assert!(is_state_ref_ignored("refs/heads/nightly-ci", &patterns));
+            assert!(!is_state_ref_ignored("refs/heads/x/nightly-ci", &patterns));
+        }
+
+        #[test]
+        fn double_star_crosses_slash() {
+            let patterns = vec!["refs/**/ci".to_string()];
+            assert!(is_state_ref_ignored("refs/heads/x/ci", &patterns));
+        }
+
+        #[test]
+        fn proposal_notes_ref_always_ignored() {
+            assert!(is_state_ref_ignored("refs/notes/nostr", &[]));
+            assert!(!is_state_ref_ignored("refs/notes/commits", &[]));
+        }
+
+        #[test]
+        fn other_refs_are_kept() {
+            assert!(!is_state_ref_ignored("refs/heads/main", &patterns()));
+            assert!(!is_state_ref_ignored("refs/heads/cid", &patterns()));
+            assert!(!is_state_ref_ignored("HEAD", &patterns()));
+        }
+    }
+
+    mod point_head_at_existing_branch {
+        use super::*;
+
+        fn state(refs: &[(&str, &str)]) -> HashMap<String, String> {
+            refs.iter()
+                .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                .collect()
+        }
+
+        #[test]
+        fn empty_state_has_no_head() {
+            let mut empty = state(&[("HEAD", "ref: refs/heads/main")]);
+            point_head_at_existing_branch(&mut empty);
+            assert!(empty.is_empty());
+        }
+
+        #[test]
+        fn head_added_once_main_exists() {
+            let oid = "431b84edc0d2fa118d63faa3c2db9c73d630a5ae";
+            let mut pushed = state(&[("refs/heads/main", oid)]);
+            point_head_at_existing_branch(&mut pushed);
+            assert_eq!(
+                pushed,
+                state(&[("refs/heads/main", oid), ("HEAD", "ref: refs/heads/main")])
+            );
+        }
+
+        #[test]
+        fn existing_head_kept() {
+            let oid = "431b84edc0d2fa118d63faa3c2db9c73d630a5ae";
+            let mut existing = state(&[
+                ("refs/heads/main", oid),
+                ("refs/heads/dev", oid),
+                ("HEAD", "ref: refs/heads/dev"),
+            ]);
+            let expected = existing.clone();
+            point_head_at_existing_branch(&mut existing);
+            assert_eq!(existing, expected);
+        }
+    }
+}
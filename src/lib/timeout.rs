@@ -0,0 +1,246 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    error::{ErrorCategory, NgitError, exit_code},
+    git::{Repo, RepoActions},
+};
+
+/// time allowed after the deadline for blocking git operations, which the
+/// runtime can't interrupt, before the process is exited regardless
+static BACKSTOP_GRACE_SECS: u64 = 1;
+
+/// time allowed after ctrl-c for the command to stop before the process is
+/// exited regardless, eg. when blocked on a prompt or a git operation
+static CANCEL_GRACE_SECS: u64 = 1;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+static RELAYS_ASKED: AtomicUsize = AtomicUsize::new(0);
+static RELAYS_ANSWERED: AtomicUsize = AtomicUsize::new(0);
+static GIT_SERVERS_ASKED: AtomicUsize = AtomicUsize::new(0);
+static GIT_SERVERS_ANSWERED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn relay_asked() {
+    RELAYS_ASKED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn relay_answered() {
+    RELAYS_ANSWERED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn git_server_asked() {
+    GIT_SERVERS_ASKED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn git_server_answered() {
+    GIT_SERVERS_ANSWERED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// network requests made so far, reported when the deadline is reached
+#[derive(Debug, Default)]
+struct Progress {
+    relays_asked: usize,
+    relays_answered: usize,
+    git_servers_asked: usize,
+    git_servers_answered: usize,
+}
+
+impl Progress {
+    fn current() -> Self {
+        Self {
+            relays_asked: RELAYS_ASKED.load(Ordering::Relaxed),
+            relays_answered: RELAYS_ANSWERED.load(Ordering::Relaxed),
+            git_servers_asked: GIT_SERVERS_ASKED.load(Ordering::Relaxed),
+            git_servers_answered: GIT_SERVERS_ANSWERED.load(Ordering::Relaxed),
+        }
+    }
+
+    fn timed_out_message(&self, secs: u64) -> String {
+        let mut completed = vec![];
+        if self.relays_asked > 0 {
+            completed.push(format!(
+                "{}/{} relays answered",
+                self.relays_answered, self.relays_asked
+            ));
+        }
+        if self.git_servers_answered < self.git_servers_asked {
+            completed.push("git server list incomplete".to_string());
+        }
+        if completed.is_empty() {
+            format!("timed out after {secs}s")
+        } else {
+            format!("timed out after {secs}s: {}", completed.join(", "))
+        }
+    }
+
+    fn cancelled_message(&self, secs: u64) -> String {
+        if self.relays_answered > 0 {
+            format!("cancelled after {secs}s — partial results cached")
+        } else {
+            format!("cancelled after {secs}s")
+        }
+    }
+}
+
+fn timed_out_error(secs: u64) -> anyhow::Error {
+    NgitError::Network(anyhow!(Progress::current().timed_out_message(secs))).into()
+}
+
+/// run `launch` until it completes or `secs` have passed. outstanding network
+/// requests are dropped and a report of what completed is returned as a
+/// network error
+pub async fn with_timeout<F>(secs: Option<u64>, launch: F) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let Some(secs) = secs else {
+        return launch.await;
+    };
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(secs + BACKSTOP_GRACE_SECS));
+        let error = timed_out_error(secs);
+        eprintln!("Error: {error:?}");
+        std::process::exit(exit_code(&error).into());
+    });
+    tokio::time::timeout(Duration::from_secs(secs), launch)
+        .await
+        .unwrap_or_else(|_| Err(timed_out_error(secs)))
+}
+
+fn cancelled_error(started: Instant) -> anyhow::Error {
+    NgitError::UserAbort(anyhow!(
+        Progress::current().cancelled_message(started.elapsed().as_secs())
+    ))
+    .into()
+}
+
+/// run `launch` until it completes or ctrl-c is pressed. on ctrl-c `launch`
+/// is dropped, aborting outstanding network requests and clearing spinners,
+/// and a user abort is returned. cache files are written to a temporary file
+/// and renamed into place, and lmdb writes are transactions, so are either
+/// completed or never made. if `launch` can't be
+/// dropped in time, or ctrl-c is pressed again, the process exits regardless
+pub async fn with_cancellation<F>(launch: F) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let started = Instant::now();
+    let (cancel, cancelled) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        let _ = cancel.send(());
+        tokio::select! {
+            () = tokio::time::sleep(Duration::from_secs(CANCEL_GRACE_SECS)) => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+        if !CANCELLED.load(Ordering::Relaxed) {
+            // prompts hide the cursor while they are shown
+            let _ = console::Term::stderr().show_cursor();
+            let error = cancelled_error(started);
+            eprintln!("\nError: {error:?}");
+            std::process::exit(exit_code(&error).into());
+        }
+    });
+    tokio::select! {
+        result = launch => result,
+        Ok(()) = cancelled => {
+            CANCELLED.store(true, Ordering::Relaxed);
+            Err(cancelled_error(started))
+        }
+    }
+}
+
+/// the remote helper can't be passed `--timeout` as git controls its argv
+pub fn get_timeout_from_git_config(git_repo: &Repo) -> Result<Option<u64>> {
+    git_repo
+        .get_git_config_item("nostr.timeout", None)?
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .context(format!(
+                    "git config item nostr.timeout should be a whole number of seconds but is '{value}'"
+                ))
+        })
+        .transpose()
+        .category(NgitError::Config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod timed_out_message {
+        use super::*;
+
+        #[test]
+        fn reports_relays_answered_and_incomplete_git_server_list() {
+            assert_eq!(
+                Progress {
+                    relays_asked: 4,
+                    relays_answered: 2,
+                    git_servers_asked: 1,
+                    git_servers_answered: 0,
+                }
+                .timed_out_message(30),
+                "timed out after 30s: 2/4 relays answered, git server list incomplete"
+            );
+        }
+
+        #[test]
+        fn git_server_list_omitted_when_complete() {
+            assert_eq!(
+                Progress {
+                    relays_asked: 2,
+                    relays_answered: 1,
+                    git_servers_asked: 1,
+                    git_servers_answered: 1,
+                }
+                .timed_out_message(5),
+                "timed out after 5s: 1/2 relays answered"
+            );
+        }
+
+        #[test]
+        fn nothing_asked() {
+            assert_eq!(
+                Progress::default().timed_out_message(5),
+                "timed out after 5s"
+            );
+        }
+    }
+
+    mod cancelled_message {
+        use super::*;
+
+        #[test]
+        fn reports_partial_results_cached_when_relays_answered() {
+            assert_eq!(
+                Progress {
+                    relays_asked: 3,
+                    relays_answered: 1,
+                    ..Progress::default()
+                }
+                .cancelled_message(14),
+                "cancelled after 14s — partial results cached"
+            );
+        }
+
+        #[test]
+        fn nothing_answered() {
+            assert_eq!(
+                Progress::default().cancelled_message(2),
+                "cancelled after 2s"
+            );
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, ensure};
+
+/// proposal page used unless `nostr.web-viewer-url` is set
+pub static DEFAULT_WEB_VIEWER_URL: &str = "https://gitworkshop.dev/repo/{naddr}/proposal/{nevent}";
+
+/// launches a url in a browser
+pub type Opener = fn(&str) -> Result<()>;
+
+/// fill the `{naddr}` and `{nevent}` placeholders of a web viewer url template
+pub fn proposal_web_url(template: &str, naddr: &str, nevent: &str) -> String {
+    template
+        .replace("{naddr}", naddr)
+        .replace("{nevent}", nevent)
+}
+
+/// the configured viewer's proposal page followed by the pages in the
+/// repository announcement's `web` tag, which are filled in when they are
+/// templates themselves
+pub fn proposal_web_urls(template: &str, web: &[String], naddr: &str, nevent: &str) -> Vec<String> {
+    let mut urls = vec![proposal_web_url(template, naddr, nevent)];
+    for url in web.iter().map(|w| proposal_web_url(w, naddr, nevent)) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+fn launch_system_opener(url: &str) -> Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("open", &[])
+    } else if cfg!(windows) {
+        ("cmd", &["/C", "start", ""])
+    } else {
+        ("xdg-open", &[])
+    };
+    let status = Command::new(program)
+        .args(args)
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context(format!("failed to run {program}"))?;
+    ensure!(status.success(), "{program} exited with {status}");
+    Ok(())
+}
+
+/// the platform's url opener, eg. `xdg-open`. none during tests or in ssh
+/// sessions without a display, where a browser can't be shown to the user
+pub fn system_opener() -> Option<Opener> {
+    if std::env::var("NGITTEST").is_ok() {
+        return None;
+    }
+    let over_ssh =
+        std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some();
+    let has_display = cfg!(any(target_os = "macos", windows))
+        || std::env::var_os("DISPLAY").is_some()
+        || std::env::var_os("WAYLAND_DISPLAY").is_some();
+    if over_ssh && !has_display {
+        None
+    } else {
+        Some(launch_system_opener)
+    }
+}
+
+/// open `url` with `opener`, returning the line to print. the url is always
+/// included so it can be opened by hand when there is no opener or it fails
+pub fn open_url(url: &str, opener: Option<Opener>) -> String {
+    match opener.map(|open| open(url)) {
+        Some(Ok(())) => format!("opened {url}"),
+        Some(Err(error)) => format!("failed to open browser: {error}\n{url}"),
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+
+    use super::*;
+
+    mod proposal_web_urls {
+        use super::*;
+
+        #[test]
+        fn default_viewer_is_gitworkshop() {
+            assert_eq!(
+                proposal_web_urls(DEFAULT_WEB_VIEWER_URL, &[], "naddr1abc", "nevent1def"),
+                vec!["https://gitworkshop.dev/repo/naddr1abc/proposal/nevent1def".to_string()],
+            );
+        }
+
+        #[test]
+        fn custom_template_placeholders_filled() {
+            assert_eq!(
+                proposal_web_urls(
+                    "https://viewer.example/{naddr}/pr/{nevent}",
+                    &[],
+                    "naddr1abc",
+                    "nevent1def"
+                ),
+                vec!["https://viewer.example/naddr1abc/pr/nevent1def".to_string()],
+            );
+        }
+
+        #[test]
+        fn announced_web_pages_offered_after_viewer_without_duplicates() {
+            assert_eq!(
+                proposal_web_urls(
+                    DEFAULT_WEB_VIEWER_URL,
+                    &[
+                        "https://exampleproject.xyz".to_string(),
+                        DEFAULT_WEB_VIEWER_URL.to_string(),
+                        "https://other.viewer/{nevent}".to_string(),
+                    ],
+                    "naddr1abc",
+                    "nevent1def"
+                ),
+                vec![
+                    "https://gitworkshop.dev/repo/naddr1abc/proposal/nevent1def".to_string(),
+                    "https://exampleproject.xyz".to_string(),
+                    "https://other.viewer/nevent1def".to_string(),
+                ],
+            );
+        }
+    }
+
+    mod open_url {
+        use super::*;
+
+        #[test]
+        fn reports_opened_url() {
+            assert_eq!(
+                open_url("https://a.viewer", Some(|_| Ok(()))),
+                "opened https://a.viewer"
+            );
+        }
+
+        #[test]
+        fn prints_url_without_opener() {
+            assert_eq!(open_url("https://a.viewer", None), "https://a.viewer");
+        }
+
+        #[test]
+        fn prints_url_when_opener_fails() {
+            assert_eq!(
+                open_url("https://a.viewer", Some(|_| bail!("xdg-open not found"))),
+                "failed to open browser: xdg-open not found\nhttps://a.viewer"
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// an upload received by [`BlossomServer`]
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// a tiny blossom-like media server. `PUT /upload` stores the body and
+/// responds with a blob descriptor and `GET /<sha256>` serves it back
+pub struct BlossomServer {
+    pub url: String,
+    pub uploads: Arc<Mutex<Vec<Upload>>>,
+    tamper: Arc<AtomicBool>,
+}
+
+impl BlossomServer {
+    /// listens on a free port until the test process exits
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let uploads = Arc::new(Mutex::new(vec![]));
+        let tamper = Arc::new(AtomicBool::new(false));
+        let blobs: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::new(Mutex::new(HashMap::new()));
+        {
+            let (url, uploads, tamper) = (url.clone(), uploads.clone(), tamper.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = respond(stream, &url, &uploads, &blobs, &tamper);
+                }
+            });
+        }
+        Ok(Self {
+            url,
+            uploads,
+            tamper,
+        })
+    }
+
+    /// serve different bytes than were uploaded from now on
+    pub fn tamper_with_blobs(&self) {
+        self.tamper.store(true, Ordering::Relaxed);
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    url: &str,
+    uploads: &Mutex<Vec<Upload>>,
+    blobs: &Mutex<HashMap<String, Vec<u8>>>,
+    tamper: &AtomicBool,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content) = match (parts.next(), parts.next()) {
+        (Some("PUT"), Some("/upload")) => {
+            let sha256 = format!("{:x}", Sha256::digest(&body));
+            uploads.lock().unwrap().push(Upload {
+                authorization: headers.get("authorization").cloned(),
+                body: body.clone(),
+            });
+            blobs.lock().unwrap().insert(sha256.clone(), body);
+            (
+                "200 OK",
+                format!(r#"{{"url":"{url}/{sha256}","sha256":"{sha256}"}}"#).into_bytes(),
+            )
+        }
+        (Some("GET"), Some(path)) => {
+            match blobs.lock().unwrap().get(path.trim_start_matches('/')) {
+                Some(_) if tamper.load(Ordering::Relaxed) => ("200 OK", b"tampered".to_vec()),
+                Some(blob) => ("200 OK", blob.clone()),
+                None => ("404 Not Found", vec![]),
+            }
+        }
+        _ => ("400 Bad Request", vec![]),
+    };
+    stream.write_all(
+        format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            content.len()
+        )
+        .as_bytes(),
+    )?;
+    stream.write_all(&content).context("failed to respond")?;
+    stream.flush()?;
+    Ok(())
+}
@@ -73,6 +73,13 @@ impl GitTestRepo {
         })
     }
 
+    /// linked worktree of this repository, on a new branch `name` from HEAD
+    pub fn add_worktree(&self, name: &str) -> Result<Self> {
+        let path = current_dir()?.join(format!("tmpgit-worktree-{}", rand::random::<u64>()));
+        self.git_repo.worktree(name, &path, None)?;
+        Self::open(&path)
+    }
+
     pub fn duplicate(existing_repo: &GitTestRepo) -> Result<Self> {
         let path = current_dir()?.join(format!("tmpgit-{}", rand::random::<u64>()));
         // function source: https://stackoverflow.com/a/65192210
@@ -101,8 +108,8 @@ impl GitTestRepo {
         })
     }
 
-    pub fn recreate_as_bare(existing_repo: &GitTestRepo) -> Result<Self> {
-        // create bare repo
+    /// bare repository with no commits, like a git server before first push
+    pub fn new_bare() -> Result<Self> {
         let path = current_dir()?.join(format!("tmpgit-{}", rand::random::<u64>()));
         let git_repo = git2::Repository::init_opts(
             &path,
@@ -111,10 +118,21 @@ impl GitTestRepo {
                 .bare(true)
                 .mkpath(true),
         )?;
+        Ok(Self {
+            dir: path,
+            git_repo,
+            delete_dir_on_drop: true,
+        })
+    }
+
+    pub fn recreate_as_bare(existing_repo: &GitTestRepo) -> Result<Self> {
+        let bare_repo = Self::new_bare()?;
         // clone existing to a temp repo
         let tmp_repo = Self::duplicate(existing_repo)?;
         // add bare as a remote and push branches
-        let mut remote = tmp_repo.git_repo.remote("tmp", path.to_str().unwrap())?;
+        let mut remote = tmp_repo
+            .git_repo
+            .remote("tmp", bare_repo.dir.to_str().unwrap())?;
         let refspecs = tmp_repo
             .git_repo
             .branches(Some(git2::BranchType::Local))?
@@ -129,11 +147,7 @@ impl GitTestRepo {
             .collect::<Vec<String>>();
         remote.push(&refspecs, None)?;
         // TODO: push tags
-        Ok(Self {
-            dir: path,
-            git_repo,
-            delete_dir_on_drop: true,
-        })
+        Ok(bare_repo)
     }
 
     pub fn clone_repo(existing_repo: &GitTestRepo) -> Result<Self> {
@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+/// serves a git repository over the smart http protocol by running `git
+/// http-backend` for each request, so both libgit2 and system git can fetch
+/// from and push to it
+pub struct GitHttpServer {
+    pub url: String,
+}
+
+impl GitHttpServer {
+    /// listens on a free port until the test process exits
+    pub fn start(repo_dir: &Path) -> Result<Self> {
+        Self::serve(repo_dir, false)
+    }
+
+    /// drops connections from libgit2 without responding, as a proxy that
+    /// mangles libgit2's http requests would, while serving system git
+    pub fn start_dropping_libgit2(repo_dir: &Path) -> Result<Self> {
+        Self::serve(repo_dir, true)
+    }
+
+    fn serve(repo_dir: &Path, drop_libgit2: bool) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let project_root = repo_dir
+            .parent()
+            .context("repository has no parent directory")?
+            .to_path_buf();
+        let url = format!(
+            "http://{}/{}",
+            listener.local_addr()?,
+            repo_dir
+                .file_name()
+                .context("repository has no directory name")?
+                .to_string_lossy()
+        );
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = respond(stream, &project_root, drop_libgit2);
+            }
+        });
+        Ok(Self { url })
+    }
+}
+
+fn respond(mut stream: TcpStream, project_root: &Path, drop_libgit2: bool) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    if drop_libgit2
+        && headers
+            .get("user-agent")
+            .is_some_and(|agent| agent.contains("libgit2"))
+    {
+        return stream
+            .shutdown(Shutdown::Both)
+            .context("failed to drop connection");
+    }
+    if headers
+        .get("expect")
+        .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+    {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    let body = if headers
+        .get("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        read_chunked(&mut reader)?
+    } else {
+        let length = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut command = Command::new("git");
+    command
+        .arg("http-backend")
+        .env_remove("GIT_DIR")
+        .env("GIT_PROJECT_ROOT", project_root)
+        .env("GIT_HTTP_EXPORT_ALL", "1")
+        // fetching by oid over protocol v0
+        .env("GIT_CONFIG_COUNT", "1")
+        .env("GIT_CONFIG_KEY_0", "uploadpack.allowAnySHA1InWant")
+        .env("GIT_CONFIG_VALUE_0", "true")
+        // enables receive-pack for pushes
+        .env("REMOTE_USER", "test")
+        .env("REMOTE_ADDR", "127.0.0.1")
+        .env("REQUEST_METHOD", method)
+        .env("PATH_INFO", path)
+        .env("QUERY_STRING", query)
+        .env("CONTENT_LENGTH", body.len().to_string());
+    for (header, var) in [
+        ("content-type", "CONTENT_TYPE"),
+        ("content-encoding", "HTTP_CONTENT_ENCODING"),
+        ("git-protocol", "GIT_PROTOCOL"),
+    ] {
+        if let Some(value) = headers.get(header) {
+            command.env(var, value);
+        }
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("failed to open http-backend stdin")?;
+    // written from another thread so large responses can't block it
+    let writer = std::thread::spawn(move || stdin.write_all(&body));
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+
+    // http-backend responds with cgi headers, which may set the status
+    let split = output
+        .stdout
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| (i, i + 4))
+        .or_else(|| {
+            output
+                .stdout
+                .windows(2)
+                .position(|w| w == b"\n\n")
+                .map(|i| (i, i + 2))
+        })
+        .unwrap_or((0, 0));
+    let mut status = "200 OK".to_string();
+    let mut response_headers = vec![];
+    for line in String::from_utf8_lossy(&output.stdout[..split.0]).lines() {
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("status") => {
+                status = value.trim().to_string();
+            }
+            Some(_) => response_headers.push(line.to_string()),
+            None => {}
+        }
+    }
+    let content = &output.stdout[split.1..];
+    stream.write_all(
+        format!(
+            "HTTP/1.1 {status}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            response_headers
+                .iter()
+                .map(|h| format!("{h}\r\n"))
+                .collect::<String>(),
+            content.len()
+        )
+        .as_bytes(),
+    )?;
+    stream.write_all(content).context("failed to respond")?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size)?;
+        let size = usize::from_str_radix(size.trim(), 16)?;
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
@@ -11,7 +11,11 @@ use dialoguer::theme::{ColorfulTheme, Theme};
 use futures::executor::block_on;
 use git::GitTestRepo;
 use git2::{Signature, Time};
-use nostr::{self, Kind, Tag, nips::nip65::RelayMetadata};
+use nostr::{
+    self, Kind, Tag,
+    hashes::{Hash, sha256::Hash as Sha256Hash},
+    nips::nip65::RelayMetadata,
+};
 use nostr_database::NostrEventsDatabase;
 use nostr_lmdb::NostrLMDB;
 use nostr_sdk::{Client, NostrSigner, TagStandard, serde_json};
@@ -20,8 +24,11 @@ use rexpect::session::{Options, PtySession};
 use strip_ansi_escapes::strip_str;
 use tokio::runtime::Handle;
 
+pub mod blossom;
 pub mod git;
+pub mod git_http;
 pub mod relay;
+pub mod socks_proxy;
 
 pub static TEST_KEY_1_NSEC: &str =
     "nsec1ppsg5sm2aexq06juxmu9evtutr6jkwkhp98exxxvwamhru9lyx9s3rwseq";
@@ -150,6 +157,18 @@ pub fn generate_repo_ref_event() -> nostr::Event {
     generate_repo_ref_event_with_git_server(vec!["git:://123.gitexample.com/test".to_string()])
 }
 
+/// announcement listing blossom servers for proposal attachments
+pub fn generate_repo_ref_event_with_blossoms(blossoms: Vec<String>) -> nostr::Event {
+    let event = generate_repo_ref_event();
+    nostr::event::EventBuilder::new(event.kind, event.content.clone())
+        .tags(event.tags.iter().cloned().chain([Tag::custom(
+            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("blossoms")),
+            blossoms,
+        )]))
+        .sign_with_keys(&TEST_KEY_1_KEYS)
+        .unwrap()
+}
+
 pub fn generate_repo_ref_event_with_git_server(git_servers: Vec<String>) -> nostr::Event {
     // taken from test git_repo
     // TODO - this may not be consistant across computers as it might take the
@@ -281,6 +300,20 @@ impl CliTester {
         Ok(i)
     }
 
+    pub fn expect_choice_eventually(
+        &mut self,
+        prompt: &str,
+        choices: Vec<String>,
+    ) -> Result<CliTesterChoicePrompt> {
+        let mut i = CliTesterChoicePrompt {
+            tester: self,
+            prompt: prompt.to_string(),
+            choices,
+        };
+        i.prompt(true).context("initial choice prompt")?;
+        Ok(i)
+    }
+
     pub fn expect_multi_select(
         &mut self,
         prompt: &str,
@@ -835,6 +868,33 @@ impl CliTester {
         }
     }
 
+    /// press ctrl-c, sending SIGINT to the process
+    pub fn interrupt(&mut self) -> Result<()> {
+        self.rexpect_session
+            .send_control('c')
+            .context("failed to send ctrl-c")?;
+        Ok(())
+    }
+
+    /// wait for the process to exit and check its exit code
+    pub fn expect_exit_code(&mut self, code: i32) -> Result<()> {
+        match self
+            .rexpect_session
+            .process
+            .wait()
+            .context("expect process to exit")?
+        {
+            rexpect::process::wait::WaitStatus::Exited(_, exit_code) => {
+                ensure!(
+                    exit_code == code,
+                    "expected exit code {code} but got {exit_code}"
+                );
+                Ok(())
+            }
+            status => bail!("expected process to exit but got {status:?}"),
+        }
+    }
+
     fn exp_string(&mut self, message: &str) -> Result<String> {
         match self
             .rexpect_session
@@ -1030,6 +1090,41 @@ where
     })
 }
 
+/// run ngit with stdin closed, as scripts and CI do. fails if it hasn't
+/// exited after `timeout_ms`, which is what waiting on a prompt looks like
+pub fn run_ngit_without_stdin_from_dir<I, S>(
+    dir: &PathBuf,
+    args: I,
+    timeout_ms: u64,
+) -> Result<std::process::Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+        .env("NGITTEST", "TRUE")
+        .env("RUST_BACKTRACE", "0")
+        .current_dir(dir)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    let started = std::time::Instant::now();
+    while child.try_wait()?.is_none() {
+        if started.elapsed() > Duration::from_millis(timeout_ms) {
+            child.kill()?;
+            let output = child.wait_with_output()?;
+            bail!(
+                "ngit still running after {timeout_ms}ms with stdin closed. stderr: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Ok(child.wait_with_output()?)
+}
+
 pub fn remote_helper_rexpect_with_from_dir(
     dir: &PathBuf,
     nostr_remote_url: &str,
@@ -1081,6 +1176,11 @@ where
         assert_cmd::cargo::cargo_bin("git-remote-nostr"),
         git_exec_dir.join("git-remote-nostr"),
     )?;
+    // git-remote-nostr runs the ngit installed alongside it
+    std::fs::copy(
+        assert_cmd::cargo::cargo_bin("ngit"),
+        git_exec_dir.join("ngit"),
+    )?;
 
     let mut cmd = std::process::Command::new("git");
     cmd.env("GIT_EXEC_PATH", git_exec_dir);
@@ -1096,10 +1196,65 @@ where
     .context("spawning failed")
 }
 
+/** copied from git/mod.rs */
+fn get_git_dir(git_repo_path: &Path) -> PathBuf {
+    let dot_git = git_repo_path.join(".git");
+    if !dot_git.exists()
+        && git_repo_path.join("HEAD").is_file()
+        && git_repo_path.join("objects").is_dir()
+    {
+        git_repo_path.to_path_buf()
+    } else {
+        dot_git
+    }
+}
+
+/** copied from cache_dir.rs */
+fn get_cache_dir_override(git_repo_path: &Path) -> Option<PathBuf> {
+    let dir = std::env::var("NGIT_CACHE_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .or_else(|| {
+            git2::Repository::open(git_repo_path)
+                .ok()?
+                .config()
+                .ok()?
+                .get_string("nostr.cache-dir")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+        })?;
+    Some(PathBuf::from(dir.trim()))
+}
+
+/** copied from cache_dir.rs */
+fn cache_file_name(git_repo_path: &Path) -> String {
+    let git_dir = get_git_dir(git_repo_path);
+    let git_dir = git_dir.canonicalize().unwrap_or(git_dir);
+    format!(
+        "{}.lmdb",
+        Sha256Hash::hash(git_dir.to_string_lossy().as_bytes())
+    )
+}
+
+/** copied from client.rs */
+pub fn get_local_cache_path(git_repo_path: &Path) -> PathBuf {
+    if let Some(dir) = get_cache_dir_override(git_repo_path) {
+        dir.join(cache_file_name(git_repo_path))
+    } else {
+        get_git_dir(git_repo_path).join("nostr-cache.lmdb")
+    }
+}
+
 /** copied from client.rs */
 async fn get_local_cache_database(git_repo_path: &Path) -> Result<NostrLMDB> {
-    NostrLMDB::open(git_repo_path.join(".git/nostr-cache.lmdb"))
-        .context("failed to open or create nostr cache database at .git/nostr-cache.lmdb")
+    let path = get_local_cache_path(git_repo_path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    NostrLMDB::open(&path).context(format!(
+        "failed to open or create nostr cache database at {}",
+        path.display()
+    ))
 }
 
 /** copied from client.rs */
@@ -1111,12 +1266,32 @@ pub async fn get_events_from_cache(
         .await?
         .query(filters.clone())
         .await
-        .context(
-            "failed to execute query on opened git repo nostr cache database .git/nostr-cache.lmdb",
-        )?
+        .context("failed to execute query on opened git repo nostr cache database")?
         .to_vec())
 }
 
+/** copied from client.rs */
+pub async fn save_event_in_local_cache(git_repo_path: &Path, event: &nostr::Event) -> Result<bool> {
+    get_local_cache_database(git_repo_path)
+        .await?
+        .save_event(event)
+        .await
+        .context("failed to save event in local cache")
+}
+
+/** copied from client.rs. the global cache is kept in the test repo during
+ * integration tests */
+pub async fn save_event_in_global_cache(
+    git_repo_path: &Path,
+    event: &nostr::Event,
+) -> Result<bool> {
+    NostrLMDB::open(get_git_dir(git_repo_path).join("test-global-cache.lmdb"))
+        .context("failed to open ngit global nostr cache database")?
+        .save_event(event)
+        .await
+        .context("failed to save event in global cache")
+}
+
 pub fn get_proposal_branch_name(
     test_repo: &GitTestRepo,
     branch_name_in_event: &str,
@@ -1152,6 +1327,26 @@ pub fn get_proposal_branch_name_from_events(
     bail!("failed to find proposal root with branch-name tag matching title")
 }
 
+/// hex id of the cached proposal root with `branch_name_in_event` in its
+/// branch-name tag
+pub fn get_proposal_root_id(test_repo: &GitTestRepo, branch_name_in_event: &str) -> Result<String> {
+    let events = block_on(get_events_from_cache(&test_repo.dir, vec![
+        nostr::Filter::default()
+            .kind(nostr::Kind::GitPatch)
+            .hashtag("root"),
+    ]))?;
+    Ok(events
+        .iter()
+        .find(|e| {
+            e.tags.iter().any(|t| {
+                t.as_slice()[0].eq("branch-name") && t.as_slice()[1].eq(branch_name_in_event)
+            })
+        })
+        .context("failed to find proposal root with branch-name tag")?
+        .id
+        .to_hex())
+}
+
 pub static FEATURE_BRANCH_NAME_1: &str = "feature-example-t";
 pub static FEATURE_BRANCH_NAME_2: &str = "feature-example-f";
 pub static FEATURE_BRANCH_NAME_3: &str = "feature-example-c";
@@ -1190,6 +1385,58 @@ pub fn cli_tester_create_proposals() -> Result<GitTestRepo> {
     Ok(git_repo)
 }
 
+/// run `test` against a repo with the proposals from
+/// `cli_tester_create_proposals` fetched into its cache, passing the id of the
+/// proposal on `FEATURE_BRANCH_NAME_1`
+pub async fn with_proposals(test: fn(&GitTestRepo, &str) -> Result<()>) -> Result<()> {
+    let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+        relay::Relay::new(8051, None, None),
+        relay::Relay::new(8052, None, None),
+        relay::Relay::new(8053, None, None),
+        relay::Relay::new(8055, None, None),
+        relay::Relay::new(8056, None, None),
+    );
+
+    r51.events.push(generate_test_key_1_relay_list_event());
+    r51.events.push(generate_test_key_1_metadata_event("fred"));
+    r51.events.push(generate_repo_ref_event());
+
+    r55.events.push(generate_repo_ref_event());
+    r55.events.push(generate_test_key_1_metadata_event("fred"));
+    r55.events.push(generate_test_key_1_relay_list_event());
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        cli_tester_create_proposals()?;
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        // fetch proposals into the cache
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("all proposals")?;
+        p.exit()?;
+
+        let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+        test(&test_repo, &proposal_id)?;
+
+        for p in [51, 52, 53, 55, 56] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+
+    // launch relay
+    let _ = futures::join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(())
+}
+
 pub fn cli_tester_create_proposal_branches_ready_to_send() -> Result<GitTestRepo> {
     let git_repo = GitTestRepo::default();
     git_repo.populate()?;
@@ -15,6 +15,9 @@ pub struct Relay<'a> {
     clients: HashMap<u64, simple_websockets::Responder>,
     pub events: Vec<nostr::Event>,
     pub reqs: Vec<Vec<nostr::Filter>>,
+    /// answer REQs with matching events but never send EOSE, like relays
+    /// that stall mid subscription
+    pub withhold_eose: bool,
     event_listener: Option<ListenerEventFunc<'a>>,
     req_listener: Option<ListenerReqFunc<'a>>,
 }
@@ -31,6 +34,7 @@ impl<'a> Relay<'a> {
             port,
             events: vec![],
             reqs: vec![],
+            withhold_eose: false,
             event_hub,
             clients: HashMap::new(),
             event_listener,
@@ -73,6 +77,19 @@ impl<'a> Relay<'a> {
         client_id: u64,
         subscription_id: &nostr::SubscriptionId,
         events: &Vec<nostr::Event>,
+    ) -> Result<bool> {
+        if !self.respond_events_without_eose(client_id, subscription_id, events)? {
+            return Ok(false);
+        }
+        self.respond_eose(client_id, subscription_id.clone())
+    }
+
+    /// send events without eose
+    pub fn respond_events_without_eose(
+        &self,
+        client_id: u64,
+        subscription_id: &nostr::SubscriptionId,
+        events: &Vec<nostr::Event>,
     ) -> Result<bool> {
         let responder = self.clients.get(&client_id).unwrap();
 
@@ -88,10 +105,11 @@ impl<'a> Relay<'a> {
                 return Ok(false);
             }
         }
-        self.respond_eose(client_id, subscription_id.clone())
+        Ok(true)
     }
 
-    /// send collected events, filtered by filters, and eose
+    /// send collected events, filtered by filters, and eose unless
+    /// `withhold_eose` is set
     pub fn respond_standard_req(
         &self,
         client_id: u64,
@@ -99,17 +117,33 @@ impl<'a> Relay<'a> {
         // TODO: enable filters
         filters: &[nostr::Filter],
     ) -> Result<bool> {
-        self.respond_events(
-            client_id,
-            subscription_id,
-            &self
+        // like relays, only the newest events up to each filter's limit
+        let mut within_limits = std::collections::HashSet::new();
+        for filter in filters {
+            let mut matching = self
                 .events
                 .iter()
-                .filter(|e| filters.iter().any(|filter| filter.match_event(e)))
-                .filter(|_| true)
-                .cloned()
-                .collect(),
-        )
+                .filter(|e| filter.match_event(e))
+                .collect::<Vec<&nostr::Event>>();
+            matching.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+            within_limits.extend(
+                matching
+                    .into_iter()
+                    .take(filter.limit.unwrap_or(usize::MAX))
+                    .map(|e| e.id),
+            );
+        }
+        let events = self
+            .events
+            .iter()
+            .filter(|e| within_limits.contains(&e.id))
+            .cloned()
+            .collect();
+        if self.withhold_eose {
+            self.respond_events_without_eose(client_id, subscription_id, &events)
+        } else {
+            self.respond_events(client_id, subscription_id, &events)
+        }
     }
     /// listen, collect events and responds with event_listener to events or
     /// Ok(eventid) if event_listner is None
@@ -194,6 +228,18 @@ pub fn shutdown_relay(port: u64) -> Result<()> {
     Ok(())
 }
 
+/// publish `event` to a running test relay as another client would, eg. a
+/// bot, and wait for the relay to accept it
+pub fn publish_to_relay(port: u64, event: &nostr::Event) -> Result<()> {
+    let (mut socket, _) = tungstenite::connect(format!("ws://localhost:{}", port))?;
+    socket.send(tungstenite::Message::text(
+        ClientMessage::event(event.clone()).as_json(),
+    ))?;
+    socket.read()?;
+    socket.close(None)?;
+    Ok(())
+}
+
 fn get_nevent(message: &simple_websockets::Message) -> Result<nostr::Event> {
     if let simple_websockets::Message::Text(s) = message.clone() {
         let cm_result = ClientMessage::from_json(s);
@@ -0,0 +1,102 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Result, bail};
+
+/// a socks5 proxy without authentication that records the `host:port` of
+/// each connection made through it
+pub struct SocksProxy {
+    pub addr: SocketAddr,
+    connections: Arc<Mutex<Vec<String>>>,
+}
+
+impl SocksProxy {
+    /// listens on a free port until the test process exits
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let connections = Arc::new(Mutex::new(vec![]));
+        let recorded = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let recorded = recorded.clone();
+                std::thread::spawn(move || {
+                    let _ = proxy(stream, &recorded);
+                });
+            }
+        });
+        Ok(Self { addr, connections })
+    }
+
+    /// `host:port` of each connection made through the proxy so far
+    pub fn connections(&self) -> Vec<String> {
+        self.connections.lock().unwrap().clone()
+    }
+}
+
+fn proxy(mut client: TcpStream, recorded: &Mutex<Vec<String>>) -> Result<()> {
+    // greeting: version, number of methods, methods
+    let mut header = [0; 2];
+    client.read_exact(&mut header)?;
+    let mut methods = vec![0; header[1] as usize];
+    client.read_exact(&mut methods)?;
+    if header[0] != 5 || !methods.contains(&0) {
+        client.write_all(&[5, 0xff])?;
+        bail!("only socks5 without authentication is supported");
+    }
+    client.write_all(&[5, 0])?;
+
+    // request: version, command, reserved, address type, address, port
+    let mut request = [0; 4];
+    client.read_exact(&mut request)?;
+    if request[1] != 1 {
+        // command not supported
+        client.write_all(&[5, 7, 0, 1, 0, 0, 0, 0, 0, 0])?;
+        bail!("only CONNECT is supported");
+    }
+    let host = match request[3] {
+        1 => {
+            let mut ip = [0; 4];
+            client.read_exact(&mut ip)?;
+            std::net::Ipv4Addr::from(ip).to_string()
+        }
+        3 => {
+            let mut len = [0; 1];
+            client.read_exact(&mut len)?;
+            let mut domain = vec![0; len[0] as usize];
+            client.read_exact(&mut domain)?;
+            String::from_utf8(domain)?
+        }
+        4 => {
+            let mut ip = [0; 16];
+            client.read_exact(&mut ip)?;
+            format!("[{}]", std::net::Ipv6Addr::from(ip))
+        }
+        _ => bail!("unknown socks5 address type"),
+    };
+    let mut port = [0; 2];
+    client.read_exact(&mut port)?;
+    let target = format!("{host}:{}", u16::from_be_bytes(port));
+    recorded.lock().unwrap().push(target.clone());
+
+    let Ok(upstream) = TcpStream::connect(&target) else {
+        // connection refused
+        client.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])?;
+        bail!("failed to connect to {target}");
+    };
+    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+
+    let (mut client_read, mut upstream_write) = (client.try_clone()?, upstream.try_clone()?);
+    let to_upstream = std::thread::spawn(move || {
+        let _ = std::io::copy(&mut client_read, &mut upstream_write);
+        let _ = upstream_write.shutdown(Shutdown::Write);
+    });
+    let (mut upstream_read, mut client_write) = (upstream, client);
+    let _ = std::io::copy(&mut upstream_read, &mut client_write);
+    let _ = client_write.shutdown(Shutdown::Write);
+    let _ = to_upstream.join();
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use anyhow::Result;
+
+/// packagers and library users can build without the binaries' dependencies
+#[test]
+fn library_builds_without_default_features() -> Result<()> {
+    let status = std::process::Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["check", "--lib", "--no-default-features", "--quiet"])
+        .arg("--target-dir")
+        .arg(std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-default-features"))
+        .status()?;
+    assert!(status.success());
+    Ok(())
+}
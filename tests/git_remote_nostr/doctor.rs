@@ -0,0 +1,90 @@
+use super::*;
+
+fn run_doctor(git_repo: &GitTestRepo) -> Result<String> {
+    run_doctor_from_dir(git_repo, &git_repo.dir, true)
+}
+
+/// `set_git_dir` as git does when it runs the helper, rather than by hand
+fn run_doctor_from_dir(
+    git_repo: &GitTestRepo,
+    dir: &std::path::Path,
+    set_git_dir: bool,
+) -> Result<String> {
+    // no relays are launched as doctor must not access the network
+    let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("git-remote-nostr"));
+    cmd.env("NGITTEST", "TRUE").env_remove("GIT_DIR");
+    if set_git_dir {
+        cmd.env("GIT_DIR", git_repo.dir.join(".git"));
+    }
+    let output = cmd.current_dir(dir).arg("--doctor").output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+#[serial]
+fn reports_versions_nips_and_kinds() -> Result<()> {
+    let git_repo = prep_git_repo()?;
+    let output = run_doctor(&git_repo)?;
+    let version = env!("CARGO_PKG_VERSION");
+    assert!(output.contains(&format!("git-remote-nostr: v{version}\n")));
+    assert_eq!(output.matches(&format!("v{version}")).count(), 1);
+    assert!(output.contains("NIP-34"));
+    assert!(output.contains("kinds: 30617, 30618, 1617, 1630, 1631, 1632, 1633\n"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn reports_login_from_local_git_config_without_secret() -> Result<()> {
+    let git_repo = prep_git_repo()?;
+    let output = run_doctor(&git_repo)?;
+    assert!(output.contains(&format!(
+        "login: nsec via local git config as {TEST_KEY_2_NPUB}\n"
+    )));
+    assert!(!output.contains(TEST_KEY_2_NSEC));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn reports_cache_locations() -> Result<()> {
+    let git_repo = prep_git_repo()?;
+    let output = run_doctor(&git_repo)?;
+    assert!(output.contains(&format!(
+        "local cache: {} (not created yet)\n",
+        git_repo.dir.join(".git/nostr-cache.lmdb").display()
+    )));
+    assert!(output.contains(&format!(
+        "global cache: {} (not created yet)\n",
+        git_repo.dir.join(".git/test-global-cache.lmdb").display()
+    )));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn reports_whether_helper_is_on_path() -> Result<()> {
+    let git_repo = prep_git_repo()?;
+    let output = run_doctor(&git_repo)?;
+    assert!(output.contains("git-remote-nostr on PATH: "));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn finds_repository_from_working_directory_without_git_dir() -> Result<()> {
+    let git_repo = prep_git_repo()?;
+    let subdir = git_repo.dir.join("subdir");
+    std::fs::create_dir_all(&subdir)?;
+    let output = run_doctor_from_dir(&git_repo, &subdir, false)?;
+    assert!(output.contains("(found from working directory)\n"));
+    assert!(output.contains(&format!(
+        "login: nsec via local git config as {TEST_KEY_2_NPUB}\n"
+    )));
+    assert!(output.contains(&format!(
+        "local cache: {} (not created yet)\n",
+        git_repo.dir.join(".git/nostr-cache.lmdb").display()
+    )));
+    Ok(())
+}
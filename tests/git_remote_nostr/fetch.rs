@@ -67,6 +67,78 @@ async fn fetch_downloads_speficied_commits_from_git_server() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+#[serial]
+async fn fetch_batch_of_10_refs_negotiates_with_git_server_once() -> Result<()> {
+    let source_git_repo = prep_git_repo()?;
+    let source_path = source_git_repo.dir.to_str().unwrap().to_string();
+
+    let mut commit_ids = vec![];
+    for i in 0..10 {
+        source_git_repo.checkout("main")?;
+        source_git_repo.create_branch(&format!("branch-{i}"))?;
+        source_git_repo.checkout(&format!("branch-{i}"))?;
+        std::fs::write(source_git_repo.dir.join(format!("{i}.md")), "some content")?;
+        commit_ids.push(source_git_repo.stage_and_commit(&format!("{i}.md"))?);
+    }
+
+    let git_repo = prep_git_repo()?;
+    let events = vec![
+        generate_test_key_1_metadata_event("fred"),
+        generate_test_key_1_relay_list_event(),
+        generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+        ]),
+    ];
+    // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+        Relay::new(8057, None, None),
+    );
+    r51.events = events.clone();
+    r55.events = events;
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        for commit_id in &commit_ids {
+            assert!(git_repo.git_repo.find_commit(*commit_id).is_err());
+        }
+
+        let mut p = cli_tester_after_fetch(&git_repo)?;
+        for (i, commit_id) in commit_ids.iter().enumerate() {
+            p.send_line(format!("fetch {commit_id} refs/heads/branch-{i}").as_str())?;
+        }
+        p.send_line("")?;
+        p.expect(format!("fetching {source_path} over filesystem...").as_str())?;
+        let output = p.expect_eventually_and_print("\r\n\r\n")?;
+        assert!(!output.contains("fetching "));
+
+        for commit_id in &commit_ids {
+            assert!(git_repo.git_repo.find_commit(*commit_id).is_ok());
+        }
+
+        p.exit()?;
+        for p in [51, 52, 53, 55, 56, 57] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+        r57.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(())
+}
+
 mod when_first_git_server_fails_ {
     use super::*;
 
@@ -190,3 +262,963 @@ async fn creates_commits_from_open_proposal_with_no_warnings_printed() -> Result
 
     Ok(())
 }
+
+fn count_objects(git_repo: &GitTestRepo) -> Result<usize> {
+    let mut count = 0;
+    git_repo.git_repo.odb()?.foreach(|_| {
+        count += 1;
+        true
+    })?;
+    Ok(count)
+}
+
+mod when_cloning_a_single_branch {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn unrequested_branch_commits_are_not_downloaded() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+
+        source_git_repo.create_branch("vnext")?;
+        source_git_repo.checkout("vnext")?;
+        std::fs::write(source_git_repo.dir.join("vnext.md"), "some content")?;
+        let vnext_commit_id = source_git_repo.stage_and_commit("vnext.md")?;
+
+        source_git_repo.checkout("main")?;
+        std::fs::write(source_git_repo.dir.join("main.md"), "different content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("main.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+            std::fs::create_dir(path.clone())?;
+            CliTester::new_git_with_remote_helper_from_dir(&path, [
+                "clone",
+                "--single-branch",
+                "--branch",
+                "vnext",
+                &get_nostr_remote_url()?,
+                ".",
+            ])
+            .expect_end_eventually_and_print()?;
+            let git_repo = GitTestRepo::open(&path)?;
+
+            assert!(git_repo.git_repo.find_commit(vnext_commit_id).is_ok());
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+            assert!(count_objects(&git_repo)? < count_objects(&source_git_repo)?);
+
+            // the single branch fetch refspec is respected by later fetches
+            CliTester::new_git_with_remote_helper_from_dir(&path, ["fetch"])
+                .expect_end_eventually_and_print()?;
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_cloning_with_blob_none_filter {
+    use super::*;
+
+    /// source repository where t1.md was changed so its first version is only
+    /// in history. returns the blob of that first version
+    fn prep_source_repo_with_blob_only_in_history(
+        allow_filter: bool,
+    ) -> Result<(GitTestRepo, Oid)> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("t1.md"), "updated content")?;
+        source_git_repo.stage_and_commit("update t1.md")?;
+        source_git_repo
+            .git_repo
+            .config()?
+            .set_bool("uploadpack.allowFilter", allow_filter)?;
+        let historic_blob = Oid::hash_object(git2::ObjectType::Blob, b"some content")?;
+        Ok((source_git_repo, historic_blob))
+    }
+
+    async fn clone_with_filter_then<F>(source_git_repo: &GitTestRepo, after_clone: F) -> Result<()>
+    where
+        F: FnOnce(&std::path::PathBuf, String) -> Result<()> + Send + 'static,
+    {
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+            std::fs::create_dir(path.clone())?;
+            let output = CliTester::new_git_with_remote_helper_from_dir(&path, [
+                "clone",
+                "--filter=blob:none",
+                &get_nostr_remote_url()?,
+                ".",
+            ])
+            .expect_end_eventually()?;
+            let res = after_clone(&path, output);
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            res
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn blobs_only_in_history_are_left_out() -> Result<()> {
+        let (source_git_repo, historic_blob) = prep_source_repo_with_blob_only_in_history(true)?;
+        let source_count = count_objects(&source_git_repo)?;
+        clone_with_filter_then(&source_git_repo, move |path, _| {
+            let git_repo = GitTestRepo::open(path)?;
+            assert!(!git_repo.git_repo.odb()?.exists(historic_blob));
+            assert!(count_objects(&git_repo)? < source_count);
+            // checked out during clone, fetched on demand through the helper
+            assert_eq!(
+                std::fs::read_to_string(path.join("t1.md"))?,
+                "updated content"
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn missing_blobs_are_fetched_on_demand_through_helper() -> Result<()> {
+        let (source_git_repo, historic_blob) = prep_source_repo_with_blob_only_in_history(true)?;
+        clone_with_filter_then(&source_git_repo, move |path, _| {
+            let mut p = CliTester::new_git_with_remote_helper_from_dir(path, [
+                "cat-file",
+                "-p",
+                &historic_blob.to_string(),
+            ]);
+            p.expect_eventually("some content")?;
+            p.expect_end_eventually()?;
+            let git_repo = GitTestRepo::open(path)?;
+            assert!(git_repo.git_repo.odb()?.exists(historic_blob));
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn server_without_filter_support_warns_and_sends_everything() -> Result<()> {
+        let (source_git_repo, historic_blob) = prep_source_repo_with_blob_only_in_history(false)?;
+        clone_with_filter_then(&source_git_repo, move |path, output| {
+            assert!(output.contains("doesn't support partial clone so 'blob:none' was ignored"));
+            let git_repo = GitTestRepo::open(path)?;
+            assert!(git_repo.git_repo.odb()?.exists(historic_blob));
+            Ok(())
+        })
+        .await
+    }
+}
+
+mod when_cloning {
+    use super::*;
+
+    async fn clone_then<F>(clone_args: &'static [&'static str], after_clone: F) -> Result<()>
+    where
+        F: FnOnce(&std::path::PathBuf, String) -> Result<()> + Send + 'static,
+    {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        source_git_repo.stage_and_commit("commit.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+            std::fs::create_dir(path.clone())?;
+            let url = get_nostr_remote_url()?;
+            let args = [&["clone"][..], clone_args, &[url.as_str(), "."][..]].concat();
+            let output = CliTester::new_git_with_remote_helper_from_dir(&path, args)
+                .expect_end_eventually()?;
+
+            after_clone(&path, output)?;
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn prints_repository_links_and_maintainers_once() -> Result<()> {
+        clone_then(&[], |path, output| {
+            for line in [
+                "nostr: cloned example name - example description",
+                "  web: https://exampleproject.xyz https://gitworkshop.dev/123",
+                "  maintainers: fred, ",
+                "  run `ngit list` to see open proposals",
+            ] {
+                assert_eq!(output.matches(line).count(), 1, "{line} in {output}");
+            }
+
+            let output = CliTester::new_git_with_remote_helper_from_dir(path, ["fetch"])
+                .expect_end_eventually()?;
+            assert!(!output.contains("nostr: cloned"), "{output}");
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn nothing_printed_with_quiet_clone() -> Result<()> {
+        clone_then(&["-c", "nostr.quiet-clone=true"], |_, output| {
+            assert!(!output.contains("nostr: cloned"), "{output}");
+            assert!(!output.contains("ngit list"), "{output}");
+            Ok(())
+        })
+        .await
+    }
+}
+
+mod when_cloning_with_alias_url {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn alias_resolves_via_git_config_and_is_kept_as_remote_url() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+            std::fs::create_dir(path.clone())?;
+            CliTester::new_git_with_remote_helper_from_dir(&path, [
+                "clone",
+                "-c",
+                &format!("nostr.alias.upstream={}", get_nostr_remote_url()?),
+                "nostr://alias/upstream",
+                ".",
+            ])
+            .expect_end_eventually_and_print()?;
+            let git_repo = GitTestRepo::open(&path)?;
+
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+            assert_eq!(
+                git_repo.git_repo.find_remote("origin")?.url(),
+                Some("nostr://alias/upstream"),
+            );
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn missing_alias_is_named_in_error() -> Result<()> {
+        let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+        std::fs::create_dir(path.clone())?;
+        let mut p = CliTester::new_git_with_remote_helper_from_dir(&path, [
+            "clone",
+            "nostr://alias/missing",
+            ".",
+        ]);
+        p.expect_eventually("no nostr url alias 'missing'")?;
+        std::fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
+
+mod when_cloning_with_legacy_url {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn relays_found_via_fallback_relays_and_remote_migrated_to_naddr() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+
+        let repo_event = generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+        ]);
+        let legacy_url = format!(
+            "nostr://30617:{}:{}",
+            repo_event.pubkey.to_hex(),
+            repo_event.tags.identifier().unwrap(),
+        );
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            repo_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+            std::fs::create_dir(path.clone())?;
+            let mut p =
+                CliTester::new_git_with_remote_helper_from_dir(&path, ["clone", &legacy_url, "."]);
+            p.expect_confirm_eventually(
+                "remote 'origin' uses a legacy nostr url. rewrite it in naddr format?",
+                Some(true),
+            )?
+            .succeeds_with(Some(true))?;
+            p.expect_eventually("migrated remote 'origin' to nostr://naddr1")?;
+            p.expect_end_eventually()?;
+            let git_repo = GitTestRepo::open(&path)?;
+
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+            let url = git_repo
+                .git_repo
+                .find_remote("origin")?
+                .url()
+                .unwrap()
+                .to_string();
+            let coordinate = Coordinate::parse(url.strip_prefix("nostr://").unwrap())?;
+            assert_eq!(coordinate.relays, vec![
+                RelayUrl::parse("ws://localhost:8055")?,
+                RelayUrl::parse("ws://localhost:8056")?,
+            ]);
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_fetching_from_linked_worktree {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn cache_is_shared_with_main_repository() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+
+        let git_repo = prep_git_repo()?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let worktree = git_repo.add_worktree("worktree-branch")?;
+
+            CliTester::new_git_with_remote_helper_from_dir(&worktree.dir, [
+                "fetch",
+                NOSTR_REMOTE_NAME,
+            ])
+            .expect_end_eventually_and_print()?;
+
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+            assert!(git_repo.dir.join(".git/nostr-cache.lmdb").exists());
+            // previously placed relative to the per-worktree git dir
+            assert!(!git_repo.dir.join(".git/worktrees/.git").exists());
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_fetching_head {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn ls_remote_shows_symref_and_head_fetched_by_oid() -> Result<()> {
+        let (state_event, source_git_repo) = generate_repo_with_state_event().await?;
+        let main_commit_id = source_git_repo.get_tip_of_local_branch("main")?;
+
+        let git_repo = prep_git_repo_minus_1_commit()?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+            state_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                "ls-remote",
+                "--symref",
+                NOSTR_REMOTE_NAME,
+                "HEAD",
+            ])
+            .expect_end_eventually()?;
+            assert!(output.contains("ref: refs/heads/main\tHEAD"));
+            assert!(output.contains(&format!("{main_commit_id}\tHEAD")));
+
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line(format!("fetch {main_commit_id} HEAD").as_str())?;
+            p.send_line("")?;
+            let output = p.expect_eventually_and_print("\r\n\r\n")?;
+            assert!(!output.contains("failed"));
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_nostr_state_moves_main {
+    use nostr::{EventBuilder, Tag, TagKind};
+
+    use super::*;
+
+    /// fetch after main moves from a tip already fetched into `nostr/main` to
+    /// a new one, either on top of it or, when `force_pushed`, replacing it
+    async fn fetch_after_main_moves(force_pushed: bool) -> Result<(String, Oid, Oid)> {
+        let source_git_repo = prep_git_repo()?;
+        source_git_repo.create_branch("rewritten")?;
+        std::fs::write(source_git_repo.dir.join("old.md"), "some content")?;
+        let old_commit_id = source_git_repo.stage_and_commit("old.md")?;
+
+        let git_repo = GitTestRepo::duplicate(&source_git_repo)?;
+        git_repo.git_repo.reference(
+            "refs/remotes/nostr/main",
+            old_commit_id,
+            true,
+            "fetched before main moved",
+        )?;
+
+        if force_pushed {
+            source_git_repo.checkout("rewritten")?;
+        }
+        std::fs::write(source_git_repo.dir.join("new.md"), "some content")?;
+        let new_commit_id = source_git_repo.stage_and_commit("new.md")?;
+
+        let repo_event = generate_repo_ref_event();
+        let state_event = EventBuilder::new(STATE_KIND, "")
+            .tags([
+                Tag::identifier(repo_event.tags.identifier().unwrap()),
+                Tag::custom(TagKind::Custom("HEAD".into()), ["ref: refs/heads/main"]),
+                Tag::custom(
+                    TagKind::Custom("refs/heads/main".into()),
+                    [new_commit_id.to_string()],
+                ),
+            ])
+            .sign_with_keys(&TEST_KEY_1_KEYS)?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+            state_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                "fetch",
+                NOSTR_REMOTE_NAME,
+            ])
+            .expect_end_eventually()?;
+
+            assert!(git_repo.git_repo.find_commit(new_commit_id).is_ok());
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(output)
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let output = cli_tester_handle.join().unwrap()?;
+        Ok((output, old_commit_id, new_commit_id))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn force_push_explained_with_recovery_commands() -> Result<()> {
+        let (output, old_commit_id, new_commit_id) = fetch_after_main_moves(true).await?;
+        let old = &old_commit_id.to_string()[..7];
+        let new = &new_commit_id.to_string()[..7];
+        assert!(output.contains(&format!(
+            "WARNING: nostr/main was force pushed on nostr. it moved from {old} to {new}, which doesn't build on {old}"
+        )));
+        assert!(output.contains(
+            "to drop your local commits on main: git fetch nostr && git reset --hard nostr/main"
+        ));
+        assert!(output.contains(&format!(
+            "to keep them: git fetch nostr && git rebase --onto nostr/main {old} main"
+        )));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fast_forward_not_reported() -> Result<()> {
+        let (output, _, _) = fetch_after_main_moves(false).await?;
+        assert!(!output.contains("force pushed"));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn fetch_with_cache_dir_set_keeps_cache_out_of_git_dir() -> Result<()> {
+    let source_git_repo = prep_git_repo()?;
+    std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+    let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+
+    let git_repo = prep_git_repo()?;
+    let cache_dir =
+        std::env::temp_dir().join(format!("ngit-test-remote-cache-dir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache_dir);
+    git_repo
+        .git_repo
+        .config()?
+        .set_str("nostr.cache-dir", cache_dir.to_str().unwrap())?;
+
+    let events = vec![
+        generate_test_key_1_metadata_event("fred"),
+        generate_test_key_1_relay_list_event(),
+        generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+        ]),
+    ];
+    // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+        Relay::new(8057, None, None),
+    );
+    r51.events = events.clone();
+    r55.events = events;
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+            "fetch",
+            NOSTR_REMOTE_NAME,
+        ])
+        .expect_end_eventually_and_print()?;
+
+        assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+        assert!(!git_repo.dir.join(".git/nostr-cache.lmdb").exists());
+        assert_eq!(std::fs::read_dir(&cache_dir)?.count(), 1);
+        std::fs::remove_dir_all(&cache_dir)?;
+
+        for p in [51, 52, 53, 55, 56, 57] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+        r57.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn fetch_into_bare_repository_keeps_cache_in_it() -> Result<()> {
+    let source_git_repo = prep_git_repo()?;
+    let source_path = source_git_repo.dir.to_str().unwrap().to_string();
+
+    std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+    let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+
+    // like a ci mirror
+    let git_repo = GitTestRepo::recreate_as_bare(&prep_git_repo()?)?;
+    set_git_nostr_login_config(&git_repo)?;
+    git_repo.add_remote(NOSTR_REMOTE_NAME, &get_nostr_remote_url()?)?;
+
+    let events = vec![
+        generate_test_key_1_metadata_event("fred"),
+        generate_test_key_1_relay_list_event(),
+        generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+        ]),
+    ];
+    // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+        Relay::new(8057, None, None),
+    );
+    r51.events = events.clone();
+    r55.events = events;
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+
+        let mut p = cli_tester_after_fetch(&git_repo)?;
+        p.send_line(format!("fetch {main_commit_id} refs/heads/main").as_str())?;
+        p.send_line("")?;
+        p.expect(format!("fetching {source_path} over filesystem...\r\n").as_str())?;
+        p.expect_eventually_and_print("\r\n")?;
+
+        assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+        assert!(git_repo.dir.join("nostr-cache.lmdb").exists());
+        assert!(!git_repo.dir.join(".git").exists());
+
+        p.exit()?;
+        for p in [51, 52, 53, 55, 56, 57] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+        r57.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(())
+}
+
+mod when_libgit2_cannot_reach_http_git_server {
+    use test_utils::git_http::GitHttpServer;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_falls_back_to_system_git() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+        let server = GitHttpServer::start_dropping_libgit2(&source_git_repo.dir)?;
+
+        let git_repo = prep_git_repo()?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![server.url.clone()]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line(format!("fetch {main_commit_id} main").as_str())?;
+            p.send_line("")?;
+            p.expect_eventually(
+                format!("fetch: libgit2 failed to reach {}: ", server.url).as_str(),
+            )?;
+            p.expect_eventually("retrying with system git\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_ok());
+
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_fails_when_shell_git_fallback_disabled() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        std::fs::write(source_git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = source_git_repo.stage_and_commit("commit.md")?;
+        let server = GitHttpServer::start_dropping_libgit2(&source_git_repo.dir)?;
+
+        let git_repo = prep_git_repo()?;
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.shell-git-fallback", "false")?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![server.url.clone()]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line(format!("fetch {main_commit_id} main").as_str())?;
+            p.send_line("")?;
+            let output = p.expect_eventually(
+                format!("fetch: {} failed over http (unauthenticated): ", server.url).as_str(),
+            )?;
+            assert!(!output.contains("retrying with system git"), "{output}");
+            assert!(git_repo.git_repo.find_commit(main_commit_id).is_err());
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+}
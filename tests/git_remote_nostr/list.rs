@@ -353,3 +353,678 @@ mod with_state_announcement {
         }
     }
 }
+
+mod when_nostr_timeout_is_reached {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn exits_with_network_error_reporting_relays_answered() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        git_repo.git_repo.config()?.set_str("nostr.timeout", "2")?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event(),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+        r56.withhold_eose = true;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<std::time::Duration> {
+            let start = std::time::Instant::now();
+            let mut p = cli_tester(&git_repo);
+            p.expect("nostr: fetching...\r\n")?;
+            p.expect_eventually("Error: timed out after 2s: ")?;
+            p.expect_eventually(" relays answered\r\n")?;
+            let elapsed = start.elapsed();
+            p.expect_end_eventually()?;
+            p.expect_exit_code(4)?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(elapsed)
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let elapsed = cli_tester_handle.join().unwrap()?;
+        assert!(elapsed < std::time::Duration::from_secs(4));
+        Ok(())
+    }
+}
+
+mod proposal_notes {
+
+    use super::*;
+
+    static NOTES_REF: &str = "refs/notes/nostr";
+
+    fn notes_in_repo(git_repo: &GitTestRepo) -> Result<Vec<String>> {
+        let mut notes = vec![];
+        if let Ok(iter) = git_repo.git_repo.notes(Some(NOTES_REF)) {
+            for item in iter {
+                let (_, annotated_id) = item?;
+                notes.push(
+                    git_repo
+                        .git_repo
+                        .find_note(Some(NOTES_REF), annotated_id)?
+                        .message()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+            }
+        }
+        notes.sort();
+        Ok(notes)
+    }
+
+    fn expected_note_start(events: &[Event], branch_name: &str, title: &str) -> Result<String> {
+        Ok(format!(
+            "branch: {}\ntitle: {title}\nauthor: {TEST_KEY_1_NPUB}\n",
+            get_proposal_branch_name_from_events(events, branch_name)?,
+        ))
+    }
+
+    fn proposal_root_id(events: &[Event], branch_name: &str) -> Result<nostr::EventId> {
+        Ok(events
+            .iter()
+            .find(|e| {
+                e.tags
+                    .iter()
+                    .any(|t| t.as_slice() == ["t", "root"].as_slice())
+                    && e.tags
+                        .iter()
+                        .any(|t| t.as_slice() == ["branch-name", branch_name].as_slice())
+            })
+            .context("proposal root not found")?
+            .id)
+    }
+
+    async fn list_with_relay_events(
+        git_repo: GitTestRepo,
+        events: Vec<Event>,
+    ) -> Result<GitTestRepo> {
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<GitTestRepo> {
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line("list")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(git_repo)
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn note_written_for_each_open_proposal_tip() -> Result<()> {
+        let (events, _source_git_repo) = prep_source_repo_and_events_including_proposals().await?;
+
+        let git_repo = list_with_relay_events(prep_git_repo()?, events.clone()).await?;
+
+        let notes = notes_in_repo(&git_repo)?;
+        assert_eq!(notes.len(), 3);
+        for (branch_name, title) in [
+            (FEATURE_BRANCH_NAME_1, PROPOSAL_TITLE_1),
+            (FEATURE_BRANCH_NAME_2, PROPOSAL_TITLE_2),
+            (FEATURE_BRANCH_NAME_3, PROPOSAL_TITLE_3),
+        ] {
+            let start = expected_note_start(&events, branch_name, title)?;
+            let note = notes
+                .iter()
+                .find(|n| n.starts_with(&start))
+                .context(format!("no note starting with: {start}"))?;
+            assert!(note.contains(&format!(
+                "root: {}\nstatus: open\n",
+                proposal_root_id(&events, branch_name)?
+            )));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn notes_removed_for_closed_proposals_and_unlisted_refs() -> Result<()> {
+        let (mut events, _source_git_repo) =
+            prep_source_repo_and_events_including_proposals().await?;
+        events.push(
+            nostr::EventBuilder::new(Kind::GitStatusClosed, "")
+                .tags([nostr::Tag::event(proposal_root_id(
+                    &events,
+                    FEATURE_BRANCH_NAME_3,
+                )?)])
+                .sign_with_keys(&TEST_KEY_1_KEYS)?,
+        );
+
+        let git_repo = prep_git_repo()?;
+        // stale note from an earlier fetch against a commit no longer listed
+        let signature = git2::Signature::now("test", "test@test.com")?;
+        git_repo.git_repo.note(
+            &signature,
+            &signature,
+            Some(NOTES_REF),
+            git_repo.get_tip_of_local_branch("main")?,
+            "branch: pr/gone(aaaaaaaa)\ntitle: gone\nauthor: npub1\nroot: aa\nstatus: open\n",
+            false,
+        )?;
+
+        let git_repo = list_with_relay_events(git_repo, events.clone()).await?;
+
+        let notes = notes_in_repo(&git_repo)?;
+        assert_eq!(notes.len(), 2);
+        assert!(!notes.iter().any(|n| n.contains("pr/gone")));
+        let closed_start = expected_note_start(&events, FEATURE_BRANCH_NAME_3, PROPOSAL_TITLE_3)?;
+        assert!(!notes.iter().any(|n| n.starts_with(&closed_start)));
+        Ok(())
+    }
+}
+
+mod prs_as_refs {
+
+    use super::*;
+
+    struct ListedAndFetched {
+        /// refs and oids shown by `git ls-remote`
+        listed: Vec<(String, String)>,
+        /// branch name of the first proposal, eg. `pr/name(1234abcd)`
+        branch_name: String,
+        git_repo: GitTestRepo,
+    }
+
+    /// `git ls-remote` then `git fetch` twice so refspecs added to the remote
+    /// during the first fetch are used by the second
+    async fn list_and_fetch_with_prs_as_refs(mode: Option<&str>) -> Result<ListedAndFetched> {
+        // the git server proposal parent commits are fetched from
+        let (events, _source_git_repo) = prep_source_repo_and_events_including_proposals().await?;
+        let branch_name = get_proposal_branch_name_from_events(&events, FEATURE_BRANCH_NAME_1)?;
+        let git_repo = prep_git_repo()?;
+        if let Some(mode) = mode {
+            git_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.prs-as-refs", mode)?;
+        }
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<ListedAndFetched> {
+            let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                "ls-remote",
+                NOSTR_REMOTE_NAME,
+            ])
+            .expect_end_eventually()?;
+            for _ in 0..2 {
+                CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                    "fetch",
+                    NOSTR_REMOTE_NAME,
+                ])
+                .expect_end_eventually_and_print()?;
+            }
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(ListedAndFetched {
+                listed: output
+                    .split("\r\n")
+                    .filter_map(|line| line.split_once('\t'))
+                    .map(|(oid, name)| (name.to_string(), oid.to_string()))
+                    .collect(),
+                branch_name,
+                git_repo,
+            })
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    fn listed_names_starting_with(res: &ListedAndFetched, prefix: &str) -> Vec<String> {
+        res.listed
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn fetched_names_starting_with(res: &ListedAndFetched, prefix: &str) -> Result<Vec<String>> {
+        Ok(res
+            .git_repo
+            .git_repo
+            .references_glob(&format!("{prefix}*"))?
+            .filter_map(|r| r.ok()?.name().map(String::from))
+            .collect())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn heads_by_default() -> Result<()> {
+        let res = list_and_fetch_with_prs_as_refs(None).await?;
+        assert!(
+            listed_names_starting_with(&res, "refs/heads/pr/")
+                .contains(&format!("refs/heads/{}", res.branch_name))
+        );
+        assert!(listed_names_starting_with(&res, "refs/prs/").is_empty());
+        assert!(
+            fetched_names_starting_with(&res, "refs/remotes/nostr/pr/")?
+                .contains(&format!("refs/remotes/nostr/{}", res.branch_name))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn prs_listed_outside_heads_and_fetched_with_commits() -> Result<()> {
+        let res = list_and_fetch_with_prs_as_refs(Some("prs")).await?;
+        let prs_refstr = format!("refs/prs/{}", res.branch_name.trim_start_matches("pr/"));
+        assert!(listed_names_starting_with(&res, "refs/heads/pr/").is_empty());
+        assert!(listed_names_starting_with(&res, "refs/prs/").contains(&prs_refstr));
+        assert!(
+            listed_names_starting_with(&res, "refs/heads/")
+                .contains(&"refs/heads/main".to_string())
+        );
+
+        assert!(fetched_names_starting_with(&res, "refs/remotes/nostr/pr/")?.is_empty());
+        let listed_oid = &res
+            .listed
+            .iter()
+            .find(|(name, _)| name.eq(&prs_refstr))
+            .unwrap()
+            .1;
+        let fetched = res.git_repo.git_repo.find_reference(&format!(
+            "refs/remotes/nostr/prs/{}",
+            res.branch_name.trim_start_matches("pr/")
+        ))?;
+        let fetched_commit = fetched.peel_to_commit()?;
+        assert_eq!(fetched_commit.id().to_string(), *listed_oid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn none_lists_no_proposals() -> Result<()> {
+        let res = list_and_fetch_with_prs_as_refs(Some("none")).await?;
+        assert!(listed_names_starting_with(&res, "refs/heads/pr/").is_empty());
+        assert!(listed_names_starting_with(&res, "refs/prs/").is_empty());
+        assert!(
+            listed_names_starting_with(&res, "refs/heads/")
+                .contains(&"refs/heads/main".to_string())
+        );
+        assert!(fetched_names_starting_with(&res, "refs/remotes/nostr/pr")?.is_empty());
+        Ok(())
+    }
+}
+
+mod when_a_patch_is_from_neither_the_author_nor_a_maintainer {
+
+    use super::*;
+
+    /// a copy of the tip patch of `branch_name`'s proposal with a different
+    /// commit message, signed by `keys` so it appears to be a newer revision
+    fn forge_tip_patch(
+        events: &[Event],
+        branch_name: &str,
+        keys: &nostr::Keys,
+    ) -> Result<nostr::Event> {
+        let root = events
+            .iter()
+            .find(|e| {
+                e.tags
+                    .iter()
+                    .any(|t| t.as_slice() == ["t", "root"].as_slice())
+                    && e.tags
+                        .iter()
+                        .any(|t| t.as_slice() == ["branch-name", branch_name].as_slice())
+            })
+            .context("proposal root not found")?;
+        let patches: Vec<&Event> = events
+            .iter()
+            .filter(|e| e.kind.eq(&Kind::GitPatch) && e.tags.event_ids().any(|id| id.eq(&root.id)))
+            .collect();
+        let tip = patches
+            .iter()
+            .find(|p| {
+                !patches
+                    .iter()
+                    .any(|e| e.tags.event_ids().any(|id| id.eq(&p.id)))
+            })
+            .context("tip patch not found")?;
+        Ok(nostr::EventBuilder::new(
+            Kind::GitPatch,
+            tip.content.replacen("Subject: ", "Subject: forged ", 1),
+        )
+        .tags(
+            tip.tags
+                .iter()
+                .filter(|t| t.as_slice().first().is_some_and(|k| k != "commit"))
+                .cloned(),
+        )
+        .custom_created_at(tip.created_at + 10)
+        .sign_with_keys(keys)?)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn patch_excluded_with_warning_naming_its_author() -> Result<()> {
+        let (mut events, source_git_repo) =
+            prep_source_repo_and_events_including_proposals().await?;
+        let source_path = source_git_repo.dir.to_str().unwrap().to_string();
+        // TEST_KEY_2 is a maintainer in the default announcement
+        let announcement = generate_repo_ref_event_with_git_server(vec![source_path]);
+        events.push(
+            nostr::EventBuilder::new(Kind::GitRepoAnnouncement, "")
+                .tags(announcement.tags.iter().map(|t| {
+                    if t.as_slice().first().is_some_and(|k| k == "maintainers") {
+                        nostr::Tag::custom(
+                            nostr::TagKind::Custom("maintainers".into()),
+                            [TEST_KEY_1_KEYS.public_key().to_string()],
+                        )
+                    } else {
+                        t.clone()
+                    }
+                }))
+                .custom_created_at(announcement.created_at + 1)
+                .sign_with_keys(&TEST_KEY_1_KEYS)?,
+        );
+        events.push(forge_tip_patch(
+            &events,
+            FEATURE_BRANCH_NAME_1,
+            &TEST_KEY_2_KEYS,
+        )?);
+        let branch_name = get_proposal_branch_name_from_events(&events, FEATURE_BRANCH_NAME_1)?;
+
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line("list")?;
+            let res = p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(res)
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let res = cli_tester_handle.join().unwrap()?;
+
+        assert!(res.contains(&format!(
+            "from {TEST_KEY_2_NPUB} who is neither the proposal author nor a maintainer"
+        )));
+        let proposal_creation_repo = cli_tester_create_proposal_branches_ready_to_send()?;
+        assert!(res.contains(&format!(
+            "{} refs/heads/{branch_name}\r\n",
+            proposal_creation_repo.get_tip_of_local_branch(FEATURE_BRANCH_NAME_1)?,
+        )));
+        Ok(())
+    }
+}
+
+fn listed_refs(output: &str) -> HashSet<String> {
+    output
+        .split("\r\n")
+        .filter(|line| line.contains('\t'))
+        .map(String::from)
+        .collect()
+}
+
+mod when_cache_is_fresh {
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn second_ls_remote_is_answered_from_cache_without_relays() -> Result<()> {
+        let (state_event, source_git_repo) = generate_repo_with_state_event().await?;
+        let git_repo = prep_git_repo()?;
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.cache-max-age", "600")?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+            state_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<(String, GitTestRepo)> {
+            let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                "ls-remote",
+                NOSTR_REMOTE_NAME,
+            ])
+            .expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok((output, git_repo))
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let (first_output, git_repo) = cli_tester_handle.join().unwrap()?;
+        assert!(first_output.contains("nostr: fetching..."));
+        let reqs_during_first_run = r55.reqs.len();
+
+        // the relays no longer answer so a fetch would stall
+        let second_output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+            "ls-remote",
+            NOSTR_REMOTE_NAME,
+        ])
+        .expect_end_eventually()?;
+        assert!(second_output.contains("nostr: using cached state ("));
+        assert!(!second_output.contains("nostr: fetching"));
+        assert_eq!(r55.reqs.len(), reqs_during_first_run);
+        assert_eq!(listed_refs(&second_output), listed_refs(&first_output));
+        assert!(!listed_refs(&second_output).is_empty());
+        Ok(())
+    }
+}
+
+mod when_cache_is_stale_with_background_fetch {
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn answers_from_cache_and_refreshes_it_in_the_background() -> Result<()> {
+        let (state_event, source_git_repo) = generate_repo_with_state_event().await?;
+        let git_repo = prep_git_repo()?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+            state_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events.clone();
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<(String, GitTestRepo)> {
+            let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                "ls-remote",
+                NOSTR_REMOTE_NAME,
+            ])
+            .expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok((output, git_repo))
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let (first_output, git_repo) = cli_tester_handle.join().unwrap()?;
+        assert!(first_output.contains("nostr: fetching..."));
+
+        // nostr.cache-max-age is 0 during tests so the cache is already stale
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.background-fetch", "true")?;
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle =
+            std::thread::spawn(move || -> Result<(String, String, GitTestRepo)> {
+                let output = CliTester::new_git_with_remote_helper_from_dir(&git_repo.dir, [
+                    "ls-remote",
+                    NOSTR_REMOTE_NAME,
+                ])
+                .expect_end_eventually()?;
+                // the background fetch outlives git
+                let log_path = git_repo.dir.join(".git/nostr/last-background-fetch.log");
+                let mut log = String::new();
+                for _ in 0..100 {
+                    log = std::fs::read_to_string(&log_path).unwrap_or_default();
+                    if log.contains("fetched at") {
+                        break;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(200));
+                }
+                for p in [51, 52, 53, 55, 56, 57] {
+                    relay::shutdown_relay(8000 + p)?;
+                }
+                Ok((output, log, git_repo))
+            });
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let (second_output, log, git_repo) = cli_tester_handle.join().unwrap()?;
+        assert!(second_output.contains("nostr: using cached state ("));
+        assert!(second_output.contains("refreshing in the background"));
+        assert!(!second_output.contains("nostr: fetching"));
+        assert_eq!(listed_refs(&second_output), listed_refs(&first_output));
+        assert!(log.contains("fetched at"), "background fetch log: {log}");
+        // only the background fetch queried the relays
+        assert!(!r55.reqs.is_empty());
+        assert!(
+            !git_repo
+                .dir
+                .join(".git/nostr/background-fetch.lock")
+                .exists()
+        );
+        Ok(())
+    }
+}
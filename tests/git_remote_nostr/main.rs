@@ -9,6 +9,7 @@ use relay::Relay;
 use serial_test::serial;
 use test_utils::{git::GitTestRepo, *};
 
+mod doctor;
 mod fetch;
 mod list;
 mod push;
@@ -273,4 +274,110 @@ mod initially_runs_fetch {
         cli_tester_handle.join().unwrap()?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn reports_counts_per_kind_of_update() -> Result<()> {
+        let (events, _source_git_repo) = prep_source_repo_and_events_including_proposals().await?;
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester(&git_repo);
+            p.expect("nostr: fetching...\r\n")?;
+            p.expect_eventually("nostr updates: ")?;
+            let report = p.expect_eventually("\r\n")?;
+            assert!(report.contains("3 new proposals"), "{report}");
+            assert!(!report.contains("revision"), "{report}");
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_stored_nsec_is_encrypted {
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn continues_without_logging_in_instead_of_prompting_for_password() -> Result<()> {
+        let source_git_repo = prep_git_repo()?;
+        let git_repo = prep_git_repo()?;
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.nsec", TEST_KEY_1_ENCRYPTED_WEAK)?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester(&git_repo);
+            p.expect(
+                "nostr: stored nsec is encrypted with a password so continuing without logging in\r\n",
+            )?;
+            cli_expect_nostr_fetch(&mut p)?;
+            p.send_line("list")?;
+            let list = p.expect_eventually("\r\n\r\n")?;
+            assert!(!list.contains("password"), "{list}");
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
 }
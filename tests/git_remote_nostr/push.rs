@@ -441,6 +441,186 @@ mod two_branches_in_batch_one_added_one_updated {
         Ok(())
     }
 }
+mod when_repo_relays_reject_state_event {
+
+    use super::*;
+
+    fn reject_state_events(relay: &mut Relay, client_id: u64, event: nostr::Event) -> Result<()> {
+        if event.kind.as_u16() == 30618 {
+            relay.respond_ok(
+                client_id,
+                event,
+                Some("auth-required: authentication required to publish"),
+            )?;
+        } else {
+            relay.respond_ok(client_id, event, None)?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn errors_reported_for_all_refs_in_batch_and_git_server_rolled_back() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        let original_main_commit_id = source_git_repo.get_tip_of_local_branch("main")?;
+
+        std::fs::write(git_repo.dir.join("commit.md"), "some content")?;
+        git_repo.stage_and_commit("commit.md")?;
+
+        git_repo.create_branch("vnext")?;
+        git_repo.checkout("vnext")?;
+        std::fs::write(git_repo.dir.join("vnext.md"), "some content")?;
+        git_repo.stage_and_commit("vnext.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, Some(&reject_state_events), None),
+            Relay::new(8056, Some(&reject_state_events), None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("push refs/heads/vnext:refs/heads/vnext")?;
+            p.send_line("")?;
+            p.expect_eventually("error refs/heads/main nostr state update failed\r\n")?;
+            p.expect("error refs/heads/vnext nostr state update failed\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+
+            assert_eq!(
+                source_git_repo.get_tip_of_local_branch("main")?,
+                original_main_commit_id
+            );
+            assert!(source_git_repo.get_tip_of_local_branch("vnext").is_err());
+
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_previous_state_event_has_same_or_later_timestamp {
+    use nostr::{EventBuilder, Tag, TagKind, Timestamp};
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn new_state_event_is_newer_and_kept_by_relays() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        let original_main_commit_id = source_git_repo.get_tip_of_local_branch("main")?;
+
+        // a push moments earlier from a machine whose clock runs ahead
+        let repo_event = generate_repo_ref_event();
+        let previous_state_event = EventBuilder::new(STATE_KIND, "")
+            .tags([
+                Tag::identifier(repo_event.tags.identifier().unwrap()),
+                Tag::custom(TagKind::Custom("HEAD".into()), ["ref: refs/heads/main"]),
+                Tag::custom(
+                    TagKind::Custom("refs/heads/main".into()),
+                    [original_main_commit_id.to_string()],
+                ),
+            ])
+            .custom_created_at(Timestamp::now() + 5)
+            .sign_with_keys(&TEST_KEY_1_KEYS)?;
+
+        std::fs::write(git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = git_repo.stage_and_commit("commit.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+            previous_state_event.clone(),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("")?;
+            p.expect_eventually("ok refs/heads/main\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let state_events = r55
+            .events
+            .iter()
+            .filter(|e| e.kind.eq(&STATE_KIND))
+            .collect::<Vec<&nostr::Event>>();
+        let kept = state_events
+            .iter()
+            .max_by(|a, b| a.created_at.cmp(&b.created_at).then(b.id.cmp(&a.id)))
+            .unwrap();
+        assert!(kept.created_at > previous_state_event.created_at);
+        assert_eq!(
+            kept.tags
+                .iter()
+                .find(|t| t.kind().eq(&TagKind::Custom("refs/heads/main".into())))
+                .and_then(|t| t.content()),
+            Some(main_commit_id.to_string().as_str()),
+        );
+        Ok(())
+    }
+}
+
 mod delete_one_branch {
 
     use super::*;
@@ -1833,3 +2013,1250 @@ async fn push_new_pr_branch_creates_proposal() -> Result<()> {
 
     Ok(())
 }
+
+mod when_pushing_multiple_new_pr_branches_in_one_batch {
+
+    use super::*;
+
+    fn prep_git_repo_with_two_pr_branches() -> Result<(GitTestRepo, GitTestRepo)> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        for branch_name in ["pr/experiment-a", "pr/experiment-b"] {
+            git_repo.checkout("main")?;
+            git_repo.create_branch(branch_name)?;
+            git_repo.checkout(branch_name)?;
+            let file_name = format!("{}.md", branch_name.replace("pr/", ""));
+            std::fs::write(git_repo.dir.join(&file_name), "some content")?;
+            git_repo.stage_and_commit(&file_name)?;
+        }
+        Ok((git_repo, source_git_repo))
+    }
+
+    async fn push_both_branches(
+        git_repo: GitTestRepo,
+        source_git_repo: &GitTestRepo,
+        expect_output: fn(&mut CliTester) -> Result<()>,
+    ) -> Result<Vec<Event>> {
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+
+            p.send_line("push refs/heads/pr/experiment-a:refs/heads/pr/experiment-a")?;
+            p.send_line("push refs/heads/pr/experiment-b:refs/heads/pr/experiment-b")?;
+            p.send_line("")?;
+            expect_output(&mut p)?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r55
+            .events
+            .into_iter()
+            .filter(|e| e.kind == nostr::Kind::GitPatch)
+            .collect())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn refused_when_not_confirmed() -> Result<()> {
+        let (git_repo, source_git_repo) = prep_git_repo_with_two_pr_branches()?;
+
+        let patches = push_both_branches(git_repo, &source_git_repo, |p| {
+            p.expect_confirm_eventually(
+                "publish 2 pr/ branches as new public proposals?",
+                Some(false),
+            )?
+            .succeeds_with(Some(false))?;
+            p.expect_eventually(
+                "error refs/heads/pr/experiment-a refusing to create 2 new proposals in one push (set nostr.allow-bulk-proposals)\r\n",
+            )?;
+            p.expect(
+                "error refs/heads/pr/experiment-b refusing to create 2 new proposals in one push (set nostr.allow-bulk-proposals)\r\n",
+            )?;
+            Ok(())
+        })
+        .await?;
+
+        assert!(patches.is_empty(), "no proposals published");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn allowed_without_prompt_when_git_config_set() -> Result<()> {
+        let (git_repo, source_git_repo) = prep_git_repo_with_two_pr_branches()?;
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.allow-bulk-proposals", "true")?;
+
+        let patches = push_both_branches(git_repo, &source_git_repo, |p| {
+            p.expect_eventually("ok refs/heads/pr/experiment-a\r\n")?;
+            p.expect("ok refs/heads/pr/experiment-b\r\n")?;
+            Ok(())
+        })
+        .await?;
+
+        assert_eq!(patches.len(), 2, "a proposal published for each branch");
+        Ok(())
+    }
+}
+
+mod when_force_push_would_orphan_review_comments {
+
+    use super::*;
+
+    /// tip patch of the proposal on `branch_name` with a review comment on it
+    fn comment_on_tip_patch(events: &[Event], branch_name: &str) -> Result<(Event, Event)> {
+        let proposal = events
+            .iter()
+            .find(|e| {
+                e.tags
+                    .iter()
+                    .find(|t| t.as_slice()[0].eq("branch-name"))
+                    .is_some_and(|t| t.as_slice()[1].eq(branch_name))
+            })
+            .context("proposal not found")?;
+        let patches = events
+            .iter()
+            .filter(|e| {
+                e.kind == Kind::GitPatch
+                    && (e.id == proposal.id
+                        || e.tags
+                            .iter()
+                            .any(|t| t.is_root() && t.as_slice()[1].eq(&proposal.id.to_string())))
+            })
+            .collect::<Vec<&Event>>();
+        let tip_patch = patches
+            .iter()
+            .find(|patch| {
+                !patches.iter().any(|other| {
+                    other
+                        .tags
+                        .iter()
+                        .any(|t| t.is_reply() && t.as_slice()[1].eq(&patch.id.to_string()))
+                })
+            })
+            .context("tip patch not found")?;
+        let comment = nostr::EventBuilder::new(Kind::Custom(1111), "please rename this variable")
+            .tags(vec![nostr::Tag::event(tip_patch.id)])
+            .sign_with_keys(&TEST_KEY_2_KEYS)?;
+        Ok(((*tip_patch).clone(), comment))
+    }
+
+    /// replaces the last commit on the proposal branch and force pushes it
+    async fn force_push_rewritten_proposal(
+        push_options: &'static [&'static str],
+        expect_output: fn(&mut CliTester, &str) -> Result<()>,
+    ) -> Result<(Vec<Event>, Event)> {
+        let (events, _source_git_repo) = prep_source_repo_and_events_including_proposals().await?;
+        let (tip_patch, comment) = comment_on_tip_patch(&events, FEATURE_BRANCH_NAME_1)?;
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = [events.clone(), vec![comment.clone()]].concat();
+        r55.events = [events.clone(), vec![comment]].concat();
+
+        #[allow(clippy::mutable_key_type)]
+        let before = r55.events.iter().cloned().collect::<HashSet<Event>>();
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let branch_name = get_proposal_branch_name_from_events(&events, FEATURE_BRANCH_NAME_1)?;
+
+            let git_repo = clone_git_repo_with_nostr_url()?;
+            let oid = git_repo.checkout_remote_branch(&branch_name)?;
+            // replace last commit
+            git_repo.checkout("main")?;
+            git_repo.git_repo.branch(
+                &branch_name,
+                &git_repo.git_repo.find_commit(oid)?.parent(0)?,
+                true,
+            )?;
+            git_repo.checkout(&branch_name)?;
+            std::fs::write(git_repo.dir.join("new.md"), "some content")?;
+            git_repo.stage_and_commit("new.md")?;
+
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            for push_option in push_options {
+                p.send_line(format!("option push-option {push_option}").as_str())?;
+                p.expect("ok\r\n")?;
+            }
+            p.send_line(
+                format!("push +refs/heads/{branch_name}:refs/heads/{branch_name}").as_str(),
+            )?;
+            p.send_line("")?;
+            expect_output(&mut p, &branch_name)?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let new_patches = r55
+            .events
+            .iter()
+            .cloned()
+            .collect::<HashSet<Event>>()
+            .difference(&before)
+            .filter(|e| e.kind == Kind::GitPatch)
+            .cloned()
+            .collect::<Vec<Event>>();
+        Ok((new_patches, tip_patch))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn refused_when_not_confirmed() -> Result<()> {
+        let (new_patches, _) = force_push_rewritten_proposal(&[], |p, branch_name| {
+            p.expect_eventually(
+                "force push rewrites commits with review comments that will no longer point at any branch:\r\n",
+            )?;
+            p.expect(format!("  {TEST_KEY_2_NPUB}: \"please rename this variable\"\r\n").as_str())?;
+            p.expect_confirm("force push anyway and orphan 1 review comments?", Some(false))?
+                .succeeds_with(Some(false))?;
+            p.expect_eventually(
+                format!(
+                    "error refs/heads/{branch_name} force push would orphan 1 review comments on rewritten commits (push with -o yes to confirm)\r\n"
+                )
+                .as_str(),
+            )?;
+            Ok(())
+        })
+        .await?;
+
+        assert!(new_patches.is_empty(), "no revision published");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn push_option_yes_publishes_revision_tagging_replaced_patches() -> Result<()> {
+        let (new_patches, tip_patch) = force_push_rewritten_proposal(&["yes"], |p, branch_name| {
+            p.expect_eventually(format!("ok refs/heads/{branch_name}\r\n").as_str())?;
+            Ok(())
+        })
+        .await?;
+
+        let revision_root_patch = new_patches
+            .iter()
+            .find(|e| e.tags.iter().any(|t| t.as_slice()[1].eq("revision-root")))
+            .unwrap();
+        assert!(
+            revision_root_patch.tags.iter().any(|t| t.as_slice()
+                == [
+                    "e".to_string(),
+                    tip_patch.id.to_string(),
+                    String::new(),
+                    "replaces".to_string()
+                ]
+                .as_slice()),
+            "revision root tags the rewritten patch as replaced: {revision_root_patch:?}",
+        );
+        Ok(())
+    }
+}
+
+mod when_pushing_renamed_proposal_branch {
+    use nostr_sdk::EventId;
+
+    use super::*;
+
+    /// checks out the proposal branch listed by the remote helper, adds a
+    /// commit and pushes it under the name returned by `renamed`
+    async fn push_renamed_proposal_branch(
+        renamed: fn(&EventId) -> String,
+        expect_output: fn(&mut CliTester, &str) -> Result<()>,
+    ) -> Result<(Vec<Event>, EventId)> {
+        let (events, _source_git_repo) = prep_source_repo_and_events_including_proposals().await?;
+        let proposal_id = events
+            .iter()
+            .find(|e| {
+                e.tags
+                    .iter()
+                    .find(|t| t.as_slice()[0].eq("branch-name"))
+                    .is_some_and(|t| t.as_slice()[1].eq(FEATURE_BRANCH_NAME_1))
+            })
+            .context("proposal not found")?
+            .id;
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events.clone();
+
+        #[allow(clippy::mutable_key_type)]
+        let before = r55.events.iter().cloned().collect::<HashSet<Event>>();
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let branch_name = get_proposal_branch_name_from_events(&events, FEATURE_BRANCH_NAME_1)?;
+            let new_branch_name = renamed(&proposal_id);
+
+            let git_repo = clone_git_repo_with_nostr_url()?;
+            git_repo.checkout_remote_branch(&branch_name)?;
+            std::fs::write(git_repo.dir.join("new.md"), "some content")?;
+            git_repo.stage_and_commit("new.md")?;
+            git_repo.create_branch(&new_branch_name)?;
+            git_repo.checkout(&new_branch_name)?;
+
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line(
+                format!("push refs/heads/{new_branch_name}:refs/heads/{new_branch_name}").as_str(),
+            )?;
+            p.send_line("")?;
+            expect_output(&mut p, &new_branch_name)?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let new_patches = r55
+            .events
+            .iter()
+            .cloned()
+            .collect::<HashSet<Event>>()
+            .difference(&before)
+            .filter(|e| e.kind == Kind::GitPatch)
+            .cloned()
+            .collect::<Vec<Event>>();
+        Ok((new_patches, proposal_id))
+    }
+
+    fn replies_to_proposal(patch: &Event, proposal_id: &EventId) -> bool {
+        patch
+            .tags
+            .iter()
+            .any(|t| t.is_root() && t.as_slice()[1].eq(&proposal_id.to_string()))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn name_with_proposal_id_fragment_appends_patch_to_proposal() -> Result<()> {
+        let (new_patches, proposal_id) = push_renamed_proposal_branch(
+            |proposal_id| format!("pr/my-fix({})", &proposal_id.to_hex()[..8]),
+            |p, branch_name| {
+                p.expect_eventually(format!("ok refs/heads/{branch_name}\r\n").as_str())?;
+                Ok(())
+            },
+        )
+        .await?;
+
+        assert_eq!(new_patches.len(), 1);
+        assert!(
+            replies_to_proposal(&new_patches[0], &proposal_id),
+            "patch added to the original proposal thread: {:?}",
+            new_patches[0],
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn name_without_id_appends_patch_to_proposal_when_confirmed() -> Result<()> {
+        let (new_patches, proposal_id) = push_renamed_proposal_branch(
+            |_| "pr/my-fix".to_string(),
+            |p, branch_name| {
+                p.expect_confirm_eventually(
+                    format!(
+                        "push refs/heads/{branch_name} as an update to \"{PROPOSAL_TITLE_1}\"?"
+                    )
+                    .as_str(),
+                    Some(true),
+                )?
+                .succeeds_with(Some(true))?;
+                p.expect_eventually(format!("ok refs/heads/{branch_name}\r\n").as_str())?;
+                Ok(())
+            },
+        )
+        .await?;
+
+        assert_eq!(new_patches.len(), 1);
+        assert!(
+            replies_to_proposal(&new_patches[0], &proposal_id),
+            "patch added to the original proposal thread: {:?}",
+            new_patches[0],
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn name_without_id_creates_new_proposal_when_not_confirmed() -> Result<()> {
+        let (new_patches, proposal_id) = push_renamed_proposal_branch(
+            |_| "pr/my-fix".to_string(),
+            |p, branch_name| {
+                p.expect_confirm_eventually(
+                    format!(
+                        "push refs/heads/{branch_name} as an update to \"{PROPOSAL_TITLE_1}\"?"
+                    )
+                    .as_str(),
+                    Some(true),
+                )?
+                .succeeds_with(Some(false))?;
+                p.expect_eventually(format!("ok refs/heads/{branch_name}\r\n").as_str())?;
+                Ok(())
+            },
+        )
+        .await?;
+
+        assert!(!new_patches.is_empty());
+        assert!(
+            new_patches
+                .iter()
+                .all(|patch| !replies_to_proposal(patch, &proposal_id)),
+            "new proposal published rather than updating the original",
+        );
+        Ok(())
+    }
+}
+
+mod when_state_ref_ignore_configured {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn matching_refs_left_out_of_state_event() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+
+        std::fs::write(git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = git_repo.stage_and_commit("commit.md")?;
+
+        git_repo.create_branch("ci/build-1")?;
+        git_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.state-ref-ignore", "refs/heads/ci/*")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("push refs/heads/ci/build-1:refs/heads/ci/build-1")?;
+            p.send_line("")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            // the ref is still pushed to the git server
+            assert_eq!(
+                source_git_repo.get_tip_of_local_branch("ci/build-1")?,
+                main_commit_id
+            );
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let state_event = r56
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&STATE_KIND))
+            .context("state event not created")?;
+
+        let ref_names = state_event
+            .tags
+            .iter()
+            .filter_map(|t| t.as_slice().first().cloned())
+            .collect::<HashSet<String>>();
+        assert!(ref_names.contains("refs/heads/main"));
+        assert!(!ref_names.contains("refs/heads/ci/build-1"));
+        Ok(())
+    }
+}
+
+mod when_pushing_git_notes {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn notes_ref_in_state_event_and_fetchable_from_fresh_clone() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+
+        let head_commit_id = git_repo.git_repo.head()?.peel_to_commit()?.id();
+        let signature = git_repo.git_repo.signature()?;
+        git_repo.git_repo.note(
+            &signature,
+            &signature,
+            None,
+            head_commit_id,
+            "reviewed-by: fred",
+            false,
+        )?;
+        let notes_tip = git_repo
+            .git_repo
+            .refname_to_id("refs/notes/commits")?
+            .to_string();
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/notes/commits:refs/notes/commits")?;
+            p.send_line("")?;
+            p.expect_eventually("ok refs/notes/commits\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+
+            let cloned = clone_git_repo_with_nostr_url()?;
+            CliTester::new_git_with_remote_helper_from_dir(&cloned.dir, [
+                "fetch",
+                "origin",
+                "refs/notes/*:refs/notes/*",
+            ])
+            .expect_end_eventually()?;
+            let mut p = CliTester::new_git_with_remote_helper_from_dir(&cloned.dir, [
+                "notes",
+                "show",
+                &head_commit_id.to_string(),
+            ]);
+            p.expect("reviewed-by: fred\r\n")?;
+            p.expect_end()?;
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        assert_eq!(
+            source_git_repo
+                .git_repo
+                .refname_to_id("refs/notes/commits")?
+                .to_string(),
+            notes_tip
+        );
+        let state_event = r56
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&STATE_KIND))
+            .context("state event not created")?;
+        assert_eq!(
+            state_event
+                .tags
+                .iter()
+                .find(|t| t.as_slice()[0].eq("refs/notes/commits"))
+                .map(|t| t.as_slice()[1].clone()),
+            Some(notes_tip),
+        );
+        Ok(())
+    }
+}
+
+mod when_git_server_marked_read_only {
+    use super::*;
+
+    /// pushes main with a duplicate of the git server listed as a second git
+    /// server that is marked read-only in the announcement or git config
+    async fn push_main_with_read_only_mirror(
+        mark_in_announcement: bool,
+    ) -> Result<(GitTestRepo, GitTestRepo, Oid, Oid)> {
+        let (state_event, source_git_repo) = generate_repo_with_state_event().await?;
+        let mirror_git_repo = GitTestRepo::duplicate(&source_git_repo)?;
+        let mirror_main_before = mirror_git_repo.get_tip_of_local_branch("main")?;
+        let mirror_url = mirror_git_repo.dir.to_str().unwrap().to_string();
+
+        let git_repo = prep_git_repo()?;
+        std::fs::write(git_repo.dir.join("new.md"), "some content")?;
+        let main_commit_id = git_repo.stage_and_commit("new.md")?;
+
+        let mut repo_event = generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+            mirror_url.clone(),
+        ]);
+        if mark_in_announcement {
+            repo_event = nostr::EventBuilder::new(repo_event.kind, "")
+                .tags(
+                    repo_event
+                        .tags
+                        .iter()
+                        .cloned()
+                        .chain([nostr::Tag::custom(
+                            nostr::TagKind::Custom("mirror".into()),
+                            vec![mirror_url],
+                        )])
+                        .collect::<Vec<nostr::Tag>>(),
+                )
+                .sign_with_keys(&TEST_KEY_1_KEYS)?;
+        } else {
+            git_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.push-skip-server", &mirror_url)?;
+        }
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            repo_event,
+            state_event.clone(),
+        ];
+
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("")?;
+            p.expect_eventually("skipped 1 read-only mirror\r\n")?;
+            p.expect_eventually("ok ")?;
+            p.expect("refs/heads/main\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        Ok((
+            source_git_repo,
+            mirror_git_repo,
+            main_commit_id,
+            mirror_main_before,
+        ))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn mirror_listed_in_announcement_is_skipped() -> Result<()> {
+        let (source_git_repo, mirror_git_repo, main_commit_id, mirror_main_before) =
+            push_main_with_read_only_mirror(true).await?;
+        assert_eq!(
+            source_git_repo.get_tip_of_local_branch("main")?,
+            main_commit_id
+        );
+        assert_eq!(
+            mirror_git_repo.get_tip_of_local_branch("main")?,
+            mirror_main_before
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn server_in_git_config_push_skip_server_is_skipped() -> Result<()> {
+        let (source_git_repo, mirror_git_repo, main_commit_id, mirror_main_before) =
+            push_main_with_read_only_mirror(false).await?;
+        assert_eq!(
+            source_git_repo.get_tip_of_local_branch("main")?,
+            main_commit_id
+        );
+        assert_eq!(
+            mirror_git_repo.get_tip_of_local_branch("main")?,
+            mirror_main_before
+        );
+        Ok(())
+    }
+}
+
+mod when_announcement_lists_contributor_push_server {
+    use super::*;
+
+    fn reject_patch_events(relay: &mut Relay, client_id: u64, event: nostr::Event) -> Result<()> {
+        if event.kind == nostr::Kind::GitPatch {
+            relay.respond_ok(client_id, event, Some("blocked: not accepting patches"))?;
+        } else {
+            relay.respond_ok(client_id, event, None)?;
+        }
+        Ok(())
+    }
+
+    /// pushes a new pr/ branch as a non-maintainer with `contributor_push_url`
+    /// listed in the announcement's contributor-push tag. returns the tip of
+    /// the branch and the root patch event
+    async fn push_new_pr_branch(
+        contributor_push_url: String,
+        repo_relays_reject_patches: bool,
+    ) -> Result<(Oid, Event)> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        git_repo.create_branch("pr/feature")?;
+        git_repo.checkout("pr/feature")?;
+        std::fs::write(git_repo.dir.join("feature.md"), "some content")?;
+        let tip = git_repo.stage_and_commit("feature.md")?;
+
+        let repo_event = generate_repo_ref_event_with_git_server(vec![
+            source_git_repo.dir.to_str().unwrap().to_string(),
+        ]);
+        let repo_event = nostr::EventBuilder::new(repo_event.kind, "")
+            .tags(
+                repo_event
+                    .tags
+                    .iter()
+                    .cloned()
+                    .chain([nostr::Tag::custom(
+                        nostr::TagKind::Custom("contributor-push".into()),
+                        vec![contributor_push_url],
+                    )])
+                    .collect::<Vec<nostr::Tag>>(),
+            )
+            .sign_with_keys(&TEST_KEY_1_KEYS)?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            repo_event,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                if repo_relays_reject_patches {
+                    Some(&reject_patch_events)
+                } else {
+                    None
+                },
+                None,
+            ),
+            Relay::new(
+                8056,
+                if repo_relays_reject_patches {
+                    Some(&reject_patch_events)
+                } else {
+                    None
+                },
+                None,
+            ),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/heads/pr/feature:refs/heads/pr/feature")?;
+            p.send_line("")?;
+            p.expect_eventually("ok refs/heads/pr/feature\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        let root_patch = r55
+            .events
+            .into_iter()
+            .find(|e| e.kind == nostr::Kind::GitPatch)
+            .context("proposal not published")?;
+        Ok((tip, root_patch))
+    }
+
+    fn clone_url_tag(event: &Event) -> Option<Vec<String>> {
+        event
+            .tags
+            .iter()
+            .find(|t| t.as_slice()[0].eq("clone-url"))
+            .map(|t| t.as_slice().to_vec())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn commits_pushed_to_contributor_namespace_and_tagged_in_patch() -> Result<()> {
+        let contributor_push_server = GitTestRepo::recreate_as_bare(&prep_git_repo()?)?;
+        let url = contributor_push_server.dir.to_str().unwrap().to_string();
+        let (tip, root_patch) = push_new_pr_branch(url.clone(), false).await?;
+
+        let ref_name = format!("refs/heads/contrib/{TEST_KEY_2_NPUB}/feature");
+        assert_eq!(
+            contributor_push_server
+                .git_repo
+                .find_reference(&ref_name)?
+                .peel_to_commit()?
+                .id(),
+            tip,
+        );
+        assert_eq!(clone_url_tag(&root_patch), Some(vec![
+            "clone-url".to_string(),
+            url,
+            ref_name
+        ]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn proposal_published_without_clone_url_when_server_refuses() -> Result<()> {
+        let missing_server = std::env::temp_dir().join("ngit-missing-contributor-push-server");
+        let (_, root_patch) =
+            push_new_pr_branch(missing_server.to_str().unwrap().to_string(), false).await?;
+        assert_eq!(clone_url_tag(&root_patch), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn commits_not_pushed_until_repo_relays_accept_patches() -> Result<()> {
+        let contributor_push_server = GitTestRepo::recreate_as_bare(&prep_git_repo()?)?;
+        let url = contributor_push_server.dir.to_str().unwrap().to_string();
+        push_new_pr_branch(url, true).await?;
+        assert!(
+            contributor_push_server
+                .git_repo
+                .find_reference(&format!("refs/heads/contrib/{TEST_KEY_2_NPUB}/feature"))
+                .is_err()
+        );
+        Ok(())
+    }
+}
+
+mod when_repo_announced_before_first_commit {
+    use super::*;
+
+    fn generate_repo_ref_event_without_root_commit(git_server: &GitTestRepo) -> Result<Event> {
+        let repo_event = generate_repo_ref_event_with_git_server(vec![
+            git_server.dir.to_str().unwrap().to_string(),
+        ]);
+        Ok(nostr::EventBuilder::new(repo_event.kind, "")
+            .tags(
+                repo_event
+                    .tags
+                    .iter()
+                    .filter(|t| !t.as_slice()[0].eq("r"))
+                    .cloned()
+                    .collect::<Vec<nostr::Tag>>(),
+            )
+            .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    fn clone_into_new_dir() -> Result<(GitTestRepo, String)> {
+        let path = current_dir()?.join(format!("tmpgit-clone{}", rand::random::<u64>()));
+        std::fs::create_dir(path.clone())?;
+        let output = CliTester::new_git_with_remote_helper_from_dir(&path, [
+            "clone",
+            &get_nostr_remote_url()?,
+            ".",
+        ])
+        .expect_end_eventually()?;
+        Ok((GitTestRepo::open(&path)?, output))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn empty_clone_then_first_push_adds_state_and_root_commit() -> Result<()> {
+        let git_server = GitTestRepo::new_bare()?;
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_without_root_commit(&git_server)?,
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<Oid> {
+            let (git_repo, output) = clone_into_new_dir()?;
+            assert!(
+                output.contains("cloned an empty repository"),
+                "unexpected output: {output}"
+            );
+
+            set_git_nostr_login_config(&git_repo)?;
+            let mut config = git_repo.git_repo.config()?;
+            config.set_str("nostr.nsec", TEST_KEY_1_NSEC)?;
+            config.set_str("nostr.npub", TEST_KEY_1_NPUB)?;
+            let first_commit = git_repo.populate()?;
+
+            let mut p = cli_tester(&git_repo);
+            p.expect("nostr: fetching...\r\n")?;
+            p.send_line("list for-push")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("")?;
+            p.expect_eventually(
+                "repository was announced before its first commit so adding root commit",
+            )?;
+            p.expect_eventually("ok refs/heads/main\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+
+            let (recloned, _) = clone_into_new_dir()?;
+            assert_eq!(recloned.get_checked_out_branch_name()?, "main");
+            assert_eq!(recloned.get_tip_of_local_branch("main")?, first_commit);
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(first_commit)
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let first_commit = cli_tester_handle.join().unwrap()?;
+
+        let state_event = r56
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&STATE_KIND))
+            .context("state event not created")?;
+        assert_eq!(
+            state_event
+                .tags
+                .iter()
+                .filter(|t| t.kind().to_string().as_str().ne("d"))
+                .map(|t| t.as_slice().to_vec())
+                .collect::<HashSet<Vec<String>>>(),
+            HashSet::from([
+                vec!["HEAD".to_string(), "ref: refs/heads/main".to_string()],
+                vec!["refs/heads/main".to_string(), first_commit.to_string()],
+            ]),
+        );
+
+        let announcement = r55
+            .events
+            .iter()
+            .filter(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .max_by_key(|e| e.created_at)
+            .context("announcement missing")?;
+        assert_eq!(
+            announcement
+                .tags
+                .iter()
+                .find(|t| t.as_slice()[0].eq("r"))
+                .map(|t| t.as_slice().to_vec()),
+            Some(vec![
+                "r".to_string(),
+                first_commit.to_string(),
+                "euc".to_string()
+            ]),
+        );
+        Ok(())
+    }
+}
+
+mod when_git_server_head_is_detached {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn state_event_head_is_commit_id_and_round_trips_through_list_and_clone() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        let detached_commit_id = source_git_repo.get_tip_of_local_branch("main")?;
+        source_git_repo
+            .git_repo
+            .set_head_detached(detached_commit_id)?;
+
+        std::fs::write(git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = git_repo.stage_and_commit("commit.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![
+                source_git_repo.dir.to_str().unwrap().to_string(),
+            ]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("")?;
+            p.expect_eventually("ok refs/heads/main\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+
+            let mut p = cli_tester_after_fetch(&git_repo)?;
+            p.send_line("list")?;
+            let listed = p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            assert!(
+                listed.contains(&format!("{detached_commit_id} HEAD\r\n")),
+                "unexpected list output: {listed}"
+            );
+            assert!(!listed.contains("@refs/heads/main HEAD"));
+
+            let cloned = clone_git_repo_with_nostr_url()?;
+            assert!(cloned.git_repo.head_detached()?);
+            assert_eq!(
+                cloned.git_repo.head()?.peel_to_commit()?.id(),
+                detached_commit_id
+            );
+            assert_eq!(cloned.get_tip_of_local_branch("main")?, main_commit_id);
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let state_event = r56
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&STATE_KIND))
+            .context("state event not created")?;
+        assert_eq!(
+            state_event
+                .tags
+                .iter()
+                .find(|t| t.as_slice()[0].eq("HEAD"))
+                .map(|t| t.as_slice().to_vec()),
+            Some(vec!["HEAD".to_string(), detached_commit_id.to_string()]),
+        );
+        Ok(())
+    }
+}
+
+mod when_libgit2_cannot_reach_http_git_server {
+    use test_utils::git_http::GitHttpServer;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn push_falls_back_to_system_git_and_ok_printed() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let source_git_repo = GitTestRepo::recreate_as_bare(&git_repo)?;
+        let server = GitHttpServer::start_dropping_libgit2(&source_git_repo.dir)?;
+        // ssh would be attempted first
+        git_repo.git_repo.config()?.set_str(
+            "nostr.protocol-push",
+            &format!(
+                "http,127.0.0.1/{};",
+                source_git_repo.dir.file_name().unwrap().to_string_lossy()
+            ),
+        )?;
+
+        std::fs::write(git_repo.dir.join("commit.md"), "some content")?;
+        let main_commit_id = git_repo.stage_and_commit("commit.md")?;
+
+        let events = vec![
+            generate_test_key_1_metadata_event("fred"),
+            generate_test_key_1_relay_list_event(),
+            generate_repo_ref_event_with_git_server(vec![server.url.clone()]),
+        ];
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events = events.clone();
+        r55.events = events;
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_after_nostr_fetch_and_sent_list_for_push_responds(&git_repo)?;
+
+            p.send_line("push refs/heads/main:refs/heads/main")?;
+            p.send_line("")?;
+            p.expect_eventually(
+                format!("push: libgit2 failed to reach {}: ", server.url).as_str(),
+            )?;
+            p.expect_eventually("ok refs/heads/main\r\n")?;
+            p.expect_eventually("\r\n\r\n")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+
+            assert_eq!(
+                source_git_repo.get_tip_of_local_branch("main")?,
+                main_commit_id
+            );
+            Ok(())
+        });
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+}
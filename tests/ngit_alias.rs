@@ -0,0 +1,82 @@
+use anyhow::Result;
+use git::GitTestRepo;
+use nostr::nips::nip01::Coordinate;
+use nostr_sdk::{Kind, RelayUrl, ToBech32};
+use serial_test::serial;
+use test_utils::*;
+
+fn get_nostr_remote_url() -> Result<String> {
+    let repo_event = generate_repo_ref_event();
+    let naddr = Coordinate {
+        kind: Kind::GitRepoAnnouncement,
+        public_key: repo_event.pubkey,
+        identifier: repo_event.tags.identifier().unwrap().to_string(),
+        relays: vec![RelayUrl::parse("ws://localhost:8055").unwrap()],
+    }
+    .to_bech32()?;
+    Ok(format!("nostr://{naddr}"))
+}
+
+#[test]
+#[serial]
+fn add_list_and_remove_round_trip() -> Result<()> {
+    let test_repo = GitTestRepo::default();
+    let url = get_nostr_remote_url()?;
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["alias", "add", "upstream", &url]);
+    p.expect_end_with("added alias 'upstream'. use it as nostr://alias/upstream\r\n")?;
+    assert_eq!(
+        test_repo
+            .git_repo
+            .config()?
+            .get_string("nostr.alias.upstream")?,
+        url,
+    );
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["alias", "list"]);
+    p.expect_eventually(format!("upstream {url}\r\n").as_str())?;
+    p.expect_end_eventually()?;
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["alias", "remove", "upstream"]);
+    p.expect_end_with("removed alias 'upstream'\r\n")?;
+    assert!(
+        test_repo
+            .git_repo
+            .config()?
+            .get_string("nostr.alias.upstream")
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn add_rejects_invalid_nostr_url() -> Result<()> {
+    let test_repo = GitTestRepo::default();
+    let mut p = CliTester::new_from_dir(&test_repo.dir, [
+        "alias",
+        "add",
+        "upstream",
+        "https://example.com/repo.git",
+    ]);
+    p.expect_eventually("https://example.com/repo.git is not a valid nostr git url")?;
+    p.expect_end_eventually()?;
+    assert!(
+        test_repo
+            .git_repo
+            .config()?
+            .get_string("nostr.alias.upstream")
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn remove_names_missing_alias() -> Result<()> {
+    let test_repo = GitTestRepo::default();
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["alias", "remove", "missing"]);
+    p.expect_eventually("no nostr url alias 'missing'")?;
+    p.expect_end_eventually()?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use futures::join;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+static THREE_PATCH_PROPOSAL_TITLE: &str = "proposal with three patches";
+
+/// proposal on `FEATURE_BRANCH_NAME_1` adding a3.md, a4.md and a5.md in
+/// separate commits
+fn cli_tester_create_three_patch_proposal() -> Result<GitTestRepo> {
+    let git_repo = GitTestRepo::default();
+    git_repo.populate()?;
+    git_repo.create_branch(FEATURE_BRANCH_NAME_1)?;
+    git_repo.checkout(FEATURE_BRANCH_NAME_1)?;
+    for file_name in ["a3.md", "a4.md", "a5.md"] {
+        std::fs::write(git_repo.dir.join(file_name), "some content")?;
+        git_repo.stage_and_commit(&format!("add {file_name}"))?;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    let mut p = CliTester::new_from_dir(&git_repo.dir, [
+        "--nsec",
+        TEST_KEY_1_NSEC,
+        "--password",
+        TEST_PASSWORD,
+        "--disable-cli-spinners",
+        "send",
+        "HEAD~3",
+        "--title",
+        THREE_PATCH_PROPOSAL_TITLE,
+        "--description",
+        "description",
+    ]);
+    p.expect_end_eventually()?;
+    Ok(git_repo)
+}
+
+mod with_patches_arg {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn applies_only_selected_patch_to_current_branch() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_three_patch_proposal()?;
+
+            let test_repo = GitTestRepo::default();
+            let main_tip = test_repo.populate()?;
+            let mut config = test_repo.git_repo.config()?;
+            config.set_str("user.name", "Joe Bloggs")?;
+            config.set_str("user.email", "joe.bloggs@pm.me")?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p =
+                CliTester::new_from_dir(&test_repo.dir, ["apply", &proposal_id, "--patches", "2"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually(
+                "WARNING: earlier patches were skipped so later patches may not apply. `git am` will stop at the first that fails\r\n",
+            )?;
+            p.expect("applying to current branch with `git am`\r\n")?;
+            p.expect_end_eventually()?;
+
+            let head = test_repo.git_repo.head()?.peel_to_commit()?;
+            assert_eq!(head.summary(), Some("add a4.md"));
+            assert_eq!(head.parent_id(0)?, main_tip);
+            assert!(test_repo.dir.join("a4.md").exists());
+            assert!(!test_repo.dir.join("a3.md").exists());
+            assert!(!test_repo.dir.join("a5.md").exists());
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_a_patch_conflicts {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn reports_failed_patch_and_abort_restores_clean_tree() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_three_patch_proposal()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut config = test_repo.git_repo.config()?;
+            config.set_str("user.name", "Joe Bloggs")?;
+            config.set_str("user.email", "joe.bloggs@pm.me")?;
+            // the second patch adds a4.md
+            std::fs::write(test_repo.dir.join("a4.md"), "different content")?;
+            let tip_before = test_repo.stage_and_commit("add a different a4.md")?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "apply",
+                &proposal_id,
+                "--patches",
+                "1,2,3",
+            ]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("applying to current branch with `git am`\r\n")?;
+            p.expect("patch 2/3 'add a4.md' failed: a4.md had conflicts\r\n")?;
+            let mut c = p.expect_choice("`git am` stopped", vec![
+                "abort `git am` and restore the branch".to_string(),
+                "leave it to resolve manually".to_string(),
+                "retry with a 3-way merge".to_string(),
+            ])?;
+            c.succeeds_with(0, true, None)?;
+            p.expect_end_with("Error: aborted `git am` so the branch is as it was before\r\n")?;
+
+            assert_eq!(
+                test_repo.git_repo.head()?.peel_to_commit()?.id(),
+                tip_before
+            );
+            assert!(!test_repo.git_repo.path().join("rebase-apply").exists());
+            assert!(test_repo.git_repo.statuses(None)?.is_empty());
+            assert!(!test_repo.dir.join("a3.md").exists());
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
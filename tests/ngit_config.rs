@@ -0,0 +1,99 @@
+use anyhow::Result;
+use git::GitTestRepo;
+use serial_test::serial;
+use test_utils::*;
+
+mod config_show {
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn without_config_file_reports_defaults() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["config", "--show"]);
+        p.expect(
+            format!(
+                "config file: {} (not found)\r\n",
+                test_repo.dir.join(".git/test-config.toml").display()
+            )
+            .as_str(),
+        )?;
+        p.expect("fallback_relays = ws://localhost:8051 ws://localhost:8052 (default)\r\n")?;
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn config_file_values_override_defaults() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let config_path = test_repo.dir.join(".git/test-config.toml");
+        std::fs::write(
+            &config_path,
+            "fallback_relays = [\"ws://localhost:8058\"]\nrelay_timeout_secs = 3\n",
+        )?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["config", "--show"]);
+        p.expect(format!("config file: {}\r\n", config_path.display()).as_str())?;
+        p.expect(
+            format!(
+                "fallback_relays = ws://localhost:8058 ({})\r\n",
+                config_path.display()
+            )
+            .as_str(),
+        )?;
+        p.expect_eventually(
+            format!("relay_timeout_secs = 3 ({})\r\n", config_path.display()).as_str(),
+        )?;
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn git_config_overrides_config_file() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let config_path = test_repo.dir.join(".git/test-config.toml");
+        std::fs::write(
+            &config_path,
+            "fallback_relays = [\"ws://localhost:8058\"]\n",
+        )?;
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.fallback-relays", "ws://localhost:8057")?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["config", "--show"]);
+        p.expect(format!("config file: {}\r\n", config_path.display()).as_str())?;
+        p.expect("fallback_relays = ws://localhost:8057 (git config nostr.fallback-relays)\r\n")?;
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn use_blaster_from_git_config_and_built_in_blaster_relays_shown() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.use-blaster", "false")?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["config", "--show"]);
+        p.expect_eventually("use_blaster = false (git config nostr.use-blaster)\r\n")?;
+        p.expect("blaster_relays = ws://localhost:8057 (built in)\r\n")?;
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn socks_proxy_in_git_config_used_for_relays() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.proxy", "socks5h://127.0.0.1:9050")?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["config", "--show"]);
+        p.expect_eventually("relay_proxy = 127.0.0.1:9050 (git config nostr.proxy)\r\n")?;
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+}
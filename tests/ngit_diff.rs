@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use serial_test::serial;
+use test_utils::*;
+
+#[tokio::test]
+#[serial]
+async fn piped_diff_has_each_patch_hunk_in_order_and_exits_cleanly() -> Result<()> {
+    with_proposals(|test_repo, proposal_id| {
+        let output =
+            run_ngit_without_stdin_from_dir(&test_repo.dir, ["diff", &proposal_id[..8]], 10_000)?;
+        assert!(output.status.success(), "exit status: {}", output.status);
+        let diff = String::from_utf8(output.stdout)?;
+        let first = diff
+            .find("diff --git a/a3.md b/a3.md\n")
+            .context(format!("first patch diff missing: {diff}"))?;
+        let second = diff
+            .find("diff --git a/a4.md b/a4.md\n")
+            .context(format!("second patch diff missing: {diff}"))?;
+        assert!(first < second, "patches out of order: {diff}");
+        assert_eq!(diff.matches("@@ -0,0 +1 @@\n+some content\n").count(), 2);
+        assert!(
+            !diff.contains("Subject:") && !diff.contains("\n-- \n"),
+            "email headers and signature left out: {diff}"
+        );
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn piped_through_core_pager_unless_no_pager() -> Result<()> {
+    with_proposals(|test_repo, proposal_id| {
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("core.pager", "sed 's/^/paged: /'")?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["diff", proposal_id]);
+        p.expect_eventually("paged: diff --git a/a3.md b/a3.md\r\n")?;
+        p.expect_eventually("paged: diff --git a/a4.md b/a4.md\r\n")?;
+        p.expect_end_eventually()?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["diff", proposal_id, "--no-pager"]);
+        let output = p.expect_end_eventually()?;
+        assert!(
+            output.contains("diff --git a/a3.md b/a3.md\r\n") && !output.contains("paged: "),
+            "printed without pager: {output}"
+        );
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn list_view_full_diff_pages_then_returns_to_proposal_actions() -> Result<()> {
+    with_proposals(|test_repo, _| {
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("core.pager", "sed 's/^/paged: /'")?;
+
+        let actions = vec![
+            "create and checkout proposal branch (2 ahead 0 behind 'main')".to_string(),
+            "apply to current branch with `git am`".to_string(),
+            "select patches to apply…".to_string(),
+            "download to ./patches".to_string(),
+            "view full diff".to_string(),
+            "open in browser".to_string(),
+            "back".to_string(),
+        ];
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        p.expect("fetching updates...\r\n")?;
+        p.expect_choice_eventually("all proposals", vec![
+            format!("\"{PROPOSAL_TITLE_3}\""),
+            format!("\"{PROPOSAL_TITLE_2}\""),
+            format!("\"{PROPOSAL_TITLE_1}\""),
+        ])?
+        .succeeds_with(2, true, None)?;
+        p.expect_choice("", actions.clone())?
+            .succeeds_with(4, true, None)?;
+        p.expect_eventually("paged: diff --git a/a3.md b/a3.md\r\n")?;
+        p.expect_eventually("paged: diff --git a/a4.md b/a4.md\r\n")?;
+        p.expect_choice_eventually("", actions)?;
+        p.exit()
+    })
+    .await
+}
@@ -0,0 +1,157 @@
+use std::{env, path::PathBuf, process::Output};
+
+use anyhow::Result;
+use futures::join;
+use git::GitTestRepo;
+use serial_test::serial;
+use test_utils::{relay::Relay, *};
+
+fn dir_containing(exe_name: &str) -> Option<PathBuf> {
+    env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .find(|dir| dir.join(exe_name).is_file())
+}
+
+/// run `ngit doctor` with only the given directories on PATH
+fn run_doctor(test_repo: &GitTestRepo, path: Vec<PathBuf>) -> Result<Output> {
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+        .env("NGITTEST", "TRUE")
+        .env("RUST_BACKTRACE", "0")
+        .env("PATH", env::join_paths(path)?)
+        .env_remove("GIT_EXEC_PATH")
+        .current_dir(&test_repo.dir)
+        .arg("doctor")
+        .output()?;
+    Ok(output)
+}
+
+fn helper_dir() -> PathBuf {
+    assert_cmd::cargo::cargo_bin("git-remote-nostr")
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+fn git_dir() -> PathBuf {
+    dir_containing(&format!("git{}", env::consts::EXE_SUFFIX)).unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+mod helper_discoverable {
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn found_when_on_path() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let output = run_doctor(&test_repo, vec![helper_dir(), git_dir()])?;
+        assert!(stdout(&output).contains(&format!(
+            "✓ git-remote-nostr discoverable by git: {}\n",
+            assert_cmd::cargo::cargo_bin("git-remote-nostr").display()
+        )));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn missing_is_a_critical_failure() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let output = run_doctor(&test_repo, vec![git_dir()])?;
+        let exe_name = format!("git-remote-nostr{}", env::consts::EXE_SUFFIX);
+        assert!(stdout(&output).contains(&format!(
+            "✗ git-remote-nostr discoverable by git: {exe_name} not found in git's exec-path or PATH\n  hint: "
+        )));
+        assert!(!output.status.success());
+        Ok(())
+    }
+}
+
+mod login {
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn reports_nsec_in_local_git_config_without_secret() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.nsec", TEST_KEY_1_NSEC)?;
+        let output = stdout(&run_doctor(&test_repo, vec![helper_dir(), git_dir()])?);
+        assert!(output.contains(&format!(
+            "✓ login: nsec via local git config as {TEST_KEY_1_NPUB}\n"
+        )));
+        assert!(!output.contains(TEST_KEY_1_NSEC));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn not_logged_in_suggests_login() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let output = stdout(&run_doctor(&test_repo, vec![helper_dir(), git_dir()])?);
+        assert!(output.contains("✗ login: not logged in\n  hint: run `ngit account login`\n"));
+        Ok(())
+    }
+}
+
+mod cache {
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn not_created_yet_passes() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let output = stdout(&run_doctor(&test_repo, vec![helper_dir(), git_dir()])?);
+        assert!(output.contains(&format!(
+            "✓ local cache: {} (not created yet)\n",
+            test_repo.dir.join(".git/nostr-cache.lmdb").display()
+        )));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn corrupt_cache_is_a_critical_failure() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let cache_path = test_repo.dir.join(".git/nostr-cache.lmdb");
+        std::fs::create_dir_all(&cache_path)?;
+        std::fs::write(cache_path.join("data.mdb"), "not a database")?;
+        let output = run_doctor(&test_repo, vec![helper_dir(), git_dir()])?;
+        let stdout = stdout(&output);
+        assert!(stdout.contains(&format!(
+            "✗ local cache: {} cannot be opened: ",
+            cache_path.display()
+        )));
+        assert!(stdout.contains(&format!("  hint: delete {}", cache_path.display())));
+        assert!(!output.status.success());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn exits_successfully_when_no_critical_checks_fail() -> Result<()> {
+    let (mut r51, mut r52) = (Relay::new(8051, None, None), Relay::new(8052, None, None));
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<Output> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        let output = run_doctor(&test_repo, vec![helper_dir(), git_dir()]);
+        for p in [51, 52] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        output
+    });
+
+    let _ = join!(r51.listen_until_close(), r52.listen_until_close());
+    let output = cli_tester_handle.join().unwrap()?;
+    let stdout = stdout(&output);
+    assert!(stdout.contains("✓ relay ws://localhost:8051: reachable\n"));
+    assert!(stdout.contains("- repo announcement: no nostr remote or git config nostr.repo\n"));
+    assert!(output.status.success());
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::process::Output;
+
+use anyhow::Result;
+use futures::join;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+/// fetch with stdout piped so it can be checked on its own
+async fn run_fetch(args: &'static [&'static str]) -> Result<Output> {
+    // fallback (51,52) user write (53, 55) repo (55, 56)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+    );
+    r51.events = vec![
+        generate_test_key_1_metadata_event("fred"),
+        generate_test_key_1_relay_list_event(),
+    ];
+    r55.events = vec![generate_repo_ref_event(), get_pretend_proposal_root_event()];
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<Output> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+            .env("NGITTEST", "TRUE")
+            .env("RUST_BACKTRACE", "0")
+            .current_dir(&test_repo.dir)
+            .arg("fetch")
+            .args(args)
+            .output();
+        for p in [51, 52, 53, 55, 56] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(output?)
+    });
+
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+    );
+    let output = cli_tester_handle.join().unwrap()?;
+    assert!(output.status.success());
+    Ok(output)
+}
+
+#[tokio::test]
+#[serial]
+async fn reports_updates_of_each_kind() -> Result<()> {
+    let output = run_fetch(&[]).await?;
+    assert!(
+        String::from_utf8(output.stdout)?
+            .contains("updates: 1 new maintainer, 1 announcement update, 1 new proposal\n")
+    );
+    Ok(())
+}
+
+mod json {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn stdout_is_only_the_count_of_each_kind() -> Result<()> {
+        let output = run_fetch(&["--json"]).await?;
+        let counts: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(counts["new_maintainers"], 1);
+        assert_eq!(counts["announcement_updates"], 1);
+        assert_eq!(counts["state_updated"], false);
+        assert_eq!(counts["proposals"], 1);
+        assert_eq!(counts["revisions"], 0);
+        assert_eq!(counts["comments"], 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn with_quiet_stdout_is_only_the_count_of_each_kind() -> Result<()> {
+        let output = run_fetch(&["--quiet", "--json"]).await?;
+        let counts: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(counts["proposals"], 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use futures::join;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+fn no_args() -> Vec<&'static str> {
+    vec![]
+}
+
+#[test]
+#[serial]
+fn when_not_logged_in_offers_login() -> Result<()> {
+    let test_repo = GitTestRepo::default();
+    let mut p = CliTester::new_from_dir(&test_repo.dir, no_args());
+    p.expect_confirm(
+        "you are not logged in to nostr. login or create an account now?",
+        Some(true),
+    )?
+    .succeeds_with(Some(false))?;
+    p.expect("run `ngit account login` when you are ready\r\n")?;
+    p.expect_end()?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn when_logged_in_and_repo_not_announced_offers_init() -> Result<()> {
+    let test_repo = GitTestRepo::without_repo_in_git_config();
+    test_repo.populate()?;
+    let mut p = CliTester::new_from_dir(&test_repo.dir, [
+        "--nsec",
+        TEST_KEY_1_NSEC,
+        "--password",
+        TEST_PASSWORD,
+        "--disable-cli-spinners",
+    ]);
+    p.expect_confirm(
+        "this repository hasn't been announced on nostr. announce it now?",
+        Some(true),
+    )?
+    .succeeds_with(Some(false))?;
+    p.expect("run `ngit init` when you are ready to announce this repository\r\n")?;
+    p.expect_end()?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn when_logged_in_and_repo_announced_shows_repo_summary_and_hints() -> Result<()> {
+    // fallback (51,52) user write (53, 55) repo (55, 56)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+    );
+    r51.events.push(generate_test_key_1_relay_list_event());
+    r51.events.push(generate_test_key_1_metadata_event("fred"));
+    r51.events.push(generate_repo_ref_event());
+    r55.events.push(generate_repo_ref_event());
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, [
+            "--nsec",
+            TEST_KEY_1_NSEC,
+            "--password",
+            TEST_PASSWORD,
+            "--disable-cli-spinners",
+        ]);
+        p.expect_eventually("example name\r\n")?;
+        p.expect("example description\r\n")?;
+        p.expect_eventually("git servers: git:://123.gitexample.com/test\r\n")?;
+        p.expect("relays: ws://localhost:8055")?;
+        p.expect_eventually("run `ngit list` to view PRs or `ngit send` to submit one\r\n")?;
+        p.expect_end()?;
+        for p in [51, 52, 53, 55, 56] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use futures::join;
+use nostr::{Event, EventBuilder, Kind, Tag, TagStandard, Timestamp, nips::nip01::Coordinate};
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+fn repo_coordinate(repo_event: &Event) -> String {
+    format!(
+        "30617:{}:{}",
+        TEST_KEY_1_PUBKEY_HEX,
+        repo_event.tags.identifier().unwrap()
+    )
+}
+
+fn generate_second_repo_ref_event() -> Result<Event> {
+    let repo_event = generate_repo_ref_event();
+    Ok(EventBuilder::new(Kind::GitRepoAnnouncement, "")
+        .tags(
+            repo_event
+                .tags
+                .iter()
+                .map(|t| match t.as_slice()[0].as_str() {
+                    "d" => Tag::identifier("second-repo"),
+                    "name" => Tag::from_standardized(TagStandard::Name("second repo".into())),
+                    _ => t.clone(),
+                }),
+        )
+        .sign_with_keys(&TEST_KEY_1_KEYS)?)
+}
+
+fn generate_proposal(repo_event: &Event, title: &str, created_ago: u64) -> Result<Event> {
+    Ok(EventBuilder::new(
+        Kind::GitPatch,
+        format!(
+            "From fe973a840fba2a8ab37dd505c154854a69a6505c Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/1] {title}\n\nexampledescription"
+        ),
+    )
+    .tags([
+        Tag::coordinate(Coordinate::parse(repo_coordinate(repo_event))?),
+        Tag::hashtag("cover-letter"),
+        Tag::hashtag("root"),
+    ])
+    .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - created_ago))
+    .sign_with_keys(&TEST_KEY_2_KEYS)?)
+}
+
+async fn run_inbox(args: &'static [&'static str]) -> Result<String> {
+    let second_repo_event = generate_second_repo_ref_event()?;
+    let events = vec![
+        generate_test_key_1_relay_list_event(),
+        generate_test_key_1_metadata_event("fred"),
+        generate_test_key_2_metadata_event("carole"),
+        generate_repo_ref_event(),
+        second_repo_event.clone(),
+        generate_proposal(&generate_repo_ref_event(), "first repo older", 60 * 60)?,
+        generate_proposal(&generate_repo_ref_event(), "first repo newer", 60)?,
+        generate_proposal(&second_repo_event, "second repo proposal", 2 * 60 * 60)?,
+    ];
+    // fallback (51,52) user write (53, 55) repo (55, 56)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+    );
+    r51.events = events.clone();
+    r55.events = events;
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let mut p = CliTester::new_from_dir(
+            &test_repo.dir,
+            [
+                vec![
+                    "--nsec",
+                    TEST_KEY_1_NSEC,
+                    "--password",
+                    TEST_PASSWORD,
+                    "--disable-cli-spinners",
+                    "inbox",
+                ],
+                args.to_vec(),
+            ]
+            .concat(),
+        );
+        let output = p.expect_end_eventually()?;
+        for p in [51, 52, 53, 55, 56] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(output.replace('\r', ""))
+    });
+
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn open_proposals_grouped_by_maintained_repo_newest_first() -> Result<()> {
+    let output = run_inbox(&[]).await?;
+    assert!(
+        output.contains(
+            "example name\n  proposal first repo newer by carole (1 minute ago)\n  proposal first repo older by carole (1 hour ago)\nsecond repo\n  proposal second repo proposal by carole (2 hours ago)\n"
+        ),
+        "unexpected output: {output}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn json_lists_proposals_for_each_repo() -> Result<()> {
+    let output = run_inbox(&["--json"]).await?;
+    let inbox: serde_json::Value = serde_json::from_str(&output[output.find('[').unwrap()..])?;
+    let titles = inbox
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|repo| {
+            (
+                repo["name"].as_str().unwrap().to_string(),
+                repo["items"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|item| {
+                        assert_eq!(item["author"], "carole");
+                        assert_eq!(item["unread"], false);
+                        item["title"].as_str().unwrap().to_string()
+                    })
+                    .collect::<Vec<String>>(),
+            )
+        })
+        .collect::<Vec<(String, Vec<String>)>>();
+    assert_eq!(
+        titles,
+        vec![
+            (
+                "example name".to_string(),
+                vec![
+                    "first repo newer".to_string(),
+                    "first repo older".to_string()
+                ]
+            ),
+            (
+                "second repo".to_string(),
+                vec!["second repo proposal".to_string()]
+            ),
+        ]
+    );
+    Ok(())
+}
@@ -518,3 +518,972 @@ mod when_repo_not_previously_claimed {
     // TODO: cli caputuring input
 }
 // TODO: when_updating_existing_repoistory correct defaults are used
+
+mod when_repo_previously_announced_by_user {
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    fn get_cli_args_with(description: &str, other_maintainers: Vec<&str>) -> Vec<String> {
+        let identifier = generate_repo_ref_event()
+            .tags
+            .identifier()
+            .unwrap()
+            .to_string();
+        [
+            vec![
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "init",
+                "--title",
+                "example name",
+                "--identifier",
+                identifier.as_str(),
+                "--description",
+                description,
+                "--web",
+                "https://exampleproject.xyz",
+                "https://gitworkshop.dev/123",
+                "--relays",
+                "ws://localhost:8055",
+                "ws://localhost:8056",
+                "--clone-url",
+                "git:://123.gitexample.com/test",
+                "--earliest-unique-commit",
+                "9ee507fc4357d7ee16a5d8901bedcd103f23c17d",
+                "--other-maintainers",
+            ],
+            other_maintainers,
+        ]
+        .concat()
+        .into_iter()
+        .map(std::string::ToString::to_string)
+        .collect()
+    }
+
+    async fn run_init_with_relays(
+        args: Vec<String>,
+        interact: fn(&mut CliTester) -> Result<()>,
+    ) -> Result<Vec<nostr::Event>> {
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, args);
+            interact(&mut p)?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r55
+            .events
+            .into_iter()
+            .filter(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .collect())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn editing_description_leaves_other_tags_untouched() -> Result<()> {
+        let maintainers = vec![TEST_KEY_1_NPUB, TEST_KEY_2_NPUB];
+        let args = get_cli_args_with("updated description", maintainers);
+        let announcements = run_init_with_relays(args, |p| {
+            p.expect_eventually("repository announcement changes:\r\n")?;
+            p.expect("- [\"description\", \"example description\"]\r\n")?;
+            p.expect("+ [\"description\", \"updated description\"]\r\n")?;
+            p.expect_confirm("publish these changes?", Some(true))?
+                .succeeds_with(Some(true))?;
+            p.expect("publishing repostory reference...\r\n")?;
+            expect_prompt_to_set_origin(p)?;
+            p.expect_end_eventually()?;
+            Ok(())
+        })
+        .await?;
+
+        let baseline = generate_repo_ref_event();
+        let is_updated =
+            |t: &nostr::Tag| t.as_slice() == ["description", "updated description"].as_slice();
+        let updated = announcements
+            .iter()
+            .find(|e| e.tags.iter().any(is_updated))
+            .unwrap();
+        for name in ["d", "r", "name", "clone", "web", "relays", "maintainers"] {
+            let tag_values = |event: &nostr::Event| {
+                event
+                    .tags
+                    .iter()
+                    .filter(|t| t.as_slice()[0].eq(name))
+                    .map(|t| t.as_slice().to_vec())
+                    .collect::<Vec<Vec<String>>>()
+            };
+            assert_eq!(tag_values(updated), tag_values(&baseline), "{name} tag");
+        }
+        assert_eq!(updated.tags.len(), baseline.tags.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn removing_a_maintainer_must_be_confirmed() -> Result<()> {
+        let args = get_cli_args_with("example description", vec![TEST_KEY_1_NPUB]);
+        let announcements = run_init_with_relays(args, |p| {
+            p.expect_eventually("maintainers to be removed:\r\n")?;
+            p.expect(format!("  {TEST_KEY_2_NPUB}\r\n"))?;
+            p.expect_confirm(
+                "remove 1 maintainer from the repository announcement?",
+                Some(false),
+            )?
+            .succeeds_with(Some(false))?;
+            p.expect_end_with(
+                "Error: aborting so maintainers are not removed from the repository announcement\r\n",
+            )?;
+            Ok(())
+        })
+        .await?;
+
+        assert_eq!(announcements.len(), 1);
+        Ok(())
+    }
+}
+
+mod when_blaster_disabled {
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    async fn run_init_and_get_blaster_relay_events(
+        args: Vec<&'static str>,
+        use_blaster_git_config: Option<&'static str>,
+    ) -> Result<Vec<nostr::Event>> {
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::without_repo_in_git_config();
+            test_repo.populate()?;
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            if let Some(value) = use_blaster_git_config {
+                test_repo
+                    .git_repo
+                    .config()?
+                    .set_str("nostr.use-blaster", value)?;
+            }
+            let mut p = CliTester::new_from_dir(&test_repo.dir, args);
+            expect_msgs_first(&mut p)?;
+            expect_prompt_to_set_origin(&mut p)?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        assert_eq!(
+            r55.events
+                .iter()
+                .filter(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+                .count(),
+            1,
+        );
+        Ok(r57.events)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_blaster_flag_sends_nothing_to_blaster_relay() -> Result<()> {
+        let args = [get_cli_args(), vec!["--no-blaster"]].concat();
+        assert!(
+            run_init_and_get_blaster_relay_events(args, None)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn use_blaster_git_config_false_sends_nothing_to_blaster_relay() -> Result<()> {
+        assert!(
+            run_init_and_get_blaster_relay_events(get_cli_args(), Some("false"))
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn use_blaster_git_config_true_sends_announcement_to_blaster_relay() -> Result<()> {
+        let events = run_init_and_get_blaster_relay_events(get_cli_args(), Some("true")).await?;
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+                .count(),
+            1,
+        );
+        Ok(())
+    }
+}
+
+mod when_identifier_already_announced_by_user {
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    fn existing_identifier() -> String {
+        generate_repo_ref_event()
+            .tags
+            .identifier()
+            .unwrap()
+            .to_string()
+    }
+
+    fn get_cli_args_with_existing_identifier() -> Vec<String> {
+        let mut args = get_cli_args()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let i = args.iter().position(|a| a == "--identifier").unwrap();
+        args[i + 1] = existing_identifier();
+        // keep the maintainers of the existing announcement
+        args.push(TEST_KEY_2_NPUB.to_string());
+        args
+    }
+
+    async fn run_init_in_repo_without_coordinate(
+        interact: fn(&mut CliTester) -> Result<()>,
+    ) -> Result<Vec<nostr::Event>> {
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::without_repo_in_git_config();
+            test_repo.populate()?;
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            let mut p =
+                CliTester::new_from_dir(&test_repo.dir, get_cli_args_with_existing_identifier());
+            interact(&mut p)?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r55
+            .events
+            .into_iter()
+            .filter(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .collect())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn declining_to_edit_it_aborts_without_publishing() -> Result<()> {
+        let announcements = run_init_in_repo_without_coordinate(|p| {
+            let identifier = existing_identifier();
+            p.expect_confirm_eventually(
+                &format!(
+                    "you already announced a repository with identifier '{identifier}'. edit that one instead?"
+                ),
+                Some(true),
+            )?
+            .succeeds_with(Some(false))?;
+            p.expect_end_with(&format!(
+                "Error: aborting so your existing announcement with identifier '{identifier}' is not replaced\r\n"
+            ))?;
+            Ok(())
+        })
+        .await?;
+
+        assert!(announcements.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn accepting_uses_it_as_the_baseline() -> Result<()> {
+        let announcements = run_init_in_repo_without_coordinate(|p| {
+            p.expect_confirm_eventually(
+                &format!(
+                    "you already announced a repository with identifier '{}'. edit that one instead?",
+                    existing_identifier()
+                ),
+                Some(true),
+            )?
+            .succeeds_with(Some(true))?;
+            p.expect_eventually("repository announcement changes:\r\n")?;
+            p.expect_confirm_eventually("publish these changes?", Some(true))?
+                .succeeds_with(Some(true))?;
+            p.expect("publishing repostory reference...\r\n")?;
+            expect_prompt_to_set_origin(p)?;
+            p.expect_end_eventually()?;
+            Ok(())
+        })
+        .await?;
+
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(
+            announcements[0].tags.identifier(),
+            Some(existing_identifier().as_str())
+        );
+        Ok(())
+    }
+}
+
+mod when_clone_url_is_a_grasp_server {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, Ordering},
+        },
+    };
+
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    type Requests = Arc<Mutex<Vec<String>>>;
+
+    /// emulates a grasp server which creates a repository when its clone url
+    /// receives a NIP-98 authorized POST. returns the clone url for
+    /// example-identifier and the requests received
+    fn launch_grasp_server_fixture() -> Result<(String, Requests)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let clone_url = format!(
+            "http://{}/{TEST_KEY_1_NPUB}/example-identifier.git",
+            listener.local_addr()?
+        );
+        let requests: Requests = Arc::new(Mutex::new(vec![]));
+        let created = Arc::new(AtomicBool::new(false));
+        let recorded = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let recorded = recorded.clone();
+                let created = created.clone();
+                std::thread::spawn(move || serve_grasp_connection(stream, &recorded, &created));
+            }
+        });
+        Ok((clone_url, requests))
+    }
+
+    fn serve_grasp_connection(
+        mut stream: TcpStream,
+        requests: &Mutex<Vec<String>>,
+        created: &AtomicBool,
+    ) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line)? == 0 {
+                return Ok(());
+            }
+            let mut authorized = false;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header)?;
+                if header.trim().is_empty() {
+                    break;
+                }
+                if header.to_lowercase().starts_with("authorization: nostr ") {
+                    authorized = true;
+                }
+            }
+            let request = request_line
+                .trim()
+                .trim_end_matches(" HTTP/1.1")
+                .to_string();
+            let (status, body) = if request.starts_with("POST ") {
+                if authorized {
+                    created.store(true, Ordering::SeqCst);
+                    ("201 Created", "")
+                } else {
+                    ("401 Unauthorized", "")
+                }
+            } else if created.load(Ordering::SeqCst) {
+                ("200 OK", "0000")
+            } else {
+                ("404 Not Found", "")
+            };
+            requests.lock().unwrap().push(if authorized {
+                format!("{request} (nip98)")
+            } else {
+                request
+            });
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )?;
+        }
+    }
+
+    fn cli_args_with_clone_url(clone_url: &str, extra_args: &[&str]) -> Vec<String> {
+        let mut args = get_cli_args()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let i = args.iter().position(|a| a == "--clone-url").unwrap();
+        args[i + 1] = clone_url.to_string();
+        args.extend(extra_args.iter().map(|a| (*a).to_string()));
+        args
+    }
+
+    async fn run_init(args: Vec<String>, expected_report: Option<String>) -> Result<()> {
+        let git_repo = GitTestRepo::without_repo_in_git_config();
+        git_repo.populate()?;
+        git_repo.add_remote("origin", "https://localhost:1000")?;
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&git_repo.dir, args);
+            p.expect("searching for profile...\r\n")?;
+            p.expect("logged in as fred via cli arguments\r\n")?;
+            if let Some(expected_report) = expected_report {
+                p.expect(format!("{expected_report}\r\n"))?;
+            }
+            p.expect("publishing repostory reference...\r\n")?;
+            expect_prompt_to_set_origin(&mut p)?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn repo_created_with_nip98_auth_before_announcement() -> Result<()> {
+        let (clone_url, requests) = launch_grasp_server_fixture()?;
+        run_init(
+            cli_args_with_clone_url(&clone_url, &[]),
+            Some(format!("grasp server {clone_url}: created")),
+        )
+        .await?;
+        let path = format!("/{TEST_KEY_1_NPUB}/example-identifier.git");
+        assert_eq!(*requests.lock().unwrap(), vec![
+            format!("GET {path}/info/refs?service=git-upload-pack"),
+            format!("POST {path} (nip98)"),
+            format!("GET {path}/info/refs?service=git-upload-pack"),
+        ]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn existing_repo_reported_and_not_created_again() -> Result<()> {
+        let (clone_url, requests) = launch_grasp_server_fixture()?;
+        run_init(
+            cli_args_with_clone_url(&clone_url, &[]),
+            Some(format!("grasp server {clone_url}: created")),
+        )
+        .await?;
+        requests.lock().unwrap().clear();
+        run_init(
+            cli_args_with_clone_url(&clone_url, &[]),
+            Some(format!("grasp server {clone_url}: already exists")),
+        )
+        .await?;
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn skip_server_setup_makes_no_requests() -> Result<()> {
+        let (clone_url, requests) = launch_grasp_server_fixture()?;
+        run_init(
+            cli_args_with_clone_url(&clone_url, &["--skip-server-setup"]),
+            None,
+        )
+        .await?;
+        assert!(requests.lock().unwrap().is_empty());
+        Ok(())
+    }
+}
+
+mod when_repo_has_no_commits {
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn announced_without_root_commit() -> Result<()> {
+        let args = get_cli_args()
+            .into_iter()
+            .filter(|arg| {
+                !["--earliest-unique-commit", "9ee507fc4357d7ee16a5d8901bedcd103f23c17d"]
+                    .contains(arg)
+            })
+            .collect::<Vec<&str>>();
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::without_repo_in_git_config();
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, args);
+            p.expect_eventually(
+                "no commits yet so the root commit will be added to the announcement when you first push\r\n",
+            )?;
+            expect_prompt_to_set_origin(&mut p)?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        let announcement = r55
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .unwrap();
+        assert!(!announcement.tags.iter().any(|t| t.as_slice()[0].eq("r")));
+        Ok(())
+    }
+}
+
+mod when_yes_flag_given {
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn publishes_with_defaults_without_prompting() -> Result<()> {
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<(String, String)> {
+            let test_repo = GitTestRepo::without_repo_in_git_config();
+            test_repo.populate()?;
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "init",
+                "--yes",
+                "--relays",
+                "ws://localhost:8055",
+                "ws://localhost:8056",
+            ]);
+            let output = p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            let dir_name = test_repo
+                .dir
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            Ok((output, dir_name))
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let (output, dir_name) = cli_tester_handle.join().unwrap()?;
+        for prompt in [
+            "repo identifier",
+            "repo name",
+            "repo description",
+            "are you the only maintainer?",
+            "git server remote url(s)",
+            "repo website",
+            "earliest unique commit (to help with discoverability)",
+            "set remote \"origin\"",
+        ] {
+            assert!(!output.contains(prompt), "prompted for '{prompt}'");
+        }
+
+        let announcement = r55
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .unwrap();
+        let tag_values = |name: &str| -> Vec<String> {
+            announcement
+                .tags
+                .iter()
+                .find(|t| t.as_slice()[0].eq(name))
+                .map(|t| t.as_slice()[1..].to_vec())
+                .unwrap_or_default()
+        };
+        assert_eq!(tag_values("d"), vec![dir_name.clone()]);
+        assert_eq!(tag_values("name"), vec![dir_name.clone()]);
+        assert_eq!(
+            tag_values("clone"),
+            vec!["https://localhost:1000".to_string()]
+        );
+        assert_eq!(
+            tag_values("web"),
+            vec![format!("https://gitworkshop.dev/repo/{dir_name}")]
+        );
+        assert_eq!(tag_values("maintainers"), vec![
+            TEST_KEY_1_KEYS.public_key().to_string()
+        ]);
+        Ok(())
+    }
+}
+
+mod when_remote_is_on_github {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+    };
+
+    use futures::join;
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    type Requests = Arc<Mutex<Vec<String>>>;
+
+    static REPO_JSON: &str = r#"{"name":"My Repo","description":"an example repository","homepage":"https://myrepo.example","topics":["nostr","git"],"clone_url":"https://github.com/example/my-repo.git","private":false}"#;
+
+    /// emulates the github repos api for example/my-repo. returns the api url
+    /// and the requests received, with their authorization header
+    fn launch_github_api_fixture() -> Result<(String, Requests)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let api_url = format!("http://{}", listener.local_addr()?);
+        let requests: Requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = serve_github_api_connection(stream, &recorded);
+            }
+        });
+        Ok((api_url, requests))
+    }
+
+    fn serve_github_api_connection(
+        mut stream: TcpStream,
+        requests: &Mutex<Vec<String>>,
+    ) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut authorization = None;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("authorization") {
+                    authorization = Some(value.trim().to_string());
+                }
+            }
+        }
+        let request = request_line
+            .trim()
+            .trim_end_matches(" HTTP/1.1")
+            .to_string();
+        let (status, body) = if request == "GET /repos/example/my-repo" {
+            ("200 OK", REPO_JSON)
+        } else {
+            ("404 Not Found", r#"{"message":"Not Found"}"#)
+        };
+        requests.lock().unwrap().push(match authorization {
+            Some(authorization) => format!("{request} ({authorization})"),
+            None => request,
+        });
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(())
+    }
+
+    /// runs `ngit init --yes` so every prompt takes its default, and returns
+    /// the published announcement
+    async fn run_init_with_defaults(api_url: &str, extra_args: &[&str]) -> Result<nostr::Event> {
+        std::env::set_var("NGITTEST_GITHUB_API_URL", api_url);
+        // fallback (51,52) user write (53, 55) repo (55, 56) blaster (57)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+
+        let extra_args = extra_args
+            .iter()
+            .map(|a| (*a).to_string())
+            .collect::<Vec<String>>();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::without_repo_in_git_config();
+            test_repo.populate()?;
+            test_repo.add_remote("origin", "https://localhost:1000")?;
+            test_repo.add_remote("upstream", "https://github.com/example/my-repo.git")?;
+            let mut args = [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "init",
+                "--yes",
+                "--relays",
+                "ws://localhost:8055",
+                "ws://localhost:8056",
+            ]
+            .iter()
+            .map(|a| (*a).to_string())
+            .collect::<Vec<String>>();
+            args.extend(extra_args);
+            let mut p = CliTester::new_from_dir(&test_repo.dir, args);
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let res = cli_tester_handle.join().unwrap();
+        std::env::remove_var("NGITTEST_GITHUB_API_URL");
+        res?;
+        Ok(r55
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&Kind::GitRepoAnnouncement))
+            .unwrap()
+            .clone())
+    }
+
+    fn tag_values(announcement: &nostr::Event, name: &str) -> Vec<String> {
+        announcement
+            .tags
+            .iter()
+            .filter(|t| t.as_slice()[0].eq(name))
+            .flat_map(|t| t.as_slice()[1..].to_vec())
+            .collect()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn prompt_defaults_prefilled_from_github_api() -> Result<()> {
+        let (api_url, requests) = launch_github_api_fixture()?;
+        let announcement = run_init_with_defaults(&api_url, &[]).await?;
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert!(requests.lock().unwrap()[0].starts_with("GET /repos/example/my-repo"));
+        assert_eq!(tag_values(&announcement, "d"), vec!["my-repo".to_string()]);
+        assert_eq!(
+            tag_values(&announcement, "name"),
+            vec!["My Repo".to_string()]
+        );
+        assert_eq!(
+            tag_values(&announcement, "description"),
+            vec!["an example repository".to_string()]
+        );
+        assert_eq!(
+            tag_values(&announcement, "web"),
+            vec![
+                "https://myrepo.example".to_string(),
+                "https://gitworkshop.dev/repo/my-repo".to_string(),
+            ]
+        );
+        assert_eq!(
+            tag_values(&announcement, "t"),
+            vec!["nostr".to_string(), "git".to_string(),]
+        );
+        assert_eq!(
+            tag_values(&announcement, "clone"),
+            vec![
+                "https://localhost:1000".to_string(),
+                "https://github.com/example/my-repo.git".to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn github_token_sent_when_present() -> Result<()> {
+        let (api_url, requests) = launch_github_api_fixture()?;
+        std::env::set_var("GITHUB_TOKEN", "example-token");
+        let res = run_init_with_defaults(&api_url, &[]).await;
+        std::env::remove_var("GITHUB_TOKEN");
+        res?;
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["GET /repos/example/my-repo (Bearer example-token)".to_string()]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_import_makes_no_requests() -> Result<()> {
+        let (api_url, requests) = launch_github_api_fixture()?;
+        let announcement = run_init_with_defaults(&api_url, &["--no-import"]).await?;
+        assert!(requests.lock().unwrap().is_empty());
+        assert_eq!(
+            tag_values(&announcement, "name"),
+            vec!["my-repo".to_string()]
+        );
+        assert!(tag_values(&announcement, "t").is_empty());
+        Ok(())
+    }
+}
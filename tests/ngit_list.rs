@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use futures::join;
 use serial_test::serial;
 use test_utils::{git::GitTestRepo, relay::Relay, *};
@@ -207,7 +207,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, None)?;
@@ -324,7 +327,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -445,7 +451,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -520,7 +529,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -639,7 +651,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -659,7 +674,10 @@ mod when_main_branch_is_uptodate {
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch (2 ahead 0 behind 'main')"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -726,7 +744,10 @@ mod when_main_branch_is_uptodate {
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -746,7 +767,10 @@ mod when_main_branch_is_uptodate {
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch (2 ahead 0 behind 'main')"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -831,7 +855,10 @@ mod when_main_branch_is_uptodate {
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch and apply 1 appendments"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -901,7 +928,10 @@ mod when_main_branch_is_uptodate {
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch and apply 1 appendments"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -1011,7 +1041,10 @@ mod when_main_branch_is_uptodate {
                                 format!("checkout local branch with unpublished changes"),
                                 format!("discard unpublished changes and checkout new revision"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 "back".to_string(),
                             ])?;
                             c.succeeds_with(1, true, Some(0))?;
@@ -1087,11 +1120,16 @@ mod when_main_branch_is_uptodate {
                                 format!("checkout local branch with unpublished changes"),
                                 format!("discard unpublished changes and checkout new revision"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 "back".to_string(),
                             ])?;
                             c.succeeds_with(1, true, Some(1))?;
-                            p.expect_end_with("checked out latest version of proposal (2 ahead 0 behind 'main'), replacing unpublished version (2 ahead 0 behind 'main')\r\n")?;
+                            p.expect("checked out latest version of proposal (2 ahead 0 behind 'main'), replacing unpublished version (2 ahead 0 behind 'main')\r\n")?;
+                            p.expect("previous tip saved as refs/ngit/backup/pr/")?;
+                            p.expect_end_eventually()?;
 
                             for p in [51, 52, 53, 55, 56] {
                                 relay::shutdown_relay(8000 + p)?;
@@ -1345,17 +1383,22 @@ mod when_main_branch_is_uptodate {
                                 format!("\"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("revision 2: 0 patches changed, 2 unchanged\r\n")?;
                             p.expect("updated proposal available (2 ahead 0 behind 'main'). existing version is 2 ahead 1 behind 'main'\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout and overwrite existing proposal branch"),
                                 format!("checkout existing outdated proposal branch"),
                                 format!("apply to current branch with `git am`"),
+                                format!("select patches to apply…"),
                                 format!("download to ./patches"),
+                                format!("view full diff"),
+                                format!("open in browser"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
                             p.expect("checked out new version of proposal (2 ahead 0 behind 'main'), replacing old version (2 ahead 1 behind 'main')\r\n")?;
-                            p.expect_end()?;
+                            p.expect("previous tip saved as refs/ngit/backup/pr/")?;
+                            p.expect_end_eventually()?;
 
                             for p in [51, 52, 53, 55, 56] {
                                 relay::shutdown_relay(8000 + p)?;
@@ -1412,17 +1455,22 @@ mod when_main_branch_is_uptodate {
                                     format!("\"{PROPOSAL_TITLE_1}\""),
                                 ])?;
                                 c.succeeds_with(2, true, None)?;
+                                p.expect("revision 2: 0 patches changed, 2 unchanged\r\n")?;
                                 p.expect("updated proposal available (2 ahead 0 behind 'main'). existing version is 2 ahead 1 behind 'main'\r\n")?;
                                 let mut c = p.expect_choice("", vec![
                                     format!("checkout and overwrite existing proposal branch"),
                                     format!("checkout existing outdated proposal branch"),
                                     format!("apply to current branch with `git am`"),
+                                    format!("select patches to apply…"),
                                     format!("download to ./patches"),
+                                    format!("view full diff"),
+                                    format!("open in browser"),
                                     format!("back"),
                                 ])?;
                                 c.succeeds_with(0, true, Some(0))?;
                                 p.expect("checked out new version of proposal (2 ahead 0 behind 'main'), replacing old version (2 ahead 1 behind 'main')\r\n")?;
-                                p.expect_end()?;
+                                p.expect("previous tip saved as refs/ngit/backup/pr/")?;
+                                p.expect_end_eventually()?;
 
                                 for p in [51, 52, 53, 55, 56] {
                                     relay::shutdown_relay(8000 + p)?;
@@ -1456,6 +1504,36 @@ mod when_main_branch_is_uptodate {
                     Ok(())
                 }
 
+                /// the outdated proposal branch is overwritten so its old tip is
+                /// backed up and `ngit backups restore` brings it back
+                #[tokio::test]
+                #[serial]
+                async fn old_tip_backed_up_and_restorable() -> Result<()> {
+                    let (originating_repo, test_repo) = prep_and_run().await?;
+                    let branch_name = get_proposal_branch_name(&test_repo, FEATURE_BRANCH_NAME_1)?;
+                    let backup = test_repo
+                        .git_repo
+                        .references_glob(&format!("refs/ngit/backup/{branch_name}/*"))?
+                        .next()
+                        .context("no backup ref")??;
+                    let old_tip = backup.target().context("backup ref has no target")?;
+                    assert_ne!(
+                        old_tip,
+                        originating_repo.get_tip_of_local_branch(FEATURE_BRANCH_NAME_1)?,
+                    );
+
+                    let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                        "backups",
+                        "restore",
+                        backup.name().unwrap(),
+                    ]);
+                    p.expect(format!("restored '{branch_name}' to ").as_str())?;
+                    p.expect_eventually("previous tip saved as refs/ngit/backup/pr/")?;
+                    p.expect_end_eventually()?;
+                    assert_eq!(test_repo.get_tip_of_local_branch(&branch_name)?, old_tip);
+                    Ok(())
+                }
+
                 #[tokio::test]
                 #[serial]
                 async fn proposal_branch_tip_is_most_recent_proposal_revision_tip() -> Result<()> {
@@ -1473,3 +1551,2121 @@ mod when_main_branch_is_uptodate {
         }
     }
 }
+
+mod when_announcement_lists_no_relays {
+    use nostr::EventBuilder;
+
+    use super::*;
+
+    fn generate_repo_ref_event_without_relays() -> Result<nostr::Event> {
+        let event = generate_repo_ref_event();
+        Ok(EventBuilder::new(event.kind, event.content.clone())
+            .tags(
+                event
+                    .tags
+                    .iter()
+                    .filter(|t| t.as_slice().first().is_some_and(|k| k != "relays"))
+                    .cloned(),
+            )
+            .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn proposals_found_on_maintainers_write_relays() -> Result<()> {
+        // fallback (51,52) maintainer write (53, 55)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event_without_relays()?);
+        // only on a relay in the maintainer's relay list
+        r53.events.push(get_pretend_proposal_root_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect_eventually(
+                "repo announcement lists no relays — using maintainer's relay list\r\n",
+            )?;
+            p.expect_eventually("exampletitle")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relays
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod exit_codes {
+    use super::*;
+
+    #[test]
+    fn outside_a_git_repository_exits_with_git_error_code() -> Result<()> {
+        let dir = std::env::temp_dir().join("ngit-test-not-a-git-repository");
+        std::fs::create_dir_all(&dir)?;
+        let mut p = CliTester::new_from_dir(&dir, ["list"]);
+        p.expect("Error: failed to find a git repository\r\n")?;
+        p.expect_end_eventually()?;
+        p.expect_exit_code(5)
+    }
+
+    #[test]
+    #[serial]
+    fn fetch_with_all_relays_down_exits_with_network_error_code() -> Result<()> {
+        // no relays are launched
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let mut p = CliTester::new_with_timeout_from_dir(10000, &test_repo.dir, ["list"]);
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("Error: no repo announcement event found at specified coordinates")?;
+        p.expect_end_eventually()?;
+        p.expect_exit_code(4)
+    }
+}
+
+mod when_multiple_nostr_remotes_point_at_different_repositories {
+    use super::*;
+
+    fn prep_repo_with_upstream_and_fork_remotes() -> Result<GitTestRepo> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        test_repo.populate()?;
+        for identifier in ["upstream", "fork"] {
+            test_repo.add_remote(
+                identifier,
+                &format!(
+                    "nostr://{TEST_KEY_1_NPUB}/{}/{identifier}",
+                    urlencoding::encode("ws://localhost:8055"),
+                ),
+            )?;
+        }
+        Ok(test_repo)
+    }
+
+    fn remote_choices() -> Vec<String> {
+        vec![
+            format!("fork - {TEST_KEY_1_NPUB}/fork"),
+            format!("upstream - {TEST_KEY_1_NPUB}/upstream"),
+        ]
+    }
+
+    fn default_remote(test_repo: &GitTestRepo) -> Result<Option<String>> {
+        Ok(test_repo
+            .git_repo
+            .config()?
+            .get_string("nostr.default-remote")
+            .ok())
+    }
+
+    #[test]
+    #[serial]
+    fn prompts_to_choose_and_remembers_choice() -> Result<()> {
+        let test_repo = prep_repo_with_upstream_and_fork_remotes()?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        let mut c = p.expect_choice(
+            "select nostr repository from those listed as git remotes",
+            remote_choices(),
+        )?;
+        c.succeeds_with(1, false, Some(0))?;
+        p.expect(
+            "saved \"upstream\" as git config \"nostr.default-remote\". use --remote to choose another\r\n",
+        )?;
+        p.expect("fetching updates...\r\n")?;
+        p.exit()?;
+        assert_eq!(default_remote(&test_repo)?, Some("upstream".to_string()));
+
+        // no prompt the second time
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        p.expect("fetching updates...\r\n")?;
+        p.exit()
+    }
+
+    #[test]
+    #[serial]
+    fn remote_flag_skips_prompt_without_saving_default() -> Result<()> {
+        let test_repo = prep_repo_with_upstream_and_fork_remotes()?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--remote", "fork"]);
+        p.expect("fetching updates...\r\n")?;
+        p.exit()?;
+        assert_eq!(default_remote(&test_repo)?, None);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn remote_flag_naming_non_nostr_remote_exits_with_config_error_code() -> Result<()> {
+        let test_repo = prep_repo_with_upstream_and_fork_remotes()?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--remote", "origin"]);
+        p.expect("Error: \"origin\" is not a git remote with a nostr url\r\n")?;
+        p.expect_end_eventually()?;
+        p.expect_exit_code(2)
+    }
+
+    #[test]
+    #[serial]
+    fn warns_and_prompts_when_default_remote_was_renamed() -> Result<()> {
+        let test_repo = prep_repo_with_upstream_and_fork_remotes()?;
+        test_repo
+            .git_repo
+            .config()?
+            .set_str("nostr.default-remote", "origin")?;
+
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        p.expect(
+            "warning: git config \"nostr.default-remote\" is set to \"origin\" which is no longer a nostr git remote. it may have been renamed\r\n",
+        )?;
+        let mut c = p.expect_choice(
+            "select nostr repository from those listed as git remotes",
+            remote_choices(),
+        )?;
+        c.succeeds_with(0, false, Some(0))?;
+        p.expect(
+            "saved \"fork\" as git config \"nostr.default-remote\". use --remote to choose another\r\n",
+        )?;
+        p.exit()?;
+        assert_eq!(default_remote(&test_repo)?, Some("fork".to_string()));
+        Ok(())
+    }
+}
+
+mod unread_markers {
+    use std::path::PathBuf;
+
+    use nostr::{EventBuilder, Kind, Tag};
+
+    use super::*;
+
+    async fn run_list_with_relays(
+        dir: PathBuf,
+        extra_events: Vec<nostr::Event>,
+        run: fn(&PathBuf) -> Result<()>,
+    ) -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(get_pretend_proposal_root_event());
+        r55.events.extend(extra_events);
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            run(&dir)?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+
+    fn view_proposal(dir: &PathBuf, args: &[&str], choice: &str) -> Result<()> {
+        let mut p = CliTester::new_from_dir(dir, args);
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("\r\n")?; // some updates listed here
+        let mut c = p.expect_choice("all proposals", vec![choice.to_string()])?;
+        c.succeeds_with(0, true, None)?;
+        let mut c = p.expect_confirm(
+            "failed to find any patches on this proposal. choose another proposal?",
+            Some(true),
+        )?;
+        c.succeeds_with(Some(false))?;
+        p.expect_end()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn comments_since_last_viewed_are_marked_and_listed_with_unread() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+
+        // read state starts now so the existing proposal isn't marked
+        run_list_with_relays(test_repo.dir.clone(), vec![], |dir| {
+            view_proposal(dir, &["list"], "exampletitle")
+        })
+        .await?;
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let comment = EventBuilder::new(Kind::Custom(1111), "looks good")
+            .tags(vec![Tag::event(get_pretend_proposal_root_event().id)])
+            .sign_with_keys(&TEST_KEY_2_KEYS)?;
+
+        run_list_with_relays(test_repo.dir.clone(), vec![comment.clone()], |dir| {
+            view_proposal(dir, &["list", "--unread"], "● exampletitle (1 new comment)")
+        })
+        .await?;
+
+        run_list_with_relays(test_repo.dir.clone(), vec![comment], |dir| {
+            let mut p = CliTester::new_from_dir(dir, ["list", "--unread"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("no proposals with activity since they were last viewed\r\n")?;
+            p.expect_end()
+        })
+        .await
+    }
+}
+
+mod relay_advertising_max_filters {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn receives_a_request_per_filter() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        // as if fetched from the relay's NIP-11 document
+        std::fs::write(
+            test_repo.dir.join(".git/test-relay-info.json"),
+            format!(
+                "{{\"ws://localhost:8055\":{{\"fetched_at\":{},\"max_filters\":1,\"max_limit\":null}}}}",
+                nostr::Timestamp::now().as_u64()
+            ),
+        )?;
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&dir, ["list", "-v"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("advertises max_filters 1: splitting ")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        assert!(r55.reqs.len() > 1);
+        assert!(r55.reqs.iter().all(|filters| filters.len() == 1));
+        // relays that don't advertise limits still get a single request
+        assert!(r51.reqs.iter().any(|filters| filters.len() > 1));
+        Ok(())
+    }
+}
+
+mod when_proposal_based_on_commit_not_in_main {
+    use super::*;
+
+    /// main is rewritten after the proposal base so the base commit exists
+    /// locally but isn't in main's history, like a fork that diverged
+    fn repo_with_rewritten_main() -> Result<(GitTestRepo, String)> {
+        let test_repo = GitTestRepo::default();
+        let proposal_base = test_repo.populate()?;
+        test_repo.create_branch("fork")?;
+        test_repo.checkout("fork")?;
+        let diverged_at = test_repo.git_repo.find_commit(proposal_base)?.parent(0)?;
+        test_repo.git_repo.branch("main", &diverged_at, true)?;
+        test_repo.checkout("main")?;
+        std::fs::write(test_repo.dir.join("t2.md"), "rewritten content")?;
+        test_repo.stage_and_commit("rewrite t2.md")?;
+        Ok((test_repo, diverged_at.id().to_string()[..7].to_string()))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn warns_with_divergence_point_and_requires_confirmation() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<GitTestRepo> {
+            cli_tester_create_proposals()?;
+
+            let (test_repo, diverged_at) = repo_with_rewritten_main()?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let proposal_choices = vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\""),
+            ];
+            let action_choices = vec![
+                format!(
+                    "create and checkout proposal branch (2 ahead 1 behind 'main') - based on a commit not in 'main'"
+                ),
+                format!("apply to current branch with `git am`"),
+                format!("select patches to apply…"),
+                format!("download to ./patches"),
+                format!("view full diff"),
+                format!("open in browser"),
+                format!("back"),
+            ];
+            for confirm in [false, true] {
+                let mut c = p.expect_choice("all proposals", proposal_choices.clone())?;
+                c.succeeds_with(2, true, None)?;
+                p.expect(
+                    "WARNING: proposal is based on a commit not in 'main' - the author's fork may have diverged\r\n",
+                )?;
+                p.expect(format!(
+                    "it diverged from 'main' at {diverged_at} \"add t1.md\"\r\n"
+                ))?;
+                let mut c = p.expect_choice("", action_choices.clone())?;
+                c.succeeds_with(0, true, None)?;
+                let mut c = p.expect_confirm(
+                    "proposal is based on a commit not in 'main'. checkout anyway?",
+                    Some(false),
+                )?;
+                c.succeeds_with(Some(confirm))?;
+            }
+            p.expect(format!(
+                "checked out proposal as 'pr/{}(",
+                FEATURE_BRANCH_NAME_1,
+            ))?;
+            p.expect_end_eventually_with(")' branch\r\n")?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(test_repo)
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let test_repo = cli_tester_handle.join().unwrap()?;
+        assert_eq!(
+            get_proposal_branch_name(&test_repo, FEATURE_BRANCH_NAME_1)?,
+            test_repo.get_checked_out_branch_name()?,
+        );
+        Ok(())
+    }
+}
+
+mod when_another_process_is_fetching {
+    use super::*;
+
+    fn now() -> u64 {
+        nostr::Timestamp::now().as_u64()
+    }
+
+    /// runs `ngit list` with a fetch in-flight marker from another process
+    /// which, a second later, is replaced with `then` or removed if None.
+    /// returns whether it said it used the cache
+    async fn run_list_with_fetch_marker(
+        marker: String,
+        then: Option<String>,
+    ) -> Result<(bool, Relay<'static>)> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        std::fs::create_dir_all(test_repo.dir.join(".git/nostr"))?;
+        let marker_path = test_repo.dir.join(".git/nostr/fetch-in-flight");
+        std::fs::write(&marker_path, marker)?;
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<bool> {
+            let mut p = CliTester::new_from_dir(&dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            if let Some(then) = then {
+                std::fs::write(&marker_path, then)?;
+            } else {
+                std::fs::remove_file(&marker_path)?;
+            }
+            let used_cache = p.expect_eventually("another ngit process fetched ").is_ok();
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(used_cache)
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        Ok((cli_tester_handle.join().unwrap()?, r55))
+    }
+
+    // pid 1 is never this test's ngit process
+
+    #[tokio::test]
+    #[serial]
+    async fn waits_for_it_to_complete_then_uses_cache() -> Result<()> {
+        let started_at = now() - 5;
+        let (used_cache, r55) = run_list_with_fetch_marker(
+            format!("1 {started_at}\n"),
+            Some(format!("1 {started_at} {}\n", now() + 1)),
+        )
+        .await?;
+        assert!(used_cache);
+        assert!(r55.reqs.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetches_when_it_is_cancelled() -> Result<()> {
+        let (used_cache, r55) =
+            run_list_with_fetch_marker(format!("1 {}\n", now() - 5), None).await?;
+        assert!(!used_cache);
+        assert!(!r55.reqs.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_completed_before_starting_is_not_reused() -> Result<()> {
+        let marker = format!("1 {} {}\n", now() - 5, now() - 2);
+        let (used_cache, r55) = run_list_with_fetch_marker(marker.clone(), Some(marker)).await?;
+        assert!(!used_cache);
+        assert!(!r55.reqs.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn stale_marker_is_ignored() -> Result<()> {
+        let (used_cache, r55) =
+            run_list_with_fetch_marker(format!("1 {}\n", now() - 120), None).await?;
+        assert!(!used_cache);
+        assert!(!r55.reqs.is_empty());
+        Ok(())
+    }
+}
+
+mod when_repo_relay_withholds_eose {
+    use nostr::{EventBuilder, Kind, Tag, TagKind};
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn completes_within_deadline_and_warns_of_partial_results() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r56.events.push(generate_repo_ref_event());
+        // only the relay that withholds EOSE has this state event
+        let state = EventBuilder::new(Kind::Custom(30618), "")
+            .tags([
+                Tag::identifier(
+                    generate_repo_ref_event()
+                        .tags
+                        .identifier()
+                        .context("repo ref event has no identifier")?,
+                ),
+                Tag::custom(TagKind::Custom("refs/heads/main".into()), [
+                    "431b84edc0d2fa118d63faa3c2db9c73d630a5ae",
+                ]),
+            ])
+            .sign_with_keys(&TEST_KEY_1_KEYS)?;
+        r56.events.push(state.clone());
+        r56.withhold_eose = true;
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<std::time::Duration> {
+            let start = std::time::Instant::now();
+            let mut p = CliTester::new_from_dir(&dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("WARNING: partial results from ws://localhost:8056 (no EOSE)")?;
+            let elapsed = start.elapsed();
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(elapsed)
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let elapsed = cli_tester_handle.join().unwrap()?;
+        // a single 7s subscription deadline, not a hang or a deadline per
+        // follow-up request
+        assert!(elapsed < std::time::Duration::from_secs(14));
+        // events sent before the deadline are kept
+        let events = get_events_from_cache(&test_repo.dir, vec![
+            nostr::Filter::default().kind(Kind::Custom(30618)),
+        ])
+        .await?;
+        assert!(events.iter().any(|e| e.id == state.id));
+        Ok(())
+    }
+}
+
+mod when_timeout_is_reached {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn exits_with_network_error_reporting_relays_answered() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r56.events.push(generate_repo_ref_event());
+        r56.withhold_eose = true;
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<std::time::Duration> {
+            let start = std::time::Instant::now();
+            let mut p = CliTester::new_from_dir(&dir, ["list", "--timeout", "2"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("Error: timed out after 2s: ")?;
+            let answered = p.expect_eventually(" relays answered\r\n")?;
+            let elapsed = start.elapsed();
+            p.expect_end_eventually()?;
+            p.expect_exit_code(4)?;
+            let (answered, asked) = answered
+                .split_once('/')
+                .context("expected answered/asked relay count")?;
+            // the relay withholding EOSE never answers
+            assert!(answered.parse::<usize>()? < asked.parse::<usize>()?);
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(elapsed)
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let elapsed = cli_tester_handle.join().unwrap()?;
+        // well within the 7s relay timeout
+        assert!(elapsed < std::time::Duration::from_secs(4));
+        Ok(())
+    }
+}
+
+mod when_cancelled_with_ctrl_c {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn exits_cleanly_with_user_abort_code_and_cache_still_opens() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r56.events.push(generate_repo_ref_event());
+        // stalls the fetch until it is cancelled
+        r56.withhold_eose = true;
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            std::thread::sleep(std::time::Duration::from_secs(2));
+            p.interrupt()?;
+            p.expect_eventually("Error: cancelled after ")?;
+            p.expect_eventually("s — partial results cached\r\n")?;
+            p.expect_end_eventually()?;
+            p.expect_exit_code(130)?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        // the cache still opens and holds what relays returned before ctrl-c
+        let events = get_events_from_cache(&test_repo.dir, vec![
+            nostr::Filter::default().kind(nostr::Kind::GitRepoAnnouncement),
+        ])
+        .await?;
+        assert!(!events.is_empty());
+        Ok(())
+    }
+}
+
+mod when_no_repo_relay_can_be_reached {
+    use nostr::EventBuilder;
+
+    use super::*;
+
+    /// announcement listing relays on ports nothing listens on
+    fn generate_repo_ref_event_with_closed_relays() -> Result<nostr::Event> {
+        let event = generate_repo_ref_event();
+        Ok(EventBuilder::new(event.kind, event.content.clone())
+            .tags(
+                event
+                    .tags
+                    .iter()
+                    .filter(|t| t.as_slice().first().is_some_and(|k| k != "relays"))
+                    .cloned()
+                    .chain([nostr::Tag::custom(
+                        nostr::TagKind::Custom(std::borrow::Cow::Borrowed("relays")),
+                        vec!["ws://localhost:8061", "ws://localhost:8062"],
+                    )]),
+            )
+            .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn reports_unreachable_relays_rather_than_no_proposals() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        let announcement = generate_repo_ref_event_with_closed_relays()?;
+        r51.events.push(announcement);
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut p = CliTester::new_with_timeout_from_dir(20000, &test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually(
+                "Error: could not reach any of the repository's relays (ws://localhost:8061, ws://localhost:8062) — proposals may exist but are unavailable\r\n",
+            )?;
+            p.expect_end_eventually()?;
+            p.expect_exit_code(4)?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_run_from_linked_worktree {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn lists_proposals_using_main_repository_cache() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let worktree = test_repo.add_worktree("worktree-branch")?;
+
+            let mut p = CliTester::new_from_dir(&worktree.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            p.expect_choice("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\""),
+            ])?;
+            p.exit()?;
+
+            assert!(test_repo.dir.join(".git/nostr-cache.lmdb").exists());
+            // .git in a linked worktree is a file pointing to the main repository
+            assert!(worktree.dir.join(".git").is_file());
+            assert_eq!(
+                futures::executor::block_on(get_events_from_cache(&test_repo.dir, vec![
+                    nostr::Filter::default()
+                        .kind(nostr::Kind::GitPatch)
+                        .hashtag("root"),
+                ]))?
+                .len(),
+                3,
+            );
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_cache_dir_is_set {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn lists_proposals_without_a_cache_in_git_dir() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let cache_dir =
+                std::env::temp_dir().join(format!("ngit-test-cache-dir-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&cache_dir);
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.cache-dir", cache_dir.to_str().unwrap())?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            p.expect_choice("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\""),
+            ])?;
+            p.exit()?;
+
+            assert!(!test_repo.dir.join(".git/nostr-cache.lmdb").exists());
+            assert_eq!(std::fs::read_dir(&cache_dir)?.count(), 1);
+            assert_eq!(
+                futures::executor::block_on(get_events_from_cache(&test_repo.dir, vec![
+                    nostr::Filter::default()
+                        .kind(nostr::Kind::GitPatch)
+                        .hashtag("root"),
+                ]))?
+                .len(),
+                3,
+            );
+            std::fs::remove_dir_all(&cache_dir)?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_socks_proxy_is_configured {
+    use test_utils::socks_proxy::SocksProxy;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn relay_connections_go_through_it() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let proxy = SocksProxy::start()?;
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.proxy", &format!("socks5h://{}", proxy.addr))?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            p.expect_choice("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\""),
+            ])?;
+            p.exit()?;
+
+            let connections = proxy.connections();
+            for port in [8051, 8055] {
+                assert!(
+                    connections.iter().any(|c| c.ends_with(&format!(":{port}"))),
+                    "no connection to relay {port} through proxy in {connections:?}"
+                );
+            }
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_profile_in_global_cache {
+    use super::*;
+
+    /// runs `ngit list` in a new repository with an empty repository cache
+    /// and the announcement and fred's profile in the global cache, as if
+    /// cached when using another repository. the relays don't serve profiles.
+    /// `fetched_ago` is how long ago the profile was recorded as fetched
+    async fn run_list_with_profile_in_global_cache(
+        fetched_ago: Option<u64>,
+        ttl_secs: Option<&str>,
+    ) -> Result<(GitTestRepo, Relay<'static>)> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        save_event_in_global_cache(&test_repo.dir, &generate_repo_ref_event()).await?;
+        save_event_in_global_cache(&test_repo.dir, &generate_test_key_1_metadata_event("fred"))
+            .await?;
+        if let Some(ago) = fetched_ago {
+            std::fs::write(
+                test_repo.dir.join(".git/profiles-fetched-at.json"),
+                format!(
+                    "{{\"{TEST_KEY_1_PUBKEY_HEX}\":{}}}",
+                    nostr::Timestamp::now().as_u64() - ago
+                ),
+            )?;
+        }
+        if let Some(ttl_secs) = ttl_secs {
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.profile-cache-ttl-secs", ttl_secs)?;
+        }
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok((test_repo, r55))
+    }
+
+    fn profile_requested(relay: &Relay) -> bool {
+        let metadata = generate_test_key_1_metadata_event("fred");
+        relay
+            .reqs
+            .iter()
+            .flatten()
+            .any(|filter| filter.match_event(&metadata))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn name_resolves_without_relays_serving_profile() -> Result<()> {
+        let (test_repo, _) = run_list_with_profile_in_global_cache(Some(60), None).await?;
+        let profiles = get_events_from_cache(&test_repo.dir, vec![
+            nostr::Filter::default()
+                .kind(nostr::Kind::Metadata)
+                .author(TEST_KEY_1_KEYS.public_key()),
+        ])
+        .await?;
+        assert_eq!(profiles.len(), 1);
+        assert!(profiles[0].content.contains("fred"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fresh_profile_not_requested() -> Result<()> {
+        let (_, r55) = run_list_with_profile_in_global_cache(Some(60), None).await?;
+        assert!(!profile_requested(&r55));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn profile_never_recorded_as_fetched_is_requested() -> Result<()> {
+        let (_, r55) = run_list_with_profile_in_global_cache(None, None).await?;
+        assert!(profile_requested(&r55));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn profile_older_than_ttl_in_git_config_is_requested() -> Result<()> {
+        let (_, r55) = run_list_with_profile_in_global_cache(Some(60), Some("30")).await?;
+        assert!(profile_requested(&r55));
+        Ok(())
+    }
+}
+
+mod when_proposals_by_many_authors {
+    use nostr::{Keys, Kind};
+
+    use super::*;
+
+    /// pubkeys in the kind 0 filters of the REQs `relay` received
+    fn metadata_pubkeys_requested(relay: &Relay) -> Vec<nostr::PublicKey> {
+        relay
+            .reqs
+            .iter()
+            .flatten()
+            .filter(|filter| {
+                filter
+                    .kinds
+                    .as_ref()
+                    .is_some_and(|kinds| kinds.contains(&Kind::Metadata))
+            })
+            .flat_map(|filter| filter.authors.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn only_maintainer_profiles_requested_during_fetch() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        let mut authors = vec![];
+        for i in 0..20 {
+            let keys = Keys::generate();
+            authors.push(keys.public_key());
+            r55.events.push(make_event_old_or_change_user(
+                get_pretend_proposal_root_event(),
+                &keys,
+                i,
+            ));
+            r55.events.push(
+                nostr::EventBuilder::metadata(&nostr::Metadata::new().name(format!("author{i}")))
+                    .sign_with_keys(&keys)?,
+            );
+        }
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        // previously each of the 20 proposal authors was requested too
+        let requested = [&r51, &r52, &r53, &r55, &r56]
+            .into_iter()
+            .flat_map(metadata_pubkeys_requested)
+            .collect::<Vec<nostr::PublicKey>>();
+        assert!(requested.len() < authors.len());
+        assert!(
+            !requested
+                .iter()
+                .any(|public_key| authors.contains(public_key))
+        );
+        Ok(())
+    }
+}
+
+mod when_proposals_have_expiration {
+    use nostr::{EventBuilder, Kind, Tag, Timestamp};
+
+    use super::*;
+
+    fn cover_letter(title: &str, created_ago: u64, expiration: Timestamp) -> Result<nostr::Event> {
+        let pretend = get_pretend_proposal_root_event();
+        Ok(EventBuilder::new(
+            Kind::GitPatch,
+            format!(
+                "From fe973a840fba2a8ab37dd505c154854a69a6505c Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] {title}\n\nexampledescription"
+            ),
+        )
+        .tags(
+            pretend
+                .tags
+                .iter()
+                .filter(|t| !t.as_slice()[0].eq("e"))
+                .cloned()
+                .chain([Tag::expiration(expiration)]),
+        )
+        .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - created_ago))
+        .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn nearing_expiry_labelled_and_expired_skipped() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let now = Timestamp::now().as_u64();
+        let day = 24 * 60 * 60;
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(get_pretend_proposal_root_event());
+        r55.events.push(cover_letter(
+            "expiring soon",
+            10,
+            Timestamp::from(now + 12 * day - 60),
+        )?);
+        r55.events.push(cover_letter(
+            "expiring later",
+            20,
+            Timestamp::from(now + 60 * day),
+        )?);
+        r55.events.push(cover_letter(
+            "expired",
+            2 * day,
+            Timestamp::from(now - 60 * 60),
+        )?);
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let mut c = p.expect_choice("all proposals", vec![
+                "expiring soon (expires in 12d)".to_string(),
+                "expiring later".to_string(),
+                "exampletitle".to_string(),
+            ])?;
+            c.succeeds_with(0, true, None)?;
+            let mut c = p.expect_confirm(
+                "failed to find any patches on this proposal. choose another proposal?",
+                Some(true),
+            )?;
+            c.succeeds_with(Some(false))?;
+            p.expect_end()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()
+    }
+}
+
+mod when_relay_holds_more_proposals_than_page_size {
+    use nostr::{EventBuilder, Kind, Timestamp};
+
+    use super::*;
+
+    static TITLES: [&str; 5] = ["fifth", "fourth", "third", "second", "first"];
+
+    pub(super) fn cover_letter(title: &str, created_ago: u64) -> Result<nostr::Event> {
+        let pretend = get_pretend_proposal_root_event();
+        Ok(EventBuilder::new(
+            Kind::GitPatch,
+            format!(
+                "From fe973a840fba2a8ab37dd505c154854a69a6505c Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/2] {title}\n\nexampledescription"
+            ),
+        )
+        .tags(
+            pretend
+                .tags
+                .iter()
+                .filter(|t| !t.as_slice()[0].eq("e"))
+                .cloned(),
+        )
+        .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - created_ago))
+        .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    /// runs `ngit list` with the repo relay advertising a max_limit of 2 and
+    /// returns the REQs it received
+    async fn run_list(
+        args: &'static [&'static str],
+        fetch_max_events_per_kind: Option<&'static str>,
+        expected_notice: Option<&'static str>,
+        expected_titles: Vec<String>,
+    ) -> Result<Vec<Vec<nostr::Filter>>> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+        for (i, title) in TITLES.iter().enumerate() {
+            r55.events
+                .push(cover_letter(title, 10 * (u64::try_from(i)? + 1))?);
+        }
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        // as if fetched from the relay's NIP-11 document
+        std::fs::write(
+            test_repo.dir.join(".git/test-relay-info.json"),
+            format!(
+                "{{\"ws://localhost:8055\":{{\"fetched_at\":{},\"max_filters\":null,\"max_limit\":2}}}}",
+                Timestamp::now().as_u64()
+            ),
+        )?;
+        if let Some(max) = fetch_max_events_per_kind {
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.fetch-max-events-per-kind", max)?;
+        }
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [&["list"], args].concat());
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            if let Some(notice) = expected_notice {
+                p.expect_eventually(format!("{notice}\r\n").as_str())?;
+            }
+            p.expect_choice("all proposals", expected_titles)?;
+            p.exit()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r55.reqs)
+    }
+
+    fn windowed_patch_reqs(reqs: &[Vec<nostr::Filter>]) -> usize {
+        reqs.iter()
+            .filter(|filters| {
+                filters.iter().any(|f| {
+                    f.until.is_some()
+                        && f.kinds
+                            .as_ref()
+                            .is_some_and(|k| k.contains(&Kind::GitPatch))
+                })
+            })
+            .count()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetches_windows_until_every_proposal_is_returned() -> Result<()> {
+        let reqs = run_list(
+            &[],
+            None,
+            None,
+            TITLES.iter().map(ToString::to_string).collect(),
+        )
+        .await?;
+        assert!(windowed_patch_reqs(&reqs) >= 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn stops_at_max_events_per_kind_and_says_so() -> Result<()> {
+        let reqs = run_list(
+            &[],
+            Some("2"),
+            Some("showing most recent 2 proposals (use --fetch-all for complete history)"),
+            TITLES[..2].iter().map(ToString::to_string).collect(),
+        )
+        .await?;
+        assert_eq!(windowed_patch_reqs(&reqs), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_all_removes_max_events_per_kind() -> Result<()> {
+        run_list(
+            &["--fetch-all"],
+            Some("2"),
+            None,
+            TITLES.iter().map(ToString::to_string).collect(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+mod when_announcement_lists_many_relays {
+    use futures::future::join_all;
+
+    use super::{when_relay_holds_more_proposals_than_page_size::cover_letter, *};
+
+    static PORTS: [u16; 8] = [51, 52, 53, 54, 55, 56, 57, 58];
+
+    /// runs `ngit list` against 8 relays holding the same events, plus
+    /// `extra_events` on the relay with the given port, fetching from one
+    /// relay at a time, and returns the relays that received a REQ. fails if
+    /// reliability was recorded for a relay that wasn't asked
+    async fn run_list_with_8_relays(
+        args: &'static [&'static str],
+        extra_events: Vec<(u16, nostr::Event)>,
+        expected_titles: Vec<String>,
+    ) -> Result<Vec<u16>> {
+        let events = vec![
+            generate_test_key_1_relay_list_event(),
+            generate_test_key_1_metadata_event("fred"),
+            generate_repo_ref_event(),
+        ];
+        let mut relays = PORTS
+            .iter()
+            .map(|p| {
+                let mut relay = Relay::new(8000 + p, None, None);
+                relay.events.clone_from(&events);
+                relay.events.extend(
+                    extra_events
+                        .iter()
+                        .filter(|(port, _)| port == p)
+                        .map(|(_, event)| event.clone()),
+                );
+                relay
+            })
+            .collect::<Vec<Relay>>();
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let mut git_config = test_repo.git_repo.config()?;
+        git_config.set_str(
+            "nostr.fallback-relays",
+            &PORTS
+                .iter()
+                .map(|p| format!("ws://localhost:80{p}"))
+                .collect::<Vec<String>>()
+                .join(","),
+        )?;
+        git_config.set_str("nostr.max-concurrent-relays", "1")?;
+        // the last three relays have been the fastest
+        std::fs::write(
+            test_repo.dir.join(".git/test-relay-stats.json"),
+            "{\"ws://localhost:8058\":{\"successes\":5,\"failures\":0,\"mean_latency_ms\":10},\"ws://localhost:8057\":{\"successes\":5,\"failures\":0,\"mean_latency_ms\":20},\"ws://localhost:8056\":{\"successes\":5,\"failures\":0,\"mean_latency_ms\":30}}",
+        )?;
+
+        let expect_skipped = !args.contains(&"--fetch-all") && extra_events.is_empty();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [&["list"], args].concat());
+            p.expect("fetching updates...\r\n")?;
+            if expect_skipped {
+                p.expect_eventually(
+                    "skipped 5 relays as 3 others returned the same newest event\r\n",
+                )?;
+            }
+            if expected_titles.is_empty() {
+                p.expect_eventually("all proposals")?;
+            } else {
+                p.expect_eventually("\r\n")?; // some updates listed here
+                p.expect_choice("all proposals", expected_titles)?;
+            }
+            p.exit()?;
+            for p in PORTS {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(std::fs::read_to_string(
+                test_repo.dir.join(".git/test-relay-stats.json"),
+            )?)
+        });
+
+        let _ = join_all(relays.iter_mut().map(Relay::listen_until_close)).await;
+        let relay_stats = cli_tester_handle.join().unwrap()?;
+        let relays_asked = PORTS
+            .iter()
+            .zip(&relays)
+            .filter(|(_, relay)| !relay.reqs.is_empty())
+            .map(|(p, _)| *p)
+            .collect::<Vec<u16>>();
+        for p in PORTS {
+            assert_eq!(
+                relay_stats.contains(&format!("ws://localhost:80{p}")),
+                relays_asked.contains(&p),
+                "relay stats for ws://localhost:80{p}: {relay_stats}",
+            );
+        }
+        Ok(relays_asked)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetches_from_most_reliable_until_3_agree_and_skips_the_rest() -> Result<()> {
+        // with more than one relay fetched at a time, a fourth would be asked
+        // before the first three agreed
+        assert_eq!(
+            run_list_with_8_relays(&[], vec![], vec![]).await?,
+            vec![56, 57, 58]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn keeps_asking_for_proposals_until_relays_agree_on_them() -> Result<()> {
+        // the first three agree on the newest event overall but not on the
+        // newest proposal, so the fourth is asked and returns an older one
+        let relays_asked = run_list_with_8_relays(
+            &[],
+            vec![
+                (56, cover_letter("newer", 10)?),
+                (55, cover_letter("older", 20)?),
+            ],
+            vec!["newer".to_string(), "older".to_string()],
+        )
+        .await?;
+        assert!(relays_asked.contains(&55));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_all_asks_every_relay() -> Result<()> {
+        assert_eq!(
+            run_list_with_8_relays(&["--fetch-all"], vec![], vec![]).await?,
+            PORTS.to_vec()
+        );
+        Ok(())
+    }
+}
+
+mod when_downloading_patches {
+    use super::*;
+
+    static HOSTILE_TITLE: &str = "../../.git/hooks/post-checkout";
+
+    fn send_proposal_with_hostile_title() -> Result<()> {
+        let originating_repo = GitTestRepo::default();
+        originating_repo.populate()?;
+        originating_repo.create_branch(FEATURE_BRANCH_NAME_1)?;
+        originating_repo.checkout(FEATURE_BRANCH_NAME_1)?;
+        std::fs::write(originating_repo.dir.join("a3.md"), "some content")?;
+        originating_repo.stage_and_commit(HOSTILE_TITLE)?;
+        let mut p = CliTester::new_from_dir(&originating_repo.dir, [
+            "--nsec",
+            TEST_KEY_1_NSEC,
+            "--password",
+            TEST_PASSWORD,
+            "--disable-cli-spinners",
+            "send",
+            "HEAD~1",
+            "--title",
+            format!("\"{HOSTILE_TITLE}\"").as_str(),
+            "--description",
+            "\"proposal a description\"",
+        ]);
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    fn download_patches(test_repo: &GitTestRepo, force: bool) -> Result<String> {
+        let mut p = CliTester::new_from_dir(
+            &test_repo.dir,
+            if force {
+                vec!["list", "--force"]
+            } else {
+                vec!["list"]
+            },
+        );
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("\r\n")?; // some updates listed here
+        let mut c = p.expect_choice("all proposals", vec![format!("\"{HOSTILE_TITLE}\"")])?;
+        c.succeeds_with(0, true, None)?;
+        let mut c = p.expect_choice("", vec![
+            format!("create and checkout proposal branch (1 ahead 0 behind 'main')"),
+            format!("apply to current branch with `git am`"),
+            format!("select patches to apply…"),
+            format!("download to ./patches"),
+            format!("view full diff"),
+            format!("open in browser"),
+            format!("back"),
+        ])?;
+        c.succeeds_with(3, true, None)?;
+        p.expect_end_eventually()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn files_stay_inside_patches_dir_and_arent_overwritten_without_force() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            send_proposal_with_hostile_title()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let output = download_patches(&test_repo, false)?;
+            assert!(output.contains("created 1 patch files in ./patches/"));
+
+            let file_names = std::fs::read_dir(test_repo.dir.join("patches"))?
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+                .collect::<Result<Vec<String>>>()?;
+            assert_eq!(file_names.len(), 1);
+            assert!(file_names[0].ends_with("-0001-git-hooks-post-checkout.patch"));
+            assert!(!test_repo.dir.join(".git/hooks/post-checkout").exists());
+
+            let output = download_patches(&test_repo, false)?;
+            assert!(output.contains("already exists. use --force to overwrite it"));
+
+            let output = download_patches(&test_repo, true)?;
+            assert!(output.contains("created 1 patch files in ./patches/"));
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_proposal_has_attachments {
+    use test_utils::blossom::BlossomServer;
+
+    use super::*;
+
+    static ATTACHMENT_NAME: &str = "ngit-test-list-screenshot.png";
+
+    fn send_proposal_with_attachment() -> Result<()> {
+        let attachment = std::env::temp_dir().join(ATTACHMENT_NAME);
+        std::fs::write(&attachment, "image bytes")?;
+        let originating_repo = cli_tester_create_proposal_branches_ready_to_send()?;
+        let mut p = CliTester::new_from_dir(&originating_repo.dir, [
+            "--nsec",
+            TEST_KEY_1_NSEC,
+            "--password",
+            TEST_PASSWORD,
+            "--disable-cli-spinners",
+            "send",
+            "HEAD~2",
+            "--title",
+            "\"proposal a\"",
+            "--description",
+            "\"proposal a description\"",
+            "--attach",
+            attachment.to_str().unwrap(),
+        ]);
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    fn download_attachments(test_repo: &GitTestRepo) -> Result<CliTester> {
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("\r\n")?; // some updates listed here
+        let mut c = p.expect_choice("all proposals", vec![format!("\"proposal a\"")])?;
+        c.succeeds_with(0, true, None)?;
+        p.expect_eventually("attachments:\r\n")?;
+        p.expect_eventually(format!("  {ATTACHMENT_NAME} sha256:"))?;
+        p.expect_eventually("\r\n")?;
+        let mut c = p.expect_choice("", vec![
+            "continue".to_string(),
+            "download attachments to ./attachments".to_string(),
+        ])?;
+        c.succeeds_with(1, true, None)?;
+        Ok(p)
+    }
+
+    async fn run(tamper: bool) -> Result<()> {
+        let blossom = BlossomServer::start()?;
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        let repo_ref_event = generate_repo_ref_event_with_blossoms(vec![blossom.url.clone()]);
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(repo_ref_event.clone());
+
+        r55.events.push(repo_ref_event);
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            send_proposal_with_attachment()?;
+            if tamper {
+                blossom.tamper_with_blobs();
+            }
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut p = download_attachments(&test_repo)?;
+            let file_path = test_repo
+                .dir
+                .join(format!("attachments/de70302-{ATTACHMENT_NAME}"));
+            if tamper {
+                p.expect_eventually(format!("{ATTACHMENT_NAME} not saved: hash mismatch for "))?;
+                p.expect_eventually("Error: 1 attachment couldn't be downloaded and verified\r\n")?;
+                p.expect_exit_code(1)?;
+                assert!(!file_path.exists());
+            } else {
+                p.expect_eventually(format!(
+                    "saved ./attachments/de70302-{ATTACHMENT_NAME} (sha256 verified)\r\n"
+                ))?;
+                p.expect_end_eventually()?;
+                assert_eq!(std::fs::read_to_string(&file_path)?, "image bytes");
+            }
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn saves_files_matching_their_sha256() -> Result<()> {
+        run(false).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn refuses_files_that_dont_match_their_sha256() -> Result<()> {
+        run(true).await
+    }
+}
+
+mod when_run_in_bare_repository {
+    use super::*;
+
+    fn send_proposal() -> Result<()> {
+        let originating_repo = GitTestRepo::default();
+        originating_repo.populate()?;
+        originating_repo.create_branch(FEATURE_BRANCH_NAME_1)?;
+        originating_repo.checkout(FEATURE_BRANCH_NAME_1)?;
+        std::fs::write(originating_repo.dir.join("a3.md"), "some content")?;
+        originating_repo.stage_and_commit("add a3.md")?;
+        let mut p = CliTester::new_from_dir(&originating_repo.dir, [
+            "--nsec",
+            TEST_KEY_1_NSEC,
+            "--password",
+            TEST_PASSWORD,
+            "--disable-cli-spinners",
+            "send",
+            "HEAD~1",
+            "--title",
+            "\"proposal a\"",
+            "--description",
+            "\"proposal a description\"",
+        ]);
+        p.expect_end_eventually()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn only_offers_actions_that_dont_need_a_working_tree() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            send_proposal()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let bare_repo = GitTestRepo::recreate_as_bare(&test_repo)?;
+            bare_repo.git_repo.config()?.set_str(
+                "nostr.repo",
+                &test_repo.git_repo.config()?.get_string("nostr.repo")?,
+            )?;
+
+            let mut p = CliTester::new_from_dir(&bare_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let mut c = p.expect_choice("all proposals", vec![format!("\"proposal a\"")])?;
+            c.succeeds_with(0, true, None)?;
+            p.expect_eventually("1 commit\r\n")?;
+            let mut c = p.expect_choice("", vec![
+                format!("download to ./patches"),
+                format!("view full diff"),
+                format!("open in browser"),
+                format!("back"),
+            ])?;
+            c.succeeds_with(0, true, None)?;
+            p.expect_eventually("created 1 patch files in ./patches/")?;
+            p.expect_end_eventually()?;
+
+            assert!(bare_repo.dir.join("nostr-cache.lmdb").exists());
+            assert!(bare_repo.dir.join("patches").is_dir());
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod when_repo_announcement_changes_relays {
+    use nostr::{EventBuilder, Tag, Timestamp, ToBech32, nips::nip01::Coordinate};
+    use nostr_sdk::RelayUrl;
+
+    use super::*;
+
+    /// newer than `previous` and moved to relays 8055 and 8057 and a new git
+    /// server
+    fn generate_moved_repo_ref_event(previous: &nostr::Event) -> Result<nostr::Event> {
+        Ok(EventBuilder::new(previous.kind, "")
+            .tags(
+                previous
+                    .tags
+                    .iter()
+                    .filter(|t| !["relays", "clone"].contains(&t.as_slice()[0].as_str()))
+                    .cloned()
+                    .chain([
+                        Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("relays")),
+                            vec!["ws://localhost:8055", "ws://localhost:8057"],
+                        ),
+                        Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("clone")),
+                            vec!["https://git.new/test"],
+                        ),
+                    ]),
+            )
+            .custom_created_at(Timestamp::from(previous.created_at.as_u64() + 1))
+            .sign_with_keys(&TEST_KEY_1_KEYS)?)
+    }
+
+    fn naddr_url(previous: &nostr::Event, relays: &[&str]) -> Result<String> {
+        Ok(format!(
+            "nostr://{}",
+            Coordinate {
+                kind: previous.kind,
+                public_key: previous.pubkey,
+                identifier: previous.tags.identifier().unwrap().to_string(),
+                relays: relays
+                    .iter()
+                    .copied()
+                    .map(RelayUrl::parse)
+                    .collect::<Result<_, _>>()?,
+            }
+            .to_bech32()?
+        ))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn notifies_fetches_from_new_relays_and_updates_remote_in_same_run() -> Result<()> {
+        let previous = generate_repo_ref_event();
+        let moved = generate_moved_repo_ref_event(&previous)?;
+
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        test_repo.add_remote(
+            "origin",
+            &naddr_url(&previous, &["ws://localhost:8055", "ws://localhost:8056"])?,
+        )?;
+        // cached by an earlier fetch
+        save_event_in_local_cache(&test_repo.dir, &previous).await?;
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(moved.clone());
+        r56.events.push(previous.clone());
+        r57.events.push(moved);
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<GitTestRepo> {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually(
+                "repo relays changed: removed ws://localhost:8056, added ws://localhost:8057\r\n",
+            )?;
+            p.expect(
+                "repo git servers changed: removed git:://123.gitexample.com/test, added https://git.new/test\r\n",
+            )?;
+            p.expect("updated relay hints in git remote 'origin'\r\n")?;
+            p.expect_end_eventually()?;
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(test_repo)
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let test_repo = cli_tester_handle.join().unwrap()?;
+
+        assert!(!r57.reqs.is_empty());
+        assert_eq!(
+            test_repo.git_repo.find_remote("origin")?.url(),
+            Some(naddr_url(&previous, &["ws://localhost:8055", "ws://localhost:8057"])?.as_str())
+        );
+        Ok(())
+    }
+}
+
+mod when_proposal_has_been_revised {
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn summarises_changed_and_unchanged_patches() -> Result<()> {
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle: JoinHandle<Result<()>> = tokio::task::spawn_blocking(move || {
+            // the revision keeps the first commit and replaces the second
+            let (_, test_repo) =
+                create_proposals_with_first_revised_and_repo_with_unrevised_proposal_checkedout()?;
+            test_repo.checkout("main")?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let mut c = p.expect_choice("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\""),
+            ])?;
+            c.succeeds_with(2, true, None)?;
+            p.expect("revision 2: 1 patch changed, 1 unchanged\r\n")?;
+            p.exit()?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.await??;
+        Ok(())
+    }
+}
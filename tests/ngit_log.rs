@@ -0,0 +1,241 @@
+use anyhow::Result;
+use nostr::{Tag, TagKind, TagStandard, nips::nip10::Marker};
+use nostr_sdk::Kind;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, *};
+
+static DAY: u64 = 24 * 60 * 60;
+
+/// the announcement, a state event, a proposal, an issue and a status
+/// closing the proposal in the repository cache with fred's and carole's
+/// profiles in the global cache
+async fn prep_repo_with_activity_in_cache() -> Result<GitTestRepo> {
+    let test_repo = GitTestRepo::default();
+    test_repo.populate()?;
+    let announcement =
+        make_event_old_or_change_user(generate_repo_ref_event(), &TEST_KEY_1_KEYS, 10 * DAY);
+    let state = make_event_old_or_change_user(
+        nostr::EventBuilder::new(Kind::Custom(30618), "")
+            .tags([
+                Tag::identifier(announcement.tags.identifier().unwrap()),
+                Tag::custom(
+                    TagKind::Custom("refs/heads/main".into()),
+                    ["9ee507fc4357d7ee16a5d8901bedcd103f23c17d"],
+                ),
+                Tag::custom(TagKind::Custom("HEAD".into()), ["ref: refs/heads/main"]),
+            ])
+            .sign_with_keys(&TEST_KEY_1_KEYS)?,
+        &TEST_KEY_1_KEYS,
+        5 * DAY,
+    );
+    let proposal =
+        make_event_old_or_change_user(get_pretend_proposal_root_event(), &TEST_KEY_1_KEYS, 3 * DAY);
+    let issue = make_event_old_or_change_user(
+        nostr::EventBuilder::new(Kind::GitIssue, "it broke when i ran it")
+            .tags([
+                Tag::custom(TagKind::Custom("subject".into()), ["it broke"]),
+                Tag::coordinate(nostr::nips::nip01::Coordinate {
+                    kind: Kind::GitRepoAnnouncement,
+                    public_key: TEST_KEY_1_KEYS.public_key(),
+                    identifier: announcement.tags.identifier().unwrap().to_string(),
+                    relays: vec![],
+                }),
+            ])
+            .sign_with_keys(&TEST_KEY_2_KEYS)?,
+        &TEST_KEY_2_KEYS,
+        2 * DAY,
+    );
+    let closed = make_event_old_or_change_user(
+        nostr::EventBuilder::new(Kind::GitStatusClosed, "")
+            .tags([Tag::from_standardized(TagStandard::Event {
+                event_id: proposal.id,
+                relay_url: None,
+                marker: Some(Marker::Root),
+                public_key: None,
+                uppercase: false,
+            })])
+            .sign_with_keys(&TEST_KEY_1_KEYS)?,
+        &TEST_KEY_1_KEYS,
+        60 * 60,
+    );
+    for event in [announcement, state, proposal, issue, closed] {
+        save_event_in_local_cache(&test_repo.dir, &event).await?;
+    }
+    save_event_in_global_cache(&test_repo.dir, &generate_test_key_1_metadata_event("fred")).await?;
+    save_event_in_global_cache(
+        &test_repo.dir,
+        &generate_test_key_2_metadata_event("carole"),
+    )
+    .await?;
+    Ok(test_repo)
+}
+
+fn run_log(test_repo: &GitTestRepo, args: &[&str], expected_lines: &[&str]) -> Result<()> {
+    let mut p = CliTester::new_from_dir(&test_repo.dir, [vec!["log"], args.to_vec()].concat());
+    for line in expected_lines {
+        p.expect(format!("{line}\r\n"))?;
+    }
+    p.expect_end()
+}
+
+#[tokio::test]
+#[serial]
+async fn newest_first_one_line_each() -> Result<()> {
+    let test_repo = prep_repo_with_activity_in_cache().await?;
+    run_log(
+        &test_repo,
+        &[],
+        &[
+            "1 hour ago fred closed exampletitle",
+            "2 days ago carole issue it broke",
+            "3 days ago fred proposal exampletitle",
+            "5 days ago fred state 2 refs changed",
+            "10 days ago fred announcement example name",
+        ],
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn stat_lists_refs_changed_by_state_events() -> Result<()> {
+    let test_repo = prep_repo_with_activity_in_cache().await?;
+    run_log(
+        &test_repo,
+        &["--stat", "--kind", "state"],
+        &[
+            "5 days ago fred state 2 refs changed",
+            "    HEAD new ref: refs/heads/main",
+            "    refs/heads/main new 9ee507f",
+        ],
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn filtered_by_author_kind_and_since() -> Result<()> {
+    let test_repo = prep_repo_with_activity_in_cache().await?;
+    run_log(
+        &test_repo,
+        &["--author", "carole"],
+        &["2 days ago carole issue it broke"],
+    )?;
+    run_log(
+        &test_repo,
+        &["--kind", "proposal,status"],
+        &[
+            "1 hour ago fred closed exampletitle",
+            "3 days ago fred proposal exampletitle",
+        ],
+    )?;
+    run_log(
+        &test_repo,
+        &["--since", "4d"],
+        &[
+            "1 hour ago fred closed exampletitle",
+            "2 days ago carole issue it broke",
+            "3 days ago fred proposal exampletitle",
+        ],
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn piped_through_core_pager() -> Result<()> {
+    let test_repo = prep_repo_with_activity_in_cache().await?;
+    test_repo
+        .git_repo
+        .config()?
+        .set_str("core.pager", "sed 's/^/paged: /'")?;
+    run_log(
+        &test_repo,
+        &["--kind", "issue"],
+        &["paged: 2 days ago carole issue it broke"],
+    )
+}
+
+mod when_proposal_authors_profiles_not_cached {
+    use futures::join;
+    use nostr::{Keys, ToBech32};
+    use test_utils::relay::Relay;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn names_fetched_in_one_batch_with_short_npub_fallback() -> Result<()> {
+        let (mut r51, mut r52, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        save_event_in_local_cache(&test_repo.dir, &generate_repo_ref_event()).await?;
+        save_event_in_global_cache(&test_repo.dir, &generate_test_key_1_metadata_event("fred"))
+            .await?;
+        let mut authors = vec![];
+        for i in 0..20 {
+            let keys = Keys::generate();
+            authors.push(keys.public_key());
+            save_event_in_local_cache(
+                &test_repo.dir,
+                &make_event_old_or_change_user(get_pretend_proposal_root_event(), &keys, DAY),
+            )
+            .await?;
+            // the last author has no profile
+            if i < 19 {
+                r55.events.push(
+                    nostr::EventBuilder::metadata(
+                        &nostr::Metadata::new().name(format!("author{i}")),
+                    )
+                    .sign_with_keys(&keys)?,
+                );
+            }
+        }
+
+        let dir = test_repo.dir.clone();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            let mut p = CliTester::new_from_dir(&dir, ["log", "--kind", "proposal"]);
+            let output = p.expect_end_eventually()?;
+            for p in [51, 52, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(output)
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let output = cli_tester_handle.join().unwrap()?;
+
+        for i in 0..19 {
+            assert!(output.contains(&format!("author{i} proposal exampletitle")));
+        }
+        let npub = authors[19].to_bech32()?;
+        assert!(output.contains(&format!("{}… proposal exampletitle", &npub[..12])));
+
+        for relay in [&r51, &r52, &r55, &r56] {
+            let metadata_filters = relay
+                .reqs
+                .iter()
+                .flatten()
+                .filter(|filter| {
+                    filter
+                        .kinds
+                        .as_ref()
+                        .is_some_and(|kinds| kinds.contains(&Kind::Metadata))
+                })
+                .collect::<Vec<&nostr::Filter>>();
+            assert_eq!(metadata_filters.len(), 1);
+            assert_eq!(
+                metadata_filters[0].authors.as_ref().map(|a| a.len()),
+                Some(authors.len())
+            );
+        }
+        Ok(())
+    }
+}
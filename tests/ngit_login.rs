@@ -545,12 +545,130 @@ mod with_relays {
             }
         }
     }
+
+    mod when_creating_account {
+        use nostr::Kind;
+
+        use super::*;
+
+        #[tokio::test]
+        #[serial]
+        async fn prompts_for_name_and_relays_then_publishes_profile_and_relay_list() -> Result<()> {
+            let (mut r51, mut r52, mut r53) = (
+                Relay::new(8051, None, None),
+                Relay::new(8052, None, None),
+                Relay::new(8053, None, None),
+            );
+
+            let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+                let test_repo = GitTestRepo::default();
+                let mut p = CliTester::new_from_dir(&test_repo.dir, ["account", "login"]);
+
+                show_first_time_login_choices(&mut p)?.succeeds_with(2, false, Some(0))?;
+                p.expect("create account\r\n")?;
+                p.expect_input("user display name")?.succeeds_with("bob")?;
+                p.expect_eventually("relays")?;
+                p.send_line("ws://localhost:8053")?;
+                p.expect_eventually("publishing user profile and relay list to relays\r\n")?;
+                let output = p.expect_end_eventually()?;
+                for p in [51, 52, 53] {
+                    shutdown_relay(8000 + p)?;
+                }
+                Ok(output)
+            });
+
+            let _ = join!(
+                r51.listen_until_close(),
+                r52.listen_until_close(),
+                r53.listen_until_close(),
+            );
+
+            let output = cli_tester_handle.join().unwrap()?;
+            assert!(output.contains("logged in as bob"));
+            assert!(!output.contains("failed to find"));
+
+            for relay in [&r51, &r52, &r53] {
+                let metadata = relay
+                    .events
+                    .iter()
+                    .find(|e| e.kind.eq(&Kind::Metadata))
+                    .unwrap();
+                assert_eq!(
+                    nostr::Metadata::from_json(&metadata.content)?.name,
+                    Some("bob".to_string())
+                );
+                let relay_list = relay
+                    .events
+                    .iter()
+                    .find(|e| e.kind.eq(&Kind::RelayList))
+                    .unwrap();
+                assert_eq!(relay_list.pubkey, metadata.pubkey);
+                assert_eq!(
+                    relay_list
+                        .tags
+                        .iter()
+                        .map(|t| t.as_slice().to_vec())
+                        .collect::<Vec<Vec<String>>>(),
+                    vec![vec!["r".to_string(), "ws://localhost:8053".to_string()]],
+                );
+            }
+            Ok(())
+        }
+
+        #[tokio::test]
+        #[serial]
+        async fn with_skip_profile_flag_publishes_nothing() -> Result<()> {
+            let (mut r51, mut r52) = (Relay::new(8051, None, None), Relay::new(8052, None, None));
+
+            let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+                let test_repo = GitTestRepo::default();
+                let mut p =
+                    CliTester::new_from_dir(&test_repo.dir, ["account", "login", "--skip-profile"]);
+
+                show_first_time_login_choices(&mut p)?.succeeds_with(2, false, Some(0))?;
+                p.expect("create account\r\n")?;
+                let output = p.expect_end_eventually()?;
+                for p in [51, 52] {
+                    shutdown_relay(8000 + p)?;
+                }
+                Ok(output)
+            });
+
+            let _ = join!(r51.listen_until_close(), r52.listen_until_close());
+
+            let output = cli_tester_handle.join().unwrap()?;
+            assert!(output.contains("failed to find profile..."));
+            assert!(r51.events.is_empty());
+            assert!(r52.events.is_empty());
+            Ok(())
+        }
+    }
 }
 
 /// using the offline flag simplifies the test. relay interaction is tested
 /// seperately
 mod with_offline_flag {
     use super::*;
+
+    mod when_stdin_is_closed {
+        use super::*;
+
+        #[test]
+        fn login_prompt_fails_fast_naming_flags() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let output = run_ngit_without_stdin_from_dir(
+                &test_repo.dir,
+                ["account", "login", "--offline"],
+                5_000,
+            )?;
+            assert!(String::from_utf8_lossy(&output.stderr).contains(
+                "interactive input required for 'login to nostr'; pass --nsec or --bunker-uri"
+            ));
+            assert_eq!(output.status.code(), Some(2));
+            Ok(())
+        }
+    }
+
     mod when_first_time_login {
         use super::*;
 
@@ -662,6 +780,21 @@ mod with_offline_flag {
                 "Error: invalid nsec parameter\r\n\r\nCaused by:\r\n    Invalid secret key\r\n",
             )
         }
+
+        #[test]
+        fn invalid_nsec_param_exits_with_auth_error_code() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "account",
+                "login",
+                "--offline",
+                "--nsec",
+                TEST_INVALID_NSEC,
+            ]);
+
+            p.expect_end_eventually()?;
+            p.expect_exit_code(3)
+        }
     }
 
     mod when_called_with_nsec_and_password_parameter {
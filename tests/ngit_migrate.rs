@@ -0,0 +1,90 @@
+use anyhow::Result;
+use git::GitTestRepo;
+use nostr::nips::nip01::Coordinate;
+use nostr_sdk::{Kind, RelayUrl, ToBech32};
+use serial_test::serial;
+use test_utils::*;
+
+fn get_repo_coordinate(relays: Vec<RelayUrl>) -> Coordinate {
+    let repo_event = generate_repo_ref_event();
+    Coordinate {
+        kind: Kind::GitRepoAnnouncement,
+        public_key: repo_event.pubkey,
+        identifier: repo_event.tags.identifier().unwrap().to_string(),
+        relays,
+    }
+}
+
+fn get_legacy_nostr_remote_url() -> String {
+    let c = get_repo_coordinate(vec![]);
+    format!(
+        "nostr://30617:{}:{}?relay=ws://localhost:8055",
+        c.public_key.to_hex(),
+        c.identifier
+    )
+}
+
+#[test]
+#[serial]
+fn nothing_to_migrate_in_current_repository() -> Result<()> {
+    let test_repo = GitTestRepo::default();
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["migrate"]);
+    p.expect_end_with("nothing to migrate\r\n")?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn rewrites_legacy_remote_renamed_config_items_and_removes_old_cache() -> Result<()> {
+    let test_repo = GitTestRepo::without_repo_in_git_config();
+    test_repo.add_remote("origin", &get_legacy_nostr_remote_url())?;
+    let naddr = get_repo_coordinate(vec![RelayUrl::parse("ws://localhost:8055")?]).to_bech32()?;
+    let mut config = test_repo.git_repo.config()?;
+    config.set_str("nostr.repo-naddr", &naddr)?;
+    config.set_str("nostr.bunker-app-secret", "app-secret")?;
+    std::fs::write(test_repo.dir.join(".git/nostr-cache.sqlite"), "")?;
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["migrate"]);
+    p.expect(format!("migrated remote 'origin' to nostr://{naddr}\r\n").as_str())?;
+    p.expect("renamed local git config item nostr.repo-naddr to nostr.repo\r\n")?;
+    p.expect("renamed local git config item nostr.bunker-app-secret to nostr.bunker-app-key\r\n")?;
+    p.expect_end_with("removed old nostr cache .git/nostr-cache.sqlite\r\n")?;
+
+    assert_eq!(
+        test_repo.git_repo.find_remote("origin")?.url(),
+        Some(format!("nostr://{naddr}").as_str()),
+    );
+    let config = test_repo.git_repo.config()?.snapshot()?;
+    assert_eq!(config.get_str("nostr.repo")?, naddr);
+    assert_eq!(config.get_str("nostr.bunker-app-key")?, "app-secret");
+    assert!(config.get_str("nostr.repo-naddr").is_err());
+    assert!(config.get_str("nostr.bunker-app-secret").is_err());
+    assert!(!test_repo.dir.join(".git/nostr-cache.sqlite").exists());
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["migrate"]);
+    p.expect_end_with("nothing to migrate\r\n")?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn saves_nostr_repo_coordinate_as_naddr() -> Result<()> {
+    let test_repo = GitTestRepo::without_repo_in_git_config();
+    let c = get_repo_coordinate(vec![]);
+    test_repo.git_repo.config()?.set_str(
+        "nostr.repo",
+        &format!("30617:{}:{}", c.public_key.to_hex(), c.identifier),
+    )?;
+
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["migrate"]);
+    p.expect_end_with("saved git config item nostr.repo as an naddr\r\n")?;
+    assert_eq!(
+        test_repo
+            .git_repo
+            .config()?
+            .snapshot()?
+            .get_str("nostr.repo")?,
+        c.to_bech32()?,
+    );
+    Ok(())
+}
@@ -0,0 +1,708 @@
+use anyhow::Result;
+use futures::join;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+static EDITED_TITLE: &str = "proposal a with typo fixed";
+
+mod edit {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn list_shows_edited_title_and_checkout_produces_same_commits() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let originating_repo = cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "proposal",
+                "edit",
+                &proposal_id,
+                "--title",
+                EDITED_TITLE,
+            ]);
+            p.expect_end_eventually_with(
+                format!("updated proposal title to \"{EDITED_TITLE}\"\r\n").as_str(),
+            )?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let mut c = p.expect_choice("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                EDITED_TITLE.to_string(),
+            ])?;
+            c.succeeds_with(2, true, None)?;
+            let mut c = p.expect_choice("", vec![
+                format!("create and checkout proposal branch (2 ahead 0 behind 'main')"),
+                format!("apply to current branch with `git am`"),
+                format!("select patches to apply…"),
+                format!("download to ./patches"),
+                format!("view full diff"),
+                format!("open in browser"),
+                format!("back"),
+            ])?;
+            c.succeeds_with(0, true, None)?;
+            p.expect(format!(
+                "checked out proposal as 'pr/{}(",
+                FEATURE_BRANCH_NAME_1,
+            ))?;
+            p.expect_end_eventually_with(")' branch\r\n")?;
+
+            assert_eq!(
+                originating_repo.get_tip_of_local_branch(FEATURE_BRANCH_NAME_1)?,
+                test_repo.get_tip_of_local_branch(&get_proposal_branch_name(
+                    &test_repo,
+                    FEATURE_BRANCH_NAME_1
+                )?)?,
+            );
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod share {
+    use nostr::{FromBech32, nips::nip19::Nip19Event};
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn nevent_includes_repo_relays_that_returned_proposal() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p =
+                CliTester::new_from_dir(&test_repo.dir, ["proposal", "share", &proposal_id]);
+            let before = p.expect_eventually("\r\nhttps://gitworkshop.dev/repo/")?;
+            let nevent = before
+                .lines()
+                .last()
+                .context("expected nevent before web url")?
+                .to_string();
+            p.expect_eventually("/proposal/")?;
+            p.expect_end_with(format!("{nevent}\r\n").as_str())?;
+
+            let decoded = Nip19Event::from_bech32(&nevent)?;
+            assert_eq!(decoded.event_id.to_hex(), proposal_id);
+            assert_eq!(decoded.relays, vec![
+                "ws://localhost:8055".to_string(),
+                "ws://localhost:8056".to_string(),
+            ]);
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod open {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn offers_configured_viewer_then_announced_web_pages() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo.git_repo.config()?.set_str(
+                "nostr.web-viewer-url",
+                "https://viewer.example/{naddr}/pr/{nevent}",
+            )?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p =
+                CliTester::new_from_dir(&test_repo.dir, ["proposal", "share", &proposal_id]);
+            p.expect_eventually("\r\nhttps://viewer.example/naddr1")?;
+            let viewer_url = format!(
+                "https://viewer.example/naddr1{}",
+                p.expect_end_eventually()?.trim_end()
+            );
+            assert!(viewer_url.contains("/pr/nevent1"));
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["proposal", "open", &proposal_id]);
+            let mut c = p.expect_choice_eventually("open in", vec![
+                viewer_url.clone(),
+                "https://exampleproject.xyz".to_string(),
+                "https://gitworkshop.dev/123".to_string(),
+            ])?;
+            c.succeeds_with(0, true, None)?;
+            // no browser is launched under test so the url is printed
+            p.expect_end_with(format!("{viewer_url}\r\n").as_str())?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
+
+mod label {
+    use nostr::ToBech32;
+
+    use super::*;
+
+    fn add_bug_label(test_repo: &GitTestRepo, nsec: &str, proposal_id: &str) -> CliTester {
+        CliTester::new_from_dir(&test_repo.dir, [
+            "--nsec",
+            nsec,
+            "--password",
+            TEST_PASSWORD,
+            "--disable-cli-spinners",
+            "proposal",
+            "label",
+            proposal_id,
+            "--add",
+            "bug",
+        ])
+    }
+
+    fn expect_only_labelled_proposal_listed(test_repo: &GitTestRepo) -> Result<()> {
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--label", "bug"]);
+        p.expect_choice_eventually("all proposals", vec![format!(
+            "\"{PROPOSAL_TITLE_1}\" [bug]"
+        )])?;
+        p.exit()
+    }
+
+    fn expect_no_labelled_proposals(test_repo: &GitTestRepo) -> Result<()> {
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--label", "bug"]);
+        p.expect_end_eventually_with("no proposals labelled bug\r\n")
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn maintainer_label_shown_in_list_and_filters_proposals() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            let mut p = add_bug_label(test_repo, TEST_KEY_1_NSEC, proposal_id);
+            p.expect_end_eventually_with("labels: [bug]\r\n")?;
+            expect_only_labelled_proposal_listed(test_repo)?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect_choice_eventually("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+                format!("\"{PROPOSAL_TITLE_1}\" [bug]"),
+            ])?;
+            p.exit()
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn non_maintainer_labels_ignored_unless_anyone_allowed() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            let nsec = nostr::Keys::generate().secret_key().to_bech32()?;
+            let mut p = add_bug_label(test_repo, &nsec, proposal_id);
+            p.expect_end_eventually_with(
+                "Error: only maintainers can label proposals. set git config nostr.labels-allow-anyone to true to accept labels from anyone\r\n",
+            )?;
+
+            let mut config = test_repo.git_repo.config()?;
+            config.set_str("nostr.labels-allow-anyone", "true")?;
+            let mut p = add_bug_label(test_repo, &nsec, proposal_id);
+            p.expect_end_eventually_with("labels: [bug]\r\n")?;
+            expect_only_labelled_proposal_listed(test_repo)?;
+
+            config.set_str("nostr.labels-allow-anyone", "false")?;
+            expect_no_labelled_proposals(test_repo)
+        })
+        .await
+    }
+}
+
+mod delete {
+    use nostr::{EventId, Kind};
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn deletion_covers_every_patch_and_list_hides_proposal() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56, mut r57) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+            Relay::new(8057, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "proposal",
+                "delete",
+                &proposal_id,
+                "--yes",
+            ]);
+            p.expect_eventually("WARNING: relays are not obliged to honour deletion requests")?;
+            p.expect_eventually(format!(
+                "requested deletion of proposal \"{PROPOSAL_TITLE_1}\" and "
+            ))?;
+            p.expect_end_eventually()?;
+
+            // relays still serve the proposal as they don't honour deletions
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_choice_eventually("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\""),
+            ])?;
+            p.exit()?;
+
+            for p in [51, 52, 53, 55, 56, 57] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(proposal_id)
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+            r57.listen_until_close(),
+        );
+        let proposal_id = EventId::from_hex(&cli_tester_handle.join().unwrap()?)?;
+
+        let deletion = r55
+            .events
+            .iter()
+            .find(|e| e.kind.eq(&Kind::EventDeletion))
+            .context("deletion not sent to repo relay")?;
+        let patch_ids = r55
+            .events
+            .iter()
+            .filter(|e| {
+                e.kind.eq(&Kind::GitPatch)
+                    && (e.id.eq(&proposal_id) || e.tags.event_ids().any(|id| id.eq(&proposal_id)))
+            })
+            .map(|e| e.id)
+            .collect::<Vec<EventId>>();
+        assert!(patch_ids.len() > 1);
+        for id in &patch_ids {
+            assert!(deletion.tags.event_ids().any(|e| e.eq(id)));
+        }
+        assert!(r57.events.iter().any(|e| e.id.eq(&deletion.id)));
+        Ok(())
+    }
+}
+
+mod coverage {
+    use nostr::Kind;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn shows_patches_missing_from_a_repo_relay_and_repair_republishes_them() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(
+                8056,
+                None,
+                // only keeps proposal roots until they are republished
+                Some(&|relay, client_id, subscription_id, filters| -> Result<()> {
+                    relay.events.retain(|e| {
+                        !e.kind.eq(&Kind::GitPatch)
+                            || e.tags.iter().any(|t| t.as_slice().eq(&["t", "root"]))
+                    });
+                    relay.respond_standard_req(client_id, &subscription_id, &filters)?;
+                    Ok(())
+                }),
+            ),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            cli_tester_create_proposals()?;
+
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            // fetch proposals into the cache
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("all proposals")?;
+            p.exit()?;
+
+            let proposal_id = get_proposal_root_id(&test_repo, FEATURE_BRANCH_NAME_1)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--color",
+                "never",
+                "proposal",
+                "coverage",
+                &proposal_id,
+            ]);
+            p.expect_eventually(format!(
+                "1 proposal {} \"{PROPOSAL_TITLE_1}\"\r\n",
+                &proposal_id[..7]
+            ))?;
+            p.expect_eventually("ws://localhost:8055 ✔ ✔ ✔\r\n")?;
+            p.expect("ws://localhost:8056 ✔ ✘ ✘ missing 2 of 3\r\n")?;
+            p.expect("1 repo relay is missing events. use --repair to republish them\r\n")?;
+            p.expect_end()?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--color",
+                "never",
+                "proposal",
+                "coverage",
+                &proposal_id,
+                "--repair",
+            ]);
+            p.expect_eventually("ws://localhost:8056 ✔ ✘ ✘ missing 2 of 3\r\n")?;
+            p.expect(
+                "skipped 2 missing events authored by others. use --include-others to republish them too\r\n",
+            )?;
+            p.expect_end()?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--color",
+                "never",
+                "proposal",
+                "coverage",
+                &proposal_id,
+                "--repair",
+                "--include-others",
+            ]);
+            p.expect_eventually("ws://localhost:8056 ✔ ✘ ✘ missing 2 of 3\r\n")?;
+            let output = p.expect_end_eventually()?;
+            assert_eq!(output.matches("ws://localhost:8056 ").count(), 2);
+            assert!(!output.contains("skipped"));
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        // the three proposal roots and the two republished patches
+        assert_eq!(
+            r56.events
+                .iter()
+                .filter(|e| e.kind.eq(&Kind::GitPatch))
+                .count(),
+            5
+        );
+        Ok(())
+    }
+}
+
+mod show {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn prints_metadata_and_patches_of_proposal() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["proposal", "show", proposal_id]);
+            p.expect_eventually(format!("{PROPOSAL_TITLE_1}\r\nopen by "))?;
+            p.expect_eventually(format!("id: {proposal_id}\r\n"))?;
+            p.expect("target: main\r\n")?;
+            p.expect("1 revision, 2 patches, 2 files +2 -0\r\n")?;
+            p.expect("ci: no results\r\n")?;
+            p.expect("\r\n  proposal a description\r\n")?;
+            p.expect("\r\npatches:\r\n")?;
+            p.expect_eventually(" add a3.md (1 file +1 -0)\r\n")?;
+            p.expect_eventually(" add a4.md (1 file +1 -0)\r\n")?;
+            p.expect_end_with("comments: none\r\n")
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn json_includes_diffs_with_patch() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "proposal", "show", proposal_id, "--json", "--patch",
+            ]);
+            let output = p.expect_end_eventually()?;
+            let json: serde_json::Value =
+                serde_json::from_str(&output[output.find('{').context("no json printed")?..])?;
+            assert_eq!(json["title"], PROPOSAL_TITLE_1);
+            assert_eq!(json["status"], "open");
+            assert_eq!(json["revisions"], 1);
+            assert_eq!(
+                json["patches"]
+                    .as_array()
+                    .context("patches should be an array")?
+                    .iter()
+                    .map(|p| p["subject"].as_str().unwrap_or_default())
+                    .collect::<Vec<&str>>(),
+                vec!["add a3.md", "add a4.md"]
+            );
+            assert!(
+                json["patches"][0]["diff"]
+                    .as_str()
+                    .is_some_and(|diff| diff.starts_with("diff --git a/a3.md b/a3.md"))
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn found_by_local_proposal_branch() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            let branch_name = format!("pr/renamed({})", &proposal_id[..8]);
+            test_repo.create_branch(&branch_name)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["proposal", "show", &branch_name]);
+            p.expect_eventually(format!("{PROPOSAL_TITLE_1}\r\n"))?;
+            p.expect_eventually(format!("id: {proposal_id}\r\n"))?;
+            p.expect_end_eventually()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+mod ci {
+    use nostr::{EventBuilder, Keys, Kind, Tag, TagKind, Timestamp, ToBech32};
+
+    use super::*;
+
+    /// a comment from a ci bot labelled with the result of its run
+    fn ci_result(proposal_id: &str, keys: &Keys, state: &str) -> Result<nostr::Event> {
+        Ok(
+            EventBuilder::new(Kind::Custom(1111), format!("tests {state}"))
+                .tags([
+                    Tag::event(nostr::EventId::from_hex(proposal_id)?),
+                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("L")), vec!["ci"]),
+                    Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("l")),
+                        vec![state, "ci"],
+                    ),
+                    Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("r")),
+                        vec![format!("https://ci.example/runs/{state}")],
+                    ),
+                ])
+                // before the proposals were first listed so they aren't unread
+                .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - 60))
+                .sign_with_keys(keys)?,
+        )
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn bot_results_shown_in_list_and_show_and_filtered_by_ci_failed() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            test_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.ci-bots", &TEST_KEY_2_KEYS.public_key().to_bech32()?)?;
+            let proposal_2_id = get_proposal_root_id(test_repo, FEATURE_BRANCH_NAME_2)?;
+            relay::publish_to_relay(8055, &ci_result(proposal_id, &TEST_KEY_2_KEYS, "failed")?)?;
+            relay::publish_to_relay(
+                8055,
+                &ci_result(&proposal_2_id, &TEST_KEY_2_KEYS, "running")?,
+            )?;
+            // not from a ci bot
+            relay::publish_to_relay(
+                8055,
+                &ci_result(&proposal_2_id, &Keys::generate(), "failed")?,
+            )?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+            p.expect_choice_eventually("all proposals", vec![
+                format!("\"{PROPOSAL_TITLE_3}\""),
+                format!("\"{PROPOSAL_TITLE_2}\" ●"),
+                format!("\"{PROPOSAL_TITLE_1}\" ✗"),
+            ])?;
+            p.exit()?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--ci-failed"]);
+            p.expect_choice_eventually("all proposals", vec![format!("\"{PROPOSAL_TITLE_1}\" ✗")])?;
+            p.exit()?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["proposal", "show", proposal_id]);
+            p.expect_eventually(format!("{PROPOSAL_TITLE_1}\r\n"))?;
+            p.expect_eventually("ci: ")?;
+            p.expect_eventually(" failed\r\n")?;
+            p.expect("  tests failed\r\n")?;
+            p.expect("  https://ci.example/runs/failed\r\n")?;
+            p.expect_end_eventually()?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_proposals_listed_with_ci_failed_when_no_bots_configured() -> Result<()> {
+        with_proposals(|test_repo, proposal_id| {
+            relay::publish_to_relay(8055, &ci_result(proposal_id, &TEST_KEY_2_KEYS, "failed")?)?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list", "--ci-failed"]);
+            p.expect_end_eventually_with("no proposals with failing ci\r\n")
+        })
+        .await
+    }
+}
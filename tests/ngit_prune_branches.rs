@@ -0,0 +1,118 @@
+use std::fs;
+
+use anyhow::Result;
+use nostr::{Tag, TagKind, TagStandard, nips::nip10::Marker};
+use nostr_sdk::Kind;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, *};
+
+fn generate_proposal(branch_name: &str, title: &str) -> Result<nostr::Event> {
+    Ok(nostr::EventBuilder::new(
+        Kind::GitPatch,
+        format!(
+            "From fe973a840fba2a8ab37dd505c154854a69a6505c Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/1] {title}\n\ndescription"
+        ),
+    )
+    .tags(
+        get_pretend_proposal_root_event()
+            .tags
+            .iter()
+            .filter(|t| {
+                t.as_slice()
+                    .first()
+                    .is_some_and(|k| k != "alt" && k != "branch-name")
+            })
+            .cloned()
+            .chain([Tag::custom(TagKind::Custom("branch-name".into()), [
+                branch_name,
+            ])]),
+    )
+    .sign_with_keys(&TEST_KEY_1_KEYS)?)
+}
+
+fn generate_closed_status(proposal: &nostr::Event) -> Result<nostr::Event> {
+    Ok(nostr::EventBuilder::new(Kind::GitStatusClosed, "")
+        .tags([Tag::from_standardized(TagStandard::Event {
+            event_id: proposal.id,
+            relay_url: None,
+            marker: Some(Marker::Root),
+            public_key: None,
+            uppercase: false,
+        })])
+        .sign_with_keys(&TEST_KEY_1_KEYS)?)
+}
+
+fn pr_branch_name(branch_name: &str, proposal: &nostr::Event) -> String {
+    format!("pr/{branch_name}({})", &proposal.id.to_hex()[..8])
+}
+
+/// pr branches of a closed proposal already in main, a closed proposal with
+/// unpublished local work and an open proposal. returns their names in that
+/// order
+async fn prep_repo_with_pr_branches() -> Result<(GitTestRepo, [String; 3])> {
+    let test_repo = GitTestRepo::default();
+    test_repo.populate()?;
+    let merged = generate_proposal("merged", "merged work")?;
+    let unmerged = generate_proposal("unmerged", "unmerged work")?;
+    let open = generate_proposal("open", "open work")?;
+    for event in [
+        generate_repo_ref_event(),
+        generate_closed_status(&merged)?,
+        generate_closed_status(&unmerged)?,
+        merged.clone(),
+        unmerged.clone(),
+        open.clone(),
+    ] {
+        save_event_in_local_cache(&test_repo.dir, &event).await?;
+    }
+    let branch_names = [
+        pr_branch_name("merged", &merged),
+        pr_branch_name("unmerged", &unmerged),
+        pr_branch_name("open", &open),
+    ];
+    test_repo.create_branch(&branch_names[0])?;
+    test_repo.create_branch(&branch_names[2])?;
+    test_repo.create_branch(&branch_names[1])?;
+    test_repo.checkout(&branch_names[1])?;
+    fs::write(test_repo.dir.join("wip.md"), "local work")?;
+    test_repo.stage_and_commit("add wip.md")?;
+    test_repo.checkout("main")?;
+    Ok((test_repo, branch_names))
+}
+
+fn sorted_local_branch_names(test_repo: &GitTestRepo) -> Result<Vec<String>> {
+    let mut branch_names = test_repo.get_local_branch_names()?;
+    branch_names.sort();
+    Ok(branch_names)
+}
+
+#[tokio::test]
+#[serial]
+async fn only_deletes_branches_of_closed_proposals_already_in_main() -> Result<()> {
+    let (test_repo, [merged, unmerged, open]) = prep_repo_with_pr_branches().await?;
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["prune-branches", "--yes"]);
+    p.expect(format!(
+        "keeping {unmerged} as it has commits that aren't in main or the proposal\r\n"
+    ))?;
+    p.expect(format!("deleted {merged} (was "))?;
+    p.expect_end_eventually()?;
+    assert_eq!(
+        sorted_local_branch_names(&test_repo)?,
+        vec!["main".to_string(), open, unmerged]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn dry_run_deletes_nothing() -> Result<()> {
+    let (test_repo, [merged, _, _]) = prep_repo_with_pr_branches().await?;
+    let before = sorted_local_branch_names(&test_repo)?;
+    let mut p = CliTester::new_from_dir(&test_repo.dir, ["prune-branches", "--dry-run"]);
+    p.expect_eventually(format!(
+        "would delete {merged} (closed, in main: merged work)\r\n"
+    ))?;
+    p.expect_end()?;
+    assert_eq!(sorted_local_branch_names(&test_repo)?, before);
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use futures::join;
+use nostr::nips::nip01::Coordinate;
+use nostr_sdk::RelayUrl;
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+mod share {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn naddr_lists_announced_relays_that_returned_announcement_first() -> Result<()> {
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        // announced relays are 8055 then 8056 but only 8056 has the announcement
+        r56.events.push(generate_repo_ref_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, ["repo", "share"]);
+            p.expect_eventually("git clone nostr://")?;
+            let naddr = p.expect_eventually("\r\n")?;
+            p.expect_end_with(format!("https://gitworkshop.dev/repo/{naddr}\r\n").as_str())?;
+
+            let coordinate = Coordinate::parse(&naddr)?;
+            assert_eq!(
+                coordinate.identifier,
+                generate_repo_ref_event().tags.identifier().unwrap()
+            );
+            assert_eq!(coordinate.relays, vec![
+                RelayUrl::parse("ws://localhost:8056")?,
+                RelayUrl::parse("ws://localhost:8055")?,
+            ]);
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(())
+    }
+}
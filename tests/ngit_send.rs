@@ -174,7 +174,22 @@ async fn prep_run_create_proposal(
     Relay<'static>,
     Relay<'static>,
 )> {
-    let git_repo = prep_git_repo()?;
+    run_create_proposal(prep_git_repo()?, move |git_repo| {
+        cli_tester_create_proposal(git_repo, include_cover_letter)
+    })
+    .await
+}
+
+async fn run_create_proposal(
+    git_repo: GitTestRepo,
+    cli_tester: impl FnOnce(&GitTestRepo) -> CliTester + Send + 'static,
+) -> Result<(
+    Relay<'static>,
+    Relay<'static>,
+    Relay<'static>,
+    Relay<'static>,
+    Relay<'static>,
+)> {
     // fallback (51,52) user write (53, 55) repo (55, 56)
     let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
         Relay::new(
@@ -205,7 +220,7 @@ async fn prep_run_create_proposal(
 
     // // check relay had the right number of events
     let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
-        let mut p = cli_tester_create_proposal(&git_repo, include_cover_letter);
+        let mut p = cli_tester(&git_repo);
         p.expect_end_eventually()?;
         for p in [51, 52, 53, 55, 56] {
             relay::shutdown_relay(8000 + p)?;
@@ -225,6 +240,57 @@ async fn prep_run_create_proposal(
     Ok((r51, r52, r53, r55, r56))
 }
 
+mod when_range_specified_as_main_dot_dot_branch {
+    use super::*;
+
+    fn patches(relay: &Relay) -> Vec<String> {
+        let mut patches = relay
+            .events
+            .iter()
+            .filter(|e| is_patch(e))
+            .map(|e| e.content.clone())
+            .collect::<Vec<String>>();
+        patches.sort();
+        patches
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn sends_same_2_patches_as_head_2() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        git_repo.create_branch(FEATURE_BRANCH_NAME_1)?;
+        git_repo.checkout("main")?;
+        let (_, _, _, r55, _) = run_create_proposal(git_repo, |git_repo| {
+            CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                format!("main..{FEATURE_BRANCH_NAME_1}").as_str(),
+                "--no-cover-letter",
+            ])
+        })
+        .await?;
+        let from_range = patches(&r55);
+
+        let (_, _, _, r55, _) = prep_run_create_proposal(false).await?;
+        assert_eq!(from_range.len(), 2);
+        assert_eq!(from_range, patches(&r55));
+        Ok(())
+    }
+
+    #[test]
+    fn symmetric_difference_errors() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        let mut p = CliTester::new_from_dir(&git_repo.dir, ["send", "main...feature"]);
+        p.expect_eventually("symmetric difference ranges like main...feature are not supported")?;
+        p.exit()?;
+        Ok(())
+    }
+}
+
 mod when_cover_letter_details_specified_with_range_of_head_2_sends_cover_letter_and_2_patches_to_3_relays {
 
     use super::*;
@@ -948,6 +1014,10 @@ mod when_cover_letter_details_specified_with_range_of_head_2_sends_cover_letter_
                         ],
                         3,
                     )?;
+                    p.expect_after_whitespace(
+                        "some relays rejected events. run `ngit events status ",
+                    )?;
+                    p.expect_eventually("` for details\r\n")?;
                     expect_msgs_after(&mut p)?;
                     p.expect_end_with_whitespace()?;
                     for p in [51, 52, 53, 55, 56] {
@@ -1354,7 +1424,7 @@ mod root_proposal_specified_using_in_reply_to_with_range_of_head_2_and_cover_let
     }
     fn expect_msgs_first(p: &mut CliTester, include_cover_letter: bool) -> Result<()> {
         p.expect("fetching updates...\r\n")?;
-        p.expect("updates: 1 new maintainer, 1 announcement update, 1 proposal\r\n")?;
+        p.expect("updates: 1 new maintainer, 1 announcement update, 1 new proposal\r\n")?;
         let proposal_root_bech32 = get_pretend_proposal_root_event().id.to_bech32().unwrap();
         p.expect(format!(
             "creating proposal revision for: {}\r\n",
@@ -1721,6 +1791,215 @@ mod in_reply_to_mentions_issue {
         Ok(())
     }
 }
+mod in_reply_to_event_not_found {
+    use nostr::ToBech32;
+
+    use super::*;
+
+    fn expect_not_found_prompt(p: &mut CliTester, note: &str) -> Result<CliTesterConfirmPrompt> {
+        p.expect("fetching updates...\r\n")?;
+        p.expect_eventually("\r\n")?; // may be 'no updates' or some updates
+        p.expect_confirm(
+            format!(
+                "in-reply-to event {note} cannot be found in the local cache or on the repository relays. continue anyway?"
+            )
+            .as_str(),
+            Some(false),
+        )
+    }
+
+    fn missing_note() -> String {
+        nostr::EventId::from_hex("431e58eb8e1b4e20292d1d5bbe81d5cfb042e1bc165de32eddfdd52245a4cce4")
+            .unwrap()
+            .to_bech32()
+            .unwrap()
+    }
+
+    #[test]
+    #[serial]
+    fn prompts_and_aborts_when_response_is_false() -> Result<()> {
+        let test_repo = prep_git_repo()?;
+        let note = missing_note();
+        let mut p = CliTester::new_from_dir(&test_repo.dir, [
+            "send",
+            "HEAD~2",
+            "--in-reply-to",
+            &note,
+        ]);
+        expect_not_found_prompt(&mut p, &note)?.succeeds_with(Some(false))?;
+        p.expect_end_with(
+            format!("Error: aborting as in-reply-to event {note} cannot be found\r\n").as_str(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn continues_when_response_is_true() -> Result<()> {
+        let test_repo = prep_git_repo()?;
+        let note = missing_note();
+        let mut p = CliTester::new_from_dir(&test_repo.dir, [
+            "send",
+            "HEAD~2",
+            "--in-reply-to",
+            &note,
+        ]);
+        expect_not_found_prompt(&mut p, &note)?.succeeds_with(Some(true))?;
+        p.expect("creating proposal from 2 commits:\r\n")?;
+        p.exit()?;
+        Ok(())
+    }
+}
+
+mod in_reply_to_naddr_first {
+    use nostr::{ToBech32, nips::nip01::Coordinate};
+
+    use super::*;
+
+    fn repo_naddr() -> Result<String> {
+        Ok(Coordinate {
+            kind: Kind::GitRepoAnnouncement,
+            public_key: TEST_KEY_1_KEYS.public_key(),
+            identifier: generate_repo_ref_event()
+                .tags
+                .identifier()
+                .unwrap()
+                .to_string(),
+            relays: vec![],
+        }
+        .to_bech32()?)
+    }
+
+    /// send with the repository naddr as the first --in-reply-to, from a
+    /// branch checked out from the cached proposal or from 'feature'
+    async fn prep_run_create_proposal(
+        on_proposal_branch: bool,
+        expected_msg: String,
+    ) -> Result<Relay<'static>> {
+        let git_repo = prep_git_repo()?;
+        if on_proposal_branch {
+            git_repo.create_branch("pr/feature(431e58eb)")?;
+            git_repo.checkout("pr/feature(431e58eb)")?;
+        }
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                        get_pretend_proposal_root_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let naddr = repo_naddr()?;
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--in-reply-to",
+                &naddr,
+                "--title",
+                "exampletitle",
+                "--description",
+                "exampledescription",
+            ]);
+            p.expect_eventually(expected_msg)?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r55)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn revises_the_repo_proposal_for_the_checked_out_branch() -> Result<()> {
+        let r55 = prep_run_create_proposal(
+            true,
+            format!("creating proposal revision for: {}\r\n", repo_naddr()?),
+        )
+        .await?;
+        let cover_letter_event = r55.events.iter().find(|e| is_cover_letter(e)).unwrap();
+        assert_eq!(
+            cover_letter_event
+                .tags
+                .iter()
+                .find(|t| {
+                    t.as_slice()[0].eq("e")
+                        && t.as_slice().len().eq(&4)
+                        && t.as_slice()[3].eq("reply")
+                })
+                .unwrap()
+                .as_slice()[1],
+            get_pretend_proposal_root_event().id.to_string(),
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn otherwise_tagged_as_a_mention_of_a_new_proposal() -> Result<()> {
+        let naddr = repo_naddr()?;
+        let r55 = prep_run_create_proposal(
+            false,
+            format!(
+                "{naddr} doesn't identify a proposal for the checked out branch so it will be tagged as a mention\r\n"
+            ),
+        )
+        .await?;
+        let cover_letter_event = r55.events.iter().find(|e| is_cover_letter(e)).unwrap();
+        assert!(
+            !cover_letter_event
+                .tags
+                .iter()
+                .any(|t| t.as_slice()[0].eq("t") && t.as_slice()[1].eq("revision-root"))
+        );
+        assert!(
+            cover_letter_event
+                .tags
+                .iter()
+                .any(|t| t.as_slice()[0].eq("p")
+                    && t.as_slice()[1].eq(&TEST_KEY_1_KEYS.public_key().to_hex()))
+        );
+        Ok(())
+    }
+}
+
 mod in_reply_to_mentions_npub_and_nprofile_which_get_mentioned_in_proposal_root {
 
     use super::*;
@@ -1834,3 +2113,1586 @@ mod in_reply_to_mentions_npub_and_nprofile_which_get_mentioned_in_proposal_root
         Ok(())
     }
 }
+
+mod when_local_clock_is_an_hour_behind_relays {
+    use super::*;
+
+    static RELAY_AHEAD_SECS: u64 = 60 * 60;
+
+    fn relay_now() -> u64 {
+        nostr::Timestamp::now().as_u64() + RELAY_AHEAD_SECS
+    }
+
+    fn events_from_relay_clock() -> Vec<nostr::Event> {
+        [&TEST_KEY_1_KEYS, &TEST_KEY_2_KEYS]
+            .iter()
+            .map(|keys| {
+                nostr::EventBuilder::text_note("hello")
+                    .custom_created_at(nostr::Timestamp::from(relay_now()))
+                    .sign_with_keys(keys)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    async fn prep_run_create_proposal(fix_timestamp: bool) -> Result<Relay<'static>> {
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(
+                        client_id,
+                        &subscription_id,
+                        &[vec![generate_repo_ref_event()], events_from_relay_clock()].concat(),
+                    )?;
+                    Ok(())
+                }),
+            ),
+            // rejects events dated more than 5 minutes away from its own clock
+            Relay::new(
+                8056,
+                Some(&|relay, client_id, event| -> Result<()> {
+                    let created_at = event.created_at.as_u64();
+                    if created_at > relay_now() + 300 {
+                        relay.respond_ok(
+                            client_id,
+                            event,
+                            Some("invalid: created_at too far in the future"),
+                        )?;
+                    } else if created_at + 300 < relay_now() {
+                        relay.respond_ok(client_id, event, Some("invalid: created_at too old"))?;
+                    } else {
+                        relay.respond_ok(client_id, event, None)?;
+                    }
+                    Ok(())
+                }),
+                None,
+            ),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut args = vec![
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--no-cover-letter",
+            ];
+            if fix_timestamp {
+                args.push("--fix-timestamp");
+            }
+            let mut p = CliTester::new_from_dir(&git_repo.dir, args);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("WARNING: your system clock appears to be ")?;
+            p.expect_eventually(" behind nostr relays")?;
+            if fix_timestamp {
+                p.expect_eventually("publishing events using relay-inferred time\r\n")?;
+            } else {
+                p.expect_eventually(
+                    "WARNING: some relays rejected events because of their created_at timestamp",
+                )?;
+            }
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r56)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn warns_and_relay_rejects_events_without_fix_timestamp() -> Result<()> {
+        let r56 = prep_run_create_proposal(false).await?;
+        // only the first rejected event is sent
+        assert_eq!(r56.events.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fix_timestamp_publishes_events_using_relay_time() -> Result<()> {
+        let r56 = prep_run_create_proposal(true).await?;
+        assert_eq!(r56.events.iter().filter(|e| is_patch(e)).count(), 2);
+        for event in &r56.events {
+            assert!(event.created_at.as_u64() + 300 > relay_now());
+        }
+        Ok(())
+    }
+}
+
+mod when_local_clock_is_an_hour_ahead_of_relays {
+    use super::*;
+
+    static RELAY_BEHIND_SECS: u64 = 60 * 60;
+
+    fn relay_now() -> u64 {
+        nostr::Timestamp::now().as_u64() - RELAY_BEHIND_SECS
+    }
+
+    fn events_from_relay_clock(created_at: u64) -> Vec<nostr::Event> {
+        [&TEST_KEY_1_KEYS, &TEST_KEY_2_KEYS]
+            .iter()
+            .map(|keys| {
+                nostr::EventBuilder::text_note("hello")
+                    .custom_created_at(nostr::Timestamp::from(created_at))
+                    .sign_with_keys(keys)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// like relays, rejects events dated more than 5 minutes after its own
+    /// clock. rejected events are dropped
+    fn reject_future_dated(relay: &mut Relay, client_id: u64, event: nostr::Event) -> Result<()> {
+        if event.created_at.as_u64() > relay_now() + 300 {
+            relay.events.retain(|e| e.id != event.id);
+            relay.respond_ok(
+                client_id,
+                event,
+                Some("invalid: created_at too far in the future"),
+            )?;
+        } else {
+            relay.respond_ok(client_id, event, None)?;
+        }
+        Ok(())
+    }
+
+    /// relay time is inferred from the newest events on the relays, created
+    /// at `cached_events_created_at`
+    async fn prep_run_create_proposal(
+        fix_timestamp: bool,
+        cached_events_created_at: u64,
+    ) -> Result<Relay<'static>> {
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                Some(&reject_future_dated),
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, Some(&reject_future_dated), None),
+            Relay::new(8053, Some(&reject_future_dated), None),
+            Relay::new(
+                8055,
+                Some(&reject_future_dated),
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    let events = relay.events.clone();
+                    relay.respond_events(client_id, &subscription_id, &events)?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, Some(&reject_future_dated), None),
+        );
+        r55.events = [
+            vec![generate_repo_ref_event()],
+            events_from_relay_clock(cached_events_created_at),
+        ]
+        .concat();
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut args = vec![
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--no-cover-letter",
+            ];
+            if fix_timestamp {
+                args.push("--fix-timestamp");
+            }
+            let mut p = CliTester::new_from_dir(&git_repo.dir, args);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually(
+                "WARNING: some relays rejected events because of their created_at timestamp",
+            )?;
+            if fix_timestamp {
+                p.expect_eventually(
+                    "your system clock appears to be ahead of nostr relays. publishing again with timestamps 1h 0m earlier",
+                )?;
+            } else {
+                p.expect_eventually("events were dated in the future")?;
+            }
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        Ok(r56)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn warns_and_relays_reject_events_without_fix_timestamp() -> Result<()> {
+        let r56 = prep_run_create_proposal(false, relay_now()).await?;
+        assert!(r56.events.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fix_timestamp_publishes_events_again_using_relay_time() -> Result<()> {
+        let r56 = prep_run_create_proposal(true, relay_now()).await?;
+        assert_eq!(r56.events.iter().filter(|e| is_patch(e)).count(), 2);
+        for event in &r56.events {
+            assert!(event.created_at.as_u64() <= relay_now() + 300);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fix_timestamp_doesnt_backdate_events_to_old_cached_events() -> Result<()> {
+        // a quiet repository where the newest events are months old
+        let months_ago = relay_now() - 90 * 24 * 60 * 60;
+        let r56 = prep_run_create_proposal(true, months_ago).await?;
+        assert_eq!(r56.events.iter().filter(|e| is_patch(e)).count(), 2);
+        for event in &r56.events {
+            assert!(event.created_at.as_u64() <= relay_now() + 300);
+            assert!(event.created_at.as_u64() + 300 > relay_now());
+        }
+        Ok(())
+    }
+}
+
+mod when_sending_from_detached_head {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn synthesizes_branch_name_from_title_and_proposal_checks_out_in_fresh_clone()
+    -> Result<()> {
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+
+        r55.events.push(generate_repo_ref_event());
+        r55.events.push(generate_test_key_1_metadata_event("fred"));
+        r55.events.push(generate_test_key_1_relay_list_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<String> {
+            let test_repo = prep_git_repo()?;
+            let tip = test_repo.get_tip_of_local_branch("feature")?;
+            test_repo.git_repo.set_head_detached(tip)?;
+
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--title",
+                "Release Prep",
+                "--description",
+                "exampledescription",
+            ]);
+            p.expect_eventually(
+                "warning: HEAD is detached so no local branch will track this proposal. it will be listed as 'pr/release-prep'\r\n",
+            )?;
+            p.expect_end_eventually()?;
+
+            let fresh_clone = GitTestRepo::default();
+            fresh_clone.populate()?;
+            let mut p = CliTester::new_from_dir(&fresh_clone.dir, ["list"]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("\r\n")?; // some updates listed here
+            let mut c = p.expect_choice("all proposals", vec!["Release Prep".to_string()])?;
+            c.succeeds_with(0, true, None)?;
+            let mut c = p.expect_choice("", vec![
+                "create and checkout proposal branch (2 ahead 0 behind 'main')".to_string(),
+                "apply to current branch with `git am`".to_string(),
+                "select patches to apply…".to_string(),
+                "download to ./patches".to_string(),
+                "view full diff".to_string(),
+                "open in browser".to_string(),
+                "back".to_string(),
+            ])?;
+            c.succeeds_with(0, true, Some(0))?;
+            p.expect_end_eventually()?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(fresh_clone.get_checked_out_branch_name()?)
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let checked_out_branch = cli_tester_handle.join().unwrap()?;
+
+        let cover_letter = r55.events.iter().find(|e| is_cover_letter(e)).unwrap();
+        assert!(
+            cover_letter
+                .tags
+                .iter()
+                .any(|t| t.as_slice() == ["branch-name", "release-prep"].as_slice())
+        );
+        assert_eq!(
+            checked_out_branch,
+            format!("pr/release-prep({})", &cover_letter.id.to_hex()[..8]),
+        );
+        Ok(())
+    }
+}
+
+mod when_no_cover_letter_details_specified_opens_editor {
+    use super::*;
+
+    static EDITOR_PROMPT: &str = "edited cover letter (\\n for new lines)";
+
+    fn expect_template(p: &mut CliTester) -> Result<()> {
+        p.expect_eventually("creating proposal from 2 commits:\r\n")?;
+        p.expect_confirm_eventually("include cover letter?", Some(false))?
+            .succeeds_with(Some(true))?;
+        p.expect_eventually("add t3.md\r\n\r\n# Please enter the title and description")?;
+        p.expect_eventually("# Commits being sent:\r\n")?;
+        p.expect("#   232efb3 add t3.md\r\n")?;
+        p.expect("#   fe973a8 add t4.md\r\n")?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn template_has_first_commit_subject_and_lists_commits() -> Result<()> {
+        let test_repo = prep_git_repo()?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["send", "HEAD~2"]);
+        expect_template(&mut p)?;
+        p.expect_input_eventually(EDITOR_PROMPT)?;
+        p.exit()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn aborts_when_only_comments_are_left() -> Result<()> {
+        let test_repo = prep_git_repo()?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, ["send", "HEAD~2"]);
+        expect_template(&mut p)?;
+        p.expect_input_eventually(EDITOR_PROMPT)?
+            .succeeds_with("\\n# just a comment\\n")?;
+        p.expect_end_with("Error: aborting proposal due to empty cover letter\r\n")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn first_line_is_title_and_rest_is_description_without_comments() -> Result<()> {
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(generate_repo_ref_event());
+        r55.events.push(generate_repo_ref_event());
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let test_repo = prep_git_repo()?;
+            let mut p = CliTester::new_from_dir(&test_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+            ]);
+            expect_template(&mut p)?;
+            p.expect_input_eventually(EDITOR_PROMPT)?.succeeds_with(
+                "\\nEdited Title\\n# a comment\\nfirst paragraph\\n\\nsecond paragraph\\n\\n# Commits being sent:",
+            )?;
+            p.expect_eventually("posting 2 patches with a covering letter...\r\n")?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let cover_letter = r55.events.iter().find(|e| is_cover_letter(e)).unwrap();
+        let expected = "Subject: [PATCH 0/2] Edited Title\n\nfirst paragraph\n\nsecond paragraph";
+        assert!(cover_letter.content.ends_with(expected));
+        Ok(())
+    }
+}
+
+mod porcelain {
+    use std::process::Output;
+
+    use anyhow::{Context, bail};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Record {
+        Event {
+            id: String,
+            kind: u16,
+            published: bool,
+            accepted: usize,
+            attempted: usize,
+        },
+        ProposalRoot(String),
+        Branch(String),
+    }
+
+    fn is_hex_id(s: &str) -> bool {
+        s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// fails on any line that isn't exactly a documented record
+    fn parse_record(line: &str) -> Result<Record> {
+        match line.split(' ').collect::<Vec<&str>>().as_slice() {
+            [
+                "event",
+                id,
+                "kind",
+                kind,
+                "status",
+                status @ ("published" | "failed"),
+                "relays",
+                relays,
+            ] if is_hex_id(id) => {
+                let (accepted, attempted) = relays
+                    .split_once('/')
+                    .context(format!("relays not in the format n/n: {line:?}"))?;
+                Ok(Record::Event {
+                    id: (*id).to_string(),
+                    kind: kind.parse()?,
+                    published: *status == "published",
+                    accepted: accepted.parse()?,
+                    attempted: attempted.parse()?,
+                })
+            }
+            ["proposal-root", id] if is_hex_id(id) => Ok(Record::ProposalRoot((*id).to_string())),
+            ["branch", name] if name.starts_with("pr/") => Ok(Record::Branch((*name).to_string())),
+            _ => bail!("unexpected porcelain line: {line:?}"),
+        }
+    }
+
+    fn run_send_porcelain(test_repo: &GitTestRepo) -> Result<Output> {
+        Ok(
+            std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+                .env("NGITTEST", "TRUE")
+                .env("RUST_BACKTRACE", "0")
+                .current_dir(&test_repo.dir)
+                .args([
+                    "--nsec",
+                    TEST_KEY_1_NSEC,
+                    "--password",
+                    TEST_PASSWORD,
+                    "send",
+                    "HEAD~2",
+                    "--title",
+                    "exampletitle",
+                    "--description",
+                    "exampledescription",
+                    "--porcelain",
+                ])
+                .output()?,
+        )
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn stdout_only_contains_records_for_events_proposal_and_branch() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<Output> {
+            let output = run_send_porcelain(&git_repo);
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            output
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let output = cli_tester_handle.join().unwrap()?;
+        assert!(output.status.success());
+
+        let records = String::from_utf8(output.stdout)?
+            .lines()
+            .map(parse_record)
+            .collect::<Result<Vec<Record>>>()?;
+
+        let cover_letter = r55.events.iter().find(|e| is_cover_letter(e)).unwrap();
+        let patch_ids = r55
+            .events
+            .iter()
+            .filter(|e| is_patch(e))
+            .map(|e| e.id.to_hex())
+            .collect::<Vec<String>>();
+        let published = |id: &str| Record::Event {
+            id: id.to_string(),
+            kind: Kind::GitPatch.as_u16(),
+            published: true,
+            accepted: 5,
+            attempted: 5,
+        };
+        let cover_letter_id = cover_letter.id.to_hex();
+        let expected = vec![
+            published(&cover_letter_id),
+            published(&patch_ids[0]),
+            published(&patch_ids[1]),
+            Record::ProposalRoot(cover_letter_id.clone()),
+            Record::Branch(format!("pr/feature({})", &cover_letter_id[..8])),
+        ];
+        assert_eq!(records, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn stdout_is_empty_on_error() -> Result<()> {
+        let test_repo = GitTestRepo::new("notmain")?;
+        test_repo.populate()?;
+        let output = run_send_porcelain(&test_repo)?;
+        assert!(output.stdout.is_empty());
+        assert!(
+            String::from_utf8(output.stderr)?
+                .contains("Error: the default branches (main or master) do not exist")
+        );
+        assert_eq!(output.status.code(), Some(1));
+        Ok(())
+    }
+}
+
+mod summary {
+    use std::process::Output;
+
+    use super::*;
+
+    fn run_send_with_summary_args(
+        test_repo: &GitTestRepo,
+        summary_args: &[&str],
+    ) -> Result<Output> {
+        Ok(
+            std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+                .env("NGITTEST", "TRUE")
+                .env("RUST_BACKTRACE", "0")
+                .current_dir(&test_repo.dir)
+                .args(
+                    [
+                        vec![
+                            "--nsec",
+                            TEST_KEY_1_NSEC,
+                            "--password",
+                            TEST_PASSWORD,
+                            "send",
+                            "HEAD~2",
+                            "--title",
+                            "exampletitle",
+                            "--description",
+                            "exampledescription",
+                        ],
+                        summary_args.to_vec(),
+                    ]
+                    .concat(),
+                )
+                .output()?,
+        )
+    }
+
+    /// sends with `summary_args` and returns the output and the events the
+    /// repo relay received
+    async fn send_with_summary_args(
+        summary_args: &'static [&'static str],
+    ) -> Result<(GitTestRepo, Output, Vec<nostr::Event>)> {
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<(GitTestRepo, Output)> {
+            let output = run_send_with_summary_args(&git_repo, summary_args);
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok((git_repo, output?))
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let (git_repo, output) = cli_tester_handle.join().unwrap()?;
+        Ok((git_repo, output, r55.events))
+    }
+
+    fn assert_summary_matches_events(summary: &str, events: &[nostr::Event]) -> Result<()> {
+        let summary: serde_json::Value = serde_json::from_str(summary)?;
+        let cover_letter_id = events
+            .iter()
+            .find(|e| is_cover_letter(e))
+            .unwrap()
+            .id
+            .to_hex();
+        let patch_ids = events
+            .iter()
+            .filter(|e| is_patch(e))
+            .map(|e| e.id.to_hex())
+            .collect::<Vec<String>>();
+        assert_eq!(summary["proposal_root"], cover_letter_id);
+        assert_eq!(summary["patches"], serde_json::json!(patch_ids));
+        let repo_event = generate_repo_ref_event();
+        assert_eq!(
+            summary["repo"],
+            format!(
+                "30617:{}:{}",
+                repo_event.pubkey.to_hex(),
+                repo_event.tags.identifier().unwrap()
+            )
+        );
+        assert_eq!(
+            summary["branch"],
+            format!("pr/feature({})", &cover_letter_id[..8])
+        );
+        let relays = summary["relays"].as_object().unwrap();
+        assert_eq!(relays.len(), 5);
+        for accepted in relays.values() {
+            assert_eq!(
+                accepted,
+                &serde_json::json!([&[cover_letter_id.clone()], patch_ids.as_slice()].concat())
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn summary_stdout_only_contains_json_matching_events_on_relay() -> Result<()> {
+        let (_, output, events) = send_with_summary_args(&["--summary-stdout"]).await?;
+        assert!(output.status.success());
+        assert_summary_matches_events(&String::from_utf8(output.stdout)?, &events)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn emit_summary_writes_json_to_path() -> Result<()> {
+        let (git_repo, output, events) =
+            send_with_summary_args(&["--emit-summary", "ci/summary.json"]).await?;
+        assert!(output.status.success());
+        assert_summary_matches_events(
+            &std::fs::read_to_string(git_repo.dir.join("ci/summary.json"))?,
+            &events,
+        )?;
+        Ok(())
+    }
+}
+
+mod events_status {
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn shows_rejection_reason_per_relay_and_resends_to_rejecting_relay() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(
+                8056,
+                Some(&|relay, client_id, event| -> Result<()> {
+                    relay.respond_ok(client_id, event, Some("blocked: pubkey not admitted"))?;
+                    Ok(())
+                }),
+                None,
+            ),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = cli_tester_create_proposal(&git_repo, true);
+            p.expect_end_eventually()?;
+
+            let root_id = futures::executor::block_on(get_events_from_cache(&git_repo.dir, vec![
+                nostr::Filter::default()
+                    .kind(Kind::GitPatch)
+                    .hashtag("root"),
+            ]))?
+            .first()
+            .map(|e| e.id.to_hex())
+            .unwrap();
+
+            let mut p = CliTester::new_from_dir(&git_repo.dir, ["events", "status", &root_id]);
+            p.expect("ws://localhost:8051 accepted\r\n")?;
+            p.expect("ws://localhost:8052 accepted\r\n")?;
+            p.expect("ws://localhost:8053 accepted\r\n")?;
+            p.expect("ws://localhost:8055 accepted\r\n")?;
+            p.expect("ws://localhost:8056 rejected: blocked: pubkey not admitted\r\n")?;
+            p.expect_end()?;
+
+            let mut p =
+                CliTester::new_from_dir(&git_repo.dir, ["events", "status", &root_id, "--resend"]);
+            p.expect_eventually("resending to 1 relay(s)...\r\n")?;
+            p.expect("ws://localhost:8056 rejected: blocked: pubkey not admitted\r\n")?;
+            p.expect_end()?;
+
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        // original send stopped at the rejected root event; resend tried it again
+        assert_eq!(r56.events.len(), 2);
+        Ok(())
+    }
+}
+
+mod when_commit_already_upstream {
+    use super::*;
+
+    /// feature branch adding t3.md and t4.md, and a commit on main making
+    /// the same change as the t3.md commit
+    fn prep_test_repo() -> Result<GitTestRepo> {
+        let test_repo = prep_git_repo()?;
+        test_repo.checkout("main")?;
+        std::fs::write(test_repo.dir.join("t3.md"), "some content")?;
+        test_repo.stage_and_commit("add t3.md with a different message")?;
+        test_repo.checkout("feature")?;
+        Ok(test_repo)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn skip_duplicates_drops_it_from_proposal() -> Result<()> {
+        let git_repo = prep_test_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--no-cover-letter",
+                "--skip-duplicates",
+            ]);
+            p.expect("fetching updates...\r\n")?;
+            p.expect_eventually("creating proposal from 2 commits:\r\n")?;
+            p.expect("fe973a8 add t4.md\r\n")?;
+            p.expect("232efb3 add t3.md\r\n")?;
+            p.expect_confirm(
+                "proposal is 1 behind 'main'. consider rebasing before submission. proceed anyway?",
+                Some(false),
+            )?
+            .succeeds_with(Some(true))?;
+            p.expect("commit 232efb3 appears to already be upstream as ")?;
+            p.expect_eventually("\r\n")?;
+            p.expect("dropped 1 commit\r\n")?;
+            p.expect_eventually("posting 1 patch without a covering letter...\r\n")?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let patches = r55
+            .events
+            .iter()
+            .filter(|e| is_patch(e))
+            .collect::<Vec<&nostr::Event>>();
+        assert_eq!(patches.len(), 1);
+        assert!(patches[0].content.contains("t4.md"));
+        assert!(!patches[0].content.contains("t3.md"));
+        Ok(())
+    }
+}
+
+mod color {
+    use std::process::Output;
+
+    use super::*;
+
+    /// send with stdout and stderr piped rather than a terminal, optionally
+    /// setting color in the config file and git config nostr.color first
+    async fn run_send_piped(
+        color_args: &'static [&'static str],
+        config_file_color: Option<&str>,
+        git_config_color: Option<&str>,
+    ) -> Result<Output> {
+        let git_repo = prep_git_repo()?;
+        if let Some(color) = config_file_color {
+            std::fs::write(
+                git_repo.dir.join(".git/test-config.toml"),
+                format!("color = \"{color}\"\n"),
+            )?;
+        }
+        if let Some(color) = git_config_color {
+            git_repo.git_repo.config()?.set_str("nostr.color", color)?;
+        }
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<Output> {
+            let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("ngit"))
+                .env("NGITTEST", "TRUE")
+                .env("RUST_BACKTRACE", "0")
+                .env_remove("NO_COLOR")
+                .env_remove("CLICOLOR_FORCE")
+                .current_dir(&git_repo.dir)
+                .args(color_args)
+                .args([
+                    "--nsec",
+                    TEST_KEY_1_NSEC,
+                    "--password",
+                    TEST_PASSWORD,
+                    "send",
+                    "HEAD~2",
+                    "--title",
+                    "exampletitle",
+                    "--description",
+                    "exampledescription",
+                ])
+                .output();
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(output?)
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        let output = cli_tester_handle.join().unwrap()?;
+        assert!(output.status.success());
+        Ok(output)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn piped_output_contains_no_escape_codes() -> Result<()> {
+        let output = run_send_piped(&[], None, None).await?;
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(stdout.contains("creating proposal from 2 commits:"));
+        assert!(!stdout.contains('\u{1b}'), "stdout: {stdout:?}");
+        let stderr = String::from_utf8(output.stderr)?;
+        assert!(!stderr.contains('\u{1b}'), "stderr: {stderr:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn color_always_styles_piped_output() -> Result<()> {
+        let output = run_send_piped(&["--color", "always"], None, None).await?;
+        assert!(String::from_utf8(output.stdout)?.contains('\u{1b}'));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn config_file_color_always_styles_piped_output() -> Result<()> {
+        let output = run_send_piped(&[], Some("always"), None).await?;
+        assert!(String::from_utf8(output.stdout)?.contains('\u{1b}'));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn git_config_color_overrides_config_file() -> Result<()> {
+        let output = run_send_piped(&[], Some("always"), Some("never")).await?;
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(!stdout.contains('\u{1b}'), "stdout: {stdout:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn color_flag_overrides_config_file_and_git_config() -> Result<()> {
+        let output = run_send_piped(&["--color", "never"], Some("always"), Some("always")).await?;
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(!stdout.contains('\u{1b}'), "stdout: {stdout:?}");
+        Ok(())
+    }
+}
+
+mod when_expiry_days_set {
+    use super::*;
+
+    /// returns the events sent to a repo relay and the range of timestamps
+    /// send ran between
+    async fn run_send_with_expiry(
+        expiry_args: &'static [&'static str],
+        git_config_expiry_days: Option<&'static str>,
+    ) -> Result<(Vec<nostr::Event>, u64, u64)> {
+        let git_repo = prep_git_repo()?;
+        if let Some(days) = git_config_expiry_days {
+            git_repo
+                .git_repo
+                .config()?
+                .set_str("nostr.proposal-expiry-days", days)?;
+        }
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let before = nostr::Timestamp::now().as_u64();
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut args = vec![
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--title",
+                "exampletitle",
+                "--description",
+                "exampledescription",
+            ];
+            args.extend(expiry_args);
+            let mut p = CliTester::new_from_dir(&git_repo.dir, args);
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        let after = nostr::Timestamp::now().as_u64();
+        Ok((r55.events, before, after))
+    }
+
+    fn expirations(events: &[nostr::Event]) -> Vec<Option<u64>> {
+        events
+            .iter()
+            .filter(|e| is_cover_letter(e) || is_patch(e))
+            .map(|e| {
+                e.tags
+                    .iter()
+                    .find(|t| t.as_slice()[0].eq("expiration"))
+                    .map(|t| t.as_slice()[1].parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn expiry_days_flag_adds_expiration_to_cover_letter_and_patches() -> Result<()> {
+        let (events, before, after) = run_send_with_expiry(&["--expiry-days", "12"], None).await?;
+        let expirations = expirations(&events);
+        assert_eq!(expirations.len(), 3);
+        for expiration in expirations {
+            let expiration = expiration.expect("expiration tag on every event");
+            assert!(expiration >= before + 12 * 24 * 60 * 60);
+            assert!(expiration <= after + 12 * 24 * 60 * 60);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn git_config_proposal_expiry_days_used_without_flag() -> Result<()> {
+        let (events, before, after) = run_send_with_expiry(&[], Some("30")).await?;
+        for expiration in expirations(&events) {
+            let expiration = expiration.expect("expiration tag on every event");
+            assert!(expiration >= before + 30 * 24 * 60 * 60);
+            assert!(expiration <= after + 30 * 24 * 60 * 60);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_expiration_by_default() -> Result<()> {
+        let (events, _, _) = run_send_with_expiry(&[], None).await?;
+        assert_eq!(expirations(&events), vec![None, None, None]);
+        Ok(())
+    }
+}
+
+mod when_announcement_requires_cover_letter {
+    use nostr::{EventBuilder, Tag, TagKind};
+
+    use super::*;
+
+    fn generate_repo_ref_event_requiring_cover_letter() -> nostr::Event {
+        let event = generate_repo_ref_event();
+        EventBuilder::new(event.kind, event.content.clone())
+            .tags(event.tags.iter().cloned().chain([Tag::custom(
+                TagKind::Custom("submission-policy".into()),
+                vec!["cover-letter=required"],
+            )]))
+            .sign_with_keys(&TEST_KEY_1_KEYS)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_cover_letter_flag_rejected_without_publishing() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        // fallback (51,52) user write (53, 55) repo (55, 56)
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(
+                8051,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_test_key_1_metadata_event("fred"),
+                        generate_test_key_1_relay_list_event(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(
+                8055,
+                None,
+                Some(&|relay, client_id, subscription_id, _| -> Result<()> {
+                    relay.respond_events(client_id, &subscription_id, &vec![
+                        generate_repo_ref_event_requiring_cover_letter(),
+                    ])?;
+                    Ok(())
+                }),
+            ),
+            Relay::new(8056, None, None),
+        );
+
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--no-cover-letter",
+            ]);
+            p.expect_eventually("a cover letter is required. use --ignore-policy to send anyway")?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        // launch relay
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+        for relay in [&r53, &r55, &r56] {
+            assert!(!relay.events.iter().any(is_patch));
+        }
+        Ok(())
+    }
+}
+
+mod when_stdin_is_closed {
+    use super::*;
+
+    #[test]
+    fn repository_prompt_fails_fast_naming_config_item() -> Result<()> {
+        let test_repo = GitTestRepo::without_repo_in_git_config();
+        test_repo.populate()?;
+        let output = run_ngit_without_stdin_from_dir(
+            &test_repo.dir,
+            [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "send",
+                "HEAD~1",
+                "--title",
+                "exampletitle",
+                "--description",
+                "exampledescription",
+            ],
+            5_000,
+        )?;
+        assert!(String::from_utf8_lossy(&output.stderr).contains(
+            "interactive input required for 'nostr repository'; set git config nostr.repo"
+        ));
+        assert_eq!(output.status.code(), Some(2));
+        Ok(())
+    }
+}
+
+mod when_pre_send_hook_installed {
+    use super::*;
+
+    fn install_hook(git_repo: &GitTestRepo, command: &str) -> Result<()> {
+        CliTester::new_from_dir(&git_repo.dir, ["hooks", "install", command])
+            .expect_end_eventually()?;
+        Ok(())
+    }
+
+    fn patch_events_received(relays: &[&Relay]) -> usize {
+        relays
+            .iter()
+            .map(|r| {
+                r.events
+                    .iter()
+                    .filter(|e| e.kind.eq(&Kind::GitPatch))
+                    .count()
+            })
+            .sum()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn failing_hook_stops_events_reaching_relays() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        install_hook(&git_repo, "exit 1")?;
+        let (r51, r52, r53, r55, r56) = run_create_proposal(git_repo, |git_repo| {
+            cli_tester_create_proposal(git_repo, false)
+        })
+        .await?;
+        assert_eq!(patch_events_received(&[&r51, &r52, &r53, &r55, &r56]), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn passing_hook_gets_commit_range_and_publishing_proceeds() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        // outside the test repo, which is removed once the proposal is sent
+        let range_path =
+            std::env::temp_dir().join(format!("ngit-pre-send-range-{}", std::process::id()));
+        install_hook(
+            &git_repo,
+            &format!("echo \"$1\" > '{}'", range_path.display()),
+        )?;
+        let expected_range = format!(
+            "{}..{}",
+            git_repo.get_tip_of_local_branch("main")?,
+            git_repo.get_tip_of_local_branch("feature")?,
+        );
+        let (_, _, _, r55, _) = run_create_proposal(git_repo, |git_repo| {
+            cli_tester_create_proposal(git_repo, false)
+        })
+        .await?;
+        let range = std::fs::read_to_string(&range_path)?;
+        std::fs::remove_file(&range_path)?;
+        assert_eq!(range.trim(), expected_range);
+        assert_eq!(patch_events_received(&[&r55]), 2);
+        Ok(())
+    }
+}
+
+mod when_owners_file_lists_paths_changed {
+    use nostr::{PublicKey, TagStandard};
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn owners_of_paths_changed_tagged_in_cover_letter() -> Result<()> {
+        let git_repo = prep_git_repo()?;
+        // t3.md is owned by key 2. t4.md only by the sender, key 1
+        std::fs::write(
+            git_repo.dir.join(".nostr-owners"),
+            format!("# owners\n*.md {TEST_KEY_2_NPUB} npub1invalid\nt4.md {TEST_KEY_1_NPUB}\n"),
+        )?;
+        let (_, _, _, r55, _) = run_create_proposal(git_repo, |git_repo| {
+            CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "--yes",
+                "send",
+                "HEAD~2",
+                "--title",
+                "exampletitle",
+                "--description",
+                "exampledescription",
+            ])
+        })
+        .await?;
+
+        let cover_letter = r55
+            .events
+            .iter()
+            .find(|e| is_cover_letter(e))
+            .expect("cover letter not published");
+        let tagged = cover_letter
+            .tags
+            .iter()
+            .filter_map(|t| match t.as_standardized() {
+                Some(TagStandard::PublicKey { public_key, .. }) => Some(*public_key),
+                _ => None,
+            })
+            .collect::<Vec<PublicKey>>();
+        let count = |npub: &str| {
+            tagged
+                .iter()
+                .filter(|pk| pk.eq(&&PublicKey::parse(npub).unwrap()))
+                .count()
+        };
+        assert_eq!(count(TEST_KEY_2_NPUB), 1);
+        // only as a maintainer, not as an owner of t4.md
+        assert_eq!(count(TEST_KEY_1_NPUB), 1);
+        Ok(())
+    }
+}
+
+mod when_attaching_a_file {
+    use test_utils::blossom::BlossomServer;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn uploads_to_blossom_server_and_tags_cover_letter() -> Result<()> {
+        let blossom = BlossomServer::start()?;
+        let attachment = std::env::temp_dir().join("ngit-test-screenshot.png");
+        std::fs::write(&attachment, "image bytes")?;
+        // sha256 of "image bytes"
+        let sha256 = "de7030234493a8bea844dbe1d8676e68a2c1a4b014c721f0425a22b6df66faec";
+
+        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+            Relay::new(8051, None, None),
+            Relay::new(8052, None, None),
+            Relay::new(8053, None, None),
+            Relay::new(8055, None, None),
+            Relay::new(8056, None, None),
+        );
+        let repo_ref_event = generate_repo_ref_event_with_blossoms(vec![blossom.url.clone()]);
+        r51.events.push(generate_test_key_1_relay_list_event());
+        r51.events.push(generate_test_key_1_metadata_event("fred"));
+        r51.events.push(repo_ref_event.clone());
+        r55.events.push(repo_ref_event);
+
+        let git_repo = prep_git_repo()?;
+        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+            let mut p = CliTester::new_from_dir(&git_repo.dir, [
+                "--nsec",
+                TEST_KEY_1_NSEC,
+                "--password",
+                TEST_PASSWORD,
+                "--disable-cli-spinners",
+                "send",
+                "HEAD~2",
+                "--title",
+                "exampletitle",
+                "--description",
+                "exampledescription",
+                "--attach",
+                attachment.to_str().unwrap(),
+            ]);
+            p.expect_eventually("attached ngit-test-screenshot.png ")?;
+            p.expect_end_eventually()?;
+            for p in [51, 52, 53, 55, 56] {
+                relay::shutdown_relay(8000 + p)?;
+            }
+            Ok(())
+        });
+
+        let _ = join!(
+            r51.listen_until_close(),
+            r52.listen_until_close(),
+            r53.listen_until_close(),
+            r55.listen_until_close(),
+            r56.listen_until_close(),
+        );
+        cli_tester_handle.join().unwrap()?;
+
+        let uploads = blossom.uploads.lock().unwrap().clone();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].body, b"image bytes");
+        assert!(
+            uploads[0]
+                .authorization
+                .as_ref()
+                .is_some_and(|auth| auth.starts_with("Nostr "))
+        );
+
+        let cover_letter = r55
+            .events
+            .iter()
+            .find(|e| is_cover_letter(e))
+            .expect("cover letter not published");
+        assert!(cover_letter.tags.iter().any(|t| t.as_slice()
+            == [
+                "attachment".to_string(),
+                format!("{}/{sha256}", blossom.url),
+                sha256.to_string(),
+                "ngit-test-screenshot.png".to_string(),
+            ]));
+        Ok(())
+    }
+}
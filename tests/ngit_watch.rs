@@ -0,0 +1,165 @@
+use anyhow::Result;
+use futures::join;
+use nostr::{Event, EventBuilder, Kind, Tag, nips::nip01::Coordinate};
+use serial_test::serial;
+use test_utils::{git::GitTestRepo, relay::Relay, *};
+
+static LIST_KIND: Kind = Kind::Custom(10018);
+
+fn repo_coordinate() -> String {
+    let repo_event = generate_repo_ref_event();
+    format!(
+        "30617:{}:{}",
+        TEST_KEY_1_PUBKEY_HEX,
+        repo_event.tags.identifier().unwrap()
+    )
+}
+
+fn other_repo_coordinate() -> String {
+    format!("30617:{}:other-repo", TEST_KEY_1_PUBKEY_HEX)
+}
+
+fn generate_list_event(coordinates: Vec<String>) -> Result<Event> {
+    Ok(EventBuilder::new(LIST_KIND, "")
+        .tags(
+            coordinates
+                .into_iter()
+                .map(|c| Ok(Tag::coordinate(Coordinate::parse(c)?)))
+                .collect::<Result<Vec<Tag>>>()?,
+        )
+        .sign_with_keys(&TEST_KEY_1_KEYS)?)
+}
+
+fn listed_coordinates(events: &[Event]) -> Vec<Vec<String>> {
+    events
+        .iter()
+        .filter(|e| e.kind == LIST_KIND)
+        .map(|e| {
+            e.tags
+                .iter()
+                .filter(|t| t.as_slice().first().is_some_and(|n| n == "a"))
+                .filter_map(|t| t.as_slice().get(1).cloned())
+                .collect()
+        })
+        .collect()
+}
+
+fn cli_args(command: &str) -> Vec<&str> {
+    vec![
+        "--nsec",
+        TEST_KEY_1_NSEC,
+        "--password",
+        TEST_PASSWORD,
+        "--disable-cli-spinners",
+        command,
+    ]
+}
+
+async fn run_with_relays(
+    existing_list: Option<Event>,
+    command: &'static str,
+    expected_output: &'static str,
+) -> Result<Vec<Event>> {
+    // fallback (51,52) user write (53, 55) repo (55, 56)
+    let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+        Relay::new(8051, None, None),
+        Relay::new(8052, None, None),
+        Relay::new(8053, None, None),
+        Relay::new(8055, None, None),
+        Relay::new(8056, None, None),
+    );
+    r51.events.push(generate_test_key_1_relay_list_event());
+    r51.events.push(generate_test_key_1_metadata_event("fred"));
+    r51.events.push(generate_repo_ref_event());
+    r55.events.push(generate_repo_ref_event());
+    if let Some(list) = existing_list {
+        r53.events.push(list);
+    }
+
+    let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        let mut p = CliTester::new_from_dir(&test_repo.dir, cli_args(command));
+        p.expect_eventually(expected_output)?;
+        p.expect_end_eventually()?;
+        for p in [51, 52, 53, 55, 56] {
+            relay::shutdown_relay(8000 + p)?;
+        }
+        Ok(())
+    });
+
+    // launch relays
+    let _ = join!(
+        r51.listen_until_close(),
+        r52.listen_until_close(),
+        r53.listen_until_close(),
+        r55.listen_until_close(),
+        r56.listen_until_close(),
+    );
+    cli_tester_handle.join().unwrap()?;
+    Ok(r53.events)
+}
+
+#[tokio::test]
+#[serial]
+async fn watch_publishes_list_containing_repo_coordinate() -> Result<()> {
+    let events = run_with_relays(None, "watch", "watching example name\r\n").await?;
+    assert_eq!(listed_coordinates(&events), vec![vec![repo_coordinate()]]);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn watch_preserves_existing_list_items() -> Result<()> {
+    let events = run_with_relays(
+        Some(generate_list_event(vec![other_repo_coordinate()])?),
+        "watch",
+        "watching example name\r\n",
+    )
+    .await?;
+    assert!(
+        listed_coordinates(&events).contains(&vec![other_repo_coordinate(), repo_coordinate()])
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn unwatch_publishes_list_without_repo_coordinate() -> Result<()> {
+    let events = run_with_relays(
+        Some(generate_list_event(vec![
+            other_repo_coordinate(),
+            repo_coordinate(),
+        ])?),
+        "unwatch",
+        "stopped watching example name\r\n",
+    )
+    .await?;
+    assert!(listed_coordinates(&events).contains(&vec![other_repo_coordinate()]));
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn watch_when_already_watching_publishes_nothing() -> Result<()> {
+    let events = run_with_relays(
+        Some(generate_list_event(vec![repo_coordinate()])?),
+        "watch",
+        "already watching example name\r\n",
+    )
+    .await?;
+    assert_eq!(listed_coordinates(&events).len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn watched_lists_repo_name_and_maintainer() -> Result<()> {
+    run_with_relays(
+        Some(generate_list_event(vec![repo_coordinate()])?),
+        "watched",
+        "example name by fred (last activity ",
+    )
+    .await?;
+    Ok(())
+}